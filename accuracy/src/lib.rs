@@ -1,3 +1,5 @@
+//! **Deprecated:** use the `metrics` proc-block with `metrics=accuracy` instead.
+
 // use linfa_logistic::LogisticRegression;
 use smartcore::metrics::*;
 
@@ -21,6 +23,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
         metadata.set_homepage(env!("CARGO_PKG_HOMEPAGE"));
         metadata.add_tag("metric");
+        metadata.add_tag("deprecated");
         metadata.add_tag("analytics");
 
         let y_true = TensorMetadata::new("y_true");
@@ -82,10 +85,9 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             })
         })?;
 
-        let _ytrue: ndarray::ArrayView1<f64> = y_true
+        let y_true_values: ndarray::ArrayView1<f64> = y_true
             .buffer
-            .view(&y_true.dimensions)
-            .and_then(|t| t.into_dimensionality())
+            .try_view(&y_true.dimensions)
             .map_err(|e| {
                 KernelError::InvalidInput(InvalidInput {
                     name: "y_train".to_string(),
@@ -99,10 +101,9 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
                 reason: BadInputReason::NotFound,
             })
         })?;
-        let _ypred: ndarray::ArrayView1<f64> = y_pred
+        let y_pred_values: ndarray::ArrayView1<f64> = y_pred
             .buffer
-            .view(&y_pred.dimensions)
-            .and_then(|t| t.into_dimensionality())
+            .try_view(&y_pred.dimensions)
             .map_err(|e| {
                 KernelError::InvalidInput(InvalidInput {
                     name: "y_pred".to_string(),
@@ -118,20 +119,17 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             )));
         }
 
-        let accuracy = transform(
-            y_true.buffer.elements().to_vec(),
-            y_pred.buffer.elements().to_vec(),
-        )
-        .unwrap();
+        let accuracy =
+            transform(y_true_values.to_vec(), y_pred_values.to_vec()).unwrap();
 
-        let output = vec![accuracy];
+        let output = [accuracy];
 
         ctx.set_output_tensor(
             "accuracy",
             TensorParam {
                 element_type: ElementType::F64,
                 dimensions: &[1 as u32],
-                buffer: &output.as_bytes(),
+                buffer: output.as_bytes(),
             },
         );
 
@@ -0,0 +1,271 @@
+//! A gate which "opens" when a score crosses a threshold and stays open for a
+//! number of subsequent invocations.
+//!
+//! This is useful for cascading models, where a cheap model (e.g. a wake word
+//! detector) decides when a more expensive model should run.
+//!
+//! The only thing remembered between invocations is how much longer the
+//! gate will stay open.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    mask, prelude::*, runtime_v1::*, state::NodeState, BufferExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// Each node's gate.
+static GATES: NodeState<Gate> = NodeState::new();
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Activation Gate", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("audio");
+        metadata.add_tag("wake-word");
+        metadata.add_tag("stateful");
+
+        let threshold = ArgumentMetadata::new("threshold");
+        threshold.set_description(
+            "The gate opens whenever the score is greater than or equal to this value.",
+        );
+        threshold.set_default_value("0.5");
+        threshold.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&threshold);
+
+        let hold = ArgumentMetadata::new("hold");
+        hold.set_description(
+            "How many invocations the gate stays open for after the score drops below the threshold.",
+        );
+        hold.set_default_value("10");
+        hold.add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&hold);
+
+        let score = TensorMetadata::new("score");
+        score.set_description(
+            "The trigger score (e.g. a wake word's confidence). If there are multiple values, the largest is used.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Dynamic);
+        score.add_hint(&hint);
+        metadata.add_input(&score);
+
+        let active = TensorMetadata::new("active");
+        active.set_description(
+            "A mask which is 1 while the gate is open, otherwise 0.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::U8], DimensionsParam::Fixed(&[1]));
+        active.add_hint(&hint);
+        metadata.add_output(&active);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "score",
+            ElementType::F32,
+            DimensionsParam::Dynamic,
+        );
+        ctx.add_output_tensor(
+            "active",
+            ElementType::U8,
+            DimensionsParam::Fixed(&[1]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            buffer,
+            ..
+        } = ctx.get_input_tensor("score").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "score".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::F32 {
+            return Err(KernelError::InvalidInput(InvalidInput {
+                name: "score".to_string(),
+                reason: BadInputReason::InvalidValue(format!(
+                    "Expected a f32 tensor, found {}",
+                    element_type
+                )),
+            }));
+        }
+
+        let score = buffer
+            .try_elements::<f32>()
+            .map_err(|e| {
+                KernelError::InvalidInput(InvalidInput {
+                    name: "score".to_string(),
+                    reason: BadInputReason::InvalidValue(e.to_string()),
+                })
+            })?
+            .iter()
+            .copied()
+            .fold(f32::NEG_INFINITY, f32::max);
+
+        let active = GATES.with(&id, |gate| gate.update(score, settings));
+
+        ctx.set_output_tensor(
+            "active",
+            TensorParam {
+                element_type: ElementType::U8,
+                dimensions: &[1],
+                buffer: &mask::from_bools([active]),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    threshold: f32,
+    hold: u32,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let threshold =
+            ctx.parse_argument_with_default::<_, E>("threshold", 0.5)?;
+        let hold = ctx.parse_argument_with_default::<_, E>("hold", 10)?;
+
+        Ok(Settings { threshold, hold })
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+struct Gate {
+    /// How many more invocations the gate will stay open for.
+    remaining: u32,
+}
+
+impl Gate {
+    /// Feed the gate a new score, returning whether it is open.
+    fn update(&mut self, score: f32, settings: Settings) -> bool {
+        if score >= settings.threshold {
+            self.remaining = settings.hold;
+            true
+        } else if self.remaining > 0 {
+            self.remaining -= 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: Settings = Settings {
+        threshold: 0.8,
+        hold: 2,
+    };
+
+    fn run(scores: &[f32]) -> Vec<bool> {
+        let mut gate = Gate::default();
+        scores.iter().map(|&s| gate.update(s, SETTINGS)).collect()
+    }
+
+    #[test]
+    fn closed_until_triggered() {
+        assert_eq!(run(&[0.1, 0.5, 0.79]), [false, false, false]);
+    }
+
+    #[test]
+    fn stays_open_for_the_hold_period() {
+        let got = run(&[0.0, 0.9, 0.1, 0.1, 0.1, 0.1]);
+
+        assert_eq!(got, [false, true, true, true, false, false]);
+    }
+
+    #[test]
+    fn retriggering_restarts_the_hold_period() {
+        let got = run(&[0.9, 0.1, 0.95, 0.1, 0.1, 0.1]);
+
+        assert_eq!(got, [true, true, true, true, true, false]);
+    }
+
+    #[test]
+    fn zero_hold_only_opens_while_above_the_threshold() {
+        let settings = Settings {
+            threshold: 0.5,
+            hold: 0,
+        };
+        let mut gate = Gate::default();
+
+        assert!(gate.update(0.5, settings));
+        assert!(!gate.update(0.4, settings));
+    }
+}
@@ -0,0 +1,422 @@
+//! Trade accuracy for compute by choosing which resolution to run a detector
+//! at, based on how confident its last set of detections were.
+//!
+//! While every detection is at least `confidence_threshold` confident (or
+//! nothing is detected at all), the cheaper low resolution is good enough.
+//! As soon as an uncertain detection shows up, this switches to the high
+//! resolution and only drops back down after `cooldown` frames in a row
+//! without any uncertain detections. Starting at the high resolution means
+//! objects aren't missed before the detector has had a chance to see them.
+//!
+//! The output is the `[width, height]` to resize the *next* frame to, so this
+//! would normally sit at the end of a pipeline with its output fed back to
+//! the part of the application which captures and resizes images.
+//!
+//! The current resolution and how many confident frames have been seen in a
+//! row are kept between invocations.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::ArrayView2, prelude::*, runtime_v1::*, state::NodeState,
+    BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// Each node's current resolution.
+static SELECTORS: NodeState<Selector> = NodeState::new();
+
+/// The column containing each detection's confidence, as used by
+/// `object_filter` and `tracker`.
+const CONFIDENCE_COLUMN: usize = 4;
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Adaptive Resolution", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("image");
+        metadata.add_tag("object-detection");
+        metadata.add_tag("stateful");
+
+        let low_resolution = ArgumentMetadata::new("low_resolution");
+        low_resolution.set_description(
+            "The resolution to use when detections are confident, as \"WIDTHxHEIGHT\".",
+        );
+        low_resolution.set_default_value("160x160");
+        low_resolution.add_hint(&supported_argument_type(ArgumentType::String));
+        metadata.add_argument(&low_resolution);
+
+        let high_resolution = ArgumentMetadata::new("high_resolution");
+        high_resolution.set_description(
+            "The resolution to use when detections are uncertain, as \"WIDTHxHEIGHT\".",
+        );
+        high_resolution.set_default_value("320x320");
+        high_resolution
+            .add_hint(&supported_argument_type(ArgumentType::String));
+        metadata.add_argument(&high_resolution);
+
+        let confidence_threshold =
+            ArgumentMetadata::new("confidence_threshold");
+        confidence_threshold.set_description(
+            "Detections less confident than this will switch to the high resolution.",
+        );
+        confidence_threshold.set_default_value("0.5");
+        confidence_threshold
+            .add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&confidence_threshold);
+
+        let cooldown = ArgumentMetadata::new("cooldown");
+        cooldown.set_description(
+            "How many confident frames in a row are needed before switching back to the low resolution.",
+        );
+        cooldown.set_default_value("10");
+        cooldown
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&cooldown);
+
+        let detections = TensorMetadata::new("detections");
+        detections.set_description(
+            "The frame's detections, where the 5th column is the confidence (e.g. the output of `object_filter`).",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        detections.add_hint(&hint);
+        metadata.add_input(&detections);
+
+        let resolution = TensorMetadata::new("resolution");
+        resolution.set_description(
+            "The `[width, height]` the next frame should be resized to.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::U32], DimensionsParam::Fixed(&[2]));
+        resolution.add_hint(&hint);
+        metadata.add_output(&resolution);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "detections",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "resolution",
+            ElementType::U32,
+            DimensionsParam::Fixed(&[2]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("detections").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "detections".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::F32 {
+            return Err(invalid_detections(format!(
+                "Expected a f32 tensor, found {}",
+                element_type
+            )));
+        }
+
+        let detections: ArrayView2<'_, f32> = buffer
+            .try_view(&dimensions)
+            .map_err(invalid_detections)?;
+
+        if detections.ncols() <= CONFIDENCE_COLUMN {
+            return Err(invalid_detections(format!(
+                "Expected at least {} columns, found {:?}",
+                CONFIDENCE_COLUMN + 1,
+                dimensions
+            )));
+        }
+
+        let confidences: Vec<f32> =
+            detections.column(CONFIDENCE_COLUMN).to_vec();
+
+        let Resolution { width, height } = SELECTORS.with(&id, |selector| {
+            selector.update(&confidences, &settings)
+        });
+
+        ctx.set_output_tensor(
+            "resolution",
+            TensorParam {
+                element_type: ElementType::U32,
+                dimensions: &[2],
+                buffer: [width, height].as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_detections(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "detections".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    low_resolution: Resolution,
+    high_resolution: Resolution,
+    confidence_threshold: f32,
+    cooldown: u32,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let low_resolution = ctx.parse_argument_with_default::<_, E>(
+            "low_resolution",
+            Resolution {
+                width: 160,
+                height: 160,
+            },
+        )?;
+        let high_resolution = ctx.parse_argument_with_default::<_, E>(
+            "high_resolution",
+            Resolution {
+                width: 320,
+                height: 320,
+            },
+        )?;
+        let confidence_threshold: f32 = ctx
+            .parse_argument_with_default::<_, E>("confidence_threshold", 0.5)?;
+        let cooldown = ctx.parse_argument_with_default::<_, E>("cooldown", 10)?;
+
+        if !(0.0..=1.0).contains(&confidence_threshold) {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "confidence_threshold",
+                "The threshold must be in the range [0, 1]",
+            )));
+        }
+
+        Ok(Settings {
+            low_resolution,
+            high_resolution,
+            confidence_threshold,
+            cooldown,
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Resolution {
+    width: u32,
+    height: u32,
+}
+
+impl FromStr for Resolution {
+    type Err = InvalidResolution;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidResolution(s.to_string());
+
+        let (width, height) = s.split_once('x').ok_or_else(invalid)?;
+        let width: u32 = width.trim().parse().map_err(|_| invalid())?;
+        let height: u32 = height.trim().parse().map_err(|_| invalid())?;
+
+        if width == 0 || height == 0 {
+            return Err(invalid());
+        }
+
+        Ok(Resolution { width, height })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct InvalidResolution(String);
+
+impl Display for InvalidResolution {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Expected a resolution like \"320x240\", found \"{}\"",
+            self.0
+        )
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+struct Selector {
+    /// Are we currently using the low resolution?
+    low: bool,
+    /// How many frames in a row have only had confident detections.
+    confident_frames: u32,
+}
+
+impl Selector {
+    /// Look at a frame's detection confidences and pick the resolution to use
+    /// for the next frame.
+    fn update(
+        &mut self,
+        confidences: &[f32],
+        settings: &Settings,
+    ) -> Resolution {
+        let uncertain = confidences
+            .iter()
+            .any(|&c| c < settings.confidence_threshold);
+
+        if uncertain {
+            self.low = false;
+            self.confident_frames = 0;
+        } else {
+            self.confident_frames = self.confident_frames.saturating_add(1);
+
+            if self.confident_frames >= settings.cooldown {
+                self.low = true;
+            }
+        }
+
+        if self.low {
+            settings.low_resolution
+        } else {
+            settings.high_resolution
+        }
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOW: Resolution = Resolution {
+        width: 160,
+        height: 120,
+    };
+    const HIGH: Resolution = Resolution {
+        width: 640,
+        height: 480,
+    };
+    const SETTINGS: Settings = Settings {
+        low_resolution: LOW,
+        high_resolution: HIGH,
+        confidence_threshold: 0.5,
+        cooldown: 3,
+    };
+
+    #[test]
+    fn parse_resolution() {
+        assert_eq!("640x480".parse::<Resolution>().unwrap(), HIGH);
+        assert_eq!(" 160 x 120 ".parse::<Resolution>().unwrap(), LOW);
+        assert!("640".parse::<Resolution>().is_err());
+        assert!("0x480".parse::<Resolution>().is_err());
+        assert!("axb".parse::<Resolution>().is_err());
+    }
+
+    #[test]
+    fn start_at_high_resolution_then_cool_down() {
+        let mut selector = Selector::default();
+
+        let resolutions: Vec<_> = (0..4)
+            .map(|_| selector.update(&[0.9], &SETTINGS))
+            .collect();
+
+        assert_eq!(resolutions, [HIGH, HIGH, LOW, LOW]);
+    }
+
+    #[test]
+    fn nothing_detected_counts_as_confident() {
+        let mut selector = Selector::default();
+
+        for _ in 0..SETTINGS.cooldown {
+            selector.update(&[], &SETTINGS);
+        }
+
+        assert_eq!(selector.update(&[], &SETTINGS), LOW);
+    }
+
+    #[test]
+    fn uncertain_detections_switch_to_high_resolution() {
+        let mut selector = Selector {
+            low: true,
+            confident_frames: 100,
+        };
+
+        assert_eq!(selector.update(&[0.9, 0.3], &SETTINGS), HIGH);
+        // And we need to wait for the cooldown again
+        assert_eq!(selector.update(&[0.9], &SETTINGS), HIGH);
+        assert_eq!(selector.update(&[0.9], &SETTINGS), HIGH);
+        assert_eq!(selector.update(&[0.9], &SETTINGS), LOW);
+    }
+}
@@ -0,0 +1,605 @@
+//! Align two timestamped streams (e.g. from an accelerometer and a gyroscope
+//! which are sampled at different rates) so that their samples line up.
+//!
+//! Both streams are linearly interpolated onto a common, evenly spaced time
+//! base covering the period where they overlap.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    common::element_type::parse_element_type,
+    ndarray::{Array2, Axis, Ix2},
+    prelude::*,
+    runtime_v1::*,
+    BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+const TIMESTAMP_TYPES: &[ElementType] = &[
+    ElementType::U32,
+    ElementType::I32,
+    ElementType::F32,
+    ElementType::U64,
+    ElementType::I64,
+    ElementType::F64,
+];
+const TIMESTAMP_TYPE_NAMES: &[&str] =
+    &["u32", "i32", "f32", "u64", "i64", "f64"];
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Align Streams", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("sensor");
+        metadata.add_tag("resampling");
+        metadata.add_tag("sensor-fusion");
+
+        let period = ArgumentMetadata::new("period");
+        period.set_description(
+            "The time between samples in the aligned streams, using the same units as the timestamps. Defaults to the sampling period of the faster stream.",
+        );
+        period.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&period);
+
+        let timestamp_type = ArgumentMetadata::new("timestamp_type");
+        timestamp_type
+            .set_description("The element type used by both timestamp tensors.");
+        timestamp_type.set_default_value("f64");
+        let hint = interpret_as_string_in_enum(TIMESTAMP_TYPE_NAMES);
+        timestamp_type.add_hint(&hint);
+        metadata.add_argument(&timestamp_type);
+
+        for name in ["a", "b"] {
+            let values = TensorMetadata::new(name);
+            values.set_description(
+                "The stream's samples, either `[samples]` or `[samples, channels]`.",
+            );
+            let hint = supported_shapes(
+                &[ElementType::F32],
+                DimensionsParam::Dynamic,
+            );
+            values.add_hint(&hint);
+            metadata.add_input(&values);
+
+            let timestamps =
+                TensorMetadata::new(&format!("{}_timestamps", name));
+            timestamps.set_description(
+                "The time each sample was taken. This must be increasing.",
+            );
+            let hint =
+                supported_shapes(TIMESTAMP_TYPES, DimensionsParam::Fixed(&[0]));
+            timestamps.add_hint(&hint);
+            metadata.add_input(&timestamps);
+        }
+
+        let timestamps = TensorMetadata::new("timestamps");
+        timestamps.set_description("The common time base.");
+        let hint =
+            supported_shapes(&[ElementType::F64], DimensionsParam::Fixed(&[0]));
+        timestamps.add_hint(&hint);
+        metadata.add_output(&timestamps);
+
+        for name in ["a_aligned", "b_aligned"] {
+            let aligned = TensorMetadata::new(name);
+            aligned.set_description(
+                "The stream's samples at each of the common timestamps.",
+            );
+            let hint = supported_shapes(
+                &[ElementType::F32],
+                DimensionsParam::Dynamic,
+            );
+            aligned.add_hint(&hint);
+            metadata.add_output(&aligned);
+        }
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = period::<_, GraphError>(&ctx)?;
+        let timestamp_type = timestamp_type::<_, GraphError>(&ctx)?;
+
+        for name in ["a", "b"] {
+            ctx.add_input_tensor(
+                name,
+                ElementType::F32,
+                DimensionsParam::Dynamic,
+            );
+            ctx.add_input_tensor(
+                &format!("{}_timestamps", name),
+                timestamp_type,
+                DimensionsParam::Fixed(&[0]),
+            );
+        }
+
+        ctx.add_output_tensor(
+            "timestamps",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0]),
+        );
+        ctx.add_output_tensor(
+            "a_aligned",
+            ElementType::F32,
+            DimensionsParam::Dynamic,
+        );
+        ctx.add_output_tensor(
+            "b_aligned",
+            ElementType::F32,
+            DimensionsParam::Dynamic,
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let period = period::<_, KernelError>(&ctx)?;
+
+        let a = Stream::from_context(&ctx, "a")?;
+        let b = Stream::from_context(&ctx, "b")?;
+
+        let timestamps = common_time_base(&a, &b, period)?;
+        let a_aligned = a.resample(&timestamps);
+        let b_aligned = b.resample(&timestamps);
+
+        ctx.set_output_tensor(
+            "timestamps",
+            TensorParam {
+                element_type: ElementType::F64,
+                dimensions: &[timestamps.len() as u32],
+                buffer: timestamps.as_bytes(),
+            },
+        );
+
+        for (name, stream, aligned) in
+            [("a_aligned", &a, a_aligned), ("b_aligned", &b, b_aligned)]
+        {
+            let dimensions = stream.output_dimensions(timestamps.len());
+
+            ctx.set_output_tensor(
+                name,
+                TensorParam {
+                    element_type: ElementType::F32,
+                    dimensions: &dimensions,
+                    buffer: aligned
+                        .as_slice()
+                        .expect("Freshly created arrays are contiguous")
+                        .as_bytes(),
+                },
+            );
+        }
+
+        Ok(())
+    }
+}
+
+fn period<C, E>(ctx: &C) -> Result<Option<f64>, E>
+where
+    C: ContextExt,
+    E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+{
+    let period = match ctx._get_argument("period") {
+        Some(_) => ctx.parse_argument::<f64, E>("period")?,
+        None => return Ok(None),
+    };
+
+    if period > 0.0 && period.is_finite() {
+        Ok(Some(period))
+    } else {
+        Err(E::invalid_argument(InvalidArgument::invalid_value(
+            "period",
+            "The period must be a positive number",
+        )))
+    }
+}
+
+fn timestamp_type<C, E>(ctx: &C) -> Result<ElementType, E>
+where
+    C: ContextExt,
+    E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+{
+    match ctx._get_argument("timestamp_type") {
+        Some(name) => parse_element_type(&name, TIMESTAMP_TYPE_NAMES)
+            .map_err(|e| {
+                E::invalid_argument(InvalidArgument::invalid_value(
+                    "timestamp_type",
+                    e,
+                ))
+            }),
+        None => Ok(ElementType::F64),
+    }
+}
+
+fn invalid_input(name: &str, reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: name.to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+fn get_input(
+    ctx: &KernelContext,
+    name: &str,
+) -> Result<TensorResult, KernelError> {
+    ctx.get_input_tensor(name).ok_or_else(|| {
+        KernelError::InvalidInput(InvalidInput {
+            name: name.to_string(),
+            reason: BadInputReason::NotFound,
+        })
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Stream {
+    name: &'static str,
+    timestamps: Vec<f64>,
+    /// The samples as a `[samples, channels]` array.
+    values: Array2<f32>,
+    /// Was the input 1D?
+    is_1d: bool,
+}
+
+impl Stream {
+    fn from_context(
+        ctx: &KernelContext,
+        name: &'static str,
+    ) -> Result<Self, KernelError> {
+        let timestamps_name = format!("{}_timestamps", name);
+        let timestamps = get_input(ctx, &timestamps_name)?;
+        let timestamps = timestamps_to_f64(&timestamps)
+            .map_err(|e| invalid_input(&timestamps_name, e))?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = get_input(ctx, name)?;
+
+        if element_type != ElementType::F32 {
+            return Err(invalid_input(
+                name,
+                format!("Expected a f32 tensor, found {}", element_type),
+            ));
+        }
+
+        let (values, is_1d) = match *dimensions {
+            [samples] => (buffer.try_view::<f32, Ix2>(&[samples, 1]), true),
+            [_, _] => (buffer.try_view::<f32, Ix2>(&dimensions), false),
+            _ => {
+                return Err(invalid_input(
+                    name,
+                    format!(
+                        "Expected a [samples] or [samples, channels] tensor, found {:?}",
+                        dimensions
+                    ),
+                ))
+            },
+        };
+        let values = values
+            .map_err(|e| invalid_input(name, e))?
+            .to_owned();
+
+        Stream::new(name, timestamps, values, is_1d)
+    }
+
+    fn new(
+        name: &'static str,
+        timestamps: Vec<f64>,
+        values: Array2<f32>,
+        is_1d: bool,
+    ) -> Result<Self, KernelError> {
+        if timestamps.len() != values.nrows() {
+            return Err(invalid_input(
+                name,
+                format!(
+                    "Expected {} samples (one per timestamp), found {}",
+                    timestamps.len(),
+                    values.nrows()
+                ),
+            ));
+        }
+
+        if timestamps.is_empty() {
+            return Err(invalid_input(name, "The stream is empty"));
+        }
+
+        if timestamps.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(invalid_input(
+                &format!("{}_timestamps", name),
+                "Timestamps must be strictly increasing",
+            ));
+        }
+
+        Ok(Stream {
+            name,
+            timestamps,
+            values,
+            is_1d,
+        })
+    }
+
+    fn start(&self) -> f64 { self.timestamps[0] }
+
+    fn end(&self) -> f64 { self.timestamps[self.timestamps.len() - 1] }
+
+    /// The average time between samples, if there are enough samples to know.
+    fn average_period(&self) -> Option<f64> {
+        let intervals = self.timestamps.len().checked_sub(1)?;
+
+        if intervals == 0 {
+            None
+        } else {
+            Some((self.end() - self.start()) / intervals as f64)
+        }
+    }
+
+    /// Interpolate each channel at the provided timestamps.
+    fn resample(&self, timestamps: &[f64]) -> Array2<f32> {
+        let channels = self.values.ncols();
+        let mut resampled = Array2::zeros((timestamps.len(), channels));
+
+        for (channel, mut output) in self
+            .values
+            .axis_iter(Axis(1))
+            .zip(resampled.axis_iter_mut(Axis(1)))
+        {
+            let channel: Vec<f64> =
+                channel.iter().map(|&v| f64::from(v)).collect();
+            let interpolated =
+                interp1d_core::interp(timestamps, &self.timestamps, &channel);
+
+            for (out, value) in output.iter_mut().zip(interpolated) {
+                *out = value as f32;
+            }
+        }
+
+        resampled
+    }
+
+    fn output_dimensions(&self, samples: usize) -> Vec<u32> {
+        if self.is_1d {
+            vec![samples as u32]
+        } else {
+            vec![samples as u32, self.values.ncols() as u32]
+        }
+    }
+}
+
+fn timestamps_to_f64(tensor: &TensorResult) -> Result<Vec<f64>, String> {
+    let TensorResult {
+        element_type,
+        dimensions,
+        buffer,
+    } = tensor;
+
+    if dimensions.len() != 1 {
+        return Err(format!(
+            "Timestamps should be a 1D tensor, found {:?}",
+            dimensions
+        ));
+    }
+
+    let timestamps = match element_type {
+        ElementType::U32 => convert::<u32>(buffer)?,
+        ElementType::I32 => convert::<i32>(buffer)?,
+        ElementType::F32 => convert::<f32>(buffer)?,
+        ElementType::U64 => {
+            elements::<u64>(buffer)?.iter().map(|&t| t as f64).collect()
+        },
+        ElementType::I64 => {
+            elements::<i64>(buffer)?.iter().map(|&t| t as f64).collect()
+        },
+        ElementType::F64 => elements::<f64>(buffer)?.to_vec(),
+        other => {
+            return Err(format!("Timestamps must be numeric, found {}", other))
+        },
+    };
+
+    Ok(timestamps)
+}
+
+fn convert<T>(buffer: &[u8]) -> Result<Vec<f64>, String>
+where
+    T: hotg_rune_proc_blocks::ValueType + Copy + Into<f64>,
+{
+    Ok(elements::<T>(buffer)?.iter().map(|&t| t.into()).collect())
+}
+
+fn elements<T>(buffer: &[u8]) -> Result<&[T], String>
+where
+    T: hotg_rune_proc_blocks::ValueType,
+{
+    buffer.try_elements().map_err(|e| e.to_string())
+}
+
+/// Find evenly spaced timestamps covering the period where both streams
+/// overlap.
+fn common_time_base(
+    a: &Stream,
+    b: &Stream,
+    period: Option<f64>,
+) -> Result<Vec<f64>, KernelError> {
+    let start = f64::max(a.start(), b.start());
+    let end = f64::min(a.end(), b.end());
+
+    if start > end {
+        return Err(KernelError::Other(format!(
+            "The \"{}\" stream ({} to {}) and \"{}\" stream ({} to {}) don't overlap",
+            a.name,
+            a.start(),
+            a.end(),
+            b.name,
+            b.start(),
+            b.end(),
+        )));
+    }
+
+    let period = match period {
+        Some(p) => p,
+        None => match (a.average_period(), b.average_period()) {
+            (Some(x), Some(y)) => f64::min(x, y),
+            (Some(p), None) | (None, Some(p)) => p,
+            (None, None) => {
+                return Err(KernelError::Other(
+                    "Unable to determine a sampling period from streams with a single sample. Please set the \"period\" argument.".to_string(),
+                ))
+            },
+        },
+    };
+
+    // Note: the small epsilon makes sure we don't lose the last sample due to
+    // rounding errors.
+    let samples = ((end - start) / period + 1e-9).floor() as usize + 1;
+
+    Ok((0..samples).map(|i| start + i as f64 * period).collect())
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::{
+        ndarray,
+        testing::{assert_slices_close, assert_tensors_close, Tolerance},
+    };
+
+    fn stream(timestamps: &[f64], values: &[f32]) -> Stream {
+        let values = Array2::from_shape_vec((values.len(), 1), values.to_vec())
+            .unwrap();
+        Stream::new("test", timestamps.to_vec(), values, true).unwrap()
+    }
+
+    #[test]
+    fn time_base_covers_the_overlap_at_the_faster_rate() {
+        // 10 Hz and 20 Hz
+        let a = stream(&[0.0, 0.1, 0.2, 0.3], &[0.0; 4]);
+        let b = stream(&[0.05, 0.1, 0.15, 0.2, 0.25], &[0.0; 5]);
+
+        let got = common_time_base(&a, &b, None).unwrap();
+
+        assert_slices_close(
+            &got,
+            &[0.05, 0.1, 0.15, 0.2, 0.25],
+            Tolerance::DEFAULT,
+        );
+    }
+
+    #[test]
+    fn explicit_period() {
+        let a = stream(&[0.0, 1.0], &[0.0, 0.0]);
+        let b = stream(&[0.0, 1.0], &[0.0, 0.0]);
+
+        let got = common_time_base(&a, &b, Some(0.25)).unwrap();
+
+        assert_slices_close(
+            &got,
+            &[0.0, 0.25, 0.5, 0.75, 1.0],
+            Tolerance::DEFAULT,
+        );
+    }
+
+    #[test]
+    fn streams_must_overlap() {
+        let a = stream(&[0.0, 1.0], &[0.0, 0.0]);
+        let b = stream(&[2.0, 3.0], &[0.0, 0.0]);
+
+        assert!(common_time_base(&a, &b, None).is_err());
+    }
+
+    #[test]
+    fn resample_each_channel() {
+        let values = ndarray::arr2(&[[0.0_f32, 10.0], [1.0, 20.0]]);
+        let s = Stream::new("test", vec![0.0, 2.0], values, false).unwrap();
+
+        let got = s.resample(&[0.0, 0.5, 1.0, 2.0]);
+
+        let should_be = ndarray::arr2(&[
+            [0.0, 10.0],
+            [0.25, 12.5],
+            [0.5, 15.0],
+            [1.0, 20.0],
+        ]);
+        assert_tensors_close(&got, &should_be, Tolerance::F32);
+        assert_eq!(s.output_dimensions(4), [4, 2]);
+    }
+
+    #[test]
+    fn aligned_streams_line_up() {
+        let accelerometer = stream(&[0.0, 0.5, 1.0], &[0.0, 5.0, 10.0]);
+        let gyroscope = stream(&[0.0, 0.25, 0.5, 0.75, 1.0], &[4.0; 5]);
+
+        let timestamps =
+            common_time_base(&accelerometer, &gyroscope, None).unwrap();
+        let a = accelerometer.resample(&timestamps);
+        let b = gyroscope.resample(&timestamps);
+
+        assert_eq!(a.nrows(), b.nrows());
+        assert_tensors_close(
+            &a,
+            &ndarray::arr2(&[[0.0], [2.5], [5.0], [7.5], [10.0]]),
+            Tolerance::F32,
+        );
+        assert_eq!(accelerometer.output_dimensions(5), [5]);
+    }
+
+    #[test]
+    fn timestamps_must_increase() {
+        let values = Array2::zeros((3, 1));
+
+        let err = Stream::new("test", vec![0.0, 2.0, 1.0], values, true)
+            .unwrap_err();
+
+        assert!(matches!(err, KernelError::InvalidInput(_)));
+    }
+}
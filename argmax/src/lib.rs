@@ -1,7 +1,7 @@
 use crate::proc_block_v1::{
     BadInputReason, GraphError, InvalidInput, KernelError,
 };
-use hotg_rune_proc_blocks::{runtime_v1::*, BufferExt};
+use hotg_rune_proc_blocks::{runtime_v1::*, BufferExt, ValueType};
 use std::cmp::Ordering;
 
 wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
@@ -70,16 +70,16 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         })?;
 
         let index = match element_type {
-            ElementType::U8 => arg_max(buffer.elements::<u8>()),
-            ElementType::I8 => arg_max(buffer.elements::<i8>()),
-            ElementType::U16 => arg_max(buffer.elements::<u16>()),
-            ElementType::I16 => arg_max(buffer.elements::<i16>()),
-            ElementType::U32 => arg_max(buffer.elements::<u32>()),
-            ElementType::I32 => arg_max(buffer.elements::<i32>()),
-            ElementType::F32 => arg_max(buffer.elements::<f32>()),
-            ElementType::U64 => arg_max(buffer.elements::<u64>()),
-            ElementType::I64 => arg_max(buffer.elements::<i64>()),
-            ElementType::F64 => arg_max(buffer.elements::<f64>()),
+            ElementType::U8 => arg_max(elements::<u8>(&buffer)?),
+            ElementType::I8 => arg_max(elements::<i8>(&buffer)?),
+            ElementType::U16 => arg_max(elements::<u16>(&buffer)?),
+            ElementType::I16 => arg_max(elements::<i16>(&buffer)?),
+            ElementType::U32 => arg_max(elements::<u32>(&buffer)?),
+            ElementType::I32 => arg_max(elements::<i32>(&buffer)?),
+            ElementType::F32 => arg_max(elements::<f32>(&buffer)?),
+            ElementType::U64 => arg_max(elements::<u64>(&buffer)?),
+            ElementType::I64 => arg_max(elements::<i64>(&buffer)?),
+            ElementType::F64 => arg_max(elements::<f64>(&buffer)?),
             other => {
                 return Err(KernelError::Other(format!(
                     "The Arg Max proc-block doesn't support {:?} element type",
@@ -123,6 +123,15 @@ where
     Some(index)
 }
 
+fn elements<T: ValueType>(buffer: &[u8]) -> Result<&[T], KernelError> {
+    buffer.try_elements().map_err(|e| {
+        KernelError::InvalidInput(InvalidInput {
+            name: "input".to_string(),
+            reason: BadInputReason::InvalidValue(e.to_string()),
+        })
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2,7 +2,9 @@ use crate::proc_block_v1::{
     BadInputReason, GraphError, InvalidInput, KernelError,
 };
 use hotg_rune_proc_blocks::{
-    ndarray::ArrayView1, runtime_v1::*, BufferExt, SliceExt,
+    ndarray::ArrayView1,
+    runtime_v1::*,
+    BufferExt, InvalidBuffer, SliceExt,
 };
 
 wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
@@ -79,17 +81,18 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
 
         let tensor: ArrayView1<i16> = match element_type {
             ElementType::I16 => {
-                let tensor = buffer.view::<i16>(&dimensions)
-                    .map_err(|e| KernelError::InvalidInput(InvalidInput {
+                buffer.try_view(&dimensions).map_err(|e| {
+                    let reason = match e {
+                        InvalidBuffer::WrongRank { .. } => {
+                            BadInputReason::UnsupportedShape
+                        },
+                        other => BadInputReason::Other(other.to_string()),
+                    };
+                    KernelError::InvalidInput(InvalidInput {
                         name: "input".to_string(),
-                        reason: BadInputReason::Other(e.to_string()),
-                    }))?;
-
-                    tensor.into_dimensionality()
-                    .map_err(|_| KernelError::InvalidInput(InvalidInput {
-                        name: "input".to_string(),
-                        reason: BadInputReason::UnsupportedShape,
-                    }))?
+                        reason,
+                    })
+                })?
             },
 
             other => {
@@ -0,0 +1,441 @@
+//! Convert `i16` PCM audio from one sample rate to another.
+//!
+//! Microphones typically deliver 44.1 kHz or 48 kHz audio, while the `fft`
+//! proc-block and most speech models assume 16 kHz.
+
+use std::f64::consts::PI;
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{prelude::*, runtime_v1::*, BufferExt, SliceExt};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// How many zero crossings of the sinc function to use on either side of a
+/// sample.
+const SINC_ZERO_CROSSINGS: f64 = 16.0;
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Audio Resample", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("audio");
+        metadata.add_tag("resample");
+
+        let input_rate = ArgumentMetadata::new("input_rate");
+        input_rate
+            .set_description("The sample rate of the input audio, in Hz.");
+        input_rate.set_default_value("44100");
+        input_rate
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&input_rate);
+
+        let output_rate = ArgumentMetadata::new("output_rate");
+        output_rate
+            .set_description("The sample rate to convert the audio to, in Hz.");
+        output_rate.set_default_value("16000");
+        output_rate
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&output_rate);
+
+        let interpolation = ArgumentMetadata::new("interpolation");
+        interpolation.set_description(
+            "`linear` interpolation is cheap, while `sinc` uses a windowed sinc filter which avoids aliasing when downsampling.",
+        );
+        interpolation.set_default_value("linear");
+        interpolation
+            .add_hint(&interpret_as_string_in_enum(Interpolation::NAMES));
+        metadata.add_argument(&interpolation);
+
+        let input = TensorMetadata::new("audio");
+        input.set_description("A 1D tensor of `i16` samples.");
+        let hint = supported_shapes(
+            &[ElementType::I16],
+            DimensionsParam::Fixed(&[1, 0]),
+        );
+        input.add_hint(&hint);
+        metadata.add_input(&input);
+
+        let output = TensorMetadata::new("resampled");
+        output.set_description("The audio, at the output sample rate.");
+        let hint = supported_shapes(
+            &[ElementType::I16],
+            DimensionsParam::Fixed(&[1, 0]),
+        );
+        output.add_hint(&hint);
+        metadata.add_output(&output);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "audio",
+            ElementType::I16,
+            DimensionsParam::Fixed(&[1, 0]),
+        );
+        ctx.add_output_tensor(
+            "resampled",
+            ElementType::I16,
+            DimensionsParam::Fixed(&[1, 0]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let Settings {
+            input_rate,
+            output_rate,
+            interpolation,
+        } = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("audio").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "audio".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::I16 {
+            return Err(invalid_input(format!(
+                "The Audio Resample proc-block only accepts i16 tensors, found {}",
+                element_type
+            )));
+        }
+
+        match *dimensions {
+            [_] | [1, _] => {},
+            _ => {
+                return Err(invalid_input(format!(
+                    "Expected a 1D tensor of samples, found {:?}",
+                    dimensions
+                )))
+            },
+        }
+
+        let samples = buffer
+            .try_elements_with_shape::<i16>(&dimensions)
+            .map_err(invalid_input)?;
+        let resampled =
+            resample(samples, input_rate, output_rate, interpolation);
+
+        let mut output_dimensions = dimensions.clone();
+        if let Some(last) = output_dimensions.last_mut() {
+            *last = resampled.len() as u32;
+        }
+
+        ctx.set_output_tensor(
+            "resampled",
+            TensorParam {
+                element_type: ElementType::I16,
+                dimensions: &output_dimensions,
+                buffer: resampled.as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "audio".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+/// Convert `samples` recorded at `input_rate` Hz to `output_rate` Hz.
+///
+/// # Panics
+///
+/// Both sample rates must be non-zero.
+pub fn resample(
+    samples: &[i16],
+    input_rate: u32,
+    output_rate: u32,
+    interpolation: Interpolation,
+) -> Vec<i16> {
+    assert!(input_rate > 0 && output_rate > 0);
+
+    if input_rate == output_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    // Round up so the last partial step still gets a sample
+    let scaled_len = samples.len() as u64 * output_rate as u64;
+    let output_len = (scaled_len + input_rate as u64 - 1) / input_rate as u64;
+    // How far to move through the input for each output sample
+    let step = input_rate as f64 / output_rate as f64;
+
+    (0..output_len)
+        .map(|i| {
+            let position = i as f64 * step;
+            let value = match interpolation {
+                Interpolation::Linear => linear(samples, position),
+                Interpolation::Sinc => sinc(samples, position, step),
+            };
+            value.round().clamp(i16::MIN as f64, i16::MAX as f64) as i16
+        })
+        .collect()
+}
+
+fn linear(samples: &[i16], position: f64) -> f64 {
+    let index = position.floor() as usize;
+    let fraction = position - index as f64;
+
+    let current = samples[index] as f64;
+    let next = samples.get(index + 1).map_or(current, |&s| s as f64);
+
+    current + (next - current) * fraction
+}
+
+/// Evaluate a Hann-windowed sinc filter centered at `position`, lowering the
+/// filter's cutoff when downsampling so frequencies above the new Nyquist
+/// limit don't alias.
+fn sinc(samples: &[i16], position: f64, step: f64) -> f64 {
+    let cutoff = f64::min(1.0, 1.0 / step);
+    let half_width = SINC_ZERO_CROSSINGS / cutoff;
+
+    let first = (position - half_width).ceil().max(0.0) as usize;
+    let last = ((position + half_width).floor() as usize)
+        .min(samples.len() - 1);
+
+    let mut sum = 0.0;
+    let mut total_weight = 0.0;
+
+    for (index, &sample) in (first..=last).zip(&samples[first..=last]) {
+        let distance = index as f64 - position;
+        let weight = windowed_sinc(distance, cutoff, half_width);
+        sum += sample as f64 * weight;
+        total_weight += weight;
+    }
+
+    if total_weight == 0.0 {
+        0.0
+    } else {
+        sum / total_weight
+    }
+}
+
+fn windowed_sinc(distance: f64, cutoff: f64, half_width: f64) -> f64 {
+    if distance.abs() >= half_width {
+        return 0.0;
+    }
+
+    let x = PI * cutoff * distance;
+    let sinc = if x == 0.0 { 1.0 } else { x.sin() / x };
+    let window = 0.5 * (1.0 + (PI * distance / half_width).cos());
+
+    cutoff * sinc * window
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    Sinc,
+}
+
+impl Interpolation {
+    pub const NAMES: &'static [&'static str] = &["linear", "sinc"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "linear" => Some(Interpolation::Linear),
+            "sinc" => Some(Interpolation::Sinc),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    input_rate: u32,
+    output_rate: u32,
+    interpolation: Interpolation,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let input_rate =
+            ctx.parse_argument_with_default::<u32, E>("input_rate", 44100)?;
+        let output_rate =
+            ctx.parse_argument_with_default::<u32, E>("output_rate", 16000)?;
+        let interpolation = ctx.parse_argument_with_default::<_, E>(
+            "interpolation",
+            String::from("linear"),
+        )?;
+
+        for (name, value) in
+            [("input_rate", input_rate), ("output_rate", output_rate)]
+        {
+            if value == 0 {
+                return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                    name,
+                    "The sample rate must be greater than zero",
+                )));
+            }
+        }
+
+        let interpolation = Interpolation::from_name(&interpolation)
+            .ok_or_else(|| {
+                E::invalid_argument(InvalidArgument::invalid_value(
+                    "interpolation",
+                    format!(
+                        "Unknown interpolation, \"{}\". Expected one of {:?}",
+                        interpolation,
+                        Interpolation::NAMES
+                    ),
+                ))
+            })?;
+
+        Ok(Settings {
+            input_rate,
+            output_rate,
+            interpolation,
+        })
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine_wave(frequency: f64, sample_rate: u32, len: usize) -> Vec<i16> {
+        (0..len)
+            .map(|i| {
+                let t = i as f64 / sample_rate as f64;
+                (10_000.0 * (2.0 * PI * frequency * t).sin()).round() as i16
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_rate_is_a_no_op() {
+        let samples = [1, -2, 3, -4];
+
+        for &name in Interpolation::NAMES {
+            let interpolation = Interpolation::from_name(name).unwrap();
+
+            let got = resample(&samples, 16000, 16000, interpolation);
+
+            assert_eq!(got, samples, "{}", name);
+        }
+    }
+
+    #[test]
+    fn linear_upsampling_fills_in_the_gaps() {
+        let samples = [0, 100, -100];
+
+        let got = resample(&samples, 8000, 16000, Interpolation::Linear);
+
+        assert_eq!(got, vec![0, 50, 100, 0, -100, -100]);
+    }
+
+    #[test]
+    fn microphone_audio_to_speech_model_audio() {
+        let samples = vec![0; 44100];
+
+        let got = resample(&samples, 44100, 16000, Interpolation::Linear);
+
+        assert_eq!(got.len(), 16000);
+    }
+
+    #[test]
+    fn sinc_preserves_a_constant_signal() {
+        let samples = vec![1234; 480];
+
+        let got = resample(&samples, 48000, 16000, Interpolation::Sinc);
+
+        assert_eq!(got.len(), 160);
+        assert!(got.iter().all(|&s| s == 1234), "{:?}", got);
+    }
+
+    #[test]
+    fn sinc_downsampling_keeps_low_frequencies() {
+        let samples = sine_wave(500.0, 44100, 4410);
+        let should_be = sine_wave(500.0, 16000, 1600);
+
+        let got = resample(&samples, 44100, 16000, Interpolation::Sinc);
+
+        assert_eq!(got.len(), should_be.len());
+        // Ignore the edges, where the filter runs off the end of the signal
+        for i in 100..1500 {
+            let difference = (got[i] as i32 - should_be[i] as i32).abs();
+            assert!(difference < 100, "{}: {} vs {}", i, got[i], should_be[i]);
+        }
+    }
+
+    #[test]
+    fn sinc_downsampling_removes_frequencies_above_nyquist() {
+        // 12 kHz is above the 8 kHz Nyquist limit for 16 kHz audio
+        let samples = sine_wave(12_000.0, 48000, 4800);
+
+        let got = resample(&samples, 48000, 16000, Interpolation::Sinc);
+
+        let peak = got[100..1500].iter().map(|s| s.abs()).max().unwrap();
+        assert!(peak < 1000, "{}", peak);
+    }
+}
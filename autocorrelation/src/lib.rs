@@ -0,0 +1,331 @@
+//! The autocorrelation function (ACF) of a 1-D signal,
+//!
+//! ```text
+//! acf[k] = Σ (x[t] - mean) * (x[t + k] - mean) / Σ (x[t] - mean)²
+//! ```
+//!
+//! A signal which repeats every `p` samples will have a peak at `acf[p]`, so
+//! the ACF is a cheap way to estimate the cadence of something like a
+//! person's gait or a vibrating motor without needing a full spectrum.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    prelude::*, runtime_v1::*, BufferExt, SliceExt,
+};
+use std::cmp::Ordering;
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Autocorrelation", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("time series");
+        metadata.add_tag("signal processing");
+        metadata.add_tag("frequency");
+
+        let max_lag = ArgumentMetadata::new("max_lag");
+        max_lag.set_description(
+            "The largest lag (in samples) to calculate the autocorrelation for. This must be less than the signal's length.",
+        );
+        max_lag.add_hint(&non_negative_number());
+        max_lag.add_hint(&supported_argument_type(ArgumentType::Integer));
+        metadata.add_argument(&max_lag);
+
+        let signal = TensorMetadata::new("signal");
+        signal.set_description("The signal to analyse.");
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Fixed(&[0]));
+        signal.add_hint(&hint);
+        metadata.add_input(&signal);
+
+        let acf = TensorMetadata::new("acf");
+        acf.set_description(
+            "The autocorrelation for every lag from 0 to `max_lag` (inclusive), normalized so the value at lag 0 is 1.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Fixed(&[0]));
+        acf.add_hint(&hint);
+        metadata.add_output(&acf);
+
+        let period = TensorMetadata::new("period");
+        period.set_description(
+            "The lag of the strongest peak in the autocorrelation (i.e. the signal's dominant period, in samples), or 0 if the signal doesn't repeat within `max_lag` samples.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::U32], DimensionsParam::Fixed(&[1]));
+        period.add_hint(&hint);
+        metadata.add_output(&period);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        let max_lag: u32 = ctx.parse_argument::<_, GraphError>("max_lag")?;
+
+        ctx.add_input_tensor(
+            "signal",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0]),
+        );
+        ctx.add_output_tensor(
+            "acf",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[max_lag + 1]),
+        );
+        ctx.add_output_tensor(
+            "period",
+            ElementType::U32,
+            DimensionsParam::Fixed(&[1]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let max_lag: usize = ctx.parse_argument::<_, KernelError>("max_lag")?;
+
+        let TensorResult {
+            element_type,
+            buffer,
+            ..
+        } = ctx.get_input_tensor("signal").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "signal".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::F32 {
+            return Err(invalid_input(format!(
+                "Expected a f32 tensor, found {}",
+                element_type
+            )));
+        }
+
+        let signal = buffer.try_elements::<f32>().map_err(invalid_input)?;
+
+        if max_lag >= signal.len() {
+            return Err(invalid_input(format!(
+                "The max lag ({}) must be less than the signal's length ({})",
+                max_lag,
+                signal.len()
+            )));
+        }
+
+        let acf = autocorrelation(signal, max_lag);
+        let period = dominant_period(&acf).unwrap_or(0) as u32;
+
+        ctx.set_output_tensor(
+            "acf",
+            TensorParam {
+                element_type: ElementType::F32,
+                dimensions: &[acf.len() as u32],
+                buffer: acf.as_bytes(),
+            },
+        );
+        ctx.set_output_tensor(
+            "period",
+            TensorParam {
+                element_type: ElementType::U32,
+                dimensions: &[1],
+                buffer: [period].as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "signal".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+/// Calculate the normalized autocorrelation of `signal` for every lag from 0
+/// to `max_lag` (inclusive).
+///
+/// A constant signal has no variance to normalize by, so it is treated as
+/// being uncorrelated with itself at every non-zero lag.
+///
+/// # Panics
+///
+/// This will panic if `max_lag` isn't less than `signal.len()`.
+pub fn autocorrelation(signal: &[f32], max_lag: usize) -> Vec<f32> {
+    assert!(max_lag < signal.len());
+
+    let mean = signal.iter().map(|&x| f64::from(x)).sum::<f64>()
+        / signal.len() as f64;
+    let centered: Vec<f64> =
+        signal.iter().map(|&x| f64::from(x) - mean).collect();
+    let variance: f64 = centered.iter().map(|x| x * x).sum();
+
+    (0..=max_lag)
+        .map(|lag| {
+            if variance == 0.0 {
+                return if lag == 0 { 1.0 } else { 0.0 };
+            }
+
+            let covariance: f64 = centered
+                .iter()
+                .zip(&centered[lag..])
+                .map(|(a, b)| a * b)
+                .sum();
+
+            (covariance / variance) as f32
+        })
+        .collect()
+}
+
+/// Find the lag of the highest positive peak in an autocorrelation curve.
+///
+/// Every signal is perfectly correlated with itself at lag 0 and neighbouring
+/// samples are usually similar, so the initial descent is skipped and only
+/// peaks after the first local minimum are considered. Peaks must have a
+/// neighbour on both sides, so a curve which is still rising at `max_lag`
+/// won't report `max_lag` as the period.
+pub fn dominant_period(acf: &[f32]) -> Option<usize> {
+    let first_minimum = acf.windows(2).position(|w| w[1] >= w[0])?;
+
+    (first_minimum + 1..acf.len().saturating_sub(1))
+        .filter(|&lag| {
+            acf[lag] > 0.0
+                && acf[lag] >= acf[lag - 1]
+                && acf[lag] > acf[lag + 1]
+        })
+        .max_by(|&a, &b| {
+            acf[a]
+                .partial_cmp(&acf[b])
+                .unwrap_or(Ordering::Equal)
+                .then(b.cmp(&a))
+        })
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn sine(period: f32, length: usize) -> Vec<f32> {
+        (0..length)
+            .map(|t| (2.0 * PI * t as f32 / period).sin())
+            .collect()
+    }
+
+    #[test]
+    fn lag_zero_is_always_one() {
+        let signal = [3.0, -1.0, 4.0, 1.0, -5.0];
+
+        let acf = autocorrelation(&signal, 4);
+
+        assert_eq!(acf.len(), 5);
+        assert!((acf[0] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn known_autocorrelation() {
+        // mean is 2.5 so the centered signal is [-1.5, -0.5, 0.5, 1.5] and
+        // the variance sum is 5
+        let signal = [1.0, 2.0, 3.0, 4.0];
+
+        let acf = autocorrelation(&signal, 3);
+
+        let expected = [1.0, 0.25, -0.3, -0.45];
+        for (got, want) in acf.iter().zip(expected) {
+            assert!((got - want).abs() < 1e-6, "{:?}", acf);
+        }
+    }
+
+    #[test]
+    fn constant_signals_are_uncorrelated() {
+        let acf = autocorrelation(&[2.0; 8], 3);
+
+        assert_eq!(acf, vec![1.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn find_the_period_of_a_sine_wave() {
+        let signal = sine(25.0, 200);
+
+        let acf = autocorrelation(&signal, 60);
+
+        assert_eq!(dominant_period(&acf), Some(25));
+    }
+
+    #[test]
+    fn the_strongest_peak_wins() {
+        // A weak fast oscillation on top of a strong slow one
+        let signal: Vec<f32> = sine(30.0, 300)
+            .into_iter()
+            .zip(sine(5.0, 300))
+            .map(|(slow, fast)| slow + 0.2 * fast)
+            .collect();
+
+        let acf = autocorrelation(&signal, 45);
+
+        assert_eq!(dominant_period(&acf), Some(30));
+    }
+
+    #[test]
+    fn monotonic_signals_have_no_period() {
+        let signal: Vec<f32> = (0..50).map(|x| x as f32).collect();
+
+        let acf = autocorrelation(&signal, 20);
+
+        assert_eq!(dominant_period(&acf), None);
+    }
+}
@@ -95,7 +95,13 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             ElementType::F32 =>{
                 buffer.view::<f32>(&dimensions)
                 .map_err(|e| KernelError::InvalidInput(InvalidInput{ name: "bounding_boxes".to_string(), reason: BadInputReason::InvalidValue(e.to_string()) }))?;
-                transform(buffer.elements(), threshold)
+                let scores = buffer.try_elements().map_err(|e| {
+                    KernelError::InvalidInput(InvalidInput {
+                        name: "input".to_string(),
+                        reason: BadInputReason::InvalidValue(e.to_string()),
+                    })
+                })?;
+                transform(scores, threshold)
             }
             other => {
                 return Err(KernelError::Other(format!(
@@ -0,0 +1,460 @@
+//! Convert between interleaved stereo (`[samples, 2]`) and mono (`[samples]`)
+//! audio.
+//!
+//! Samples are converted to `f32` while mixing, so `i16` audio is rounded and
+//! clamped to the `i16` range when the gain would make it overflow.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::{Array1, Array2, ArrayView1, ArrayView2},
+    prelude::*,
+    runtime_v1::*,
+    BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// The sample types this proc-block can mix.
+const SUPPORTED_ELEMENT_TYPES: &[&str] = &["i16", "f32"];
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Channel Mixer", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("audio");
+
+        let mode = ArgumentMetadata::new("mode");
+        mode.set_description(
+            "How to convert the audio. \"average\" and \"select\" turn stereo into mono, while \"duplicate\" copies mono audio to both channels.",
+        );
+        mode.set_default_value("average");
+        mode.add_hint(&interpret_as_string_in_enum(&[
+            "average",
+            "select",
+            "duplicate",
+        ]));
+        metadata.add_argument(&mode);
+
+        let channel = ArgumentMetadata::new("channel");
+        channel.set_description(
+            "The channel to keep when using the \"select\" mode (0 for left, 1 for right).",
+        );
+        channel.set_default_value("0");
+        channel.add_hint(&interpret_as_string_in_enum(&["0", "1"]));
+        metadata.add_argument(&channel);
+
+        let gains = ArgumentMetadata::new("gains");
+        gains.set_description(
+            "The gain for the left and right channels (e.g. \"1.0, 0.5\"). Gains are applied to the stereo side of the conversion.",
+        );
+        gains.set_default_value("1.0, 1.0");
+        gains.add_hint(&supported_argument_type(ArgumentType::String));
+        metadata.add_argument(&gains);
+
+        let element_type = ArgumentMetadata::new("element_type");
+        element_type.set_description("The type used for audio samples.");
+        element_type.set_default_value("i16");
+        element_type
+            .add_hint(&interpret_as_string_in_enum(SUPPORTED_ELEMENT_TYPES));
+        metadata.add_argument(&element_type);
+
+        let input = TensorMetadata::new("input");
+        input.set_description(
+            "Interleaved `[samples, 2]` stereo audio, or `[samples]` mono audio when using the \"duplicate\" mode.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::I16, ElementType::F32],
+            DimensionsParam::Dynamic,
+        );
+        input.add_hint(&hint);
+        metadata.add_input(&input);
+
+        let output = TensorMetadata::new("output");
+        output.set_description("The converted audio.");
+        let hint = supported_shapes(
+            &[ElementType::I16, ElementType::F32],
+            DimensionsParam::Dynamic,
+        );
+        output.add_hint(&hint);
+        metadata.add_output(&output);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        let mixer = Mixer::from_context::<_, GraphError>(&ctx)?;
+        let element_type = ctx.element_type::<GraphError>(
+            ElementType::I16,
+            SUPPORTED_ELEMENT_TYPES,
+        )?;
+
+        let (input, output): (&[u32], &[u32]) = match mixer.mode {
+            Mode::Average | Mode::Select(_) => (&[0, 2], &[0]),
+            Mode::Duplicate => (&[0], &[0, 2]),
+        };
+
+        ctx.add_input_tensor(
+            "input",
+            element_type,
+            DimensionsParam::Fixed(input),
+        );
+        ctx.add_output_tensor(
+            "output",
+            element_type,
+            DimensionsParam::Fixed(output),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let mixer = Mixer::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("input").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "input".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        let samples: Vec<f32> = match element_type {
+            ElementType::I16 => buffer
+                .try_elements::<i16>()
+                .map_err(invalid_input)?
+                .iter()
+                .map(|&s| f32::from(s))
+                .collect(),
+            ElementType::F32 => buffer
+                .try_elements::<f32>()
+                .map_err(invalid_input)?
+                .to_vec(),
+            other => {
+                return Err(invalid_input(format!(
+                    "Expected i16 or f32 samples, found {}",
+                    other
+                )))
+            },
+        };
+
+        let (samples, dimensions) = mixer.apply(&samples, &dimensions)?;
+
+        let buffer = match element_type {
+            ElementType::I16 => samples
+                .iter()
+                .map(|&s| s.round().clamp(i16::MIN.into(), i16::MAX.into()))
+                .map(|s| s as i16)
+                .collect::<Vec<_>>()
+                .as_bytes()
+                .to_vec(),
+            _ => samples.as_bytes().to_vec(),
+        };
+
+        ctx.set_output_tensor(
+            "output",
+            TensorParam {
+                element_type,
+                dimensions: &dimensions,
+                buffer: &buffer,
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "input".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Mode {
+    /// Take the mean of both channels.
+    Average,
+    /// Only keep one channel.
+    Select(usize),
+    /// Copy mono audio to both channels.
+    Duplicate,
+}
+
+/// The gain applied to the left and right channels.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Gains([f32; 2]);
+
+impl Default for Gains {
+    fn default() -> Self { Gains([1.0, 1.0]) }
+}
+
+impl FromStr for Gains {
+    type Err = InvalidGains;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let gains = s
+            .split(',')
+            .map(|g| g.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| InvalidGains(s.to_string()))?;
+
+        match *gains {
+            [left, right] => Ok(Gains([left, right])),
+            _ => Err(InvalidGains(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct InvalidGains(String);
+
+impl Display for InvalidGains {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Expected a gain for the left and right channels (e.g. \"1.0, 0.5\"), found \"{}\"",
+            self.0
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Mixer {
+    mode: Mode,
+    gains: Gains,
+}
+
+impl Mixer {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let mode = ctx.parse_argument_with_default::<_, E>(
+            "mode",
+            String::from("average"),
+        )?;
+        let channel: usize =
+            ctx.parse_argument_with_default::<_, E>("channel", 0)?;
+        let gains: Gains =
+            ctx.parse_argument_with_default::<_, E>("gains", Gains::default())?;
+
+        let mode = match mode.as_str() {
+            "average" => Mode::Average,
+            "select" if channel < 2 => Mode::Select(channel),
+            "select" => {
+                return Err(E::invalid_argument(
+                    InvalidArgument::invalid_value(
+                        "channel",
+                        format!("Expected 0 or 1, found {}", channel),
+                    ),
+                ))
+            },
+            "duplicate" => Mode::Duplicate,
+            other => {
+                return Err(E::invalid_argument(
+                    InvalidArgument::invalid_value(
+                        "mode",
+                        format!("Unknown mode, \"{}\"", other),
+                    ),
+                ))
+            },
+        };
+
+        Ok(Mixer { mode, gains })
+    }
+
+    /// Convert the samples, returning the new samples and their dimensions.
+    fn apply(
+        &self,
+        samples: &[f32],
+        dimensions: &[u32],
+    ) -> Result<(Vec<f32>, Vec<u32>), KernelError> {
+        match (self.mode, dimensions) {
+            (Mode::Average | Mode::Select(_), &[frames, 2]) => {
+                let stereo =
+                    ArrayView2::from_shape((frames as usize, 2), samples)
+                        .map_err(invalid_input)?;
+
+                let mono = self.downmix(stereo);
+                let dimensions = vec![mono.len() as u32];
+                Ok((mono.to_vec(), dimensions))
+            },
+            (Mode::Average | Mode::Select(_), _) => Err(invalid_input(format!(
+                "Expected [samples, 2] stereo audio, found {:?}",
+                dimensions
+            ))),
+            (Mode::Duplicate, &[_]) => {
+                let stereo = self.upmix(ArrayView1::from(samples));
+                let dimensions = vec![stereo.nrows() as u32, 2];
+                Ok((stereo.into_raw_vec(), dimensions))
+            },
+            (Mode::Duplicate, _) => Err(invalid_input(format!(
+                "Expected [samples] mono audio, found {:?}",
+                dimensions
+            ))),
+        }
+    }
+
+    fn downmix(&self, stereo: ArrayView2<'_, f32>) -> Array1<f32> {
+        let Gains([left_gain, right_gain]) = self.gains;
+
+        stereo
+            .outer_iter()
+            .map(|frame| {
+                let left = frame[0] * left_gain;
+                let right = frame[1] * right_gain;
+
+                match self.mode {
+                    Mode::Select(0) => left,
+                    Mode::Select(_) => right,
+                    _ => (left + right) / 2.0,
+                }
+            })
+            .collect()
+    }
+
+    fn upmix(&self, mono: ArrayView1<'_, f32>) -> Array2<f32> {
+        let Gains([left_gain, right_gain]) = self.gains;
+        let mut stereo = Array2::zeros((mono.len(), 2));
+
+        for (mut frame, &sample) in stereo.outer_iter_mut().zip(mono) {
+            frame[0] = sample * left_gain;
+            frame[1] = sample * right_gain;
+        }
+
+        stereo
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEREO: [f32; 6] = [1.0, 3.0, -2.0, 2.0, 0.5, 0.5];
+
+    fn mixer(mode: Mode, gains: [f32; 2]) -> Mixer {
+        Mixer {
+            mode,
+            gains: Gains(gains),
+        }
+    }
+
+    #[test]
+    fn average_both_channels() {
+        let got = mixer(Mode::Average, [1.0, 1.0])
+            .apply(&STEREO, &[3, 2])
+            .unwrap();
+
+        assert_eq!(got, (vec![2.0, 0.0, 0.5], vec![3]));
+    }
+
+    #[test]
+    fn select_a_channel() {
+        let left = mixer(Mode::Select(0), [1.0, 1.0])
+            .apply(&STEREO, &[3, 2])
+            .unwrap();
+        let right = mixer(Mode::Select(1), [1.0, 1.0])
+            .apply(&STEREO, &[3, 2])
+            .unwrap();
+
+        assert_eq!(left.0, vec![1.0, -2.0, 0.5]);
+        assert_eq!(right.0, vec![3.0, 2.0, 0.5]);
+    }
+
+    #[test]
+    fn gains_are_applied_before_mixing() {
+        let got = mixer(Mode::Average, [2.0, 0.0])
+            .apply(&STEREO, &[3, 2])
+            .unwrap();
+
+        assert_eq!(got.0, vec![1.0, -2.0, 0.5]);
+    }
+
+    #[test]
+    fn duplicate_mono_to_stereo() {
+        let got = mixer(Mode::Duplicate, [1.0, 0.5])
+            .apply(&[1.0, -4.0], &[2])
+            .unwrap();
+
+        assert_eq!(got, (vec![1.0, 0.5, -4.0, -2.0], vec![2, 2]));
+    }
+
+    #[test]
+    fn stereo_audio_needs_two_channels() {
+        let err = mixer(Mode::Average, [1.0, 1.0])
+            .apply(&STEREO, &[2, 3])
+            .unwrap_err();
+
+        assert!(matches!(err, KernelError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn parse_gains() {
+        assert_eq!("1.0, 0.5".parse(), Ok(Gains([1.0, 0.5])));
+        assert_eq!("2,2".parse(), Ok(Gains([2.0, 2.0])));
+        assert!("1.0".parse::<Gains>().is_err());
+        assert!("1.0, 2.0, 3.0".parse::<Gains>().is_err());
+        assert!("left, right".parse::<Gains>().is_err());
+    }
+}
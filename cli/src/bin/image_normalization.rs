@@ -0,0 +1,24 @@
+use proc_blocks_cli::{Elements, Error, Tensor};
+
+fn main() {
+    proc_blocks_cli::run(|_, inputs| {
+        let image = proc_blocks_cli::single_input(inputs)?;
+
+        let pixels = match &image.elements {
+            Elements::U8(pixels) => pixels,
+            _ => return Err(Error::new("Expected a u8 image")),
+        };
+
+        let dimensions: Vec<u32> =
+            image.dimensions.iter().map(|&d| d as u32).collect();
+        image_normalization_core::check_input_dimensions(&dimensions)
+            .map_err(Error::new)?;
+
+        let normalized = pixels
+            .iter()
+            .map(|&p| image_normalization_core::normalize(p))
+            .collect();
+
+        Ok(vec![Tensor::new(image.dimensions, Elements::F32(normalized))])
+    })
+}
@@ -0,0 +1,12 @@
+use proc_blocks_cli::{Elements, Error, Tensor};
+
+fn main() {
+    proc_blocks_cli::run(|_, inputs| {
+        let input = proc_blocks_cli::single_input(inputs)?;
+
+        let output = normalize_core::transform(&input.elements.to_f32())
+            .ok_or_else(|| Error::new("The input tensor was empty"))?;
+
+        Ok(vec![Tensor::new(input.dimensions, Elements::F32(output))])
+    })
+}
@@ -0,0 +1,22 @@
+use hotg_rune_proc_blocks::ndarray::ArrayView3;
+use proc_blocks_cli::{Elements, Error, Tensor};
+
+fn main() {
+    proc_blocks_cli::run(|args, inputs| {
+        let threshold: f32 = args.get_or("threshold", 0.7)?;
+        let input = proc_blocks_cli::single_input(inputs)?;
+
+        let shape = match *input.dimensions {
+            [a, b, c] => (a, b, c),
+            _ => return Err(Error::new("Expected a 3D tensor")),
+        };
+        let elements = input.elements.to_f32();
+        let bounding_boxes = ArrayView3::from_shape(shape, &elements)
+            .map_err(|e| Error::new(e.to_string()))?;
+
+        let output = object_filter_core::transform(bounding_boxes, threshold);
+        let rows = output.len() / 6;
+
+        Ok(vec![Tensor::new(vec![rows, 6], Elements::F32(output))])
+    })
+}
@@ -0,0 +1,19 @@
+use hotg_rune_proc_blocks::ndarray::Array1;
+use proc_blocks_cli::{Elements, Error, Tensor};
+
+fn main() {
+    proc_blocks_cli::run(|_, inputs| {
+        let input = proc_blocks_cli::single_input(inputs)?;
+
+        let mut output = match input.elements {
+            Elements::F32(e) => Array1::from_vec(e),
+            _ => return Err(Error::new("Softmax only accepts f32 tensors")),
+        };
+        softmax_core::softmax(output.view_mut());
+
+        Ok(vec![Tensor::new(
+            input.dimensions,
+            Elements::F32(output.to_vec()),
+        )])
+    })
+}
@@ -0,0 +1,281 @@
+//! Command-line wrappers which let you run a single proc-block from the
+//! terminal.
+//!
+//! Each binary reads its input tensors from `*.npy` or `*.json` files, runs
+//! the proc-block, and writes the outputs back out. When compiled to
+//! `wasm32-wasi` (see `cargo xtask wasi`), this lets you test a proc-block
+//! with something like
+//!
+//! ```console
+//! $ wasmtime run --dir=. softmax.wasm -- --input x.npy --output y.npy
+//! ```
+
+mod npy;
+
+use std::{
+    collections::HashMap, convert::TryFrom, fmt::Display, path::PathBuf,
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+pub use crate::npy::{read_npy, write_npy};
+
+/// A tensor's elements.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "element_type", content = "elements", rename_all = "lowercase")]
+pub enum Elements {
+    U8(Vec<u8>),
+    I8(Vec<i8>),
+    U16(Vec<u16>),
+    I16(Vec<i16>),
+    U32(Vec<u32>),
+    I32(Vec<i32>),
+    F32(Vec<f32>),
+    U64(Vec<u64>),
+    I64(Vec<i64>),
+    F64(Vec<f64>),
+}
+
+impl Elements {
+    pub fn len(&self) -> usize {
+        match self {
+            Elements::U8(e) => e.len(),
+            Elements::I8(e) => e.len(),
+            Elements::U16(e) => e.len(),
+            Elements::I16(e) => e.len(),
+            Elements::U32(e) => e.len(),
+            Elements::I32(e) => e.len(),
+            Elements::F32(e) => e.len(),
+            Elements::U64(e) => e.len(),
+            Elements::I64(e) => e.len(),
+            Elements::F64(e) => e.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Convert every element to a `f32`.
+    pub fn to_f32(&self) -> Vec<f32> {
+        match self {
+            Elements::U8(e) => e.iter().map(|&v| v as f32).collect(),
+            Elements::I8(e) => e.iter().map(|&v| v as f32).collect(),
+            Elements::U16(e) => e.iter().map(|&v| v as f32).collect(),
+            Elements::I16(e) => e.iter().map(|&v| v as f32).collect(),
+            Elements::U32(e) => e.iter().map(|&v| v as f32).collect(),
+            Elements::I32(e) => e.iter().map(|&v| v as f32).collect(),
+            Elements::F32(e) => e.clone(),
+            Elements::U64(e) => e.iter().map(|&v| v as f32).collect(),
+            Elements::I64(e) => e.iter().map(|&v| v as f32).collect(),
+            Elements::F64(e) => e.iter().map(|&v| v as f32).collect(),
+        }
+    }
+}
+
+/// A tensor read from (or written to) disk.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tensor {
+    pub dimensions: Vec<usize>,
+    #[serde(flatten)]
+    pub elements: Elements,
+}
+
+impl Tensor {
+    pub fn new(dimensions: Vec<usize>, elements: Elements) -> Self {
+        Tensor {
+            dimensions,
+            elements,
+        }
+    }
+
+    /// Load a tensor from a `*.npy` or `*.json` file.
+    pub fn load(path: &std::path::Path) -> Result<Self, Error> {
+        let bytes = std::fs::read(path)
+            .map_err(|e| Error::new(format!("{}: {}", path.display(), e)))?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_slice(&bytes)
+                .map_err(|e| Error::new(format!("{}: {}", path.display(), e))),
+            _ => read_npy(&bytes)
+                .map_err(|e| Error::new(format!("{}: {}", path.display(), e))),
+        }
+    }
+
+    /// Save a tensor as a `*.npy` or `*.json` file, depending on the
+    /// extension.
+    pub fn save(&self, path: &std::path::Path) -> Result<(), Error> {
+        let bytes = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::to_vec_pretty(self)
+                .map_err(|e| Error::new(e.to_string()))?,
+            _ => write_npy(self),
+        };
+
+        std::fs::write(path, bytes)
+            .map_err(|e| Error::new(format!("{}: {}", path.display(), e)))
+    }
+}
+
+/// Something went wrong while running a proc-block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error(String);
+
+impl Error {
+    pub fn new(msg: impl Into<String>) -> Self { Error(msg.into()) }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The command-line arguments passed to a proc-block.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Args {
+    pub inputs: Vec<PathBuf>,
+    pub outputs: Vec<PathBuf>,
+    pub arguments: HashMap<String, String>,
+}
+
+impl Args {
+    pub const USAGE: &'static str = "Usage: <proc-block> --input <file>... \
+                                     [--output <file>...] [--arg key=value...]";
+
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Self, Error> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(flag) = args.next() {
+            let mut value = || {
+                args.next().ok_or_else(|| {
+                    Error::new(format!("\"{}\" requires a value", flag))
+                })
+            };
+
+            match flag.as_str() {
+                "-i" | "--input" => parsed.inputs.push(value()?.into()),
+                "-o" | "--output" => parsed.outputs.push(value()?.into()),
+                "-a" | "--arg" => {
+                    let arg = value()?;
+                    let (key, value) = arg.split_once('=').ok_or_else(|| {
+                        Error::new(format!("Expected key=value, found {}", arg))
+                    })?;
+                    parsed.arguments.insert(key.to_string(), value.to_string());
+                },
+                other => {
+                    return Err(Error::new(format!(
+                        "Unknown argument, \"{}\"",
+                        other
+                    )))
+                },
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Parse an argument, falling back to a default if it wasn't provided.
+    pub fn get_or<T>(&self, name: &str, default: T) -> Result<T, Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.arguments.get(name) {
+            Some(value) => value
+                .parse()
+                .map_err(|e| Error::new(format!("Invalid {}: {}", name, e))),
+            None => Ok(default),
+        }
+    }
+}
+
+/// Run a proc-block as a command-line program.
+///
+/// Outputs are written to the `--output` paths in order. Any outputs without
+/// a path are printed to stdout as JSON.
+pub fn run(
+    proc_block: impl FnOnce(&Args, Vec<Tensor>) -> Result<Vec<Tensor>, Error>,
+) -> ! {
+    match try_run(proc_block) {
+        Ok(()) => std::process::exit(0),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            eprintln!("{}", Args::USAGE);
+            std::process::exit(1);
+        },
+    }
+}
+
+fn try_run(
+    proc_block: impl FnOnce(&Args, Vec<Tensor>) -> Result<Vec<Tensor>, Error>,
+) -> Result<(), Error> {
+    let args = Args::parse(std::env::args().skip(1))?;
+
+    let inputs = args
+        .inputs
+        .iter()
+        .map(|path| Tensor::load(path))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let outputs = proc_block(&args, inputs)?;
+
+    for (i, output) in outputs.iter().enumerate() {
+        match args.outputs.get(i) {
+            Some(path) => output.save(path)?,
+            None => {
+                let json = serde_json::to_string(output)
+                    .map_err(|e| Error::new(e.to_string()))?;
+                println!("{}", json);
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Make sure the proc-block was given exactly one input.
+pub fn single_input(inputs: Vec<Tensor>) -> Result<Tensor, Error> {
+    let count = inputs.len();
+
+    match <[Tensor; 1]>::try_from(inputs) {
+        Ok([input]) => Ok(input),
+        Err(_) => Err(Error::new(format!(
+            "Expected 1 input tensor, found {}",
+            count
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_command_line_arguments() {
+        let args = ["--input", "x.npy", "-o", "y.json", "--arg", "a=1"];
+
+        let got = Args::parse(args.iter().map(|s| s.to_string())).unwrap();
+
+        assert_eq!(got.inputs, vec![PathBuf::from("x.npy")]);
+        assert_eq!(got.outputs, vec![PathBuf::from("y.json")]);
+        assert_eq!(got.arguments["a"], "1");
+    }
+
+    #[test]
+    fn tensors_round_trip_through_json() {
+        let tensor = Tensor::new(vec![2], Elements::F32(vec![1.0, 2.0]));
+
+        let json = serde_json::to_string(&tensor).unwrap();
+        let round_tripped: Tensor = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(round_tripped, tensor);
+        assert_eq!(
+            json,
+            r#"{"dimensions":[2],"element_type":"f32","elements":[1.0,2.0]}"#
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! A minimal reader and writer for NumPy's [`*.npy` format][npy].
+//!
+//! Only little-endian, C-ordered arrays of numbers are supported.
+//!
+//! [npy]: https://numpy.org/doc/stable/reference/generated/numpy.lib.format.html
+
+use crate::{Elements, Error, Tensor};
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Parse the contents of a `*.npy` file.
+pub fn read_npy(bytes: &[u8]) -> Result<Tensor, Error> {
+    let rest = bytes
+        .strip_prefix(MAGIC)
+        .ok_or_else(|| Error::new("Not a *.npy file"))?;
+
+    let (header_len, rest) = match *rest {
+        [1, _, a, b, ref rest @ ..] => {
+            (u16::from_le_bytes([a, b]) as usize, rest)
+        },
+        [2 | 3, _, a, b, c, d, ref rest @ ..] => {
+            (u32::from_le_bytes([a, b, c, d]) as usize, rest)
+        },
+        _ => return Err(Error::new("Unsupported *.npy version")),
+    };
+
+    if rest.len() < header_len {
+        return Err(Error::new("The *.npy header is truncated"));
+    }
+    let (header, data) = rest.split_at(header_len);
+    let header = std::str::from_utf8(header)
+        .map_err(|e| Error::new(format!("Invalid header: {}", e)))?;
+
+    let descr = header_value(header, "descr")
+        .ok_or_else(|| Error::new("The header doesn't specify a dtype"))?;
+    let descr = descr.trim_matches(|c| c == '\'' || c == '"');
+
+    if header_value(header, "fortran_order") == Some("True") {
+        return Err(Error::new("Fortran-ordered arrays aren't supported"));
+    }
+
+    let dimensions = parse_shape(header)?;
+    let elements = parse_elements(descr, data)?;
+
+    let expected: usize = dimensions.iter().product();
+    if elements.len() != expected {
+        return Err(Error::new(format!(
+            "Expected {} elements for a shape of {:?}, found {}",
+            expected,
+            dimensions,
+            elements.len()
+        )));
+    }
+
+    Ok(Tensor::new(dimensions, elements))
+}
+
+/// Serialize a tensor using the `*.npy` format.
+pub fn write_npy(tensor: &Tensor) -> Vec<u8> {
+    let (descr, data) = match &tensor.elements {
+        Elements::U8(e) => ("|u1", e.clone()),
+        Elements::I8(e) => ("|i1", to_bytes(e, |v| v.to_le_bytes())),
+        Elements::U16(e) => ("<u2", to_bytes(e, |v| v.to_le_bytes())),
+        Elements::I16(e) => ("<i2", to_bytes(e, |v| v.to_le_bytes())),
+        Elements::U32(e) => ("<u4", to_bytes(e, |v| v.to_le_bytes())),
+        Elements::I32(e) => ("<i4", to_bytes(e, |v| v.to_le_bytes())),
+        Elements::F32(e) => ("<f4", to_bytes(e, |v| v.to_le_bytes())),
+        Elements::U64(e) => ("<u8", to_bytes(e, |v| v.to_le_bytes())),
+        Elements::I64(e) => ("<i8", to_bytes(e, |v| v.to_le_bytes())),
+        Elements::F64(e) => ("<f8", to_bytes(e, |v| v.to_le_bytes())),
+    };
+
+    let shape = match tensor.dimensions.as_slice() {
+        [single] => format!("({},)", single),
+        dims => {
+            let dims: Vec<String> =
+                dims.iter().map(|d| d.to_string()).collect();
+            format!("({})", dims.join(", "))
+        },
+    };
+
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr, shape
+    );
+
+    // The header is padded with spaces so the data is 64-byte aligned
+    let unpadded = MAGIC.len() + 4 + header.len() + 1;
+    let padding = (64 - unpadded % 64) % 64;
+    header.extend(std::iter::repeat(' ').take(padding));
+    header.push('\n');
+
+    let mut bytes = Vec::with_capacity(unpadded + padding + data.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&[1, 0]);
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    bytes.extend_from_slice(&data);
+
+    bytes
+}
+
+fn to_bytes<T: Copy, const N: usize>(
+    elements: &[T],
+    to_le_bytes: impl Fn(T) -> [u8; N],
+) -> Vec<u8> {
+    elements.iter().flat_map(|&e| to_le_bytes(e)).collect()
+}
+
+/// Find the (unparsed) value for a key in the header's Python dict.
+fn header_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let start = header
+        .find(&format!("'{}'", key))
+        .or_else(|| header.find(&format!("\"{}\"", key)))?;
+    let rest = &header[start + key.len() + 2..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+
+    let end = if rest.starts_with('(') {
+        rest.find(')')? + 1
+    } else {
+        rest.find(',')?
+    };
+
+    Some(rest[..end].trim())
+}
+
+fn parse_shape(header: &str) -> Result<Vec<usize>, Error> {
+    let shape = header_value(header, "shape")
+        .ok_or_else(|| Error::new("The header doesn't specify a shape"))?;
+
+    shape
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .split(',')
+        .map(|d| d.trim())
+        .filter(|d| !d.is_empty())
+        .map(|d| {
+            d.parse()
+                .map_err(|e| Error::new(format!("Invalid dimension: {}", e)))
+        })
+        .collect()
+}
+
+fn parse_elements(descr: &str, data: &[u8]) -> Result<Elements, Error> {
+    macro_rules! parse {
+        ($variant:ident, $ty:ty) => {{
+            const SIZE: usize = std::mem::size_of::<$ty>();
+            let chunks = data.chunks_exact(SIZE);
+
+            if !chunks.remainder().is_empty() {
+                return Err(Error::new(
+                    "The data isn't a multiple of the element size",
+                ));
+            }
+
+            Elements::$variant(
+                chunks
+                    .map(|chunk| {
+                        let mut bytes = [0; SIZE];
+                        bytes.copy_from_slice(chunk);
+                        <$ty>::from_le_bytes(bytes)
+                    })
+                    .collect(),
+            )
+        }};
+    }
+
+    let elements = match descr {
+        "|u1" | "<u1" => Elements::U8(data.to_vec()),
+        "|i1" | "<i1" => parse!(I8, i8),
+        "<u2" => parse!(U16, u16),
+        "<i2" => parse!(I16, i16),
+        "<u4" => parse!(U32, u32),
+        "<i4" => parse!(I32, i32),
+        "<f4" => parse!(F32, f32),
+        "<u8" => parse!(U64, u64),
+        "<i8" => parse!(I64, i64),
+        "<f8" => parse!(F64, f64),
+        other => {
+            return Err(Error::new(format!("Unsupported dtype, \"{}\"", other)))
+        },
+    };
+
+    Ok(elements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let tensor = Tensor::new(
+            vec![2, 3],
+            Elements::F32(vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]),
+        );
+
+        let bytes = write_npy(&tensor);
+        let got = read_npy(&bytes).unwrap();
+
+        assert_eq!(got, tensor);
+        assert_eq!((bytes.len() - 6 * 4) % 64, 0);
+    }
+
+    #[test]
+    fn read_one_dimensional_header() {
+        let header =
+            "{'descr': '<i4', 'fortran_order': False, 'shape': (3,), }";
+
+        assert_eq!(parse_shape(header).unwrap(), vec![3]);
+        assert_eq!(header_value(header, "descr"), Some("'<i4'"));
+    }
+}
@@ -0,0 +1,398 @@
+//! Cepstral Mean and Variance Normalization (CMVN).
+//!
+//! Speech models are usually trained on features (e.g. MFCCs) that have had
+//! the per-coefficient mean subtracted and been scaled to unit variance, which
+//! removes the effect of things like the microphone and room acoustics.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::{Array2, ArrayView2, Axis},
+    prelude::*,
+    runtime_v1::*,
+    BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// Used to avoid dividing by zero when a coefficient is constant.
+const MIN_STANDARD_DEVIATION: f64 = 1e-10;
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata = Metadata::new("CMVN", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("audio");
+        metadata.add_tag("speech");
+        metadata.add_tag("normalization");
+
+        let window = ArgumentMetadata::new("window");
+        window.set_description(
+            "The number of frames used when calculating statistics for each frame. Use 0 to normalize over the whole utterance.",
+        );
+        window.set_default_value("0");
+        window
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&window);
+
+        let normalize_variance = ArgumentMetadata::new("normalize_variance");
+        normalize_variance.set_description(
+            "Scale each coefficient to unit variance as well as subtracting the mean.",
+        );
+        normalize_variance.set_default_value("true");
+        normalize_variance
+            .add_hint(&interpret_as_string_in_enum(&["true", "false"]));
+        metadata.add_argument(&normalize_variance);
+
+        let features = TensorMetadata::new("features");
+        features.set_description(
+            "A `[frames, coefficients]` tensor of features (e.g. MFCCs).",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        features.add_hint(&hint);
+        metadata.add_input(&features);
+
+        let normalized = TensorMetadata::new("normalized");
+        normalized.set_description("The normalized features.");
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        normalized.add_hint(&hint);
+        metadata.add_output(&normalized);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Cmvn::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "features",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "normalized",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let cmvn = Cmvn::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("features").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "features".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::F32 {
+            return Err(invalid_features(format!(
+                "Expected a f32 tensor, found {}",
+                element_type
+            )));
+        }
+
+        let features = buffer
+            .try_view::<f32, _>(&dimensions)
+            .map_err(invalid_features)?;
+
+        let normalized = cmvn.apply(features);
+
+        ctx.set_output_tensor(
+            "normalized",
+            TensorParam {
+                element_type: ElementType::F32,
+                dimensions: &dimensions,
+                buffer: normalized
+                    .as_slice()
+                    .expect("Freshly created arrays are contiguous")
+                    .as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_features(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "features".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Cmvn {
+    /// The number of frames to calculate statistics over, or `None` to use
+    /// the whole utterance.
+    window: Option<usize>,
+    normalize_variance: bool,
+}
+
+impl Cmvn {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let window: usize =
+            ctx.parse_argument_with_default::<_, E>("window", 0)?;
+        let normalize_variance: bool = ctx
+            .parse_argument_with_default::<_, E>("normalize_variance", true)?;
+
+        Ok(Cmvn {
+            window: if window == 0 { None } else { Some(window) },
+            normalize_variance,
+        })
+    }
+
+    fn apply(&self, features: ArrayView2<'_, f32>) -> Array2<f32> {
+        let frames = features.len_of(Axis(0));
+        let window = self.window.unwrap_or(frames).min(frames);
+
+        let sums = CumulativeSums::new(features);
+        let mut normalized = Array2::zeros(features.dim());
+
+        for (t, (frame, mut output)) in features
+            .outer_iter()
+            .zip(normalized.outer_iter_mut())
+            .enumerate()
+        {
+            let (start, end) = window_bounds(t, window, frames);
+
+            for (c, (&x, y)) in frame.iter().zip(output.iter_mut()).enumerate()
+            {
+                let (mean, variance) = sums.statistics(c, start, end);
+                let mut value = f64::from(x) - mean;
+
+                if self.normalize_variance {
+                    value /= variance.sqrt().max(MIN_STANDARD_DEVIATION);
+                }
+
+                *y = value as f32;
+            }
+        }
+
+        normalized
+    }
+}
+
+/// Get the `[start, end)` range of a `window`-frame window centred on frame
+/// `t`, shifting it at the edges so it always contains `window` frames.
+fn window_bounds(t: usize, window: usize, frames: usize) -> (usize, usize) {
+    let start = t.saturating_sub(window / 2);
+    let end = usize::min(start + window, frames);
+    let start = end.saturating_sub(window);
+
+    (start, end)
+}
+
+/// Running totals for each coefficient, letting us find the mean and variance
+/// of any range of frames in constant time.
+struct CumulativeSums {
+    /// `sums[[t, c]]` is the sum of coefficient `c` over frames `0..t`.
+    sums: Array2<f64>,
+    /// The same as `sums`, but for the squared values.
+    squares: Array2<f64>,
+}
+
+impl CumulativeSums {
+    fn new(features: ArrayView2<'_, f32>) -> Self {
+        let (frames, coefficients) = features.dim();
+        let mut sums = Array2::zeros((frames + 1, coefficients));
+        let mut squares = Array2::zeros((frames + 1, coefficients));
+
+        for (t, frame) in features.outer_iter().enumerate() {
+            for (c, &x) in frame.iter().enumerate() {
+                let x = f64::from(x);
+                sums[[t + 1, c]] = sums[[t, c]] + x;
+                squares[[t + 1, c]] = squares[[t, c]] + x * x;
+            }
+        }
+
+        CumulativeSums { sums, squares }
+    }
+
+    /// The mean and (population) variance of coefficient `c` over frames
+    /// `start..end`.
+    fn statistics(&self, c: usize, start: usize, end: usize) -> (f64, f64) {
+        let n = (end - start) as f64;
+        let mean = (self.sums[[end, c]] - self.sums[[start, c]]) / n;
+        let mean_of_squares =
+            (self.squares[[end, c]] - self.squares[[start, c]]) / n;
+        let variance = (mean_of_squares - mean * mean).max(0.0);
+
+        (mean, variance)
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::{
+        ndarray,
+        testing::{assert_close, assert_tensors_close, Tolerance},
+    };
+
+    const UTTERANCE: Cmvn = Cmvn {
+        window: None,
+        normalize_variance: true,
+    };
+
+    #[test]
+    fn whole_utterance_has_zero_mean_and_unit_variance() {
+        let features = ndarray::arr2(&[
+            [1.0_f32, 10.0],
+            [2.0, 20.0],
+            [3.0, 30.0],
+            [4.0, 40.0],
+        ]);
+
+        let normalized = UTTERANCE.apply(features.view());
+
+        for column in normalized.columns() {
+            let mean = column.mean().unwrap();
+            let variance = column.mapv(|x| (x - mean).powi(2)).mean().unwrap();
+            assert_close(mean, 0.0, Tolerance::absolute(1e-6));
+            assert_close(variance, 1.0, Tolerance::F32);
+        }
+    }
+
+    #[test]
+    fn mean_only() {
+        let cmvn = Cmvn {
+            normalize_variance: false,
+            ..UTTERANCE
+        };
+        let features = ndarray::arr2(&[[1.0_f32, 5.0], [3.0, 5.0]]);
+
+        let normalized = cmvn.apply(features.view());
+
+        let should_be = ndarray::arr2(&[[-1.0, 0.0], [1.0, 0.0]]);
+        assert_tensors_close(&normalized, &should_be, Tolerance::F32);
+    }
+
+    #[test]
+    fn constant_coefficients_become_zero() {
+        let features = ndarray::arr2(&[[7.0_f32], [7.0], [7.0]]);
+
+        let normalized = UTTERANCE.apply(features.view());
+
+        assert_tensors_close(
+            &normalized,
+            &ndarray::arr2(&[[0.0], [0.0], [0.0]]),
+            Tolerance::F32,
+        );
+    }
+
+    #[test]
+    fn sliding_window_only_uses_nearby_frames() {
+        let cmvn = Cmvn {
+            window: Some(2),
+            normalize_variance: false,
+        };
+        let features = ndarray::arr2(&[[0.0_f32], [2.0], [10.0], [20.0]]);
+
+        let normalized = cmvn.apply(features.view());
+
+        // Frame 0 uses frames 0..2, frame 1 uses 0..2, frame 2 uses 1..3,
+        // and frame 3 uses 2..4.
+        let should_be = ndarray::arr2(&[[-1.0], [1.0], [4.0], [5.0]]);
+        assert_tensors_close(&normalized, &should_be, Tolerance::F32);
+    }
+
+    #[test]
+    fn window_bounds_are_shifted_at_the_edges() {
+        assert_eq!(window_bounds(0, 3, 10), (0, 3));
+        assert_eq!(window_bounds(5, 3, 10), (4, 7));
+        assert_eq!(window_bounds(9, 3, 10), (7, 10));
+        assert_eq!(window_bounds(1, 5, 3), (0, 3));
+    }
+
+    #[test]
+    fn window_larger_than_the_utterance() {
+        let windowed = Cmvn {
+            window: Some(100),
+            ..UTTERANCE
+        };
+        let features = ndarray::arr2(&[[1.0_f32], [2.0], [6.0]]);
+
+        let got = windowed.apply(features.view());
+
+        let should_be = UTTERANCE.apply(features.view());
+        assert_tensors_close(&got, &should_be, Tolerance::F32);
+    }
+
+    #[test]
+    fn empty_features() {
+        let features = Array2::<f32>::zeros((0, 13));
+
+        let normalized = UTTERANCE.apply(features.view());
+
+        assert_eq!(normalized.dim(), (0, 13));
+    }
+}
@@ -0,0 +1,391 @@
+//! Template matching for 1-D signals using normalized cross-correlation.
+//!
+//! The template is slid along the signal and, at each offset, compared with
+//! the overlapping window using the Pearson correlation coefficient,
+//!
+//! ```text
+//! ncc[k] = Σ (s[k + i] - mean(s[k..k + m])) * (t[i] - mean(t))
+//!          / (|s[k..k + m] - mean(s[k..k + m])| * |t - mean(t)|)
+//! ```
+//!
+//! Each window is normalized separately, so the template matches regardless
+//! of the signal's local offset or gain. Only offsets where the template fits
+//! completely inside the signal are considered.
+//!
+//! The template can either be passed in as the `template` argument (useful
+//! for a fixed pattern like a wake gesture) or provided at runtime as a
+//! second input tensor (useful for aligning two streams).
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    prelude::*, runtime_v1::*, BufferExt, SliceExt,
+};
+use std::cmp::Ordering;
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Cross Correlate", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("time series");
+        metadata.add_tag("signal processing");
+        metadata.add_tag("alignment");
+
+        let template = ArgumentMetadata::new("template");
+        template.set_description(
+            "A comma-separated list of values to search for. When this isn't provided, the template is read from the `template` input tensor instead.",
+        );
+        template.add_hint(&supported_argument_type(ArgumentType::LongString));
+        metadata.add_argument(&template);
+
+        let signal = TensorMetadata::new("signal");
+        signal.set_description("The signal to search.");
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Fixed(&[0]));
+        signal.add_hint(&hint);
+        metadata.add_input(&signal);
+
+        let template = TensorMetadata::new("template");
+        template.set_description(
+            "The pattern to search for. This input is only used when the `template` argument isn't set, and it can't be longer than the signal.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Fixed(&[0]));
+        template.add_hint(&hint);
+        metadata.add_input(&template);
+
+        let correlation = TensorMetadata::new("correlation");
+        correlation.set_description(
+            "The normalized cross-correlation (between -1 and 1) for each offset where the template fits inside the signal.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Fixed(&[0]));
+        correlation.add_hint(&hint);
+        metadata.add_output(&correlation);
+
+        let offset = TensorMetadata::new("offset");
+        offset.set_description(
+            "The offset (in samples) where the template matches the signal best.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::U32], DimensionsParam::Fixed(&[1]));
+        offset.add_hint(&hint);
+        metadata.add_output(&offset);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        let template = template_argument::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "signal",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0]),
+        );
+        if template.is_none() {
+            ctx.add_input_tensor(
+                "template",
+                ElementType::F32,
+                DimensionsParam::Fixed(&[0]),
+            );
+        }
+        ctx.add_output_tensor(
+            "correlation",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0]),
+        );
+        ctx.add_output_tensor(
+            "offset",
+            ElementType::U32,
+            DimensionsParam::Fixed(&[1]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let template = match template_argument::<_, KernelError>(&ctx)? {
+            Some(template) => template,
+            None => get_input(&ctx, "template")?,
+        };
+        let signal = get_input(&ctx, "signal")?;
+
+        if template.len() > signal.len() {
+            return Err(invalid_input(
+                "template",
+                format!(
+                    "The template ({} samples) can't be longer than the signal ({} samples)",
+                    template.len(),
+                    signal.len()
+                ),
+            ));
+        }
+
+        let correlation = normalized_cross_correlation(&signal, &template);
+        let offset = best_offset(&correlation).unwrap_or(0) as u32;
+
+        ctx.set_output_tensor(
+            "correlation",
+            TensorParam {
+                element_type: ElementType::F32,
+                dimensions: &[correlation.len() as u32],
+                buffer: correlation.as_bytes(),
+            },
+        );
+        ctx.set_output_tensor(
+            "offset",
+            TensorParam {
+                element_type: ElementType::U32,
+                dimensions: &[1],
+                buffer: [offset].as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn get_input(ctx: &KernelContext, name: &str) -> Result<Vec<f32>, KernelError> {
+    let TensorResult {
+        element_type,
+        buffer,
+        ..
+    } = ctx.get_input_tensor(name).ok_or_else(|| {
+        KernelError::InvalidInput(InvalidInput {
+            name: name.to_string(),
+            reason: BadInputReason::NotFound,
+        })
+    })?;
+
+    if element_type != ElementType::F32 {
+        return Err(invalid_input(
+            name,
+            format!("Expected a f32 tensor, found {}", element_type),
+        ));
+    }
+
+    let values = buffer
+        .try_elements::<f32>()
+        .map_err(|e| invalid_input(name, e))?;
+
+    if values.is_empty() {
+        return Err(invalid_input(name, "The tensor is empty"));
+    }
+
+    Ok(values.to_vec())
+}
+
+fn invalid_input(name: &str, reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: name.to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+fn template_argument<C, E>(ctx: &C) -> Result<Option<Vec<f32>>, E>
+where
+    C: ContextExt,
+    E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+{
+    ctx._get_argument("template")
+        .map(|t| parse_template(&t))
+        .transpose()
+        .map_err(|e| {
+            E::invalid_argument(InvalidArgument::invalid_value("template", e))
+        })
+}
+
+fn parse_template(s: &str) -> Result<Vec<f32>, String> {
+    let template = s
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            value
+                .parse::<f32>()
+                .map_err(|e| format!("Unable to parse \"{}\": {}", value, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if template.is_empty() {
+        return Err("The template must contain at least one value".to_string());
+    }
+
+    Ok(template)
+}
+
+/// Calculate the normalized cross-correlation between `template` and every
+/// window of `signal` it completely overlaps with, giving
+/// `signal.len() - template.len() + 1` values.
+///
+/// A window (or template) with no variance doesn't correlate with anything,
+/// so it gets a score of `0.0`.
+///
+/// # Panics
+///
+/// This will panic if `template` is empty or longer than `signal`.
+pub fn normalized_cross_correlation(
+    signal: &[f32],
+    template: &[f32],
+) -> Vec<f32> {
+    assert!(!template.is_empty() && template.len() <= signal.len());
+
+    let (template, template_norm) = center(template);
+
+    signal
+        .windows(template.len())
+        .map(|window| {
+            let (window, window_norm) = center(window);
+            let denominator = window_norm * template_norm;
+
+            if denominator == 0.0 {
+                return 0.0;
+            }
+
+            let dot: f64 =
+                window.iter().zip(&template).map(|(a, b)| a * b).sum();
+            (dot / denominator) as f32
+        })
+        .collect()
+}
+
+/// Subtract the mean from each value, returning the centered values and
+/// their L2 norm.
+fn center(values: &[f32]) -> (Vec<f64>, f64) {
+    let mean = values.iter().map(|&x| f64::from(x)).sum::<f64>()
+        / values.len() as f64;
+    let centered: Vec<f64> =
+        values.iter().map(|&x| f64::from(x) - mean).collect();
+    let norm = centered.iter().map(|x| x * x).sum::<f64>().sqrt();
+
+    (centered, norm)
+}
+
+/// The index of the highest correlation, preferring the earliest offset when
+/// there is a tie.
+pub fn best_offset(correlation: &[f32]) -> Option<usize> {
+    correlation
+        .iter()
+        .enumerate()
+        .max_by(|(i, a), (j, b)| {
+            a.partial_cmp(b).unwrap_or(Ordering::Equal).then(j.cmp(i))
+        })
+        .map(|(i, _)| i)
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_an_exact_copy_of_the_template() {
+        let signal = [0.0, 0.0, 1.0, 3.0, 2.0, 0.0, 0.0];
+        let template = [1.0, 3.0, 2.0];
+
+        let correlation = normalized_cross_correlation(&signal, &template);
+
+        assert_eq!(correlation.len(), 5);
+        assert!((correlation[2] - 1.0).abs() < 1e-6, "{:?}", correlation);
+        assert_eq!(best_offset(&correlation), Some(2));
+    }
+
+    #[test]
+    fn matches_are_independent_of_offset_and_gain() {
+        let template = [1.0, -1.0, 2.0];
+        let signal = [5.0, 5.0, 5.0, 15.0, -5.0, 25.0, 5.0];
+
+        let correlation = normalized_cross_correlation(&signal, &template);
+
+        assert_eq!(best_offset(&correlation), Some(3));
+        assert!((correlation[3] - 1.0).abs() < 1e-6, "{:?}", correlation);
+    }
+
+    #[test]
+    fn an_inverted_template_is_anti_correlated() {
+        let signal = [1.0, 2.0, 3.0];
+        let template = [3.0, 2.0, 1.0];
+
+        let correlation = normalized_cross_correlation(&signal, &template);
+
+        assert_eq!(correlation.len(), 1);
+        assert!((correlation[0] + 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn flat_windows_score_zero() {
+        let signal = [4.0, 4.0, 4.0, 1.0];
+        let template = [1.0, 2.0];
+
+        let correlation = normalized_cross_correlation(&signal, &template);
+
+        assert_eq!(correlation[..2], [0.0, 0.0]);
+        assert!((correlation[2] + 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn parse_a_template() {
+        let template = parse_template(" 1, -2.5,3 ,").unwrap();
+
+        assert_eq!(template, vec![1.0, -2.5, 3.0]);
+    }
+
+    #[test]
+    fn templates_must_be_numbers() {
+        assert!(parse_template("1, two, 3").is_err());
+        assert!(parse_template(" , ").is_err());
+    }
+}
@@ -0,0 +1,460 @@
+use std::fmt::Display;
+
+use crate::proc_block_v1::*;
+
+use hotg_rune_proc_blocks::{
+    ndarray::{Array1, ArrayView1, ArrayView2, Axis},
+    runtime_v1::{self, *},
+    BufferExt, SliceExt, ValueType,
+};
+use num_traits::ToPrimitive;
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// The integer types that can be used as class labels.
+const LABEL_TYPES: &[&str] = &["u8", "u16", "u32", "i32", "u64", "i64"];
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Cross Entropy", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.set_homepage(env!("CARGO_PKG_HOMEPAGE"));
+        metadata.add_tag("metric");
+        metadata.add_tag("analytics");
+        metadata.add_tag("classification");
+
+        let element_type = ArgumentMetadata::new("element_type");
+        element_type.set_description("The type used for class labels.");
+        element_type.set_default_value("u32");
+        let hint = runtime_v1::interpret_as_string_in_enum(LABEL_TYPES);
+        element_type.add_hint(&hint);
+        metadata.add_argument(&element_type);
+
+        let class_weights = ArgumentMetadata::new("class_weights");
+        class_weights.set_description(
+            "An optional comma-separated list of weights, one per class, used to scale each sample's loss (e.g. \"1.0, 5.0, 1.0\").",
+        );
+        let hint = runtime_v1::supported_argument_type(ArgumentType::String);
+        class_weights.add_hint(&hint);
+        metadata.add_argument(&class_weights);
+
+        let logits = TensorMetadata::new("logits");
+        logits.set_description(
+            "The unnormalized `[N, num_classes]` scores for each sample.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        logits.add_hint(&hint);
+        metadata.add_input(&logits);
+
+        let labels = TensorMetadata::new("labels");
+        labels.set_description("The true class index for each sample.");
+        let hint = supported_shapes(
+            &[
+                ElementType::U8,
+                ElementType::U16,
+                ElementType::U32,
+                ElementType::I32,
+                ElementType::U64,
+                ElementType::I64,
+            ],
+            DimensionsParam::Fixed(&[0]),
+        );
+        labels.add_hint(&hint);
+        metadata.add_input(&labels);
+
+        let loss = TensorMetadata::new("loss");
+        loss.set_description("The (weighted) loss for each sample.");
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Fixed(&[0]));
+        loss.add_hint(&hint);
+        metadata.add_output(&loss);
+
+        let mean_loss = TensorMetadata::new("mean_loss");
+        mean_loss.set_description(
+            "The weighted mean of the per-sample losses, normalised by the sum of the weights.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Fixed(&[1]));
+        mean_loss.add_hint(&hint);
+        metadata.add_output(&mean_loss);
+
+        register_node(&metadata);
+    }
+
+    fn graph(node_id: String) -> Result<(), GraphError> {
+        let ctx = GraphContext::for_node(&node_id)
+            .ok_or(GraphError::MissingContext)?;
+
+        let element_type = get_element_type(|n| ctx.get_argument(n))
+            .map_err(GraphError::InvalidArgument)?;
+        get_class_weights(|n| ctx.get_argument(n))
+            .map_err(GraphError::InvalidArgument)?;
+
+        ctx.add_input_tensor(
+            "logits",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_input_tensor(
+            "labels",
+            element_type,
+            DimensionsParam::Fixed(&[0]),
+        );
+        ctx.add_output_tensor(
+            "loss",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0]),
+        );
+        ctx.add_output_tensor(
+            "mean_loss",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[1]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(node_id: String) -> Result<(), KernelError> {
+        let ctx = KernelContext::for_node(&node_id)
+            .ok_or(KernelError::MissingContext)?;
+
+        let class_weights = get_class_weights(|n| ctx.get_argument(n))
+            .map_err(KernelError::InvalidArgument)?;
+        let class_weights = class_weights.as_deref();
+
+        let logits = ctx.get_input_tensor("logits").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput::not_found("logits"))
+        })?;
+        let labels = ctx.get_input_tensor("labels").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput::not_found("labels"))
+        })?;
+
+        if logits.element_type != ElementType::F32 {
+            return Err(KernelError::InvalidInput(InvalidInput::invalid_value(
+                "logits",
+                format!(
+                    "Expected a f32 tensor, found {:?}",
+                    logits.element_type
+                ),
+            )));
+        }
+
+        let logits = logits
+            .buffer
+            .try_view::<f32, _>(&logits.dimensions)
+            .map_err(|e| {
+                KernelError::InvalidInput(InvalidInput::invalid_value(
+                    "logits", e,
+                ))
+            })?;
+
+        let buffer = &labels.buffer;
+        let dimensions = &labels.dimensions;
+
+        let (loss, mean_loss) = match labels.element_type {
+            ElementType::U8 => preprocess_buffer::<u8>(buffer, dimensions)
+                .and_then(|l| cross_entropy(logits, l, class_weights))?,
+            ElementType::U16 => preprocess_buffer::<u16>(buffer, dimensions)
+                .and_then(|l| cross_entropy(logits, l, class_weights))?,
+            ElementType::U32 => preprocess_buffer::<u32>(buffer, dimensions)
+                .and_then(|l| cross_entropy(logits, l, class_weights))?,
+            ElementType::I32 => preprocess_buffer::<i32>(buffer, dimensions)
+                .and_then(|l| cross_entropy(logits, l, class_weights))?,
+            ElementType::U64 => preprocess_buffer::<u64>(buffer, dimensions)
+                .and_then(|l| cross_entropy(logits, l, class_weights))?,
+            ElementType::I64 => preprocess_buffer::<i64>(buffer, dimensions)
+                .and_then(|l| cross_entropy(logits, l, class_weights))?,
+            other => {
+                return Err(KernelError::InvalidInput(
+                    InvalidInput::invalid_value(
+                        "labels",
+                        format!("Labels must be integers, found {:?}", other),
+                    ),
+                ))
+            },
+        };
+
+        ctx.set_output_tensor(
+            "loss",
+            TensorParam {
+                element_type: ElementType::F32,
+                dimensions: &[loss.len() as u32],
+                buffer: loss
+                    .as_slice()
+                    .expect("Freshly created arrays are contiguous")
+                    .as_bytes(),
+            },
+        );
+        ctx.set_output_tensor(
+            "mean_loss",
+            TensorParam {
+                element_type: ElementType::F32,
+                dimensions: &[1],
+                buffer: [mean_loss].as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn preprocess_buffer<'buf, T>(
+    buffer: &'buf [u8],
+    dimensions: &[u32],
+) -> Result<ArrayView1<'buf, T>, KernelError>
+where
+    T: ValueType,
+{
+    buffer
+        .try_view::<T, _>(dimensions)
+        .map_err(|e| {
+            KernelError::InvalidInput(InvalidInput::invalid_value("labels", e))
+        })
+}
+
+/// Calculate the loss for each sample and their weighted mean.
+///
+/// The mean is normalised by the total weight of the samples (the same as
+/// PyTorch's `CrossEntropyLoss`), and is `0.0` when there are no samples.
+fn cross_entropy<T>(
+    logits: ArrayView2<'_, f32>,
+    labels: ArrayView1<'_, T>,
+    class_weights: Option<&[f32]>,
+) -> Result<(Array1<f32>, f32), KernelError>
+where
+    T: ToPrimitive + Display + Copy,
+{
+    let (samples, num_classes) = logits.dim();
+
+    if labels.len() != samples {
+        return Err(KernelError::InvalidInput(InvalidInput::invalid_value(
+            "labels",
+            format!(
+                "Expected {} labels (one per sample), found {}",
+                samples,
+                labels.len()
+            ),
+        )));
+    }
+
+    if let Some(weights) = class_weights {
+        if weights.len() != num_classes {
+            return Err(KernelError::InvalidArgument(
+                InvalidArgument::invalid_value(
+                    "class_weights",
+                    format!(
+                        "Expected {} weights (one per class), found {}",
+                        num_classes,
+                        weights.len()
+                    ),
+                ),
+            ));
+        }
+    }
+
+    let mut log_probabilities = logits.to_owned();
+    softmax_core::log_softmax_along_axis(
+        log_probabilities.view_mut(),
+        Axis(1),
+        1.0,
+    );
+
+    let mut loss = Array1::zeros(samples);
+    let mut total_weight = 0.0;
+
+    for (i, &label) in labels.iter().enumerate() {
+        let class =
+            label.to_usize().filter(|&c| c < num_classes).ok_or_else(|| {
+                KernelError::InvalidInput(InvalidInput::invalid_value(
+                    "labels",
+                    format!(
+                        "{} is not a valid label when there are {} classes",
+                        label, num_classes
+                    ),
+                ))
+            })?;
+
+        let weight = class_weights.map(|w| w[class]).unwrap_or(1.0);
+        loss[i] = -weight * log_probabilities[[i, class]];
+        total_weight += weight;
+    }
+
+    let mean_loss = if total_weight > 0.0 {
+        loss.sum() / total_weight
+    } else {
+        0.0
+    };
+
+    Ok((loss, mean_loss))
+}
+
+fn get_class_weights(
+    get_argument: impl FnOnce(&str) -> Option<String>,
+) -> Result<Option<Vec<f32>>, InvalidArgument> {
+    let weights = match get_argument("class_weights") {
+        Some(w) => w,
+        None => return Ok(None),
+    };
+
+    weights
+        .split(',')
+        .map(|w| match w.trim().parse::<f32>() {
+            Ok(w) if w >= 0.0 && w.is_finite() => Ok(w),
+            Ok(w) => Err(InvalidArgument::invalid_value(
+                "class_weights",
+                format!("Weights must be non-negative, found {}", w),
+            )),
+            Err(e) => Err(InvalidArgument::invalid_value("class_weights", e)),
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+fn get_element_type(
+    get_argument: impl FnOnce(&str) -> Option<String>,
+) -> Result<ElementType, InvalidArgument> {
+    use hotg_rune_proc_blocks::common::element_type::{
+        parse_element_type, NAME,
+    };
+
+    match get_argument(NAME) {
+        Some(name) => parse_element_type(&name, LABEL_TYPES)
+            .map_err(|e| InvalidArgument::invalid_value(NAME, e)),
+        None => Ok(ElementType::U32),
+    }
+}
+
+impl InvalidArgument {
+    fn invalid_value(name: impl Into<String>, reason: impl Display) -> Self {
+        InvalidArgument {
+            name: name.into(),
+            reason: BadArgumentReason::InvalidValue(reason.to_string()),
+        }
+    }
+}
+
+impl InvalidInput {
+    fn not_found(name: impl Into<String>) -> Self {
+        InvalidInput {
+            name: name.into(),
+            reason: BadInputReason::NotFound,
+        }
+    }
+
+    fn invalid_value(name: impl Into<String>, reason: impl Display) -> Self {
+        InvalidInput {
+            name: name.into(),
+            reason: BadInputReason::InvalidValue(reason.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::{
+        ndarray,
+        testing::{assert_close, assert_tensors_close, Tolerance},
+    };
+
+    #[test]
+    fn uniform_logits_give_log_num_classes() {
+        let logits = ndarray::arr2(&[[0.0_f32, 0.0, 0.0, 0.0]]);
+        let labels = ndarray::arr1(&[2_u32]);
+
+        let (loss, mean) =
+            cross_entropy(logits.view(), labels.view(), None).unwrap();
+
+        let should_be = 4.0_f32.ln();
+        assert_tensors_close(
+            &loss,
+            &ndarray::arr1(&[should_be]),
+            Tolerance::F32,
+        );
+        assert_close(mean, should_be, Tolerance::F32);
+    }
+
+    #[test]
+    fn per_sample_and_mean_loss() {
+        let logits = ndarray::arr2(&[[2.0_f32, 0.0], [0.0, 2.0]]);
+        let labels = ndarray::arr1(&[0_i64, 0]);
+
+        let (loss, mean) =
+            cross_entropy(logits.view(), labels.view(), None).unwrap();
+
+        // -ln(softmax([2, 0])[0]) and -ln(softmax([0, 2])[0])
+        let should_be = ndarray::arr1(&[0.126_928_01, 2.126_928]);
+        assert_tensors_close(&loss, &should_be, Tolerance::F32);
+        assert_close(mean, (0.126_928_01 + 2.126_928) / 2.0, Tolerance::F32);
+    }
+
+    #[test]
+    fn class_weights_scale_the_loss() {
+        let logits = ndarray::arr2(&[[0.0_f32, 0.0], [0.0, 0.0]]);
+        let labels = ndarray::arr1(&[0_u8, 1]);
+        let weights = [1.0, 3.0];
+
+        let (loss, mean) =
+            cross_entropy(logits.view(), labels.view(), Some(&weights))
+                .unwrap();
+
+        let ln2 = 2.0_f32.ln();
+        assert_tensors_close(
+            &loss,
+            &ndarray::arr1(&[ln2, 3.0 * ln2]),
+            Tolerance::F32,
+        );
+        assert_close(mean, ln2, Tolerance::F32);
+    }
+
+    #[test]
+    fn large_logits_are_stable() {
+        let logits = ndarray::arr2(&[[1000.0_f32, 0.0]]);
+        let labels = ndarray::arr1(&[1_u32]);
+
+        let (loss, _) =
+            cross_entropy(logits.view(), labels.view(), None).unwrap();
+
+        assert_tensors_close(&loss, &ndarray::arr1(&[1000.0]), Tolerance::F32);
+    }
+
+    #[test]
+    fn out_of_range_labels_are_an_error() {
+        let logits = ndarray::arr2(&[[0.0_f32, 0.0]]);
+        let labels = ndarray::arr1(&[2_u32]);
+
+        let error =
+            cross_entropy(logits.view(), labels.view(), None).unwrap_err();
+
+        assert!(matches!(error, KernelError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn label_count_must_match_the_batch_size() {
+        let logits = ndarray::arr2(&[[0.0_f32, 0.0]]);
+        let labels = ndarray::arr1(&[0_u32, 1]);
+
+        let error =
+            cross_entropy(logits.view(), labels.view(), None).unwrap_err();
+
+        assert!(matches!(error, KernelError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn parse_class_weights() {
+        let got = get_class_weights(|_| Some("1.0, 2.5,0".to_string()))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(got, vec![1.0, 2.5, 0.0]);
+        assert!(get_class_weights(|_| Some("1.0, -1".to_string())).is_err());
+        assert_eq!(get_class_weights(|_| None).unwrap(), None);
+    }
+}
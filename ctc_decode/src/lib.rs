@@ -0,0 +1,504 @@
+//! Decoding for models trained with Connectionist Temporal Classification
+//! (CTC), which output a probability distribution over an alphabet (plus a
+//! special "blank" symbol) for every frame.
+
+use std::{cmp::Ordering, collections::HashMap};
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::{ArrayView2, Axis},
+    prelude::*,
+    runtime_v1::*,
+    BufferExt, StringBuilder,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata = Metadata::new("CTC Decode", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("nlp");
+        metadata.add_tag("audio");
+        metadata.add_tag("speech-to-text");
+
+        let alphabet = ArgumentMetadata::new("alphabet");
+        alphabet.set_description(
+            "The character for each (non-blank) class, in order. For example, \" abcdefghijklmnopqrstuvwxyz'\".",
+        );
+        alphabet.add_hint(&supported_argument_type(ArgumentType::String));
+        metadata.add_argument(&alphabet);
+
+        let blank_index = ArgumentMetadata::new("blank_index");
+        blank_index.set_description("The index of the CTC blank symbol.");
+        blank_index.set_default_value("0");
+        blank_index
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&blank_index);
+
+        let beam_width = ArgumentMetadata::new("beam_width");
+        beam_width.set_description(
+            "How many candidates to keep while decoding. A width of 1 uses greedy decoding.",
+        );
+        beam_width.set_default_value("1");
+        beam_width
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&beam_width);
+
+        let probabilities = TensorMetadata::new("probabilities");
+        probabilities.set_description(
+            "The `[frames, classes]` probabilities for each frame, where `classes` is the alphabet's length plus one for the blank.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        probabilities.add_hint(&hint);
+        metadata.add_input(&probabilities);
+
+        let text = TensorMetadata::new("text");
+        text.set_description("The decoded text.");
+        let hint = supported_shapes(
+            &[ElementType::Utf8],
+            DimensionsParam::Fixed(&[1]),
+        );
+        text.add_hint(&hint);
+        metadata.add_output(&text);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Decoder::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "probabilities",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "text",
+            ElementType::Utf8,
+            DimensionsParam::Fixed(&[1]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let decoder = Decoder::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("probabilities").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "probabilities".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::F32 {
+            return Err(invalid_probabilities(format!(
+                "Expected a f32 tensor, found {}",
+                element_type
+            )));
+        }
+
+        // Allow a leading batch dimension of 1 (i.e. [1, frames, classes])
+        let dimensions = match *dimensions {
+            [frames, classes] | [1, frames, classes] => [frames, classes],
+            _ => {
+                return Err(invalid_probabilities(format!(
+                    "Expected a [frames, classes] tensor, found {:?}",
+                    dimensions
+                )))
+            },
+        };
+
+        let probabilities = buffer
+            .try_view::<f32, _>(&dimensions)
+            .map_err(invalid_probabilities)?;
+
+        let text = decoder.decode(probabilities)?;
+
+        let mut builder = StringBuilder::new();
+        builder.push(&text);
+
+        ctx.set_output_tensor(
+            "text",
+            TensorParam {
+                element_type: ElementType::Utf8,
+                dimensions: &[1],
+                buffer: &builder.finish(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_probabilities(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "probabilities".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Decoder {
+    alphabet: Vec<char>,
+    blank_index: usize,
+    beam_width: usize,
+}
+
+impl Decoder {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let alphabet: String = ctx.parse_argument::<_, E>("alphabet")?;
+        let blank_index: usize =
+            ctx.parse_argument_with_default::<_, E>("blank_index", 0)?;
+        let beam_width: usize =
+            ctx.parse_argument_with_default::<_, E>("beam_width", 1)?;
+
+        if beam_width == 0 {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "beam_width",
+                "The beam width must be at least 1",
+            )));
+        }
+
+        let alphabet: Vec<char> = alphabet.chars().collect();
+
+        if blank_index > alphabet.len() {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "blank_index",
+                format!(
+                    "The blank index must be at most {} for an alphabet with {} characters",
+                    alphabet.len(),
+                    alphabet.len()
+                ),
+            )));
+        }
+
+        Ok(Decoder {
+            alphabet,
+            blank_index,
+            beam_width,
+        })
+    }
+
+    fn decode(
+        &self,
+        probabilities: ArrayView2<'_, f32>,
+    ) -> Result<String, KernelError> {
+        let classes = probabilities.len_of(Axis(1));
+
+        if classes != self.alphabet.len() + 1 {
+            return Err(invalid_probabilities(format!(
+                "Expected {} classes (the alphabet plus a blank), found {}",
+                self.alphabet.len() + 1,
+                classes
+            )));
+        }
+
+        let labels = if self.beam_width == 1 {
+            greedy_decode(probabilities, self.blank_index)
+        } else {
+            beam_search_decode(probabilities, self.blank_index, self.beam_width)
+        };
+
+        Ok(labels.into_iter().map(|label| self.character(label)).collect())
+    }
+
+    /// Get the character for a (non-blank) class.
+    fn character(&self, class: usize) -> char {
+        if class < self.blank_index {
+            self.alphabet[class]
+        } else {
+            self.alphabet[class - 1]
+        }
+    }
+}
+
+/// Take the most likely class for each frame, then merge repeated classes and
+/// remove blanks.
+fn greedy_decode(
+    probabilities: ArrayView2<'_, f32>,
+    blank: usize,
+) -> Vec<usize> {
+    let mut labels = Vec::new();
+    let mut previous = None;
+
+    for frame in probabilities.outer_iter() {
+        let best = frame
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| {
+                a.partial_cmp(b).unwrap_or(Ordering::Equal)
+            })
+            .map(|(class, _)| class);
+
+        if let Some(class) = best {
+            if class != blank && best != previous {
+                labels.push(class);
+            }
+        }
+
+        previous = best;
+    }
+
+    labels
+}
+
+/// Log-probabilities for a prefix ending in a blank and not ending in a blank,
+/// respectively.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Score {
+    blank: f32,
+    non_blank: f32,
+}
+
+impl Score {
+    const ZERO: Score = Score {
+        blank: f32::NEG_INFINITY,
+        non_blank: f32::NEG_INFINITY,
+    };
+
+    fn total(self) -> f32 { log_sum_exp(self.blank, self.non_blank) }
+}
+
+/// CTC prefix beam search.
+///
+/// Unlike a normal beam search, many alignments collapse to the same output,
+/// so we keep track of the probability of each *prefix* and merge alignments
+/// as we go.
+fn beam_search_decode(
+    probabilities: ArrayView2<'_, f32>,
+    blank: usize,
+    beam_width: usize,
+) -> Vec<usize> {
+    let mut beams = vec![(
+        Vec::new(),
+        Score {
+            blank: 0.0,
+            non_blank: f32::NEG_INFINITY,
+        },
+    )];
+
+    for frame in probabilities.outer_iter() {
+        let mut next: HashMap<Vec<usize>, Score> = HashMap::new();
+
+        for (prefix, score) in &beams {
+            for (class, &p) in frame.iter().enumerate() {
+                let p = p.ln();
+
+                if class == blank {
+                    let entry =
+                        next.entry(prefix.clone()).or_insert(Score::ZERO);
+                    entry.blank = log_sum_exp(entry.blank, score.total() + p);
+                    continue;
+                }
+
+                let mut extended = prefix.clone();
+                extended.push(class);
+
+                if prefix.last() == Some(&class) {
+                    // A repeated character needs a blank in between,
+                    // otherwise it gets merged with the previous one.
+                    let entry = next.entry(extended).or_insert(Score::ZERO);
+                    entry.non_blank =
+                        log_sum_exp(entry.non_blank, score.blank + p);
+
+                    let entry =
+                        next.entry(prefix.clone()).or_insert(Score::ZERO);
+                    entry.non_blank =
+                        log_sum_exp(entry.non_blank, score.non_blank + p);
+                } else {
+                    let entry = next.entry(extended).or_insert(Score::ZERO);
+                    entry.non_blank =
+                        log_sum_exp(entry.non_blank, score.total() + p);
+                }
+            }
+        }
+
+        let mut candidates: Vec<_> = next.into_iter().collect();
+        // Note: break ties using the prefix so results are deterministic
+        candidates.sort_by(|(left_prefix, left), (right_prefix, right)| {
+            right
+                .total()
+                .partial_cmp(&left.total())
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| left_prefix.cmp(right_prefix))
+        });
+        candidates.truncate(beam_width);
+        beams = candidates;
+    }
+
+    beams
+        .into_iter()
+        .next()
+        .map(|(prefix, _)| prefix)
+        .unwrap_or_default()
+}
+
+fn log_sum_exp(a: f32, b: f32) -> f32 {
+    let max = a.max(b);
+
+    if max == f32::NEG_INFINITY {
+        return f32::NEG_INFINITY;
+    }
+
+    max + ((a - max).exp() + (b - max).exp()).ln()
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::ndarray::{self, Array2};
+
+    fn decoder(alphabet: &str, beam_width: usize) -> Decoder {
+        Decoder {
+            alphabet: alphabet.chars().collect(),
+            blank_index: 0,
+            beam_width,
+        }
+    }
+
+    /// Create a probability matrix where each frame is (almost) certain of a
+    /// particular class.
+    fn one_hot(classes: usize, frames: &[usize]) -> Array2<f32> {
+        let mut probabilities =
+            Array2::from_elem((frames.len(), classes), 0.01);
+
+        for (mut row, &class) in probabilities.outer_iter_mut().zip(frames) {
+            row[class] = 1.0 - 0.01 * (classes - 1) as f32;
+        }
+
+        probabilities
+    }
+
+    #[test]
+    fn greedy_merges_repeats_and_drops_blanks() {
+        // "h h _ e l l _ l o" (where "_" is the blank)
+        let probabilities = one_hot(5, &[1, 1, 0, 2, 3, 3, 0, 3, 4]);
+
+        let got = decoder("helo", 1).decode(probabilities.view()).unwrap();
+
+        assert_eq!(got, "hello");
+    }
+
+    #[test]
+    fn blank_can_be_anywhere_in_the_alphabet() {
+        let decoder = Decoder {
+            alphabet: vec!['a', 'b'],
+            blank_index: 2,
+            beam_width: 1,
+        };
+        let probabilities = one_hot(3, &[0, 2, 1, 1]);
+
+        let got = decoder.decode(probabilities.view()).unwrap();
+
+        assert_eq!(got, "ab");
+    }
+
+    #[test]
+    fn beam_search_agrees_with_greedy_on_confident_inputs() {
+        let probabilities = one_hot(5, &[1, 1, 0, 2, 3, 3, 0, 3, 4]);
+
+        let got = decoder("helo", 4).decode(probabilities.view()).unwrap();
+
+        assert_eq!(got, "hello");
+    }
+
+    #[test]
+    fn beam_search_sums_over_alignments() {
+        // The classic example where greedy decoding picks the blank path,
+        // but "a" is more likely once you add up every alignment that
+        // collapses to it.
+        let probabilities = ndarray::arr2(&[[0.6_f32, 0.4], [0.6, 0.4]]);
+
+        let greedy = decoder("a", 1).decode(probabilities.view()).unwrap();
+        let beam = decoder("a", 2).decode(probabilities.view()).unwrap();
+
+        // P("") = 0.36, P("a") = 0.4 * 0.4 + 0.4 * 0.6 + 0.6 * 0.4 = 0.64
+        assert_eq!(greedy, "");
+        assert_eq!(beam, "a");
+    }
+
+    #[test]
+    fn wrong_number_of_classes_is_an_error() {
+        let probabilities = one_hot(3, &[0, 1]);
+
+        let error =
+            decoder("abcd", 1).decode(probabilities.view()).unwrap_err();
+
+        assert!(matches!(error, KernelError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn log_sum_exp_handles_zero_probabilities() {
+        let got = log_sum_exp(f32::NEG_INFINITY, f32::NEG_INFINITY);
+
+        assert_eq!(got, f32::NEG_INFINITY);
+        let got = log_sum_exp(0.5_f32.ln(), 0.25_f32.ln());
+
+        assert!((got - 0.75_f32.ln()).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,477 @@
+//! Density-based clustering using [DBSCAN][wiki].
+//!
+//! A row is a "core point" if at least `min_samples` rows (including itself)
+//! are within `eps` of it. Clusters are made of core points which are within
+//! `eps` of each other, plus any rows within `eps` of those core points. Rows
+//! which aren't part of a cluster are labelled as noise.
+//!
+//! Unlike k-means, the number of clusters doesn't need to be known up front
+//! and clusters can be any shape, which makes it a good fit for grouping
+//! detections or geo-points.
+//!
+//! [wiki]: https://en.wikipedia.org/wiki/DBSCAN
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    common::element_type,
+    ndarray::{ArrayView1, ArrayView2},
+    prelude::*,
+    runtime_v1::*,
+    BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// The label given to rows which don't belong to a cluster.
+const NOISE: i32 = -1;
+
+/// The mean radius of the Earth, in kilometers.
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata = Metadata::new("DBSCAN", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("clustering");
+        metadata.add_tag("analytics");
+
+        let eps = ArgumentMetadata::new("eps");
+        eps.set_description(
+            "The maximum distance between two rows for them to be considered neighbours. When using the `haversine` metric, this is in kilometers.",
+        );
+        eps.set_default_value("0.5");
+        eps.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&eps);
+
+        let min_samples = ArgumentMetadata::new("min_samples");
+        min_samples.set_description(
+            "How many neighbours (including itself) a row needs to be the core of a cluster.",
+        );
+        min_samples.set_default_value("5");
+        min_samples
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&min_samples);
+
+        let metric = ArgumentMetadata::new("metric");
+        metric.set_description(
+            "How to measure the distance between rows. `haversine` expects each row to be a `[latitude, longitude]` pair in degrees.",
+        );
+        metric.set_default_value("euclidean");
+        let hint = interpret_as_string_in_enum(&[
+            "euclidean",
+            "manhattan",
+            "haversine",
+        ]);
+        metric.add_hint(&hint);
+        metadata.add_argument(&metric);
+
+        let element_type = ArgumentMetadata::new("element_type");
+        element_type.set_description("The type of the features.");
+        element_type.set_default_value("f32");
+        element_type
+            .add_hint(&interpret_as_string_in_enum(element_type::FLOAT));
+        metadata.add_argument(&element_type);
+
+        let features = TensorMetadata::new("features");
+        features.set_description(
+            "The rows to cluster, as a `[rows, features]` tensor.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32, ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        features.add_hint(&hint);
+        metadata.add_input(&features);
+
+        let labels = TensorMetadata::new("labels");
+        labels.set_description(
+            "The cluster each row belongs to, starting from 0, or -1 for noise.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::I32], DimensionsParam::Fixed(&[0]));
+        labels.add_hint(&hint);
+        metadata.add_output(&labels);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Dbscan::from_context::<_, GraphError>(&ctx)?;
+        let element_type = ctx.element_type::<GraphError>(
+            ElementType::F32,
+            element_type::FLOAT,
+        )?;
+
+        ctx.add_input_tensor(
+            "features",
+            element_type,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "labels",
+            ElementType::I32,
+            DimensionsParam::Fixed(&[0]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let dbscan = Dbscan::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("features").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "features".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        let features = match element_type {
+            ElementType::F32 => buffer
+                .try_view::<f32, _>(&dimensions)
+                .map(|t| t.mapv(f64::from)),
+            ElementType::F64 => buffer
+                .try_view::<f64, _>(&dimensions)
+                .map(|t| t.to_owned()),
+            other => {
+                return Err(invalid_features(format!(
+                    "Expected f32 or f64 features, found {}",
+                    other
+                )))
+            },
+        }
+        .map_err(invalid_features)?;
+
+        let labels = dbscan.fit(features.view())?;
+
+        ctx.set_output_tensor(
+            "labels",
+            TensorParam {
+                element_type: ElementType::I32,
+                dimensions: &[labels.len() as u32],
+                buffer: labels.as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_features(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "features".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Metric {
+    Euclidean,
+    Manhattan,
+    /// The great-circle distance between two `[latitude, longitude]` pairs,
+    /// in kilometers.
+    Haversine,
+}
+
+impl Metric {
+    fn distance(self, a: ArrayView1<'_, f64>, b: ArrayView1<'_, f64>) -> f64 {
+        match self {
+            Metric::Euclidean => a
+                .iter()
+                .zip(b)
+                .map(|(a, b)| (a - b).powi(2))
+                .sum::<f64>()
+                .sqrt(),
+            Metric::Manhattan => {
+                a.iter().zip(b).map(|(a, b)| (a - b).abs()).sum()
+            },
+            Metric::Haversine => {
+                let (lat_a, lon_a) = (a[0].to_radians(), a[1].to_radians());
+                let (lat_b, lon_b) = (b[0].to_radians(), b[1].to_radians());
+
+                let h = ((lat_b - lat_a) / 2.0).sin().powi(2)
+                    + lat_a.cos()
+                        * lat_b.cos()
+                        * ((lon_b - lon_a) / 2.0).sin().powi(2);
+
+                2.0 * EARTH_RADIUS_KM * h.sqrt().min(1.0).asin()
+            },
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Dbscan {
+    eps: f64,
+    min_samples: usize,
+    metric: Metric,
+}
+
+impl Dbscan {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let eps: f64 = ctx.parse_argument_with_default::<_, E>("eps", 0.5)?;
+        let min_samples =
+            ctx.parse_argument_with_default::<_, E>("min_samples", 5)?;
+        let metric = ctx.parse_argument_with_default::<_, E>(
+            "metric",
+            String::from("euclidean"),
+        )?;
+
+        if !(eps > 0.0 && eps.is_finite()) {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "eps",
+                "eps must be a positive number",
+            )));
+        }
+
+        let metric = match metric.as_str() {
+            "euclidean" => Metric::Euclidean,
+            "manhattan" => Metric::Manhattan,
+            "haversine" => Metric::Haversine,
+            other => {
+                return Err(E::invalid_argument(
+                    InvalidArgument::invalid_value(
+                        "metric",
+                        format!("Unknown metric, \"{}\"", other),
+                    ),
+                ))
+            },
+        };
+
+        Ok(Dbscan {
+            eps,
+            min_samples,
+            metric,
+        })
+    }
+
+    /// Assign each row in `features` to a cluster.
+    fn fit(
+        &self,
+        features: ArrayView2<'_, f64>,
+    ) -> Result<Vec<i32>, KernelError> {
+        if self.metric == Metric::Haversine && features.ncols() != 2 {
+            return Err(invalid_features(format!(
+                "The haversine metric needs [latitude, longitude] rows, but found {} features",
+                features.ncols()
+            )));
+        }
+
+        let mut labels: Vec<Option<i32>> = vec![None; features.nrows()];
+        let mut next_cluster = 0;
+
+        for row in 0..features.nrows() {
+            if labels[row].is_some() {
+                continue;
+            }
+
+            let neighbours = self.neighbours(features, row);
+
+            if neighbours.len() < self.min_samples {
+                // This might still end up as a border point of a cluster we
+                // find later on
+                labels[row] = Some(NOISE);
+                continue;
+            }
+
+            let cluster = next_cluster;
+            next_cluster += 1;
+            labels[row] = Some(cluster);
+
+            let mut pending = neighbours;
+
+            while let Some(candidate) = pending.pop() {
+                match labels[candidate] {
+                    // Border points which were previously deemed noise
+                    Some(NOISE) => labels[candidate] = Some(cluster),
+                    Some(_) => {},
+                    None => {
+                        labels[candidate] = Some(cluster);
+                        let neighbours = self.neighbours(features, candidate);
+
+                        if neighbours.len() >= self.min_samples {
+                            pending.extend(neighbours);
+                        }
+                    },
+                }
+            }
+        }
+
+        Ok(labels.into_iter().map(|l| l.unwrap_or(NOISE)).collect())
+    }
+
+    /// Find every row within `eps` of `row`, including `row` itself.
+    fn neighbours(
+        &self,
+        features: ArrayView2<'_, f64>,
+        row: usize,
+    ) -> Vec<usize> {
+        let point = features.row(row);
+
+        features
+            .outer_iter()
+            .enumerate()
+            .filter(|(_, other)| {
+                self.metric.distance(point, *other) <= self.eps
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::{
+        ndarray::{self, Array2},
+        testing::{assert_close, Tolerance},
+    };
+
+    const DBSCAN: Dbscan = Dbscan {
+        eps: 0.5,
+        min_samples: 3,
+        metric: Metric::Euclidean,
+    };
+
+    #[test]
+    fn two_clusters_and_an_outlier() {
+        let features = ndarray::arr2(&[
+            [0.0, 0.0],
+            [0.1, 0.2],
+            [0.2, 0.1],
+            [5.0, 5.0],
+            [5.1, 4.9],
+            [4.9, 5.2],
+            [10.0, -10.0],
+        ]);
+
+        let labels = DBSCAN.fit(features.view()).unwrap();
+
+        assert_eq!(labels, [0, 0, 0, 1, 1, 1, -1]);
+    }
+
+    #[test]
+    fn clusters_can_be_chained_together() {
+        // A long thin line of points, each only close to its neighbours
+        let features = Array2::from_shape_fn((10, 2), |(i, j)| match j {
+            0 => i as f64 * 0.4,
+            _ => 0.0,
+        });
+
+        let labels = DBSCAN.fit(features.view()).unwrap();
+
+        assert_eq!(labels, vec![0; 10]);
+    }
+
+    #[test]
+    fn border_points_join_a_cluster_even_if_seen_first() {
+        // The first row only has 2 neighbours, so it isn't a core point, but
+        // it is within eps of the second row, which is
+        let features =
+            ndarray::arr2(&[[0.0], [0.4], [0.8], [0.85], [0.9], [100.0]]);
+
+        let labels = DBSCAN.fit(features.view()).unwrap();
+
+        assert_eq!(labels, [0, 0, 0, 0, 0, -1]);
+    }
+
+    #[test]
+    fn everything_is_noise_when_min_samples_is_too_big() {
+        let dbscan = Dbscan {
+            min_samples: 10,
+            ..DBSCAN
+        };
+        let features = ndarray::arr2(&[[0.0], [0.1], [0.2]]);
+
+        let labels = dbscan.fit(features.view()).unwrap();
+
+        assert_eq!(labels, [-1, -1, -1]);
+    }
+
+    #[test]
+    fn no_rows() {
+        let labels = DBSCAN.fit(Array2::zeros((0, 2)).view()).unwrap();
+
+        assert!(labels.is_empty());
+    }
+
+    #[test]
+    fn haversine_distance() {
+        let london = ndarray::arr1(&[51.5074, -0.1278]);
+        let paris = ndarray::arr1(&[48.8566, 2.3522]);
+
+        let distance = Metric::Haversine.distance(london.view(), paris.view());
+
+        assert_close(distance, 343.5, Tolerance::absolute(1.0));
+    }
+
+    #[test]
+    fn haversine_needs_lat_lon_pairs() {
+        let dbscan = Dbscan {
+            metric: Metric::Haversine,
+            ..DBSCAN
+        };
+
+        assert!(dbscan.fit(Array2::zeros((2, 3)).view()).is_err());
+    }
+}
@@ -0,0 +1,504 @@
+//! A decision tree classifier, backed by smartcore's
+//! `DecisionTreeClassifier`.
+//!
+//! As well as the predicted class for each row in `x_test`, the trained tree
+//! is serialized to JSON so it can be inspected or saved for later.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::{ArrayView1, ArrayView2},
+    prelude::*,
+    runtime_v1::*,
+    BufferExt, SliceExt, StringBuilder,
+};
+use smartcore::{
+    linalg::naive::dense_matrix::DenseMatrix,
+    tree::decision_tree_classifier::{
+        DecisionTreeClassifier, DecisionTreeClassifierParameters,
+        SplitCriterion,
+    },
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Decision Tree", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("classification");
+        metadata.add_tag("decision tree");
+        metadata.add_tag("analytics");
+
+        let max_depth = ArgumentMetadata::new("max_depth");
+        max_depth.set_description(
+            "The maximum depth of the tree. The tree is grown until every leaf is pure when this isn't set.",
+        );
+        max_depth
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&max_depth);
+
+        let min_samples_split = ArgumentMetadata::new("min_samples_split");
+        min_samples_split.set_description(
+            "The minimum number of samples a node needs before it can be split.",
+        );
+        min_samples_split.set_default_value("2");
+        min_samples_split
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&min_samples_split);
+
+        let criterion = ArgumentMetadata::new("criterion");
+        criterion.set_description("How to measure the quality of a split.");
+        criterion.set_default_value("gini");
+        criterion.add_hint(&interpret_as_string_in_enum(Criterion::NAMES));
+        metadata.add_argument(&criterion);
+
+        let x_train = TensorMetadata::new("x_train");
+        x_train.set_description("The training samples, one per row.");
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        x_train.add_hint(&hint);
+        metadata.add_input(&x_train);
+
+        let y_train = TensorMetadata::new("y_train");
+        y_train.set_description("The class for each training sample.");
+        let hint =
+            supported_shapes(&[ElementType::F64], DimensionsParam::Fixed(&[0]));
+        y_train.add_hint(&hint);
+        metadata.add_input(&y_train);
+
+        let x_test = TensorMetadata::new("x_test");
+        x_test.set_description("The samples to classify, one per row.");
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        x_test.add_hint(&hint);
+        metadata.add_input(&x_test);
+
+        let y_test = TensorMetadata::new("y_test");
+        y_test.set_description("The predicted class for each row in `x_test`.");
+        let hint =
+            supported_shapes(&[ElementType::F64], DimensionsParam::Fixed(&[0]));
+        y_test.add_hint(&hint);
+        metadata.add_output(&y_test);
+
+        let model = TensorMetadata::new("model");
+        model.set_description("The trained tree, serialized as JSON.");
+        let hint = supported_shapes(
+            &[ElementType::Utf8],
+            DimensionsParam::Fixed(&[1]),
+        );
+        model.add_hint(&hint);
+        metadata.add_output(&model);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "x_train",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_input_tensor(
+            "y_train",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0]),
+        );
+        ctx.add_input_tensor(
+            "x_test",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "y_test",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0]),
+        );
+        ctx.add_output_tensor(
+            "model",
+            ElementType::Utf8,
+            DimensionsParam::Fixed(&[1]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let x_train = get_input(&ctx, "x_train")?;
+        let x_train = view_2d(&x_train, "x_train")?;
+        let y_train = get_input(&ctx, "y_train")?;
+        let y_train = view_1d(&y_train, "y_train")?;
+        let x_test = get_input(&ctx, "x_test")?;
+        let x_test = view_2d(&x_test, "x_test")?;
+
+        let model = fit(x_train, y_train, settings)?;
+        let predictions = predict(&model, x_train.ncols(), x_test)?;
+        let serialized = serde_json::to_string(&model)
+            .map_err(|e| KernelError::Other(e.to_string()))?;
+
+        ctx.set_output_tensor(
+            "y_test",
+            TensorParam {
+                element_type: ElementType::F64,
+                dimensions: &[predictions.len() as u32],
+                buffer: predictions.as_bytes(),
+            },
+        );
+
+        let mut builder = StringBuilder::new();
+        builder.push(&serialized);
+        ctx.set_output_tensor(
+            "model",
+            TensorParam {
+                element_type: ElementType::Utf8,
+                dimensions: &[1],
+                buffer: &builder.finish(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(name: &str, reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: name.to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+fn get_input(
+    ctx: &KernelContext,
+    name: &str,
+) -> Result<TensorResult, KernelError> {
+    let tensor = ctx.get_input_tensor(name).ok_or_else(|| {
+        KernelError::InvalidInput(InvalidInput {
+            name: name.to_string(),
+            reason: BadInputReason::NotFound,
+        })
+    })?;
+
+    if tensor.element_type != ElementType::F64 {
+        return Err(invalid_input(
+            name,
+            format!("Expected a f64 tensor, found {}", tensor.element_type),
+        ));
+    }
+
+    Ok(tensor)
+}
+
+fn view_2d<'a>(
+    tensor: &'a TensorResult,
+    name: &str,
+) -> Result<ArrayView2<'a, f64>, KernelError> {
+    tensor
+        .buffer
+        .try_view::<f64, _>(&tensor.dimensions)
+        .map_err(|e| invalid_input(name, e))
+}
+
+fn view_1d<'a>(
+    tensor: &'a TensorResult,
+    name: &str,
+) -> Result<ArrayView1<'a, f64>, KernelError> {
+    tensor
+        .buffer
+        .try_view::<f64, _>(&tensor.dimensions)
+        .map_err(|e| invalid_input(name, e))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Criterion {
+    Gini,
+    Entropy,
+    ClassificationError,
+}
+
+impl Criterion {
+    const NAMES: &'static [&'static str] =
+        &["gini", "entropy", "classification_error"];
+
+    fn split_criterion(self) -> SplitCriterion {
+        match self {
+            Criterion::Gini => SplitCriterion::Gini,
+            Criterion::Entropy => SplitCriterion::Entropy,
+            Criterion::ClassificationError => {
+                SplitCriterion::ClassificationError
+            },
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    max_depth: Option<u16>,
+    min_samples_split: usize,
+    criterion: Criterion,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let max_depth = match ctx._get_argument("max_depth") {
+            Some(_) => Some(ctx.parse_argument::<u16, E>("max_depth")?),
+            None => None,
+        };
+        let min_samples_split =
+            ctx.parse_argument_with_default::<_, E>("min_samples_split", 2)?;
+        let criterion = ctx.parse_argument_with_default::<_, E>(
+            "criterion",
+            String::from("gini"),
+        )?;
+
+        if max_depth == Some(0) {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "max_depth",
+                "The max depth must be at least 1",
+            )));
+        }
+
+        if min_samples_split < 2 {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "min_samples_split",
+                format!(
+                    "A node needs at least 2 samples to be split, found {}",
+                    min_samples_split
+                ),
+            )));
+        }
+
+        let criterion = match criterion.as_str() {
+            "gini" => Criterion::Gini,
+            "entropy" => Criterion::Entropy,
+            "classification_error" => Criterion::ClassificationError,
+            other => {
+                return Err(E::invalid_argument(
+                    InvalidArgument::invalid_value(
+                        "criterion",
+                        format!(
+                            "Unknown criterion, \"{}\". Expected one of {:?}",
+                            other,
+                            Criterion::NAMES
+                        ),
+                    ),
+                ))
+            },
+        };
+
+        Ok(Settings {
+            max_depth,
+            min_samples_split,
+            criterion,
+        })
+    }
+
+    fn parameters(self) -> DecisionTreeClassifierParameters {
+        let Settings {
+            max_depth,
+            min_samples_split,
+            criterion,
+        } = self;
+
+        let parameters = DecisionTreeClassifierParameters::default()
+            .with_criterion(criterion.split_criterion())
+            .with_min_samples_split(min_samples_split);
+
+        match max_depth {
+            Some(depth) => parameters.with_max_depth(depth),
+            None => parameters,
+        }
+    }
+}
+
+fn fit(
+    x: ArrayView2<'_, f64>,
+    y: ArrayView1<'_, f64>,
+    settings: Settings,
+) -> Result<DecisionTreeClassifier<f64>, KernelError> {
+    if x.nrows() != y.len() {
+        return Err(invalid_input(
+            "y_train",
+            format!(
+                "Expected {} targets (one per training sample), found {}",
+                x.nrows(),
+                y.len()
+            ),
+        ));
+    }
+
+    if y.is_empty() {
+        return Err(invalid_input("x_train", "No training samples"));
+    }
+
+    let x = dense_matrix(x);
+    let y = y.to_vec();
+
+    DecisionTreeClassifier::fit(&x, &y, settings.parameters())
+        .map_err(|e| KernelError::Other(e.to_string()))
+}
+
+fn predict(
+    model: &DecisionTreeClassifier<f64>,
+    features: usize,
+    x: ArrayView2<'_, f64>,
+) -> Result<Vec<f64>, KernelError> {
+    if x.ncols() != features {
+        return Err(invalid_input(
+            "x_test",
+            format!(
+                "The model was trained with {} features, but the test samples have {}",
+                features,
+                x.ncols()
+            ),
+        ));
+    }
+
+    model
+        .predict(&dense_matrix(x))
+        .map_err(|e| KernelError::Other(e.to_string()))
+}
+
+fn dense_matrix(array: ArrayView2<'_, f64>) -> DenseMatrix<f64> {
+    let values: Vec<f64> = array.iter().copied().collect();
+    DenseMatrix::from_array(array.nrows(), array.ncols(), &values)
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::ndarray::{arr1, Array2};
+
+    const SETTINGS: Settings = Settings {
+        max_depth: None,
+        min_samples_split: 2,
+        criterion: Criterion::Gini,
+    };
+
+    /// The first 5 setosa (`0`) and versicolor (`1`) samples from the iris
+    /// dataset.
+    fn iris() -> (Array2<f64>, Vec<f64>) {
+        #[rustfmt::skip]
+        let x = vec![
+            5.1, 3.5, 1.4, 0.2,
+            4.9, 3.0, 1.4, 0.2,
+            4.7, 3.2, 1.3, 0.2,
+            4.6, 3.1, 1.5, 0.2,
+            5.0, 3.6, 1.4, 0.2,
+            7.0, 3.2, 4.7, 1.4,
+            6.4, 3.2, 4.5, 1.5,
+            6.9, 3.1, 4.9, 1.5,
+            5.5, 2.3, 4.0, 1.3,
+            6.5, 2.8, 4.6, 1.5,
+        ];
+        let y = vec![0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+
+        (Array2::from_shape_vec((10, 4), x).unwrap(), y)
+    }
+
+    #[test]
+    fn classify_the_training_data() {
+        let (x, y) = iris();
+
+        let model = fit(x.view(), arr1(&y).view(), SETTINGS).unwrap();
+        let predictions = predict(&model, 4, x.view()).unwrap();
+
+        assert_eq!(predictions, y);
+    }
+
+    #[test]
+    fn the_model_can_be_serialized() {
+        let (x, y) = iris();
+        let settings = Settings {
+            max_depth: Some(1),
+            criterion: Criterion::Entropy,
+            ..SETTINGS
+        };
+        let model = fit(x.view(), arr1(&y).view(), settings).unwrap();
+
+        let serialized = serde_json::to_string(&model).unwrap();
+
+        let round_tripped: DecisionTreeClassifier<f64> =
+            serde_json::from_str(&serialized).unwrap();
+        assert_eq!(round_tripped.predict(&dense_matrix(x.view())).unwrap(), y);
+    }
+
+    #[test]
+    fn the_number_of_features_must_match() {
+        let (x, y) = iris();
+        let model = fit(x.view(), arr1(&y).view(), SETTINGS).unwrap();
+        let x_test = Array2::zeros((2, 3));
+
+        assert!(predict(&model, 4, x_test.view()).is_err());
+    }
+
+    #[test]
+    fn the_number_of_targets_must_match() {
+        let (x, y) = iris();
+
+        assert!(fit(x.view(), arr1(&y[..4]).view(), SETTINGS).is_err());
+    }
+}
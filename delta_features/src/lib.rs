@@ -0,0 +1,386 @@
+//! Delta (and delta-delta) features, which describe how each coefficient in a
+//! speech feature vector changes over time.
+//!
+//! The deltas are calculated using the regression formula from HTK,
+//!
+//! ```text
+//!        Σₙ n (c[t+n] - c[t-n])
+//! d[t] = ----------------------,   n = 1..=N
+//!             2 Σₙ n²
+//! ```
+//!
+//! where frames past the start or end of the utterance are replaced with the
+//! first or last frame.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::{s, Array2, ArrayView2, Axis},
+    prelude::*,
+    runtime_v1::*,
+    BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Delta Features", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("audio");
+        metadata.add_tag("speech");
+        metadata.add_tag("feature-extraction");
+
+        let window = ArgumentMetadata::new("window");
+        window.set_description(
+            "How many frames either side of the current one are used when calculating each derivative.",
+        );
+        window.set_default_value("2");
+        window
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&window);
+
+        let order = ArgumentMetadata::new("order");
+        order.set_description(
+            "The highest derivative to calculate (1 for deltas, 2 for deltas and delta-deltas).",
+        );
+        order.set_default_value("2");
+        order.add_hint(&interpret_as_string_in_enum(&["1", "2"]));
+        metadata.add_argument(&order);
+
+        let features = TensorMetadata::new("features");
+        features.set_description(
+            "A `[frames, coefficients]` tensor of features (e.g. MFCCs).",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        features.add_hint(&hint);
+        metadata.add_input(&features);
+
+        let output = TensorMetadata::new("features_with_deltas");
+        output.set_description(
+            "A `[frames, coefficients * (order + 1)]` tensor containing the original features followed by each derivative.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        output.add_hint(&hint);
+        metadata.add_output(&output);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Deltas::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "features",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "features_with_deltas",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let deltas = Deltas::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("features").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "features".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::F32 {
+            return Err(invalid_features(format!(
+                "Expected a f32 tensor, found {}",
+                element_type
+            )));
+        }
+
+        let features = buffer
+            .try_view::<f32, _>(&dimensions)
+            .map_err(invalid_features)?;
+
+        let output = deltas.append_to(features);
+        let (frames, columns) = output.dim();
+
+        ctx.set_output_tensor(
+            "features_with_deltas",
+            TensorParam {
+                element_type: ElementType::F32,
+                dimensions: &[frames as u32, columns as u32],
+                buffer: output
+                    .as_slice()
+                    .expect("Freshly created arrays are contiguous")
+                    .as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_features(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "features".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Deltas {
+    window: usize,
+    order: usize,
+}
+
+impl Deltas {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let window: usize =
+            ctx.parse_argument_with_default::<_, E>("window", 2)?;
+        let order: usize =
+            ctx.parse_argument_with_default::<_, E>("order", 2)?;
+
+        if window == 0 {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "window",
+                "The window must be at least 1",
+            )));
+        }
+
+        if !(1..=2).contains(&order) {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "order",
+                format!("Expected 1 or 2, found {}", order),
+            )));
+        }
+
+        Ok(Deltas { window, order })
+    }
+
+    /// Calculate each derivative and concatenate them onto the end of the
+    /// features.
+    fn append_to(&self, features: ArrayView2<'_, f32>) -> Array2<f32> {
+        let (frames, coefficients) = features.dim();
+        let mut output =
+            Array2::zeros((frames, coefficients * (self.order + 1)));
+
+        let mut previous = features.to_owned();
+        output.slice_mut(s![.., ..coefficients]).assign(&previous);
+
+        for i in 1..=self.order {
+            let next = delta(previous.view(), self.window);
+            output
+                .slice_mut(s![.., i * coefficients..(i + 1) * coefficients])
+                .assign(&next);
+            previous = next;
+        }
+
+        output
+    }
+}
+
+/// Calculate the first derivative of each coefficient over time.
+fn delta(features: ArrayView2<'_, f32>, window: usize) -> Array2<f32> {
+    let frames = features.len_of(Axis(0));
+    let mut deltas = Array2::zeros(features.dim());
+
+    if frames == 0 {
+        return deltas;
+    }
+
+    let denominator: f32 =
+        2.0 * (1..=window).map(|n| (n * n) as f32).sum::<f32>();
+    let frame = |t: isize| {
+        let t = t.clamp(0, frames as isize - 1) as usize;
+        features.row(t)
+    };
+
+    for (t, mut row) in deltas.outer_iter_mut().enumerate() {
+        let t = t as isize;
+
+        for n in 1..=window {
+            let n = n as isize;
+            let difference = &frame(t + n) - &frame(t - n);
+            row.scaled_add(n as f32, &difference);
+        }
+
+        row /= denominator;
+    }
+
+    deltas
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::{
+        ndarray,
+        testing::{assert_tensors_close, Tolerance},
+    };
+
+    #[test]
+    fn deltas_of_a_linear_ramp_are_constant_in_the_middle() {
+        let features =
+            ndarray::arr2(&[[0.0_f32], [1.0], [2.0], [3.0], [4.0], [5.0]]);
+
+        let got = delta(features.view(), 1);
+
+        // The edges are attenuated because the first/last frames are repeated
+        let should_be =
+            ndarray::arr2(&[[0.5], [1.0], [1.0], [1.0], [1.0], [0.5]]);
+        assert_tensors_close(&got, &should_be, Tolerance::F32);
+    }
+
+    #[test]
+    fn known_deltas_with_a_wider_window() {
+        let features = ndarray::arr2(&[[0.0_f32], [1.0], [4.0], [9.0], [16.0]]);
+
+        let got = delta(features.view(), 2);
+
+        // e.g. for t=2: (1*(9 - 1) + 2*(16 - 0)) / 10 = 4
+        let should_be = ndarray::arr2(&[[0.9], [2.2], [4.0], [4.2], [3.1]]);
+        assert_tensors_close(&got, &should_be, Tolerance::F32);
+    }
+
+    #[test]
+    fn constant_features_have_no_deltas() {
+        let features = ndarray::arr2(&[[3.0_f32, -1.0], [3.0, -1.0]]);
+
+        let got = delta(features.view(), 2);
+
+        assert_tensors_close(&got, &Array2::zeros((2, 2)), Tolerance::F32);
+    }
+
+    #[test]
+    fn deltas_are_appended_along_the_feature_axis() {
+        let deltas = Deltas {
+            window: 1,
+            order: 2,
+        };
+        let features = ndarray::arr2(&[
+            [0.0_f32, 1.0],
+            [1.0, 1.0],
+            [2.0, 1.0],
+            [3.0, 1.0],
+        ]);
+
+        let got = deltas.append_to(features.view());
+
+        assert_eq!(got.dim(), (4, 6));
+        assert_eq!(got.column(0), features.column(0));
+        assert_eq!(got.column(1), features.column(1));
+        // first derivative
+        assert_tensors_close(
+            &got.column(2),
+            &ndarray::arr1(&[0.5, 1.0, 1.0, 0.5]),
+            Tolerance::F32,
+        );
+        assert_tensors_close(
+            &got.column(3),
+            &ndarray::arr1(&[0.0, 0.0, 0.0, 0.0]),
+            Tolerance::F32,
+        );
+        // second derivative
+        assert_tensors_close(
+            &got.column(4),
+            &ndarray::arr1(&[0.25, 0.25, -0.25, -0.25]),
+            Tolerance::F32,
+        );
+    }
+
+    #[test]
+    fn first_order_only() {
+        let deltas = Deltas {
+            window: 2,
+            order: 1,
+        };
+        let features = Array2::<f32>::zeros((10, 13));
+
+        let got = deltas.append_to(features.view());
+
+        assert_eq!(got.dim(), (10, 26));
+    }
+
+    #[test]
+    fn empty_features() {
+        let deltas = Deltas {
+            window: 2,
+            order: 2,
+        };
+        let features = Array2::<f32>::zeros((0, 13));
+
+        let got = deltas.append_to(features.view());
+
+        assert_eq!(got.dim(), (0, 39));
+    }
+}
@@ -0,0 +1,487 @@
+//! Accumulate the centre of each detection into a 2D histogram, giving a
+//! heatmap of where objects are usually found.
+//!
+//! This is useful for occupancy analytics (e.g. which parts of a shop get the
+//! most foot traffic) because only the aggregated heatmap needs to leave the
+//! device, rather than every detection from every frame.
+//!
+//! Detections are expected to use normalized coordinates, where `(0, 0)` is
+//! the top-left corner of the image and `(1, 1)` is the bottom-right.
+//! Detections outside the image are ignored.
+//!
+//! The accumulated counts are kept between invocations, and start again from
+//! zero if the heatmap's size changes.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    prelude::*, runtime_v1::*, state::NodeState, BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// Each node's heatmap.
+static HEATMAPS: NodeState<Heatmap> = NodeState::new();
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Detection Heatmap", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("image");
+        metadata.add_tag("object-detection");
+        metadata.add_tag("analytics");
+        metadata.add_tag("stateful");
+
+        let rows = ArgumentMetadata::new("rows");
+        rows.set_description("The number of cells along the heatmap's height.");
+        rows.set_default_value("32");
+        rows.add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&rows);
+
+        let columns = ArgumentMetadata::new("columns");
+        columns
+            .set_description("The number of cells along the heatmap's width.");
+        columns.set_default_value("32");
+        columns
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&columns);
+
+        let emit_every = ArgumentMetadata::new("emit_every");
+        emit_every.set_description(
+            "How many frames to wait between updates to the heatmap output. In between updates, the previous heatmap is emitted again.",
+        );
+        emit_every.set_default_value("1");
+        emit_every
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&emit_every);
+
+        let decay = ArgumentMetadata::new("decay");
+        decay.set_description(
+            "How much of the accumulated heatmap to keep each frame. Values less than 1 make older detections fade away over time.",
+        );
+        decay.set_default_value("1.0");
+        decay.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&decay);
+
+        let normalization = ArgumentMetadata::new("normalization");
+        normalization.set_description(
+            "How to scale the heatmap. `max` makes the busiest cell 1.0, while `sum` makes all cells add up to 1.0.",
+        );
+        normalization.set_default_value("max");
+        normalization.add_hint(&interpret_as_string_in_enum(&["max", "sum"]));
+        metadata.add_argument(&normalization);
+
+        let detections = TensorMetadata::new("detections");
+        detections.set_description(
+            "The objects detected in the current frame, where the first two columns of each row are the `(x, y)` coordinates of the object's centre (e.g. the output of `object_filter` or `tracker`).",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        detections.add_hint(&hint);
+        metadata.add_input(&detections);
+
+        let heatmap = TensorMetadata::new("heatmap");
+        heatmap.set_description(
+            "The normalized heatmap, as a `[rows, columns]` tensor.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        heatmap.add_hint(&hint);
+        metadata.add_output(&heatmap);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "detections",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "heatmap",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[settings.rows, settings.columns]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("detections").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "detections".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::F32 {
+            return Err(invalid_detections(format!(
+                "Expected a f32 tensor, found {}",
+                element_type
+            )));
+        }
+
+        let columns = match *dimensions {
+            [_, columns] if columns >= 2 => columns as usize,
+            _ => {
+                return Err(invalid_detections(format!(
+                    "Expected a [detections, columns] tensor with at least 2 columns, found {:?}",
+                    dimensions
+                )))
+            },
+        };
+
+        let centres: Vec<(f32, f32)> = buffer
+            .try_elements_with_shape::<f32>(&dimensions)
+            .map_err(invalid_detections)?
+            .chunks_exact(columns)
+            .map(|row| (row[0], row[1]))
+            .collect();
+
+        let heatmap = HEATMAPS.with_or_insert(
+            &id,
+            || Heatmap::new(&settings),
+            |heatmap| heatmap.update(&centres, &settings).to_vec(),
+        );
+
+        ctx.set_output_tensor(
+            "heatmap",
+            TensorParam {
+                element_type: ElementType::F32,
+                dimensions: &[settings.rows, settings.columns],
+                buffer: heatmap.as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_detections(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "detections".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Normalization {
+    /// Divide by the largest cell.
+    Max,
+    /// Divide by the sum of all cells.
+    Sum,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    rows: u32,
+    columns: u32,
+    emit_every: u32,
+    decay: f32,
+    normalization: Normalization,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let rows = ctx.parse_argument_with_default::<_, E>("rows", 32)?;
+        let columns = ctx.parse_argument_with_default::<_, E>("columns", 32)?;
+        let emit_every =
+            ctx.parse_argument_with_default::<_, E>("emit_every", 1)?;
+        let decay: f32 = ctx.parse_argument_with_default::<_, E>("decay", 1.0)?;
+        let normalization = ctx.parse_argument_with_default::<_, E>(
+            "normalization",
+            String::from("max"),
+        )?;
+
+        for (name, value) in
+            [("rows", rows), ("columns", columns), ("emit_every", emit_every)]
+        {
+            if value == 0 {
+                return Err(E::invalid_argument(
+                    InvalidArgument::invalid_value(
+                        name,
+                        "Must be greater than zero",
+                    ),
+                ));
+            }
+        }
+
+        if !(decay > 0.0 && decay <= 1.0) {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "decay",
+                "The decay must be in the range (0, 1]",
+            )));
+        }
+
+        let normalization = match normalization.as_str() {
+            "max" => Normalization::Max,
+            "sum" => Normalization::Sum,
+            other => {
+                return Err(E::invalid_argument(
+                    InvalidArgument::invalid_value(
+                        "normalization",
+                        format!("Unknown normalization, \"{}\"", other),
+                    ),
+                ))
+            },
+        };
+
+        Ok(Settings {
+            rows,
+            columns,
+            emit_every,
+            decay,
+            normalization,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Heatmap {
+    rows: usize,
+    columns: usize,
+    /// The (decayed) number of detections in each cell, in row-major order.
+    counts: Vec<f32>,
+    /// The normalized heatmap that was last emitted.
+    latest: Vec<f32>,
+    frames: u64,
+}
+
+impl Heatmap {
+    fn new(settings: &Settings) -> Self {
+        let rows = settings.rows as usize;
+        let columns = settings.columns as usize;
+
+        Heatmap {
+            rows,
+            columns,
+            counts: vec![0.0; rows * columns],
+            latest: vec![0.0; rows * columns],
+            frames: 0,
+        }
+    }
+
+    /// Add a frame's detections to the heatmap, returning the normalized
+    /// heatmap to emit.
+    fn update(
+        &mut self,
+        centres: &[(f32, f32)],
+        settings: &Settings,
+    ) -> &[f32] {
+        if (self.rows, self.columns)
+            != (settings.rows as usize, settings.columns as usize)
+        {
+            // The arguments changed, so the old heatmap is meaningless
+            *self = Heatmap::new(settings);
+        }
+
+        if settings.decay < 1.0 {
+            self.counts.iter_mut().for_each(|c| *c *= settings.decay);
+        }
+
+        for &(x, y) in centres {
+            if let Some(ix) = self.cell(x, y) {
+                self.counts[ix] += 1.0;
+            }
+        }
+
+        self.frames += 1;
+
+        if self.frames % u64::from(settings.emit_every) == 0 {
+            let scale = match settings.normalization {
+                Normalization::Max => {
+                    self.counts.iter().copied().fold(0.0, f32::max)
+                },
+                Normalization::Sum => self.counts.iter().sum(),
+            };
+
+            self.latest.copy_from_slice(&self.counts);
+
+            if scale > 0.0 {
+                self.latest.iter_mut().for_each(|c| *c /= scale);
+            }
+        }
+
+        &self.latest
+    }
+
+    /// Find the index of the cell containing `(x, y)`.
+    fn cell(&self, x: f32, y: f32) -> Option<usize> {
+        let range = 0.0..=1.0;
+
+        if !range.contains(&x) || !range.contains(&y) {
+            return None;
+        }
+
+        // Points on the bottom or right edge go in the last cell
+        let column = ((x * self.columns as f32) as usize).min(self.columns - 1);
+        let row = ((y * self.rows as f32) as usize).min(self.rows - 1);
+
+        Some(row * self.columns + column)
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::testing::{assert_slices_close, Tolerance};
+
+    const SETTINGS: Settings = Settings {
+        rows: 2,
+        columns: 2,
+        emit_every: 1,
+        decay: 1.0,
+        normalization: Normalization::Max,
+    };
+
+    #[test]
+    fn accumulate_detections_over_time() {
+        let mut heatmap = Heatmap::new(&SETTINGS);
+
+        heatmap.update(&[(0.25, 0.25), (0.75, 0.25)], &SETTINGS);
+        let got = heatmap.update(&[(0.25, 0.25), (0.75, 0.75)], &SETTINGS);
+
+        assert_slices_close(got, &[1.0, 0.5, 0.0, 0.5], Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn normalize_by_sum() {
+        let settings = Settings {
+            normalization: Normalization::Sum,
+            ..SETTINGS
+        };
+        let mut heatmap = Heatmap::new(&settings);
+
+        let got = heatmap.update(
+            &[(0.25, 0.25), (0.25, 0.25), (0.75, 0.75), (0.1, 0.9)],
+            &settings,
+        );
+
+        assert_slices_close(got, &[0.5, 0.0, 0.25, 0.25], Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn edges_and_out_of_bounds() {
+        let heatmap = Heatmap::new(&SETTINGS);
+
+        assert_eq!(heatmap.cell(0.0, 0.0), Some(0));
+        assert_eq!(heatmap.cell(1.0, 1.0), Some(3));
+        assert_eq!(heatmap.cell(1.5, 0.5), None);
+        assert_eq!(heatmap.cell(0.5, -0.1), None);
+        assert_eq!(heatmap.cell(f32::NAN, 0.5), None);
+    }
+
+    #[test]
+    fn old_detections_decay() {
+        let settings = Settings {
+            decay: 0.5,
+            ..SETTINGS
+        };
+        let mut heatmap = Heatmap::new(&settings);
+
+        heatmap.update(&[(0.25, 0.25)], &settings);
+        let got = heatmap.update(&[(0.75, 0.75)], &settings);
+
+        assert_slices_close(got, &[0.5, 0.0, 0.0, 1.0], Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn only_emit_periodically() {
+        let settings = Settings {
+            emit_every: 2,
+            ..SETTINGS
+        };
+        let mut heatmap = Heatmap::new(&settings);
+
+        let first = heatmap.update(&[(0.25, 0.25)], &settings).to_vec();
+        let second = heatmap.update(&[(0.75, 0.75)], &settings).to_vec();
+        let third = heatmap.update(&[(0.75, 0.75)], &settings).to_vec();
+
+        assert_eq!(first, [0.0; 4]);
+        assert_slices_close(&second, &[1.0, 0.0, 0.0, 1.0], Tolerance::DEFAULT);
+        assert_eq!(third, second);
+    }
+
+    #[test]
+    fn changing_the_shape_resets_the_heatmap() {
+        let mut heatmap = Heatmap::new(&SETTINGS);
+        heatmap.update(&[(0.25, 0.25)], &SETTINGS);
+
+        let settings = Settings {
+            rows: 1,
+            columns: 1,
+            ..SETTINGS
+        };
+        let got = heatmap.update(&[], &settings);
+
+        assert_eq!(got, [0.0]);
+    }
+}
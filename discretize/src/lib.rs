@@ -0,0 +1,496 @@
+//! Bin each column of a matrix of continuous features into integer bins.
+//!
+//! The bin edges are determined by the `strategy` argument,
+//!
+//! - `equal_width` - each column's range is split into `bins` bins of the
+//!   same width
+//! - `equal_frequency` - edges are placed at each column's quantiles, so
+//!   every bin contains roughly the same number of samples
+//! - `custom` - the same `edges` are used for every column
+//!
+//! Values which fall outside the edges are put in the first or last bin, so
+//! the output is always in the range `0..bins`. The edges are also emitted so
+//! the same binning can be reproduced later on.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::{Array2, ArrayView1, ArrayView2},
+    prelude::*,
+    runtime_v1::*,
+    BufferExt, SliceExt,
+};
+use std::cmp::Ordering;
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata = Metadata::new("Discretize", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("preprocessing");
+        metadata.add_tag("binning");
+        metadata.add_tag("analytics");
+
+        let strategy = ArgumentMetadata::new("strategy");
+        strategy.set_description("How the bin edges are chosen.");
+        strategy.set_default_value("equal_width");
+        strategy.add_hint(&interpret_as_string_in_enum(Strategy::NAMES));
+        metadata.add_argument(&strategy);
+
+        let bins = ArgumentMetadata::new("bins");
+        bins.set_description(
+            "The number of bins to use for the `equal_width` and `equal_frequency` strategies.",
+        );
+        bins.set_default_value("5");
+        bins.add_hint(&non_negative_number());
+        bins.add_hint(&supported_argument_type(ArgumentType::Integer));
+        metadata.add_argument(&bins);
+
+        let edges = ArgumentMetadata::new("edges");
+        edges.set_description(
+            "A comma-separated list of increasing bin edges, used by the `custom` strategy. `n` edges give `n - 1` bins.",
+        );
+        edges.add_hint(&supported_argument_type(ArgumentType::LongString));
+        metadata.add_argument(&edges);
+
+        let input = TensorMetadata::new("input");
+        input.set_description("The samples to discretize, one per row.");
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        input.add_hint(&hint);
+        metadata.add_input(&input);
+
+        let bins = TensorMetadata::new("bins");
+        bins.set_description(
+            "The index of the bin each value was put in. This has the same shape as `input`.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::U32],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        bins.add_hint(&hint);
+        metadata.add_output(&bins);
+
+        let edges = TensorMetadata::new("edges");
+        edges.set_description(
+            "A `[columns, bins + 1]` tensor containing the edges used for each column.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        edges.add_hint(&hint);
+        metadata.add_output(&edges);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        let strategy = Strategy::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "input",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "bins",
+            ElementType::U32,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "edges",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0, strategy.bins() as u32 + 1]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let strategy = Strategy::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("input").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "input".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::F64 {
+            return Err(invalid_input(format!(
+                "Expected a f64 tensor, found {}",
+                element_type
+            )));
+        }
+
+        let input: ArrayView2<f64> =
+            buffer.try_view(&dimensions).map_err(invalid_input)?;
+
+        if input.nrows() == 0 {
+            return Err(invalid_input("No samples to discretize"));
+        }
+
+        let discretizer = Discretizer::fit(&strategy, input);
+        let bins = discretizer.transform(input);
+        let edges = discretizer.edges();
+        let (columns, edge_count) = edges.dim();
+
+        ctx.set_output_tensor(
+            "bins",
+            TensorParam {
+                element_type: ElementType::U32,
+                dimensions: &dimensions,
+                buffer: bins.as_slice().unwrap().as_bytes(),
+            },
+        );
+        ctx.set_output_tensor(
+            "edges",
+            TensorParam {
+                element_type: ElementType::F64,
+                dimensions: &[columns as u32, edge_count as u32],
+                buffer: edges.as_slice().unwrap().as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "input".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+/// How the bin edges are chosen, as set by the `strategy`, `bins`, and
+/// `edges` arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Strategy {
+    EqualWidth { bins: usize },
+    EqualFrequency { bins: usize },
+    Custom { edges: Vec<f64> },
+}
+
+impl Strategy {
+    pub const NAMES: &'static [&'static str] =
+        &["equal_width", "equal_frequency", "custom"];
+
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let name = ctx.parse_argument_with_default::<_, E>(
+            "strategy",
+            String::from("equal_width"),
+        )?;
+
+        let bins = || -> Result<usize, E> {
+            let bins = ctx.parse_argument_with_default::<_, E>("bins", 5)?;
+
+            if bins == 0 {
+                return Err(E::invalid_argument(
+                    InvalidArgument::invalid_value(
+                        "bins",
+                        "Must be greater than zero",
+                    ),
+                ));
+            }
+
+            Ok(bins)
+        };
+
+        match name.as_str() {
+            "equal_width" => Ok(Strategy::EqualWidth { bins: bins()? }),
+            "equal_frequency" => {
+                Ok(Strategy::EqualFrequency { bins: bins()? })
+            },
+            "custom" => {
+                let edges = ctx.required_argument::<E>("edges")?;
+                let edges = parse_edges(&edges).map_err(|e| {
+                    E::invalid_argument(InvalidArgument::invalid_value(
+                        "edges", e,
+                    ))
+                })?;
+                Ok(Strategy::Custom { edges })
+            },
+            _ => Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "strategy",
+                format!(
+                    "Unknown strategy, \"{}\". Expected one of {:?}",
+                    name,
+                    Strategy::NAMES
+                ),
+            ))),
+        }
+    }
+
+    /// The number of bins this strategy will produce.
+    pub fn bins(&self) -> usize {
+        match self {
+            Strategy::EqualWidth { bins }
+            | Strategy::EqualFrequency { bins } => *bins,
+            Strategy::Custom { edges } => edges.len() - 1,
+        }
+    }
+}
+
+fn parse_edges(s: &str) -> Result<Vec<f64>, String> {
+    let edges = s
+        .split(',')
+        .map(str::trim)
+        .filter(|edge| !edge.is_empty())
+        .map(|edge| {
+            edge.parse::<f64>()
+                .map_err(|e| format!("Unable to parse \"{}\": {}", edge, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if edges.len() < 2 {
+        return Err("At least 2 edges are needed to make a bin".to_string());
+    }
+
+    if let Some(pair) = edges.windows(2).find(|pair| pair[0] >= pair[1]) {
+        return Err(format!(
+            "The edges must be strictly increasing, but {} is followed by {}",
+            pair[0], pair[1]
+        ));
+    }
+
+    Ok(edges)
+}
+
+/// The bin edges for each column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discretizer {
+    pub edges: Vec<Vec<f64>>,
+}
+
+impl Discretizer {
+    pub fn fit(strategy: &Strategy, x: ArrayView2<'_, f64>) -> Self {
+        let edges = x
+            .columns()
+            .into_iter()
+            .map(|column| match strategy {
+                Strategy::EqualWidth { bins } => {
+                    equal_width_edges(column, *bins)
+                },
+                Strategy::EqualFrequency { bins } => {
+                    equal_frequency_edges(column, *bins)
+                },
+                Strategy::Custom { edges } => edges.clone(),
+            })
+            .collect();
+
+        Discretizer { edges }
+    }
+
+    pub fn features(&self) -> usize { self.edges.len() }
+
+    /// Put each value in its bin.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if `x` doesn't have [`Discretizer::features()`]
+    /// columns.
+    pub fn transform(&self, x: ArrayView2<'_, f64>) -> Array2<u32> {
+        assert_eq!(x.ncols(), self.features());
+
+        Array2::from_shape_fn(x.dim(), |(row, column)| {
+            bin_index(&self.edges[column], x[[row, column]]) as u32
+        })
+    }
+
+    /// The edges as a `[features, bins + 1]` array.
+    pub fn edges(&self) -> Array2<f64> {
+        let edge_count = self.edges.first().map_or(0, |e| e.len());
+        let values = self.edges.iter().flatten().copied().collect();
+
+        Array2::from_shape_vec((self.features(), edge_count), values).unwrap()
+    }
+}
+
+fn equal_width_edges(column: ArrayView1<'_, f64>, bins: usize) -> Vec<f64> {
+    let (min, max) = column
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &value| {
+            (min.min(value), max.max(value))
+        });
+    let width = (max - min) / bins as f64;
+
+    (0..=bins).map(|i| min + width * i as f64).collect()
+}
+
+fn equal_frequency_edges(
+    column: ArrayView1<'_, f64>,
+    bins: usize,
+) -> Vec<f64> {
+    let mut sorted = column.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+    (0..=bins)
+        .map(|i| quantile(&sorted, i as f64 / bins as f64))
+        .collect()
+}
+
+/// Get the `q`'th quantile of some sorted values, linearly interpolating
+/// between the two closest values (the same as `numpy.quantile()`).
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let position = q * (sorted.len() - 1) as f64;
+    let below = position.floor() as usize;
+    let above = position.ceil() as usize;
+    let fraction = position - below as f64;
+
+    sorted[below] + (sorted[above] - sorted[below]) * fraction
+}
+
+/// Find which bin a value falls in, where bin `i` covers the half-open
+/// interval `(edges[i], edges[i + 1]]` and the first bin also includes
+/// `edges[0]` (the same as `pandas.cut()`).
+///
+/// Values outside the edges go in the nearest bin, and a value which lands
+/// on several identical edges (e.g. a constant column) goes in the first of
+/// them.
+fn bin_index(edges: &[f64], value: f64) -> usize {
+    let bins = edges.len() - 1;
+    // Only the inner edges matter when deciding which bin to use
+    let inner = &edges[1..bins];
+
+    inner.partition_point(|&edge| edge < value)
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::ndarray;
+
+    #[test]
+    fn equal_width_bins() {
+        let x = ndarray::arr2(&[[0.0], [2.4], [5.0], [7.6], [10.0]]);
+        let strategy = Strategy::EqualWidth { bins: 4 };
+
+        let discretizer = Discretizer::fit(&strategy, x.view());
+
+        assert_eq!(discretizer.edges, vec![vec![0.0, 2.5, 5.0, 7.5, 10.0]]);
+        assert_eq!(
+            discretizer.transform(x.view()),
+            ndarray::arr2(&[[0], [0], [1], [3], [3]])
+        );
+    }
+
+    #[test]
+    fn equal_frequency_bins() {
+        let x = ndarray::arr2(&[[1.0], [2.0], [3.0], [4.0], [100.0]]);
+        let strategy = Strategy::EqualFrequency { bins: 2 };
+
+        let discretizer = Discretizer::fit(&strategy, x.view());
+
+        assert_eq!(discretizer.edges, vec![vec![1.0, 3.0, 100.0]]);
+        assert_eq!(
+            discretizer.transform(x.view()),
+            ndarray::arr2(&[[0], [0], [0], [1], [1]])
+        );
+    }
+
+    #[test]
+    fn each_column_gets_its_own_edges() {
+        let x = ndarray::arr2(&[[0.0, 10.0], [1.0, 30.0]]);
+        let strategy = Strategy::EqualWidth { bins: 2 };
+
+        let edges = Discretizer::fit(&strategy, x.view()).edges();
+
+        assert_eq!(
+            edges,
+            ndarray::arr2(&[[0.0, 0.5, 1.0], [10.0, 20.0, 30.0]])
+        );
+    }
+
+    #[test]
+    fn custom_edges_clip_out_of_range_values() {
+        let x = ndarray::arr2(&[[-5.0], [0.0], [0.5], [1.0], [2.0], [99.0]]);
+        let strategy = Strategy::Custom {
+            edges: vec![0.0, 1.0, 2.0],
+        };
+
+        let bins = Discretizer::fit(&strategy, x.view()).transform(x.view());
+
+        assert_eq!(bins, ndarray::arr2(&[[0], [0], [0], [0], [1], [1]]));
+    }
+
+    #[test]
+    fn constant_columns_use_the_first_bin() {
+        let x = ndarray::arr2(&[[3.0], [3.0]]);
+        let strategy = Strategy::EqualWidth { bins: 3 };
+
+        let bins = Discretizer::fit(&strategy, x.view()).transform(x.view());
+
+        assert_eq!(bins, ndarray::arr2(&[[0], [0]]));
+    }
+
+    #[test]
+    fn parse_custom_edges() {
+        assert_eq!(parse_edges("0, 1.5,10").unwrap(), vec![0.0, 1.5, 10.0]);
+        assert!(parse_edges("1").is_err());
+        assert!(parse_edges("1, 1, 2").is_err());
+        assert!(parse_edges("1, x").is_err());
+    }
+}
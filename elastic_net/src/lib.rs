@@ -5,7 +5,9 @@ use crate::proc_block_v1::{
     BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
     InvalidInput, KernelError,
 };
-use hotg_rune_proc_blocks::{runtime_v1::*, BufferExt, SliceExt};
+use hotg_rune_proc_blocks::{
+    prelude::*, runtime_v1::*, BufferExt, SliceExt, StringBuilder,
+};
 
 wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
 
@@ -24,6 +26,14 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         metadata.add_tag("linear modeling");
         metadata.add_tag("analytics");
 
+        let residuals = ArgumentMetadata::new("residuals");
+        residuals.set_description(
+            "Also output the residuals (`y_true - y_pred`) for each row in `x_test`. This adds a `y_true` input.",
+        );
+        residuals.set_default_value("false");
+        residuals.add_hint(&interpret_as_string_in_enum(&["true", "false"]));
+        metadata.add_argument(&residuals);
+
         let x_train = TensorMetadata::new("x_train");
         let supported_types = [ElementType::F64];
         let hint =
@@ -43,6 +53,15 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         x_test.add_hint(&hint);
         metadata.add_input(&x_test);
 
+        let y_true = TensorMetadata::new("y_true");
+        y_true.set_description(
+            "The true value for each row in `x_test`. Only used when `residuals` is enabled.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F64], DimensionsParam::Fixed(&[0]));
+        y_true.add_hint(&hint);
+        metadata.add_input(&y_true);
+
         let y_test = TensorMetadata::new("y_test");
         let supported_types = [ElementType::F64];
         let hint =
@@ -50,6 +69,26 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         y_test.add_hint(&hint);
         metadata.add_output(&y_test);
 
+        let model = TensorMetadata::new("model");
+        model.set_description(
+            "The fitted smartcore model, serialized as JSON.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::Utf8],
+            DimensionsParam::Fixed(&[1]),
+        );
+        model.add_hint(&hint);
+        metadata.add_output(&model);
+
+        let residuals = TensorMetadata::new("residuals");
+        residuals.set_description(
+            "The residual (`y_true - y_pred`) for each row in `x_test`. Only set when `residuals` is enabled.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F64], DimensionsParam::Fixed(&[0]));
+        residuals.add_hint(&hint);
+        metadata.add_output(&residuals);
+
         register_node(&metadata);
     }
 
@@ -81,6 +120,25 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             DimensionsParam::Fixed(&[0]),
         );
 
+        ctx.add_output_tensor(
+            "model",
+            ElementType::Utf8,
+            DimensionsParam::Fixed(&[1]),
+        );
+
+        if residuals_enabled::<_, GraphError>(&ctx)? {
+            ctx.add_input_tensor(
+                "y_true",
+                ElementType::F64,
+                DimensionsParam::Fixed(&[0]),
+            );
+            ctx.add_output_tensor(
+                "residuals",
+                ElementType::F64,
+                DimensionsParam::Fixed(&[0]),
+            );
+        }
+
         Ok(())
     }
 
@@ -109,13 +167,15 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             })
         })?;
 
-        let output = transform(
-            &x_train.buffer.elements(),
+        let (output, model) = transform(
+            f64_elements("x_train", &x_train)?,
             &x_train.dimensions,
-            &y_train.buffer.elements(),
-            &x_test.buffer.elements(),
+            f64_elements("y_train", &y_train)?,
+            f64_elements("x_test", &x_test)?,
             &x_test.dimensions,
         );
+        let serialized = serde_json::to_string(&model)
+            .map_err(|e| KernelError::Other(e.to_string()))?;
 
         let y_test_dimension = [x_test.dimensions[0]];
 
@@ -128,17 +188,98 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             },
         );
 
+        let mut builder = StringBuilder::new();
+        builder.push(&serialized);
+        ctx.set_output_tensor(
+            "model",
+            TensorParam {
+                element_type: ElementType::Utf8,
+                dimensions: &[1],
+                buffer: &builder.finish(),
+            },
+        );
+
+        if residuals_enabled::<_, KernelError>(&ctx)? {
+            let y_true = ctx.get_input_tensor("y_true").ok_or_else(|| {
+                KernelError::InvalidInput(InvalidInput {
+                    name: "y_true".to_string(),
+                    reason: BadInputReason::NotFound,
+                })
+            })?;
+            if y_true.element_type != ElementType::F64 {
+                return Err(KernelError::InvalidInput(InvalidInput {
+                    name: "y_true".to_string(),
+                    reason: BadInputReason::InvalidValue(format!(
+                        "Expected a f64 tensor, found {}",
+                        y_true.element_type
+                    )),
+                }));
+            }
+
+            let residuals =
+                residuals(f64_elements("y_true", &y_true)?, &output)?;
+
+            ctx.set_output_tensor(
+                "residuals",
+                TensorParam {
+                    element_type: ElementType::F64,
+                    dimensions: &[residuals.len() as u32],
+                    buffer: residuals.as_bytes(),
+                },
+            );
+        }
+
         Ok(())
     }
 }
 
+fn residuals_enabled<C, E>(ctx: &C) -> Result<bool, E>
+where
+    C: ContextExt,
+    E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+{
+    ctx.parse_argument_with_default::<_, E>("residuals", false)
+}
+
+/// Get the difference between each true value and its prediction
+/// (`y_true - y_pred`).
+fn residuals(y_true: &[f64], y_pred: &[f64]) -> Result<Vec<f64>, KernelError> {
+    if y_true.len() != y_pred.len() {
+        return Err(KernelError::InvalidInput(InvalidInput {
+            name: "y_true".to_string(),
+            reason: BadInputReason::InvalidValue(format!(
+                "Expected {} values (one per row in \"x_test\"), found {}",
+                y_pred.len(),
+                y_true.len()
+            )),
+        }));
+    }
+
+    Ok(y_true.iter().zip(y_pred).map(|(t, p)| t - p).collect())
+}
+
+fn f64_elements<'a>(
+    name: &str,
+    tensor: &'a TensorResult,
+) -> Result<&'a [f64], KernelError> {
+    tensor
+        .buffer
+        .try_elements_with_shape(&tensor.dimensions)
+        .map_err(|e| {
+            KernelError::InvalidInput(InvalidInput {
+                name: name.to_string(),
+                reason: BadInputReason::Other(e.to_string()),
+            })
+        })
+}
+
 fn transform(
     x_train: &[f64],
     x_train_dim: &[u32],
     y_train: &[f64],
     x_test: &[f64],
     x_test_dim: &[u32],
-) -> Vec<f64> {
+) -> (Vec<f64>, ElasticNet<f64, DenseMatrix<f64>>) {
     // Iris data
     let x_train = DenseMatrix::from_array(
         x_train_dim[0] as usize,
@@ -158,7 +299,46 @@ fn transform(
 
     let y_hat = model.predict(&x_test).unwrap();
 
-    y_hat
+    (y_hat, model)
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
 }
 
 // comenting out test because it will in after deciaml places everytime so we
@@ -0,0 +1,315 @@
+use std::fmt::Display;
+
+use crate::proc_block_v1::*;
+
+use hotg_rune_proc_blocks::{
+    ndarray::{Array2, ArrayView1, ArrayView2},
+    runtime_v1::{self, *},
+    BufferExt, SliceExt, ValueType,
+};
+use num_traits::ToPrimitive;
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// The integer types that can be used as token indices.
+const INDEX_TYPES: &[&str] = &["u8", "u16", "u32", "i32", "u64", "i64"];
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Embedding Lookup", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.set_homepage(env!("CARGO_PKG_HOMEPAGE"));
+        metadata.add_tag("nlp");
+        metadata.add_tag("embedding");
+
+        let element_type = ArgumentMetadata::new("element_type");
+        element_type.set_description("The type used for token indices.");
+        element_type.set_default_value("u32");
+        let hint = runtime_v1::interpret_as_string_in_enum(INDEX_TYPES);
+        element_type.add_hint(&hint);
+        metadata.add_argument(&element_type);
+
+        let indices = TensorMetadata::new("indices");
+        indices.set_description("A 1D tensor of token indices.");
+        let hint = supported_shapes(
+            &[
+                ElementType::U8,
+                ElementType::U16,
+                ElementType::U32,
+                ElementType::I32,
+                ElementType::U64,
+                ElementType::I64,
+            ],
+            DimensionsParam::Fixed(&[0]),
+        );
+        indices.add_hint(&hint);
+        metadata.add_input(&indices);
+
+        let embeddings = TensorMetadata::new("embeddings");
+        embeddings.set_description(
+            "The `[vocab_size, dim]` embedding matrix, where row `i` is the vector for token `i`. This is typically loaded from a resource.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        embeddings.add_hint(&hint);
+        metadata.add_input(&embeddings);
+
+        let vectors = TensorMetadata::new("vectors");
+        vectors.set_description(
+            "A `[N, dim]` tensor containing the vector for each token.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        vectors.add_hint(&hint);
+        metadata.add_output(&vectors);
+
+        register_node(&metadata);
+    }
+
+    fn graph(node_id: String) -> Result<(), GraphError> {
+        let ctx = GraphContext::for_node(&node_id)
+            .ok_or(GraphError::MissingContext)?;
+
+        let element_type = get_element_type(|n| ctx.get_argument(n))
+            .map_err(GraphError::InvalidArgument)?;
+
+        ctx.add_input_tensor(
+            "indices",
+            element_type,
+            DimensionsParam::Fixed(&[0]),
+        );
+        ctx.add_input_tensor(
+            "embeddings",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "vectors",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(node_id: String) -> Result<(), KernelError> {
+        let ctx = KernelContext::for_node(&node_id)
+            .ok_or(KernelError::MissingContext)?;
+
+        let indices = ctx.get_input_tensor("indices").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput::not_found("indices"))
+        })?;
+        let embeddings = ctx.get_input_tensor("embeddings").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput::not_found("embeddings"))
+        })?;
+
+        if embeddings.element_type != ElementType::F32 {
+            return Err(KernelError::InvalidInput(InvalidInput::invalid_value(
+                "embeddings",
+                format!(
+                    "Expected a f32 tensor, found {:?}",
+                    embeddings.element_type
+                ),
+            )));
+        }
+
+        let embeddings = embeddings
+            .buffer
+            .try_view::<f32, _>(&embeddings.dimensions)
+            .map_err(|e| {
+                KernelError::InvalidInput(InvalidInput::invalid_value(
+                    "embeddings",
+                    e,
+                ))
+            })?;
+
+        let buffer = &indices.buffer;
+        let dimensions = &indices.dimensions;
+
+        let vectors = match indices.element_type {
+            ElementType::U8 => preprocess_buffer::<u8>(buffer, dimensions)
+                .and_then(|ix| embedding_lookup(embeddings, ix))?,
+            ElementType::U16 => preprocess_buffer::<u16>(buffer, dimensions)
+                .and_then(|ix| embedding_lookup(embeddings, ix))?,
+            ElementType::U32 => preprocess_buffer::<u32>(buffer, dimensions)
+                .and_then(|ix| embedding_lookup(embeddings, ix))?,
+            ElementType::I32 => preprocess_buffer::<i32>(buffer, dimensions)
+                .and_then(|ix| embedding_lookup(embeddings, ix))?,
+            ElementType::U64 => preprocess_buffer::<u64>(buffer, dimensions)
+                .and_then(|ix| embedding_lookup(embeddings, ix))?,
+            ElementType::I64 => preprocess_buffer::<i64>(buffer, dimensions)
+                .and_then(|ix| embedding_lookup(embeddings, ix))?,
+            other => {
+                return Err(KernelError::InvalidInput(
+                    InvalidInput::invalid_value(
+                        "indices",
+                        format!(
+                            "Token indices must be integers, found {:?}",
+                            other
+                        ),
+                    ),
+                ))
+            },
+        };
+
+        let (rows, columns) = vectors.dim();
+
+        ctx.set_output_tensor(
+            "vectors",
+            TensorParam {
+                element_type: ElementType::F32,
+                dimensions: &[rows as u32, columns as u32],
+                buffer: vectors
+                    .as_slice()
+                    .expect("Freshly created arrays are contiguous")
+                    .as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn preprocess_buffer<'buf, T>(
+    buffer: &'buf [u8],
+    dimensions: &[u32],
+) -> Result<ArrayView1<'buf, T>, KernelError>
+where
+    T: ValueType,
+{
+    buffer
+        .try_view::<T, _>(dimensions)
+        .map_err(|e| {
+            KernelError::InvalidInput(InvalidInput::invalid_value(
+                "indices", e,
+            ))
+        })
+}
+
+/// Look up the row in `embeddings` for each token index.
+fn embedding_lookup<T>(
+    embeddings: ArrayView2<'_, f32>,
+    indices: ArrayView1<'_, T>,
+) -> Result<Array2<f32>, KernelError>
+where
+    T: ToPrimitive + Display + Copy,
+{
+    let (vocab_size, dim) = embeddings.dim();
+    let mut vectors = Array2::zeros((indices.len(), dim));
+
+    for (mut row, &index) in vectors.outer_iter_mut().zip(indices) {
+        let ix = index.to_usize().filter(|&ix| ix < vocab_size).ok_or_else(
+            || {
+                KernelError::InvalidInput(InvalidInput::invalid_value(
+                    "indices",
+                    format!(
+                        "{} is not a valid index into a vocabulary with {} tokens",
+                        index, vocab_size
+                    ),
+                ))
+            },
+        )?;
+
+        row.assign(&embeddings.row(ix));
+    }
+
+    Ok(vectors)
+}
+
+fn get_element_type(
+    get_argument: impl FnOnce(&str) -> Option<String>,
+) -> Result<ElementType, InvalidArgument> {
+    use hotg_rune_proc_blocks::common::element_type::{
+        parse_element_type, NAME,
+    };
+
+    match get_argument(NAME) {
+        Some(name) => parse_element_type(&name, INDEX_TYPES)
+            .map_err(|e| InvalidArgument::invalid_value(NAME, e)),
+        None => Ok(ElementType::U32),
+    }
+}
+
+impl InvalidArgument {
+    fn invalid_value(name: impl Into<String>, reason: impl Display) -> Self {
+        InvalidArgument {
+            name: name.into(),
+            reason: BadArgumentReason::InvalidValue(reason.to_string()),
+        }
+    }
+}
+
+impl InvalidInput {
+    fn not_found(name: impl Into<String>) -> Self {
+        InvalidInput {
+            name: name.into(),
+            reason: BadInputReason::NotFound,
+        }
+    }
+
+    fn invalid_value(name: impl Into<String>, reason: impl Display) -> Self {
+        InvalidInput {
+            name: name.into(),
+            reason: BadInputReason::InvalidValue(reason.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::ndarray;
+
+    #[test]
+    fn look_up_each_token() {
+        let embeddings =
+            ndarray::arr2(&[[0.0_f32, 0.0], [1.0, 2.0], [3.0, 4.0]]);
+        let indices = ndarray::arr1(&[2_u32, 0, 2, 1]);
+
+        let got = embedding_lookup(embeddings.view(), indices.view()).unwrap();
+
+        let should_be =
+            ndarray::arr2(&[[3.0, 4.0], [0.0, 0.0], [3.0, 4.0], [1.0, 2.0]]);
+        assert_eq!(got, should_be);
+    }
+
+    #[test]
+    fn empty_input_gives_empty_output() {
+        let embeddings = ndarray::arr2(&[[1.0_f32, 2.0, 3.0]]);
+        let indices = ndarray::arr1::<u32>(&[]);
+
+        let got = embedding_lookup(embeddings.view(), indices.view()).unwrap();
+
+        assert_eq!(got.dim(), (0, 3));
+    }
+
+    #[test]
+    fn out_of_range_indices_are_an_error() {
+        let embeddings = ndarray::arr2(&[[1.0_f32], [2.0]]);
+        let indices = ndarray::arr1(&[0_i64, 2]);
+
+        let error =
+            embedding_lookup(embeddings.view(), indices.view()).unwrap_err();
+
+        assert!(matches!(error, KernelError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn negative_indices_are_an_error() {
+        let embeddings = ndarray::arr2(&[[1.0_f32], [2.0]]);
+        let indices = ndarray::arr1(&[-1_i32]);
+
+        let error =
+            embedding_lookup(embeddings.view(), indices.view()).unwrap_err();
+
+        assert!(matches!(error, KernelError::InvalidInput(_)));
+    }
+}
@@ -0,0 +1,321 @@
+//! Helpers for tests which wire several proc-blocks together.
+//!
+//! Each proc-block's unit tests only exercise its own transform, so nothing
+//! checks that one proc-block's outputs actually line up with the next
+//! proc-block's inputs. These helpers load the compiled WebAssembly modules
+//! and pass tensors between them the same way the Rune runtime would, so a
+//! renamed tensor or a changed element type shows up as a test failure.
+//!
+//! The modules are read from `target/proc-blocks/` (or the directory in the
+//! `PROC_BLOCK_DIR` environment variable), so you will need to compile them
+//! first:
+//!
+//! ```console
+//! $ cargo xtask dist
+//! $ cargo test --package proc-block-examples -- --ignored
+//! ```
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Error};
+use xtask::runtime::{
+    Dimension, Dimensions, ElementType, NodeInfo, Runtime, Tensor, TensorInfo,
+};
+
+/// The directory containing the compiled proc-blocks.
+pub fn proc_block_dir() -> PathBuf {
+    match std::env::var_os("PROC_BLOCK_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join("target")
+            .join("proc-blocks"),
+    }
+}
+
+/// A proc-block which has been loaded and had its `graph()` function called.
+pub struct Node {
+    name: String,
+    runtime: Runtime,
+    arguments: HashMap<String, String>,
+    info: NodeInfo,
+}
+
+impl Node {
+    /// Load a compiled proc-block (e.g. `"train_test_split"`) and run its
+    /// `graph()` function with the provided arguments.
+    pub fn load(name: &str, arguments: &[(&str, &str)]) -> Result<Self, Error> {
+        let filename = proc_block_dir().join(format!("{}.wasm", name));
+        let wasm = std::fs::read(&filename).with_context(|| {
+            format!(
+                "Unable to read \"{}\". Did you run \"cargo xtask dist\"?",
+                filename.display()
+            )
+        })?;
+
+        let mut runtime = Runtime::load(&wasm)
+            .with_context(|| format!("Unable to load \"{}\"", name))?;
+
+        let arguments: HashMap<String, String> = arguments
+            .iter()
+            .map(|&(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+
+        let info = runtime.graph(arguments.clone()).with_context(|| {
+            format!("Unable to run graph() for \"{}\"", name)
+        })?;
+
+        Ok(Node {
+            name: name.to_string(),
+            runtime,
+            arguments,
+            info,
+        })
+    }
+
+    pub fn name(&self) -> &str { &self.name }
+
+    /// The inputs and outputs declared by `graph()`.
+    pub fn info(&self) -> &NodeInfo { &self.info }
+
+    /// Run the proc-block's `kernel()` function, making sure every declared
+    /// input is provided and every declared output is set.
+    pub fn run(
+        &mut self,
+        inputs: HashMap<String, Tensor>,
+    ) -> Result<HashMap<String, Tensor>, Error> {
+        for input in &self.info.inputs {
+            anyhow::ensure!(
+                inputs.contains_key(&input.name),
+                "No value was provided for \"{}\"'s \"{}\" input",
+                self.name,
+                input.name,
+            );
+        }
+
+        let outputs = self
+            .runtime
+            .kernel(self.arguments.clone(), inputs)
+            .with_context(|| {
+                format!("Unable to run kernel() for \"{}\"", self.name)
+            })?;
+
+        for output in &self.info.outputs {
+            let tensor = outputs.get(&output.name).with_context(|| {
+                format!(
+                    "\"{}\" declared a \"{}\" output but never set it",
+                    self.name, output.name,
+                )
+            })?;
+            anyhow::ensure!(
+                tensor.element_type == output.element_type,
+                "\"{}\" declared its \"{}\" output as {} but set it to {}",
+                self.name,
+                output.name,
+                output.element_type,
+                tensor.element_type,
+            );
+            anyhow::ensure!(
+                accepts(&output.dimensions, &tensor.dimensions),
+                "\"{}\" declared its \"{}\" output as {:?} but set it to {:?}",
+                self.name,
+                output.name,
+                output.dimensions,
+                tensor.dimensions,
+            );
+        }
+
+        Ok(outputs)
+    }
+
+    fn input(&self, name: &str) -> Result<&TensorInfo, Error> {
+        find(&self.info.inputs, name).with_context(|| {
+            format!("\"{}\" has no \"{}\" input", self.name, name)
+        })
+    }
+
+    fn output(&self, name: &str) -> Result<&TensorInfo, Error> {
+        find(&self.info.outputs, name).with_context(|| {
+            format!("\"{}\" has no \"{}\" output", self.name, name)
+        })
+    }
+}
+
+fn find<'a>(tensors: &'a [TensorInfo], name: &str) -> Option<&'a TensorInfo> {
+    tensors.iter().find(|t| t.name == name)
+}
+
+/// Check that one node's output can be passed to another node's input,
+/// according to the tensors they declared in `graph()`.
+pub fn check_connection(
+    from: &Node,
+    output: &str,
+    to: &Node,
+    input: &str,
+) -> Result<(), Error> {
+    let output = from.output(output)?;
+    let input = to.input(input)?;
+
+    anyhow::ensure!(
+        output.element_type == input.element_type,
+        "\"{}.{}\" is {} but \"{}.{}\" expects {}",
+        from.name,
+        output.name,
+        output.element_type,
+        to.name,
+        input.name,
+        input.element_type,
+    );
+
+    anyhow::ensure!(
+        compatible(&output.dimensions, &input.dimensions),
+        "\"{}.{}\" has the shape {:?} but \"{}.{}\" expects {:?}",
+        from.name,
+        output.name,
+        output.dimensions,
+        to.name,
+        input.name,
+        input.dimensions,
+    );
+
+    Ok(())
+}
+
+/// Could a tensor with these dimensions be passed to something that was
+/// declared with `declared`?
+fn accepts(declared: &Dimensions, dimensions: &[u32]) -> bool {
+    match declared {
+        Dimensions::Dynamic => true,
+        Dimensions::Fixed(declared) => {
+            declared.len() == dimensions.len()
+                && declared.iter().zip(dimensions).all(|(d, &actual)| match d {
+                    Dimension::Fixed(fixed) => fixed.get() == actual as usize,
+                    Dimension::Dynamic => true,
+                })
+        },
+    }
+}
+
+/// Could some tensors declared with `output`'s dimensions be passed to
+/// something declared with `input`'s dimensions?
+fn compatible(output: &Dimensions, input: &Dimensions) -> bool {
+    match (output, input) {
+        (Dimensions::Fixed(output), Dimensions::Fixed(input)) => {
+            output.len() == input.len()
+                && output.iter().zip(input).all(|pair| match pair {
+                    (Dimension::Fixed(a), Dimension::Fixed(b)) => a == b,
+                    _ => true,
+                })
+        },
+        _ => true,
+    }
+}
+
+/// Pass some of a node's outputs to the inputs of another node, checking the
+/// connection first.
+///
+/// Each pair is the name of an output on `from` and the name of the input on
+/// `to` it should be passed to.
+pub fn connect(
+    outputs: &HashMap<String, Tensor>,
+    from: &Node,
+    to: &Node,
+    pairs: &[(&str, &str)],
+) -> Result<HashMap<String, Tensor>, Error> {
+    let mut inputs = HashMap::new();
+
+    for &(output, input) in pairs {
+        check_connection(from, output, to, input)?;
+        let tensor = outputs.get(output).with_context(|| {
+            format!("\"{}\" didn't set its \"{}\" output", from.name, output)
+        })?;
+        inputs.insert(input.to_string(), tensor.clone());
+    }
+
+    Ok(inputs)
+}
+
+/// A type which can be stored in a [`Tensor`].
+pub trait Element: Copy {
+    const ELEMENT_TYPE: ElementType;
+    const SIZE: usize = std::mem::size_of::<Self>();
+
+    fn to_le_bytes(self) -> Vec<u8>;
+    fn from_le_bytes(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_element {
+    ($($type:ty => $element_type:ident),* $(,)?) => {
+        $(
+            impl Element for $type {
+                const ELEMENT_TYPE: ElementType = ElementType::$element_type;
+
+                fn to_le_bytes(self) -> Vec<u8> {
+                    <$type>::to_le_bytes(self).to_vec()
+                }
+
+                fn from_le_bytes(bytes: &[u8]) -> Self {
+                    <$type>::from_le_bytes(bytes.try_into().unwrap())
+                }
+            }
+        )*
+    };
+}
+
+impl_element!(u8 => U8, u32 => U32, f32 => F32, f64 => F64);
+
+/// Create a tensor from some values.
+pub fn tensor<T: Element>(dimensions: &[u32], values: &[T]) -> Tensor {
+    assert_eq!(
+        dimensions.iter().product::<u32>() as usize,
+        values.len(),
+        "The dimensions don't match the number of values",
+    );
+
+    Tensor {
+        element_type: T::ELEMENT_TYPE,
+        dimensions: dimensions.to_vec(),
+        buffer: values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+    }
+}
+
+/// Read a tensor's values, making sure it has the expected element type.
+pub fn values<T: Element>(tensor: &Tensor) -> Result<Vec<T>, Error> {
+    anyhow::ensure!(
+        tensor.element_type == T::ELEMENT_TYPE,
+        "Expected a {} tensor, found {}",
+        T::ELEMENT_TYPE,
+        tensor.element_type,
+    );
+
+    Ok(tensor.buffer.chunks_exact(T::SIZE).map(T::from_le_bytes).collect())
+}
+
+/// Read the strings from a UTF-8 tensor, where each string is prefixed by its
+/// length as a little-endian `u32`.
+pub fn strings(tensor: &Tensor) -> Result<Vec<String>, Error> {
+    anyhow::ensure!(
+        tensor.element_type == ElementType::Utf8,
+        "Expected a utf8 tensor, found {}",
+        tensor.element_type,
+    );
+
+    let mut strings = Vec::new();
+    let mut buffer = tensor.buffer.as_slice();
+
+    while !buffer.is_empty() {
+        anyhow::ensure!(buffer.len() >= 4, "Truncated string length");
+        let (length, rest) = buffer.split_at(4);
+        let length = u32::from_le_bytes(length.try_into().unwrap()) as usize;
+
+        anyhow::ensure!(rest.len() >= length, "Truncated string");
+        let (string, rest) = rest.split_at(length);
+        strings.push(std::str::from_utf8(string)?.to_string());
+        buffer = rest;
+    }
+
+    Ok(strings)
+}
@@ -0,0 +1,72 @@
+//! Train a classifier on part of the iris dataset and score its predictions,
+//! with every step running as a compiled proc-block.
+
+use std::collections::HashMap;
+
+use proc_block_examples::{connect, tensor, values, Node};
+
+/// The first 10 setosa (`0`) and versicolor (`1`) samples from the iris
+/// dataset, which are linearly separable.
+#[rustfmt::skip]
+const FEATURES: [f64; 80] = [
+    5.1, 3.5, 1.4, 0.2, 4.9, 3.0, 1.4, 0.2, 4.7, 3.2, 1.3, 0.2, 4.6, 3.1,
+    1.5, 0.2, 5.0, 3.6, 1.4, 0.2, 5.4, 3.9, 1.7, 0.4, 4.6, 3.4, 1.4, 0.3,
+    5.0, 3.4, 1.5, 0.2, 4.4, 2.9, 1.4, 0.2, 4.9, 3.1, 1.5, 0.1, 7.0, 3.2,
+    4.7, 1.4, 6.4, 3.2, 4.5, 1.5, 6.9, 3.1, 4.9, 1.5, 5.5, 2.3, 4.0, 1.3,
+    6.5, 2.8, 4.6, 1.5, 5.7, 2.8, 4.5, 1.3, 6.3, 3.3, 4.7, 1.6, 4.9, 2.4,
+    3.3, 1.0, 6.6, 2.9, 4.6, 1.3, 5.2, 2.7, 3.9, 1.4,
+];
+#[rustfmt::skip]
+const TARGETS: [f64; 20] = [
+    0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+    1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0,
+];
+
+#[test]
+#[ignore = "requires the compiled proc-blocks (cargo xtask dist)"]
+fn split_train_and_score() {
+    let mut split = Node::load("train_test_split", &[("test_size", "0.25")])
+        .unwrap();
+    let mut classifier = Node::load("logistic_regression", &[]).unwrap();
+    let mut metrics =
+        Node::load("metrics", &[("metrics", "accuracy,f1,precision,recall")])
+            .unwrap();
+
+    let mut inputs = HashMap::new();
+    inputs.insert("features".to_string(), tensor(&[20, 4], &FEATURES));
+    inputs.insert("targets".to_string(), tensor(&[20], &TARGETS));
+    let split_outputs = split.run(inputs).unwrap();
+
+    let inputs = connect(
+        &split_outputs,
+        &split,
+        &classifier,
+        &[
+            ("x_train", "x_train"),
+            ("y_train", "y_train"),
+            ("x_test", "x_test"),
+        ],
+    )
+    .unwrap();
+    let predictions = classifier.run(inputs).unwrap();
+
+    let mut inputs =
+        connect(&split_outputs, &split, &metrics, &[("y_test", "y_true")])
+            .unwrap();
+    inputs.extend(
+        connect(&predictions, &classifier, &metrics, &[("y_test", "y_pred")])
+            .unwrap(),
+    );
+    let scores = metrics.run(inputs).unwrap();
+
+    assert_eq!(values::<f64>(&scores["accuracy"]).unwrap(), vec![1.0]);
+    for name in ["f1", "precision", "recall"] {
+        assert_eq!(scores[name].dimensions, vec![1], "{}", name);
+    }
+    // Precision and recall are meaningless when the test set only has one
+    // class, so only check the values when both classes made it in.
+    let y_true = values::<f64>(&split_outputs["y_test"]).unwrap();
+    if y_true.contains(&0.0) && y_true.contains(&1.0) {
+        assert_eq!(values::<f64>(&scores["f1"]).unwrap(), vec![1.0]);
+    }
+}
@@ -0,0 +1,253 @@
+//! Run an image through the vision proc-blocks and compare the detected
+//! objects against a checked-in golden file.
+//!
+//! There is no object detection model in this repository, so the model is
+//! stubbed out with a function that emits one detection per quadrant of the
+//! image. That is still enough to make sure `image_input`,
+//! `image-normalization`, `object_filter`, and `label` agree on tensor names,
+//! element types, and the `[1, rows, columns, channels]` image layout.
+//!
+//! If you change the image or the stub, regenerate the golden file by
+//! running the test with `UPDATE_GOLDEN=1`.
+
+use std::{collections::HashMap, fmt::Write as _, path::Path};
+
+use anyhow::{Context, Error};
+use proc_block_examples::{connect, strings, tensor, values, Node};
+use xtask::runtime::Tensor;
+
+/// An 8x8 image with a red, green, blue, and black quadrant.
+const IMAGE: &str = "tests/data/quadrants.ppm";
+const GOLDEN: &str = "tests/data/quadrants.golden";
+const LABELS: &str = "red\ngreen\nblue";
+const THRESHOLD: &str = "0.5";
+
+#[test]
+#[ignore = "requires the compiled proc-blocks (cargo xtask dist)"]
+fn detect_objects_in_an_image() {
+    let image = read_ppm(IMAGE).unwrap();
+    let height = image.dimensions[1].to_string();
+    let width = image.dimensions[2].to_string();
+
+    let mut input = Node::load(
+        "image_input",
+        &[
+            ("width", width.as_str()),
+            ("height", height.as_str()),
+            ("pixel_format", "rgb8"),
+        ],
+    )
+    .unwrap();
+    let mut normalization =
+        Node::load("image-normalization", &[("element_type", "u8")]).unwrap();
+    let mut filter =
+        Node::load("object_filter", &[("threshold", THRESHOLD)]).unwrap();
+    let mut label = Node::load("label", &[("wordlist", LABELS)]).unwrap();
+
+    let mut inputs = HashMap::new();
+    inputs.insert("image".to_string(), image);
+    let pixels = input.run(inputs).unwrap();
+
+    let inputs =
+        connect(&pixels, &input, &normalization, &[("output", "image")])
+            .unwrap();
+    let normalized = normalization.run(inputs).unwrap();
+
+    let mut inputs = HashMap::new();
+    inputs.insert(
+        "bounding_boxes".to_string(),
+        stub_model(&normalized["normalized_image"]).unwrap(),
+    );
+    let objects = filter.run(inputs).unwrap();
+
+    let detections = values::<f32>(&objects["normalized"]).unwrap();
+    let rows: Vec<&[f32]> = detections.chunks_exact(6).collect();
+    assert_eq!(objects["normalized"].dimensions, vec![rows.len() as u32, 6]);
+
+    // The detections store the label index as a float, so there's a bit of
+    // glue before they can be passed to the label proc-block.
+    let indices: Vec<u32> = rows.iter().map(|row| row[5] as u32).collect();
+    let mut inputs = HashMap::new();
+    inputs.insert(
+        "indices".to_string(),
+        tensor(&[indices.len() as u32], &indices),
+    );
+    let labels = label.run(inputs).unwrap();
+    let labels = strings(&labels["labels"]).unwrap();
+
+    let got: Vec<Detection> = labels
+        .into_iter()
+        .zip(rows)
+        .map(|(label, row)| Detection::new(label, row))
+        .collect();
+
+    check_golden(GOLDEN, &got).unwrap();
+}
+
+/// Pretend to be an object detection model by emitting one detection per
+/// quadrant, where each class's score is the mean of a colour channel.
+///
+/// Each row is `[x, y, height, width, max_confidence, red, green, blue]`.
+fn stub_model(image: &Tensor) -> Result<Tensor, Error> {
+    let pixels = values::<f32>(image)?;
+    let (rows, columns, channels) = match *image.dimensions {
+        [1, rows, columns, channels] => {
+            (rows as usize, columns as usize, channels as usize)
+        },
+        ref other => anyhow::bail!(
+            "Expected [1, rows, columns, channels], found {:?}",
+            other
+        ),
+    };
+    anyhow::ensure!(channels == 3, "Expected an RGB image");
+
+    let mut detections = Vec::new();
+
+    for (top, left) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+        let row_range = top * rows / 2..(top + 1) * rows / 2;
+        let column_range = left * columns / 2..(left + 1) * columns / 2;
+        let count = (row_range.len() * column_range.len()) as f32;
+
+        let mut scores = [0.0; 3];
+        for row in row_range {
+            for column in column_range.clone() {
+                let pixel = (row * columns + column) * channels;
+                for (channel, score) in scores.iter_mut().enumerate() {
+                    *score += pixels[pixel + channel] / count;
+                }
+            }
+        }
+
+        let x = (left as f32 + 0.5) / 2.0;
+        let y = (top as f32 + 0.5) / 2.0;
+        let confidence = scores.iter().copied().fold(0.0, f32::max);
+        detections.extend([x, y, 0.5, 0.5, confidence]);
+        detections.extend(scores);
+    }
+
+    let count = detections.len() as u32 / 8;
+    Ok(tensor(&[1, count, 8], &detections))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Detection {
+    label: String,
+    x: f32,
+    y: f32,
+    height: f32,
+    width: f32,
+    confidence: f32,
+}
+
+impl Detection {
+    fn new(label: String, row: &[f32]) -> Self {
+        Detection {
+            label,
+            x: row[0],
+            y: row[1],
+            height: row[2],
+            width: row[3],
+            confidence: row[4],
+        }
+    }
+
+    fn parse(line: &str) -> Result<Self, Error> {
+        let mut words = line.split_whitespace();
+        let label = words.next().context("Missing the label")?.to_string();
+        let mut numbers = words.map(|w| w.parse::<f32>());
+        let mut next = |name: &str| -> Result<f32, Error> {
+            numbers
+                .next()
+                .with_context(|| format!("Missing the {}", name))?
+                .with_context(|| format!("Invalid {}", name))
+        };
+
+        Ok(Detection {
+            label,
+            x: next("x")?,
+            y: next("y")?,
+            height: next("height")?,
+            width: next("width")?,
+            confidence: next("confidence")?,
+        })
+    }
+
+    fn approx_eq(&self, other: &Detection) -> bool {
+        let close = |a: f32, b: f32| (a - b).abs() <= 1e-5;
+
+        self.label == other.label
+            && close(self.x, other.x)
+            && close(self.y, other.y)
+            && close(self.height, other.height)
+            && close(self.width, other.width)
+            && close(self.confidence, other.confidence)
+    }
+}
+
+fn check_golden(path: &str, got: &[Detection]) -> Result<(), Error> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(path);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        let mut golden = String::from("# label x y height width confidence\n");
+        for d in got {
+            writeln!(
+                golden,
+                "{} {} {} {} {} {:.6}",
+                d.label, d.x, d.y, d.height, d.width, d.confidence
+            )?;
+        }
+        std::fs::write(&path, golden)?;
+        return Ok(());
+    }
+
+    let golden = std::fs::read_to_string(&path)
+        .with_context(|| format!("Unable to read \"{}\"", path.display()))?;
+    let expected = golden
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(Detection::parse)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    anyhow::ensure!(
+        expected.len() == got.len()
+            && expected.iter().zip(got).all(|(e, g)| e.approx_eq(g)),
+        "Expected {:#?}, got {:#?}",
+        expected,
+        got,
+    );
+
+    Ok(())
+}
+
+/// Read a binary (`P6`) PPM image as a `[1, rows, columns, 3]` tensor.
+fn read_ppm(path: &str) -> Result<Tensor, Error> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(path);
+    let bytes = std::fs::read(&path)
+        .with_context(|| format!("Unable to read \"{}\"", path.display()))?;
+
+    // The header is "P6", the width, height, and max value, separated by
+    // whitespace and followed by a single whitespace byte.
+    let mut rest = bytes.as_slice();
+    let mut fields = Vec::new();
+    for _ in 0..4 {
+        let start = rest
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .context("Truncated header")?;
+        rest = &rest[start..];
+        let end = rest
+            .iter()
+            .position(|b| b.is_ascii_whitespace())
+            .context("Truncated header")?;
+        fields.push(std::str::from_utf8(&rest[..end])?);
+        rest = &rest[end..];
+    }
+    let pixels = &rest[1..];
+
+    anyhow::ensure!(fields[0] == "P6", "Only binary PPM images are supported");
+    anyhow::ensure!(fields[3] == "255", "Only 8-bit images are supported");
+    let width: u32 = fields[1].parse()?;
+    let height: u32 = fields[2].parse()?;
+
+    Ok(tensor(&[1, height, width, 3], pixels))
+}
@@ -1,3 +1,5 @@
+//! **Deprecated:** use the `metrics` proc-block with `metrics=f1,precision,recall` instead.
+
 use smartcore::metrics::{f1::F1, precision::Precision, recall::Recall};
 
 use crate::proc_block_v1::{
@@ -18,6 +20,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
         metadata.set_homepage(env!("CARGO_PKG_HOMEPAGE"));
         metadata.add_tag("metric");
+        metadata.add_tag("deprecated");
         metadata.add_tag("analytics");
 
         let y_true = TensorMetadata::new("y_true");
@@ -105,10 +108,9 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             })
         })?;
 
-        let _ytrue: ndarray::ArrayView1<f64> = y_true
+        let y_true_values: ndarray::ArrayView1<f64> = y_true
             .buffer
-            .view(&y_true.dimensions)
-            .and_then(|t| t.into_dimensionality())
+            .try_view(&y_true.dimensions)
             .map_err(|e| {
                 KernelError::InvalidInput(InvalidInput {
                     name: "y_train".to_string(),
@@ -122,10 +124,9 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
                 reason: BadInputReason::NotFound,
             })
         })?;
-        let _ypred: ndarray::ArrayView1<f64> = y_pred
+        let y_pred_values: ndarray::ArrayView1<f64> = y_pred
             .buffer
-            .view(&y_pred.dimensions)
-            .and_then(|t| t.into_dimensionality())
+            .try_view(&y_pred.dimensions)
             .map_err(|e| {
                 KernelError::InvalidInput(InvalidInput {
                     name: "y_pred".to_string(),
@@ -141,42 +142,39 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             )));
         }
 
-        let metric = transform(
-            y_true.buffer.elements().to_vec(),
-            y_pred.buffer.elements().to_vec(),
-        )
-        .unwrap();
+        let metric =
+            transform(y_true_values.to_vec(), y_pred_values.to_vec()).unwrap();
 
-        let f1 = vec![metric.0];
+        let f1 = [metric.0];
 
         ctx.set_output_tensor(
             "f1_score",
             TensorParam {
                 element_type: ElementType::F64,
                 dimensions: &[1 as u32],
-                buffer: &f1.as_bytes(),
+                buffer: f1.as_bytes(),
             },
         );
 
-        let precision = vec![metric.1];
+        let precision = [metric.1];
 
         ctx.set_output_tensor(
             "precision",
             TensorParam {
                 element_type: ElementType::F64,
                 dimensions: &[1 as u32],
-                buffer: &precision.as_bytes(),
+                buffer: precision.as_bytes(),
             },
         );
 
-        let recall = vec![metric.2];
+        let recall = [metric.2];
 
         ctx.set_output_tensor(
             "recall",
             TensorParam {
                 element_type: ElementType::F64,
                 dimensions: &[1 as u32],
-                buffer: &recall.as_bytes(),
+                buffer: recall.as_bytes(),
             },
         );
 
@@ -204,6 +202,7 @@ fn transform(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use hotg_rune_proc_blocks::testing::{assert_close, Tolerance};
 
     #[test]
     fn check_f1() {
@@ -212,7 +211,7 @@ mod tests {
 
         let metric = transform(y_true, y_pred).unwrap();
 
-        assert_eq!(0.5714285714285715, metric.0);
+        assert_close(metric.0, 0.5714285714285715, Tolerance::DEFAULT);
     }
 
     #[test]
@@ -222,7 +221,7 @@ mod tests {
 
         let metric = transform(y_true, y_pred).unwrap();
 
-        assert_eq!(0.6666666666666666, metric.1);
+        assert_close(metric.1, 0.6666666666666666, Tolerance::DEFAULT);
     }
 
     #[test]
@@ -232,6 +231,6 @@ mod tests {
 
         let metric = transform(y_true, y_pred).unwrap();
 
-        assert_eq!(0.5, metric.2);
+        assert_close(metric.2, 0.5, Tolerance::DEFAULT);
     }
 }
@@ -0,0 +1,237 @@
+//! Map categorical strings to integer indices using the "hashing trick".
+//!
+//! Each value is hashed and the remainder after dividing by `buckets` is
+//! used as its index. Unlike the `label` and `one_hot` proc-blocks, there is
+//! no vocabulary to ship with the Rune and categories which have never been
+//! seen before still get a valid index, at the cost of unrelated categories
+//! occasionally sharing a bucket.
+//!
+//! Values are hashed with 64-bit FNV-1a over their UTF-8 bytes, so the
+//! indices are stable across platforms and easy to reproduce when preparing
+//! training data in another language.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    hash::fnv1a, prelude::*, runtime_v1::*, BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Feature Hash", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("preprocessing");
+        metadata.add_tag("categorical");
+        metadata.add_tag("feature extraction");
+        metadata.with_const_eval();
+
+        let buckets = ArgumentMetadata::new("buckets");
+        buckets.set_description(
+            "The number of distinct indices. Larger values mean fewer collisions between categories.",
+        );
+        buckets.set_default_value("1024");
+        buckets.add_hint(&non_negative_number());
+        buckets.add_hint(&supported_argument_type(ArgumentType::Integer));
+        metadata.add_argument(&buckets);
+
+        let input = TensorMetadata::new("input");
+        input.set_description("The categorical values to hash.");
+        let hint =
+            supported_shapes(&[ElementType::Utf8], DimensionsParam::Dynamic);
+        input.add_hint(&hint);
+        metadata.add_input(&input);
+
+        let output = TensorMetadata::new("indices");
+        output.set_description(
+            "The index (between 0 and `buckets - 1`) for each value. This has the same shape as `input`.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::U32], DimensionsParam::Dynamic);
+        output.add_hint(&hint);
+        metadata.add_output(&output);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = buckets::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "input",
+            ElementType::Utf8,
+            DimensionsParam::Dynamic,
+        );
+        ctx.add_output_tensor(
+            "indices",
+            ElementType::U32,
+            DimensionsParam::Dynamic,
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let buckets = buckets::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("input").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "input".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::Utf8 {
+            return Err(invalid_input(format!(
+                "Expected a utf8 tensor, found {}",
+                element_type
+            )));
+        }
+
+        let values = buffer.strings().map_err(invalid_input)?;
+        let indices: Vec<u32> = values
+            .iter()
+            .map(|value| feature_hash(value, buckets))
+            .collect();
+
+        ctx.set_output_tensor(
+            "indices",
+            TensorParam {
+                element_type: ElementType::U32,
+                dimensions: &dimensions,
+                buffer: indices.as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "input".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+fn buckets<C, E>(ctx: &C) -> Result<u32, E>
+where
+    C: ContextExt,
+    E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+{
+    let buckets = ctx.parse_argument_with_default::<_, E>("buckets", 1024)?;
+
+    if buckets == 0 {
+        return Err(E::invalid_argument(InvalidArgument::invalid_value(
+            "buckets",
+            "Must be greater than zero",
+        )));
+    }
+
+    Ok(buckets)
+}
+
+/// Get the bucket a value is hashed into.
+///
+/// # Panics
+///
+/// This will panic if `buckets` is zero.
+pub fn feature_hash(value: &str, buckets: u32) -> u32 {
+    (fnv1a(value.as_bytes()) % u64::from(buckets)) as u32
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_are_stable() {
+        let expected = 0xaf63_dc4c_8601_ec8c % u64::from(u32::MAX);
+        assert_eq!(u64::from(feature_hash("a", u32::MAX)), expected);
+        assert_eq!(feature_hash("red", 1024), feature_hash("red", 1024));
+    }
+
+    #[test]
+    fn indices_are_always_in_range() {
+        let values = ["", "red", "green", "blue", "🦀", "a longer value"];
+
+        for value in values {
+            assert!(feature_hash(value, 7) < 7);
+        }
+    }
+
+    #[test]
+    fn a_single_bucket_maps_everything_to_zero() {
+        assert_eq!(feature_hash("red", 1), 0);
+        assert_eq!(feature_hash("blue", 1), 0);
+    }
+
+    #[test]
+    fn distinct_values_usually_get_distinct_buckets() {
+        let values = ["red", "green", "blue", "cyan", "magenta", "yellow"];
+
+        let mut indices: Vec<u32> =
+            values.iter().map(|v| feature_hash(v, 1 << 20)).collect();
+        indices.sort_unstable();
+        indices.dedup();
+
+        assert_eq!(indices.len(), values.len());
+    }
+}
@@ -0,0 +1,178 @@
+//! [`Node`] implementations for each proc-block.
+
+use std::collections::HashMap;
+
+use hotg_rune_proc_blocks::ndarray::{Array1, ArrayView3};
+
+use crate::{ElementType, Node, OwnedTensor, TensorRef};
+
+fn single_input<'a>(
+    inputs: &[TensorRef<'a>],
+) -> Result<TensorRef<'a>, String> {
+    match *inputs {
+        [input] => Ok(input),
+        _ => Err(format!("Expected 1 input tensor, found {}", inputs.len())),
+    }
+}
+
+pub struct Softmax;
+
+impl Node for Softmax {
+    const OUTPUTS: usize = 1;
+
+    fn create(_: &HashMap<String, String>) -> Result<Self, String> {
+        Ok(Softmax)
+    }
+
+    fn run(
+        &mut self,
+        inputs: &[TensorRef<'_>],
+    ) -> Result<Vec<OwnedTensor>, String> {
+        let input = single_input(inputs)?;
+        let elements = input.elements::<f32>(ElementType::F32)?;
+        let mut output = Array1::from_vec(elements.to_vec());
+
+        softmax_core::softmax(output.view_mut());
+
+        Ok(vec![OwnedTensor::new(
+            ElementType::F32,
+            input.dimensions.to_vec(),
+            output.as_slice().expect("Always contiguous"),
+        )])
+    }
+}
+
+crate::export_node!(softmax => Softmax);
+
+pub struct Normalize;
+
+impl Node for Normalize {
+    const OUTPUTS: usize = 1;
+
+    fn create(_: &HashMap<String, String>) -> Result<Self, String> {
+        Ok(Normalize)
+    }
+
+    fn run(
+        &mut self,
+        inputs: &[TensorRef<'_>],
+    ) -> Result<Vec<OwnedTensor>, String> {
+        let input = single_input(inputs)?;
+
+        let output = match input.element_type {
+            ElementType::U8 => normalize_core::transform(
+                input.elements::<u8>(ElementType::U8)?,
+            ),
+            ElementType::I32 => normalize_core::transform(
+                input.elements::<i32>(ElementType::I32)?,
+            ),
+            ElementType::F32 => normalize_core::transform(
+                input.elements::<f32>(ElementType::F32)?,
+            ),
+            ElementType::F64 => normalize_core::transform(
+                input.elements::<f64>(ElementType::F64)?,
+            ),
+            other => {
+                return Err(format!("Unsupported element type, {:?}", other))
+            },
+        };
+        let output =
+            output.ok_or_else(|| "The input tensor was empty".to_string())?;
+
+        Ok(vec![OwnedTensor::new(
+            ElementType::F32,
+            input.dimensions.to_vec(),
+            &output,
+        )])
+    }
+}
+
+crate::export_node!(normalize => Normalize);
+
+pub struct ImageNormalization;
+
+impl Node for ImageNormalization {
+    const OUTPUTS: usize = 1;
+
+    fn create(_: &HashMap<String, String>) -> Result<Self, String> {
+        Ok(ImageNormalization)
+    }
+
+    fn run(
+        &mut self,
+        inputs: &[TensorRef<'_>],
+    ) -> Result<Vec<OwnedTensor>, String> {
+        let image = single_input(inputs)?;
+
+        match *image.dimensions {
+            [_, _, _, 1] | [_, _, _, 3] => {},
+            _ => {
+                return Err(format!(
+                    "Expected a [frames, rows, columns, channels] image, found {:?}",
+                    image.dimensions
+                ))
+            },
+        }
+
+        let output: Vec<f32> = image
+            .elements::<u8>(ElementType::U8)?
+            .iter()
+            .map(|&pixel| image_normalization_core::normalize(pixel))
+            .collect();
+
+        Ok(vec![OwnedTensor::new(
+            ElementType::F32,
+            image.dimensions.to_vec(),
+            &output,
+        )])
+    }
+}
+
+crate::export_node!(image_normalization => ImageNormalization);
+
+pub struct ObjectFilter {
+    threshold: f32,
+}
+
+impl Node for ObjectFilter {
+    const OUTPUTS: usize = 1;
+
+    fn create(arguments: &HashMap<String, String>) -> Result<Self, String> {
+        let threshold = match arguments.get("threshold") {
+            Some(t) => t
+                .parse()
+                .map_err(|e| format!("Invalid threshold: {}", e))?,
+            None => 0.7,
+        };
+
+        Ok(ObjectFilter { threshold })
+    }
+
+    fn run(
+        &mut self,
+        inputs: &[TensorRef<'_>],
+    ) -> Result<Vec<OwnedTensor>, String> {
+        let input = single_input(inputs)?;
+
+        let shape = match *input.dimensions {
+            [a, b, c] => (a as usize, b as usize, c as usize),
+            _ => {
+                return Err(format!(
+                    "Expected a 3D tensor of bounding boxes, found {:?}",
+                    input.dimensions
+                ))
+            },
+        };
+        let elements = input.elements::<f32>(ElementType::F32)?;
+        let bounding_boxes = ArrayView3::from_shape(shape, elements)
+            .map_err(|e| e.to_string())?;
+
+        let output =
+            object_filter_core::transform(bounding_boxes, self.threshold);
+        let rows = (output.len() / 6) as u32;
+
+        Ok(vec![OwnedTensor::new(ElementType::F32, vec![rows, 6], &output)])
+    }
+}
+
+crate::export_node!(object_filter => ObjectFilter);
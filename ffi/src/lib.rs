@@ -0,0 +1,422 @@
+//! A small C API for running proc-blocks directly from firmware, without
+//! needing a WebAssembly runtime.
+//!
+//! Every proc-block gets the same set of functions, prefixed with
+//! `hotg_<name>_`:
+//!
+//! - `create()` - create a node from a list of `key=value` arguments
+//! - `run()` - pass the input tensors to the node and get its outputs back
+//! - `last_error()` - get a message describing the most recent failure
+//! - `free()` - destroy the node and any outputs it owns
+//!
+//! Output tensors are owned by the node and remain valid until the next call
+//! to `run()` or `free()`.
+//!
+//! When `create()` fails there is no node to ask, so `hotg_last_error()`
+//! returns the reason the most recent `create()` call on the current thread
+//! failed.
+//!
+//! The matching header file is generated with `cargo xtask ffi`.
+
+mod blocks;
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    convert::TryFrom,
+    ffi::{CStr, CString},
+    fmt::{self, Display, Formatter},
+    os::raw::{c_char, c_int},
+};
+
+use hotg_rune_proc_blocks::ValueType;
+
+/// The type of each element in a tensor.
+///
+/// This uses the same numbering as the `element-type` enum in
+/// `runtime-v1.wit`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ElementType {
+    U8 = 0,
+    I8 = 1,
+    U16 = 2,
+    I16 = 3,
+    U32 = 4,
+    I32 = 5,
+    F32 = 6,
+    U64 = 7,
+    I64 = 8,
+    F64 = 9,
+    Utf8 = 10,
+}
+
+impl TryFrom<u32> for ElementType {
+    type Error = UnknownElementType;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        let element_type = match value {
+            0 => ElementType::U8,
+            1 => ElementType::I8,
+            2 => ElementType::U16,
+            3 => ElementType::I16,
+            4 => ElementType::U32,
+            5 => ElementType::I32,
+            6 => ElementType::F32,
+            7 => ElementType::U64,
+            8 => ElementType::I64,
+            9 => ElementType::F64,
+            10 => ElementType::Utf8,
+            other => return Err(UnknownElementType(other)),
+        };
+
+        Ok(element_type)
+    }
+}
+
+/// A tensor was passed in with an element type we don't know about.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UnknownElementType(pub u32);
+
+impl Display for UnknownElementType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown element type: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownElementType {}
+
+/// A `key=value` argument passed to a node when it is created.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Argument {
+    pub name: *const c_char,
+    pub value: *const c_char,
+}
+
+/// A description of a tensor which lives somewhere else in memory.
+///
+/// The element type is passed as a plain `u32` because C doesn't guarantee
+/// the size of an enum, and the caller could hand us any value.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Tensor {
+    /// One of the [`ElementType`] values.
+    pub element_type: u32,
+    pub dimensions: *const u32,
+    pub rank: usize,
+    pub data: *const u8,
+    pub data_len: usize,
+}
+
+impl Tensor {
+    /// # Safety
+    ///
+    /// The `dimensions` and `data` pointers must be valid for the lifetime
+    /// `'a`.
+    unsafe fn as_ref<'a>(&self) -> Result<TensorRef<'a>, UnknownElementType> {
+        Ok(TensorRef {
+            element_type: ElementType::try_from(self.element_type)?,
+            dimensions: slice_or_empty(self.dimensions, self.rank),
+            data: slice_or_empty(self.data, self.data_len),
+        })
+    }
+}
+
+/// A borrowed tensor, as seen by a [`Node`].
+#[derive(Debug, Copy, Clone)]
+pub struct TensorRef<'a> {
+    pub element_type: ElementType,
+    pub dimensions: &'a [u32],
+    pub data: &'a [u8],
+}
+
+impl<'a> TensorRef<'a> {
+    /// Reinterpret the tensor's data as a slice of `T`, making sure the
+    /// element type matches.
+    pub fn elements<T: ValueType>(
+        &self,
+        expected: ElementType,
+    ) -> Result<&'a [T], String> {
+        if self.element_type != expected {
+            return Err(format!(
+                "Expected a {:?} tensor, found {:?}",
+                expected, self.element_type
+            ));
+        }
+
+        // Safety: ValueTypes are plain old data, and align_to() makes sure
+        // the data is correctly aligned.
+        let (head, elements, tail) = unsafe { self.data.align_to::<T>() };
+
+        if !head.is_empty() || !tail.is_empty() {
+            return Err("The tensor's data isn't correctly aligned".to_string());
+        }
+
+        Ok(elements)
+    }
+}
+
+/// A tensor created by a [`Node`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnedTensor {
+    pub element_type: ElementType,
+    pub dimensions: Vec<u32>,
+    pub data: Vec<u8>,
+}
+
+impl OwnedTensor {
+    pub fn new<T: ValueType>(
+        element_type: ElementType,
+        dimensions: Vec<u32>,
+        elements: &[T],
+    ) -> Self {
+        // Safety: ValueTypes are plain old data, so they can always be
+        // viewed as bytes.
+        let (_, data, _) = unsafe { elements.align_to::<u8>() };
+
+        OwnedTensor {
+            element_type,
+            dimensions,
+            data: data.to_vec(),
+        }
+    }
+
+    fn as_tensor(&self) -> Tensor {
+        Tensor {
+            element_type: self.element_type as u32,
+            dimensions: self.dimensions.as_ptr(),
+            rank: self.dimensions.len(),
+            data: self.data.as_ptr(),
+            data_len: self.data.len(),
+        }
+    }
+}
+
+/// The operations each proc-block needs to implement to be exposed over FFI.
+pub trait Node: Sized {
+    /// The number of tensors returned from [`Node::run()`].
+    const OUTPUTS: usize;
+
+    fn create(arguments: &HashMap<String, String>) -> Result<Self, String>;
+
+    fn run(
+        &mut self,
+        inputs: &[TensorRef<'_>],
+    ) -> Result<Vec<OwnedTensor>, String>;
+}
+
+/// The object behind each opaque node pointer handed out to C.
+#[doc(hidden)]
+pub struct Handle<N> {
+    node: N,
+    outputs: Vec<OwnedTensor>,
+    last_error: Option<CString>,
+}
+
+#[doc(hidden)]
+pub mod __private {
+    use super::*;
+
+    pub use paste;
+
+    pub unsafe fn create<N: Node>(
+        arguments: *const Argument,
+        num_arguments: usize,
+    ) -> *mut Handle<N> {
+        let node = parse_arguments(arguments, num_arguments)
+            .map_err(|e| format!("Invalid argument: {}", e))
+            .and_then(|arguments| N::create(&arguments));
+
+        match node {
+            Ok(node) => {
+                set_create_error(None);
+                Box::into_raw(Box::new(Handle {
+                    node,
+                    outputs: Vec::new(),
+                    last_error: None,
+                }))
+            },
+            Err(e) => {
+                set_create_error(Some(e));
+                std::ptr::null_mut()
+            },
+        }
+    }
+
+    pub unsafe fn run<N: Node>(
+        handle: *mut Handle<N>,
+        inputs: *const Tensor,
+        num_inputs: usize,
+        outputs: *mut Tensor,
+        num_outputs: usize,
+    ) -> c_int {
+        let handle = match handle.as_mut() {
+            Some(h) => h,
+            None => return -1,
+        };
+
+        if outputs.is_null() || num_outputs < N::OUTPUTS {
+            handle.set_error(format!(
+                "Expected space for {} output tensors, found {}",
+                N::OUTPUTS,
+                num_outputs
+            ));
+            return -1;
+        }
+
+        let inputs: Result<Vec<TensorRef<'_>>, _> =
+            slice_or_empty(inputs, num_inputs)
+                .iter()
+                .map(|t| t.as_ref())
+                .collect();
+        let inputs = match inputs {
+            Ok(inputs) => inputs,
+            Err(e) => {
+                handle.set_error(e.to_string());
+                return -1;
+            },
+        };
+
+        match handle.node.run(&inputs) {
+            Ok(results) => {
+                handle.outputs = results;
+                handle.last_error = None;
+
+                let outputs =
+                    std::slice::from_raw_parts_mut(outputs, num_outputs);
+                for (dest, src) in outputs.iter_mut().zip(&handle.outputs) {
+                    *dest = src.as_tensor();
+                }
+
+                0
+            },
+            Err(e) => {
+                handle.set_error(e);
+                -1
+            },
+        }
+    }
+
+    pub unsafe fn last_error<N>(handle: *const Handle<N>) -> *const c_char {
+        match handle.as_ref() {
+            Some(Handle {
+                last_error: Some(msg),
+                ..
+            }) => msg.as_ptr(),
+            Some(_) => std::ptr::null(),
+            None => hotg_last_error(),
+        }
+    }
+
+    pub unsafe fn free<N>(handle: *mut Handle<N>) {
+        if !handle.is_null() {
+            drop(Box::from_raw(handle));
+        }
+    }
+}
+
+impl<N> Handle<N> {
+    fn set_error(&mut self, msg: String) {
+        self.last_error = Some(c_string(msg));
+    }
+}
+
+thread_local! {
+    static CREATE_ERROR: RefCell<Option<CString>> =
+        const { RefCell::new(None) };
+}
+
+fn set_create_error(msg: Option<String>) {
+    CREATE_ERROR.with(|e| *e.borrow_mut() = msg.map(c_string));
+}
+
+fn c_string(msg: String) -> CString {
+    CString::new(msg.replace('\0', " ")).expect("All nul bytes were replaced")
+}
+
+/// Get a message describing why the most recent `create()` call on this
+/// thread failed, or `NULL` if it succeeded.
+///
+/// The message remains valid until the next `create()` call on this thread.
+#[no_mangle]
+pub extern "C" fn hotg_last_error() -> *const c_char {
+    CREATE_ERROR.with(|e| match &*e.borrow() {
+        Some(msg) => msg.as_ptr(),
+        None => std::ptr::null(),
+    })
+}
+
+unsafe fn parse_arguments(
+    arguments: *const Argument,
+    num_arguments: usize,
+) -> Result<HashMap<String, String>, std::str::Utf8Error> {
+    let mut parsed = HashMap::new();
+
+    for arg in slice_or_empty(arguments, num_arguments) {
+        if arg.name.is_null() || arg.value.is_null() {
+            continue;
+        }
+
+        let name = CStr::from_ptr(arg.name).to_str()?;
+        let value = CStr::from_ptr(arg.value).to_str()?;
+        parsed.insert(name.to_string(), value.to_string());
+    }
+
+    Ok(parsed)
+}
+
+unsafe fn slice_or_empty<'a, T>(ptr: *const T, len: usize) -> &'a [T] {
+    if ptr.is_null() || len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(ptr, len)
+    }
+}
+
+/// Generate the `extern "C"` functions for a [`Node`].
+#[macro_export]
+macro_rules! export_node {
+    ($name:ident => $node:ty) => {
+        $crate::__private::paste::paste! {
+            #[no_mangle]
+            pub unsafe extern "C" fn [<hotg_ $name _create>](
+                arguments: *const $crate::Argument,
+                num_arguments: usize,
+            ) -> *mut $crate::Handle<$node> {
+                $crate::__private::create::<$node>(arguments, num_arguments)
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn [<hotg_ $name _run>](
+                node: *mut $crate::Handle<$node>,
+                inputs: *const $crate::Tensor,
+                num_inputs: usize,
+                outputs: *mut $crate::Tensor,
+                num_outputs: usize,
+            ) -> std::os::raw::c_int {
+                $crate::__private::run(
+                    node,
+                    inputs,
+                    num_inputs,
+                    outputs,
+                    num_outputs,
+                )
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn [<hotg_ $name _last_error>](
+                node: *const $crate::Handle<$node>,
+            ) -> *const std::os::raw::c_char {
+                $crate::__private::last_error(node)
+            }
+
+            #[no_mangle]
+            pub unsafe extern "C" fn [<hotg_ $name _free>](
+                node: *mut $crate::Handle<$node>,
+            ) {
+                $crate::__private::free(node)
+            }
+        }
+    };
+}
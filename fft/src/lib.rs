@@ -3,7 +3,9 @@ use std::fmt::Display;
 use crate::proc_block_v1::*;
 
 use hotg_rune_proc_blocks::{
+    prelude::*,
     runtime_v1::{self, *},
+    state::NodeState,
     BufferExt, SliceExt,
 };
 
@@ -21,6 +23,11 @@ wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
 
 struct ProcBlockV1;
 
+/// Building the mel filterbank is more expensive than the FFT itself for
+/// short windows, so each node keeps the last one it used around.
+static FILTERBANKS: NodeState<Option<(Filterbank, DMatrix<f64>)>> =
+    NodeState::new();
+
 impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
     fn register_metadata() {
         let metadata = Metadata::new("FFT", env!("CARGO_PKG_VERSION"));
@@ -32,7 +39,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         metadata.add_tag("stft");
         metadata.add_tag("frequency domain");
 
-        let sampling_rate = ArgumentMetadata::new("Sample Rate");
+        let sampling_rate = ArgumentMetadata::new("sampling_rate");
         sampling_rate.set_description("Sampling Rate");
         sampling_rate.set_default_value("16000");
         let hint =
@@ -40,21 +47,52 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         sampling_rate.add_hint(&hint);
         metadata.add_argument(&sampling_rate);
 
-        let bins = ArgumentMetadata::new("Bins");
-        bins.set_description("Intervals between samples in frequency domain");
-        bins.set_default_value("480");
+        let window_size = ArgumentMetadata::new("window_size");
+        window_size.set_description(
+            "The number of samples in each FFT window (previously called `bins`).",
+        );
+        window_size.set_default_value("480");
         let hint =
             runtime_v1::supported_argument_type(ArgumentType::UnsignedInteger);
-        bins.add_hint(&hint);
-        metadata.add_argument(&bins);
+        window_size.add_hint(&hint);
+        metadata.add_argument(&window_size);
 
-        let window_overlap = ArgumentMetadata::new("Window Overlap");
+        let window_overlap = ArgumentMetadata::new("window_overlap");
         window_overlap.set_description("Ratio of overlapped intervals.");
         window_overlap.set_default_value("0.6666667");
         let hint = runtime_v1::supported_argument_type(ArgumentType::Float);
         window_overlap.add_hint(&hint);
         metadata.add_argument(&window_overlap);
 
+        let power_spectrum_size = ArgumentMetadata::new("power_spectrum_size");
+        power_spectrum_size.set_description(
+            "The number of frequency bins in the spectrogram. This can't be more than `window_size / 2 + 1`.",
+        );
+        power_spectrum_size.set_default_value("241");
+        let hint =
+            runtime_v1::supported_argument_type(ArgumentType::UnsignedInteger);
+        power_spectrum_size.add_hint(&hint);
+        metadata.add_argument(&power_spectrum_size);
+
+        let frame_count = ArgumentMetadata::new("frame_count");
+        frame_count
+            .set_description("The number of time steps in the spectrogram.");
+        frame_count.set_default_value("49");
+        let hint =
+            runtime_v1::supported_argument_type(ArgumentType::UnsignedInteger);
+        frame_count.add_hint(&hint);
+        metadata.add_argument(&frame_count);
+
+        let filter_count = ArgumentMetadata::new("filter_count");
+        filter_count.set_description(
+            "The number of mel filters each time step is reduced to.",
+        );
+        filter_count.set_default_value("40");
+        let hint =
+            runtime_v1::supported_argument_type(ArgumentType::UnsignedInteger);
+        filter_count.add_hint(&hint);
+        metadata.add_argument(&filter_count);
+
         let input = TensorMetadata::new("audio");
         input.set_description("A 1D tensor of `i16` samples.");
         let hint = supported_shapes(
@@ -65,7 +103,9 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         metadata.add_input(&input);
 
         let output = TensorMetadata::new("output");
-        output.set_description("output signal after applying STFT");
+        output.set_description(
+            "output signal after applying STFT, with `frame_count * filter_count` values",
+        );
         let hint = supported_shapes(
             &[ElementType::U32],
             DimensionsParam::Fixed(&[1, 0]),
@@ -80,6 +120,8 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let ctx = GraphContext::for_node(&node_id)
             .ok_or(GraphError::MissingContext)?;
 
+        let settings = Settings::from_context::<_, GraphError>(&ctx)?;
+
         ctx.add_input_tensor(
             "audio",
             ElementType::I16,
@@ -88,9 +130,15 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         ctx.add_output_tensor(
             "output",
             ElementType::F32,
-            DimensionsParam::Fixed(&[1, 0]),
+            DimensionsParam::Fixed(&[1, settings.output_len() as u32]),
         );
 
+        // Build the mel filterbank now so the first kernel() call doesn't
+        // have to
+        FILTERBANKS.with(&node_id, |cached| {
+            mel_filter_matrix(cached, settings.filterbank());
+        });
+
         Ok(())
     }
 
@@ -98,20 +146,13 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let ctx = KernelContext::for_node(&node_id)
             .ok_or(KernelError::MissingContext)?;
 
-        let sampling_rate =
-            get_u32_args("sampling_rate", |n| ctx.get_argument(n))
-                .map_err(KernelError::InvalidArgument)?;
-        let bins = get_u32_args("bins", |n| ctx.get_argument(n))
-            .map_err(KernelError::InvalidArgument)?;
-        let window_overlap =
-            get_f32_args("window_overlap", |n| ctx.get_argument(n))
-                .map_err(KernelError::InvalidArgument)?;
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
 
         let TensorResult {
             element_type,
             dimensions,
             buffer,
-        } = ctx.get_input_tensor("input").ok_or_else(|| {
+        } = ctx.get_input_tensor("audio").ok_or_else(|| {
             KernelError::InvalidInput(InvalidInput {
                 name: "audio".to_string(),
                 reason: BadInputReason::NotFound,
@@ -120,12 +161,23 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
 
         check_input_dimensions(&dimensions);
 
-        let input: Vec<i16> = buffer.elements().to_vec();
+        let input: Vec<i16> = buffer
+            .try_elements_with_shape(&dimensions)
+            .map_err(|e| {
+                KernelError::InvalidInput(InvalidInput {
+                    name: "audio".to_string(),
+                    reason: BadInputReason::InvalidValue(e.to_string()),
+                })
+            })?
+            .to_vec();
 
         let output = match element_type {
-            ElementType::I16 => {
-                transform_inner(input, sampling_rate, bins, window_overlap)
-            },
+            ElementType::I16 => FILTERBANKS.with(&node_id, |cached| {
+                let mel_filter_matrix =
+                    mel_filter_matrix(cached, settings.filterbank());
+
+                transform_inner(input, &settings, mel_filter_matrix)
+            }),
 
             other => {
                 return Err(KernelError::Other(format!(
@@ -150,7 +202,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             "output",
             TensorParam {
                 element_type: ElementType::F32,
-                dimensions: &dimensions,
+                dimensions: &[1, output.len() as u32],
                 buffer: &resulting_tensor,
             },
         );
@@ -169,80 +221,246 @@ fn check_input_dimensions(dimensions: &[u32]) {
     );
 }
 
-fn get_u32_args(
-    name: &str,
-    get_argument: impl FnOnce(&str) -> Option<String>,
-) -> Result<u32, InvalidArgument> {
-    get_argument(name)
-        .ok_or_else(|| InvalidArgument::not_found(name))?
-        .parse::<u32>()
-        .map_err(|e| InvalidArgument::invalid_value(name, e))
+/// The geometry of the spectrogram.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    sampling_rate: u32,
+    window_size: u32,
+    window_overlap: f32,
+    power_spectrum_size: u32,
+    frame_count: u32,
+    filter_count: u32,
 }
 
-fn get_f32_args(
-    name: &str,
-    get_argument: impl FnOnce(&str) -> Option<String>,
-) -> Result<f32, InvalidArgument> {
-    get_argument(name)
-        .ok_or_else(|| InvalidArgument::not_found(name))?
-        .parse::<f32>()
-        .map_err(|e| InvalidArgument::invalid_value(name, e))
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let defaults = Settings::default();
+
+        let sampling_rate = ctx.parse_argument_with_default::<_, E>(
+            "sampling_rate",
+            defaults.sampling_rate,
+        )?;
+        // "bins" is what the window size used to be called
+        let bins = ctx
+            .parse_argument_with_default::<_, E>("bins", defaults.window_size)?;
+        let window_size =
+            ctx.parse_argument_with_default::<_, E>("window_size", bins)?;
+        let window_overlap = ctx.parse_argument_with_default::<_, E>(
+            "window_overlap",
+            defaults.window_overlap,
+        )?;
+        let power_spectrum_size = ctx.parse_argument_with_default::<_, E>(
+            "power_spectrum_size",
+            defaults.power_spectrum_size,
+        )?;
+        let frame_count = ctx.parse_argument_with_default::<_, E>(
+            "frame_count",
+            defaults.frame_count,
+        )?;
+        let filter_count = ctx.parse_argument_with_default::<_, E>(
+            "filter_count",
+            defaults.filter_count,
+        )?;
+
+        let invalid = |name: &str, reason: String| {
+            E::invalid_argument(InvalidArgument::invalid_value(name, reason))
+        };
+
+        for (name, value) in [
+            ("sampling_rate", sampling_rate),
+            ("window_size", window_size),
+            ("power_spectrum_size", power_spectrum_size),
+            ("frame_count", frame_count),
+            ("filter_count", filter_count),
+        ] {
+            if value == 0 {
+                return Err(invalid(name, "Must be greater than zero".into()));
+            }
+        }
+
+        if !(0.0..1.0).contains(&window_overlap) {
+            return Err(invalid(
+                "window_overlap",
+                format!(
+                    "The overlap must be between 0 and 1, found {}",
+                    window_overlap
+                ),
+            ));
+        }
+
+        let max_power_spectrum_size = window_size / 2 + 1;
+        if power_spectrum_size > max_power_spectrum_size {
+            return Err(invalid(
+                "power_spectrum_size",
+                format!(
+                    "A window of {} samples only has {} frequency bins, but {} were requested",
+                    window_size, max_power_spectrum_size, power_spectrum_size
+                ),
+            ));
+        }
+
+        if filter_count > power_spectrum_size {
+            return Err(invalid(
+                "filter_count",
+                format!(
+                    "Can't have more mel filters than frequency bins ({} > {})",
+                    filter_count, power_spectrum_size
+                ),
+            ));
+        }
+
+        Ok(Settings {
+            sampling_rate,
+            window_size,
+            window_overlap,
+            power_spectrum_size,
+            frame_count,
+            filter_count,
+        })
+    }
+
+    fn filterbank(&self) -> Filterbank {
+        Filterbank {
+            sample_rate: self.sampling_rate as usize,
+            window_size: self.window_size as usize,
+            power_spectrum_size: self.power_spectrum_size as usize,
+            filter_count: self.filter_count as usize,
+        }
+    }
+
+    /// The number of values in the output tensor.
+    fn output_len(&self) -> usize {
+        self.frame_count as usize * self.filter_count as usize
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            sampling_rate: 16000,
+            window_size: 480,
+            window_overlap: 0.6666667,
+            power_spectrum_size: 241,
+            frame_count: 49,
+            filter_count: 40,
+        }
+    }
 }
 
-impl InvalidArgument {
-    fn not_found(name: impl Into<String>) -> Self {
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl Display) -> Self {
         InvalidArgument {
-            name: name.into(),
-            reason: BadArgumentReason::NotFound,
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
         }
     }
 
-    fn invalid_value(name: impl Into<String>, reason: impl Display) -> Self {
+    fn invalid_value(name: &str, error: impl Display) -> Self {
         InvalidArgument {
-            name: name.into(),
-            reason: BadArgumentReason::InvalidValue(reason.to_string()),
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
         }
     }
 }
 
+/// The parameters used to build a mel filterbank.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Filterbank {
+    sample_rate: usize,
+    window_size: usize,
+    power_spectrum_size: usize,
+    filter_count: usize,
+}
+
+impl Filterbank {
+    /// Build the matrix which maps a power spectrum to the mel scale.
+    fn matrix(&self) -> DMatrix<f64> {
+        let mut matrix =
+            DMatrix::<f64>::zeros(self.filter_count, self.power_spectrum_size);
+
+        for (row, col, coefficient) in mel::enumerate_mel_scaling_matrix(
+            self.sample_rate,
+            self.window_size,
+            self.power_spectrum_size,
+            self.filter_count,
+        ) {
+            matrix[(row, col)] = coefficient;
+        }
+
+        matrix
+    }
+}
+
+/// Get the matrix for a [`Filterbank`], only rebuilding the cached copy when
+/// the parameters have changed.
+fn mel_filter_matrix(
+    cached: &mut Option<(Filterbank, DMatrix<f64>)>,
+    filterbank: Filterbank,
+) -> &DMatrix<f64> {
+    if !matches!(cached, Some((f, _)) if *f == filterbank) {
+        *cached = Some((filterbank, filterbank.matrix()));
+    }
+
+    let (_, matrix) = cached.as_ref().unwrap();
+    matrix
+}
+
 fn transform_inner(
     input: Vec<i16>,
-    sample_rate: u32,
-    bins: u32,
-    window_overlap: f32,
-) -> Option<[u32; 1960]> {
+    settings: &Settings,
+    mel_filter_matrix: &DMatrix<f64>,
+) -> Option<Vec<u32>> {
+    if input.is_empty() {
+        return None;
+    }
+
+    let frame_count = settings.frame_count as usize;
+    let power_spectrum_size = settings.power_spectrum_size as usize;
+
     // Build the spectrogram computation engine
-    let mut spectrograph = SpecOptionsBuilder::new(49, 241)
-        .set_window_fn(sonogram::hann_function)
-        .load_data_from_memory(input, sample_rate as u32)
-        .build();
+    let mut spectrograph =
+        SpecOptionsBuilder::new(frame_count, power_spectrum_size)
+            .set_window_fn(sonogram::hann_function)
+            .load_data_from_memory(input, settings.sampling_rate)
+            .build();
 
-    // Compute the spectrogram giving the number of bins in a window and the
-    // overlap between neighbour windows.
-    spectrograph.compute(bins as usize, window_overlap);
+    // Compute the spectrogram giving the number of samples in a window and
+    // the overlap between neighbour windows.
+    spectrograph
+        .compute(settings.window_size as usize, settings.window_overlap);
 
     let spectrogram = spectrograph.create_in_memory(false);
 
-    let filter_count: usize = 40;
-    let power_spectrum_size = 241;
-    let window_size = 480;
-    let sample_rate_usize: usize = 16000;
-
-    // build up the mel filter matrix
-    let mut mel_filter_matrix =
-        DMatrix::<f64>::zeros(filter_count, power_spectrum_size);
-    for (row, col, coefficient) in mel::enumerate_mel_scaling_matrix(
-        sample_rate_usize,
-        window_size,
-        power_spectrum_size,
-        filter_count,
-    ) {
-        mel_filter_matrix[(row, col)] = coefficient;
-    }
-
     let spectrogram = spectrogram.into_iter().map(f64::from);
     let power_spectrum_matrix_unflipped: DMatrix<f64> =
-        DMatrix::from_iterator(49, power_spectrum_size, spectrogram);
+        DMatrix::from_iterator(frame_count, power_spectrum_size, spectrogram);
     let power_spectrum_matrix_transposed =
         power_spectrum_matrix_unflipped.transpose();
     let mut power_spectrum_vec: Vec<_> =
@@ -250,7 +468,7 @@ fn transform_inner(
     power_spectrum_vec.reverse();
     let power_spectrum_matrix: DMatrix<f64> =
         DMatrix::from_rows(&power_spectrum_vec);
-    let mel_spectrum_matrix = &mel_filter_matrix * &power_spectrum_matrix;
+    let mel_spectrum_matrix = mel_filter_matrix * &power_spectrum_matrix;
     let mel_spectrum_matrix = mel_spectrum_matrix.map(libm::sqrt);
 
     let min_value = mel_spectrum_matrix
@@ -264,7 +482,7 @@ fn transform_inner(
         .iter()
         .fold(f64::NEG_INFINITY, |a, &b| a.max(b));
 
-    let res: Vec<u32> = mel_spectrum_matrix
+    let out = mel_spectrum_matrix
         .data
         .as_vec()
         .iter()
@@ -272,8 +490,6 @@ fn transform_inner(
         .map(|freq| freq as u32)
         .collect();
 
-    let mut out = [0; 1960];
-    out.copy_from_slice(&res[..1960]);
     Some(out)
 }
 
@@ -284,9 +500,41 @@ mod tests {
     #[test]
     fn it_works() {
         let input = [0; 16000].to_vec();
+        let settings = Settings::default();
+
+        let filterbank = settings.filterbank().matrix();
 
-        let got = transform_inner(input, 16000, 480, 0.6666667).unwrap();
+        let got = transform_inner(input, &settings, &filterbank).unwrap();
 
         assert_eq!(got.len(), 1960);
     }
+
+    #[test]
+    fn custom_spectrogram_geometry() {
+        let input = [0; 16000].to_vec();
+        let settings = Settings {
+            window_size: 512,
+            power_spectrum_size: 257,
+            frame_count: 98,
+            filter_count: 64,
+            ..Settings::default()
+        };
+
+        let filterbank = settings.filterbank().matrix();
+
+        let got = transform_inner(input, &settings, &filterbank).unwrap();
+
+        assert_eq!(got.len(), 98 * 64);
+        assert_eq!(got.len(), settings.output_len());
+    }
+
+    #[test]
+    fn filterbank_matches_the_power_spectrum() {
+        let filterbank = Settings::default().filterbank();
+
+        let matrix = filterbank.matrix();
+
+        assert_eq!(matrix.nrows(), filterbank.filter_count);
+        assert_eq!(matrix.ncols(), filterbank.power_spectrum_size);
+    }
 }
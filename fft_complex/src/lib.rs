@@ -0,0 +1,441 @@
+//! A raw discrete Fourier transform which keeps the phase.
+//!
+//! The `fft` proc-block turns audio into a spectrogram, which throws away
+//! each bin's phase. This proc-block returns the full complex spectrum
+//! instead, so it can be used for things like filtering in the frequency
+//! domain and transforming the signal back again.
+//!
+//! Complex tensors are passed around as interleaved `[re, im]` pairs (see
+//! [`hotg_rune_proc_blocks::Complex`]), so a complex tensor with `n` values
+//! per row has the dimensions `[rows, n, 2]`.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    common::element_type, prelude::*, runtime_v1::*, BufferExt, Complex,
+    SliceExt, ValueType,
+};
+use rustfft::{num_complex::Complex as FftComplex, FftDirection, FftNum};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Complex FFT", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("fft");
+        metadata.add_tag("frequency domain");
+        metadata.add_tag("complex");
+
+        let direction = ArgumentMetadata::new("direction");
+        direction.set_description(
+            "Whether to do a `forward` transform (time to frequency) or an `inverse` one (frequency to time). The inverse transform is scaled by `1/n` so the two round-trip.",
+        );
+        direction.set_default_value("forward");
+        let hint = interpret_as_string_in_enum(&["forward", "inverse"]);
+        direction.add_hint(&hint);
+        metadata.add_argument(&direction);
+
+        let signal_type = ArgumentMetadata::new("signal_type");
+        signal_type.set_description(
+            "Whether the input is a `real` signal with the dimensions `[rows, n]` or a `complex` one with the dimensions `[rows, n, 2]`.",
+        );
+        signal_type.set_default_value("real");
+        let hint = interpret_as_string_in_enum(&["real", "complex"]);
+        signal_type.add_hint(&hint);
+        metadata.add_argument(&signal_type);
+
+        let element_type = ArgumentMetadata::new("element_type");
+        element_type.set_description("The type of each component.");
+        element_type.set_default_value("f32");
+        element_type
+            .add_hint(&interpret_as_string_in_enum(element_type::FLOAT));
+        metadata.add_argument(&element_type);
+
+        let signal = TensorMetadata::new("signal");
+        signal.set_description(
+            "The signal to transform, where each row is transformed independently.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32, ElementType::F64],
+            DimensionsParam::Dynamic,
+        );
+        signal.add_hint(&hint);
+        metadata.add_input(&signal);
+
+        let spectrum = TensorMetadata::new("spectrum");
+        spectrum.set_description(
+            "The transformed rows, as interleaved `[re, im]` pairs.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32, ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0, 2]),
+        );
+        spectrum.add_hint(&hint);
+        metadata.add_output(&spectrum);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, GraphError>(&ctx)?;
+        let element_type = ctx.element_type::<GraphError>(
+            ElementType::F32,
+            element_type::FLOAT,
+        )?;
+
+        let input_dimensions: &[u32] = match settings.signal_type {
+            SignalType::Real => &[0, 0],
+            SignalType::Complex => &[0, 0, 2],
+        };
+
+        ctx.add_input_tensor(
+            "signal",
+            element_type,
+            DimensionsParam::Fixed(input_dimensions),
+        );
+        ctx.add_output_tensor(
+            "spectrum",
+            element_type,
+            DimensionsParam::Fixed(&[0, 0, 2]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("signal").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "signal".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        let (rows, length) = settings
+            .signal_type
+            .rows_and_length(&dimensions)
+            .map_err(invalid_signal)?;
+        let output_dimensions = [rows as u32, length as u32, 2];
+
+        match element_type {
+            ElementType::F32 => {
+                let signal =
+                    buffer.try_elements::<f32>().map_err(invalid_signal)?;
+                settings.check_length(signal, rows, length)?;
+                let spectrum = settings.transform(signal, length);
+                ctx.set_output_tensor(
+                    "spectrum",
+                    TensorParam {
+                        element_type,
+                        dimensions: &output_dimensions,
+                        buffer: Complex::to_interleaved(&spectrum).as_bytes(),
+                    },
+                );
+            },
+            ElementType::F64 => {
+                let signal =
+                    buffer.try_elements::<f64>().map_err(invalid_signal)?;
+                settings.check_length(signal, rows, length)?;
+                let spectrum = settings.transform(signal, length);
+                ctx.set_output_tensor(
+                    "spectrum",
+                    TensorParam {
+                        element_type,
+                        dimensions: &output_dimensions,
+                        buffer: Complex::to_interleaved(&spectrum).as_bytes(),
+                    },
+                );
+            },
+            other => {
+                return Err(invalid_signal(format!(
+                    "Expected f32 or f64, found {}",
+                    other
+                )))
+            },
+        }
+
+        Ok(())
+    }
+}
+
+fn invalid_signal(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "signal".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum SignalType {
+    Real,
+    Complex,
+}
+
+impl SignalType {
+    /// Get the number of rows and the number of values in each row.
+    fn rows_and_length(
+        self,
+        dimensions: &[u32],
+    ) -> Result<(usize, usize), String> {
+        let (rows, length) = match (self, dimensions) {
+            (SignalType::Real, &[rows, length]) => (rows, length),
+            (SignalType::Complex, &[rows, length, 2]) => (rows, length),
+            (SignalType::Real, _) => {
+                return Err(format!(
+                    "Expected a real signal with the dimensions [rows, n], found {:?}",
+                    dimensions
+                ))
+            },
+            (SignalType::Complex, _) => {
+                return Err(format!(
+                    "Expected a complex signal with the dimensions [rows, n, 2], found {:?}",
+                    dimensions
+                ))
+            },
+        };
+
+        if length == 0 {
+            return Err("The signal can't be empty".to_string());
+        }
+
+        Ok((rows as usize, length as usize))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    direction: FftDirection,
+    signal_type: SignalType,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let direction = ctx.parse_argument_with_default::<_, E>(
+            "direction",
+            String::from("forward"),
+        )?;
+        let signal_type = ctx.parse_argument_with_default::<_, E>(
+            "signal_type",
+            String::from("real"),
+        )?;
+
+        let direction = match direction.as_str() {
+            "forward" => FftDirection::Forward,
+            "inverse" => FftDirection::Inverse,
+            other => {
+                return Err(E::invalid_argument(
+                    InvalidArgument::invalid_value(
+                        "direction",
+                        format!("Unknown direction, \"{}\"", other),
+                    ),
+                ))
+            },
+        };
+
+        let signal_type = match signal_type.as_str() {
+            "real" => SignalType::Real,
+            "complex" => SignalType::Complex,
+            other => {
+                return Err(E::invalid_argument(
+                    InvalidArgument::invalid_value(
+                        "signal_type",
+                        format!("Unknown signal type, \"{}\"", other),
+                    ),
+                ))
+            },
+        };
+
+        Ok(Settings {
+            direction,
+            signal_type,
+        })
+    }
+
+    /// Make sure the signal contains `rows` rows of `length` values.
+    fn check_length<T>(
+        &self,
+        signal: &[T],
+        rows: usize,
+        length: usize,
+    ) -> Result<(), KernelError> {
+        let components = match self.signal_type {
+            SignalType::Real => 1,
+            SignalType::Complex => 2,
+        };
+        let expected = rows * length * components;
+
+        if signal.len() != expected {
+            return Err(invalid_signal(format!(
+                "Expected {} values, found {}",
+                expected,
+                signal.len()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Transform each `length`-long row of a real or interleaved complex
+    /// signal.
+    fn transform<T>(&self, signal: &[T], length: usize) -> Vec<Complex<T>>
+    where
+        T: FftNum + ValueType,
+        Complex<T>: ValueType,
+    {
+        let mut buffer: Vec<FftComplex<T>> = match self.signal_type {
+            SignalType::Real => signal
+                .iter()
+                .map(|&re| FftComplex::new(re, T::zero()))
+                .collect(),
+            SignalType::Complex => Complex::from_interleaved(signal)
+                .unwrap_or_default()
+                .iter()
+                .map(|z| FftComplex::new(z.re, z.im))
+                .collect(),
+        };
+
+        if buffer.is_empty() {
+            return Vec::new();
+        }
+
+        let fft = rustfft::FftPlanner::new().plan_fft(length, self.direction);
+        fft.process(&mut buffer);
+
+        if self.direction == FftDirection::Inverse {
+            let scale = T::one() / T::from_usize(length).unwrap();
+            buffer.iter_mut().for_each(|z| *z = *z * scale);
+        }
+
+        buffer
+            .into_iter()
+            .map(|z| Complex::new(z.re, z.im))
+            .collect()
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::testing::{assert_slices_close, Tolerance};
+
+    fn settings(direction: FftDirection, signal_type: SignalType) -> Settings {
+        Settings {
+            direction,
+            signal_type,
+        }
+    }
+
+    #[test]
+    fn forward_transform_of_a_real_signal() {
+        let settings = settings(FftDirection::Forward, SignalType::Real);
+
+        let spectrum = settings.transform(&[1.0_f64, 2.0, 3.0, 4.0], 4);
+
+        assert_slices_close(
+            Complex::to_interleaved(&spectrum),
+            &[10.0, 0.0, -2.0, 2.0, -2.0, 0.0, -2.0, -2.0],
+            Tolerance::absolute(1e-9),
+        );
+    }
+
+    #[test]
+    fn rows_are_transformed_independently() {
+        let settings = settings(FftDirection::Forward, SignalType::Real);
+
+        let spectrum = settings.transform(&[1.0_f32, 0.0, 0.0, 1.0], 2);
+
+        assert_slices_close(
+            Complex::to_interleaved(&spectrum),
+            &[1.0, 0.0, 1.0, 0.0, 1.0, 0.0, -1.0, 0.0],
+            Tolerance::F32,
+        );
+    }
+
+    #[test]
+    fn inverse_undoes_forward() {
+        let signal = [0.5_f64, -1.0, 0.25, 3.0, 2.0];
+        let forward = settings(FftDirection::Forward, SignalType::Real);
+        let inverse = settings(FftDirection::Inverse, SignalType::Complex);
+
+        let spectrum = forward.transform(&signal, signal.len());
+        let round_tripped = inverse
+            .transform(Complex::to_interleaved(&spectrum), signal.len());
+
+        let real: Vec<f64> = round_tripped.iter().map(|z| z.re).collect();
+        let imaginary: Vec<f64> = round_tripped.iter().map(|z| z.im).collect();
+        assert_slices_close(&real, &signal, Tolerance::absolute(1e-9));
+        assert_slices_close(&imaginary, &[0.0; 5], Tolerance::absolute(1e-9));
+    }
+
+    #[test]
+    fn check_signal_dimensions() {
+        assert_eq!(SignalType::Real.rows_and_length(&[3, 8]), Ok((3, 8)));
+        assert_eq!(
+            SignalType::Complex.rows_and_length(&[1, 8, 2]),
+            Ok((1, 8))
+        );
+        assert!(SignalType::Complex.rows_and_length(&[1, 8]).is_err());
+        assert!(SignalType::Real.rows_and_length(&[1, 0]).is_err());
+    }
+}
@@ -0,0 +1,765 @@
+//! Render a template string using values from named input tensors, so a
+//! pipeline can produce human-readable messages without host code changes.
+//!
+//! Placeholders use a Python-like syntax:
+//!
+//! ```text
+//! Detected {label} with {confidence:.2f} confidence
+//! ```
+//!
+//! - `{name}` inserts the value from the `name` input
+//! - `{name:.2f}` (or `{name:.2}`) inserts a number with 2 decimal places
+//! - `{{` and `}}` insert a literal `{` or `}`
+//!
+//! Every placeholder becomes an input tensor. Inputs with a precision are
+//! `f32` tensors and the rest are `utf8` tensors, although this can be
+//! changed with the `element_types` argument (e.g. `count: u32`).
+//!
+//! If the inputs have more than one element, a message is rendered for each
+//! element. Inputs with a single element are re-used for every message.
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter, Write},
+    str::FromStr,
+};
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    prelude::*, runtime_v1::*, BufferExt, StringBuilder, ValueType,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata = Metadata::new("Format", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("text");
+        metadata.add_tag("format");
+
+        let template = ArgumentMetadata::new("template");
+        template.set_description(
+            "The message to render. Each `{name}` placeholder is replaced with the value from the `name` input, and `{name:.2f}` formats a number with 2 decimal places. Use `{{` and `}}` for literal braces.",
+        );
+        template.add_hint(&supported_argument_type(ArgumentType::String));
+        metadata.add_argument(&template);
+
+        let element_types = ArgumentMetadata::new("element_types");
+        element_types.set_description(
+            "A comma-separated list of `name: element_type` pairs which override an input's type. By default, placeholders with a precision are `f32` and everything else is `utf8`.",
+        );
+        element_types.set_default_value("");
+        element_types.add_hint(&supported_argument_type(ArgumentType::String));
+        metadata.add_argument(&element_types);
+
+        let value = TensorMetadata::new("value");
+        value.set_description(
+            "There is one input for each placeholder in the template, named after the placeholder.",
+        );
+        let hint = supported_shapes(
+            &[
+                ElementType::U8,
+                ElementType::I8,
+                ElementType::U16,
+                ElementType::I16,
+                ElementType::U32,
+                ElementType::I32,
+                ElementType::F32,
+                ElementType::U64,
+                ElementType::I64,
+                ElementType::F64,
+                ElementType::Utf8,
+            ],
+            DimensionsParam::Dynamic,
+        );
+        value.add_hint(&hint);
+        metadata.add_input(&value);
+
+        let message = TensorMetadata::new("message");
+        message.set_description("The rendered messages.");
+        let hint = supported_shapes(
+            &[ElementType::Utf8],
+            DimensionsParam::Fixed(&[0]),
+        );
+        message.add_hint(&hint);
+        metadata.add_output(&message);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        let Settings { inputs, .. } =
+            Settings::from_context::<_, GraphError>(&ctx)?;
+
+        for (name, element_type) in &inputs {
+            ctx.add_input_tensor(name, *element_type, DimensionsParam::Dynamic);
+        }
+        ctx.add_output_tensor(
+            "message",
+            ElementType::Utf8,
+            DimensionsParam::Fixed(&[0]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let Settings { template, inputs } =
+            Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let mut values = HashMap::new();
+
+        for (name, expected_type) in &inputs {
+            let TensorResult {
+                element_type,
+                buffer,
+                ..
+            } = ctx.get_input_tensor(name).ok_or_else(|| {
+                KernelError::InvalidInput(InvalidInput {
+                    name: name.clone(),
+                    reason: BadInputReason::NotFound,
+                })
+            })?;
+
+            if element_type != *expected_type {
+                return Err(invalid_input(
+                    name,
+                    format!(
+                        "Expected a {} tensor, found {}",
+                        expected_type, element_type
+                    ),
+                ));
+            }
+
+            let tensor_values = Value::from_buffer(element_type, &buffer)
+                .map_err(|e| invalid_input(name, e))?;
+            values.insert(name.clone(), tensor_values);
+        }
+
+        let messages = template.render(&values)?;
+
+        let mut builder = StringBuilder::new();
+        for message in &messages {
+            builder.push(message);
+        }
+
+        ctx.set_output_tensor(
+            "message",
+            TensorParam {
+                element_type: ElementType::Utf8,
+                dimensions: &[messages.len() as u32],
+                buffer: &builder.finish(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(name: &str, reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: name.to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Settings {
+    template: Template,
+    /// The name and element type of each input, in the order they first
+    /// appear in the template.
+    inputs: Vec<(String, ElementType)>,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let template = ctx.required_argument::<E>("template")?;
+        let template: Template = template.parse().map_err(|e| {
+            E::invalid_argument(InvalidArgument::invalid_value("template", e))
+        })?;
+        let overrides = ctx
+            ._get_argument("element_types")
+            .map(|s| parse_element_types(&s))
+            .transpose()
+            .map_err(|e| {
+                E::invalid_argument(InvalidArgument::invalid_value(
+                    "element_types",
+                    e,
+                ))
+            })?
+            .unwrap_or_default();
+
+        let inputs = template.inputs(&overrides).map_err(|e| {
+            E::invalid_argument(InvalidArgument::invalid_value(
+                "element_types",
+                e,
+            ))
+        })?;
+
+        Ok(Settings { template, inputs })
+    }
+}
+
+/// Parse a list of `name: element_type` pairs.
+fn parse_element_types(
+    s: &str,
+) -> Result<Vec<(String, ElementType)>, String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, element_type) = pair.split_once(':').ok_or_else(|| {
+                format!("Expected \"name: element_type\", found \"{}\"", pair)
+            })?;
+            let element_type =
+                element_type.trim().parse().map_err(|e| format!("{}", e))?;
+
+            Ok((name.trim().to_string(), element_type))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Template {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Placeholder {
+    name: String,
+    precision: Option<usize>,
+}
+
+impl Template {
+    fn placeholders(&self) -> impl Iterator<Item = &Placeholder> {
+        self.segments.iter().filter_map(|segment| match segment {
+            Segment::Placeholder(p) => Some(p),
+            Segment::Literal(_) => None,
+        })
+    }
+
+    /// Figure out which inputs are needed to render this template.
+    fn inputs(
+        &self,
+        overrides: &[(String, ElementType)],
+    ) -> Result<Vec<(String, ElementType)>, String> {
+        let mut inputs: Vec<(String, ElementType)> = Vec::new();
+
+        for placeholder in self.placeholders() {
+            let default_type = if placeholder.precision.is_some() {
+                ElementType::F32
+            } else {
+                ElementType::Utf8
+            };
+
+            let name = &placeholder.name;
+            match inputs.iter_mut().find(|(n, _)| n == name) {
+                Some((_, element_type)) if placeholder.precision.is_some() => {
+                    *element_type = ElementType::F32;
+                },
+                Some(_) => {},
+                None => inputs.push((name.clone(), default_type)),
+            }
+        }
+
+        for (name, element_type) in overrides {
+            let (_, ty) = inputs
+                .iter_mut()
+                .find(|(n, _)| n == name)
+                .ok_or_else(|| {
+                    format!("The template has no \"{}\" placeholder", name)
+                })?;
+            *ty = *element_type;
+        }
+
+        for placeholder in self.placeholders() {
+            let is_text = inputs.iter().any(|(name, ty)| {
+                *name == placeholder.name && *ty == ElementType::Utf8
+            });
+
+            if is_text && placeholder.precision.is_some() {
+                return Err(format!(
+                    "The \"{}\" placeholder has a precision, but it is a utf8 input",
+                    placeholder.name
+                ));
+            }
+        }
+
+        Ok(inputs)
+    }
+
+    /// Render one message for each element in the inputs.
+    fn render(
+        &self,
+        values: &HashMap<String, Vec<Value>>,
+    ) -> Result<Vec<String>, KernelError> {
+        let rows = row_count(values)?;
+        let mut messages = Vec::with_capacity(rows);
+
+        for row in 0..rows {
+            let mut message = String::new();
+
+            for segment in &self.segments {
+                match segment {
+                    Segment::Literal(s) => message.push_str(s),
+                    Segment::Placeholder(Placeholder { name, precision }) => {
+                        let column = &values[name];
+                        let value = if column.len() == 1 {
+                            &column[0]
+                        } else {
+                            &column[row]
+                        };
+                        value.write(&mut message, *precision);
+                    },
+                }
+            }
+
+            messages.push(message);
+        }
+
+        Ok(messages)
+    }
+}
+
+/// Work out how many messages to render, making sure every input has either
+/// a single element or the same number of elements as the others.
+fn row_count(
+    values: &HashMap<String, Vec<Value>>,
+) -> Result<usize, KernelError> {
+    let mut rows: Option<usize> = None;
+
+    for (name, column) in values {
+        match (column.len(), rows) {
+            (1, _) => {},
+            (len, None) => rows = Some(len),
+            (len, Some(expected)) if len == expected => {},
+            (len, Some(expected)) => {
+                return Err(invalid_input(
+                    name,
+                    format!(
+                        "Expected 1 or {} elements, found {}",
+                        expected, len
+                    ),
+                ));
+            },
+        }
+    }
+
+    Ok(rows.unwrap_or(1))
+}
+
+impl FromStr for Template {
+    type Err = InvalidTemplate;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                },
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                },
+                '{' => {
+                    let mut placeholder = String::new();
+
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some('{') | None => {
+                                return Err(InvalidTemplate::Unclosed);
+                            },
+                            Some(c) => placeholder.push(c),
+                        }
+                    }
+
+                    if !literal.is_empty() {
+                        let text = std::mem::take(&mut literal);
+                        segments.push(Segment::Literal(text));
+                    }
+                    segments.push(Segment::Placeholder(placeholder.parse()?));
+                },
+                '}' => return Err(InvalidTemplate::UnmatchedBrace),
+                other => literal.push(other),
+            }
+        }
+
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Template { segments })
+    }
+}
+
+impl FromStr for Placeholder {
+    type Err = InvalidTemplate;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, spec) = match s.split_once(':') {
+            Some((name, spec)) => (name.trim(), Some(spec.trim())),
+            None => (s.trim(), None),
+        };
+
+        if name.is_empty() {
+            return Err(InvalidTemplate::EmptyName);
+        }
+        if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(InvalidTemplate::InvalidName(name.to_string()));
+        }
+
+        let precision = match spec {
+            Some(spec) => {
+                let digits = spec
+                    .strip_prefix('.')
+                    .map(|s| s.strip_suffix('f').unwrap_or(s));
+
+                match digits.map(str::parse::<usize>) {
+                    Some(Ok(precision)) => Some(precision),
+                    _ => {
+                        return Err(InvalidTemplate::InvalidSpec {
+                            name: name.to_string(),
+                            spec: spec.to_string(),
+                        })
+                    },
+                }
+            },
+            None => None,
+        };
+
+        Ok(Placeholder {
+            name: name.to_string(),
+            precision,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum InvalidTemplate {
+    Unclosed,
+    UnmatchedBrace,
+    EmptyName,
+    InvalidName(String),
+    InvalidSpec { name: String, spec: String },
+}
+
+impl Display for InvalidTemplate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidTemplate::Unclosed => {
+                write!(f, "A placeholder is missing its closing \"}}\"")
+            },
+            InvalidTemplate::UnmatchedBrace => write!(
+                f,
+                "Found a \"}}\" without a matching \"{{\" (use \"}}}}\" for a literal brace)"
+            ),
+            InvalidTemplate::EmptyName => {
+                write!(f, "Placeholders need a name")
+            },
+            InvalidTemplate::InvalidName(name) => write!(
+                f,
+                "\"{}\" isn't a valid name, only letters, numbers, and underscores are allowed",
+                name
+            ),
+            InvalidTemplate::InvalidSpec { name, spec } => write!(
+                f,
+                "Unable to understand \"{}\" in the \"{}\" placeholder, expected a precision like \".2f\"",
+                spec, name
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Text(String),
+    Integer(i128),
+    F32(f32),
+    F64(f64),
+}
+
+impl Value {
+    fn from_buffer(
+        element_type: ElementType,
+        buffer: &[u8],
+    ) -> Result<Vec<Value>, String> {
+        let values = match element_type {
+            ElementType::U8 => integers::<u8>(buffer)?,
+            ElementType::I8 => integers::<i8>(buffer)?,
+            ElementType::U16 => integers::<u16>(buffer)?,
+            ElementType::I16 => integers::<i16>(buffer)?,
+            ElementType::U32 => integers::<u32>(buffer)?,
+            ElementType::I32 => integers::<i32>(buffer)?,
+            ElementType::U64 => integers::<u64>(buffer)?,
+            ElementType::I64 => integers::<i64>(buffer)?,
+            ElementType::F32 => buffer
+                .try_elements::<f32>()
+                .map_err(|e| e.to_string())?
+                .iter()
+                .map(|&v| Value::F32(v))
+                .collect(),
+            ElementType::F64 => buffer
+                .try_elements::<f64>()
+                .map_err(|e| e.to_string())?
+                .iter()
+                .map(|&v| Value::F64(v))
+                .collect(),
+            ElementType::Utf8 => buffer
+                .strings()
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .map(|s| Value::Text(s.to_string()))
+                .collect(),
+        };
+
+        Ok(values)
+    }
+
+    fn write(&self, message: &mut String, precision: Option<usize>) {
+        // Note: writing to a String can't fail
+        let _ = match (self, precision) {
+            (Value::Text(s), _) => write!(message, "{}", s),
+            (Value::Integer(i), None) => write!(message, "{}", i),
+            (Value::Integer(i), Some(p)) => {
+                write!(message, "{:.*}", p, *i as f64)
+            },
+            (Value::F32(f), None) => write!(message, "{}", f),
+            (Value::F32(f), Some(p)) => write!(message, "{:.*}", p, f),
+            (Value::F64(f), None) => write!(message, "{}", f),
+            (Value::F64(f), Some(p)) => write!(message, "{:.*}", p, f),
+        };
+    }
+}
+
+fn integers<T>(buffer: &[u8]) -> Result<Vec<Value>, String>
+where
+    T: ValueType + Into<i128> + Copy,
+{
+    let values = buffer
+        .try_elements::<T>()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|&v| Value::Integer(v.into()))
+        .collect();
+
+    Ok(values)
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(
+        pairs: Vec<(&str, Vec<Value>)>,
+    ) -> HashMap<String, Vec<Value>> {
+        pairs
+            .into_iter()
+            .map(|(name, values)| (name.to_string(), values))
+            .collect()
+    }
+
+    #[test]
+    fn parse_a_template() {
+        let got: Template = "Detected {label} with {confidence:.2f}, {{ok}}"
+            .parse()
+            .unwrap();
+
+        let should_be = Template {
+            segments: vec![
+                Segment::Literal("Detected ".into()),
+                Segment::Placeholder(Placeholder {
+                    name: "label".into(),
+                    precision: None,
+                }),
+                Segment::Literal(" with ".into()),
+                Segment::Placeholder(Placeholder {
+                    name: "confidence".into(),
+                    precision: Some(2),
+                }),
+                Segment::Literal(", {ok}".into()),
+            ],
+        };
+        assert_eq!(got, should_be);
+    }
+
+    #[test]
+    fn invalid_templates() {
+        let inputs = [
+            ("{label", InvalidTemplate::Unclosed),
+            ("label}", InvalidTemplate::UnmatchedBrace),
+            ("{}", InvalidTemplate::EmptyName),
+            ("{a b}", InvalidTemplate::InvalidName("a b".into())),
+            (
+                "{x:>5}",
+                InvalidTemplate::InvalidSpec {
+                    name: "x".into(),
+                    spec: ">5".into(),
+                },
+            ),
+        ];
+
+        for (template, should_be) in inputs {
+            let err = template.parse::<Template>().unwrap_err();
+            assert_eq!(err, should_be, "{}", template);
+        }
+    }
+
+    #[test]
+    fn infer_the_element_types() {
+        let template: Template =
+            "{label} {confidence:.2} {count} {label}".parse().unwrap();
+        let overrides = vec![("count".to_string(), ElementType::U32)];
+
+        let got = template.inputs(&overrides).unwrap();
+
+        assert_eq!(
+            got,
+            vec![
+                ("label".to_string(), ElementType::Utf8),
+                ("confidence".to_string(), ElementType::F32),
+                ("count".to_string(), ElementType::U32),
+            ]
+        );
+    }
+
+    #[test]
+    fn overrides_must_be_valid() {
+        let template: Template = "{confidence:.2}".parse().unwrap();
+
+        let unknown = vec![("label".to_string(), ElementType::Utf8)];
+        assert!(template.inputs(&unknown).is_err());
+
+        let text = vec![("confidence".to_string(), ElementType::Utf8)];
+        assert!(template.inputs(&text).is_err());
+    }
+
+    #[test]
+    fn parse_overrides() {
+        let got = parse_element_types("count: u32, score:f64,").unwrap();
+
+        assert_eq!(
+            got,
+            vec![
+                ("count".to_string(), ElementType::U32),
+                ("score".to_string(), ElementType::F64),
+            ]
+        );
+        assert!(parse_element_types("count").is_err());
+        assert!(parse_element_types("count: u128").is_err());
+    }
+
+    #[test]
+    fn render_a_message() {
+        let template: Template =
+            "Detected {label} with {confidence:.2f}".parse().unwrap();
+        let values = values(vec![
+            ("label", vec![Value::Text("person".into())]),
+            ("confidence", vec![Value::F32(0.9312)]),
+        ]);
+
+        let got = template.render(&values).unwrap();
+
+        assert_eq!(got, vec!["Detected person with 0.93"]);
+    }
+
+    #[test]
+    fn single_values_are_broadcast() {
+        let template: Template = "{name}: {count}".parse().unwrap();
+        let values = values(vec![
+            ("name", vec![Value::Text("steps".into())]),
+            ("count", vec![Value::Integer(1), Value::Integer(2)]),
+        ]);
+
+        let got = template.render(&values).unwrap();
+
+        assert_eq!(got, vec!["steps: 1", "steps: 2"]);
+    }
+
+    #[test]
+    fn inputs_must_have_the_same_length() {
+        let template: Template = "{a} {b}".parse().unwrap();
+        let values = values(vec![
+            ("a", vec![Value::Integer(1), Value::Integer(2)]),
+            ("b", vec![Value::F64(1.0), Value::F64(2.0), Value::F64(3.0)]),
+        ]);
+
+        assert!(template.render(&values).is_err());
+    }
+
+    #[test]
+    fn templates_without_placeholders_render_once() {
+        let template: Template = "Hello, World!".parse().unwrap();
+
+        let got = template.render(&HashMap::new()).unwrap();
+
+        assert_eq!(got, vec!["Hello, World!"]);
+    }
+}
@@ -0,0 +1,28 @@
+#![no_main]
+
+use hotg_rune_proc_blocks::BufferExt;
+use libfuzzer_sys::fuzz_target;
+use proc_blocks_fuzz::{ElementType, RawTensor};
+
+fuzz_target!(|tensor: RawTensor| {
+    let RawTensor {
+        element_type,
+        dimensions,
+        buffer,
+    } = tensor;
+
+    // Note: We only care about panics here, so any errors are ignored.
+    match element_type {
+        ElementType::U8 => drop(buffer.view::<u8>(&dimensions)),
+        ElementType::I8 => drop(buffer.view::<i8>(&dimensions)),
+        ElementType::U16 => drop(buffer.view::<u16>(&dimensions)),
+        ElementType::I16 => drop(buffer.view::<i16>(&dimensions)),
+        ElementType::U32 => drop(buffer.view::<u32>(&dimensions)),
+        ElementType::I32 => drop(buffer.view::<i32>(&dimensions)),
+        ElementType::F32 => drop(buffer.view::<f32>(&dimensions)),
+        ElementType::U64 => drop(buffer.view::<u64>(&dimensions)),
+        ElementType::I64 => drop(buffer.view::<i64>(&dimensions)),
+        ElementType::F64 => drop(buffer.view::<f64>(&dimensions)),
+        ElementType::Utf8 => drop(buffer.string_view(&dimensions)),
+    }
+});
@@ -0,0 +1,16 @@
+#![no_main]
+
+use image_normalization_core::{check_input_dimensions, normalize};
+use libfuzzer_sys::fuzz_target;
+use proc_blocks_fuzz::Tensor;
+
+fuzz_target!(|image: Tensor<u8>| {
+    if check_input_dimensions(&image.dimensions).is_err() {
+        return;
+    }
+
+    for &pixel in &image.elements {
+        let normalized = normalize(pixel);
+        assert!((0.0..=1.0).contains(&normalized));
+    }
+});
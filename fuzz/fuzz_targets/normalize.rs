@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use proc_blocks_fuzz::Tensor;
+
+fuzz_target!(|input: (Tensor<u8>, Tensor<i32>, Tensor<f64>)| {
+    let (bytes, integers, floats) = input;
+
+    let _ = normalize_core::transform(&bytes.elements);
+    let _ = normalize_core::transform(&integers.elements);
+    let _ = normalize_core::transform(&floats.elements);
+});
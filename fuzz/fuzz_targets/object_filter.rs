@@ -0,0 +1,25 @@
+#![no_main]
+
+use hotg_rune_proc_blocks::ndarray::ArrayView3;
+use libfuzzer_sys::fuzz_target;
+use proc_blocks_fuzz::Tensor;
+
+fuzz_target!(|input: (Tensor<f32>, f32)| {
+    let (rectangles, threshold) = input;
+
+    let dimensions: Vec<usize> =
+        rectangles.dimensions.iter().map(|&d| d as usize).collect();
+
+    // The proc-block rejects anything that isn't [1, detections, 6+]
+    if let [1, b, c] = *dimensions {
+        if c < 6 {
+            return;
+        }
+
+        if let Ok(rectangles) =
+            ArrayView3::from_shape((1, b, c), &rectangles.elements)
+        {
+            object_filter_core::transform(rectangles, threshold);
+        }
+    }
+});
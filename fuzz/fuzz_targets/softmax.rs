@@ -0,0 +1,9 @@
+#![no_main]
+
+use hotg_rune_proc_blocks::ndarray::Array1;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: Vec<f32>| {
+    let mut input = Array1::from_vec(input);
+    softmax_core::softmax(input.view_mut());
+});
@@ -0,0 +1,72 @@
+//! Shared helpers for the fuzz targets.
+//!
+//! The `kernel()` functions can only be called from inside the Rune runtime,
+//! so each fuzz target drives the plain Rust function a proc-block's kernel
+//! is built on. The `graph()` and `kernel()` functions themselves are covered
+//! by each proc-block's `kernel_never_panics` test, which feeds random
+//! tensors to them through the mock host.
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+/// The largest number of elements we'll let a [`Tensor`] contain.
+///
+/// This keeps the fuzzer from spending all its time allocating.
+const MAX_ELEMENTS: usize = 1 << 16;
+
+/// An arbitrary tensor with a random shape and contents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tensor<T> {
+    pub dimensions: Vec<u32>,
+    pub elements: Vec<T>,
+}
+
+impl<'a, T: Arbitrary<'a>> Arbitrary<'a> for Tensor<T> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let rank = u.int_in_range(0..=4)?;
+        let mut dimensions = Vec::with_capacity(rank);
+        let mut len = 1_usize;
+
+        for _ in 0..rank {
+            let dim = u.int_in_range(0..=32_u32)?;
+            len = len.saturating_mul(dim as usize);
+            dimensions.push(dim);
+        }
+
+        let len = len.min(MAX_ELEMENTS);
+        let elements = (0..len)
+            .map(|_| T::arbitrary(u))
+            .collect::<Result<Vec<T>>>()?;
+
+        Ok(Tensor {
+            dimensions,
+            elements,
+        })
+    }
+}
+
+/// A raw byte buffer, as it would be handed to a `kernel()`.
+///
+/// Unlike [`Tensor`], the dimensions aren't guaranteed to match the number
+/// of bytes.
+#[derive(Debug, Clone, PartialEq, Arbitrary)]
+pub struct RawTensor {
+    pub element_type: ElementType,
+    pub dimensions: Vec<u32>,
+    pub buffer: Vec<u8>,
+}
+
+/// The element types a [`RawTensor`] may contain.
+#[derive(Debug, Copy, Clone, PartialEq, Arbitrary)]
+pub enum ElementType {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+    U64,
+    I64,
+    F64,
+    Utf8,
+}
@@ -0,0 +1,729 @@
+//! A small gradient-boosted decision tree implementation, intended to give
+//! better accuracy than the linear models on tabular data while still fitting
+//! in a WebAssembly size budget.
+//!
+//! Each tree is fit to the gradients of the loss using the second order
+//! approximation popularised by XGBoost, so the same code handles squared
+//! error (regression) and log loss (binary classification). With the default
+//! `max_depth` of 1, every tree is a decision stump.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::{ArrayView1, ArrayView2},
+    prelude::*,
+    runtime_v1::*,
+    BufferExt, SliceExt,
+};
+use std::cmp::Ordering;
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// L2 regularization applied to leaf values, which stops tiny leaves from
+/// producing huge predictions.
+const REGULARIZATION: f64 = 1.0;
+
+/// The deepest tree we'll allow, to keep training time and memory bounded.
+const MAX_DEPTH: usize = 8;
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata = Metadata::new("GBDT Lite", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("regression");
+        metadata.add_tag("classification");
+        metadata.add_tag("decision tree");
+        metadata.add_tag("analytics");
+
+        let task = ArgumentMetadata::new("task");
+        task.set_description(
+            "Whether to predict a continuous value (`regression`) or a 0/1 label (`classification`).",
+        );
+        task.set_default_value("regression");
+        let hint =
+            interpret_as_string_in_enum(&["regression", "classification"]);
+        task.add_hint(&hint);
+        metadata.add_argument(&task);
+
+        let n_estimators = ArgumentMetadata::new("n_estimators");
+        n_estimators.set_description("The number of boosting rounds.");
+        n_estimators.set_default_value("100");
+        n_estimators
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&n_estimators);
+
+        let learning_rate = ArgumentMetadata::new("learning_rate");
+        learning_rate.set_description(
+            "How much each tree contributes to the final prediction.",
+        );
+        learning_rate.set_default_value("0.1");
+        learning_rate.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&learning_rate);
+
+        let max_depth = ArgumentMetadata::new("max_depth");
+        max_depth.set_description(
+            "The maximum depth of each tree, between 1 (decision stumps) and 8.",
+        );
+        max_depth.set_default_value("1");
+        max_depth
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&max_depth);
+
+        let x_train = TensorMetadata::new("x_train");
+        x_train.set_description(
+            "The training samples, as a `[samples, features]` tensor.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        x_train.add_hint(&hint);
+        metadata.add_input(&x_train);
+
+        let y_train = TensorMetadata::new("y_train");
+        y_train.set_description(
+            "The target for each training sample. Classification targets must be 0 or 1.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F64], DimensionsParam::Fixed(&[0]));
+        y_train.add_hint(&hint);
+        metadata.add_input(&y_train);
+
+        let x_test = TensorMetadata::new("x_test");
+        x_test.set_description("The samples to make predictions for.");
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        x_test.add_hint(&hint);
+        metadata.add_input(&x_test);
+
+        let y_test = TensorMetadata::new("y_test");
+        y_test.set_description("The prediction for each test sample.");
+        let hint =
+            supported_shapes(&[ElementType::F64], DimensionsParam::Fixed(&[0]));
+        y_test.add_hint(&hint);
+        metadata.add_output(&y_test);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "x_train",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_input_tensor(
+            "y_train",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0]),
+        );
+        ctx.add_input_tensor(
+            "x_test",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "y_test",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let x_train = get_input(&ctx, "x_train")?;
+        let x_train = view_2d(&x_train, "x_train")?;
+        let y_train = get_input(&ctx, "y_train")?;
+        let y_train = view_1d(&y_train, "y_train")?;
+        let x_test = get_input(&ctx, "x_test")?;
+        let x_test = view_2d(&x_test, "x_test")?;
+
+        let model = Model::fit(x_train, y_train, settings)?;
+        let predictions = model.predict(x_test)?;
+
+        ctx.set_output_tensor(
+            "y_test",
+            TensorParam {
+                element_type: ElementType::F64,
+                dimensions: &[predictions.len() as u32],
+                buffer: predictions.as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(name: &str, reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: name.to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+fn get_input(
+    ctx: &KernelContext,
+    name: &str,
+) -> Result<TensorResult, KernelError> {
+    let tensor = ctx.get_input_tensor(name).ok_or_else(|| {
+        KernelError::InvalidInput(InvalidInput {
+            name: name.to_string(),
+            reason: BadInputReason::NotFound,
+        })
+    })?;
+
+    if tensor.element_type != ElementType::F64 {
+        return Err(invalid_input(
+            name,
+            format!("Expected a f64 tensor, found {}", tensor.element_type),
+        ));
+    }
+
+    Ok(tensor)
+}
+
+fn view_2d<'a>(
+    tensor: &'a TensorResult,
+    name: &str,
+) -> Result<ArrayView2<'a, f64>, KernelError> {
+    tensor
+        .buffer
+        .try_view::<f64, _>(&tensor.dimensions)
+        .map_err(|e| invalid_input(name, e))
+}
+
+fn view_1d<'a>(
+    tensor: &'a TensorResult,
+    name: &str,
+) -> Result<ArrayView1<'a, f64>, KernelError> {
+    tensor
+        .buffer
+        .try_view::<f64, _>(&tensor.dimensions)
+        .map_err(|e| invalid_input(name, e))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Task {
+    Regression,
+    /// Binary classification, minimising log loss.
+    Classification,
+}
+
+impl Task {
+    /// Get the gradient and hessian of the loss for a single sample, given
+    /// the model's current raw prediction.
+    fn gradients(self, prediction: f64, target: f64) -> (f64, f64) {
+        match self {
+            Task::Regression => (prediction - target, 1.0),
+            Task::Classification => {
+                let p = sigmoid(prediction);
+                (p - target, (p * (1.0 - p)).max(1e-12))
+            },
+        }
+    }
+}
+
+fn sigmoid(x: f64) -> f64 { 1.0 / (1.0 + (-x).exp()) }
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    task: Task,
+    n_estimators: usize,
+    learning_rate: f64,
+    max_depth: usize,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let task = ctx.parse_argument_with_default::<_, E>(
+            "task",
+            String::from("regression"),
+        )?;
+        let n_estimators =
+            ctx.parse_argument_with_default::<_, E>("n_estimators", 100)?;
+        let learning_rate: f64 =
+            ctx.parse_argument_with_default::<_, E>("learning_rate", 0.1)?;
+        let max_depth =
+            ctx.parse_argument_with_default::<_, E>("max_depth", 1)?;
+
+        let task = match task.as_str() {
+            "regression" => Task::Regression,
+            "classification" => Task::Classification,
+            other => {
+                return Err(E::invalid_argument(
+                    InvalidArgument::invalid_value(
+                        "task",
+                        format!("Unknown task, \"{}\"", other),
+                    ),
+                ))
+            },
+        };
+
+        if !(learning_rate > 0.0 && learning_rate.is_finite()) {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "learning_rate",
+                "The learning rate must be a positive number",
+            )));
+        }
+
+        if !(1..=MAX_DEPTH).contains(&max_depth) {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "max_depth",
+                format!(
+                    "The max depth must be between 1 and {}, found {}",
+                    MAX_DEPTH, max_depth
+                ),
+            )));
+        }
+
+        Ok(Settings {
+            task,
+            n_estimators,
+            learning_rate,
+            max_depth,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Model {
+    task: Task,
+    features: usize,
+    /// The prediction before any trees are applied.
+    base_score: f64,
+    learning_rate: f64,
+    trees: Vec<Node>,
+}
+
+impl Model {
+    fn fit(
+        x: ArrayView2<'_, f64>,
+        y: ArrayView1<'_, f64>,
+        settings: Settings,
+    ) -> Result<Self, KernelError> {
+        let Settings {
+            task,
+            n_estimators,
+            learning_rate,
+            max_depth,
+        } = settings;
+
+        if x.nrows() != y.len() {
+            return Err(invalid_input(
+                "y_train",
+                format!(
+                    "Expected {} targets (one per training sample), found {}",
+                    x.nrows(),
+                    y.len()
+                ),
+            ));
+        }
+
+        if y.is_empty() {
+            return Err(invalid_input("x_train", "No training samples"));
+        }
+
+        if task == Task::Classification
+            && y.iter().any(|&label| label != 0.0 && label != 1.0)
+        {
+            return Err(invalid_input(
+                "y_train",
+                "Classification targets must be 0 or 1",
+            ));
+        }
+
+        let base_score = initial_prediction(y, task);
+        let mut predictions = vec![base_score; y.len()];
+        let mut trees = Vec::with_capacity(n_estimators);
+        let indices: Vec<usize> = (0..y.len()).collect();
+
+        for _ in 0..n_estimators {
+            let (gradients, hessians): (Vec<f64>, Vec<f64>) = predictions
+                .iter()
+                .zip(y)
+                .map(|(&prediction, &target)| {
+                    task.gradients(prediction, target)
+                })
+                .unzip();
+
+            let samples = Samples {
+                x: x.view(),
+                gradients: &gradients,
+                hessians: &hessians,
+            };
+            let tree = samples.build_tree(indices.clone(), max_depth);
+
+            for (prediction, row) in predictions.iter_mut().zip(x.outer_iter())
+            {
+                *prediction += learning_rate * tree.evaluate(row);
+            }
+
+            trees.push(tree);
+        }
+
+        Ok(Model {
+            task,
+            features: x.ncols(),
+            base_score,
+            learning_rate,
+            trees,
+        })
+    }
+
+    fn predict(&self, x: ArrayView2<'_, f64>) -> Result<Vec<f64>, KernelError> {
+        if x.ncols() != self.features {
+            return Err(invalid_input(
+                "x_test",
+                format!(
+                    "The model was trained with {} features, but the test samples have {}",
+                    self.features,
+                    x.ncols()
+                ),
+            ));
+        }
+
+        let predictions = x
+            .outer_iter()
+            .map(|row| {
+                let raw = self.base_score
+                    + self
+                        .trees
+                        .iter()
+                        .map(|tree| self.learning_rate * tree.evaluate(row))
+                        .sum::<f64>();
+
+                match self.task {
+                    Task::Regression => raw,
+                    Task::Classification if sigmoid(raw) >= 0.5 => 1.0,
+                    Task::Classification => 0.0,
+                }
+            })
+            .collect();
+
+        Ok(predictions)
+    }
+}
+
+/// The best constant prediction, i.e. the mean for regression and the log
+/// odds of the positive class for classification.
+fn initial_prediction(y: ArrayView1<'_, f64>, task: Task) -> f64 {
+    let mean = y.mean().unwrap_or(0.0);
+
+    match task {
+        Task::Regression => mean,
+        Task::Classification => {
+            let p = mean.clamp(1e-6, 1.0 - 1e-6);
+            (p / (1.0 - p)).ln()
+        },
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Leaf(f64),
+    Split {
+        feature: usize,
+        /// Samples with `value <= threshold` go left.
+        threshold: f64,
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+}
+
+impl Node {
+    fn evaluate(&self, row: ArrayView1<'_, f64>) -> f64 {
+        match self {
+            Node::Leaf(value) => *value,
+            Node::Split {
+                feature,
+                threshold,
+                left,
+                right,
+            } => {
+                if row[*feature] <= *threshold {
+                    left.evaluate(row)
+                } else {
+                    right.evaluate(row)
+                }
+            },
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Split {
+    feature: usize,
+    threshold: f64,
+    gain: f64,
+}
+
+/// The training samples and their gradients for one boosting round.
+struct Samples<'a> {
+    x: ArrayView2<'a, f64>,
+    gradients: &'a [f64],
+    hessians: &'a [f64],
+}
+
+impl Samples<'_> {
+    fn build_tree(&self, indices: Vec<usize>, depth: usize) -> Node {
+        let split = if depth > 0 {
+            self.best_split(&indices)
+        } else {
+            None
+        };
+
+        match split {
+            Some(Split {
+                feature, threshold, ..
+            }) => {
+                let (left, right): (Vec<usize>, Vec<usize>) = indices
+                    .into_iter()
+                    .partition(|&i| self.x[[i, feature]] <= threshold);
+
+                Node::Split {
+                    feature,
+                    threshold,
+                    left: Box::new(self.build_tree(left, depth - 1)),
+                    right: Box::new(self.build_tree(right, depth - 1)),
+                }
+            },
+            None => {
+                let (g, h) = self.sums(&indices);
+                Node::Leaf(-g / (h + REGULARIZATION))
+            },
+        }
+    }
+
+    fn sums(&self, indices: &[usize]) -> (f64, f64) {
+        indices.iter().fold((0.0, 0.0), |(g, h), &i| {
+            (g + self.gradients[i], h + self.hessians[i])
+        })
+    }
+
+    /// Find the split which reduces the loss the most, if there is one.
+    fn best_split(&self, indices: &[usize]) -> Option<Split> {
+        let (g_total, h_total) = self.sums(indices);
+        let parent_score = score(g_total, h_total);
+        let mut best: Option<Split> = None;
+        let mut sorted = indices.to_vec();
+
+        for feature in 0..self.x.ncols() {
+            let column = self.x.column(feature);
+            sorted.sort_by(|&a, &b| {
+                column[a].partial_cmp(&column[b]).unwrap_or(Ordering::Equal)
+            });
+
+            let (mut g_left, mut h_left) = (0.0, 0.0);
+
+            for pair in sorted.windows(2) {
+                let (current, next) = (pair[0], pair[1]);
+                g_left += self.gradients[current];
+                h_left += self.hessians[current];
+
+                // We can only split between distinct values
+                if column[current] == column[next] {
+                    continue;
+                }
+
+                let gain = score(g_left, h_left)
+                    + score(g_total - g_left, h_total - h_left)
+                    - parent_score;
+
+                if gain > best.map_or(0.0, |b| b.gain) {
+                    best = Some(Split {
+                        feature,
+                        threshold: (column[current] + column[next]) / 2.0,
+                        gain,
+                    });
+                }
+            }
+        }
+
+        best
+    }
+}
+
+/// How good a leaf with these gradient sums would be (higher is better).
+fn score(gradient: f64, hessian: f64) -> f64 {
+    gradient * gradient / (hessian + REGULARIZATION)
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::ndarray::{self, Array1, Array2};
+
+    fn settings(task: Task) -> Settings {
+        Settings {
+            task,
+            n_estimators: 100,
+            learning_rate: 0.1,
+            max_depth: 1,
+        }
+    }
+
+    /// Something a linear model can't learn - the target is only high when
+    /// the feature is in a particular range.
+    fn bump() -> (Array2<f64>, Array1<f64>) {
+        let x = Array2::from_shape_fn((40, 1), |(i, _)| i as f64);
+        let y = x
+            .column(0)
+            .mapv(|x| if (10.0..30.0).contains(&x) { 5.0 } else { 0.0 });
+        (x, y)
+    }
+
+    #[test]
+    fn regression_fits_a_non_linear_target() {
+        let (x, y) = bump();
+
+        let model = Model::fit(x.view(), y.view(), settings(Task::Regression))
+            .unwrap();
+        let got = model.predict(x.view()).unwrap();
+
+        let mse = got
+            .iter()
+            .zip(&y)
+            .map(|(p, t)| (p - t).powi(2))
+            .sum::<f64>()
+            / y.len() as f64;
+        assert!(mse < 0.1, "{}", mse);
+    }
+
+    #[test]
+    fn classify_with_an_interaction_between_features() {
+        // A grid of points where only the top right corner is positive
+        let x = Array2::from_shape_fn((64, 2), |(i, j)| match j {
+            0 => (i % 8) as f64,
+            _ => (i / 8) as f64,
+        });
+        let y: Array1<f64> = x
+            .outer_iter()
+            .map(|row| row[0] >= 4.0 && row[1] >= 4.0)
+            .map(|positive| if positive { 1.0 } else { 0.0 })
+            .collect();
+        let settings = Settings {
+            max_depth: 2,
+            ..settings(Task::Classification)
+        };
+
+        let model = Model::fit(x.view(), y.view(), settings).unwrap();
+        let got = model.predict(x.view()).unwrap();
+
+        assert_eq!(got, y.to_vec());
+    }
+
+    #[test]
+    fn stump_finds_the_best_threshold() {
+        let x = ndarray::arr2(&[[1.0], [2.0], [3.0], [10.0], [11.0], [12.0]]);
+        let gradients = [1.0, 1.0, 1.0, -1.0, -1.0, -1.0];
+        let hessians = [1.0; 6];
+        let samples = Samples {
+            x: x.view(),
+            gradients: &gradients,
+            hessians: &hessians,
+        };
+
+        let split = samples.best_split(&[0, 1, 2, 3, 4, 5]).unwrap();
+
+        assert_eq!(split.feature, 0);
+        assert_eq!(split.threshold, 6.5);
+    }
+
+    #[test]
+    fn constant_features_give_a_single_leaf() {
+        let x = Array2::from_elem((4, 1), 1.0);
+        let gradients = [1.0, -1.0, 2.0, 0.0];
+        let hessians = [1.0; 4];
+        let samples = Samples {
+            x: x.view(),
+            gradients: &gradients,
+            hessians: &hessians,
+        };
+
+        let tree = samples.build_tree(vec![0, 1, 2, 3], 3);
+
+        assert_eq!(tree, Node::Leaf(-2.0 / 5.0));
+    }
+
+    #[test]
+    fn classification_targets_must_be_binary() {
+        let x = ndarray::arr2(&[[1.0], [2.0]]);
+        let y = ndarray::arr1(&[0.0, 0.5]);
+
+        let err = Model::fit(x.view(), y.view(), settings(Task::Classification))
+            .unwrap_err();
+
+        assert!(matches!(err, KernelError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn test_samples_need_the_same_features() {
+        let (x, y) = bump();
+        let model = Model::fit(x.view(), y.view(), settings(Task::Regression))
+            .unwrap();
+
+        assert!(model.predict(Array2::zeros((1, 2)).view()).is_err());
+    }
+}
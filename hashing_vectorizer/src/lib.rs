@@ -0,0 +1,441 @@
+//! Convert text into a fixed-size feature vector using the "hashing trick".
+//!
+//! Each word (or character) n-gram is hashed and the count in the
+//! corresponding bucket is incremented. Because the bucket is derived from
+//! the n-gram itself there is no vocabulary to ship with the Rune, at the
+//! cost of unrelated n-grams occasionally sharing a bucket.
+//!
+//! N-grams are hashed with 64-bit FNV-1a so the same text always produces
+//! the same features, regardless of platform.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    hash::fnv1a, ndarray::Array2, prelude::*, runtime_v1::*, BufferExt,
+    SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Hashing Vectorizer", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("text");
+        metadata.add_tag("nlp");
+        metadata.add_tag("feature extraction");
+        metadata.with_const_eval();
+
+        let dimension = ArgumentMetadata::new("dimension");
+        dimension.set_description(
+            "The number of buckets in each feature vector. Larger values mean fewer collisions but more memory.",
+        );
+        dimension.set_default_value("1024");
+        dimension.add_hint(&non_negative_number());
+        dimension.add_hint(&supported_argument_type(ArgumentType::Integer));
+        metadata.add_argument(&dimension);
+
+        let n = ArgumentMetadata::new("n");
+        n.set_description(
+            "The longest n-gram to use. Every n-gram from 1 up to this length is counted.",
+        );
+        n.set_default_value("1");
+        n.add_hint(&non_negative_number());
+        n.add_hint(&supported_argument_type(ArgumentType::Integer));
+        metadata.add_argument(&n);
+
+        let analyzer = ArgumentMetadata::new("analyzer");
+        analyzer.set_description(
+            "Whether n-grams are made from words (`word`) or individual characters (`char`).",
+        );
+        analyzer.set_default_value("word");
+        analyzer.add_hint(&interpret_as_string_in_enum(Analyzer::NAMES));
+        metadata.add_argument(&analyzer);
+
+        let lowercase = ArgumentMetadata::new("lowercase");
+        lowercase.set_description("Convert the text to lowercase first.");
+        lowercase.set_default_value("true");
+        lowercase.add_hint(&interpret_as_string_in_enum(&["true", "false"]));
+        metadata.add_argument(&lowercase);
+
+        let normalize = ArgumentMetadata::new("normalize");
+        normalize.set_description(
+            "Scale each feature vector to unit (L2) length, so long documents don't dominate short ones.",
+        );
+        normalize.set_default_value("true");
+        normalize.add_hint(&interpret_as_string_in_enum(&["true", "false"]));
+        metadata.add_argument(&normalize);
+
+        let input = TensorMetadata::new("text");
+        input.set_description(
+            "The documents to vectorize. Tensors with more than one dimension are flattened.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::Utf8], DimensionsParam::Dynamic);
+        input.add_hint(&hint);
+        metadata.add_input(&input);
+
+        let output = TensorMetadata::new("features");
+        output.set_description(
+            "A `[documents, dimension]` tensor with the (possibly normalized) n-gram counts for each document.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        output.add_hint(&hint);
+        metadata.add_output(&output);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        let vectorizer =
+            HashingVectorizer::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "text",
+            ElementType::Utf8,
+            DimensionsParam::Dynamic,
+        );
+        ctx.add_output_tensor(
+            "features",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, vectorizer.dimension as u32]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let vectorizer =
+            HashingVectorizer::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            buffer,
+            ..
+        } = ctx.get_input_tensor("text").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "text".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::Utf8 {
+            return Err(invalid_input(format!(
+                "Expected a utf8 tensor, found {}",
+                element_type
+            )));
+        }
+
+        let documents = buffer.strings().map_err(invalid_input)?;
+        let features = vectorizer.transform(&documents);
+        let (rows, columns) = features.dim();
+
+        ctx.set_output_tensor(
+            "features",
+            TensorParam {
+                element_type: ElementType::F32,
+                dimensions: &[rows as u32, columns as u32],
+                buffer: features.as_slice().unwrap().as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "text".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Analyzer {
+    /// N-grams of words, where words are runs of alphanumeric characters.
+    Word,
+    /// N-grams of characters (including whitespace and punctuation).
+    Char,
+}
+
+impl Analyzer {
+    pub const NAMES: &'static [&'static str] = &["word", "char"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "word" => Some(Analyzer::Word),
+            "char" => Some(Analyzer::Char),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HashingVectorizer {
+    pub dimension: usize,
+    pub n: usize,
+    pub analyzer: Analyzer,
+    pub lowercase: bool,
+    pub normalize: bool,
+}
+
+impl HashingVectorizer {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let dimension =
+            ctx.parse_argument_with_default::<_, E>("dimension", 1024)?;
+        let n = ctx.parse_argument_with_default::<_, E>("n", 1)?;
+        let analyzer = ctx.parse_argument_with_default::<_, E>(
+            "analyzer",
+            String::from("word"),
+        )?;
+        let analyzer = Analyzer::from_name(&analyzer).ok_or_else(|| {
+            E::invalid_argument(InvalidArgument::invalid_value(
+                "analyzer",
+                format!(
+                    "Unknown analyzer, \"{}\". Expected one of {:?}",
+                    analyzer,
+                    Analyzer::NAMES
+                ),
+            ))
+        })?;
+        let lowercase =
+            ctx.parse_argument_with_default::<_, E>("lowercase", true)?;
+        let normalize =
+            ctx.parse_argument_with_default::<_, E>("normalize", true)?;
+
+        for (name, value) in [("dimension", dimension), ("n", n)] {
+            if value == 0 {
+                return Err(E::invalid_argument(
+                    InvalidArgument::invalid_value(
+                        name,
+                        "Must be greater than zero",
+                    ),
+                ));
+            }
+        }
+
+        Ok(HashingVectorizer {
+            dimension,
+            n,
+            analyzer,
+            lowercase,
+            normalize,
+        })
+    }
+
+    /// Vectorize several documents, giving a `[documents.len(), dimension]`
+    /// array.
+    pub fn transform(&self, documents: &[&str]) -> Array2<f32> {
+        let mut features = Array2::zeros((documents.len(), self.dimension));
+
+        for (mut row, document) in
+            features.rows_mut().into_iter().zip(documents)
+        {
+            self.vectorize_into(document, row.as_slice_mut().unwrap());
+        }
+
+        features
+    }
+
+    /// Add a document's n-gram counts to `features`, which must have
+    /// `dimension` elements.
+    pub fn vectorize_into(&self, document: &str, features: &mut [f32]) {
+        assert_eq!(features.len(), self.dimension);
+
+        let document = if self.lowercase {
+            document.to_lowercase()
+        } else {
+            document.to_string()
+        };
+
+        let tokens: Vec<&str> = match self.analyzer {
+            Analyzer::Word => document
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .collect(),
+            Analyzer::Char => document
+                .char_indices()
+                .map(|(i, c)| &document[i..i + c.len_utf8()])
+                .collect(),
+        };
+        let separator = match self.analyzer {
+            Analyzer::Word => " ",
+            Analyzer::Char => "",
+        };
+
+        let mut ngram = String::new();
+
+        for n in 1..=self.n {
+            for window in tokens.windows(n) {
+                ngram.clear();
+                for (i, token) in window.iter().enumerate() {
+                    if i > 0 {
+                        ngram.push_str(separator);
+                    }
+                    ngram.push_str(token);
+                }
+
+                let bucket = fnv1a(ngram.as_bytes()) % self.dimension as u64;
+                features[bucket as usize] += 1.0;
+            }
+        }
+
+        if self.normalize {
+            let length = features.iter().map(|f| f * f).sum::<f32>().sqrt();
+            if length > 0.0 {
+                features.iter_mut().for_each(|f| *f /= length);
+            }
+        }
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::testing::{assert_close, Tolerance};
+
+    const COUNTS: HashingVectorizer = HashingVectorizer {
+        dimension: 64,
+        n: 1,
+        analyzer: Analyzer::Word,
+        lowercase: true,
+        normalize: false,
+    };
+
+    fn vectorize(vectorizer: HashingVectorizer, document: &str) -> Vec<f32> {
+        let mut features = vec![0.0; vectorizer.dimension];
+        vectorizer.vectorize_into(document, &mut features);
+        features
+    }
+
+    fn bucket(ngram: &str, dimension: usize) -> usize {
+        (fnv1a(ngram.as_bytes()) % dimension as u64) as usize
+    }
+
+    #[test]
+    fn count_words() {
+        let got = vectorize(COUNTS, "The cat saw the dog!");
+
+        assert_eq!(got.iter().sum::<f32>(), 5.0);
+        assert!(got[bucket("the", 64)] >= 2.0);
+        assert!(got[bucket("cat", 64)] >= 1.0);
+    }
+
+    #[test]
+    fn the_same_text_always_gives_the_same_features() {
+        let first = vectorize(COUNTS, "hello world");
+        let second = vectorize(COUNTS, "HELLO, world");
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn bigrams_are_counted_as_well_as_words() {
+        let vectorizer = HashingVectorizer { n: 2, ..COUNTS };
+
+        let got = vectorize(vectorizer, "new york city");
+
+        // 3 words plus 2 bigrams
+        assert_eq!(got.iter().sum::<f32>(), 5.0);
+        assert!(got[bucket("new york", 64)] >= 1.0);
+    }
+
+    #[test]
+    fn character_ngrams() {
+        let vectorizer = HashingVectorizer {
+            n: 2,
+            analyzer: Analyzer::Char,
+            ..COUNTS
+        };
+
+        let got = vectorize(vectorizer, "café");
+
+        // 4 characters plus 3 bigrams
+        assert_eq!(got.iter().sum::<f32>(), 7.0);
+        assert!(got[bucket("fé", 64)] >= 1.0);
+    }
+
+    #[test]
+    fn normalized_features_have_unit_length() {
+        let vectorizer = HashingVectorizer {
+            normalize: true,
+            ..COUNTS
+        };
+
+        let got = vectorize(vectorizer, "one two three one");
+
+        let length = got.iter().map(|f| f * f).sum::<f32>().sqrt();
+        assert_close(length, 1.0, Tolerance::F32);
+    }
+
+    #[test]
+    fn empty_documents_are_all_zeroes() {
+        let vectorizer = HashingVectorizer {
+            normalize: true,
+            ..COUNTS
+        };
+
+        let features = vectorizer.transform(&["", "..."]);
+
+        assert_eq!(features.dim(), (2, 64));
+        assert!(features.iter().all(|&f| f == 0.0));
+    }
+}
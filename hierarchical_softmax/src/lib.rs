@@ -0,0 +1,479 @@
+//! A two-stage classifier for large label spaces.
+//!
+//! Instead of one huge softmax over every label, a model (or several staged
+//! models) outputs a `coarse` tensor with one logit per category and a
+//! `fine_N` tensor for each category with one logit per label in that
+//! category. The most likely category is picked first, then the most likely
+//! label within it, so only the selected category's fine logits are read.
+//!
+//! The `hierarchy` argument lists one category per line, followed by a colon
+//! and its comma-separated labels:
+//!
+//! ```text
+//! animal: dog, cat, bird
+//! vehicle: car, bus
+//! ```
+//!
+//! With this hierarchy, the proc-block has `coarse`, `fine_0` (animals), and
+//! `fine_1` (vehicles) inputs.
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::ArrayViewMut1, prelude::*, runtime_v1::*, BufferExt, SliceExt,
+    StringBuilder,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Hierarchical Softmax", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("classify");
+        metadata.add_tag("softmax");
+
+        let hierarchy = ArgumentMetadata::new("hierarchy");
+        hierarchy.set_description(
+            "One category per line, written as the category's name, a colon, and its comma-separated labels (e.g. `animal: dog, cat, bird`).",
+        );
+        hierarchy.add_hint(&supported_argument_type(ArgumentType::LongString));
+        metadata.add_argument(&hierarchy);
+
+        let softmax = ArgumentMetadata::new("softmax");
+        softmax.set_description(
+            "Apply a softmax to the logits. Disable this if the models already output probabilities.",
+        );
+        softmax.set_default_value("true");
+        softmax.add_hint(&interpret_as_string_in_enum(&["true", "false"]));
+        metadata.add_argument(&softmax);
+
+        let coarse = TensorMetadata::new("coarse");
+        coarse.set_description(
+            "The logits for each category, in the order they appear in the hierarchy.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Dynamic);
+        coarse.add_hint(&hint);
+        metadata.add_input(&coarse);
+
+        let fine = TensorMetadata::new("fine_0");
+        fine.set_description(
+            "The logits for each label in the first category. There is a `fine_N` input for every category in the hierarchy.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Dynamic);
+        fine.add_hint(&hint);
+        metadata.add_input(&fine);
+
+        let category = TensorMetadata::new("category");
+        category.set_description("The most likely category.");
+        let hint = supported_shapes(
+            &[ElementType::Utf8],
+            DimensionsParam::Fixed(&[1]),
+        );
+        category.add_hint(&hint);
+        metadata.add_output(&category);
+
+        let label = TensorMetadata::new("label");
+        label.set_description("The most likely label within that category.");
+        let hint = supported_shapes(
+            &[ElementType::Utf8],
+            DimensionsParam::Fixed(&[1]),
+        );
+        label.add_hint(&hint);
+        metadata.add_output(&label);
+
+        let confidence = TensorMetadata::new("confidence");
+        confidence.set_description(
+            "The probability of the category multiplied by the probability of the label within it.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Fixed(&[1]));
+        confidence.add_hint(&hint);
+        metadata.add_output(&confidence);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        let Settings { hierarchy, .. } =
+            Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "coarse",
+            ElementType::F32,
+            DimensionsParam::Dynamic,
+        );
+        for index in 0..hierarchy.categories.len() {
+            ctx.add_input_tensor(
+                &fine_input(index),
+                ElementType::F32,
+                DimensionsParam::Dynamic,
+            );
+        }
+
+        ctx.add_output_tensor(
+            "category",
+            ElementType::Utf8,
+            DimensionsParam::Fixed(&[1]),
+        );
+        ctx.add_output_tensor(
+            "label",
+            ElementType::Utf8,
+            DimensionsParam::Fixed(&[1]),
+        );
+        ctx.add_output_tensor(
+            "confidence",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[1]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let Settings { hierarchy, softmax } =
+            Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let coarse = logits(&ctx, "coarse")?;
+        let (index, category_probability) =
+            best(&coarse, hierarchy.categories.len(), softmax)
+                .map_err(|e| invalid_input("coarse", e))?;
+        let category = &hierarchy.categories[index];
+
+        let name = fine_input(index);
+        let fine = logits(&ctx, &name)?;
+        let (label, label_probability) =
+            best(&fine, category.labels.len(), softmax)
+                .map_err(|e| invalid_input(&name, e))?;
+
+        let mut builder = StringBuilder::new();
+        builder.push(&category.name);
+        ctx.set_output_tensor(
+            "category",
+            TensorParam {
+                element_type: ElementType::Utf8,
+                dimensions: &[1],
+                buffer: &builder.finish(),
+            },
+        );
+
+        let mut builder = StringBuilder::new();
+        builder.push(&category.labels[label]);
+        ctx.set_output_tensor(
+            "label",
+            TensorParam {
+                element_type: ElementType::Utf8,
+                dimensions: &[1],
+                buffer: &builder.finish(),
+            },
+        );
+
+        let confidence = category_probability * label_probability;
+        ctx.set_output_tensor(
+            "confidence",
+            TensorParam {
+                element_type: ElementType::F32,
+                dimensions: &[1],
+                buffer: [confidence].as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+/// The name of the input containing the logits for a category's labels.
+fn fine_input(category: usize) -> String { format!("fine_{}", category) }
+
+fn invalid_input(name: &str, reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: name.to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+fn logits(ctx: &KernelContext, name: &str) -> Result<Vec<f32>, KernelError> {
+    let TensorResult {
+        element_type,
+        buffer,
+        ..
+    } = ctx.get_input_tensor(name).ok_or_else(|| {
+        KernelError::InvalidInput(InvalidInput {
+            name: name.to_string(),
+            reason: BadInputReason::NotFound,
+        })
+    })?;
+
+    match element_type {
+        ElementType::F32 => buffer
+            .try_elements::<f32>()
+            .map(|logits| logits.to_vec())
+            .map_err(|e| invalid_input(name, e)),
+        other => Err(invalid_input(
+            name,
+            format!("Expected a f32 tensor, found {}", other),
+        )),
+    }
+}
+
+/// Find the most likely class and its probability.
+fn best(
+    logits: &[f32],
+    expected: usize,
+    apply_softmax: bool,
+) -> Result<(usize, f32), String> {
+    if logits.len() != expected {
+        return Err(format!(
+            "Expected {} values (one per entry in the hierarchy), found {}",
+            expected,
+            logits.len()
+        ));
+    }
+
+    let mut probabilities = logits.to_vec();
+    if apply_softmax {
+        softmax_core::softmax(ArrayViewMut1::from(&mut probabilities[..]));
+    }
+
+    probabilities
+        .iter()
+        .copied()
+        .enumerate()
+        .fold(None, |best: Option<(usize, f32)>, (index, p)| match best {
+            Some((_, best_p)) if best_p >= p => best,
+            _ => Some((index, p)),
+        })
+        .ok_or_else(|| "No values were provided".to_string())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Settings {
+    hierarchy: Hierarchy,
+    softmax: bool,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let hierarchy = ctx.parse_argument::<_, E>("hierarchy")?;
+        let softmax = ctx.parse_argument_with_default::<_, E>("softmax", true)?;
+
+        Ok(Settings { hierarchy, softmax })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Hierarchy {
+    categories: Vec<Category>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Category {
+    name: String,
+    labels: Vec<String>,
+}
+
+impl std::str::FromStr for Hierarchy {
+    type Err = InvalidHierarchy;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut categories: Vec<Category> = Vec::new();
+
+        for (i, line) in s.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (name, labels) = line
+                .split_once(':')
+                .ok_or(InvalidHierarchy::MissingColon { line_number })?;
+            let name = name.trim();
+
+            if name.is_empty() {
+                return Err(InvalidHierarchy::EmptyName { line_number });
+            }
+            if categories.iter().any(|c| c.name == name) {
+                return Err(InvalidHierarchy::DuplicateCategory(
+                    name.to_string(),
+                ));
+            }
+
+            let labels: Vec<String> = labels
+                .split(',')
+                .map(str::trim)
+                .filter(|label| !label.is_empty())
+                .map(String::from)
+                .collect();
+
+            if labels.is_empty() {
+                return Err(InvalidHierarchy::NoLabels(name.to_string()));
+            }
+
+            categories.push(Category {
+                name: name.to_string(),
+                labels,
+            });
+        }
+
+        if categories.is_empty() {
+            return Err(InvalidHierarchy::Empty);
+        }
+
+        Ok(Hierarchy { categories })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum InvalidHierarchy {
+    Empty,
+    MissingColon { line_number: usize },
+    EmptyName { line_number: usize },
+    DuplicateCategory(String),
+    NoLabels(String),
+}
+
+impl Display for InvalidHierarchy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidHierarchy::Empty => {
+                write!(f, "The hierarchy needs at least one category")
+            },
+            InvalidHierarchy::MissingColon { line_number } => write!(
+                f,
+                "Line {} should be the category's name, a colon, and its labels",
+                line_number
+            ),
+            InvalidHierarchy::EmptyName { line_number } => {
+                write!(f, "The category on line {} has no name", line_number)
+            },
+            InvalidHierarchy::DuplicateCategory(name) => {
+                write!(f, "The \"{}\" category appears more than once", name)
+            },
+            InvalidHierarchy::NoLabels(name) => {
+                write!(f, "The \"{}\" category has no labels", name)
+            },
+        }
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::testing::{assert_close, Tolerance};
+
+    #[test]
+    fn parse_a_hierarchy() {
+        let src = "animal: dog, cat,bird\n\n  vehicle :car, bus,\n";
+
+        let got: Hierarchy = src.parse().unwrap();
+
+        assert_eq!(
+            got.categories,
+            vec![
+                Category {
+                    name: "animal".to_string(),
+                    labels: vec!["dog".into(), "cat".into(), "bird".into()],
+                },
+                Category {
+                    name: "vehicle".to_string(),
+                    labels: vec!["car".into(), "bus".into()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_invalid_hierarchies() {
+        let inputs = [
+            ("", InvalidHierarchy::Empty),
+            ("a: x\nb", InvalidHierarchy::MissingColon { line_number: 2 }),
+            (": x", InvalidHierarchy::EmptyName { line_number: 1 }),
+            ("a: x\na: y", InvalidHierarchy::DuplicateCategory("a".into())),
+            ("a: , ", InvalidHierarchy::NoLabels("a".into())),
+        ];
+
+        for (src, should_be) in inputs {
+            assert_eq!(src.parse::<Hierarchy>(), Err(should_be), "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn pick_the_most_likely_entry() {
+        let (index, probability) = best(&[0.0, 2.0, 1.0], 3, true).unwrap();
+
+        assert_eq!(index, 1);
+        let e = std::f32::consts::E;
+        let should_be = e * e / (1.0 + e + e * e);
+        assert_close(probability, should_be, Tolerance::F32);
+    }
+
+    #[test]
+    fn probabilities_can_be_used_as_is() {
+        assert_eq!(best(&[0.2, 0.7, 0.1], 3, false), Ok((1, 0.7)));
+    }
+
+    #[test]
+    fn the_number_of_logits_must_match_the_hierarchy() {
+        assert!(best(&[0.5, 0.5], 3, true).is_err());
+    }
+}
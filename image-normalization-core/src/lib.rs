@@ -0,0 +1,179 @@
+//! A normalization routine which takes some tensor of integers and fits their
+//! values to the range `[0, 1]` as `f32`'s.
+//!
+//! The scaled pixels can optionally be standardized using a per-channel mean
+//! and standard deviation, giving `(x / 255 - mean) / std` for `u8` images.
+//! This is the preprocessing most torchvision and TensorFlow Lite image models
+//! expect (e.g. ImageNet's `mean = 0.485,0.456,0.406` and
+//! `std = 0.229,0.224,0.225`).
+//!
+//! This is the algorithm behind the `image-normalization` proc-block, without
+//! any of the WebAssembly bindings, so it can be used as a normal Rust library.
+
+use num_traits::{Bounded, ToPrimitive};
+
+/// Make sure the image has the `[frames, rows, columns, channels]` layout,
+/// with either 1 (grayscale) or 3 (RGB) channels.
+pub fn check_input_dimensions(dimensions: &[u32]) -> Result<(), String> {
+    match *dimensions {
+        [_, _, _, 1] | [_, _, _, 3] => Ok(()),
+        [_, _, _, channels] => Err(format!(
+            "The number of channels should be either 1 or 3, found {}",
+            channels
+        )),
+        _ => Err(format!(
+            "Expected an image with the dimensions [frames, rows, columns, channels], found {:?}",
+            dimensions
+        )),
+    }
+}
+
+/// Scale a pixel value to the range `[0, 1]` based on its type's bounds.
+pub fn normalize<T>(value: T) -> f32
+where
+    T: Bounded + ToPrimitive,
+{
+    let min = T::min_value().to_f32().unwrap();
+    let max = T::max_value().to_f32().unwrap();
+    let value = value.to_f32().unwrap();
+
+    (value - min) / (max - min)
+}
+
+/// Parse a comma-separated list of per-channel values (e.g. `0.5,0.5,0.5`).
+pub fn parse_channel_values(values: &str) -> Result<Vec<f32>, String> {
+    let values = values
+        .split(',')
+        .map(str::trim)
+        .map(|v| {
+            v.parse::<f32>()
+                .map_err(|e| format!("Unable to parse \"{}\": {}", v, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if values.iter().any(|v| !v.is_finite()) {
+        return Err("All values must be finite".to_string());
+    }
+
+    Ok(values)
+}
+
+/// A per-channel mean and standard deviation which are applied to pixels
+/// after they have been scaled to `[0, 1]`.
+///
+/// Either list may contain a single value, which is used for every channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Standardization {
+    pub mean: Vec<f32>,
+    pub std: Vec<f32>,
+}
+
+impl Standardization {
+    pub fn new(mean: Vec<f32>, std: Vec<f32>) -> Result<Self, String> {
+        if mean.is_empty() || std.is_empty() {
+            return Err("At least one value is required".to_string());
+        }
+        if std.contains(&0.0) {
+            return Err("The standard deviation can't be zero".to_string());
+        }
+
+        Ok(Standardization { mean, std })
+    }
+
+    /// Make sure there is a mean and standard deviation for each channel.
+    pub fn check_channels(&self, channels: usize) -> Result<(), String> {
+        for (name, values) in [("mean", &self.mean), ("std", &self.std)] {
+            if values.len() != 1 && values.len() != channels {
+                return Err(format!(
+                    "Expected 1 or {} values for the {}, found {}",
+                    channels,
+                    name,
+                    values.len()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Standardize the pixels in an image whose last dimension is the
+    /// channel.
+    pub fn apply(&self, pixels: &mut [f32], channels: usize) {
+        for pixel in pixels.chunks_exact_mut(channels) {
+            for (channel, value) in pixel.iter_mut().enumerate() {
+                let mean = per_channel(&self.mean, channel);
+                let std = per_channel(&self.std, channel);
+                *value = (*value - mean) / std;
+            }
+        }
+    }
+}
+
+impl Default for Standardization {
+    /// Leave the pixels unchanged.
+    fn default() -> Self {
+        Standardization {
+            mean: vec![0.0],
+            std: vec![1.0],
+        }
+    }
+}
+
+fn per_channel(values: &[f32], channel: usize) -> f32 {
+    match values {
+        [value] => *value,
+        values => values[channel],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::testing::{assert_close, Tolerance};
+
+    #[test]
+    fn normalizing_with_default_distribution_is_noop() {
+        let input: u8 = 5;
+        let should_be: f32 = 5.0 / 255.0;
+        let got = normalize(input);
+        assert_close(got, should_be, Tolerance::F32);
+    }
+
+    #[test]
+    fn grayscale_and_rgb_images_are_accepted() {
+        assert!(check_input_dimensions(&[1, 2, 2, 1]).is_ok());
+        assert!(check_input_dimensions(&[1, 2, 2, 3]).is_ok());
+        assert!(check_input_dimensions(&[1, 2, 2, 4]).is_err());
+        assert!(check_input_dimensions(&[2, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn parse_per_channel_values() {
+        let got = parse_channel_values("0.485, 0.456,0.406").unwrap();
+
+        assert_eq!(got, vec![0.485, 0.456, 0.406]);
+        assert!(parse_channel_values("0.5,").is_err());
+        assert!(parse_channel_values("NaN").is_err());
+    }
+
+    #[test]
+    fn standardize_each_channel() {
+        let standardization =
+            Standardization::new(vec![0.5, 0.0, 1.0], vec![0.5]).unwrap();
+        let mut pixels = vec![1.0, 1.0, 1.0, 0.0, 0.5, 0.5];
+
+        standardization.apply(&mut pixels, 3);
+
+        assert_eq!(pixels, vec![1.0, 2.0, 0.0, -1.0, 1.0, -1.0]);
+    }
+
+    #[test]
+    fn the_number_of_channels_must_match() {
+        let standardization =
+            Standardization::new(vec![0.5, 0.5, 0.5], vec![0.25]).unwrap();
+
+        assert!(standardization.check_channels(3).is_ok());
+        assert!(standardization.check_channels(1).is_err());
+        assert!(Standardization::new(vec![0.5], vec![0.0]).is_err());
+    }
+}
@@ -1,11 +1,30 @@
-use crate::proc_block_v1::*;
-use hotg_rune_proc_blocks::{runtime_v1::*, BufferExt, SliceExt};
+//! A normalization routine which takes some tensor of integers and fits their
+//! values to the range `[0, 1]` as `f32`'s.
+//!
+//! The scaled pixels can optionally be standardized using a per-channel mean
+//! and standard deviation, giving `(x / 255 - mean) / std` for `u8` images.
+//! This is the preprocessing most torchvision and TensorFlow Lite image models
+//! expect (e.g. ImageNet's `mean = 0.485,0.456,0.406` and
+//! `std = 0.229,0.224,0.225`).
+
+use hotg_rune_proc_blocks::{
+    arena::{self, Arena},
+    prelude::*,
+    runtime_v1::*,
+    BufferExt, SliceExt, ValueType,
+};
+use image_normalization_core::{
+    check_input_dimensions, normalize, parse_channel_values, Standardization,
+};
 use num_traits::{Bounded, ToPrimitive};
 
+use crate::proc_block_v1::*;
+
 wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
 
-/// A normalization routine which takes some tensor of integers and fits their
-/// values to the range `[0, 1]` as `f32`'s.
+/// The element types this proc-block knows how to normalize.
+const SUPPORTED_ELEMENT_TYPES: &[&str] =
+    &["u8", "i8", "u16", "i16", "u32", "i32"];
 
 #[derive(Debug, Clone, PartialEq)]
 struct ProcBlockV1;
@@ -22,6 +41,25 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         metadata.add_tag("image");
         metadata.add_tag("normalize");
 
+        let element_type = ArgumentMetadata::new(ElementType::NAME);
+        element_type.set_description("The type of the image's pixels.");
+        element_type.set_default_value("u8");
+        element_type
+            .add_hint(&interpret_as_string_in_enum(SUPPORTED_ELEMENT_TYPES));
+        metadata.add_argument(&element_type);
+
+        let mean = ArgumentMetadata::new("mean");
+        mean.set_description("A comma-separated list of values to subtract from each channel after scaling the pixels to `[0, 1]` (e.g. `0.485,0.456,0.406` for ImageNet). A single value is used for every channel.");
+        mean.set_default_value("0");
+        mean.add_hint(&supported_argument_type(ArgumentType::String));
+        metadata.add_argument(&mean);
+
+        let std = ArgumentMetadata::new("std");
+        std.set_description("A comma-separated list of standard deviations to divide each channel by after subtracting the mean (e.g. `0.229,0.224,0.225` for ImageNet). A single value is used for every channel.");
+        std.set_default_value("1");
+        std.add_hint(&supported_argument_type(ArgumentType::String));
+        metadata.add_argument(&std);
+
         let input = TensorMetadata::new("image");
         input.set_description("An image with the dimensions `[1, width, height, channels]`.\n\nRGB images typically have 3 channels and grayscale images have 1.");
         let hint = supported_shapes(
@@ -40,7 +78,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
 
         let output = TensorMetadata::new("normalized_image");
         output.set_description(
-            "The image's pixels, normalized to the range `[0, 1]`.",
+            "The image's pixels, normalized to the range `[0, 1]` and then standardized using the `mean` and `std`.",
         );
         let hint = supported_shapes(
             &[ElementType::F32],
@@ -56,32 +94,11 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let ctx =
             GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
 
-        let element_type = match ctx.get_argument("element_type").as_deref() {
-            Some("u8") => ElementType::U8,
-            Some("i8") => ElementType::I8,
-            Some("u16") => ElementType::U16,
-            Some("i16") => ElementType::I16,
-            Some("u32") => ElementType::U32,
-            Some("i32") => ElementType::I32,
-            Some("f32") => ElementType::F32,
-            Some("u64") => ElementType::U64,
-            Some("i64") => ElementType::I64,
-            Some("f64") => ElementType::F64,
-            Some(_) => {
-                return Err(GraphError::InvalidArgument(InvalidArgument {
-                    name: "element_type".to_string(),
-                    reason: BadArgumentReason::InvalidValue(
-                        "Unsupported element type".to_string(),
-                    ),
-                }));
-            },
-            None => {
-                return Err(GraphError::InvalidArgument(InvalidArgument {
-                    name: "element_type".to_string(),
-                    reason: BadArgumentReason::NotFound,
-                }))
-            },
-        };
+        let element_type = ctx.element_type::<GraphError>(
+            ElementType::U8,
+            SUPPORTED_ELEMENT_TYPES,
+        )?;
+        standardization::<_, GraphError>(&ctx)?;
 
         ctx.add_input_tensor(
             "image",
@@ -112,78 +129,180 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             })
         })?;
 
-        check_input_dimensions(&dimensions);
-
-        let output = match element_type {
-            ElementType::U8 => buffer
-            .view::<u8>(&dimensions)
-            .map_err( |e | KernelError::InvalidInput(InvalidInput {name: "image".to_string(), reason: BadInputReason::Other(e.to_string()),}))?.map( |&t| normalize(t)),
-            ElementType::I8 => buffer
-            .view::<i8>(&dimensions)
-            .map_err( |e | KernelError::InvalidInput(InvalidInput {name: "image".to_string(), reason: BadInputReason::Other(e.to_string()),}))?.map( |&t| normalize(t)),
-            ElementType::U16 => buffer
-            .view::<u16>(&dimensions)
-            .map_err( |e | KernelError::InvalidInput(InvalidInput {name: "image".to_string(), reason: BadInputReason::Other(e.to_string()),}))?.map( |&t| normalize(t)),
-            ElementType::I16 => buffer
-            .view::<i16>(&dimensions)
-            .map_err( |e | KernelError::InvalidInput(InvalidInput {name: "image".to_string(), reason: BadInputReason::Other(e.to_string()),}))?.map( |&t| normalize(t)),
-            ElementType::U32 => buffer
-            .view::<u32>(&dimensions)
-            .map_err( |e | KernelError::InvalidInput(InvalidInput {name: "image".to_string(), reason: BadInputReason::Other(e.to_string()),}))?.map( |&t| normalize(t)),
-            ElementType::I32 => buffer
-            .view::<i32>(&dimensions)
-            .map_err( |e | KernelError::InvalidInput(InvalidInput {name: "image".to_string(), reason: BadInputReason::Other(e.to_string()),}))?.map( |&t| normalize(t)),
-            other => {
-                return Err(KernelError::Other(format!(
-                "The Image Normalization proc-block doesn't support {:?} element type",
-                other,
-                )))
-            }
-        };
-        let output: Vec<f32> = output.iter().map(|&v| v as f32).collect();
-        ctx.set_output_tensor(
-            "output",
-            TensorParam {
-                element_type: ElementType::F32,
-                dimensions: &dimensions,
-                buffer: output.as_bytes(),
-            },
-        );
+        let standardization = standardization::<_, KernelError>(&ctx)?;
 
-        Ok(())
+        check_input_dimensions(&dimensions).map_err(invalid_image)?;
+        let channels = dimensions[3] as usize;
+        standardization
+            .check_channels(channels)
+            .map_err(invalid_image)?;
+
+        // The normalized image is as big as the input, so allocate it from the
+        // arena instead of getting a fresh Vec every time
+        arena::scoped(|arena| {
+            let output = match element_type {
+                ElementType::U8 => {
+                    normalize_buffer::<u8>(arena, &buffer, &dimensions)?
+                },
+                ElementType::I8 => {
+                    normalize_buffer::<i8>(arena, &buffer, &dimensions)?
+                },
+                ElementType::U16 => {
+                    normalize_buffer::<u16>(arena, &buffer, &dimensions)?
+                },
+                ElementType::I16 => {
+                    normalize_buffer::<i16>(arena, &buffer, &dimensions)?
+                },
+                ElementType::U32 => {
+                    normalize_buffer::<u32>(arena, &buffer, &dimensions)?
+                },
+                ElementType::I32 => {
+                    normalize_buffer::<i32>(arena, &buffer, &dimensions)?
+                },
+                other => {
+                    return Err(invalid_image(format!(
+                        "The Image Normalization proc-block doesn't support the {} element type",
+                        other,
+                    )));
+                },
+            };
+            standardization.apply(output, channels);
+
+            ctx.set_output_tensor(
+                "normalized_image",
+                TensorParam {
+                    element_type: ElementType::F32,
+                    dimensions: &dimensions,
+                    buffer: output.as_bytes(),
+                },
+            );
+
+            Ok(())
+        })
     }
 }
 
-fn check_input_dimensions(dimensions: &[u32]) {
-    match *dimensions {
-        [_, _, _, 3] => {},
-        [_, _, _, channels] => panic!(
-            "The number of channels should be either 1 or 3, found {}",
-            channels
-        ),
-        _ => panic!("The image normalization proc block only supports outputs of the form [frames, rows, columns, channels], found {:?}", dimensions),
+fn standardization<C, E>(ctx: &C) -> Result<Standardization, E>
+where
+    C: ContextExt,
+    E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+{
+    let defaults = Standardization::default();
+    let mean = channel_values::<_, E>(ctx, "mean", defaults.mean)?;
+    let std = channel_values::<_, E>(ctx, "std", defaults.std)?;
+
+    Standardization::new(mean, std).map_err(|e| {
+        E::invalid_argument(InvalidArgument::invalid_value("std", e))
+    })
+}
+
+fn channel_values<C, E>(
+    ctx: &C,
+    name: &str,
+    default: Vec<f32>,
+) -> Result<Vec<f32>, E>
+where
+    C: ContextExt,
+    E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+{
+    match ctx._get_argument(name) {
+        Some(values) => parse_channel_values(&values).map_err(|e| {
+            E::invalid_argument(InvalidArgument::invalid_value(name, e))
+        }),
+        None => Ok(default),
     }
 }
 
-fn normalize<T>(value: T) -> f32
+fn normalize_buffer<'arena, T>(
+    arena: &'arena Arena,
+    buffer: &[u8],
+    dimensions: &[u32],
+) -> Result<&'arena mut [f32], KernelError>
 where
-    T: Bounded + ToPrimitive,
+    T: ValueType + Bounded + ToPrimitive + Copy,
 {
-    let min = T::min_value().to_f32().unwrap();
-    let max = T::max_value().to_f32().unwrap();
-    let value = value.to_f32().unwrap();
+    let image = buffer.view::<T>(dimensions).map_err(invalid_image)?;
 
-    (value - min) / (max - min)
+    Ok(arena.alloc_slice_from_iter(
+        image.len(),
+        image.iter().map(|&pixel| normalize(pixel)),
+    ))
+}
+
+fn invalid_image(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "image".to_string(),
+        reason: BadInputReason::Other(reason.to_string()),
+    })
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    #[test]
-    fn normalizing_with_default_distribution_is_noop() {
-        let input: u8 = 5;
-        let should_be: f32 = 5.0 / 255.0;
-        let got = normalize(input);
-        assert_eq!(got, should_be);
+    use crate::proc_block_v1::ProcBlockV1 as _;
+    use hotg_rune_proc_blocks::{
+        mock,
+        strategies::{element_types, mock_tensors},
+    };
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn kernel_never_panics(
+            image in mock_tensors(element_types(), 4, 4),
+            element_type in proptest::sample::select(ElementType::ALL),
+            mean in prop_oneof!["0", r"0\.5,0\.5,0\.5", "[-0-9.,]{0,16}"],
+            std in prop_oneof!["1", r"0\.25,0\.25,0\.25", "[-0-9.,]{0,16}"],
+        ) {
+            mock::reset();
+            mock::set_argument("image", ElementType::NAME, element_type);
+            mock::set_argument("image", "mean", mean);
+            mock::set_argument("image", "std", std);
+            mock::set_input("image", "image", image);
+
+            // Bad arguments and inputs should be reported as errors, so we
+            // only care that nothing panics
+            let _ = ProcBlockV1::graph("image".to_string());
+            let _ = ProcBlockV1::kernel("image".to_string());
+        }
     }
 }
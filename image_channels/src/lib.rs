@@ -0,0 +1,529 @@
+//! Convert an image to grayscale or BGR, and switch between `[height, width,
+//! channels]` (HWC) and `[channels, height, width]` (CHW) layouts.
+//!
+//! The rest of the image proc-blocks produce RGB images in HWC layout, but a
+//! lot of TFLite and ONNX models (anything trained with OpenCV or PyTorch,
+//! for example) expect BGR or CHW input instead.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::{s, Array3, ArrayView3},
+    prelude::*,
+    runtime_v1::*,
+    BufferExt, SliceExt, ValueType,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+const SUPPORTED_ELEMENT_TYPES: &[&str] = &["u8", "f32"];
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Image Channels", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("image");
+        metadata.add_tag("grayscale");
+        metadata.add_tag("bgr");
+        metadata.add_tag("chw");
+
+        let element_type = ArgumentMetadata::element_type_in(
+            SUPPORTED_ELEMENT_TYPES,
+            ElementType::U8,
+        );
+        element_type.set_description("The type of the image's pixels.");
+        metadata.add_argument(&element_type);
+
+        let color = ArgumentMetadata::new("color");
+        color.set_description(
+            "`rgb` leaves the channels alone, `bgr` swaps the red and blue channels, and `grayscale` reduces the image to a single luma channel using the ITU-R BT.601 weights.",
+        );
+        color.set_default_value("rgb");
+        color.add_hint(&interpret_as_string_in_enum(Color::NAMES));
+        metadata.add_argument(&color);
+
+        let input_layout = ArgumentMetadata::new("input_layout");
+        input_layout.set_description(
+            "Whether the input image is `[height, width, channels]` (`hwc`) or `[channels, height, width]` (`chw`).",
+        );
+        input_layout.set_default_value("hwc");
+        input_layout.add_hint(&interpret_as_string_in_enum(Layout::NAMES));
+        metadata.add_argument(&input_layout);
+
+        let output_layout = ArgumentMetadata::new("output_layout");
+        output_layout.set_description(
+            "Whether the output image should be `[height, width, channels]` (`hwc`) or `[channels, height, width]` (`chw`).",
+        );
+        output_layout.set_default_value("hwc");
+        output_layout.add_hint(&interpret_as_string_in_enum(Layout::NAMES));
+        metadata.add_argument(&output_layout);
+
+        let input = TensorMetadata::new("image");
+        input.set_description(
+            "An RGB image. Any number of channels is accepted when the colors are left alone.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::U8, ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0, 0]),
+        );
+        input.add_hint(&hint);
+        metadata.add_input(&input);
+
+        let output = TensorMetadata::new("output");
+        output.set_description(
+            "The converted image, in the requested layout.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::U8, ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0, 0]),
+        );
+        output.add_hint(&hint);
+        metadata.add_output(&output);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        let Settings {
+            element_type,
+            color,
+            input_layout,
+            output_layout,
+        } = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        let (input_channels, output_channels) = match color {
+            Color::Rgb => (0, 0),
+            Color::Bgr => (3, 3),
+            Color::Grayscale => (3, 1),
+        };
+
+        ctx.add_input_tensor(
+            "image",
+            element_type,
+            DimensionsParam::Fixed(&input_layout.dimensions(input_channels)),
+        );
+        ctx.add_output_tensor(
+            "output",
+            element_type,
+            DimensionsParam::Fixed(&output_layout.dimensions(output_channels)),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("image").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "image".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != settings.element_type {
+            return Err(invalid_input(format!(
+                "Expected a {} tensor, found {}",
+                settings.element_type, element_type
+            )));
+        }
+
+        let shape = match *dimensions {
+            [a, b, c] => (a as usize, b as usize, c as usize),
+            _ => {
+                return Err(invalid_input(format!(
+                    "Expected a 3D image, found {:?}",
+                    dimensions
+                )))
+            },
+        };
+
+        let (output, dimensions) = match element_type {
+            ElementType::U8 => {
+                let output = settings.apply::<u8>(&buffer, shape)?;
+                let bytes = output.as_slice().unwrap().as_bytes().to_vec();
+                (bytes, dimensions_of(&output))
+            },
+            ElementType::F32 => {
+                let output = settings.apply::<f32>(&buffer, shape)?;
+                let bytes = output.as_slice().unwrap().as_bytes().to_vec();
+                (bytes, dimensions_of(&output))
+            },
+            other => {
+                return Err(KernelError::Other(format!(
+                    "The Image Channels proc-block doesn't support {} images",
+                    other
+                )))
+            },
+        };
+
+        ctx.set_output_tensor(
+            "output",
+            TensorParam {
+                element_type,
+                dimensions: &dimensions,
+                buffer: &output,
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "image".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+fn dimensions_of<T>(array: &Array3<T>) -> [u32; 3] {
+    let (a, b, c) = array.dim();
+    [a as u32, b as u32, c as u32]
+}
+
+/// Convert the colors of an RGB image and rearrange it into the
+/// `output_layout`.
+///
+/// The returned array is always in standard (row-major) layout, so it can be
+/// passed straight to the runtime with [`Array3::as_slice()`].
+pub fn convert<T: Luma>(
+    image: ArrayView3<'_, T>,
+    color: Color,
+    input_layout: Layout,
+    output_layout: Layout,
+) -> Result<Array3<T>, ChannelError> {
+    let hwc = match input_layout {
+        Layout::Hwc => image,
+        Layout::Chw => image.permuted_axes([1, 2, 0]),
+    };
+    let (height, width, channels) = hwc.dim();
+
+    if color != Color::Rgb && channels != 3 {
+        return Err(ChannelError::NotRgb { channels });
+    }
+
+    let converted = match color {
+        Color::Rgb => hwc.to_owned(),
+        Color::Bgr => hwc.slice(s![.., .., ..;-1]).to_owned(),
+        Color::Grayscale => Array3::from_shape_fn(
+            (height, width, 1),
+            |(y, x, _)| {
+                T::luma(hwc[[y, x, 0]], hwc[[y, x, 1]], hwc[[y, x, 2]])
+            },
+        ),
+    };
+
+    let output = match output_layout {
+        Layout::Hwc => converted,
+        Layout::Chw => converted.permuted_axes([2, 0, 1]),
+    };
+
+    if output.is_standard_layout() {
+        Ok(output)
+    } else {
+        Ok(output.as_standard_layout().into_owned())
+    }
+}
+
+/// A pixel value which can be converted to grayscale.
+pub trait Luma: Copy {
+    /// Calculate the luma of a pixel using the ITU-R BT.601 weights.
+    fn luma(red: Self, green: Self, blue: Self) -> Self;
+}
+
+impl Luma for f32 {
+    fn luma(red: f32, green: f32, blue: f32) -> f32 {
+        0.299 * red + 0.587 * green + 0.114 * blue
+    }
+}
+
+impl Luma for u8 {
+    fn luma(red: u8, green: u8, blue: u8) -> u8 {
+        f32::luma(red as f32, green as f32, blue as f32).round() as u8
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChannelError {
+    NotRgb { channels: usize },
+}
+
+impl std::fmt::Display for ChannelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChannelError::NotRgb { channels } => write!(
+                f,
+                "Converting colors requires an RGB image with 3 channels, \
+                 found {}",
+                channels
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ChannelError {}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Color {
+    Rgb,
+    Bgr,
+    Grayscale,
+}
+
+impl Color {
+    pub const NAMES: &'static [&'static str] = &["rgb", "bgr", "grayscale"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rgb" => Some(Color::Rgb),
+            "bgr" => Some(Color::Bgr),
+            "grayscale" => Some(Color::Grayscale),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Layout {
+    /// `[height, width, channels]`.
+    Hwc,
+    /// `[channels, height, width]`.
+    Chw,
+}
+
+impl Layout {
+    pub const NAMES: &'static [&'static str] = &["hwc", "chw"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "hwc" => Some(Layout::Hwc),
+            "chw" => Some(Layout::Chw),
+            _ => None,
+        }
+    }
+
+    /// The dimensions of an arbitrarily sized image with this layout.
+    fn dimensions(self, channels: u32) -> [u32; 3] {
+        match self {
+            Layout::Hwc => [0, 0, channels],
+            Layout::Chw => [channels, 0, 0],
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    element_type: ElementType,
+    color: Color,
+    input_layout: Layout,
+    output_layout: Layout,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let element_type =
+            ctx.element_type::<E>(ElementType::U8, SUPPORTED_ELEMENT_TYPES)?;
+        let color = ctx
+            .parse_argument_with_default::<_, E>("color", String::from("rgb"))?;
+        let color = Color::from_name(&color).ok_or_else(|| {
+            E::invalid_argument(InvalidArgument::invalid_value(
+                "color",
+                format!(
+                    "Unknown color, \"{}\". Expected one of {:?}",
+                    color,
+                    Color::NAMES
+                ),
+            ))
+        })?;
+        let input_layout = layout::<_, E>(ctx, "input_layout")?;
+        let output_layout = layout::<_, E>(ctx, "output_layout")?;
+
+        Ok(Settings {
+            element_type,
+            color,
+            input_layout,
+            output_layout,
+        })
+    }
+
+    fn apply<T>(
+        &self,
+        buffer: &[u8],
+        shape: (usize, usize, usize),
+    ) -> Result<Array3<T>, KernelError>
+    where
+        T: Luma + ValueType,
+    {
+        let pixels = buffer.try_elements::<T>().map_err(invalid_input)?;
+        let image =
+            ArrayView3::from_shape(shape, pixels).map_err(invalid_input)?;
+
+        convert(image, self.color, self.input_layout, self.output_layout)
+            .map_err(invalid_input)
+    }
+}
+
+fn layout<C, E>(ctx: &C, name: &str) -> Result<Layout, E>
+where
+    C: ContextExt,
+    E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+{
+    let value =
+        ctx.parse_argument_with_default::<_, E>(name, String::from("hwc"))?;
+
+    Layout::from_name(&value).ok_or_else(|| {
+        E::invalid_argument(InvalidArgument::invalid_value(
+            name,
+            format!(
+                "Unknown layout, \"{}\". Expected one of {:?}",
+                value,
+                Layout::NAMES
+            ),
+        ))
+    })
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::ndarray::array;
+
+    /// A 1x2 RGB image with a red pixel and a blue pixel.
+    fn red_and_blue() -> Array3<u8> { array![[[255, 0, 0], [0, 0, 255]]] }
+
+    #[test]
+    fn swap_red_and_blue() {
+        let image = red_and_blue();
+
+        let got =
+            convert(image.view(), Color::Bgr, Layout::Hwc, Layout::Hwc)
+                .unwrap();
+
+        assert_eq!(got, array![[[0, 0, 255], [255, 0, 0]]]);
+    }
+
+    #[test]
+    fn grayscale_uses_luma_weights() {
+        let image: Array3<u8> =
+            array![[[255, 255, 255], [255, 0, 0], [0, 255, 0], [0, 0, 0]]];
+
+        let got = convert(
+            image.view(),
+            Color::Grayscale,
+            Layout::Hwc,
+            Layout::Hwc,
+        )
+        .unwrap();
+
+        assert_eq!(got, array![[[255], [76], [150], [0]]]);
+    }
+
+    #[test]
+    fn hwc_to_chw() {
+        let image = red_and_blue();
+
+        let got = convert(image.view(), Color::Rgb, Layout::Hwc, Layout::Chw)
+            .unwrap();
+
+        assert_eq!(got.dim(), (3, 1, 2));
+        assert!(got.is_standard_layout());
+        assert_eq!(got.as_slice().unwrap(), &[255, 0, 0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn chw_round_trip() {
+        let image = red_and_blue();
+        let chw = convert(image.view(), Color::Bgr, Layout::Hwc, Layout::Chw)
+            .unwrap();
+
+        let got = convert(chw.view(), Color::Bgr, Layout::Chw, Layout::Hwc)
+            .unwrap();
+
+        assert_eq!(got, image);
+    }
+
+    #[test]
+    fn layout_changes_work_with_any_number_of_channels() {
+        let image: Array3<f32> = array![[[1.0, 2.0, 3.0, 4.0]]];
+
+        let got = convert(image.view(), Color::Rgb, Layout::Hwc, Layout::Chw)
+            .unwrap();
+
+        assert_eq!(got.dim(), (4, 1, 1));
+    }
+
+    #[test]
+    fn color_conversions_need_rgb_images() {
+        let image: Array3<u8> = Array3::zeros((2, 2, 4));
+
+        let err = convert(
+            image.view(),
+            Color::Grayscale,
+            Layout::Hwc,
+            Layout::Hwc,
+        )
+        .unwrap_err();
+
+        assert_eq!(err, ChannelError::NotRgb { channels: 4 });
+    }
+}
@@ -0,0 +1,458 @@
+//! Decode an image file (PNG, JPEG, etc.) into a `[height, width, 3]` RGB8
+//! tensor.
+//!
+//! Every codec is behind a cargo feature of the same name (`bmp`, `gif`,
+//! `jpeg`, `png`, and `webp`), so deployments which only ever see one format
+//! can build a much smaller WebAssembly module with something like
+//! `--no-default-features --features png`. The codecs that were compiled in
+//! are listed in the proc-block's metadata, and trying to decode any other
+//! format is reported as an error naming that format.
+//!
+//! HEIC images (common on iOS) are recognised, but can't be decoded until
+//! there is a pure-Rust HEIC decoder we can compile to WebAssembly.
+
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{prelude::*, runtime_v1::*};
+use image::{ImageError, ImageFormat, RgbImage};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// The formats this build of the proc-block is able to decode.
+pub const SUPPORTED_FORMATS: &[ImageFormat] = &[
+    #[cfg(feature = "bmp")]
+    ImageFormat::Bmp,
+    #[cfg(feature = "gif")]
+    ImageFormat::Gif,
+    #[cfg(feature = "jpeg")]
+    ImageFormat::Jpeg,
+    #[cfg(feature = "png")]
+    ImageFormat::Png,
+    #[cfg(feature = "webp")]
+    ImageFormat::WebP,
+];
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Image Decode", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("image");
+        metadata.add_tag("decode");
+        for &format in SUPPORTED_FORMATS {
+            metadata.add_tag(format_name(format));
+        }
+
+        let format = ArgumentMetadata::new("format");
+        format.set_description(&format!(
+            "The image's format, or \"auto\" to detect it from the file's contents. This build supports {}.",
+            supported_format_names(),
+        ));
+        let mut names = vec!["auto"];
+        names.extend(SUPPORTED_FORMATS.iter().map(|&f| format_name(f)));
+        let hint = interpret_as_string_in_enum(&names);
+        format.add_hint(&hint);
+        format.set_default_value("auto");
+        metadata.add_argument(&format);
+
+        let input = TensorMetadata::new("bytes");
+        input.set_description("The raw bytes from an image file.");
+        let hint =
+            supported_shapes(&[ElementType::U8], DimensionsParam::Fixed(&[0]));
+        input.add_hint(&hint);
+        metadata.add_input(&input);
+
+        let output = TensorMetadata::new("image");
+        output.set_description(
+            "The decoded image as a `[height, width, channels]` RGB8 tensor.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::U8],
+            DimensionsParam::Fixed(&[0, 0, 3]),
+        );
+        output.add_hint(&hint);
+        metadata.add_output(&output);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "bytes",
+            ElementType::U8,
+            DimensionsParam::Fixed(&[0]),
+        );
+        ctx.add_output_tensor(
+            "image",
+            ElementType::U8,
+            DimensionsParam::Fixed(&[0, 0, 3]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let Settings { format } =
+            Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            buffer,
+            ..
+        } = ctx.get_input_tensor("bytes").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "bytes".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::U8 {
+            return Err(KernelError::InvalidInput(InvalidInput {
+                name: "bytes".to_string(),
+                reason: BadInputReason::InvalidValue(format!(
+                    "Expected a u8 tensor, found {}",
+                    element_type
+                )),
+            }));
+        }
+
+        let image = decode(&buffer, format).map_err(|e| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "bytes".to_string(),
+                reason: BadInputReason::InvalidValue(e.to_string()),
+            })
+        })?;
+        let (width, height) = image.dimensions();
+
+        ctx.set_output_tensor(
+            "image",
+            TensorParam {
+                element_type: ElementType::U8,
+                dimensions: &[height, width, 3],
+                buffer: image.as_raw(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+/// Decode an image, detecting its format from the first few bytes if
+/// `format` is [`Format::Auto`].
+pub fn decode(bytes: &[u8], format: Format) -> Result<RgbImage, DecodeError> {
+    let format = match format {
+        Format::Auto => match image::guess_format(bytes) {
+            Ok(format) => format,
+            Err(_) if is_heic(bytes) => return Err(DecodeError::Heic),
+            Err(_) => return Err(DecodeError::UnknownFormat),
+        },
+        Format::Known(format) => format,
+    };
+
+    if !SUPPORTED_FORMATS.contains(&format) {
+        return Err(DecodeError::UnsupportedFormat(format));
+    }
+
+    let image = image::load_from_memory_with_format(bytes, format)
+        .map_err(|error| DecodeError::Corrupt { format, error })?;
+
+    Ok(image.to_rgb8())
+}
+
+/// Does this look like a HEIC/HEIF file (an ISO Base Media File with one of
+/// the HEIF brands)?
+///
+/// The image crate doesn't know about HEIC, but we still want to give mobile
+/// clients a better error message than "unknown format".
+fn is_heic(bytes: &[u8]) -> bool {
+    const BRANDS: &[&[u8]] =
+        &[b"heic", b"heix", b"hevc", b"hevx", b"heim", b"heis", b"mif1"];
+
+    match bytes.get(4..12) {
+        Some(header) if &header[..4] == b"ftyp" => {
+            BRANDS.contains(&&header[4..])
+        },
+        _ => false,
+    }
+}
+
+/// The name used for a format in the `format` argument and error messages.
+pub fn format_name(format: ImageFormat) -> &'static str {
+    match format {
+        ImageFormat::Bmp => "bmp",
+        ImageFormat::Gif => "gif",
+        ImageFormat::Jpeg => "jpeg",
+        ImageFormat::Png => "png",
+        ImageFormat::WebP => "webp",
+        other => other.extensions_str().first().copied().unwrap_or("unknown"),
+    }
+}
+
+fn supported_format_names() -> String {
+    if SUPPORTED_FORMATS.is_empty() {
+        return "no formats".to_string();
+    }
+
+    SUPPORTED_FORMATS
+        .iter()
+        .map(|&f| format_name(f))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The bytes don't look like any image format we know about.
+    UnknownFormat,
+    /// The image's format wasn't enabled when this proc-block was compiled.
+    UnsupportedFormat(ImageFormat),
+    /// The image is a HEIC file, which we can't decode because there is no
+    /// pure-Rust HEIC decoder yet.
+    Heic,
+    Corrupt {
+        format: ImageFormat,
+        error: ImageError,
+    },
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnknownFormat => write!(
+                f,
+                "Unable to determine the image format (supported formats: {})",
+                supported_format_names()
+            ),
+            DecodeError::UnsupportedFormat(format) => write!(
+                f,
+                "The \"{}\" format isn't supported by this build of the proc-block (supported formats: {})",
+                format_name(*format),
+                supported_format_names()
+            ),
+            DecodeError::Heic => write!(
+                f,
+                "HEIC images aren't supported, please convert them to one of the supported formats ({}) first",
+                supported_format_names()
+            ),
+            DecodeError::Corrupt { format, error } => write!(
+                f,
+                "Unable to decode the {} image: {}",
+                format_name(*format),
+                error
+            ),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// The value of the `format` argument.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Auto,
+    Known(ImageFormat),
+}
+
+impl FromStr for Format {
+    type Err = UnknownFormatName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Format::Auto),
+            other => ImageFormat::from_extension(other)
+                .map(Format::Known)
+                .ok_or_else(|| UnknownFormatName(other.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownFormatName(String);
+
+impl Display for UnknownFormatName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\" isn't a known image format", self.0)
+    }
+}
+
+impl Error for UnknownFormatName {}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Settings {
+    format: Format,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let format =
+            ctx.parse_argument_with_default::<_, E>("format", Format::Auto)?;
+
+        if let Format::Known(f) = format {
+            if !SUPPORTED_FORMATS.contains(&f) {
+                return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                    "format",
+                    DecodeError::UnsupportedFormat(f),
+                )));
+            }
+        }
+
+        Ok(Settings { format })
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "png")]
+    fn decode_a_png() {
+        let mut original = RgbImage::new(3, 2);
+        original.put_pixel(2, 1, image::Rgb([255, 0, 128]));
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgb8(original.clone())
+            .write_to(&mut bytes, ImageFormat::Png)
+            .unwrap();
+
+        let got = decode(bytes.get_ref(), Format::Auto).unwrap();
+
+        assert_eq!(got, original);
+    }
+
+    #[test]
+    #[cfg(feature = "webp")]
+    fn decode_a_webp() {
+        // A 1x1 lossy WebP image
+        let bytes = b"RIFF\x22\x00\x00\x00WEBPVP8 \x16\x00\x00\x00\x30\x01\x00\x9d\x01\x2a\x01\x00\x01\x00\x0e\xc0\xfe\x25\xa4\x00\x03\x70\x00\x00\x00\x00";
+
+        let got = decode(bytes, Format::Auto).unwrap();
+
+        assert_eq!(got.dimensions(), (1, 1));
+    }
+
+    #[test]
+    fn heic_images_get_their_own_error() {
+        let bytes = b"\x00\x00\x00\x18ftypheic\x00\x00\x00\x00mif1heic";
+
+        let err = decode(bytes, Format::Auto).unwrap_err();
+
+        assert!(matches!(err, DecodeError::Heic));
+        assert!(err.to_string().contains("HEIC"));
+    }
+
+    #[test]
+    fn unrecognised_bytes() {
+        let err = decode(b"definitely not an image", Format::Auto).unwrap_err();
+
+        assert!(matches!(err, DecodeError::UnknownFormat));
+    }
+
+    #[test]
+    #[cfg(not(feature = "gif"))]
+    fn disabled_formats_are_named_in_the_error() {
+        let err = decode(b"GIF89a\x01\x00\x01\x00", Format::Auto).unwrap_err();
+
+        assert!(matches!(
+            err,
+            DecodeError::UnsupportedFormat(ImageFormat::Gif)
+        ));
+        assert!(err.to_string().contains("\"gif\""));
+    }
+
+    #[test]
+    #[cfg(feature = "png")]
+    fn garbage_with_a_png_header_is_corrupt() {
+        let bytes = b"\x89PNG\r\n\x1a\nwhoops";
+
+        let err = decode(bytes, Format::Auto).unwrap_err();
+
+        assert!(matches!(
+            err,
+            DecodeError::Corrupt {
+                format: ImageFormat::Png,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_format_argument() {
+        let inputs = [
+            ("auto", Format::Auto),
+            ("png", Format::Known(ImageFormat::Png)),
+            ("jpeg", Format::Known(ImageFormat::Jpeg)),
+            ("jpg", Format::Known(ImageFormat::Jpeg)),
+            ("webp", Format::Known(ImageFormat::WebP)),
+        ];
+
+        for (input, should_be) in inputs {
+            let got: Format = input.parse().unwrap();
+            assert_eq!(got, should_be, "{}", input);
+        }
+
+        assert!("tiff-ish".parse::<Format>().is_err());
+    }
+}
@@ -0,0 +1,590 @@
+//! Fit a `[height, width, channels]` image to a fixed size without
+//! distorting it.
+//!
+//! There are two ways to do that:
+//!
+//! - `letterbox` scales the image so it fits inside the target size and pads
+//!   the leftover space, which is what YOLO-style detectors expect
+//! - `center_crop` scales the image so it covers the target size and throws
+//!   away whatever hangs over the edges
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::{s, Array3, ArrayView3},
+    prelude::*,
+    runtime_v1::*,
+    BufferExt, Dimension, SliceExt, ValueType,
+};
+use image_resize_core::{Interpolation, ResizeError, Subpixel};
+use num_traits::NumCast;
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+const SUPPORTED_ELEMENT_TYPES: &[&str] = &["u8", "f32"];
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Image Geometry", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("image");
+        metadata.add_tag("resize");
+        metadata.add_tag("letterbox");
+
+        let element_type = ArgumentMetadata::element_type_in(
+            SUPPORTED_ELEMENT_TYPES,
+            ElementType::U8,
+        );
+        element_type.set_description("The type of the image's pixels.");
+        metadata.add_argument(&element_type);
+
+        let mode = ArgumentMetadata::new("mode");
+        mode.set_description(
+            "`letterbox` shrinks the image to fit inside the target size and pads the edges, while `center_crop` fills the target size and crops whatever doesn't fit.",
+        );
+        mode.set_default_value("letterbox");
+        mode.add_hint(&interpret_as_string_in_enum(Mode::NAMES));
+        metadata.add_argument(&mode);
+
+        let width = ArgumentMetadata::new("width");
+        width.set_description("The width of the output image, in pixels.");
+        width.add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&width);
+
+        let height = ArgumentMetadata::new("height");
+        height.set_description("The height of the output image, in pixels.");
+        height
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&height);
+
+        let interpolation = ArgumentMetadata::new("interpolation");
+        interpolation
+            .set_description("How to calculate pixel values when scaling.");
+        interpolation.set_default_value("bilinear");
+        interpolation
+            .add_hint(&interpret_as_string_in_enum(Interpolation::NAMES));
+        metadata.add_argument(&interpolation);
+
+        let pad_value = ArgumentMetadata::new("pad_value");
+        pad_value.set_description(
+            "The value used for padding when letterboxing (YOLO models normally use 114).",
+        );
+        pad_value.set_default_value("0");
+        pad_value.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&pad_value);
+
+        let input = TensorMetadata::new("image");
+        input.set_description(
+            "An image tensor with the shape `[height, width, channels]`.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::U8, ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0, 0]),
+        );
+        input.add_hint(&hint);
+        metadata.add_input(&input);
+
+        let output = TensorMetadata::new("output");
+        output.set_description(
+            "The image, scaled and padded or cropped to exactly `[height, width, channels]`.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::U8, ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0, 0]),
+        );
+        output.add_hint(&hint);
+        metadata.add_output(&output);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        let Settings { element_type, .. } =
+            Settings::from_context::<_, GraphError>(&ctx)?;
+
+        let output_dimensions = ctx.resolve_dimensions::<GraphError>(&[
+            Dimension::FromArgument("height"),
+            Dimension::FromArgument("width"),
+            Dimension::Dynamic,
+        ])?;
+
+        ctx.add_input_tensor(
+            "image",
+            element_type,
+            DimensionsParam::Fixed(&[0, 0, 0]),
+        );
+        ctx.add_output_tensor(
+            "output",
+            element_type,
+            DimensionsParam::Fixed(&output_dimensions),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("image").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "image".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != settings.element_type {
+            return Err(invalid_input(format!(
+                "Expected a {} tensor, found {}",
+                settings.element_type, element_type
+            )));
+        }
+
+        let shape = match *dimensions {
+            [h, w, c] => [h, w, c],
+            _ => {
+                return Err(invalid_input(format!(
+                    "Expected a [height, width, channels] image, found {:?}",
+                    dimensions
+                )))
+            },
+        };
+
+        let output = match element_type {
+            ElementType::U8 => {
+                settings.apply::<u8>(&buffer, shape)?.as_bytes().to_vec()
+            },
+            ElementType::F32 => {
+                settings.apply::<f32>(&buffer, shape)?.as_bytes().to_vec()
+            },
+            other => {
+                return Err(KernelError::Other(format!(
+                    "The Image Geometry proc-block doesn't support {} images",
+                    other
+                )))
+            },
+        };
+
+        ctx.set_output_tensor(
+            "output",
+            TensorParam {
+                element_type,
+                dimensions: &[settings.height, settings.width, shape[2]],
+                buffer: &output,
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "image".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+/// Scale a `[height, width, channels]` image to exactly `target_width` x
+/// `target_height` while preserving its aspect ratio, either padding it with
+/// `pad_value` or cropping it depending on the [`Mode`].
+pub fn transform<T>(
+    pixels: &[T],
+    [height, width, channels]: [u32; 3],
+    target_width: u32,
+    target_height: u32,
+    mode: Mode,
+    interpolation: Interpolation,
+    pad_value: T,
+) -> Result<Vec<T>, GeometryError>
+where
+    T: Subpixel,
+{
+    if height == 0 || width == 0 {
+        return Err(GeometryError::EmptyImage);
+    }
+
+    let shape = (height as usize, width as usize, channels as usize);
+    let image = ArrayView3::from_shape(shape, pixels).map_err(|_| {
+        ResizeError::LengthMismatch {
+            expected: shape.0 * shape.1 * shape.2,
+            actual: pixels.len(),
+        }
+    })?;
+
+    let x_scale = target_width as f64 / width as f64;
+    let y_scale = target_height as f64 / height as f64;
+
+    match mode {
+        Mode::Letterbox => {
+            let scale = f64::min(x_scale, y_scale);
+            let scaled_width = scaled(width, scale, target_width);
+            let scaled_height = scaled(height, scale, target_height);
+
+            let resized = image_resize_core::resize(
+                pixels,
+                [height, width, channels],
+                scaled_width,
+                scaled_height,
+                interpolation,
+            )?;
+            let resized = ArrayView3::from_shape(
+                (
+                    scaled_height as usize,
+                    scaled_width as usize,
+                    channels as usize,
+                ),
+                &resized,
+            )
+            .expect("The resized image has the requested size");
+
+            let top = ((target_height - scaled_height) / 2) as usize;
+            let left = ((target_width - scaled_width) / 2) as usize;
+
+            let mut output = Array3::from_elem(
+                (
+                    target_height as usize,
+                    target_width as usize,
+                    channels as usize,
+                ),
+                pad_value,
+            );
+            output
+                .slice_mut(s![
+                    top..top + scaled_height as usize,
+                    left..left + scaled_width as usize,
+                    ..
+                ])
+                .assign(&resized);
+
+            Ok(output.into_raw_vec())
+        },
+        Mode::CenterCrop => {
+            let scale = f64::max(x_scale, y_scale);
+            let crop_width = scaled(target_width, 1.0 / scale, width);
+            let crop_height = scaled(target_height, 1.0 / scale, height);
+
+            let top = ((height - crop_height) / 2) as usize;
+            let left = ((width - crop_width) / 2) as usize;
+
+            let cropped: Vec<T> = image
+                .slice(s![
+                    top..top + crop_height as usize,
+                    left..left + crop_width as usize,
+                    ..
+                ])
+                .iter()
+                .copied()
+                .collect();
+
+            image_resize_core::resize(
+                &cropped,
+                [crop_height, crop_width, channels],
+                target_width,
+                target_height,
+                interpolation,
+            )
+            .map_err(GeometryError::from)
+        },
+    }
+}
+
+/// Scale a length, making sure it stays within `1..=max`.
+fn scaled(length: u32, scale: f64, max: u32) -> u32 {
+    let length = (length as f64 * scale).round() as u32;
+    length.clamp(1, max)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GeometryError {
+    EmptyImage,
+    Resize(ResizeError),
+}
+
+impl From<ResizeError> for GeometryError {
+    fn from(e: ResizeError) -> Self { GeometryError::Resize(e) }
+}
+
+impl std::fmt::Display for GeometryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GeometryError::EmptyImage => {
+                write!(f, "The image must have a non-zero width and height")
+            },
+            GeometryError::Resize(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for GeometryError {}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Mode {
+    Letterbox,
+    CenterCrop,
+}
+
+impl Mode {
+    pub const NAMES: &'static [&'static str] = &["letterbox", "center_crop"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "letterbox" => Some(Mode::Letterbox),
+            "center_crop" => Some(Mode::CenterCrop),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    element_type: ElementType,
+    mode: Mode,
+    width: u32,
+    height: u32,
+    interpolation: Interpolation,
+    pad_value: f64,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let element_type =
+            ctx.element_type::<E>(ElementType::U8, SUPPORTED_ELEMENT_TYPES)?;
+        let mode = ctx.parse_argument_with_default::<_, E>(
+            "mode",
+            String::from("letterbox"),
+        )?;
+        let width: u32 = ctx.parse_argument::<_, E>("width")?;
+        let height: u32 = ctx.parse_argument::<_, E>("height")?;
+        let interpolation = ctx.parse_argument_with_default::<_, E>(
+            "interpolation",
+            String::from("bilinear"),
+        )?;
+        let pad_value =
+            ctx.parse_argument_with_default::<f64, E>("pad_value", 0.0)?;
+
+        for (name, value) in [("width", width), ("height", height)] {
+            if value == 0 {
+                return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                    name,
+                    "The output image can't be empty",
+                )));
+            }
+        }
+
+        let mode = Mode::from_name(&mode).ok_or_else(|| {
+            E::invalid_argument(InvalidArgument::invalid_value(
+                "mode",
+                format!(
+                    "Unknown mode, \"{}\". Expected one of {:?}",
+                    mode,
+                    Mode::NAMES
+                ),
+            ))
+        })?;
+        let interpolation = Interpolation::from_name(&interpolation)
+            .ok_or_else(|| {
+                E::invalid_argument(InvalidArgument::invalid_value(
+                    "interpolation",
+                    format!(
+                        "Unknown interpolation, \"{}\". Expected one of {:?}",
+                        interpolation,
+                        Interpolation::NAMES
+                    ),
+                ))
+            })?;
+
+        Ok(Settings {
+            element_type,
+            mode,
+            width,
+            height,
+            interpolation,
+            pad_value,
+        })
+    }
+
+    fn apply<T>(
+        &self,
+        buffer: &[u8],
+        shape: [u32; 3],
+    ) -> Result<Vec<T>, KernelError>
+    where
+        T: Subpixel + ValueType,
+    {
+        let pixels = buffer
+            .try_elements_with_shape::<T>(&shape)
+            .map_err(invalid_input)?;
+        let pad_value = <T as NumCast>::from(self.pad_value).ok_or_else(|| {
+            KernelError::InvalidArgument(InvalidArgument::invalid_value(
+                "pad_value",
+                format!(
+                    "{} can't be used to pad a {} image",
+                    self.pad_value, self.element_type
+                ),
+            ))
+        })?;
+
+        transform(
+            pixels,
+            shape,
+            self.width,
+            self.height,
+            self.mode,
+            self.interpolation,
+            pad_value,
+        )
+        .map_err(invalid_input)
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIXELS: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+    #[test]
+    fn letterbox_a_wide_image() {
+        let got = transform(
+            &PIXELS,
+            [2, 4, 1],
+            4,
+            4,
+            Mode::Letterbox,
+            Interpolation::Nearest,
+            0,
+        )
+        .unwrap();
+
+        #[rustfmt::skip]
+        let should_be = [
+            0, 0, 0, 0,
+            1, 2, 3, 4,
+            5, 6, 7, 8,
+            0, 0, 0, 0,
+        ];
+        assert_eq!(got, should_be);
+    }
+
+    #[test]
+    fn letterboxing_shrinks_images_which_are_too_big() {
+        let pixels = [9_u8; 8 * 4 * 3];
+
+        let got = transform(
+            &pixels,
+            [4, 8, 3],
+            4,
+            4,
+            Mode::Letterbox,
+            Interpolation::Bilinear,
+            114,
+        )
+        .unwrap();
+
+        let rows: Vec<_> = got.chunks(4 * 3).collect();
+        assert_eq!(rows.len(), 4);
+        assert!(rows[0].iter().all(|&p| p == 114));
+        assert!(rows[1].iter().all(|&p| p == 9));
+        assert!(rows[2].iter().all(|&p| p == 9));
+        assert!(rows[3].iter().all(|&p| p == 114));
+    }
+
+    #[test]
+    fn center_crop_a_wide_image() {
+        let got = transform(
+            &PIXELS,
+            [2, 4, 1],
+            2,
+            2,
+            Mode::CenterCrop,
+            Interpolation::Nearest,
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(got, [2, 3, 6, 7]);
+    }
+
+    #[test]
+    fn empty_images_are_rejected() {
+        let err = transform(
+            &[0_u8; 0],
+            [0, 4, 3],
+            2,
+            2,
+            Mode::Letterbox,
+            Interpolation::Nearest,
+            0,
+        )
+        .unwrap_err();
+
+        assert_eq!(err, GeometryError::EmptyImage);
+    }
+
+    #[test]
+    fn parse_modes() {
+        for &name in Mode::NAMES {
+            assert!(Mode::from_name(name).is_some(), "{}", name);
+        }
+        assert_eq!(Mode::from_name("stretch"), None);
+    }
+}
@@ -40,11 +40,19 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
 
         let pixel_format = ArgumentMetadata::new("pixel_format");
         pixel_format.set_description("The pixel format.");
-        let hint = runtime_v1::non_negative_number();
+        let hint = runtime_v1::interpret_as_string_in_enum(&["rgb8", "rgb"]);
         pixel_format.add_hint(&hint);
         metadata.add_argument(&pixel_format);
 
-        let output = TensorMetadata::new("image");
+        let input = TensorMetadata::new("image");
+        let hint = supported_shapes(
+            &[ElementType::U8, ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0, 0, 0]),
+        );
+        input.add_hint(&hint);
+        metadata.add_input(&input);
+
+        let output = TensorMetadata::new("output");
         let hint = supported_shapes(
             &[ElementType::U8, ElementType::F32],
             DimensionsParam::Fixed(&[0, 0, 0, 0]),
@@ -92,9 +100,9 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             element_type,
             dimensions,
             buffer,
-        } = ctx.get_input_tensor("input").ok_or_else(|| {
+        } = ctx.get_input_tensor("image").ok_or_else(|| {
             KernelError::InvalidInput(InvalidInput {
-                name: "input".to_string(),
+                name: "image".to_string(),
                 reason: BadInputReason::NotFound,
             })
         })?;
@@ -0,0 +1,288 @@
+//! Resize an already-decoded `[height, width, channels]` image tensor.
+//!
+//! Nearest-neighbour resizing is fast, but the aliasing it introduces can
+//! hurt a model's accuracy noticeably, so the `interpolation` argument lets
+//! pipelines pick a smoother (and slower) filter.
+
+use hotg_rune_proc_blocks::{
+    prelude::*, runtime_v1::*, BufferExt, Dimension, SliceExt,
+};
+use image_resize_core::{resize, Interpolation};
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+const SUPPORTED_ELEMENT_TYPES: &[&str] = &["u8", "f32"];
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Image Resize", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("image");
+        metadata.add_tag("resize");
+
+        let element_type = ArgumentMetadata::element_type_in(
+            SUPPORTED_ELEMENT_TYPES,
+            ElementType::U8,
+        );
+        element_type.set_description("The type of the image's pixels.");
+        metadata.add_argument(&element_type);
+
+        let width = ArgumentMetadata::new("width");
+        width.set_description("The width of the resized image, in pixels.");
+        width.add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&width);
+
+        let height = ArgumentMetadata::new("height");
+        height.set_description("The height of the resized image, in pixels.");
+        height
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&height);
+
+        let interpolation = ArgumentMetadata::new("interpolation");
+        interpolation.set_description(
+            "How to calculate new pixel values. Nearest-neighbour is the fastest, while Lanczos gives the sharpest results.",
+        );
+        interpolation.set_default_value("bilinear");
+        interpolation
+            .add_hint(&interpret_as_string_in_enum(Interpolation::NAMES));
+        metadata.add_argument(&interpolation);
+
+        let input = TensorMetadata::new("image");
+        input.set_description(
+            "An image tensor with the shape `[height, width, channels]`, where there are 1 (grayscale), 2 (grayscale + alpha), 3 (RGB), or 4 (RGBA) channels.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::U8, ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0, 0]),
+        );
+        input.add_hint(&hint);
+        metadata.add_input(&input);
+
+        let output = TensorMetadata::new("resized");
+        output.set_description(
+            "The resized image, with the same number of channels.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::U8, ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0, 0]),
+        );
+        output.add_hint(&hint);
+        metadata.add_output(&output);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        let Settings { element_type, .. } =
+            Settings::from_context::<_, GraphError>(&ctx)?;
+
+        let output_dimensions = ctx.resolve_dimensions::<GraphError>(&[
+            Dimension::FromArgument("height"),
+            Dimension::FromArgument("width"),
+            Dimension::Dynamic,
+        ])?;
+
+        ctx.add_input_tensor(
+            "image",
+            element_type,
+            DimensionsParam::Fixed(&[0, 0, 0]),
+        );
+        ctx.add_output_tensor(
+            "resized",
+            element_type,
+            DimensionsParam::Fixed(&output_dimensions),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let Settings {
+            element_type: expected_type,
+            width,
+            height,
+            interpolation,
+        } = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("image").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "image".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != expected_type {
+            return Err(invalid_input(format!(
+                "Expected a {} tensor, found {}",
+                expected_type, element_type
+            )));
+        }
+
+        let shape = match *dimensions {
+            [h, w, c] => [h, w, c],
+            _ => {
+                return Err(invalid_input(format!(
+                    "Expected a [height, width, channels] image, found {:?}",
+                    dimensions
+                )))
+            },
+        };
+        let channels = shape[2];
+
+        let resized = match element_type {
+            ElementType::U8 => {
+                let pixels = buffer
+                    .try_elements_with_shape::<u8>(&shape)
+                    .map_err(invalid_input)?;
+                resize(pixels, shape, width, height, interpolation)
+                    .map_err(invalid_input)?
+                    .as_bytes()
+                    .to_vec()
+            },
+            ElementType::F32 => {
+                let pixels = buffer
+                    .try_elements_with_shape::<f32>(&shape)
+                    .map_err(invalid_input)?;
+                resize(pixels, shape, width, height, interpolation)
+                    .map_err(invalid_input)?
+                    .as_bytes()
+                    .to_vec()
+            },
+            other => {
+                return Err(KernelError::Other(format!(
+                    "The Image Resize proc-block doesn't support {} images",
+                    other
+                )))
+            },
+        };
+
+        ctx.set_output_tensor(
+            "resized",
+            TensorParam {
+                element_type,
+                dimensions: &[height, width, channels],
+                buffer: &resized,
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "image".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    element_type: ElementType,
+    width: u32,
+    height: u32,
+    interpolation: Interpolation,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let element_type =
+            ctx.element_type::<E>(ElementType::U8, SUPPORTED_ELEMENT_TYPES)?;
+        let width: u32 = ctx.parse_argument::<_, E>("width")?;
+        let height: u32 = ctx.parse_argument::<_, E>("height")?;
+        let interpolation = ctx.parse_argument_with_default::<_, E>(
+            "interpolation",
+            String::from("bilinear"),
+        )?;
+
+        for (name, value) in [("width", width), ("height", height)] {
+            if value == 0 {
+                return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                    name,
+                    "The resized image can't be empty",
+                )));
+            }
+        }
+
+        let interpolation = Interpolation::from_name(&interpolation)
+            .ok_or_else(|| {
+                E::invalid_argument(InvalidArgument::invalid_value(
+                    "interpolation",
+                    format!(
+                        "Unknown interpolation, \"{}\". Expected one of {:?}",
+                        interpolation,
+                        Interpolation::NAMES
+                    ),
+                ))
+            })?;
+
+        Ok(Settings {
+            element_type,
+            width,
+            height,
+            interpolation,
+        })
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
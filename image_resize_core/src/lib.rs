@@ -0,0 +1,242 @@
+//! Resize an already-decoded `[height, width, channels]` image tensor.
+//!
+//! Nearest-neighbour resizing is fast, but the aliasing it introduces can
+//! hurt a model's accuracy noticeably, so the `interpolation` argument lets
+//! pipelines pick a smoother (and slower) filter.
+//!
+//! This is the algorithm behind the `image_resize` proc-block, without any of
+//! the WebAssembly bindings, so it can be used as a normal Rust library.
+
+use image::{
+    imageops::{self, FilterType},
+    ImageBuffer, Luma, LumaA, Pixel, Primitive, Rgb, Rgba,
+};
+
+/// Resize a `[height, width, channels]` image to `new_width` x `new_height`.
+pub fn resize<T>(
+    pixels: &[T],
+    [height, width, channels]: [u32; 3],
+    new_width: u32,
+    new_height: u32,
+    interpolation: Interpolation,
+) -> Result<Vec<T>, ResizeError>
+where
+    T: Subpixel,
+{
+    let expected_len = height as usize * width as usize * channels as usize;
+    if pixels.len() != expected_len {
+        return Err(ResizeError::LengthMismatch {
+            expected: expected_len,
+            actual: pixels.len(),
+        });
+    }
+
+    let size = (width, height);
+    let new_size = (new_width, new_height);
+    let filter = interpolation.filter_type();
+
+    T::resize_channels(pixels, channels, size, new_size, filter)
+        .ok_or(ResizeError::UnsupportedChannels(channels))
+}
+
+/// A pixel component type that can be resized.
+///
+/// The `image` crate only implements its colour types for a fixed set of
+/// primitives, so this is implemented for the same ones.
+pub trait Subpixel: Primitive + 'static {
+    #[doc(hidden)]
+    fn resize_channels(
+        pixels: &[Self],
+        channels: u32,
+        size: (u32, u32),
+        new_size: (u32, u32),
+        filter: FilterType,
+    ) -> Option<Vec<Self>>;
+}
+
+macro_rules! impl_subpixel {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl Subpixel for $ty {
+                fn resize_channels(
+                    pixels: &[Self],
+                    channels: u32,
+                    size: (u32, u32),
+                    new_size: (u32, u32),
+                    filter: FilterType,
+                ) -> Option<Vec<Self>> {
+                    let resized = match channels {
+                        1 => resize_pixels::<Luma<$ty>>(
+                            pixels, size, new_size, filter,
+                        ),
+                        2 => resize_pixels::<LumaA<$ty>>(
+                            pixels, size, new_size, filter,
+                        ),
+                        3 => resize_pixels::<Rgb<$ty>>(
+                            pixels, size, new_size, filter,
+                        ),
+                        4 => resize_pixels::<Rgba<$ty>>(
+                            pixels, size, new_size, filter,
+                        ),
+                        _ => return None,
+                    };
+
+                    Some(resized)
+                }
+            }
+        )*
+    };
+}
+
+impl_subpixel!(u8, u16, f32);
+
+fn resize_pixels<P>(
+    pixels: &[P::Subpixel],
+    (width, height): (u32, u32),
+    (new_width, new_height): (u32, u32),
+    filter: FilterType,
+) -> Vec<P::Subpixel>
+where
+    P: Pixel + 'static,
+    P::Subpixel: 'static,
+{
+    let image: ImageBuffer<P, &[P::Subpixel]> =
+        ImageBuffer::from_raw(width, height, pixels)
+            .expect("The length was already checked");
+
+    imageops::resize(&image, new_width, new_height, filter).into_raw()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResizeError {
+    UnsupportedChannels(u32),
+    LengthMismatch { expected: usize, actual: usize },
+}
+
+impl std::fmt::Display for ResizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResizeError::UnsupportedChannels(channels) => write!(
+                f,
+                "Images must have between 1 and 4 channels, found {}",
+                channels
+            ),
+            ResizeError::LengthMismatch { expected, actual } => write!(
+                f,
+                "Expected {} pixel values, found {}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ResizeError {}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Interpolation {
+    Nearest,
+    Bilinear,
+    Bicubic,
+    Lanczos,
+}
+
+impl Interpolation {
+    pub const NAMES: &'static [&'static str] =
+        &["nearest", "bilinear", "bicubic", "lanczos"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "nearest" => Some(Interpolation::Nearest),
+            "bilinear" => Some(Interpolation::Bilinear),
+            "bicubic" => Some(Interpolation::Bicubic),
+            "lanczos" => Some(Interpolation::Lanczos),
+            _ => None,
+        }
+    }
+
+    fn filter_type(self) -> FilterType {
+        match self {
+            Interpolation::Nearest => FilterType::Nearest,
+            Interpolation::Bilinear => FilterType::Triangle,
+            Interpolation::Bicubic => FilterType::CatmullRom,
+            Interpolation::Lanczos => FilterType::Lanczos3,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::testing::{assert_slices_close, Tolerance};
+
+    #[test]
+    fn nearest_neighbour_upscaling_duplicates_pixels() {
+        let pixels = [1_u8, 2, 3, 4];
+
+        let got = resize(&pixels, [2, 2, 1], 4, 4, Interpolation::Nearest)
+            .unwrap();
+
+        assert_eq!(got, vec![1, 1, 2, 2, 1, 1, 2, 2, 3, 3, 4, 4, 3, 3, 4, 4]);
+    }
+
+    #[test]
+    fn every_interpolation_keeps_solid_colours() {
+        let pixels: Vec<f32> = [0.25, 0.5, 0.75]
+            .iter()
+            .copied()
+            .cycle()
+            .take(6 * 8 * 3)
+            .collect();
+
+        for &name in Interpolation::NAMES {
+            let interpolation = Interpolation::from_name(name).unwrap();
+
+            let got = resize(&pixels, [6, 8, 3], 5, 3, interpolation).unwrap();
+
+            assert_eq!(got.len(), 3 * 5 * 3, "{}", name);
+            for pixel in got.chunks(3) {
+                assert_slices_close(
+                    pixel,
+                    &[0.25, 0.5, 0.75],
+                    Tolerance::absolute(1e-4),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn bilinear_downscaling_blends_neighbours() {
+        let pixels = [0_u8, 255, 0, 255];
+
+        let got = resize(&pixels, [1, 4, 1], 2, 1, Interpolation::Bilinear)
+            .unwrap();
+
+        assert!(got.iter().all(|&p| p > 0 && p < 255), "{:?}", got);
+    }
+
+    #[test]
+    fn too_many_channels() {
+        let pixels = [0_u8; 5];
+
+        let err = resize(&pixels, [1, 1, 5], 2, 2, Interpolation::Nearest)
+            .unwrap_err();
+
+        assert_eq!(err, ResizeError::UnsupportedChannels(5));
+    }
+
+    #[test]
+    fn wrong_number_of_pixels() {
+        let pixels = [0_u8; 5];
+
+        let err = resize(&pixels, [2, 2, 1], 2, 2, Interpolation::Nearest)
+            .unwrap_err();
+
+        assert_eq!(
+            err,
+            ResizeError::LengthMismatch {
+                expected: 4,
+                actual: 5
+            }
+        );
+    }
+}
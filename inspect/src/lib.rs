@@ -0,0 +1,455 @@
+//! A pass-through proc-block which logs the name, element type, shape, range
+//! and a small sample of the tensor flowing through it.
+//!
+//! This makes it possible to see what is going on inside a pipeline without
+//! recompiling every proc-block with `println!()`s.
+
+use std::{
+    error::Error,
+    fmt::{self, Debug, Display, Formatter},
+    str::FromStr,
+};
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{prelude::*, runtime_v1::*, BufferExt, ValueType};
+use num_traits::ToPrimitive;
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata = Metadata::new("Inspect", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("debug");
+
+        let element_type = ArgumentMetadata::element_type();
+        metadata.add_argument(&element_type);
+
+        let label = ArgumentMetadata::new("label");
+        label.set_description(
+            "A name used to identify this tensor in the log messages.",
+        );
+        label.set_default_value("tensor");
+        metadata.add_argument(&label);
+
+        let expected_shape = ArgumentMetadata::new("expected_shape");
+        expected_shape.set_description(
+            "If provided, fail when the input doesn't have this shape (e.g. `1, *, *, 3`). Use `*` for dimensions that can be any size.",
+        );
+        metadata.add_argument(&expected_shape);
+
+        let sample_size = ArgumentMetadata::new("sample_size");
+        sample_size
+            .set_description("How many of the tensor's elements to log.");
+        sample_size.set_default_value("8");
+        sample_size.add_hint(&non_negative_number());
+        metadata.add_argument(&sample_size);
+
+        let input = TensorMetadata::new("input");
+        input.set_description("The tensor to inspect.");
+        metadata.add_input(&input);
+
+        let output = TensorMetadata::new("output");
+        output.set_description("The input tensor, unchanged.");
+        metadata.add_output(&output);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        let element_type: ElementType =
+            ctx.parse_argument_with_default("element_type", ElementType::F32)?;
+        let expected_shape: Option<ExpectedShape> =
+            match ctx.get_argument("expected_shape") {
+                Some(_) => Some(ctx.parse_argument("expected_shape")?),
+                None => None,
+            };
+
+        let fixed = expected_shape.map(|s| s.to_dimensions());
+        let dimensions = || match &fixed {
+            Some(fixed) => DimensionsParam::Fixed(fixed),
+            None => DimensionsParam::Dynamic,
+        };
+
+        ctx.add_input_tensor("input", element_type, dimensions());
+        ctx.add_output_tensor("output", element_type, dimensions());
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let label: String =
+            ctx.parse_argument_with_default("label", String::from("tensor"))?;
+        let sample_size: usize =
+            ctx.parse_argument_with_default("sample_size", 8)?;
+        let expected_shape: Option<ExpectedShape> =
+            match ctx.get_argument("expected_shape") {
+                Some(_) => Some(ctx.parse_argument("expected_shape")?),
+                None => None,
+            };
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("input").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "input".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        let summary = summarize(element_type, &buffer, sample_size)?;
+        let shape = format!("{:?}", dimensions);
+        let element_type_name = element_type.to_string();
+
+        let mut data = vec![
+            ("label", LogValue::String(&label)),
+            ("element_type", LogValue::String(&element_type_name)),
+            ("shape", LogValue::String(&shape)),
+            ("sample", LogValue::String(&summary.sample)),
+        ];
+        if let Some(min) = summary.min {
+            data.push(("min", LogValue::Float(min)));
+        }
+        if let Some(max) = summary.max {
+            data.push(("max", LogValue::Float(max)));
+        }
+
+        log(
+            LogMetadata {
+                file: Some(file!()),
+                level: LogLevel::Info,
+                line: Some(line!()),
+                module: Some(module_path!()),
+                name: "inspect",
+                target: module_path!(),
+            },
+            &format!("{}: {} {} {}", label, element_type, shape, summary),
+            &data,
+        );
+
+        if let Some(expected_shape) = expected_shape {
+            if !expected_shape.matches(&dimensions) {
+                return Err(KernelError::InvalidInput(InvalidInput {
+                    name: "input".to_string(),
+                    reason: BadInputReason::InvalidValue(format!(
+                        "Expected \"{}\" to have a shape of [{}], but found {}",
+                        label, expected_shape, shape
+                    )),
+                }));
+            }
+        }
+
+        ctx.set_output_tensor(
+            "output",
+            TensorParam {
+                element_type,
+                dimensions: &dimensions,
+                buffer: &buffer,
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn summarize(
+    element_type: ElementType,
+    buffer: &[u8],
+    sample_size: usize,
+) -> Result<Summary, KernelError> {
+    let summary = match element_type {
+        ElementType::U8 => {
+            Summary::numeric(elements::<u8>(buffer)?, sample_size)
+        },
+        ElementType::I8 => {
+            Summary::numeric(elements::<i8>(buffer)?, sample_size)
+        },
+        ElementType::U16 => {
+            Summary::numeric(elements::<u16>(buffer)?, sample_size)
+        },
+        ElementType::I16 => {
+            Summary::numeric(elements::<i16>(buffer)?, sample_size)
+        },
+        ElementType::U32 => {
+            Summary::numeric(elements::<u32>(buffer)?, sample_size)
+        },
+        ElementType::I32 => {
+            Summary::numeric(elements::<i32>(buffer)?, sample_size)
+        },
+        ElementType::F32 => {
+            Summary::numeric(elements::<f32>(buffer)?, sample_size)
+        },
+        ElementType::U64 => {
+            Summary::numeric(elements::<u64>(buffer)?, sample_size)
+        },
+        ElementType::I64 => {
+            Summary::numeric(elements::<i64>(buffer)?, sample_size)
+        },
+        ElementType::F64 => {
+            Summary::numeric(elements::<f64>(buffer)?, sample_size)
+        },
+        ElementType::Utf8 => {
+            let strings = buffer.strings().map_err(invalid_input)?;
+            Summary::strings(&strings, sample_size)
+        },
+    };
+
+    Ok(summary)
+}
+
+fn elements<T: ValueType>(buffer: &[u8]) -> Result<&[T], KernelError> {
+    buffer.try_elements().map_err(invalid_input)
+}
+
+fn invalid_input(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "input".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+/// A short description of a tensor's contents.
+#[derive(Debug, Clone, PartialEq)]
+struct Summary {
+    min: Option<f64>,
+    max: Option<f64>,
+    sample: String,
+}
+
+impl Summary {
+    fn numeric<T>(values: &[T], sample_size: usize) -> Self
+    where
+        T: ToPrimitive + Debug,
+    {
+        let range = values
+            .iter()
+            .filter_map(|v| v.to_f64())
+            .filter(|v| !v.is_nan())
+            .fold(None, |range, v| match range {
+                Some((min, max)) => Some((f64::min(min, v), f64::max(max, v))),
+                None => Some((v, v)),
+            });
+
+        Summary {
+            min: range.map(|(min, _)| min),
+            max: range.map(|(_, max)| max),
+            sample: sample(values, sample_size),
+        }
+    }
+
+    fn strings(values: &[&str], sample_size: usize) -> Self {
+        Summary {
+            min: None,
+            max: None,
+            sample: sample(values, sample_size),
+        }
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let (Some(min), Some(max)) = (self.min, self.max) {
+            write!(f, "min={} max={} ", min, max)?;
+        }
+
+        write!(f, "sample={}", self.sample)
+    }
+}
+
+fn sample<T: Debug>(values: &[T], sample_size: usize) -> String {
+    if values.len() <= sample_size {
+        format!("{:?}", values)
+    } else {
+        let sample = format!("{:?}", &values[..sample_size]);
+        // Put an ellipsis before the closing "]" so it's obvious the values
+        // were truncated.
+        let sample = sample.strip_suffix(']').unwrap_or(&sample);
+        format!("{}, ...]", sample)
+    }
+}
+
+/// The shape a tensor is expected to have, where `None` is a dimension that
+/// can be any size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ExpectedShape(Vec<Option<u32>>);
+
+impl ExpectedShape {
+    fn matches(&self, dimensions: &[u32]) -> bool {
+        self.0.len() == dimensions.len()
+            && self
+                .0
+                .iter()
+                .zip(dimensions)
+                .all(|(&expected, &actual)| match expected {
+                    Some(expected) => expected == actual,
+                    None => true,
+                })
+    }
+
+    /// Convert to the representation used by [`DimensionsParam::Fixed`],
+    /// where `0` means "any size".
+    fn to_dimensions(&self) -> Vec<u32> {
+        self.0.iter().map(|d| d.unwrap_or(0)).collect()
+    }
+}
+
+impl FromStr for ExpectedShape {
+    type Err = InvalidShape;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().trim_start_matches('[').trim_end_matches(']');
+
+        s.split(',')
+            .map(|d| match d.trim() {
+                "*" | "_" => Ok(None),
+                d => d.parse().map(Some).map_err(|_| InvalidShape {
+                    dimension: d.to_string(),
+                }),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(ExpectedShape)
+    }
+}
+
+impl Display for ExpectedShape {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, dimension) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+
+            match dimension {
+                Some(d) => write!(f, "{}", d)?,
+                None => write!(f, "*")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InvalidShape {
+    dimension: String,
+}
+
+impl Display for InvalidShape {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "\"{}\" isn't a valid dimension, expected a number or \"*\"",
+            self.dimension
+        )
+    }
+}
+
+impl Error for InvalidShape {}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_expected_shapes() {
+        let inputs = [
+            ("1, 224, 224, 3", vec![Some(1), Some(224), Some(224), Some(3)]),
+            ("[1, *, *, 3]", vec![Some(1), None, None, Some(3)]),
+            ("_", vec![None]),
+        ];
+
+        for (src, should_be) in inputs {
+            let got: ExpectedShape = src.parse().unwrap();
+
+            assert_eq!(got, ExpectedShape(should_be));
+        }
+
+        assert!("1, x".parse::<ExpectedShape>().is_err());
+    }
+
+    #[test]
+    fn wildcards_match_any_dimension() {
+        let shape: ExpectedShape = "1, *, 3".parse().unwrap();
+
+        assert!(shape.matches(&[1, 42, 3]));
+        assert!(!shape.matches(&[2, 42, 3]));
+        assert!(!shape.matches(&[1, 42, 3, 1]));
+    }
+
+    #[test]
+    fn summarize_numbers() {
+        let got = Summary::numeric(&[3.0_f32, -1.0, f32::NAN, 7.5], 2);
+
+        assert_eq!(got.min, Some(-1.0));
+        assert_eq!(got.max, Some(7.5));
+        assert_eq!(got.sample, "[3.0, -1.0, ...]");
+    }
+
+    #[test]
+    fn summarize_empty_tensor() {
+        let got = Summary::numeric::<u8>(&[], 8);
+
+        assert_eq!(got.min, None);
+        assert_eq!(got.max, None);
+        assert_eq!(got.sample, "[]");
+    }
+
+    #[test]
+    fn strings_only_have_a_sample() {
+        let got = Summary::strings(&["hello", "world"], 8);
+
+        assert_eq!(got.to_string(), r#"sample=["hello", "world"]"#);
+    }
+}
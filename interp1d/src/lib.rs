@@ -0,0 +1,236 @@
+//! Linear interpolation of 1D signals.
+
+use std::fmt::Display;
+
+use hotg_rune_proc_blocks::{
+    common::element_type,
+    ndarray::Ix1,
+    runtime_v1::{self, *},
+    BufferExt, SliceExt, ValueType,
+};
+use interp1d_core::resample;
+use num_traits::{Float, NumCast, ToPrimitive};
+
+use crate::proc_block_v1::*;
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata = Metadata::new("Interp1D", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.set_homepage(env!("CARGO_PKG_HOMEPAGE"));
+        metadata.add_tag("numeric");
+        metadata.add_tag("sensor");
+        metadata.add_tag("resampling");
+
+        let length = ArgumentMetadata::new("length");
+        length.set_description("The number of samples to resample to.");
+        let hint =
+            runtime_v1::supported_argument_type(ArgumentType::UnsignedInteger);
+        length.add_hint(&hint);
+        metadata.add_argument(&length);
+
+        let element_type = ArgumentMetadata::new("element_type");
+        element_type.set_description(
+            "The type of the samples. Integer samples are rounded to the nearest value after interpolating.",
+        );
+        element_type.set_default_value("f32");
+        let hint =
+            runtime_v1::interpret_as_string_in_enum(element_type::NUMERIC);
+        element_type.add_hint(&hint);
+        metadata.add_argument(&element_type);
+
+        let input = TensorMetadata::new("input");
+        input.set_description("A 1D signal with any number of samples.");
+        let hint =
+            supported_shapes(&NUMERIC_TYPES, DimensionsParam::Fixed(&[0]));
+        input.add_hint(&hint);
+        metadata.add_input(&input);
+
+        let output = TensorMetadata::new("output");
+        output.set_description("The signal, resampled to `length` samples.");
+        let hint =
+            supported_shapes(&NUMERIC_TYPES, DimensionsParam::Fixed(&[0]));
+        output.add_hint(&hint);
+        metadata.add_output(&output);
+
+        register_node(&metadata);
+    }
+
+    fn graph(node_id: String) -> Result<(), GraphError> {
+        let ctx = GraphContext::for_node(&node_id)
+            .ok_or(GraphError::MissingContext)?;
+
+        let element_type = get_element_type(|n| ctx.get_argument(n))
+            .map_err(GraphError::InvalidArgument)?;
+        let length = get_length(|n| ctx.get_argument(n))
+            .map_err(GraphError::InvalidArgument)?;
+
+        ctx.add_input_tensor(
+            "input",
+            element_type,
+            DimensionsParam::Fixed(&[0]),
+        );
+        ctx.add_output_tensor(
+            "output",
+            element_type,
+            DimensionsParam::Fixed(&[length as u32]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(node_id: String) -> Result<(), KernelError> {
+        let ctx = KernelContext::for_node(&node_id)
+            .ok_or(KernelError::MissingContext)?;
+
+        let length = get_length(|n| ctx.get_argument(n))
+            .map_err(KernelError::InvalidArgument)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("input").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "input".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        let input = (&buffer[..], &dimensions[..]);
+
+        let output = match element_type {
+            ElementType::U8 => resample_integers::<u8>(input, length)?,
+            ElementType::I8 => resample_integers::<i8>(input, length)?,
+            ElementType::U16 => resample_integers::<u16>(input, length)?,
+            ElementType::I16 => resample_integers::<i16>(input, length)?,
+            ElementType::U32 => resample_integers::<u32>(input, length)?,
+            ElementType::I32 => resample_integers::<i32>(input, length)?,
+            ElementType::F32 => resample_floats::<f32>(input, length)?,
+            ElementType::U64 => resample_integers::<u64>(input, length)?,
+            ElementType::I64 => resample_integers::<i64>(input, length)?,
+            ElementType::F64 => resample_floats::<f64>(input, length)?,
+            other => {
+                return Err(KernelError::InvalidInput(InvalidInput {
+                    name: "input".to_string(),
+                    reason: BadInputReason::InvalidValue(format!(
+                        "The input must be numeric, found {:?}",
+                        other
+                    )),
+                }))
+            },
+        };
+
+        ctx.set_output_tensor(
+            "output",
+            TensorParam {
+                element_type,
+                dimensions: &[length as u32],
+                buffer: &output,
+            },
+        );
+
+        Ok(())
+    }
+}
+
+const NUMERIC_TYPES: [ElementType; 10] = [
+    ElementType::U8,
+    ElementType::I8,
+    ElementType::U16,
+    ElementType::I16,
+    ElementType::U32,
+    ElementType::I32,
+    ElementType::F32,
+    ElementType::U64,
+    ElementType::I64,
+    ElementType::F64,
+];
+
+fn view_samples<'buf, T: ValueType>(
+    (buffer, dimensions): (&'buf [u8], &[u32]),
+) -> Result<&'buf [T], KernelError> {
+    let samples = buffer
+        .try_view::<T, Ix1>(dimensions)
+        .map_err(|e| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "input".to_string(),
+                reason: BadInputReason::InvalidValue(e.to_string()),
+            })
+        })?;
+
+    Ok(samples.to_slice().expect("Tensors are always contiguous"))
+}
+
+fn resample_floats<T>(
+    input: (&[u8], &[u32]),
+    length: usize,
+) -> Result<Vec<u8>, KernelError>
+where
+    T: ValueType + Float,
+{
+    let samples = view_samples::<T>(input)?;
+    Ok(resample(samples, length).as_bytes().to_vec())
+}
+
+fn resample_integers<T>(
+    input: (&[u8], &[u32]),
+    length: usize,
+) -> Result<Vec<u8>, KernelError>
+where
+    T: ValueType + ToPrimitive + NumCast + Copy,
+{
+    let samples: Vec<f64> = view_samples::<T>(input)?
+        .iter()
+        .map(|s| s.to_f64().unwrap())
+        .collect();
+
+    let resampled: Vec<T> = resample(&samples, length)
+        .into_iter()
+        .map(|s| T::from(s.round()).expect("Interpolated values stay in range"))
+        .collect();
+
+    Ok(resampled.as_bytes().to_vec())
+}
+
+fn get_length(
+    get_argument: impl FnOnce(&str) -> Option<String>,
+) -> Result<usize, InvalidArgument> {
+    get_argument("length")
+        .ok_or_else(|| InvalidArgument::not_found("length"))?
+        .parse()
+        .map_err(|e| InvalidArgument::invalid_value("length", e))
+}
+
+fn get_element_type(
+    get_argument: impl FnOnce(&str) -> Option<String>,
+) -> Result<ElementType, InvalidArgument> {
+    use element_type::{parse_element_type, NAME, NUMERIC};
+
+    match get_argument(NAME) {
+        Some(name) => parse_element_type(&name, NUMERIC)
+            .map_err(|e| InvalidArgument::invalid_value(NAME, e)),
+        None => Ok(ElementType::F32),
+    }
+}
+
+impl InvalidArgument {
+    fn not_found(name: impl Into<String>) -> Self {
+        InvalidArgument {
+            name: name.into(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+
+    fn invalid_value(name: impl Into<String>, reason: impl Display) -> Self {
+        InvalidArgument {
+            name: name.into(),
+            reason: BadArgumentReason::InvalidValue(reason.to_string()),
+        }
+    }
+}
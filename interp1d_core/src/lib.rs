@@ -0,0 +1,167 @@
+//! Linear interpolation of 1D signals.
+//!
+//! This is the algorithm behind the `interp1d` proc-block, without any of the
+//! WebAssembly bindings, so it can be used as a normal Rust library.
+
+use num_traits::Float;
+
+/// Resample `values` so it has exactly `length` elements, using linear
+/// interpolation.
+///
+/// The first and last elements are always preserved, with the new samples
+/// spread evenly between them. An empty input gives an empty output.
+///
+/// # Examples
+///
+/// ```rust
+/// let stretched = interp1d_core::resample(&[0.0, 1.0], 5);
+/// assert_eq!(stretched, [0.0, 0.25, 0.5, 0.75, 1.0]);
+///
+/// let squashed = interp1d_core::resample(&[0.0, 1.0, 2.0, 3.0, 4.0], 3);
+/// assert_eq!(squashed, [0.0, 2.0, 4.0]);
+/// ```
+pub fn resample<T: Float>(values: &[T], length: usize) -> Vec<T> {
+    match values {
+        _ if length == 0 => Vec::new(),
+        [] => Vec::new(),
+        [value] => vec![*value; length],
+        _ if length == 1 => vec![values[0]],
+        _ => {
+            let last = T::from(values.len() - 1).unwrap();
+            let step = last / T::from(length - 1).unwrap();
+
+            (0..length)
+                .map(|i| {
+                    let position = T::from(i).unwrap() * step;
+                    sample_at(values, position.min(last))
+                })
+                .collect()
+        },
+    }
+}
+
+/// Evaluate the piecewise linear function defined by the points `(xp, fp)` at
+/// each of the points in `x`.
+///
+/// This behaves like [`numpy.interp()`][numpy], where `xp` must be increasing
+/// and points outside the range of `xp` are clamped to `fp[0]` or the last
+/// `fp`.
+///
+/// # Panics
+///
+/// This will panic if `xp` and `fp` have different lengths or are empty.
+///
+/// # Examples
+///
+/// ```rust
+/// let xp = [0.0, 1.0, 3.0];
+/// let fp = [0.0, 10.0, 30.0];
+///
+/// let got = interp1d_core::interp(&[-1.0, 0.5, 2.0, 5.0], &xp, &fp);
+///
+/// assert_eq!(got, [0.0, 5.0, 20.0, 30.0]);
+/// ```
+///
+/// [numpy]: https://numpy.org/doc/stable/reference/generated/numpy.interp.html
+pub fn interp<T: Float>(x: &[T], xp: &[T], fp: &[T]) -> Vec<T> {
+    assert_eq!(xp.len(), fp.len(), "xp and fp must have the same length");
+    assert!(!xp.is_empty(), "Can't interpolate without any points");
+
+    x.iter()
+        .map(|&x| {
+            // The index of the first point which is greater than x
+            let upper = xp.partition_point(|&p| p <= x);
+
+            if upper == 0 {
+                fp[0]
+            } else if upper == xp.len() {
+                fp[fp.len() - 1]
+            } else {
+                let (x0, x1) = (xp[upper - 1], xp[upper]);
+                let (y0, y1) = (fp[upper - 1], fp[upper]);
+                let t = (x - x0) / (x1 - x0);
+                y0 + (y1 - y0) * t
+            }
+        })
+        .collect()
+}
+
+/// Linearly interpolate between the two samples either side of a (fractional)
+/// index.
+fn sample_at<T: Float>(values: &[T], position: T) -> T {
+    let index = position.floor();
+    let i = index.to_usize().unwrap();
+    let fraction = position - index;
+
+    match values.get(i + 1) {
+        Some(&next) if fraction > T::zero() => {
+            values[i] + (next - values[i]) * fraction
+        },
+        _ => values[i],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::testing::{assert_slices_close, Tolerance};
+
+    #[test]
+    fn resample_is_a_no_op_for_the_same_length() {
+        let values = [3.0_f32, -1.0, 4.0, 1.5];
+
+        let got = resample(&values, values.len());
+
+        assert_eq!(got, values);
+    }
+
+    #[test]
+    fn upsample() {
+        let got = resample(&[0.0_f64, 10.0, 0.0], 5);
+
+        assert_slices_close(
+            &got,
+            &[0.0, 5.0, 10.0, 5.0, 0.0],
+            Tolerance::DEFAULT,
+        );
+    }
+
+    #[test]
+    fn downsample_uneven() {
+        let got = resample(&[0.0_f64, 1.0, 2.0, 3.0], 3);
+
+        assert_slices_close(&got, &[0.0, 1.5, 3.0], Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn resample_edge_cases() {
+        assert!(resample::<f32>(&[], 4).is_empty());
+        assert_eq!(resample(&[7.0_f32], 3), [7.0, 7.0, 7.0]);
+        assert_eq!(resample(&[1.0_f32, 2.0, 3.0], 1), [1.0]);
+        assert!(resample(&[1.0_f32, 2.0, 3.0], 0).is_empty());
+    }
+
+    #[test]
+    fn interp_uneven_points() {
+        let xp = [0.0_f64, 0.1, 0.5, 1.0];
+        let fp = [0.0, 1.0, 1.0, 0.0];
+
+        let got = interp(&[0.05, 0.3, 0.75, 1.0], &xp, &fp);
+
+        assert_slices_close(&got, &[0.5, 1.0, 0.5, 0.0], Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn interp_clamps_outside_the_range() {
+        let got = interp(&[-10.0_f32, 10.0], &[0.0, 1.0], &[5.0, 6.0]);
+
+        assert_eq!(got, [5.0, 6.0]);
+    }
+
+    #[test]
+    fn interp_single_point() {
+        let got = interp(&[-1.0_f32, 0.0, 1.0], &[0.0], &[2.0]);
+
+        assert_eq!(got, [2.0, 2.0, 2.0]);
+    }
+}
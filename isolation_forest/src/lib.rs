@@ -0,0 +1,583 @@
+//! Anomaly detection using an [Isolation Forest][paper].
+//!
+//! Each tree recursively splits a random subsample of the training data on a
+//! random feature at a random value. Anomalies are few and different, so they
+//! tend to be isolated after only a couple of splits while normal samples end
+//! up deep in the tree. The average path length across all trees is turned
+//! into a score between 0 and 1, where values close to 1 are very likely to be
+//! anomalies and values well below 0.5 are normal.
+//!
+//! [paper]: https://cs.nju.edu.cn/zhouzh/zhouzh.files/publication/icdm08b.pdf
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::{ArrayView1, ArrayView2},
+    prelude::*,
+    runtime_v1::*,
+    BufferExt, SliceExt,
+};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// The Euler-Mascheroni constant, used to approximate harmonic numbers.
+const EULER_GAMMA: f64 = 0.577_215_664_901_532_9;
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Isolation Forest", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("anomaly detection");
+        metadata.add_tag("predictive maintenance");
+        metadata.add_tag("analytics");
+
+        let n_trees = ArgumentMetadata::new("n_trees");
+        n_trees.set_description("The number of trees in the forest.");
+        n_trees.set_default_value("100");
+        n_trees
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&n_trees);
+
+        let subsample_size = ArgumentMetadata::new("subsample_size");
+        subsample_size.set_description(
+            "How many training samples each tree is built from. Small subsamples make anomalies easier to isolate.",
+        );
+        subsample_size.set_default_value("256");
+        subsample_size
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&subsample_size);
+
+        let seed = ArgumentMetadata::new("seed");
+        seed.set_description(
+            "The seed for the random number generator, so results are reproducible.",
+        );
+        seed.set_default_value("0");
+        seed.add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&seed);
+
+        let x_train = TensorMetadata::new("x_train");
+        x_train.set_description(
+            "The (mostly normal) samples to build the forest from, as a `[samples, features]` tensor.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        x_train.add_hint(&hint);
+        metadata.add_input(&x_train);
+
+        let x_test = TensorMetadata::new("x_test");
+        x_test.set_description("The samples to score.");
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        x_test.add_hint(&hint);
+        metadata.add_input(&x_test);
+
+        let scores = TensorMetadata::new("scores");
+        scores.set_description(
+            "An anomaly score between 0 and 1 for each test sample. Higher is more anomalous.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F64], DimensionsParam::Fixed(&[0]));
+        scores.add_hint(&hint);
+        metadata.add_output(&scores);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "x_train",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_input_tensor(
+            "x_test",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "scores",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let x_train = get_input(&ctx, "x_train")?;
+        let x_train = view_2d(&x_train, "x_train")?;
+        let x_test = get_input(&ctx, "x_test")?;
+        let x_test = view_2d(&x_test, "x_test")?;
+
+        let forest = IsolationForest::fit(x_train, settings)?;
+        let scores = forest.score(x_test)?;
+
+        ctx.set_output_tensor(
+            "scores",
+            TensorParam {
+                element_type: ElementType::F64,
+                dimensions: &[scores.len() as u32],
+                buffer: scores.as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(name: &str, reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: name.to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+fn get_input(
+    ctx: &KernelContext,
+    name: &str,
+) -> Result<TensorResult, KernelError> {
+    let tensor = ctx.get_input_tensor(name).ok_or_else(|| {
+        KernelError::InvalidInput(InvalidInput {
+            name: name.to_string(),
+            reason: BadInputReason::NotFound,
+        })
+    })?;
+
+    if tensor.element_type != ElementType::F64 {
+        return Err(invalid_input(
+            name,
+            format!("Expected a f64 tensor, found {}", tensor.element_type),
+        ));
+    }
+
+    Ok(tensor)
+}
+
+fn view_2d<'a>(
+    tensor: &'a TensorResult,
+    name: &str,
+) -> Result<ArrayView2<'a, f64>, KernelError> {
+    tensor
+        .buffer
+        .try_view::<f64, _>(&tensor.dimensions)
+        .map_err(|e| invalid_input(name, e))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    n_trees: usize,
+    subsample_size: usize,
+    seed: u64,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let n_trees = ctx.parse_argument_with_default::<_, E>("n_trees", 100)?;
+        let subsample_size =
+            ctx.parse_argument_with_default::<_, E>("subsample_size", 256)?;
+        let seed = ctx.parse_argument_with_default::<_, E>("seed", 0)?;
+
+        if n_trees == 0 {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "n_trees",
+                "The forest needs at least one tree",
+            )));
+        }
+
+        if subsample_size < 2 {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "subsample_size",
+                "Each tree needs at least 2 samples",
+            )));
+        }
+
+        Ok(Settings {
+            n_trees,
+            subsample_size,
+            seed,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct IsolationForest {
+    trees: Vec<Node>,
+    features: usize,
+    /// The number of samples each tree was built from.
+    subsample_size: usize,
+}
+
+impl IsolationForest {
+    fn fit(
+        x: ArrayView2<'_, f64>,
+        settings: Settings,
+    ) -> Result<Self, KernelError> {
+        let Settings {
+            n_trees,
+            subsample_size,
+            seed,
+        } = settings;
+
+        if x.nrows() == 0 {
+            return Err(invalid_input("x_train", "No training samples"));
+        }
+        if x.iter().any(|value| !value.is_finite()) {
+            return Err(invalid_input(
+                "x_train",
+                "The training samples must be finite",
+            ));
+        }
+
+        let subsample_size = subsample_size.min(x.nrows());
+        // There's no point growing a tree any deeper than the average depth
+        // of a tree built from the subsample
+        let max_depth = (subsample_size as f64).log2().ceil() as usize;
+
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let mut indices: Vec<usize> = (0..x.nrows()).collect();
+
+        let trees = (0..n_trees)
+            .map(|_| {
+                let subsample =
+                    partial_shuffle(&mut indices, subsample_size, &mut rng);
+                build_tree(x, subsample.to_vec(), 0, max_depth, &mut rng)
+            })
+            .collect();
+
+        Ok(IsolationForest {
+            trees,
+            features: x.ncols(),
+            subsample_size,
+        })
+    }
+
+    fn score(&self, x: ArrayView2<'_, f64>) -> Result<Vec<f64>, KernelError> {
+        if x.ncols() != self.features {
+            return Err(invalid_input(
+                "x_test",
+                format!(
+                    "The forest was trained with {} features, but the test samples have {}",
+                    self.features,
+                    x.ncols()
+                ),
+            ));
+        }
+
+        let normalization = average_path_length(self.subsample_size);
+
+        let scores = x
+            .outer_iter()
+            .map(|row| {
+                let total: f64 =
+                    self.trees.iter().map(|tree| tree.path_length(row)).sum();
+                let mean = total / self.trees.len() as f64;
+
+                if normalization > 0.0 {
+                    2.0_f64.powf(-mean / normalization)
+                } else {
+                    0.5
+                }
+            })
+            .collect();
+
+        Ok(scores)
+    }
+}
+
+/// Move `count` randomly chosen items to the start of `items` and return
+/// them.
+fn partial_shuffle<'a, T>(
+    items: &'a mut [T],
+    count: usize,
+    rng: &mut impl Rng,
+) -> &'a [T] {
+    let count = count.min(items.len());
+
+    for i in 0..count {
+        let j = rng.gen_range(i..items.len());
+        items.swap(i, j);
+    }
+
+    &items[..count]
+}
+
+/// The average path length of an unsuccessful search in a binary search tree
+/// with `n` items, used to normalize path lengths.
+fn average_path_length(n: usize) -> f64 {
+    match n {
+        0 | 1 => 0.0,
+        2 => 1.0,
+        _ => {
+            let n = n as f64;
+            let harmonic = (n - 1.0).ln() + EULER_GAMMA;
+            2.0 * harmonic - 2.0 * (n - 1.0) / n
+        },
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    /// A leaf containing `size` training samples.
+    Leaf { size: usize },
+    Split {
+        feature: usize,
+        /// Samples with `value < threshold` go left.
+        threshold: f64,
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+}
+
+impl Node {
+    fn path_length(&self, row: ArrayView1<'_, f64>) -> f64 {
+        let mut node = self;
+        let mut depth = 0.0;
+
+        loop {
+            match node {
+                Node::Leaf { size } => {
+                    // Account for the tree we didn't bother building
+                    return depth + average_path_length(*size);
+                },
+                Node::Split {
+                    feature,
+                    threshold,
+                    left,
+                    right,
+                } => {
+                    node = if row[*feature] < *threshold {
+                        left
+                    } else {
+                        right
+                    };
+                    depth += 1.0;
+                },
+            }
+        }
+    }
+}
+
+fn build_tree(
+    x: ArrayView2<'_, f64>,
+    indices: Vec<usize>,
+    depth: usize,
+    max_depth: usize,
+    rng: &mut impl Rng,
+) -> Node {
+    if depth >= max_depth || indices.len() <= 1 {
+        return Node::Leaf {
+            size: indices.len(),
+        };
+    }
+
+    // We can only split on features which aren't constant
+    let ranges: Vec<(usize, f64, f64)> = (0..x.ncols())
+        .filter_map(|feature| {
+            let column = x.column(feature);
+            let values = indices.iter().map(|&i| column[i]);
+            let min = values.clone().fold(f64::INFINITY, f64::min);
+            let max = values.fold(f64::NEG_INFINITY, f64::max);
+            (min < max).then(|| (feature, min, max))
+        })
+        .collect();
+
+    if ranges.is_empty() {
+        return Node::Leaf {
+            size: indices.len(),
+        };
+    }
+
+    let (feature, min, max) = ranges[rng.gen_range(0..ranges.len())];
+    let t: f64 = rng.gen();
+    let mut threshold = min + t * (max - min);
+    if !threshold.is_finite() {
+        // The range is too wide to represent, so interpolate without
+        // subtracting
+        threshold = min * (1.0 - t) + max * t;
+    }
+    if threshold <= min {
+        // Make sure at least one sample goes each way
+        threshold = max;
+    }
+
+    let (left, right): (Vec<usize>, Vec<usize>) =
+        indices.into_iter().partition(|&i| x[[i, feature]] < threshold);
+
+    Node::Split {
+        feature,
+        threshold,
+        left: Box::new(build_tree(x, left, depth + 1, max_depth, rng)),
+        right: Box::new(build_tree(x, right, depth + 1, max_depth, rng)),
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::{
+        ndarray::{self, Array2},
+        testing::{assert_close, Tolerance},
+    };
+
+    const SETTINGS: Settings = Settings {
+        n_trees: 100,
+        subsample_size: 64,
+        seed: 42,
+    };
+
+    /// A 10x10 grid of normal readings around the origin.
+    fn normal_samples() -> Array2<f64> {
+        Array2::from_shape_fn((100, 2), |(i, j)| match j {
+            0 => (i % 10) as f64 / 10.0,
+            _ => (i / 10) as f64 / 10.0,
+        })
+    }
+
+    #[test]
+    fn outliers_score_higher_than_normal_samples() {
+        let mut x = normal_samples().into_raw_vec();
+        x.extend([5.0, 5.0, -3.0, 0.5]);
+        let x = Array2::from_shape_vec((102, 2), x).unwrap();
+        let forest = IsolationForest::fit(x.view(), SETTINGS).unwrap();
+        let x_test = ndarray::arr2(&[[0.45, 0.45], [5.0, 5.0], [-3.0, 0.5]]);
+
+        let scores = forest.score(x_test.view()).unwrap();
+
+        assert!(scores[0] < 0.5, "{:?}", scores);
+        assert!(scores[1] > 0.6, "{:?}", scores);
+        assert!(scores[2] > 0.6, "{:?}", scores);
+        assert!(scores.iter().all(|s| (0.0..=1.0).contains(s)));
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_forest() {
+        let x = normal_samples();
+
+        let first = IsolationForest::fit(x.view(), SETTINGS).unwrap();
+        let second = IsolationForest::fit(x.view(), SETTINGS).unwrap();
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn subsample_is_capped_at_the_number_of_samples() {
+        let x = ndarray::arr2(&[[0.0], [1.0], [2.0]]);
+
+        let forest = IsolationForest::fit(x.view(), SETTINGS).unwrap();
+
+        assert_eq!(forest.subsample_size, 3);
+    }
+
+    #[test]
+    fn constant_training_data_gives_neutral_scores() {
+        let x = Array2::from_elem((10, 2), 1.0);
+        let forest = IsolationForest::fit(x.view(), SETTINGS).unwrap();
+
+        let scores = forest.score(x.view()).unwrap();
+
+        for score in scores {
+            assert_close(score, 0.5, Tolerance::DEFAULT);
+        }
+    }
+
+    #[test]
+    fn known_average_path_lengths() {
+        assert_eq!(average_path_length(1), 0.0);
+        assert_eq!(average_path_length(2), 1.0);
+        assert_close(
+            average_path_length(256),
+            10.244_770_920_119_917,
+            Tolerance::absolute(1e-9),
+        );
+    }
+
+    #[test]
+    fn test_samples_need_the_same_features() {
+        let forest =
+            IsolationForest::fit(normal_samples().view(), SETTINGS).unwrap();
+
+        assert!(forest.score(Array2::zeros((1, 3)).view()).is_err());
+    }
+
+    #[test]
+    fn non_finite_training_samples_are_rejected() {
+        let x = ndarray::arr2(&[[0.0], [f64::INFINITY], [1.0]]);
+
+        assert!(IsolationForest::fit(x.view(), SETTINGS).is_err());
+    }
+
+    #[test]
+    fn huge_ranges_can_still_be_split() {
+        let x = ndarray::arr2(&[[-1e308], [0.0], [1e308]]);
+
+        let forest = IsolationForest::fit(x.view(), SETTINGS).unwrap();
+        let scores = forest.score(x.view()).unwrap();
+
+        assert!(scores.iter().all(|s| (0.0..=1.0).contains(s)));
+    }
+}
@@ -27,6 +27,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
         metadata.set_homepage(env!("CARGO_PKG_HOMEPAGE"));
         metadata.add_tag("classify");
+        metadata.with_const_eval();
 
         let labels = ArgumentMetadata::new("wordlist");
         let hint =
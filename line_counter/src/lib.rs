@@ -0,0 +1,455 @@
+//! Count tracked objects as they cross a virtual line, the last stage in a
+//! typical people-counting or traffic-counting pipeline.
+//!
+//! This expects the output of the `tracker` proc-block, where each row is
+//! `[x, y, height, width, confidence, label, track_id]`. Every frame, the
+//! centre of each track is compared with where it was last seen and if the
+//! path between the two points crosses the line, the count for that direction
+//! is incremented. Objects which stop exactly on the line are only counted
+//! once they have moved off it again.
+//!
+//! The running totals and where each track was last seen are kept between
+//! invocations.
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    prelude::*, runtime_v1::*, state::NodeState, BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// Each node's counts and last known track positions.
+static COUNTERS: NodeState<LineCounter> = NodeState::new();
+
+/// The number of columns in each row from the `tracker` proc-block.
+const TRACK_COLUMNS: usize = 7;
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Line Counter", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("image");
+        metadata.add_tag("object-detection");
+        metadata.add_tag("analytics");
+        metadata.add_tag("stateful");
+
+        let line = ArgumentMetadata::new("line");
+        line.set_description(
+            "The start and end points of the line to watch, as \"x1, y1, x2, y2\" in the same coordinates as the detections.",
+        );
+        line.add_hint(&supported_argument_type(ArgumentType::LongString));
+        metadata.add_argument(&line);
+
+        let max_age = ArgumentMetadata::new("max_age");
+        max_age.set_description(
+            "How many frames to remember where a track was last seen. This should be at least as long as the tracker's `max_age`.",
+        );
+        max_age.set_default_value("30");
+        max_age
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&max_age);
+
+        let tracks = TensorMetadata::new("tracks");
+        tracks.set_description(
+            "The tracked objects in the current frame, as `[x, y, height, width, confidence, label, track_id]` rows.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, TRACK_COLUMNS as u32]),
+        );
+        tracks.add_hint(&hint);
+        metadata.add_input(&tracks);
+
+        let counts = TensorMetadata::new("counts");
+        counts.set_description(
+            "How many objects have crossed the line so far, as `[positive, negative]`. For a line drawn from left to right across an image, `positive` counts objects moving down the image and `negative` counts objects moving up.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::U32], DimensionsParam::Fixed(&[2]));
+        counts.add_hint(&hint);
+        metadata.add_output(&counts);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "tracks",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, TRACK_COLUMNS as u32]),
+        );
+        ctx.add_output_tensor(
+            "counts",
+            ElementType::U32,
+            DimensionsParam::Fixed(&[2]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("tracks").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "tracks".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::F32 {
+            return Err(invalid_tracks(format!(
+                "Expected a f32 tensor, found {}",
+                element_type
+            )));
+        }
+
+        match *dimensions {
+            [_, columns] if columns as usize == TRACK_COLUMNS => {},
+            _ => {
+                return Err(invalid_tracks(format!(
+                    "Expected a [tracks, {}] tensor, found {:?}",
+                    TRACK_COLUMNS, dimensions
+                )))
+            },
+        }
+
+        let positions: Vec<(u32, Point)> = buffer
+            .try_elements_with_shape::<f32>(&dimensions)
+            .map_err(invalid_tracks)?
+            .chunks_exact(TRACK_COLUMNS)
+            .map(|row| (row[6] as u32, Point { x: row[0], y: row[1] }))
+            .collect();
+
+        let counts = COUNTERS.with(&id, |counter| {
+            counter.update(&positions, &settings);
+            counter.counts
+        });
+
+        ctx.set_output_tensor(
+            "counts",
+            TensorParam {
+                element_type: ElementType::U32,
+                dimensions: &[2],
+                buffer: counts.as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_tracks(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "tracks".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    line: Line,
+    max_age: u32,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let line = ctx.parse_argument::<_, E>("line")?;
+        let max_age = ctx.parse_argument_with_default::<_, E>("max_age", 30)?;
+
+        Ok(Settings { line, max_age })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Point {
+    x: f32,
+    y: f32,
+}
+
+/// Which side of `a -> b` the point `p` is on (positive or negative), or
+/// zero if all three points are in a line.
+fn orientation(a: Point, b: Point, p: Point) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Line {
+    start: Point,
+    end: Point,
+}
+
+impl Line {
+    fn side(&self, p: Point) -> f32 { orientation(self.start, self.end, p) }
+
+    /// Check whether moving from `from` to `to` crosses this line, returning
+    /// which direction it was crossed in.
+    fn crossing(&self, from: Point, to: Point) -> Option<Direction> {
+        let (before, after) = (self.side(from), self.side(to));
+
+        if before == 0.0 || after == 0.0 || before.signum() == after.signum() {
+            return None;
+        }
+
+        // We crossed the infinite line, but the path also needs to pass
+        // between the line's two end points
+        let start = orientation(from, to, self.start);
+        let end = orientation(from, to, self.end);
+
+        if start * end > 0.0 {
+            return None;
+        }
+
+        if after > 0.0 {
+            Some(Direction::Positive)
+        } else {
+            Some(Direction::Negative)
+        }
+    }
+}
+
+impl FromStr for Line {
+    type Err = InvalidLine;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let coordinates = s
+            .split(',')
+            .map(|c| c.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| InvalidLine(s.to_string()))?;
+
+        match *coordinates {
+            [x1, y1, x2, y2] if (x1, y1) != (x2, y2) => Ok(Line {
+                start: Point { x: x1, y: y1 },
+                end: Point { x: x2, y: y2 },
+            }),
+            _ => Err(InvalidLine(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct InvalidLine(String);
+
+impl Display for InvalidLine {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Expected two different points (e.g. \"0.0, 0.5, 1.0, 0.5\"), found \"{}\"",
+            self.0
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Direction {
+    Positive,
+    Negative,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct LastSeen {
+    position: Point,
+    frame: u64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct LineCounter {
+    /// How many objects crossed in the positive and negative directions.
+    counts: [u32; 2],
+    /// Where each track was last seen, keyed by track ID.
+    tracks: HashMap<u32, LastSeen>,
+    frame: u64,
+}
+
+impl LineCounter {
+    /// Update the counts with the positions of each track in a new frame.
+    fn update(&mut self, positions: &[(u32, Point)], settings: &Settings) {
+        self.frame += 1;
+        let line = &settings.line;
+
+        for &(id, position) in positions {
+            let previous = self.tracks.get(&id).map(|last| last.position);
+
+            match previous.and_then(|p| line.crossing(p, position)) {
+                Some(Direction::Positive) => {
+                    self.counts[0] = self.counts[0].saturating_add(1)
+                },
+                Some(Direction::Negative) => {
+                    self.counts[1] = self.counts[1].saturating_add(1)
+                },
+                None => {},
+            }
+
+            // Remember the last position that was off the line so an object
+            // is still counted if it pauses on the line part way across
+            let position = match previous {
+                Some(p) if line.side(position) == 0.0 => p,
+                _ => position,
+            };
+            self.tracks.insert(
+                id,
+                LastSeen {
+                    position,
+                    frame: self.frame,
+                },
+            );
+        }
+
+        let frame = self.frame;
+        let max_age = u64::from(settings.max_age);
+        self.tracks.retain(|_, last| frame - last.frame <= max_age);
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> Settings {
+        Settings {
+            // A horizontal line across the middle of the image
+            line: "0.0, 0.5, 1.0, 0.5".parse().unwrap(),
+            max_age: 2,
+        }
+    }
+
+    fn at(x: f32, y: f32) -> Point { Point { x, y } }
+
+    #[test]
+    fn parse_line() {
+        let line: Line = " 0, 0.5,1.0 , 0.5".parse().unwrap();
+
+        assert_eq!(line.start, at(0.0, 0.5));
+        assert_eq!(line.end, at(1.0, 0.5));
+        assert!("0, 0.5, 1.0".parse::<Line>().is_err());
+        assert!("0, 0, 0, 0".parse::<Line>().is_err());
+        assert!("a, b, c, d".parse::<Line>().is_err());
+    }
+
+    #[test]
+    fn count_crossings_in_each_direction() {
+        let settings = settings();
+        let mut counter = LineCounter::default();
+
+        counter.update(&[(0, at(0.2, 0.1)), (1, at(0.8, 0.9))], &settings);
+        counter.update(&[(0, at(0.2, 0.4)), (1, at(0.8, 0.6))], &settings);
+        assert_eq!(counter.counts, [0, 0]);
+
+        counter.update(&[(0, at(0.2, 0.6)), (1, at(0.8, 0.4))], &settings);
+        assert_eq!(counter.counts, [1, 1]);
+
+        // Moving further away doesn't count again
+        counter.update(&[(0, at(0.2, 0.9)), (1, at(0.8, 0.1))], &settings);
+        assert_eq!(counter.counts, [1, 1]);
+    }
+
+    #[test]
+    fn passing_beside_the_line_is_ignored() {
+        let settings = settings();
+        let mut counter = LineCounter::default();
+
+        counter.update(&[(0, at(1.5, 0.1))], &settings);
+        counter.update(&[(0, at(1.5, 0.9))], &settings);
+
+        assert_eq!(counter.counts, [0, 0]);
+    }
+
+    #[test]
+    fn pausing_on_the_line_is_counted_once() {
+        let settings = settings();
+        let mut counter = LineCounter::default();
+
+        counter.update(&[(0, at(0.5, 0.1))], &settings);
+        counter.update(&[(0, at(0.5, 0.5))], &settings);
+        counter.update(&[(0, at(0.5, 0.5))], &settings);
+        assert_eq!(counter.counts, [0, 0]);
+
+        counter.update(&[(0, at(0.5, 0.9))], &settings);
+        assert_eq!(counter.counts, [1, 0]);
+    }
+
+    #[test]
+    fn tracks_are_forgotten_after_max_age() {
+        let settings = settings();
+        let mut counter = LineCounter::default();
+
+        counter.update(&[(0, at(0.5, 0.1))], &settings);
+        for _ in 0..=settings.max_age {
+            counter.update(&[], &settings);
+        }
+        counter.update(&[(0, at(0.5, 0.9))], &settings);
+
+        assert_eq!(counter.counts, [0, 0]);
+        assert_eq!(counter.tracks.len(), 1);
+    }
+}
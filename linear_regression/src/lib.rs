@@ -1,10 +1,20 @@
 // use linfa_logistic::LogisticRegression;
-use smartcore::{linalg::naive::dense_matrix::*, linear::linear_regression::*};
+use smartcore::{
+    linalg::naive::dense_matrix::*,
+    linear::{
+        lasso::{Lasso, LassoParameters},
+        linear_regression::*,
+        ridge_regression::{RidgeRegression, RidgeRegressionParameters},
+    },
+};
 
 use crate::proc_block_v1::{
-    BadInputReason, GraphError, InvalidInput, KernelError,
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray, prelude::*, runtime_v1::*, BufferExt, SliceExt, StringBuilder,
 };
-use hotg_rune_proc_blocks::{ndarray, runtime_v1::*, BufferExt, SliceExt};
 
 wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
 
@@ -26,6 +36,32 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         metadata.add_tag("linear modeling");
         metadata.add_tag("analytics");
 
+        let residuals = ArgumentMetadata::new("residuals");
+        residuals.set_description(
+            "Also output the residuals (`y_true - y_pred`) for each row in `x_test`. This adds a `y_true` input.",
+        );
+        residuals.set_default_value("false");
+        residuals.add_hint(&interpret_as_string_in_enum(&["true", "false"]));
+        metadata.add_argument(&residuals);
+
+        let regularization = ArgumentMetadata::new("regularization");
+        regularization.set_description(
+            "The penalty used to keep the coefficients small and reduce overfitting. `ridge` adds an L2 penalty, while `lasso` adds an L1 penalty which can shrink unimportant coefficients to zero.",
+        );
+        regularization.set_default_value("none");
+        regularization
+            .add_hint(&interpret_as_string_in_enum(Regularization::NAMES));
+        metadata.add_argument(&regularization);
+
+        let alpha = ArgumentMetadata::new("alpha");
+        alpha.set_description(
+            "How strong the `ridge` or `lasso` penalty is. Larger values give simpler models.",
+        );
+        alpha.set_default_value("1.0");
+        alpha.add_hint(&non_negative_number());
+        alpha.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&alpha);
+
         let x_train = TensorMetadata::new("x_train");
         let supported_types = [ElementType::F64];
         let hint =
@@ -45,6 +81,15 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         x_test.add_hint(&hint);
         metadata.add_input(&x_test);
 
+        let y_true = TensorMetadata::new("y_true");
+        y_true.set_description(
+            "The true value for each row in `x_test`. Only used when `residuals` is enabled.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F64], DimensionsParam::Fixed(&[0]));
+        y_true.add_hint(&hint);
+        metadata.add_input(&y_true);
+
         let y_test = TensorMetadata::new("y_test");
         let supported_types = [ElementType::F64];
         let hint =
@@ -52,6 +97,26 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         y_test.add_hint(&hint);
         metadata.add_output(&y_test);
 
+        let model = TensorMetadata::new("model");
+        model.set_description(
+            "The fitted smartcore model, serialized as JSON. Its structure depends on the `regularization` argument.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::Utf8],
+            DimensionsParam::Fixed(&[1]),
+        );
+        model.add_hint(&hint);
+        metadata.add_output(&model);
+
+        let residuals = TensorMetadata::new("residuals");
+        residuals.set_description(
+            "The residual (`y_true - y_pred`) for each row in `x_test`. Only set when `residuals` is enabled.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F64], DimensionsParam::Fixed(&[0]));
+        residuals.add_hint(&hint);
+        metadata.add_output(&residuals);
+
         register_node(&metadata);
     }
 
@@ -61,6 +126,9 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let ctx = GraphContext::for_node(&node_id)
             .ok_or(GraphError::MissingContext)?;
 
+        // Make sure the arguments are valid
+        let _ = Regularization::from_context::<_, GraphError>(&ctx)?;
+
         ctx.add_input_tensor(
             "x_train",
             ElementType::F64,
@@ -85,6 +153,25 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             DimensionsParam::Fixed(&[0]),
         );
 
+        ctx.add_output_tensor(
+            "model",
+            ElementType::Utf8,
+            DimensionsParam::Fixed(&[1]),
+        );
+
+        if residuals_enabled::<_, GraphError>(&ctx)? {
+            ctx.add_input_tensor(
+                "y_true",
+                ElementType::F64,
+                DimensionsParam::Fixed(&[0]),
+            );
+            ctx.add_output_tensor(
+                "residuals",
+                ElementType::F64,
+                DimensionsParam::Fixed(&[0]),
+            );
+        }
+
         Ok(())
     }
 
@@ -94,6 +181,9 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let ctx = KernelContext::for_node(&node_id)
             .ok_or(KernelError::MissingContext)?;
 
+        let regularization =
+            Regularization::from_context::<_, KernelError>(&ctx)?;
+
         let x_train = ctx.get_input_tensor("x_train").ok_or_else(|| {
             KernelError::InvalidInput(InvalidInput {
                 name: "x_train".to_string(),
@@ -102,8 +192,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         })?;
         let _xtrain: ndarray::ArrayView2<f64> = x_train
             .buffer
-            .view(&x_train.dimensions)
-            .and_then(|t| t.into_dimensionality())
+            .try_view(&x_train.dimensions)
             .map_err(|e| {
                 KernelError::InvalidInput(InvalidInput {
                     name: "x_train".to_string(),
@@ -119,8 +208,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         })?;
         let _ytrain: ndarray::ArrayView1<f64> = y_train
             .buffer
-            .view(&y_train.dimensions)
-            .and_then(|t| t.into_dimensionality())
+            .try_view(&y_train.dimensions)
             .map_err(|e| {
                 KernelError::InvalidInput(InvalidInput {
                     name: "y_train".to_string(),
@@ -136,8 +224,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         })?;
         let _xtest: ndarray::ArrayView2<f64> = x_test
             .buffer
-            .view(&x_test.dimensions)
-            .and_then(|t| t.into_dimensionality())
+            .try_view(&x_test.dimensions)
             .map_err(|e| {
                 KernelError::InvalidInput(InvalidInput {
                     name: "x_test".to_string(),
@@ -167,13 +254,15 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             &[],
         );
 
-        let output = transform(
-            &x_train.buffer.elements(),
+        let model = fit(
+            f64_elements("x_train", &x_train)?,
             &x_train.dimensions,
-            &y_train.buffer.elements(),
-            &x_test.buffer.elements(),
-            &x_test.dimensions,
+            f64_elements("y_train", &y_train)?,
+            regularization,
         )?;
+        let output = model
+            .predict(f64_elements("x_test", &x_test)?, &x_test.dimensions)?;
+        let serialized = model.to_json()?;
 
         let y_test_dimension = [x_test.dimensions[0]];
 
@@ -186,101 +275,421 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             },
         );
 
+        let mut builder = StringBuilder::new();
+        builder.push(&serialized);
+        ctx.set_output_tensor(
+            "model",
+            TensorParam {
+                element_type: ElementType::Utf8,
+                dimensions: &[1],
+                buffer: &builder.finish(),
+            },
+        );
+
+        if residuals_enabled::<_, KernelError>(&ctx)? {
+            let y_true = ctx.get_input_tensor("y_true").ok_or_else(|| {
+                KernelError::InvalidInput(InvalidInput {
+                    name: "y_true".to_string(),
+                    reason: BadInputReason::NotFound,
+                })
+            })?;
+            if y_true.element_type != ElementType::F64 {
+                return Err(KernelError::InvalidInput(InvalidInput {
+                    name: "y_true".to_string(),
+                    reason: BadInputReason::InvalidValue(format!(
+                        "Expected a f64 tensor, found {}",
+                        y_true.element_type
+                    )),
+                }));
+            }
+
+            let residuals =
+                residuals(f64_elements("y_true", &y_true)?, &output)?;
+
+            ctx.set_output_tensor(
+                "residuals",
+                TensorParam {
+                    element_type: ElementType::F64,
+                    dimensions: &[residuals.len() as u32],
+                    buffer: residuals.as_bytes(),
+                },
+            );
+        }
+
         Ok(())
     }
 }
 
-fn transform(
+/// The penalty added to the loss function, as set by the `regularization`
+/// and `alpha` arguments.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Regularization {
+    /// Plain ordinary least squares.
+    None,
+    /// L2 regularization.
+    Ridge { alpha: f64 },
+    /// L1 regularization.
+    Lasso { alpha: f64 },
+}
+
+impl Regularization {
+    const NAMES: &'static [&'static str] = &["none", "ridge", "lasso"];
+
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let name = ctx.parse_argument_with_default::<_, E>(
+            "regularization",
+            String::from("none"),
+        )?;
+
+        let alpha = || -> Result<f64, E> {
+            let alpha: f64 =
+                ctx.parse_argument_with_default::<_, E>("alpha", 1.0)?;
+
+            if !alpha.is_finite() || alpha < 0.0 {
+                return Err(E::invalid_argument(
+                    InvalidArgument::invalid_value(
+                        "alpha",
+                        format!(
+                            "Expected a non-negative number, found {}",
+                            alpha
+                        ),
+                    ),
+                ));
+            }
+
+            Ok(alpha)
+        };
+
+        match name.as_str() {
+            "none" => Ok(Regularization::None),
+            "ridge" => Ok(Regularization::Ridge { alpha: alpha()? }),
+            "lasso" => Ok(Regularization::Lasso { alpha: alpha()? }),
+            _ => Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "regularization",
+                format!(
+                    "Unknown regularization, \"{}\". Expected one of {:?}",
+                    name,
+                    Regularization::NAMES
+                ),
+            ))),
+        }
+    }
+}
+
+fn residuals_enabled<C, E>(ctx: &C) -> Result<bool, E>
+where
+    C: ContextExt,
+    E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+{
+    ctx.parse_argument_with_default::<_, E>("residuals", false)
+}
+
+/// Get the difference between each true value and its prediction
+/// (`y_true - y_pred`).
+fn residuals(y_true: &[f64], y_pred: &[f64]) -> Result<Vec<f64>, KernelError> {
+    if y_true.len() != y_pred.len() {
+        return Err(KernelError::InvalidInput(InvalidInput {
+            name: "y_true".to_string(),
+            reason: BadInputReason::InvalidValue(format!(
+                "Expected {} values (one per row in \"x_test\"), found {}",
+                y_pred.len(),
+                y_true.len()
+            )),
+        }));
+    }
+
+    Ok(y_true.iter().zip(y_pred).map(|(t, p)| t - p).collect())
+}
+
+/// A fitted model.
+enum Model {
+    LinearRegression(LinearRegression<f64, DenseMatrix<f64>>),
+    Ridge(RidgeRegression<f64, DenseMatrix<f64>>),
+    Lasso(Lasso<f64, DenseMatrix<f64>>),
+}
+
+impl Model {
+    fn predict(
+        &self,
+        x_test: &[f64],
+        x_test_dim: &[u32],
+    ) -> Result<Vec<f64>, KernelError> {
+        let x_test = DenseMatrix::from_array(
+            x_test_dim[0] as usize,
+            x_test_dim[1] as usize,
+            x_test,
+        );
+
+        let y_hat = match self {
+            Model::LinearRegression(m) => m.predict(&x_test),
+            Model::Ridge(m) => m.predict(&x_test),
+            Model::Lasso(m) => m.predict(&x_test),
+        };
+
+        y_hat.map_err(|e| KernelError::Other(e.to_string()))
+    }
+
+    /// Serialize the underlying smartcore model as JSON.
+    fn to_json(&self) -> Result<String, KernelError> {
+        let json = match self {
+            Model::LinearRegression(m) => serde_json::to_string(m),
+            Model::Ridge(m) => serde_json::to_string(m),
+            Model::Lasso(m) => serde_json::to_string(m),
+        };
+
+        json.map_err(|e| KernelError::Other(e.to_string()))
+    }
+}
+
+fn f64_elements<'a>(
+    name: &str,
+    tensor: &'a TensorResult,
+) -> Result<&'a [f64], KernelError> {
+    tensor
+        .buffer
+        .try_elements_with_shape(&tensor.dimensions)
+        .map_err(|e| {
+            KernelError::InvalidInput(InvalidInput {
+                name: name.to_string(),
+                reason: BadInputReason::Other(e.to_string()),
+            })
+        })
+}
+
+fn fit(
     x_train: &[f64],
     x_train_dim: &[u32],
     y_train: &[f64],
-    x_test: &[f64],
-    x_test_dim: &[u32],
-) -> Result<Vec<f64>, KernelError> {
+    regularization: Regularization,
+) -> Result<Model, KernelError> {
     // Iris data
     let x_train = DenseMatrix::from_array(
         x_train_dim[0] as usize,
         x_train_dim[1] as usize,
         x_train,
     );
+    let y_train = y_train.to_vec();
+
+    let model = match regularization {
+        Regularization::None => LinearRegression::fit(
+            &x_train,
+            &y_train,
+            LinearRegressionParameters::default()
+                .with_solver(LinearRegressionSolverName::QR),
+        )
+        .map(Model::LinearRegression),
+        Regularization::Ridge { alpha } => RidgeRegression::fit(
+            &x_train,
+            &y_train,
+            RidgeRegressionParameters::default().with_alpha(alpha),
+        )
+        .map(Model::Ridge),
+        Regularization::Lasso { alpha } => Lasso::fit(
+            &x_train,
+            &y_train,
+            LassoParameters::default().with_alpha(alpha),
+        )
+        .map(Model::Lasso),
+    };
+
+    model.map_err(|e| KernelError::Other(e.to_string()))
+}
 
-    let lr = LinearRegression::fit(
-        &x_train,
-        &y_train.to_vec(),
-        LinearRegressionParameters::default()
-            .with_solver(LinearRegressionSolverName::QR),
-    )
-    .map_err(|e| KernelError::Other(e.to_string()))?;
-
-    let x_test = DenseMatrix::from_array(
-        x_test_dim[0] as usize,
-        x_test_dim[1] as usize,
-        x_test,
-    );
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
 
-    let y_hat = lr
-        .predict(&x_test)
-        .map_err(|e| KernelError::Other(e.to_string()));
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
 
-    y_hat
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
 }
 
-// comenting out test because it will in after deciaml places everytime so we
-// can't generate a fixed y_pred. BUt I have tested in locally and it's working.
-// :) #[cfg(test)]
 #[cfg(test)]
 mod tests {
     use super::*;
+    use hotg_rune_proc_blocks::testing::{assert_slices_close, Tolerance};
+
+    fn transform(
+        x_train: &[f64],
+        x_train_dim: &[u32],
+        y_train: &[f64],
+        x_test: &[f64],
+        x_test_dim: &[u32],
+        regularization: Regularization,
+    ) -> Result<Vec<f64>, KernelError> {
+        fit(x_train, x_train_dim, y_train, regularization)?
+            .predict(x_test, x_test_dim)
+    }
+
+    #[test]
+    fn check_model() {
+        let x_train = [
+            234.289, 235.6, 159.0, 107.608, 1947., 60.323, 259.426, 232.5,
+            145.6, 108.632, 1948., 61.122, 258.054, 368.2, 161.6, 109.773,
+            1949., 60.171, 284.599, 335.1, 165.0, 110.929, 1950., 61.187,
+            328.975, 209.9, 309.9, 112.075, 1951., 63.221, 346.999, 193.2,
+            359.4, 113.270, 1952., 63.639, 365.385, 187.0, 354.7, 115.094,
+            1953., 64.989, 363.112, 357.8, 335.0, 116.219, 1954., 63.761,
+            397.469, 290.4, 304.8, 117.388, 1955., 66.019, 419.180, 282.2,
+            285.7, 118.734, 1956., 67.857, 442.769, 293.6, 279.8, 120.445,
+            1957., 68.169, 444.546, 468.1, 263.7, 121.950, 1958., 66.513,
+            482.704, 381.3, 255.2, 123.366, 1959., 68.655, 502.601, 393.1,
+            251.4, 125.368, 1960., 69.564, 518.173, 480.6, 257.2, 127.852,
+            1961., 69.331, 554.894, 400.7, 282.7, 130.081, 1962., 70.551,
+        ];
+
+        let y_train: Vec<f64> = vec![
+            83.0, 88.5, 88.2, 89.5, 96.2, 98.1, 99.0, 100.0, 101.2, 104.6,
+            108.4, 110.8, 112.6, 114.2, 115.7, 116.9,
+        ];
 
-    // #[test]
-    // fn check_model() {
-    //     let x_train = [
-    //         234.289, 235.6, 159.0, 107.608, 1947., 60.323, 259.426, 232.5,
-    //         145.6, 108.632, 1948., 61.122, 258.054, 368.2, 161.6, 109.773,
-    //         1949., 60.171, 284.599, 335.1, 165.0, 110.929, 1950., 61.187,
-    //         328.975, 209.9, 309.9, 112.075, 1951., 63.221, 346.999, 193.2,
-    //         359.4, 113.270, 1952., 63.639, 365.385, 187.0, 354.7, 115.094,
-    //         1953., 64.989, 363.112, 357.8, 335.0, 116.219, 1954., 63.761,
-    //         397.469, 290.4, 304.8, 117.388, 1955., 66.019, 419.180, 282.2,
-    //         285.7, 118.734, 1956., 67.857, 442.769, 293.6, 279.8, 120.445,
-    //         1957., 68.169, 444.546, 468.1, 263.7, 121.950, 1958., 66.513,
-    //         482.704, 381.3, 255.2, 123.366, 1959., 68.655, 502.601, 393.1,
-    //         251.4, 125.368, 1960., 69.564, 518.173, 480.6, 257.2, 127.852,
-    //         1961., 69.331, 554.894, 400.7, 282.7, 130.081, 1962., 70.551,
-    //     ];
-
-    //     let y_train: Vec<f64> = vec![
-    //         83.0, 88.5, 88.2, 89.5, 96.2, 98.1, 99.0, 100.0, 101.2, 104.6,
-    //         108.4, 110.8, 112.6, 114.2, 115.7, 116.9,
-    //     ];
-
-    //     let dim: Vec<u32> = vec![16, 6];
-
-    //     let y_pred = transform(&x_train, &dim, &y_train, &x_train, &dim);
-
-    //     println!("{:?}", y_pred);
-
-    //     let should_be = vec![
-    //         83.60081557529429,
-    //         86.9497267843858,
-    //         88.0967712796537,
-    //         90.73064861498187,
-    //         96.53551391475548,
-    //         97.83066549287923,
-    //         98.12232410020943,
-    //         99.87775705667309,
-    //         103.2086121315433,
-    //         105.08598261412453,
-    //         107.33368709022488,
-    //         109.57250942066366,
-    //         112.98358207057254,
-    //         113.92897848657913,
-    //         115.50214310337833,
-    //         117.54028226408764,
-    //     ];
-
-    //     assert_eq!(y_pred.unwrap(), should_be);
-    // }
+        let dim: Vec<u32> = vec![16, 6];
+
+        let y_pred = transform(
+            &x_train,
+            &dim,
+            &y_train,
+            &x_train,
+            &dim,
+            Regularization::None,
+        );
+
+        let should_be = vec![
+            83.60081557529429,
+            86.9497267843858,
+            88.0967712796537,
+            90.73064861498187,
+            96.53551391475548,
+            97.83066549287923,
+            98.12232410020943,
+            99.87775705667309,
+            103.2086121315433,
+            105.08598261412453,
+            107.33368709022488,
+            109.57250942066366,
+            112.98358207057254,
+            113.92897848657913,
+            115.50214310337833,
+            117.54028226408764,
+        ];
+
+        assert_slices_close(
+            &y_pred.unwrap(),
+            &should_be,
+            Tolerance::relative(1e-6),
+        );
+    }
+
+    #[test]
+    fn regularized_models_still_fit_a_straight_line() {
+        let x_train: Vec<f64> = (0..10).map(|x| x as f64).collect();
+        let y_train: Vec<f64> = x_train.iter().map(|x| 2.0 * x + 1.0).collect();
+        let dim = [10, 1];
+
+        for regularization in [
+            Regularization::Ridge { alpha: 0.001 },
+            Regularization::Lasso { alpha: 0.001 },
+        ] {
+            let y_pred = transform(
+                &x_train,
+                &dim,
+                &y_train,
+                &x_train,
+                &dim,
+                regularization,
+            )
+            .unwrap();
+
+            assert_slices_close(&y_pred, &y_train, Tolerance::relative(1e-2));
+        }
+    }
+
+    #[test]
+    fn a_strong_penalty_shrinks_the_slope() {
+        let x_train: Vec<f64> = (0..10).map(|x| x as f64).collect();
+        let y_train: Vec<f64> = x_train.iter().map(|x| 2.0 * x + 1.0).collect();
+        let dim = [10, 1];
+
+        for regularization in [
+            Regularization::Ridge { alpha: 100.0 },
+            Regularization::Lasso { alpha: 100.0 },
+        ] {
+            let y_pred = transform(
+                &x_train,
+                &dim,
+                &y_train,
+                &x_train,
+                &dim,
+                regularization,
+            )
+            .unwrap();
+
+            let slope = y_pred[9] - y_pred[8];
+            assert!(slope < 2.0 * 0.9, "{:?}: {}", regularization, slope);
+        }
+    }
+
+    #[test]
+    fn the_model_can_be_serialized() {
+        let x_train: Vec<f64> = (0..10).map(|x| x as f64).collect();
+        let y_train: Vec<f64> = x_train.iter().map(|x| 3.0 * x - 2.0).collect();
+        let dim = [10, 1];
+        let model =
+            fit(&x_train, &dim, &y_train, Regularization::None).unwrap();
+
+        let serialized = model.to_json().unwrap();
+
+        let round_tripped: LinearRegression<f64, DenseMatrix<f64>> =
+            serde_json::from_str(&serialized).unwrap();
+        let x_test = DenseMatrix::from_array(10, 1, &x_train);
+        assert_slices_close(
+            &round_tripped.predict(&x_test).unwrap(),
+            &y_train,
+            Tolerance::relative(1e-6),
+        );
+    }
+
+    #[test]
+    fn residuals_are_the_true_values_minus_the_predictions() {
+        let got = residuals(&[1.0, 2.5, -1.0], &[0.5, 3.0, -1.0]).unwrap();
+
+        assert_eq!(got, vec![0.5, -0.5, 0.0]);
+        assert!(residuals(&[1.0], &[1.0, 2.0]).is_err());
+    }
 
     #[test]
     #[should_panic]
@@ -307,7 +716,14 @@ mod tests {
 
         let dim: Vec<u32> = vec![16, 6];
 
-        let y_pred = transform(&x_train, &dim, &y_train, &x_train, &dim);
+        let y_pred = transform(
+            &x_train,
+            &dim,
+            &y_train,
+            &x_train,
+            &dim,
+            Regularization::None,
+        );
 
         let should_be = vec![
             83.60081557529429,
@@ -103,8 +103,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         })?;
         let _xtrain: ndarray::ArrayView2<f64> = x_train
             .buffer
-            .view(&x_train.dimensions)
-            .and_then(|t| t.into_dimensionality())
+            .try_view(&x_train.dimensions)
             .map_err(|e| {
                 KernelError::InvalidInput(InvalidInput {
                     name: "x_train".to_string(),
@@ -120,8 +119,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         })?;
         let _ytrain: ndarray::ArrayView1<f64> = y_train
             .buffer
-            .view(&y_train.dimensions)
-            .and_then(|t| t.into_dimensionality())
+            .try_view(&y_train.dimensions)
             .map_err(|e| {
                 KernelError::InvalidInput(InvalidInput {
                     name: "y_train".to_string(),
@@ -137,8 +135,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         })?;
         let _xtest: ndarray::ArrayView2<f64> = x_test
             .buffer
-            .view(&x_test.dimensions)
-            .and_then(|t| t.into_dimensionality())
+            .try_view(&x_test.dimensions)
             .map_err(|e| {
                 KernelError::InvalidInput(InvalidInput {
                     name: "x_test".to_string(),
@@ -156,10 +153,10 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         }
 
         let output = transform(
-            &x_train.buffer.elements(),
+            f64_elements("x_train", &x_train)?,
             &x_train.dimensions,
-            &y_train.buffer.elements(),
-            &x_test.buffer.elements(),
+            f64_elements("y_train", &y_train)?,
+            f64_elements("x_test", &x_test)?,
             &x_test.dimensions,
         )?;
 
@@ -178,6 +175,21 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
     }
 }
 
+fn f64_elements<'a>(
+    name: &str,
+    tensor: &'a TensorResult,
+) -> Result<&'a [f64], KernelError> {
+    tensor
+        .buffer
+        .try_elements_with_shape(&tensor.dimensions)
+        .map_err(|e| {
+            KernelError::InvalidInput(InvalidInput {
+                name: name.to_string(),
+                reason: BadInputReason::Other(e.to_string()),
+            })
+        })
+}
+
 fn transform(
     x_train: &[f64],
     x_train_dim: &[u32],
@@ -1,3 +1,5 @@
+//! **Deprecated:** use the `metrics` proc-block with `metrics=f1,precision,recall,auc,mae,mse,r2` instead.
+
 // use linfa_logistic::LogisticRegression;
 use smartcore::metrics::{
     auc::AUC, f1::F1, mean_absolute_error::MeanAbsoluteError,
@@ -23,6 +25,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
         metadata.set_homepage(env!("CARGO_PKG_HOMEPAGE"));
         metadata.add_tag("metric");
+        metadata.add_tag("deprecated");
         metadata.add_tag("analytics");
 
         let y_true = TensorMetadata::new("y_true");
@@ -43,7 +46,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let hint =
             supported_shapes(&supported_types, DimensionsParam::Fixed(&[1]));
         f1.add_hint(&hint);
-        metadata.add_input(&f1);
+        metadata.add_output(&f1);
 
         let precision = TensorMetadata::new("precision");
         let supported_types = [ElementType::F64];
@@ -166,8 +169,8 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         })?;
 
         let metric = transform(
-            y_true.buffer.elements().to_vec(),
-            y_pred.buffer.elements().to_vec(),
+            f64_elements("y_true", &y_true)?.to_vec(),
+            f64_elements("y_pred", &y_pred)?.to_vec(),
         );
 
         let f1 = vec![metric.0];
@@ -251,6 +254,21 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
     }
 }
 
+fn f64_elements<'a>(
+    name: &str,
+    tensor: &'a TensorResult,
+) -> Result<&'a [f64], KernelError> {
+    tensor
+        .buffer
+        .try_elements_with_shape(&tensor.dimensions)
+        .map_err(|e| {
+            KernelError::InvalidInput(InvalidInput {
+                name: name.to_string(),
+                reason: BadInputReason::Other(e.to_string()),
+            })
+        })
+}
+
 fn transform(
     y_true: Vec<f64>,
     y_pred: Vec<f64>,
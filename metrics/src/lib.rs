@@ -0,0 +1,786 @@
+//! Evaluate a model's predictions against the true values.
+//!
+//! The `metrics` argument is a comma-separated list of the metrics to
+//! calculate (e.g. `accuracy,f1`), and each one gets its own `[1]`-shaped
+//! output with the same name.
+//!
+//! The classification metrics (`precision`, `recall`, `f1`, and `auc`) treat
+//! labels equal to `1` as the positive class and everything else as
+//! negative. For `auc`, `y_pred` should contain a score or probability for the
+//! positive class rather than a hard label.
+//!
+//! Adjusted R² (`adjusted_r2`) also needs the `n_features` argument, which is
+//! the number of features the model was trained on.
+
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{prelude::*, runtime_v1::*, BufferExt, SliceExt};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata = Metadata::new("Metrics", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("metric");
+        metadata.add_tag("analytics");
+
+        let metrics = ArgumentMetadata::new("metrics");
+        metrics.set_description(
+            "A comma-separated list of the metrics to calculate. Supported metrics are `accuracy`, `f1`, `precision`, `recall`, `mae`, `mse`, `r2`, `adjusted_r2`, `mape`, `smape`, and `auc`.",
+        );
+        metrics.set_default_value("accuracy");
+        metrics.add_hint(&supported_argument_type(ArgumentType::String));
+        metadata.add_argument(&metrics);
+
+        let n_features = ArgumentMetadata::new("n_features");
+        n_features.set_description(
+            "The number of features used to make the predictions. Only required for `adjusted_r2`.",
+        );
+        n_features.add_hint(&non_negative_number());
+        n_features.add_hint(&supported_argument_type(ArgumentType::Integer));
+        metadata.add_argument(&n_features);
+
+        let y_true = TensorMetadata::new("y_true");
+        y_true.set_description("The true values.");
+        let hint =
+            supported_shapes(&[ElementType::F64], DimensionsParam::Fixed(&[0]));
+        y_true.add_hint(&hint);
+        metadata.add_input(&y_true);
+
+        let y_pred = TensorMetadata::new("y_pred");
+        y_pred.set_description("The predicted values.");
+        let hint =
+            supported_shapes(&[ElementType::F64], DimensionsParam::Fixed(&[0]));
+        y_pred.add_hint(&hint);
+        metadata.add_input(&y_pred);
+
+        for metric in Metric::ALL {
+            let output = TensorMetadata::new(metric.name());
+            output.set_description(metric.description());
+            let hint = supported_shapes(
+                &[ElementType::F64],
+                DimensionsParam::Fixed(&[1]),
+            );
+            output.add_hint(&hint);
+            metadata.add_output(&output);
+        }
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        let Settings { metrics, .. } =
+            Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "y_true",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0]),
+        );
+        ctx.add_input_tensor(
+            "y_pred",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0]),
+        );
+
+        for metric in metrics {
+            ctx.add_output_tensor(
+                metric.name(),
+                ElementType::F64,
+                DimensionsParam::Fixed(&[1]),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let Settings {
+            metrics,
+            n_features,
+        } = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let y_true = f64_input(&ctx, "y_true")?;
+        let y_pred = f64_input(&ctx, "y_pred")?;
+
+        if y_true.len() != y_pred.len() {
+            return Err(invalid_input(
+                "y_pred",
+                BadInputReason::InvalidValue(format!(
+                    "Expected {} predictions to match \"y_true\", found {}",
+                    y_true.len(),
+                    y_pred.len()
+                )),
+            ));
+        }
+        if y_true.is_empty() {
+            return Err(invalid_input(
+                "y_true",
+                BadInputReason::InvalidValue(
+                    "At least one value is required".to_string(),
+                ),
+            ));
+        }
+
+        for metric in metrics {
+            let value = metric
+                .evaluate(&y_true, &y_pred, n_features)
+                .map_err(|e| KernelError::Other(e.to_string()))?;
+
+            ctx.set_output_tensor(
+                metric.name(),
+                TensorParam {
+                    element_type: ElementType::F64,
+                    dimensions: &[1],
+                    buffer: [value].as_bytes(),
+                },
+            );
+        }
+
+        Ok(())
+    }
+}
+
+fn f64_input(
+    ctx: &KernelContext,
+    name: &str,
+) -> Result<Vec<f64>, KernelError> {
+    let TensorResult {
+        element_type,
+        buffer,
+        ..
+    } = ctx
+        .get_input_tensor(name)
+        .ok_or_else(|| invalid_input(name, BadInputReason::NotFound))?;
+
+    match element_type {
+        ElementType::F64 => buffer
+            .try_elements::<f64>()
+            .map(|elements| elements.to_vec())
+            .map_err(|e| {
+                invalid_input(name, BadInputReason::InvalidValue(e.to_string()))
+            }),
+        other => Err(invalid_input(
+            name,
+            BadInputReason::InvalidValue(format!(
+                "Expected f64, found {}",
+                other
+            )),
+        )),
+    }
+}
+
+fn invalid_input(name: &str, reason: BadInputReason) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: name.to_string(),
+        reason,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Settings {
+    metrics: Vec<Metric>,
+    /// The number of features, if `adjusted_r2` was requested.
+    n_features: Option<usize>,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let metrics = ctx.parse_argument_with_default::<_, E>(
+            "metrics",
+            String::from("accuracy"),
+        )?;
+        let metrics = parse_metrics(&metrics).map_err(|e| {
+            E::invalid_argument(InvalidArgument::invalid_value("metrics", e))
+        })?;
+
+        let n_features = if metrics.contains(&Metric::AdjustedR2) {
+            Some(ctx.parse_argument::<_, E>("n_features")?)
+        } else {
+            None
+        };
+
+        Ok(Settings {
+            metrics,
+            n_features,
+        })
+    }
+}
+
+/// Parse a comma-separated list of metrics, ignoring duplicates.
+fn parse_metrics(list: &str) -> Result<Vec<Metric>, UnknownMetric> {
+    let mut metrics = Vec::new();
+
+    for name in list.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let metric: Metric = name.parse()?;
+        if !metrics.contains(&metric) {
+            metrics.push(metric);
+        }
+    }
+
+    if metrics.is_empty() {
+        return Err(UnknownMetric(list.to_string()));
+    }
+
+    Ok(metrics)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Metric {
+    Accuracy,
+    F1,
+    Precision,
+    Recall,
+    MeanAbsoluteError,
+    MeanSquaredError,
+    R2,
+    AdjustedR2,
+    MeanAbsolutePercentageError,
+    SymmetricMeanAbsolutePercentageError,
+    Auc,
+}
+
+impl Metric {
+    const ALL: [Metric; 11] = [
+        Metric::Accuracy,
+        Metric::F1,
+        Metric::Precision,
+        Metric::Recall,
+        Metric::MeanAbsoluteError,
+        Metric::MeanSquaredError,
+        Metric::R2,
+        Metric::AdjustedR2,
+        Metric::MeanAbsolutePercentageError,
+        Metric::SymmetricMeanAbsolutePercentageError,
+        Metric::Auc,
+    ];
+
+    /// The name used in the `metrics` argument and for the output tensor.
+    fn name(self) -> &'static str {
+        match self {
+            Metric::Accuracy => "accuracy",
+            Metric::F1 => "f1",
+            Metric::Precision => "precision",
+            Metric::Recall => "recall",
+            Metric::MeanAbsoluteError => "mae",
+            Metric::MeanSquaredError => "mse",
+            Metric::R2 => "r2",
+            Metric::AdjustedR2 => "adjusted_r2",
+            Metric::MeanAbsolutePercentageError => "mape",
+            Metric::SymmetricMeanAbsolutePercentageError => "smape",
+            Metric::Auc => "auc",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Metric::Accuracy => "The fraction of predictions which are exactly equal to the true value.",
+            Metric::F1 => "The harmonic mean of precision and recall.",
+            Metric::Precision => "The fraction of positive predictions which are actually positive.",
+            Metric::Recall => "The fraction of actual positives which were predicted to be positive.",
+            Metric::MeanAbsoluteError => "The mean absolute error.",
+            Metric::MeanSquaredError => "The mean squared error.",
+            Metric::R2 => "The coefficient of determination (R²).",
+            Metric::AdjustedR2 => "R², adjusted for the number of features used by the model.",
+            Metric::MeanAbsolutePercentageError => "The mean absolute percentage error, as a fraction of the true values (i.e. `0.1` is 10%).",
+            Metric::SymmetricMeanAbsolutePercentageError => "The symmetric mean absolute percentage error, which is between `0` and `2`.",
+            Metric::Auc => "The area under the ROC curve, where `y_pred` is the score for the positive class.",
+        }
+    }
+
+    fn evaluate(
+        self,
+        y_true: &[f64],
+        y_pred: &[f64],
+        n_features: Option<usize>,
+    ) -> Result<f64, MetricError> {
+        let value = match self {
+            Metric::Accuracy => accuracy(y_true, y_pred),
+            Metric::F1 => ConfusionMatrix::new(y_true, y_pred).f1(),
+            Metric::Precision => {
+                ConfusionMatrix::new(y_true, y_pred).precision()
+            },
+            Metric::Recall => ConfusionMatrix::new(y_true, y_pred).recall(),
+            Metric::MeanAbsoluteError => {
+                mean(y_true, y_pred, |error| error.abs())
+            },
+            Metric::MeanSquaredError => {
+                mean(y_true, y_pred, |error| error * error)
+            },
+            Metric::R2 => r2(y_true, y_pred),
+            Metric::AdjustedR2 => adjusted_r2(
+                y_true,
+                y_pred,
+                n_features.ok_or(MetricError::MissingFeatureCount)?,
+            )?,
+            Metric::MeanAbsolutePercentageError => mape(y_true, y_pred)?,
+            Metric::SymmetricMeanAbsolutePercentageError => {
+                smape(y_true, y_pred)
+            },
+            Metric::Auc => auc(y_true, y_pred)?,
+        };
+
+        Ok(value)
+    }
+}
+
+impl FromStr for Metric {
+    type Err = UnknownMetric;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Metric::ALL
+            .into_iter()
+            .find(|m| m.name() == s)
+            .ok_or_else(|| UnknownMetric(s.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct UnknownMetric(String);
+
+impl Display for UnknownMetric {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Unknown metric, \"{}\". Expected a comma-separated list of {}",
+            self.0,
+            Metric::ALL.map(Metric::name).join(", "),
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum MetricError {
+    /// The AUC needs both positive and negative samples.
+    SingleClass,
+    /// Scores can't be ranked when one of them is NaN or infinite.
+    NonFiniteScore,
+    /// MAPE is undefined when a true value is zero.
+    ZeroTrueValue,
+    MissingFeatureCount,
+    /// Adjusted R² needs more samples than features, plus one.
+    TooFewSamples { samples: usize, n_features: usize },
+}
+
+impl Display for MetricError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MetricError::SingleClass => write!(
+                f,
+                "The AUC is only defined when \"y_true\" contains both \
+                 positive and negative samples"
+            ),
+            MetricError::NonFiniteScore => write!(
+                f,
+                "The AUC can only be calculated when every score in \"y_pred\" \
+                 is a finite number"
+            ),
+            MetricError::ZeroTrueValue => write!(
+                f,
+                "The MAPE is undefined when \"y_true\" contains zeroes. Try \
+                 \"smape\" instead"
+            ),
+            MetricError::MissingFeatureCount => write!(
+                f,
+                "The \"n_features\" argument is required for adjusted R²"
+            ),
+            MetricError::TooFewSamples {
+                samples,
+                n_features,
+            } => write!(
+                f,
+                "Adjusted R² needs more than {} samples when there are {} \
+                 features, found {}",
+                n_features + 1,
+                n_features,
+                samples
+            ),
+        }
+    }
+}
+
+fn is_positive(label: f64) -> bool { label == 1.0 }
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+struct ConfusionMatrix {
+    true_positives: usize,
+    false_positives: usize,
+    false_negatives: usize,
+}
+
+impl ConfusionMatrix {
+    fn new(y_true: &[f64], y_pred: &[f64]) -> Self {
+        let mut matrix = ConfusionMatrix::default();
+
+        for (&truth, &prediction) in y_true.iter().zip(y_pred) {
+            match (is_positive(truth), is_positive(prediction)) {
+                (true, true) => matrix.true_positives += 1,
+                (false, true) => matrix.false_positives += 1,
+                (true, false) => matrix.false_negatives += 1,
+                (false, false) => {},
+            }
+        }
+
+        matrix
+    }
+
+    /// The precision, or `0` if nothing was predicted to be positive.
+    fn precision(&self) -> f64 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_positives,
+        )
+    }
+
+    /// The recall, or `0` if there are no positive samples.
+    fn recall(&self) -> f64 {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_negatives,
+        )
+    }
+
+    fn f1(&self) -> f64 {
+        let precision = self.precision();
+        let recall = self.recall();
+
+        if precision + recall == 0.0 {
+            0.0
+        } else {
+            2.0 * precision * recall / (precision + recall)
+        }
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+fn accuracy(y_true: &[f64], y_pred: &[f64]) -> f64 {
+    let correct = y_true.iter().zip(y_pred).filter(|(t, p)| t == p).count();
+    ratio(correct, y_true.len())
+}
+
+fn mean(y_true: &[f64], y_pred: &[f64], loss: impl Fn(f64) -> f64) -> f64 {
+    let total: f64 = y_true.iter().zip(y_pred).map(|(t, p)| loss(t - p)).sum();
+    total / y_true.len() as f64
+}
+
+/// The coefficient of determination. This is `1` for a perfect fit and `0`
+/// when the true values are constant but the predictions aren't.
+fn r2(y_true: &[f64], y_pred: &[f64]) -> f64 {
+    let y_mean = y_true.iter().sum::<f64>() / y_true.len() as f64;
+    let residual: f64 =
+        y_true.iter().zip(y_pred).map(|(t, p)| (t - p).powi(2)).sum();
+    let total: f64 = y_true.iter().map(|t| (t - y_mean).powi(2)).sum();
+
+    if total == 0.0 {
+        if residual == 0.0 {
+            1.0
+        } else {
+            0.0
+        }
+    } else {
+        1.0 - residual / total
+    }
+}
+
+/// R² with a penalty for each extra feature, so adding features which don't
+/// improve the fit makes the score worse.
+fn adjusted_r2(
+    y_true: &[f64],
+    y_pred: &[f64],
+    n_features: usize,
+) -> Result<f64, MetricError> {
+    let samples = y_true.len();
+    if samples <= n_features + 1 {
+        return Err(MetricError::TooFewSamples {
+            samples,
+            n_features,
+        });
+    }
+
+    let n = samples as f64;
+    let p = n_features as f64;
+
+    Ok(1.0 - (1.0 - r2(y_true, y_pred)) * (n - 1.0) / (n - p - 1.0))
+}
+
+fn mape(y_true: &[f64], y_pred: &[f64]) -> Result<f64, MetricError> {
+    if y_true.contains(&0.0) {
+        return Err(MetricError::ZeroTrueValue);
+    }
+
+    let total: f64 = y_true
+        .iter()
+        .zip(y_pred)
+        .map(|(t, p)| ((t - p) / t).abs())
+        .sum();
+
+    Ok(total / y_true.len() as f64)
+}
+
+/// The symmetric MAPE, where a true value and prediction which are both zero
+/// count as a perfect prediction.
+fn smape(y_true: &[f64], y_pred: &[f64]) -> f64 {
+    let total: f64 = y_true
+        .iter()
+        .zip(y_pred)
+        .map(|(t, p)| {
+            let denominator = t.abs() + p.abs();
+            if denominator == 0.0 {
+                0.0
+            } else {
+                2.0 * (t - p).abs() / denominator
+            }
+        })
+        .sum();
+
+    total / y_true.len() as f64
+}
+
+/// The area under the ROC curve, calculated from the ranks of the scores
+/// (i.e. the Mann-Whitney U statistic) so ties count as half a win.
+fn auc(y_true: &[f64], scores: &[f64]) -> Result<f64, MetricError> {
+    let positives = y_true.iter().filter(|&&t| is_positive(t)).count();
+    let negatives = y_true.len() - positives;
+    if positives == 0 || negatives == 0 {
+        return Err(MetricError::SingleClass);
+    }
+    // NaN is never equal to itself, which would stop tied scores from being
+    // grouped together below
+    if scores.iter().any(|s| !s.is_finite()) {
+        return Err(MetricError::NonFiniteScore);
+    }
+
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|&a, &b| {
+        scores[a].partial_cmp(&scores[b]).unwrap_or(Ordering::Equal)
+    });
+
+    let mut positive_rank_sum = 0.0;
+    let mut start = 0;
+
+    while start < order.len() {
+        let score = scores[order[start]];
+        let end = order[start..]
+            .iter()
+            .position(|&i| scores[i] != score)
+            .map_or(order.len(), |len| start + len);
+        // Ranks start at 1, and tied scores share the average rank
+        let rank = (start + end + 1) as f64 / 2.0;
+
+        positive_rank_sum += rank
+            * order[start..end]
+                .iter()
+                .filter(|&&i| is_positive(y_true[i]))
+                .count() as f64;
+        start = end;
+    }
+
+    let positives = positives as f64;
+    let u = positive_rank_sum - positives * (positives + 1.0) / 2.0;
+
+    Ok(u / (positives * negatives as f64))
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::testing::{assert_close, Tolerance};
+
+    const Y_TRUE: [f64; 6] = [0.0, 1.0, 1.0, 0.0, 1.0, 0.0];
+    const Y_PRED: [f64; 6] = [0.0, 0.0, 1.0, 1.0, 1.0, 1.0];
+
+    fn evaluate(metric: Metric) -> f64 {
+        metric.evaluate(&Y_TRUE, &Y_PRED, None).unwrap()
+    }
+
+    #[test]
+    fn parse_a_list_of_metrics() {
+        let metrics = parse_metrics(" f1, accuracy,f1,, r2 ").unwrap();
+
+        assert_eq!(metrics, vec![Metric::F1, Metric::Accuracy, Metric::R2]);
+    }
+
+    #[test]
+    fn reject_unknown_metrics() {
+        assert!(parse_metrics("accuracy,rmse").is_err());
+        assert!(parse_metrics(" , ").is_err());
+    }
+
+    #[test]
+    fn classification_metrics() {
+        // 2 true positives, 2 false positives, and 1 false negative
+        assert_close(evaluate(Metric::Accuracy), 0.5, Tolerance::DEFAULT);
+        assert_close(evaluate(Metric::Precision), 0.5, Tolerance::DEFAULT);
+        assert_close(evaluate(Metric::Recall), 2.0 / 3.0, Tolerance::DEFAULT);
+        assert_close(evaluate(Metric::F1), 4.0 / 7.0, Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn regression_metrics() {
+        assert_close(
+            evaluate(Metric::MeanAbsoluteError),
+            0.5,
+            Tolerance::DEFAULT,
+        );
+        assert_close(
+            evaluate(Metric::MeanSquaredError),
+            0.5,
+            Tolerance::DEFAULT,
+        );
+        assert_close(evaluate(Metric::R2), -1.0, Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn auc_counts_ties_as_half() {
+        assert_close(evaluate(Metric::Auc), 0.5, Tolerance::DEFAULT);
+
+        let scores = [0.1, 0.4, 0.35, 0.8];
+        let auc = auc(&[0.0, 0.0, 1.0, 1.0], &scores).unwrap();
+        assert_close(auc, 0.75, Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn auc_needs_both_classes() {
+        assert_eq!(
+            auc(&[1.0, 1.0], &[0.2, 0.9]),
+            Err(MetricError::SingleClass)
+        );
+    }
+
+    #[test]
+    fn auc_rejects_non_finite_scores() {
+        let y_true = [0.0, 1.0, 1.0];
+
+        for bad in [f64::NAN, f64::INFINITY] {
+            assert_eq!(
+                auc(&y_true, &[0.2, bad, 0.9]),
+                Err(MetricError::NonFiniteScore)
+            );
+        }
+    }
+
+    #[test]
+    fn adjusted_r2_penalizes_extra_features() {
+        let y_true = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let y_pred = [1.5, 2.0, 2.5, 4.0, 5.5];
+        // R² = 1 - 0.75 / 10
+        let r2 = r2(&y_true, &y_pred);
+        assert_close(r2, 0.925, Tolerance::DEFAULT);
+
+        let adjusted = adjusted_r2(&y_true, &y_pred, 2).unwrap();
+
+        assert_close(adjusted, 1.0 - 0.075 * 4.0 / 2.0, Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn adjusted_r2_needs_enough_samples() {
+        assert_eq!(
+            adjusted_r2(&[1.0, 2.0, 3.0], &[1.0, 2.0, 3.0], 2),
+            Err(MetricError::TooFewSamples {
+                samples: 3,
+                n_features: 2
+            })
+        );
+        assert_eq!(
+            Metric::AdjustedR2.evaluate(&Y_TRUE, &Y_PRED, None),
+            Err(MetricError::MissingFeatureCount)
+        );
+    }
+
+    #[test]
+    fn percentage_errors() {
+        let y_true = [100.0, 200.0, 50.0, 0.0];
+        let y_pred = [110.0, 150.0, 50.0, 0.0];
+
+        let got = mape(&y_true[..3], &y_pred[..3]).unwrap();
+        assert_close(got, (0.1 + 0.25) / 3.0, Tolerance::DEFAULT);
+
+        let got = smape(&y_true, &y_pred);
+        let should_be = (20.0 / 210.0 + 100.0 / 350.0) / 4.0;
+        assert_close(got, should_be, Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn mape_is_undefined_for_zero_true_values() {
+        let got = Metric::MeanAbsolutePercentageError
+            .evaluate(&Y_TRUE, &Y_PRED, None);
+
+        assert_eq!(got, Err(MetricError::ZeroTrueValue));
+    }
+
+    #[test]
+    fn precision_without_positive_predictions_is_zero() {
+        let matrix = ConfusionMatrix::new(&[1.0, 0.0], &[0.0, 0.0]);
+
+        assert_eq!(matrix.precision(), 0.0);
+        assert_eq!(matrix.f1(), 0.0);
+    }
+}
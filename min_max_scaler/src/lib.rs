@@ -0,0 +1,401 @@
+//! Scale each column of a matrix to a fixed range,
+//!
+//! ```text
+//! scaled = (x - data_min) / (data_max - data_min) * (max - min) + min
+//! ```
+//!
+//! Unlike `normalize`, which uses the smallest and largest value in the whole
+//! tensor, the minimum and maximum are found separately for each column so
+//! features with very different units all end up in the same range. The
+//! minimum and maximum of each column are also emitted so the same scaling
+//! can be reproduced at inference time.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::{Array2, ArrayView2},
+    prelude::*,
+    runtime_v1::*,
+    BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Min-Max Scaler", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("preprocessing");
+        metadata.add_tag("normalization");
+        metadata.add_tag("analytics");
+
+        let min = ArgumentMetadata::new("min");
+        min.set_description("The smallest value in the output range.");
+        min.set_default_value("0");
+        min.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&min);
+
+        let max = ArgumentMetadata::new("max");
+        max.set_description("The largest value in the output range.");
+        max.set_default_value("1");
+        max.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&max);
+
+        let input = TensorMetadata::new("input");
+        input.set_description("The samples to scale, one per row.");
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        input.add_hint(&hint);
+        metadata.add_input(&input);
+
+        let scaled = TensorMetadata::new("scaled");
+        scaled.set_description(
+            "The scaled samples, with every column in the range `[min, max]`.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        scaled.add_hint(&hint);
+        metadata.add_output(&scaled);
+
+        let parameters = TensorMetadata::new("parameters");
+        parameters.set_description(
+            "A `[2, columns]` tensor containing each column's minimum (first row) and maximum (second row).",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[2, 0]),
+        );
+        parameters.add_hint(&hint);
+        metadata.add_output(&parameters);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = FeatureRange::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "input",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "scaled",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "parameters",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[2, 0]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let range = FeatureRange::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("input").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "input".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::F64 {
+            return Err(invalid_input(format!(
+                "Expected a f64 tensor, found {}",
+                element_type
+            )));
+        }
+
+        let input: ArrayView2<f64> =
+            buffer.try_view(&dimensions).map_err(invalid_input)?;
+
+        if input.nrows() == 0 {
+            return Err(invalid_input("No samples to scale"));
+        }
+
+        let scaler = MinMaxScaler::fit(input);
+        let scaled = scaler.transform(input, range);
+        let parameters = scaler.parameters();
+
+        ctx.set_output_tensor(
+            "scaled",
+            TensorParam {
+                element_type: ElementType::F64,
+                dimensions: &dimensions,
+                buffer: scaled.as_slice().unwrap().as_bytes(),
+            },
+        );
+        ctx.set_output_tensor(
+            "parameters",
+            TensorParam {
+                element_type: ElementType::F64,
+                dimensions: &[2, scaler.features() as u32],
+                buffer: parameters.as_slice().unwrap().as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "input".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+/// The range values should be scaled to.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FeatureRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl FeatureRange {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let min: f64 = ctx.parse_argument_with_default::<_, E>("min", 0.0)?;
+        let max: f64 = ctx.parse_argument_with_default::<_, E>("max", 1.0)?;
+
+        for (name, value) in [("min", min), ("max", max)] {
+            if !value.is_finite() {
+                return Err(E::invalid_argument(
+                    InvalidArgument::invalid_value(
+                        name,
+                        format!("Expected a finite number, found {}", value),
+                    ),
+                ));
+            }
+        }
+
+        if min >= max {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "max",
+                format!(
+                    "The max ({}) must be greater than the min ({})",
+                    max, min
+                ),
+            )));
+        }
+
+        Ok(FeatureRange { min, max })
+    }
+}
+
+impl Default for FeatureRange {
+    fn default() -> Self { FeatureRange { min: 0.0, max: 1.0 } }
+}
+
+/// The minimum and maximum of each column in the samples it was fitted on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinMaxScaler {
+    pub data_min: Vec<f64>,
+    pub data_max: Vec<f64>,
+}
+
+impl MinMaxScaler {
+    pub fn fit(x: ArrayView2<'_, f64>) -> Self {
+        let mut data_min = Vec::with_capacity(x.ncols());
+        let mut data_max = Vec::with_capacity(x.ncols());
+
+        for column in x.columns() {
+            let (min, max) = column.iter().fold(
+                (f64::INFINITY, f64::NEG_INFINITY),
+                |(min, max), &value| (min.min(value), max.max(value)),
+            );
+            data_min.push(min);
+            data_max.push(max);
+        }
+
+        MinMaxScaler { data_min, data_max }
+    }
+
+    pub fn features(&self) -> usize { self.data_min.len() }
+
+    /// Scale some samples into the `range`.
+    ///
+    /// Constant columns (where the minimum and maximum are the same) are
+    /// mapped to `range.min`.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if `x` doesn't have [`MinMaxScaler::features()`]
+    /// columns.
+    pub fn transform(
+        &self,
+        x: ArrayView2<'_, f64>,
+        range: FeatureRange,
+    ) -> Array2<f64> {
+        assert_eq!(x.ncols(), self.features());
+
+        let mut scaled = x.to_owned();
+
+        for (mut column, (&data_min, &data_max)) in scaled
+            .columns_mut()
+            .into_iter()
+            .zip(self.data_min.iter().zip(&self.data_max))
+        {
+            let data_range = data_max - data_min;
+            let data_range = if data_range > 0.0 { data_range } else { 1.0 };
+            let scale = (range.max - range.min) / data_range;
+
+            column.mapv_inplace(|v| (v - data_min) * scale + range.min);
+        }
+
+        scaled
+    }
+
+    /// The fitted parameters as a `[2, features]` array, with the minimums in
+    /// the first row and the maximums in the second.
+    pub fn parameters(&self) -> Array2<f64> {
+        let values = self.data_min.iter().chain(&self.data_max).copied();
+
+        Array2::from_shape_vec((2, self.features()), values.collect()).unwrap()
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::{
+        ndarray,
+        testing::{assert_tensors_close, Tolerance},
+    };
+
+    #[test]
+    fn each_column_is_scaled_separately() {
+        let x = ndarray::arr2(&[[1.0, 100.0], [2.0, 300.0], [3.0, 200.0]]);
+
+        let scaler = MinMaxScaler::fit(x.view());
+        let scaled = scaler.transform(x.view(), FeatureRange::default());
+
+        assert_tensors_close(
+            &scaled,
+            &ndarray::arr2(&[[0.0, 0.0], [0.5, 1.0], [1.0, 0.5]]),
+            Tolerance::DEFAULT,
+        );
+    }
+
+    #[test]
+    fn custom_feature_range() {
+        let x = ndarray::arr2(&[[0.0], [5.0], [10.0]]);
+        let range = FeatureRange {
+            min: -1.0,
+            max: 1.0,
+        };
+
+        let scaled = MinMaxScaler::fit(x.view()).transform(x.view(), range);
+
+        assert_tensors_close(
+            &scaled,
+            &ndarray::arr2(&[[-1.0], [0.0], [1.0]]),
+            Tolerance::DEFAULT,
+        );
+    }
+
+    #[test]
+    fn constant_columns_map_to_the_minimum() {
+        let x = ndarray::arr2(&[[4.0], [4.0]]);
+        let range = FeatureRange {
+            min: 2.0,
+            max: 3.0,
+        };
+
+        let scaled = MinMaxScaler::fit(x.view()).transform(x.view(), range);
+
+        assert_eq!(scaled, ndarray::arr2(&[[2.0], [2.0]]));
+    }
+
+    #[test]
+    fn parameters_contain_the_minimum_and_maximum() {
+        let x = ndarray::arr2(&[[1.0, -5.0, 0.0], [3.0, 5.0, 7.0]]);
+
+        let parameters = MinMaxScaler::fit(x.view()).parameters();
+
+        assert_eq!(parameters, x);
+    }
+
+    #[test]
+    fn reuse_the_parameters_on_new_samples() {
+        let x_train = ndarray::arr2(&[[0.0], [10.0]]);
+        let x_test = ndarray::arr2(&[[5.0], [20.0]]);
+
+        let scaler = MinMaxScaler::fit(x_train.view());
+        let scaled = scaler.transform(x_test.view(), FeatureRange::default());
+
+        // values outside the training range aren't clipped
+        assert_tensors_close(
+            &scaled,
+            &ndarray::arr2(&[[0.5], [2.0]]),
+            Tolerance::DEFAULT,
+        );
+    }
+}
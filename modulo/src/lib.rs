@@ -9,11 +9,12 @@ use crate::proc_block_v1::{
     InvalidInput, KernelError,
 };
 use hotg_rune_proc_blocks::{
+    common::element_type,
     runtime_v1::{
         self, ArgumentMetadata, DimensionsParam, ElementType, GraphContext,
         KernelContext, Metadata, TensorMetadata, TensorParam, TensorResult,
     },
-    BufferExt,
+    BufferExt, ValueType,
 };
 use num_traits::{FromPrimitive, ToPrimitive};
 
@@ -24,18 +25,19 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let metadata =
             Metadata::new(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
         metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.with_const_eval();
 
-        let modulo = ArgumentMetadata::new("modulo");
-        modulo.add_hint(&runtime_v1::non_negative_number());
-        metadata.add_argument(&modulo);
+        let modulus = ArgumentMetadata::new("modulus");
+        modulus.add_hint(&runtime_v1::non_negative_number());
+        metadata.add_argument(&modulus);
 
         let element_type = ArgumentMetadata::new("element_type");
         element_type
             .set_description("The type of tensor this proc-block will accept");
         element_type.set_default_value("f64");
-        element_type.add_hint(&runtime_v1::interpret_as_string_in_enum(&[
-            "u8", "i8", "u16", "i16", "u32", "i32", "f32", "u64", "i64", "f64",
-        ]));
+        element_type.add_hint(&runtime_v1::interpret_as_string_in_enum(
+            element_type::NUMERIC,
+        ));
         metadata.add_argument(&element_type);
 
         let input = TensorMetadata::new("input");
@@ -56,25 +58,18 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let _ = get_modulus(|n| ctx.get_argument(n))
             .map_err(GraphError::InvalidArgument)?;
 
-        let element_type = match ctx.get_argument("element_type").as_deref() {
-            Some("u8") => ElementType::U8,
-            Some("i8") => ElementType::I8,
-            Some("u16") => ElementType::U16,
-            Some("i16") => ElementType::I16,
-            Some("u32") => ElementType::U32,
-            Some("i32") => ElementType::I32,
-            Some("f32") => ElementType::F32,
-            Some("u64") => ElementType::U64,
-            Some("i64") => ElementType::I64,
-            Some("f64") | None => ElementType::F64,
-            Some(_) => {
-                return Err(GraphError::InvalidArgument(InvalidArgument {
-                    name: "element_type".to_string(),
-                    reason: BadArgumentReason::InvalidValue(
-                        "Unsupported element type".to_string(),
-                    ),
-                }))
-            },
+        let element_type = match ctx.get_argument(element_type::NAME) {
+            Some(name) => element_type::parse_element_type(
+                &name,
+                element_type::NUMERIC,
+            )
+            .map_err(|e| {
+                GraphError::InvalidArgument(InvalidArgument {
+                    name: element_type::NAME.to_string(),
+                    reason: BadArgumentReason::InvalidValue(e.to_string()),
+                })
+            })?,
+            None => ElementType::F64,
         };
 
         ctx.add_input_tensor("input", element_type, DimensionsParam::Dynamic);
@@ -108,34 +103,34 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
 
         match element_type {
             ElementType::U8 => {
-                modulus_in_place(buffer.elements_mut::<u8>(), modulus)?
+                modulus_in_place(input_elements::<u8>(&mut buffer)?, modulus)?
             },
             ElementType::I8 => {
-                modulus_in_place(buffer.elements_mut::<i8>(), modulus)?
+                modulus_in_place(input_elements::<i8>(&mut buffer)?, modulus)?
             },
             ElementType::U16 => {
-                modulus_in_place(buffer.elements_mut::<u16>(), modulus)?
+                modulus_in_place(input_elements::<u16>(&mut buffer)?, modulus)?
             },
             ElementType::I16 => {
-                modulus_in_place(buffer.elements_mut::<i16>(), modulus)?
+                modulus_in_place(input_elements::<i16>(&mut buffer)?, modulus)?
             },
             ElementType::U32 => {
-                modulus_in_place(buffer.elements_mut::<u32>(), modulus)?
+                modulus_in_place(input_elements::<u32>(&mut buffer)?, modulus)?
             },
             ElementType::I32 => {
-                modulus_in_place(buffer.elements_mut::<i32>(), modulus)?
+                modulus_in_place(input_elements::<i32>(&mut buffer)?, modulus)?
             },
             ElementType::F32 => {
-                modulus_in_place(buffer.elements_mut::<f32>(), modulus)?
+                modulus_in_place(input_elements::<f32>(&mut buffer)?, modulus)?
             },
             ElementType::U64 => {
-                modulus_in_place(buffer.elements_mut::<u64>(), modulus)?
+                modulus_in_place(input_elements::<u64>(&mut buffer)?, modulus)?
             },
             ElementType::I64 => {
-                modulus_in_place(buffer.elements_mut::<i64>(), modulus)?
+                modulus_in_place(input_elements::<i64>(&mut buffer)?, modulus)?
             },
             ElementType::F64 => {
-                modulus_in_place(buffer.elements_mut::<f64>(), modulus)?
+                modulus_in_place(input_elements::<f64>(&mut buffer)?, modulus)?
             },
             ElementType::Utf8 => {
                 return Err(KernelError::Other(
@@ -157,6 +152,17 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
     }
 }
 
+fn input_elements<T: ValueType>(
+    buffer: &mut [u8],
+) -> Result<&mut [T], KernelError> {
+    buffer.try_elements_mut().map_err(|e| {
+        KernelError::InvalidInput(InvalidInput {
+            name: "input".to_string(),
+            reason: BadInputReason::InvalidValue(e.to_string()),
+        })
+    })
+}
+
 fn modulus_in_place<T>(
     values: &mut [T],
     modulus: f64,
@@ -222,4 +228,26 @@ mod tests {
 
         assert_eq!(values, [0.0_f64, 1.0, 0.0, 1.0, 0.0, 1.0]);
     }
+
+    #[test]
+    fn run_against_the_mock_host() {
+        use crate::proc_block_v1::ProcBlockV1 as _;
+        use hotg_rune_proc_blocks::mock;
+
+        mock::reset();
+        mock::set_argument("modulo", "modulus", "2");
+        let input =
+            mock::Tensor::new(ElementType::F64, &[3], &[1.0_f64, 2.0, 3.0]);
+        mock::set_input("modulo", "input", input);
+
+        ProcBlockV1::graph("modulo".to_string()).unwrap();
+        ProcBlockV1::kernel("modulo".to_string()).unwrap();
+
+        let node = mock::node("modulo").unwrap();
+        assert_eq!(node.graph_outputs[0].element_type, ElementType::F64);
+        assert_eq!(
+            node.outputs["output"].to_vec::<f64>(),
+            vec![1.0, 0.0, 1.0]
+        );
+    }
 }
@@ -6,7 +6,7 @@ use hotg_rune_proc_blocks::{
     common,
     ndarray::ArrayView1,
     runtime_v1::{self, *},
-    BufferExt, SliceExt, ValueType,
+    BufferExt, SliceExt, SmallTensor, ValueType,
 };
 
 wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
@@ -161,8 +161,7 @@ where
     T: ValueType,
 {
     buffer
-        .view::<T>(dimensions)
-        .and_then(|t| t.into_dimensionality())
+        .try_view::<T, _>(dimensions)
         .map_err(|e| {
             KernelError::InvalidInput(InvalidInput::invalid_value(
                 "confidences",
@@ -174,7 +173,7 @@ where
 fn most_confident_indices<T>(
     tensor: ArrayView1<T>,
     count: usize,
-) -> Result<Vec<u32>, KernelError>
+) -> Result<SmallTensor<u32>, KernelError>
 where
     T: PartialOrd + Copy,
 {
@@ -216,23 +215,13 @@ fn get_count(
 fn get_element_type(
     get_argument: impl FnOnce(&str) -> Option<String>,
 ) -> Result<ElementType, InvalidArgument> {
-    match get_argument("element_type").as_deref() {
-        Some("u8") => Ok(ElementType::U8),
-        Some("i8") => Ok(ElementType::I8),
-        Some("u16") => Ok(ElementType::U16),
-        Some("i16") => Ok(ElementType::I16),
-        Some("u32") => Ok(ElementType::U32),
-        Some("i32") => Ok(ElementType::I32),
-        Some("f32") => Ok(ElementType::F32),
-        Some("u64") => Ok(ElementType::U64),
-        Some("i64") => Ok(ElementType::I64),
-        Some("f64") => Ok(ElementType::F64),
-        Some(other) => Err(InvalidArgument::invalid_value(
-            "element_type",
-            format!("Unsupported element type: {}", other),
-        )),
-        None => Err(InvalidArgument::not_found("element_type")),
-    }
+    use common::element_type::{parse_element_type, NAME, NUMERIC};
+
+    let name =
+        get_argument(NAME).ok_or_else(|| InvalidArgument::not_found(NAME))?;
+
+    parse_element_type(&name, NUMERIC)
+        .map_err(|e| InvalidArgument::invalid_value(NAME, e))
 }
 
 impl InvalidArgument {
@@ -298,6 +287,6 @@ mod tests {
 
         let got = most_confident_indices(elements.view(), 3).unwrap();
 
-        assert_eq!(got, &[2, 3, 1]);
+        assert_eq!(got.as_slice(), &[2, 3, 1]);
     }
 }
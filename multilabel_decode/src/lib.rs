@@ -0,0 +1,374 @@
+//! Decode the output of a multi-label classifier, where any number of classes
+//! can be present at once (e.g. audio tagging, or a vision model which
+//! detects several attributes in the same image).
+//!
+//! Each logit is passed through a sigmoid and compared against its class's
+//! threshold, and every class at or above its threshold is considered active.
+//! This is different from `argmax` and `most_confident_indices`, which assume
+//! exactly one (or a fixed number of) classes.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    prelude::*, runtime_v1::*, BufferExt, SliceExt, StringBuilder,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Multi-Label Decode", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("classify");
+        metadata.add_tag("multi-label");
+
+        let labels = ArgumentMetadata::new("labels");
+        labels.set_description("The name of each class, one per line.");
+        labels.add_hint(&supported_argument_type(ArgumentType::LongString));
+        metadata.add_argument(&labels);
+
+        let thresholds = ArgumentMetadata::new("thresholds");
+        thresholds.set_description(
+            "A comma-separated list of thresholds, one per class. A single value is used for every class.",
+        );
+        thresholds.set_default_value("0.5");
+        thresholds.add_hint(&supported_argument_type(ArgumentType::String));
+        metadata.add_argument(&thresholds);
+
+        let sigmoid = ArgumentMetadata::new("sigmoid");
+        sigmoid.set_description(
+            "Apply a sigmoid to the logits before comparing them against the thresholds. Disable this if the model already outputs probabilities.",
+        );
+        sigmoid.set_default_value("true");
+        sigmoid.add_hint(&interpret_as_string_in_enum(&["true", "false"]));
+        metadata.add_argument(&sigmoid);
+
+        let logits = TensorMetadata::new("logits");
+        logits.set_description(
+            "The model's output, with one value per class (e.g. `[1, classes]`).",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Dynamic);
+        logits.add_hint(&hint);
+        metadata.add_input(&logits);
+
+        let indices = TensorMetadata::new("indices");
+        indices.set_description("The index of each active class.");
+        let hint =
+            supported_shapes(&[ElementType::U32], DimensionsParam::Fixed(&[0]));
+        indices.add_hint(&hint);
+        metadata.add_output(&indices);
+
+        let names = TensorMetadata::new("labels");
+        names.set_description("The name of each active class.");
+        let hint = supported_shapes(
+            &[ElementType::Utf8],
+            DimensionsParam::Fixed(&[0]),
+        );
+        names.add_hint(&hint);
+        metadata.add_output(&names);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "logits",
+            ElementType::F32,
+            DimensionsParam::Dynamic,
+        );
+        ctx.add_output_tensor(
+            "indices",
+            ElementType::U32,
+            DimensionsParam::Fixed(&[0]),
+        );
+        ctx.add_output_tensor(
+            "labels",
+            ElementType::Utf8,
+            DimensionsParam::Fixed(&[0]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            buffer,
+            ..
+        } = ctx.get_input_tensor("logits").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "logits".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::F32 {
+            return Err(invalid_logits(format!(
+                "Expected a f32 tensor, found {}",
+                element_type
+            )));
+        }
+
+        let logits = buffer.try_elements().map_err(invalid_logits)?;
+        let active = settings.decode(logits)?;
+
+        let indices: Vec<u32> = active.iter().map(|&i| i as u32).collect();
+        let mut names = StringBuilder::new();
+        for &index in &active {
+            names.push(&settings.labels[index]);
+        }
+
+        ctx.set_output_tensor(
+            "indices",
+            TensorParam {
+                element_type: ElementType::U32,
+                dimensions: &[indices.len() as u32],
+                buffer: indices.as_bytes(),
+            },
+        );
+        ctx.set_output_tensor(
+            "labels",
+            TensorParam {
+                element_type: ElementType::Utf8,
+                dimensions: &[indices.len() as u32],
+                buffer: &names.finish(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_logits(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "logits".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Settings {
+    labels: Vec<String>,
+    /// Either one threshold per class, or a single threshold for every
+    /// class.
+    thresholds: Vec<f32>,
+    sigmoid: bool,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let labels: Vec<String> = ctx
+            .required_argument::<E>("labels")?
+            .lines()
+            .map(|line| line.trim().to_string())
+            .collect();
+        let thresholds = ctx.parse_argument_with_default::<_, E>(
+            "thresholds",
+            String::from("0.5"),
+        )?;
+        let sigmoid = ctx.parse_argument_with_default::<_, E>("sigmoid", true)?;
+
+        if labels.is_empty() {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "labels",
+                "At least one label is required",
+            )));
+        }
+
+        let thresholds = parse_thresholds(&thresholds, labels.len())
+            .map_err(|e| {
+                E::invalid_argument(InvalidArgument::invalid_value(
+                    "thresholds",
+                    e,
+                ))
+            })?;
+
+        Ok(Settings {
+            labels,
+            thresholds,
+            sigmoid,
+        })
+    }
+
+    fn threshold(&self, class: usize) -> f32 {
+        match *self.thresholds {
+            [threshold] => threshold,
+            ref thresholds => thresholds[class],
+        }
+    }
+
+    /// Get the indices of every class whose score is at or above its
+    /// threshold.
+    fn decode(&self, logits: &[f32]) -> Result<Vec<usize>, KernelError> {
+        if logits.len() != self.labels.len() {
+            return Err(invalid_logits(format!(
+                "Expected {} logits (one per label), found {}",
+                self.labels.len(),
+                logits.len()
+            )));
+        }
+
+        let active = logits
+            .iter()
+            .enumerate()
+            .filter(|&(class, &logit)| {
+                let score = if self.sigmoid { sigmoid(logit) } else { logit };
+                score >= self.threshold(class)
+            })
+            .map(|(class, _)| class)
+            .collect();
+
+        Ok(active)
+    }
+}
+
+fn sigmoid(x: f32) -> f32 { 1.0 / (1.0 + (-x).exp()) }
+
+fn parse_thresholds(
+    thresholds: &str,
+    classes: usize,
+) -> Result<Vec<f32>, String> {
+    let thresholds = thresholds
+        .split(',')
+        .map(str::trim)
+        .map(|t| {
+            t.parse::<f32>()
+                .map_err(|e| format!("Unable to parse \"{}\": {}", t, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if thresholds.iter().any(|t| !t.is_finite()) {
+        return Err("All thresholds must be finite".to_string());
+    }
+
+    if thresholds.len() != 1 && thresholds.len() != classes {
+        return Err(format!(
+            "Expected 1 or {} thresholds (one per label), found {}",
+            classes,
+            thresholds.len()
+        ));
+    }
+
+    Ok(thresholds)
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(thresholds: &[f32], sigmoid: bool) -> Settings {
+        Settings {
+            labels: vec!["dog".into(), "cat".into(), "bird".into()],
+            thresholds: thresholds.to_vec(),
+            sigmoid,
+        }
+    }
+
+    #[test]
+    fn every_class_above_the_threshold_is_active() {
+        let settings = settings(&[0.5], true);
+
+        let got = settings.decode(&[2.0, -1.0, 0.0]).unwrap();
+
+        // sigmoid(0) is exactly 0.5, which counts as active
+        assert_eq!(got, vec![0, 2]);
+    }
+
+    #[test]
+    fn each_class_can_have_its_own_threshold() {
+        let settings = settings(&[0.9, 0.1, 0.5], false);
+
+        let got = settings.decode(&[0.8, 0.2, 0.4]).unwrap();
+
+        assert_eq!(got, vec![1]);
+    }
+
+    #[test]
+    fn no_active_classes() {
+        let settings = settings(&[0.5], true);
+
+        let got = settings.decode(&[-5.0, -5.0, -5.0]).unwrap();
+
+        assert!(got.is_empty());
+    }
+
+    #[test]
+    fn the_number_of_logits_must_match_the_labels() {
+        let settings = settings(&[0.5], true);
+
+        assert!(settings.decode(&[1.0, 2.0]).is_err());
+    }
+
+    #[test]
+    fn parse_per_class_thresholds() {
+        assert_eq!(parse_thresholds("0.5", 3).unwrap(), vec![0.5]);
+        assert_eq!(
+            parse_thresholds("0.1, 0.2,0.3", 3).unwrap(),
+            vec![0.1, 0.2, 0.3]
+        );
+        assert!(parse_thresholds("0.1,0.2", 3).is_err());
+        assert!(parse_thresholds("0.1,", 2).is_err());
+        assert!(parse_thresholds("inf", 1).is_err());
+    }
+}
@@ -0,0 +1,446 @@
+//! Naive Bayes classifiers, backed by smartcore.
+//!
+//! Naive Bayes assumes every feature is independent given the class, which
+//! makes training a single pass over the data. That means the model can be
+//! fitted on `x_train` every time the Rune is run and used to classify
+//! `x_test` straight away, even on low-powered devices.
+//!
+//! Two variants are supported,
+//!
+//! - `gaussian` - each feature is normally distributed within a class. This
+//!   is a good default for continuous measurements
+//! - `multinomial` - features are non-negative counts (e.g. word counts from
+//!   the `hashing_vectorizer` proc-block), with additive (Laplace) smoothing
+//!   controlled by the `alpha` argument
+
+use smartcore::{
+    linalg::naive::dense_matrix::DenseMatrix,
+    naive_bayes::{
+        gaussian::{GaussianNB, GaussianNBParameters},
+        multinomial::{MultinomialNB, MultinomialNBParameters},
+    },
+};
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::{ArrayView1, ArrayView2},
+    prelude::*,
+    runtime_v1::*,
+    BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        ensure_initialized();
+
+        let metadata =
+            Metadata::new("Naive Bayes", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("classification");
+        metadata.add_tag("probabilistic");
+        metadata.add_tag("analytics");
+
+        let variant = ArgumentMetadata::new("variant");
+        variant.set_description(
+            "Which distribution the features are assumed to follow. Use `gaussian` for continuous values and `multinomial` for counts.",
+        );
+        variant.set_default_value("gaussian");
+        variant.add_hint(&interpret_as_string_in_enum(Variant::NAMES));
+        metadata.add_argument(&variant);
+
+        let alpha = ArgumentMetadata::new("alpha");
+        alpha.set_description(
+            "The additive smoothing parameter used by the `multinomial` variant. Use `0` for no smoothing.",
+        );
+        alpha.set_default_value("1.0");
+        alpha.add_hint(&non_negative_number());
+        alpha.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&alpha);
+
+        let x_train = TensorMetadata::new("x_train");
+        x_train.set_description("The training samples, one per row.");
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        x_train.add_hint(&hint);
+        metadata.add_input(&x_train);
+
+        let y_train = TensorMetadata::new("y_train");
+        y_train.set_description("The class for each training sample.");
+        let hint =
+            supported_shapes(&[ElementType::F64], DimensionsParam::Fixed(&[0]));
+        y_train.add_hint(&hint);
+        metadata.add_input(&y_train);
+
+        let x_test = TensorMetadata::new("x_test");
+        x_test.set_description(
+            "The samples to classify. These must have the same number of columns as `x_train`.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        x_test.add_hint(&hint);
+        metadata.add_input(&x_test);
+
+        let y_test = TensorMetadata::new("y_test");
+        y_test.set_description("The predicted class for each row in `x_test`.");
+        let hint =
+            supported_shapes(&[ElementType::F64], DimensionsParam::Fixed(&[0]));
+        y_test.add_hint(&hint);
+        metadata.add_output(&y_test);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        ensure_initialized();
+
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Variant::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "x_train",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_input_tensor(
+            "y_train",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0]),
+        );
+        ctx.add_input_tensor(
+            "x_test",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "y_test",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        ensure_initialized();
+
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let variant = Variant::from_context::<_, KernelError>(&ctx)?;
+
+        let x_train = get_input(&ctx, "x_train")?;
+        let x_train: ArrayView2<f64> = x_train
+            .buffer
+            .try_view(&x_train.dimensions)
+            .map_err(|e| invalid_input("x_train", e))?;
+        let y_train = get_input(&ctx, "y_train")?;
+        let y_train: ArrayView1<f64> = y_train
+            .buffer
+            .try_view(&y_train.dimensions)
+            .map_err(|e| invalid_input("y_train", e))?;
+        let x_test = get_input(&ctx, "x_test")?;
+        let x_test: ArrayView2<f64> = x_test
+            .buffer
+            .try_view(&x_test.dimensions)
+            .map_err(|e| invalid_input("x_test", e))?;
+
+        let y_test = fit_and_predict(variant, x_train, y_train, x_test)?;
+
+        ctx.set_output_tensor(
+            "y_test",
+            TensorParam {
+                element_type: ElementType::F64,
+                dimensions: &[y_test.len() as u32],
+                buffer: y_test.as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(name: &str, reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: name.to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+fn get_input(
+    ctx: &KernelContext,
+    name: &str,
+) -> Result<TensorResult, KernelError> {
+    let tensor = ctx.get_input_tensor(name).ok_or_else(|| {
+        KernelError::InvalidInput(InvalidInput {
+            name: name.to_string(),
+            reason: BadInputReason::NotFound,
+        })
+    })?;
+
+    if tensor.element_type != ElementType::F64 {
+        return Err(invalid_input(
+            name,
+            format!("Expected a f64 tensor, found {}", tensor.element_type),
+        ));
+    }
+
+    Ok(tensor)
+}
+
+/// The kind of naive Bayes classifier to use, as set by the `variant` and
+/// `alpha` arguments.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Variant {
+    Gaussian,
+    Multinomial { alpha: f64 },
+}
+
+impl Variant {
+    pub const NAMES: &'static [&'static str] = &["gaussian", "multinomial"];
+
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let name = ctx.parse_argument_with_default::<_, E>(
+            "variant",
+            String::from("gaussian"),
+        )?;
+
+        match name.as_str() {
+            "gaussian" => Ok(Variant::Gaussian),
+            "multinomial" => {
+                let alpha: f64 =
+                    ctx.parse_argument_with_default::<_, E>("alpha", 1.0)?;
+
+                if !alpha.is_finite() || alpha < 0.0 {
+                    return Err(E::invalid_argument(
+                        InvalidArgument::invalid_value(
+                            "alpha",
+                            format!(
+                                "Expected a non-negative number, found {}",
+                                alpha
+                            ),
+                        ),
+                    ));
+                }
+
+                Ok(Variant::Multinomial { alpha })
+            },
+            _ => Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "variant",
+                format!(
+                    "Unknown variant, \"{}\". Expected one of {:?}",
+                    name,
+                    Variant::NAMES
+                ),
+            ))),
+        }
+    }
+}
+
+/// Train a classifier on `x_train` and `y_train`, then use it to predict the
+/// class of each row in `x_test`.
+pub fn fit_and_predict(
+    variant: Variant,
+    x_train: ArrayView2<'_, f64>,
+    y_train: ArrayView1<'_, f64>,
+    x_test: ArrayView2<'_, f64>,
+) -> Result<Vec<f64>, KernelError> {
+    if x_train.nrows() == 0 {
+        return Err(invalid_input("x_train", "No training samples"));
+    }
+    if y_train.len() != x_train.nrows() {
+        return Err(invalid_input(
+            "y_train",
+            format!(
+                "Expected {} labels (one per row in \"x_train\"), found {}",
+                x_train.nrows(),
+                y_train.len()
+            ),
+        ));
+    }
+    if x_test.ncols() != x_train.ncols() {
+        return Err(invalid_input(
+            "x_test",
+            format!(
+                "Expected {} columns to match \"x_train\", found {}",
+                x_train.ncols(),
+                x_test.ncols()
+            ),
+        ));
+    }
+
+    let x_train = dense_matrix(x_train);
+    let y_train = y_train.to_vec();
+    let x_test = dense_matrix(x_test);
+
+    match variant {
+        Variant::Gaussian => {
+            GaussianNB::fit(&x_train, &y_train, GaussianNBParameters::default())
+                .and_then(|model| model.predict(&x_test))
+        },
+        Variant::Multinomial { alpha } => MultinomialNB::fit(
+            &x_train,
+            &y_train,
+            MultinomialNBParameters::default().with_alpha(alpha),
+        )
+        .and_then(|model| model.predict(&x_test)),
+    }
+    .map_err(|e| KernelError::Other(e.to_string()))
+}
+
+fn dense_matrix(x: ArrayView2<'_, f64>) -> DenseMatrix<f64> {
+    let values: Vec<f64> = x.iter().copied().collect();
+    DenseMatrix::from_array(x.nrows(), x.ncols(), &values)
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::ndarray;
+
+    #[test]
+    fn gaussian_separates_two_clusters() {
+        let x_train = ndarray::arr2(&[
+            [1.0, 1.1],
+            [0.9, 1.0],
+            [1.2, 0.8],
+            [5.0, 5.2],
+            [5.1, 4.9],
+            [4.8, 5.0],
+        ]);
+        let y_train = ndarray::arr1(&[0.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+        let x_test = ndarray::arr2(&[[1.0, 0.9], [5.2, 5.1]]);
+
+        let y_test = fit_and_predict(
+            Variant::Gaussian,
+            x_train.view(),
+            y_train.view(),
+            x_test.view(),
+        )
+        .unwrap();
+
+        assert_eq!(y_test, vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn multinomial_classifies_word_counts() {
+        // Columns are counts of "goal", "match", "vote", and "election"
+        let x_train = ndarray::arr2(&[
+            [3.0, 2.0, 0.0, 0.0],
+            [2.0, 3.0, 1.0, 0.0],
+            [4.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 3.0, 2.0],
+            [1.0, 0.0, 2.0, 4.0],
+            [0.0, 1.0, 4.0, 3.0],
+        ]);
+        let y_train = ndarray::arr1(&[0.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+        let x_test =
+            ndarray::arr2(&[[2.0, 2.0, 0.0, 0.0], [0.0, 0.0, 1.0, 3.0]]);
+
+        let y_test = fit_and_predict(
+            Variant::Multinomial { alpha: 1.0 },
+            x_train.view(),
+            y_train.view(),
+            x_test.view(),
+        )
+        .unwrap();
+
+        assert_eq!(y_test, vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn one_label_per_training_sample() {
+        let x_train = ndarray::arr2(&[[1.0], [2.0], [3.0]]);
+        let y_train = ndarray::arr1(&[0.0, 1.0]);
+
+        let err = fit_and_predict(
+            Variant::Gaussian,
+            x_train.view(),
+            y_train.view(),
+            x_train.view(),
+        )
+        .unwrap_err();
+
+        assert!(matches!(
+            err,
+            KernelError::InvalidInput(InvalidInput { ref name, .. }) if name == "y_train"
+        ));
+    }
+
+    #[test]
+    fn test_samples_need_the_same_columns() {
+        let x_train = ndarray::arr2(&[[1.0, 2.0], [3.0, 4.0]]);
+        let y_train = ndarray::arr1(&[0.0, 1.0]);
+        let x_test = ndarray::arr2(&[[1.0, 2.0, 3.0]]);
+
+        let err = fit_and_predict(
+            Variant::Gaussian,
+            x_train.view(),
+            y_train.view(),
+            x_test.view(),
+        )
+        .unwrap_err();
+
+        assert!(matches!(
+            err,
+            KernelError::InvalidInput(InvalidInput { ref name, .. }) if name == "x_test"
+        ));
+    }
+}
@@ -4,6 +4,7 @@ pub use crate::noise_reduction::ScaledU16;
 
 use crate::proc_block_v1::*;
 use hotg_rune_proc_blocks::{
+    ndarray::IxDyn,
     runtime_v1::{self, *},
     BufferExt, SliceExt,
 };
@@ -188,12 +189,14 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
 
         let output = match element_type {
             ElementType::F32 =>{
-                buffer.view::<f32>(&dimensions)
+                let samples = buffer.try_view_mut::<u32, IxDyn>(&dimensions)
                     .map_err(|e| KernelError::InvalidInput(InvalidInput {
                         name: "input".to_string(),
                         reason: BadInputReason::Other(e.to_string()),
-                    }))?;
-                transform(noise_filtering, buffer.elements_mut())
+                    }))?
+                    .into_slice()
+                    .expect("Views over a buffer are always contiguous");
+                transform(noise_filtering, samples)
             }
             other => {
                 return Err(KernelError::Other(format!(
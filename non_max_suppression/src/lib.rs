@@ -0,0 +1,603 @@
+//! [Non-maximum suppression][nms] for object detection models.
+//!
+//! Object detectors like SSD and YOLO emit many overlapping boxes for each
+//! object. This proc-block drops boxes below `score_threshold`, then keeps
+//! the highest scoring box and suppresses any other box which overlaps it by
+//! more than `iou_threshold` ([Intersection over Union][iou]), repeating until
+//! no boxes are left.
+//!
+//! The input uses the `[batch, num_boxes, 4 + num_classes]` layout, where
+//! each box is followed by one score per class. The surviving detections are
+//! emitted as `[x, y, height, width, confidence, label]` rows, the same as the
+//! `object_filter` proc-block, so they can be passed straight to `tracker`.
+//!
+//! [nms]: https://paperswithcode.com/method/non-maximum-suppression
+//! [iou]: https://en.wikipedia.org/wiki/Jaccard_index
+
+use std::cmp::Ordering;
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::Ix3, prelude::*, runtime_v1::*, BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// The number of columns in each output detection, `[x, y, height, width,
+/// confidence, label]`.
+const DETECTION_COLUMNS: usize = 6;
+
+/// The number of values used for each box's coordinates.
+const BOX_COLUMNS: usize = 4;
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata = Metadata::new(
+            "Non-Maximum Suppression",
+            env!("CARGO_PKG_VERSION"),
+        );
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("image");
+        metadata.add_tag("object-detection");
+
+        let iou_threshold = ArgumentMetadata::new("iou_threshold");
+        iou_threshold.set_description(
+            "Boxes which overlap a higher scoring box by more than this much (Intersection over Union) are suppressed.",
+        );
+        iou_threshold.set_default_value("0.5");
+        iou_threshold.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&iou_threshold);
+
+        let score_threshold = ArgumentMetadata::new("score_threshold");
+        score_threshold.set_description(
+            "Boxes with a score below this value are ignored.",
+        );
+        score_threshold.set_default_value("0.5");
+        score_threshold.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&score_threshold);
+
+        let per_class = ArgumentMetadata::new("per_class");
+        per_class.set_description(
+            "Only suppress boxes with the same class, and consider every class above the score threshold instead of just the most likely one.",
+        );
+        per_class.set_default_value("true");
+        per_class.add_hint(&interpret_as_string_in_enum(&["true", "false"]));
+        metadata.add_argument(&per_class);
+
+        let box_format = ArgumentMetadata::new("box_format");
+        box_format.set_description(
+            "How the box coordinates are laid out. `center` is `[x, y, height, width]` with `x` and `y` at the box's centre, and `corners` is `[x_min, y_min, x_max, y_max]`.",
+        );
+        box_format.set_default_value("center");
+        box_format.add_hint(&interpret_as_string_in_enum(BoxFormat::NAMES));
+        metadata.add_argument(&box_format);
+
+        let max_detections = ArgumentMetadata::new("max_detections");
+        max_detections.set_description(
+            "The maximum number of detections to keep for each image. All detections are kept when this isn't set.",
+        );
+        max_detections
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&max_detections);
+
+        let boxes = TensorMetadata::new("boxes");
+        boxes.set_description(
+            "The model's predictions as a `[batch, num_boxes, 4 + num_classes]` tensor, where each box's coordinates are followed by a score for each class.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0, 0]),
+        );
+        boxes.add_hint(&hint);
+        metadata.add_input(&boxes);
+
+        let detections = TensorMetadata::new("detections");
+        detections.set_description(
+            "The surviving detections as `[x, y, height, width, confidence, label]` rows, sorted by confidence within each image.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, DETECTION_COLUMNS as u32]),
+        );
+        detections.add_hint(&hint);
+        metadata.add_output(&detections);
+
+        let batch_indices = TensorMetadata::new("batch_indices");
+        batch_indices.set_description(
+            "The index of the image in the batch each detection came from.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::U32], DimensionsParam::Fixed(&[0]));
+        batch_indices.add_hint(&hint);
+        metadata.add_output(&batch_indices);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "boxes",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, 0, 0]),
+        );
+        ctx.add_output_tensor(
+            "detections",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, DETECTION_COLUMNS as u32]),
+        );
+        ctx.add_output_tensor(
+            "batch_indices",
+            ElementType::U32,
+            DimensionsParam::Fixed(&[0]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("boxes").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "boxes".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::F32 {
+            return Err(invalid_boxes(format!(
+                "Expected a f32 tensor, found {}",
+                element_type
+            )));
+        }
+
+        let (batches, boxes, columns) = match *dimensions {
+            [batches, boxes, columns] if columns as usize > BOX_COLUMNS => {
+                (batches as usize, boxes as usize, columns as usize)
+            },
+            _ => {
+                return Err(invalid_boxes(format!(
+                    "Expected a [batch, num_boxes, 4 + num_classes] tensor, found {:?}",
+                    dimensions
+                )))
+            },
+        };
+
+        let raw = buffer
+            .try_view::<f32, Ix3>(&dimensions)
+            .map_err(invalid_boxes)?
+            .to_slice()
+            .expect("Views over a buffer are always contiguous");
+        let per_batch = boxes * columns;
+
+        let mut detections = Vec::new();
+        let mut batch_indices = Vec::new();
+
+        for batch in 0..batches {
+            let rows = &raw[batch * per_batch..(batch + 1) * per_batch];
+
+            for detection in settings.suppress(rows, columns) {
+                detections.extend(detection.to_row());
+                batch_indices.push(batch as u32);
+            }
+        }
+
+        ctx.set_output_tensor(
+            "detections",
+            TensorParam {
+                element_type: ElementType::F32,
+                dimensions: &[
+                    batch_indices.len() as u32,
+                    DETECTION_COLUMNS as u32,
+                ],
+                buffer: detections.as_bytes(),
+            },
+        );
+        ctx.set_output_tensor(
+            "batch_indices",
+            TensorParam {
+                element_type: ElementType::U32,
+                dimensions: &[batch_indices.len() as u32],
+                buffer: batch_indices.as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_boxes(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "boxes".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    iou_threshold: f32,
+    score_threshold: f32,
+    per_class: bool,
+    box_format: BoxFormat,
+    max_detections: Option<usize>,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let iou_threshold: f32 =
+            ctx.parse_argument_with_default::<_, E>("iou_threshold", 0.5)?;
+        let score_threshold: f32 =
+            ctx.parse_argument_with_default::<_, E>("score_threshold", 0.5)?;
+        let per_class =
+            ctx.parse_argument_with_default::<_, E>("per_class", true)?;
+        let box_format = ctx.parse_argument_with_default::<_, E>(
+            "box_format",
+            String::from("center"),
+        )?;
+        let max_detections = match ctx._get_argument("max_detections") {
+            Some(_) => {
+                Some(ctx.parse_argument::<usize, E>("max_detections")?)
+            },
+            None => None,
+        };
+
+        if !(0.0..=1.0).contains(&iou_threshold) {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "iou_threshold",
+                "The threshold must be in the range [0, 1]",
+            )));
+        }
+
+        if !score_threshold.is_finite() {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "score_threshold",
+                "The threshold must be a finite number",
+            )));
+        }
+
+        let box_format = match box_format.as_str() {
+            "center" => BoxFormat::Center,
+            "corners" => BoxFormat::Corners,
+            other => {
+                return Err(E::invalid_argument(
+                    InvalidArgument::invalid_value(
+                        "box_format",
+                        format!(
+                            "Unknown box format, \"{}\". Expected one of {:?}",
+                            other,
+                            BoxFormat::NAMES
+                        ),
+                    ),
+                ))
+            },
+        };
+
+        Ok(Settings {
+            iou_threshold,
+            score_threshold,
+            per_class,
+            box_format,
+            max_detections,
+        })
+    }
+
+    /// Run non-maximum suppression over a single image's boxes, where each
+    /// row has `columns` values (4 coordinates plus one score per class).
+    fn suppress(&self, rows: &[f32], columns: usize) -> Vec<Detection> {
+        let mut candidates = self.candidates(rows, columns);
+        candidates.sort_by(|a, b| {
+            b.confidence
+                .partial_cmp(&a.confidence)
+                .unwrap_or(Ordering::Equal)
+        });
+
+        let mut kept: Vec<Detection> = Vec::new();
+
+        for candidate in candidates {
+            if matches!(self.max_detections, Some(max) if kept.len() >= max) {
+                break;
+            }
+
+            let suppressed = kept.iter().any(|k| {
+                (!self.per_class || k.label == candidate.label)
+                    && k.bounds.iou(&candidate.bounds) > self.iou_threshold
+            });
+
+            if !suppressed {
+                kept.push(candidate);
+            }
+        }
+
+        kept
+    }
+
+    /// Find every box (and, if `per_class` is set, every class within that
+    /// box) with a score at or above the threshold.
+    fn candidates(&self, rows: &[f32], columns: usize) -> Vec<Detection> {
+        let mut candidates = Vec::new();
+
+        for row in rows.chunks_exact(columns) {
+            let (coordinates, scores) = row.split_at(BOX_COLUMNS);
+            let bounds = self.box_format.bounds(coordinates);
+
+            let mut push = |label: usize, confidence: f32| {
+                if confidence >= self.score_threshold {
+                    candidates.push(Detection {
+                        bounds,
+                        confidence,
+                        label,
+                    });
+                }
+            };
+
+            if self.per_class {
+                for (label, &score) in scores.iter().enumerate() {
+                    push(label, score);
+                }
+            } else if let Some((label, &score)) = scores
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(Ordering::Equal))
+            {
+                push(label, score);
+            }
+        }
+
+        candidates
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum BoxFormat {
+    /// `[x, y, height, width]`, where `x` and `y` are the box's centre.
+    Center,
+    /// `[x_min, y_min, x_max, y_max]`.
+    Corners,
+}
+
+impl BoxFormat {
+    const NAMES: &'static [&'static str] = &["center", "corners"];
+
+    fn bounds(self, coordinates: &[f32]) -> BoundingBox {
+        match (self, coordinates) {
+            (BoxFormat::Center, &[x, y, height, width]) => BoundingBox {
+                x,
+                y,
+                height,
+                width,
+            },
+            (BoxFormat::Corners, &[x_min, y_min, x_max, y_max]) => {
+                BoundingBox {
+                    x: (x_min + x_max) / 2.0,
+                    y: (y_min + y_max) / 2.0,
+                    height: y_max - y_min,
+                    width: x_max - x_min,
+                }
+            },
+            _ => unreachable!("Boxes always have 4 coordinates"),
+        }
+    }
+}
+
+/// An axis-aligned bounding box.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct BoundingBox {
+    /// The x coordinate of the box's centre.
+    x: f32,
+    /// The y coordinate of the box's centre.
+    y: f32,
+    height: f32,
+    width: f32,
+}
+
+impl BoundingBox {
+    fn area(&self) -> f32 { self.width.max(0.0) * self.height.max(0.0) }
+
+    /// The [Intersection over Union][iou] of two boxes, where 1.0 means they
+    /// are identical and 0.0 means they don't overlap.
+    ///
+    /// [iou]: https://en.wikipedia.org/wiki/Jaccard_index
+    fn iou(&self, other: &BoundingBox) -> f32 {
+        let overlap = |a_centre: f32, a_size: f32, b_centre: f32, b_size| {
+            let start = (a_centre - a_size / 2.0).max(b_centre - b_size / 2.0);
+            let end = (a_centre + a_size / 2.0).min(b_centre + b_size / 2.0);
+            (end - start).max(0.0)
+        };
+
+        let intersection = overlap(self.x, self.width, other.x, other.width)
+            * overlap(self.y, self.height, other.y, other.height);
+        let union = self.area() + other.area() - intersection;
+
+        if union > 0.0 {
+            intersection / union
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Detection {
+    bounds: BoundingBox,
+    confidence: f32,
+    label: usize,
+}
+
+impl Detection {
+    fn to_row(self) -> [f32; DETECTION_COLUMNS] {
+        let BoundingBox {
+            x,
+            y,
+            height,
+            width,
+        } = self.bounds;
+
+        [x, y, height, width, self.confidence, self.label as f32]
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: Settings = Settings {
+        iou_threshold: 0.5,
+        score_threshold: 0.3,
+        per_class: true,
+        box_format: BoxFormat::Corners,
+        max_detections: None,
+    };
+
+    #[test]
+    fn iou_of_boxes() {
+        let a = BoxFormat::Corners.bounds(&[0.0, 0.0, 2.0, 2.0]);
+        let b = BoxFormat::Corners.bounds(&[1.0, 0.0, 3.0, 2.0]);
+        let c = BoxFormat::Corners.bounds(&[5.0, 5.0, 6.0, 6.0]);
+
+        assert_eq!(a.iou(&a), 1.0);
+        // 2 units of overlap out of 6 units of total area
+        assert!((a.iou(&b) - 1.0 / 3.0).abs() < 1e-6);
+        assert_eq!(a.iou(&c), 0.0);
+    }
+
+    #[test]
+    fn overlapping_boxes_are_suppressed() {
+        #[rustfmt::skip]
+        let rows = [
+            0.0, 0.0, 10.0, 10.0, 0.9, 0.0,
+            1.0, 1.0, 10.0, 10.0, 0.8, 0.0,
+            20.0, 20.0, 30.0, 30.0, 0.7, 0.0,
+        ];
+
+        let got = SETTINGS.suppress(&rows, 6);
+
+        let confidences: Vec<f32> = got.iter().map(|d| d.confidence).collect();
+        assert_eq!(confidences, vec![0.9, 0.7]);
+    }
+
+    #[test]
+    fn low_scores_are_ignored() {
+        let rows = [0.0, 0.0, 10.0, 10.0, 0.2, 0.1];
+
+        let got = SETTINGS.suppress(&rows, 6);
+
+        assert!(got.is_empty());
+    }
+
+    #[test]
+    fn different_classes_are_suppressed_separately() {
+        #[rustfmt::skip]
+        let rows = [
+            0.0, 0.0, 10.0, 10.0, 0.9, 0.0,
+            0.0, 0.0, 10.0, 10.0, 0.0, 0.8,
+        ];
+
+        let per_class = SETTINGS.suppress(&rows, 6);
+        let class_agnostic = Settings {
+            per_class: false,
+            ..SETTINGS
+        }
+        .suppress(&rows, 6);
+
+        let labels: Vec<usize> = per_class.iter().map(|d| d.label).collect();
+        assert_eq!(labels, vec![0, 1]);
+        let labels: Vec<usize> =
+            class_agnostic.iter().map(|d| d.label).collect();
+        assert_eq!(labels, vec![0]);
+    }
+
+    #[test]
+    fn limit_the_number_of_detections() {
+        #[rustfmt::skip]
+        let rows = [
+            0.0, 0.0, 1.0, 1.0, 0.5,
+            10.0, 10.0, 11.0, 11.0, 0.9,
+            20.0, 20.0, 21.0, 21.0, 0.7,
+        ];
+        let settings = Settings {
+            max_detections: Some(2),
+            ..SETTINGS
+        };
+
+        let got = settings.suppress(&rows, 5);
+
+        let confidences: Vec<f32> = got.iter().map(|d| d.confidence).collect();
+        assert_eq!(confidences, vec![0.9, 0.7]);
+    }
+
+    #[test]
+    fn centre_boxes_are_emitted_unchanged() {
+        let settings = Settings {
+            box_format: BoxFormat::Center,
+            ..SETTINGS
+        };
+        let rows = [0.5, 0.25, 0.1, 0.2, 0.1, 0.9];
+
+        let got = settings.suppress(&rows, 6);
+
+        assert_eq!(got.len(), 1);
+        assert_eq!(got[0].to_row(), [0.5, 0.25, 0.1, 0.2, 0.9, 1.0]);
+    }
+}
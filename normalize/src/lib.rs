@@ -1,10 +1,20 @@
+//! Normalize the input to the range `[0, 1]`.
+//!
+//! With the `half` feature enabled, setting `element_type` to `f16` or `bf16`
+//! lets the proc-block accept half-precision values stored as raw bits in a
+//! `u16` tensor.
+
+#[cfg(feature = "half")]
+use hotg_rune_proc_blocks::half_precision::HalfPrecision;
+use hotg_rune_proc_blocks::{
+    common::element_type, runtime_v1::*, BufferExt, SliceExt, ValueType,
+};
+use normalize_core::transform;
+
 use crate::proc_block_v1::*;
-use hotg_rune_proc_blocks::{runtime_v1::*, BufferExt, SliceExt};
-use num_traits::ToPrimitive;
 
 wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
 
-/// Normalize the input to the range `[0, 1]`.
 struct ProcBlockV1;
 
 impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
@@ -17,6 +27,12 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         metadata.set_homepage(env!("CARGO_PKG_HOMEPAGE"));
         metadata.add_tag("normalize");
 
+        let element_type = ArgumentMetadata::new(element_type::NAME);
+        element_type.set_description("The type of the input's elements.");
+        let hint = interpret_as_string_in_enum(&supported_element_types());
+        element_type.add_hint(&hint);
+        metadata.add_argument(&element_type);
+
         let input = TensorMetadata::new("input");
         let supported_types = [
             ElementType::U8,
@@ -48,31 +64,22 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let ctx =
             GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
 
-        let element_type = match ctx.get_argument("element_type").as_deref() {
-            Some("u8") => ElementType::U8,
-            Some("i8") => ElementType::I8,
-            Some("u16") => ElementType::U16,
-            Some("i16") => ElementType::I16,
-            Some("u32") => ElementType::U32,
-            Some("i32") => ElementType::I32,
-            Some("f32") => ElementType::F32,
-            Some("u64") => ElementType::U64,
-            Some("i64") => ElementType::I64,
-            Some("f64") => ElementType::F64,
-            Some(_) => {
-                return Err(GraphError::InvalidArgument(InvalidArgument {
-                    name: "element_type".to_string(),
-                    reason: BadArgumentReason::InvalidValue(
-                        "Unsupported element type".to_string(),
-                    ),
-                }));
-            },
-            None => {
-                return Err(GraphError::InvalidArgument(InvalidArgument {
-                    name: "element_type".to_string(),
-                    reason: BadArgumentReason::NotFound,
-                }))
-            },
+        let name = ctx.get_argument(element_type::NAME).ok_or_else(|| {
+            GraphError::InvalidArgument(InvalidArgument {
+                name: element_type::NAME.to_string(),
+                reason: BadArgumentReason::NotFound,
+            })
+        })?;
+        let element_type = if is_half_precision(&name) {
+            ElementType::U16
+        } else {
+            element_type::parse_element_type(&name, element_type::NUMERIC)
+                .map_err(|e| {
+                    GraphError::InvalidArgument(InvalidArgument {
+                        name: element_type::NAME.to_string(),
+                        reason: BadArgumentReason::InvalidValue(e.to_string()),
+                    })
+                })?
         };
 
         ctx.add_input_tensor("input", element_type, DimensionsParam::Dynamic);
@@ -100,17 +107,40 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             })
         })?;
 
+        #[cfg(feature = "half")]
+        if let Some(values) =
+            widen_half_precision(&ctx, element_type, &buffer, &dimensions)?
+        {
+            return set_normalized(&ctx, &dimensions, transform(&values));
+        }
+
         let output = match element_type {
-            ElementType::U8 => transform(buffer.elements::<u8>()),
-            ElementType::I8 => transform(buffer.elements::<i8>()),
-            ElementType::U16 => transform(buffer.elements::<u16>()),
-            ElementType::I16 => transform(buffer.elements::<i16>()),
-            ElementType::U32 => transform(buffer.elements::<u32>()),
-            ElementType::I32 => transform(buffer.elements::<i32>()),
-            ElementType::F32 => transform(buffer.elements::<f32>()),
-            ElementType::U64 => transform(buffer.elements::<u64>()),
-            ElementType::I64 => transform(buffer.elements::<i64>()),
-            ElementType::F64 => transform(buffer.elements::<f64>()),
+            ElementType::U8 => transform(elements::<u8>(&buffer, &dimensions)?),
+            ElementType::I8 => transform(elements::<i8>(&buffer, &dimensions)?),
+            ElementType::U16 => {
+                transform(elements::<u16>(&buffer, &dimensions)?)
+            },
+            ElementType::I16 => {
+                transform(elements::<i16>(&buffer, &dimensions)?)
+            },
+            ElementType::U32 => {
+                transform(elements::<u32>(&buffer, &dimensions)?)
+            },
+            ElementType::I32 => {
+                transform(elements::<i32>(&buffer, &dimensions)?)
+            },
+            ElementType::F32 => {
+                transform(elements::<f32>(&buffer, &dimensions)?)
+            },
+            ElementType::U64 => {
+                transform(elements::<u64>(&buffer, &dimensions)?)
+            },
+            ElementType::I64 => {
+                transform(elements::<i64>(&buffer, &dimensions)?)
+            },
+            ElementType::F64 => {
+                transform(elements::<f64>(&buffer, &dimensions)?)
+            },
             other => {
                 return Err(KernelError::Other(format!(
                 "The Normalize proc-block doesn't support {:?} element type",
@@ -119,87 +149,131 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             },
         };
 
-        let output = match output {
-            Some(out) => out,
-            None => {
-                return Err(KernelError::Other(
-                    "The input tensor was empty".to_string(),
-                ))
-            },
-        };
+        set_normalized(&ctx, &dimensions, output)
+    }
+}
 
-        ctx.set_output_tensor(
-            "normalized",
-            TensorParam {
-                element_type: ElementType::U32,
-                dimensions: &dimensions,
-                buffer: &output.as_bytes(),
-            },
-        );
+fn set_normalized(
+    ctx: &KernelContext,
+    dimensions: &[u32],
+    output: Option<Vec<f32>>,
+) -> Result<(), KernelError> {
+    let output = match output {
+        Some(out) => out,
+        None => {
+            return Err(KernelError::Other(
+                "The input tensor was empty".to_string(),
+            ))
+        },
+    };
 
-        Ok(())
-    }
+    ctx.set_output_tensor(
+        "normalized",
+        TensorParam {
+            element_type: ElementType::F32,
+            dimensions,
+            buffer: output.as_bytes(),
+        },
+    );
+
+    Ok(())
 }
 
-fn transform<T>(input: &[T]) -> Option<Vec<f32>>
-where
-    T: ToPrimitive,
-{
-    let (min, max) =
-        min_max(input.iter().map(|e| e.to_f32().unwrap())).unwrap();
-    let range = max - min;
-    if range == 0.0 {
-        return Some(vec![0.0; input.len()]);
-    }
-    let mut v: Vec<f32> = Vec::new();
+fn supported_element_types() -> Vec<&'static str> {
+    let names = element_type::NUMERIC.iter().copied();
+    #[cfg(feature = "half")]
+    let names = names.chain(HalfPrecision::NAMES.iter().copied());
+    names.collect()
+}
 
-    for e in input {
-        let e = e.to_f32().unwrap();
-        v.push((e - min) / range)
-    }
-    return Some(v);
+#[cfg(feature = "half")]
+fn is_half_precision(name: &str) -> bool {
+    HalfPrecision::from_name(name).is_some()
 }
 
-fn min_max(items: impl Iterator<Item = f32>) -> Option<(f32, f32)> {
-    items.into_iter().fold(None, |bounds, item| match bounds {
-        Some((min, max)) => {
-            let min = if item < min { item } else { min };
-            let max = if max < item { item } else { max };
-            Some((min, max))
+#[cfg(not(feature = "half"))]
+fn is_half_precision(_name: &str) -> bool { false }
+
+/// Widen the input to `f32` if the `element_type` argument says it contains
+/// half-precision values.
+#[cfg(feature = "half")]
+fn widen_half_precision(
+    ctx: &KernelContext,
+    element_type: ElementType,
+    buffer: &[u8],
+    dimensions: &[u32],
+) -> Result<Option<Vec<f32>>, KernelError> {
+    let format = ctx
+        .get_argument(element_type::NAME)
+        .and_then(|name| HalfPrecision::from_name(&name));
+
+    match format {
+        Some(format) if element_type == ElementType::U16 => {
+            Ok(Some(format.widen(elements::<u16>(buffer, dimensions)?)))
         },
-        None => Some((item, item)),
+        _ => Ok(None),
+    }
+}
+
+fn elements<'a, T: ValueType>(
+    buffer: &'a [u8],
+    dimensions: &[u32],
+) -> Result<&'a [T], KernelError> {
+    buffer.try_elements_with_shape(dimensions).map_err(|e| {
+        KernelError::InvalidInput(InvalidInput {
+            name: "input".to_string(),
+            reason: BadInputReason::InvalidValue(e.to_string()),
+        })
     })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::proc_block_v1::ProcBlockV1 as _;
+    use hotg_rune_proc_blocks::{
+        mock,
+        strategies::{element_types, mock_tensors},
+    };
+    use proptest::prelude::*;
 
-    #[test]
-    fn it_works() {
-        let input = [0.0, 1.0, 2.0];
-
-        let output = transform(&input).unwrap();
-
-        assert_eq!(output, vec![0.0, 0.5, 1.0]);
-    }
-
-    #[test]
-    fn it_works_with_integers() {
-        let input = [0, 1, 2];
-
-        let output = transform(&input).unwrap();
+    proptest! {
+        #[test]
+        fn kernel_never_panics(
+            input in mock_tensors(element_types(), 3, 8),
+            element_type in prop_oneof![
+                proptest::sample::select(ElementType::ALL),
+                proptest::sample::select(supported_element_types()),
+            ],
+        ) {
+            mock::reset();
+            mock::set_argument("normalize", ElementType::NAME, element_type);
+            mock::set_input("normalize", "input", input);
 
-        assert_eq!(output, vec![0.0, 0.5, 1.0]);
+            // Bad arguments and inputs should be reported as errors, so we
+            // only care that nothing panics
+            let _ = ProcBlockV1::graph("normalize".to_string());
+            let _ = ProcBlockV1::kernel("normalize".to_string());
+        }
     }
 
     #[test]
-    fn handle_empty() {
-        let input = [0.0; 384];
+    #[cfg(feature = "half")]
+    fn normalize_half_precision_values() {
+        let format = HalfPrecision::F16;
+        let bits = format.narrow(&[0.0, 1.0, 2.0]);
+        mock::reset();
+        mock::set_argument("normalize", ElementType::NAME, "f16");
+        let input = mock::Tensor::new(ElementType::U16, &[3], &bits);
+        mock::set_input("normalize", "input", input);
 
-        let output = transform(&input.clone()).unwrap();
+        ProcBlockV1::graph("normalize".to_string()).unwrap();
+        ProcBlockV1::kernel("normalize".to_string()).unwrap();
 
-        assert_eq!(output, input);
-        assert_eq!(output.len(), 384);
+        let node = mock::node("normalize").unwrap();
+        assert_eq!(node.graph_inputs[0].element_type, ElementType::U16);
+        let output = &node.outputs["normalized"];
+        assert_eq!(output.element_type, ElementType::F32);
+        assert_eq!(output.to_vec::<f32>(), vec![0.0, 0.5, 1.0]);
     }
 }
@@ -0,0 +1,104 @@
+//! Normalize the input to the range `[0, 1]`.
+//!
+//! This is the algorithm behind the `normalize` proc-block, without any of the
+//! WebAssembly bindings, so it can be used as a normal Rust library.
+
+use num_traits::ToPrimitive;
+
+/// Scale each element in the input to the range `[0, 1]`, returning `None`
+/// if the input is empty.
+pub fn transform<T>(input: &[T]) -> Option<Vec<f32>>
+where
+    T: ToPrimitive,
+{
+    let (min, max) = min_max(input.iter().map(|e| e.to_f32().unwrap()))?;
+    let range = max - min;
+    if range == 0.0 {
+        return Some(vec![0.0; input.len()]);
+    }
+    let mut v: Vec<f32> = Vec::new();
+
+    for e in input {
+        let e = e.to_f32().unwrap();
+        v.push((e - min) / range)
+    }
+    return Some(v);
+}
+
+fn min_max(items: impl Iterator<Item = f32>) -> Option<(f32, f32)> {
+    items.into_iter().fold(None, |bounds, item| match bounds {
+        Some((min, max)) => {
+            let min = if item < min { item } else { min };
+            let max = if max < item { item } else { max };
+            Some((min, max))
+        },
+        None => Some((item, item)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::strategies::tensors;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn output_is_always_in_the_unit_range(
+            input in tensors(-1e6_f32..1e6, 4, 8),
+        ) {
+            prop_assume!(!input.is_empty());
+
+            let output = transform(input.as_slice().unwrap()).unwrap();
+
+            prop_assert_eq!(output.len(), input.len());
+            prop_assert!(output.iter().all(|&v| (0.0..=1.0).contains(&v)));
+        }
+
+        #[test]
+        fn integer_output_is_always_in_the_unit_range(
+            input in tensors(any::<i32>(), 3, 8),
+        ) {
+            prop_assume!(!input.is_empty());
+
+            let output = transform(input.as_slice().unwrap()).unwrap();
+
+            prop_assert!(output.iter().all(|&v| (0.0..=1.0).contains(&v)));
+        }
+    }
+
+    #[test]
+    fn it_works() {
+        let input = [0.0, 1.0, 2.0];
+
+        let output = transform(&input).unwrap();
+
+        assert_eq!(output, vec![0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn it_works_with_integers() {
+        let input = [0, 1, 2];
+
+        let output = transform(&input).unwrap();
+
+        assert_eq!(output, vec![0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn handle_empty() {
+        let input = [0.0; 384];
+
+        let output = transform(&input.clone()).unwrap();
+
+        assert_eq!(output, input);
+        assert_eq!(output.len(), 384);
+    }
+
+    #[test]
+    fn empty_input_is_none() {
+        let input: [f32; 0] = [];
+
+        assert_eq!(transform(&input), None);
+    }
+}
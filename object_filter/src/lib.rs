@@ -1,29 +1,24 @@
-use crate::proc_block_v1::*;
+//! A proc-block which takes 3-d tensor `[1, num_detection, detection_box(x, y,
+//! w, h) + confidence_scores + total_detection_classes]` and filter the
+//! detected objects to:
+//! 1. remove duplicate detection for a single object
+//! 2. remove the objects with low confidence based on a threshold
+//!
+//! giving a 2-d tensor with dimension `[*, 6]` (where * is total number of
+//! detected objects,  and 6 -> `[ x-coordinate, y-coordinate, h, w,
+//! confidence_value, label_index]`) as output.
+
 use hotg_rune_proc_blocks::{
-    ndarray::{s, ArrayView3},
+    prelude::*,
     runtime_v1::{self, *},
     BufferExt, SliceExt,
 };
-use libm::fabsf;
-
-#[macro_use]
-extern crate alloc;
+use object_filter_core::transform;
 
-use alloc::vec::Vec;
-use core::cmp::Ordering;
-use std::fmt::Display;
+use crate::proc_block_v1::*;
 
 wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
 
-/// A proc-block which takes 3-d tensor `[1, num_detection, detection_box(x, y,
-/// w, h) + confidence_scores + total_detection_classes]` and filter the
-/// detected objects to:
-/// 1. remove duplicate detection for a single object
-/// 2. remove the objects with low confidence based on a threshold
-///
-/// giving a 2-d tensor with dimension `[*, 6]` (where * is total number of
-/// detected objects,  and 6 -> `[ x-coordinate, y-coordinate, h, w,
-/// confidence_value, label_index]`) as output.
 struct ProcBlockV1;
 
 impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
@@ -44,7 +39,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         );
         let hint = runtime_v1::supported_argument_type(ArgumentType::Float);
         threshold.add_hint(&hint);
-        threshold.set_default_value("0.7");
+        threshold.set_default_value(&DEFAULT_THRESHOLD.to_string());
         metadata.add_argument(&threshold);
 
         let input = TensorMetadata::new("bounding_boxes");
@@ -60,7 +55,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         output.set_description("The filtered objects and their indices as a list of objects, where each row contains `[x, y, height, width, confidence, index]`.");
         let hint = supported_shapes(
             &[ElementType::F32],
-            DimensionsParam::Fixed(&[0, 5]),
+            DimensionsParam::Fixed(&[0, 6]),
         );
         output.add_hint(&hint);
         metadata.add_output(&output);
@@ -72,6 +67,9 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let ctx = GraphContext::for_node(&node_id)
             .ok_or(GraphError::MissingContext)?;
 
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+
         ctx.add_input_tensor(
             "bounding_boxes",
             ElementType::F32,
@@ -80,7 +78,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         ctx.add_output_tensor(
             "normalized",
             ElementType::F32,
-            DimensionsParam::Fixed(&[0, 5]),
+            DimensionsParam::Fixed(&[0, 6]),
         );
 
         Ok(())
@@ -90,8 +88,8 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let ctx = KernelContext::for_node(&node_id)
             .ok_or(KernelError::MissingContext)?;
 
-        let threshold = get_threshold(|n| ctx.get_argument(n))
-            .map_err(KernelError::InvalidArgument)?;
+        let Settings { threshold } =
+            Settings::from_context::<_, KernelError>(&ctx)?;
 
         let TensorResult {
             element_type,
@@ -104,10 +102,11 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             })
         })?;
 
+        check_dimensions(&dimensions)?;
+
         let output = match element_type {
             ElementType::F32 =>{
-                let tensor =buffer.view::<f32>(&dimensions)
-                .and_then(|t| t.into_dimensionality())
+                let tensor =buffer.try_view::<f32, _>(&dimensions)
                 .map_err(|e| KernelError::InvalidInput(InvalidInput{ name: "bounding_boxes".to_string(), reason: BadInputReason::InvalidValue(e.to_string()) }))?;
                 transform(tensor, threshold)
             }
@@ -119,12 +118,15 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             },
         };
 
+        // Each surviving object is `[x, y, height, width, confidence, index]`
+        let objects = (output.len() / 6) as u32;
+
         ctx.set_output_tensor(
             "normalized",
             TensorParam {
-                element_type: ElementType::U32,
-                dimensions: &dimensions,
-                buffer: &output.as_bytes(),
+                element_type: ElementType::F32,
+                dimensions: &[objects, 6],
+                buffer: output.as_bytes(),
             },
         );
 
@@ -132,173 +134,121 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
     }
 }
 
-fn get_threshold(
-    get_argument: impl FnOnce(&str) -> Option<String>,
-) -> Result<f32, InvalidArgument> {
-    get_argument("threshold")
-        .ok_or_else(|| InvalidArgument::not_found("threshold"))?
-        .parse::<f32>()
-        .map_err(|e| InvalidArgument::invalid_value("threshold", e))
+/// Make sure the input looks like `[1, detections, 4 + 1 + classes]`, with at
+/// least one class.
+fn check_dimensions(dimensions: &[u32]) -> Result<(), KernelError> {
+    match *dimensions {
+        [1, _, columns] if columns >= 6 => Ok(()),
+        _ => Err(KernelError::InvalidInput(InvalidInput {
+            name: "bounding_boxes".to_string(),
+            reason: BadInputReason::InvalidValue(format!(
+                "Expected a [1, detections, 6+] tensor, found {:?}",
+                dimensions
+            )),
+        })),
+    }
 }
 
-impl InvalidArgument {
-    fn not_found(name: impl Into<String>) -> Self {
-        InvalidArgument {
-            name: name.into(),
-            reason: BadArgumentReason::NotFound,
-        }
-    }
+const DEFAULT_THRESHOLD: f32 = 0.7;
 
-    fn invalid_value(name: impl Into<String>, reason: impl Display) -> Self {
-        InvalidArgument {
-            name: name.into(),
-            reason: BadArgumentReason::InvalidValue(reason.to_string()),
-        }
-    }
+#[derive(Debug, Clone, PartialEq)]
+struct Settings {
+    threshold: f32,
 }
 
-fn transform(rectangles: ArrayView3<f32>, threshold: f32) -> Vec<f32> {
-    let dim = rectangles.shape();
-    let mut objects: Vec<Object> = (0..dim[1])
-        .map(|object_index| {
-            rectangles.slice(s![0 as usize, object_index as usize, ..])
-        })
-        .filter(|view| view[4] > threshold)
-        .map(|view| -> Object { Object::from_row(view.as_slice().unwrap()) })
-        .collect();
-
-    while let Some((first, second)) = find_duplicate(&objects) {
-        if objects[first].confidence > objects[second].confidence {
-            objects.remove(second);
-        } else {
-            objects.remove(first);
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let threshold = ctx.parse_argument_with_default::<_, E>(
+            "threshold",
+            DEFAULT_THRESHOLD,
+        )?;
+
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "threshold",
+                "The threshold must be between 0 and 1",
+            )));
         }
+
+        Ok(Settings { threshold })
     }
+}
 
-    let elements = objects
-        .into_iter()
-        .flat_map(|j| j.into_elements())
-        .collect();
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
 
-    return elements;
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
 }
 
-#[derive(Debug, Copy, Clone)]
-struct Object {
-    x: f32,
-    y: f32,
-    height: f32,
-    width: f32,
-    confidence: f32,
-    index: usize,
-}
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
 
-impl Object {
-    pub fn from_row(slice: &[f32]) -> Self {
-        match *slice {
-            [x, y, height, width, _, ref labels @ ..] => {
-                let (index, confidence) = labels
-                    .iter()
-                    .copied()
-                    .enumerate()
-                    .max_by(|a, b| {
-                        a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal)
-                    })
-                    .expect("there should be at least one label");
-
-                Object {
-                    x,
-                    y,
-                    height,
-                    width,
-                    confidence,
-                    index,
-                }
-            },
-            _ => panic!("expected at least 5 elements"),
-        }
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
     }
+}
 
-    fn is_duplicated(&self, other: &Object, threshold: f32) -> bool {
-        fabsf(self.x - other.x) <= threshold
-            && fabsf(self.y - other.y) <= threshold
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
     }
 
-    fn into_elements(self) -> impl IntoIterator<Item = f32> {
-        let Object {
-            x,
-            y,
-            height,
-            width,
-            confidence,
-            index,
-        } = self;
-        [x, y, height, width, confidence, index as f32]
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
     }
-}
 
-fn find_duplicate(objects: &[Object]) -> Option<(usize, usize)> {
-    for i in 0..objects.len() {
-        for j in i + 1..objects.len() {
-            if objects[i].is_duplicated(&objects[j], 0.01) {
-                return Some((i, j));
-            }
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
         }
     }
-    None
 }
 
 #[cfg(test)]
-
-mod test {
-
-    use hotg_rune_proc_blocks::ndarray::{self, Array1};
-
+mod tests {
     use super::*;
+    use crate::proc_block_v1::ProcBlockV1 as _;
+    use hotg_rune_proc_blocks::{
+        mock,
+        strategies::{element_types, mock_tensors},
+    };
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn kernel_never_panics(
+            bounding_boxes in mock_tensors(
+                prop_oneof![3 => Just(ElementType::F32), 1 => element_types()],
+                3,
+                12,
+            ),
+            threshold in prop_oneof![3 => 0.0_f32..=1.0, 1 => any::<f32>()],
+        ) {
+            mock::reset();
+            mock::set_argument(
+                "object_filter",
+                "threshold",
+                threshold.to_string(),
+            );
+            mock::set_input("object_filter", "bounding_boxes", bounding_boxes);
 
-    #[test]
-    fn test_object_filter() {
-        let v: Array1<f32> = ndarray::array![
-            0.27335986, 0.43181776, 0.40072349, 0.33026114, 0.75, 0.1849257,
-            0.8824799, 0.26666544, 0.10702547, 0.34699273, 0.27335986,
-            0.43181776, 0.40072349, 0.33026114, 0.63204721, 0.2141086,
-            0.58288711, 0.08516971, 0.33079992, 0.0627511, 0.51991946,
-            0.44326415, 0.09308417, 0.25098184, 0.64352701, 0.48809405,
-            0.35556684, 0.23886549, 0.15850841, 0.61959053, 0.62318601,
-            0.34463603, 0.07799015, 0.33482861, 0.22496075, 0.58609099,
-            0.12996288, 0.47061749, 0.56641317, 0.49165747, 0.3426614,
-            0.45904443, 0.07293156, 0.2054915, 0.45656552, 0.36487279,
-            0.62364449, 0.32963318, 0.35004969, 0.14574761, 0.44673359,
-            0.29083161, 0.2129067, 0.41462883, 0.33459402, 0.17917575,
-            0.09818682, 0.04437961, 0.5769604, 0.34821418, 0.44926693,
-            0.4287493, 0.2332583, 0.29233373, 0.5974608, 0.02897593,
-            0.09843597, 0.44231495, 0.30452269, 0.56230679, 0.0113074,
-            0.56081945, 0.53853333, 0.43793348, 0.17007934, 0.35080665,
-            0.05898283, 0.05127876, 0.29145357, 0.59377787, 0.51103643,
-            0.13517603, 0.19269662, 0.47548843, 0.20795399,
-        ];
-        let v = v.broadcast((1, 1, 85)).unwrap();
-        let output = transform(v, 0.7);
-        let should_be: Vec<f32> = vec![
-            0.27335986, 0.43181776, 0.40072349, 0.33026114, 0.8824799, 1.0,
-        ];
-        assert_eq!(output, should_be);
-    }
-
-    #[test]
-    fn find_the_duplicates() {
-        let obj = Object {
-            x: 0.5,
-            y: 0.5,
-            width: 1.0,
-            height: 1.0,
-            confidence: 1.0,
-            index: 0,
-        };
-        let objects = vec![obj, obj];
-
-        let duplicate_indices = find_duplicate(&objects).unwrap();
-
-        assert_eq!(duplicate_indices, (0, 1));
+            // Bad arguments and inputs should be reported as errors, so we
+            // only care that nothing panics
+            let _ = ProcBlockV1::graph("object_filter".to_string());
+            let _ = ProcBlockV1::kernel("object_filter".to_string());
+        }
     }
 }
@@ -0,0 +1,463 @@
+//! One-hot encode categorical labels.
+//!
+//! Each label becomes a row with a `1.0` in the column for its category and
+//! `0.0` everywhere else. The categories are determined by,
+//!
+//! - The `categories` argument, when it is provided. Labels are matched
+//!   against each line (integers are compared using their decimal
+//!   representation) and unknown labels become a row of zeroes
+//! - Otherwise, integer labels are used as the column index directly and
+//!   there are `max(labels) + 1` columns
+//! - Otherwise, each distinct string gets a column in the order they first
+//!   appear
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::Array2, prelude::*, runtime_v1::*, BufferExt, SliceExt,
+    ValueType,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+const SUPPORTED_ELEMENT_TYPES: &[&str] =
+    &["u8", "i16", "u16", "u32", "i32", "u64", "i64", "utf8"];
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata = Metadata::new("One-Hot", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("preprocessing");
+        metadata.add_tag("categorical");
+        metadata.add_tag("analytics");
+
+        let element_type = ArgumentMetadata::element_type_in(
+            SUPPORTED_ELEMENT_TYPES,
+            ElementType::Utf8,
+        );
+        element_type.set_description("The type of the labels.");
+        metadata.add_argument(&element_type);
+
+        let categories = ArgumentMetadata::new("categories");
+        categories.set_description(
+            "The name of each category, one per line. When this isn't provided, integer labels are used as the column index and string labels get a column in the order they first appear.",
+        );
+        categories
+            .add_hint(&supported_argument_type(ArgumentType::LongString));
+        metadata.add_argument(&categories);
+
+        let labels = TensorMetadata::new("labels");
+        labels.set_description(
+            "The label for each sample. Tensors with more than one dimension are flattened.",
+        );
+        let hint = supported_shapes(
+            &[
+                ElementType::U8,
+                ElementType::I16,
+                ElementType::U16,
+                ElementType::U32,
+                ElementType::I32,
+                ElementType::U64,
+                ElementType::I64,
+                ElementType::Utf8,
+            ],
+            DimensionsParam::Dynamic,
+        );
+        labels.add_hint(&hint);
+        metadata.add_input(&labels);
+
+        let encoded = TensorMetadata::new("encoded");
+        encoded.set_description(
+            "A `[samples, categories]` tensor where each row has a `1.0` in the column for that sample's category.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        encoded.add_hint(&hint);
+        metadata.add_output(&encoded);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        let Settings {
+            element_type,
+            categories,
+        } = Settings::from_context::<_, GraphError>(&ctx)?;
+        let columns = categories.as_ref().map_or(0, |c| c.len() as u32);
+
+        ctx.add_input_tensor("labels", element_type, DimensionsParam::Dynamic);
+        ctx.add_output_tensor(
+            "encoded",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, columns]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            buffer,
+            ..
+        } = ctx.get_input_tensor("labels").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "labels".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != settings.element_type {
+            return Err(invalid_input(format!(
+                "Expected a {} tensor, found {}",
+                settings.element_type, element_type
+            )));
+        }
+
+        let labels = match element_type {
+            ElementType::U8 => Labels::integers::<u8>(&buffer)?,
+            ElementType::I16 => Labels::integers::<i16>(&buffer)?,
+            ElementType::U16 => Labels::integers::<u16>(&buffer)?,
+            ElementType::U32 => Labels::integers::<u32>(&buffer)?,
+            ElementType::I32 => Labels::integers::<i32>(&buffer)?,
+            ElementType::U64 => Labels::integers::<u64>(&buffer)?,
+            ElementType::I64 => Labels::integers::<i64>(&buffer)?,
+            ElementType::Utf8 => {
+                Labels::Strings(buffer.strings().map_err(invalid_input)?)
+            },
+            other => {
+                return Err(invalid_input(format!(
+                    "Labels must be integers or strings, found {}",
+                    other
+                )))
+            },
+        };
+
+        let encoded = labels
+            .encode(settings.categories.as_deref())
+            .map_err(invalid_input)?;
+        let (rows, columns) = encoded.dim();
+
+        ctx.set_output_tensor(
+            "encoded",
+            TensorParam {
+                element_type: ElementType::F32,
+                dimensions: &[rows as u32, columns as u32],
+                buffer: encoded.as_slice().unwrap().as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "labels".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Settings {
+    element_type: ElementType,
+    categories: Option<Vec<String>>,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let element_type =
+            ctx.element_type::<E>(ElementType::Utf8, SUPPORTED_ELEMENT_TYPES)?;
+        let categories = ctx
+            ._get_argument("categories")
+            .map(|c| parse_categories(&c))
+            .transpose()
+            .map_err(|e| {
+                E::invalid_argument(InvalidArgument::invalid_value(
+                    "categories",
+                    e,
+                ))
+            })?;
+
+        Ok(Settings {
+            element_type,
+            categories,
+        })
+    }
+}
+
+fn parse_categories(s: &str) -> Result<Vec<String>, String> {
+    let mut categories: Vec<String> = Vec::new();
+
+    for line in s.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if categories.iter().any(|c| c == line) {
+            return Err(format!("\"{}\" is listed more than once", line));
+        }
+        categories.push(line.to_string());
+    }
+
+    if categories.is_empty() {
+        return Err("At least one category is required".to_string());
+    }
+
+    Ok(categories)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Labels<'a> {
+    Integers(Vec<i64>),
+    Strings(Vec<&'a str>),
+}
+
+impl Labels<'_> {
+    fn integers<T>(buffer: &[u8]) -> Result<Self, KernelError>
+    where
+        T: ValueType + Copy + TryInto<i64> + std::fmt::Display,
+    {
+        let values = buffer.try_elements::<T>().map_err(invalid_input)?;
+
+        values
+            .iter()
+            .map(|&value| {
+                value.try_into().map_err(|_| {
+                    invalid_input(format!("{} is too large to be a label", value))
+                })
+            })
+            .collect::<Result<Vec<i64>, _>>()
+            .map(Labels::Integers)
+    }
+
+    /// One-hot encode the labels, using the rules described in the
+    /// module-level docs.
+    pub fn encode(
+        &self,
+        categories: Option<&[String]>,
+    ) -> Result<Array2<f32>, EncodeError> {
+        let (columns, indices): (usize, Vec<Option<usize>>) =
+            match (self, categories) {
+                (Labels::Integers(values), Some(categories)) => {
+                    let indices = values
+                        .iter()
+                        .map(|v| {
+                            let v = v.to_string();
+                            categories.iter().position(|c| *c == v)
+                        })
+                        .collect();
+                    (categories.len(), indices)
+                },
+                (Labels::Strings(values), Some(categories)) => {
+                    let indices = values
+                        .iter()
+                        .map(|&v| categories.iter().position(|c| c == v))
+                        .collect();
+                    (categories.len(), indices)
+                },
+                (Labels::Integers(values), None) => {
+                    let mut indices = Vec::with_capacity(values.len());
+                    for &value in values {
+                        let index = usize::try_from(value)
+                            .map_err(|_| EncodeError::Negative(value))?;
+                        indices.push(Some(index));
+                    }
+                    let columns =
+                        indices.iter().flatten().max().map_or(0, |&m| m + 1);
+                    (columns, indices)
+                },
+                (Labels::Strings(values), None) => {
+                    let mut seen: Vec<&str> = Vec::new();
+                    let mut indices = Vec::with_capacity(values.len());
+                    for &value in values {
+                        let index =
+                            match seen.iter().position(|&s| s == value) {
+                                Some(index) => index,
+                                None => {
+                                    seen.push(value);
+                                    seen.len() - 1
+                                },
+                            };
+                        indices.push(Some(index));
+                    }
+                    (seen.len(), indices)
+                },
+            };
+
+        Ok(one_hot(&indices, columns))
+    }
+}
+
+/// Create a `[indices.len(), columns]` array with a `1.0` in each row's
+/// column, where `None` gives a row of zeroes.
+pub fn one_hot(indices: &[Option<usize>], columns: usize) -> Array2<f32> {
+    let mut encoded = Array2::zeros((indices.len(), columns));
+
+    for (row, index) in indices.iter().enumerate() {
+        if let Some(column) = *index {
+            encoded[[row, column]] = 1.0;
+        }
+    }
+
+    encoded
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EncodeError {
+    /// Integer labels are used as indices when no categories are provided,
+    /// so they can't be negative.
+    Negative(i64),
+}
+
+impl std::fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodeError::Negative(value) => write!(
+                f,
+                "Integer labels can't be negative ({}) unless the categories are provided",
+                value
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::ndarray;
+
+    fn categories(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn integers_are_used_as_indices() {
+        let labels = Labels::Integers(vec![2, 0, 1]);
+
+        let got = labels.encode(None).unwrap();
+
+        let expected = ndarray::arr2(&[
+            [0.0, 0.0, 1.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+        ]);
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn negative_integers_need_categories() {
+        let labels = Labels::Integers(vec![1, -1]);
+
+        assert_eq!(labels.encode(None), Err(EncodeError::Negative(-1)));
+
+        let categories = categories(&["-1", "1"]);
+        let got = labels.encode(Some(&categories)).unwrap();
+        assert_eq!(got, ndarray::arr2(&[[0.0, 1.0], [1.0, 0.0]]));
+    }
+
+    #[test]
+    fn strings_get_columns_in_the_order_they_appear() {
+        let labels = Labels::Strings(vec!["cat", "dog", "cat", "bird"]);
+
+        let got = labels.encode(None).unwrap();
+
+        let expected = ndarray::arr2(&[
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0],
+        ]);
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn unknown_categories_are_all_zeroes() {
+        let labels = Labels::Strings(vec!["red", "purple", "blue"]);
+        let categories = categories(&["red", "green", "blue"]);
+
+        let got = labels.encode(Some(&categories)).unwrap();
+
+        let expected = ndarray::arr2(&[
+            [1.0, 0.0, 0.0],
+            [0.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0],
+        ]);
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn no_labels() {
+        let labels = Labels::Integers(Vec::new());
+
+        let got = labels.encode(None).unwrap();
+
+        assert_eq!(got.dim(), (0, 0));
+    }
+
+    #[test]
+    fn parse_the_category_list() {
+        assert_eq!(
+            parse_categories("red\n green \n\nblue\n").unwrap(),
+            ["red", "green", "blue"]
+        );
+        assert!(parse_categories("red\nred").is_err());
+        assert!(parse_categories("\n").is_err());
+    }
+}
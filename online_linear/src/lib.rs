@@ -0,0 +1,548 @@
+//! A linear model which is trained incrementally using stochastic gradient
+//! descent, similar to scikit-learn's `partial_fit()`.
+//!
+//! Every invocation updates the model using the samples in `x_train` and
+//! `y_train` (which may be empty), then makes predictions for `x_test`. This
+//! makes it possible to personalize a model on-device as new samples arrive,
+//! without retraining from scratch.
+//!
+//! The model's weights and bias are kept between invocations, so each call
+//! carries on training from where the last one stopped.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::{ArrayView1, ArrayView2},
+    prelude::*,
+    runtime_v1::*,
+    state::NodeState,
+    BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// Each node's model.
+static MODELS: NodeState<Model> = NodeState::new();
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Online Linear Model", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("regression");
+        metadata.add_tag("classification");
+        metadata.add_tag("linear modeling");
+        metadata.add_tag("stateful");
+
+        let task = ArgumentMetadata::new("task");
+        task.set_description(
+            "Whether to predict a continuous value (`regression`) or a 0/1 label (`classification`, using logistic regression).",
+        );
+        task.set_default_value("regression");
+        let hint =
+            interpret_as_string_in_enum(&["regression", "classification"]);
+        task.add_hint(&hint);
+        metadata.add_argument(&task);
+
+        let learning_rate = ArgumentMetadata::new("learning_rate");
+        learning_rate.set_description(
+            "How big a step to take for each training sample.",
+        );
+        learning_rate.set_default_value("0.01");
+        learning_rate.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&learning_rate);
+
+        let epochs = ArgumentMetadata::new("epochs");
+        epochs.set_description(
+            "How many passes to make over each batch of training samples.",
+        );
+        epochs.set_default_value("1");
+        epochs
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&epochs);
+
+        let x_train = TensorMetadata::new("x_train");
+        x_train.set_description(
+            "New training samples, as a `[samples, features]` tensor. This may have zero samples.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        x_train.add_hint(&hint);
+        metadata.add_input(&x_train);
+
+        let y_train = TensorMetadata::new("y_train");
+        y_train.set_description(
+            "The target for each training sample. Classification targets must be 0 or 1.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F64], DimensionsParam::Fixed(&[0]));
+        y_train.add_hint(&hint);
+        metadata.add_input(&y_train);
+
+        let x_test = TensorMetadata::new("x_test");
+        x_test.set_description("The samples to make predictions for.");
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        x_test.add_hint(&hint);
+        metadata.add_input(&x_test);
+
+        let y_test = TensorMetadata::new("y_test");
+        y_test.set_description("The prediction for each test sample.");
+        let hint =
+            supported_shapes(&[ElementType::F64], DimensionsParam::Fixed(&[0]));
+        y_test.add_hint(&hint);
+        metadata.add_output(&y_test);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "x_train",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_input_tensor(
+            "y_train",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0]),
+        );
+        ctx.add_input_tensor(
+            "x_test",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "y_test",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let x_train = get_input(&ctx, "x_train")?;
+        let x_train = view_2d(&x_train, "x_train")?;
+        let y_train = get_input(&ctx, "y_train")?;
+        let y_train = view_1d(&y_train, "y_train")?;
+        let x_test = get_input(&ctx, "x_test")?;
+        let x_test = view_2d(&x_test, "x_test")?;
+
+        let predictions = MODELS.with(&id, |model| {
+            model.partial_fit(x_train, y_train, settings)?;
+            model.predict(x_test, settings.task)
+        })?;
+
+        ctx.set_output_tensor(
+            "y_test",
+            TensorParam {
+                element_type: ElementType::F64,
+                dimensions: &[predictions.len() as u32],
+                buffer: predictions.as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(name: &str, reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: name.to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+fn get_input(
+    ctx: &KernelContext,
+    name: &str,
+) -> Result<TensorResult, KernelError> {
+    let tensor = ctx.get_input_tensor(name).ok_or_else(|| {
+        KernelError::InvalidInput(InvalidInput {
+            name: name.to_string(),
+            reason: BadInputReason::NotFound,
+        })
+    })?;
+
+    if tensor.element_type != ElementType::F64 {
+        return Err(invalid_input(
+            name,
+            format!("Expected a f64 tensor, found {}", tensor.element_type),
+        ));
+    }
+
+    Ok(tensor)
+}
+
+fn view_2d<'a>(
+    tensor: &'a TensorResult,
+    name: &str,
+) -> Result<ArrayView2<'a, f64>, KernelError> {
+    tensor
+        .buffer
+        .try_view::<f64, _>(&tensor.dimensions)
+        .map_err(|e| invalid_input(name, e))
+}
+
+fn view_1d<'a>(
+    tensor: &'a TensorResult,
+    name: &str,
+) -> Result<ArrayView1<'a, f64>, KernelError> {
+    tensor
+        .buffer
+        .try_view::<f64, _>(&tensor.dimensions)
+        .map_err(|e| invalid_input(name, e))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Task {
+    Regression,
+    /// Binary classification using logistic regression.
+    Classification,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    task: Task,
+    learning_rate: f64,
+    epochs: usize,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let task = ctx.parse_argument_with_default::<_, E>(
+            "task",
+            String::from("regression"),
+        )?;
+        let learning_rate: f64 =
+            ctx.parse_argument_with_default::<_, E>("learning_rate", 0.01)?;
+        let epochs = ctx.parse_argument_with_default::<_, E>("epochs", 1)?;
+
+        let task = match task.as_str() {
+            "regression" => Task::Regression,
+            "classification" => Task::Classification,
+            other => {
+                return Err(E::invalid_argument(
+                    InvalidArgument::invalid_value(
+                        "task",
+                        format!("Unknown task, \"{}\"", other),
+                    ),
+                ))
+            },
+        };
+
+        if !(learning_rate > 0.0 && learning_rate.is_finite()) {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "learning_rate",
+                "The learning rate must be a positive number",
+            )));
+        }
+
+        Ok(Settings {
+            task,
+            learning_rate,
+            epochs,
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Model {
+    /// One weight per feature, or empty if the model hasn't been trained.
+    weights: Vec<f64>,
+    bias: f64,
+}
+
+impl Model {
+    /// Update the model with a batch of training samples.
+    ///
+    /// The model is reset if the number of features changes.
+    fn partial_fit(
+        &mut self,
+        x: ArrayView2<'_, f64>,
+        y: ArrayView1<'_, f64>,
+        settings: Settings,
+    ) -> Result<(), KernelError> {
+        if x.nrows() != y.len() {
+            return Err(invalid_input(
+                "y_train",
+                format!(
+                    "Expected {} targets (one per training sample), found {}",
+                    x.nrows(),
+                    y.len()
+                ),
+            ));
+        }
+
+        if x.nrows() == 0 {
+            return Ok(());
+        }
+
+        if settings.task == Task::Classification
+            && y.iter().any(|&label| label != 0.0 && label != 1.0)
+        {
+            return Err(invalid_input(
+                "y_train",
+                "Classification targets must be 0 or 1",
+            ));
+        }
+
+        if self.weights.len() != x.ncols() {
+            *self = Model {
+                weights: vec![0.0; x.ncols()],
+                bias: 0.0,
+            };
+        }
+
+        for _ in 0..settings.epochs {
+            for (sample, &target) in x.outer_iter().zip(y) {
+                let sample = sample.as_slice().expect("Rows are contiguous");
+                let error = self.evaluate(sample, settings.task) - target;
+                let step = settings.learning_rate * error;
+
+                for (weight, &feature) in self.weights.iter_mut().zip(sample) {
+                    *weight -= step * feature;
+                }
+                self.bias -= step;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn predict(
+        &self,
+        x: ArrayView2<'_, f64>,
+        task: Task,
+    ) -> Result<Vec<f64>, KernelError> {
+        if x.nrows() > 0 && x.ncols() != self.weights.len() {
+            return Err(invalid_input(
+                "x_test",
+                format!(
+                    "The model was trained with {} features, but the test samples have {}",
+                    self.weights.len(),
+                    x.ncols()
+                ),
+            ));
+        }
+
+        let predictions = x
+            .outer_iter()
+            .map(|sample| {
+                let sample = sample.as_slice().expect("Rows are contiguous");
+                let value = self.evaluate(sample, task);
+
+                match task {
+                    Task::Regression => value,
+                    Task::Classification if value >= 0.5 => 1.0,
+                    Task::Classification => 0.0,
+                }
+            })
+            .collect();
+
+        Ok(predictions)
+    }
+
+    /// Get the raw model output, which is a probability when classifying.
+    fn evaluate(&self, sample: &[f64], task: Task) -> f64 {
+        let linear = self
+            .weights
+            .iter()
+            .zip(sample)
+            .map(|(w, x)| w * x)
+            .sum::<f64>()
+            + self.bias;
+
+        match task {
+            Task::Regression => linear,
+            Task::Classification => 1.0 / (1.0 + (-linear).exp()),
+        }
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::{
+        ndarray::{self, Array1, Array2},
+        testing::{assert_close, Tolerance},
+    };
+
+    fn settings(task: Task) -> Settings {
+        Settings {
+            task,
+            learning_rate: 0.1,
+            epochs: 1,
+        }
+    }
+
+    #[test]
+    fn regression_converges_over_many_batches() {
+        let mut model = Model::default();
+        let x = ndarray::arr2(&[[0.0], [0.5], [1.0], [1.5], [2.0]]);
+        let y = x.column(0).mapv(|x| 2.0 * x + 1.0);
+
+        for _ in 0..500 {
+            model
+                .partial_fit(x.view(), y.view(), settings(Task::Regression))
+                .unwrap();
+        }
+
+        assert_close(model.weights[0], 2.0, Tolerance::absolute(1e-3));
+        assert_close(model.bias, 1.0, Tolerance::absolute(1e-3));
+        let got = model
+            .predict(ndarray::arr2(&[[3.0]]).view(), Task::Regression)
+            .unwrap();
+        assert_close(got[0], 7.0, Tolerance::absolute(1e-2));
+    }
+
+    #[test]
+    fn classify_separable_samples() {
+        let mut model = Model::default();
+        let x =
+            ndarray::arr2(&[[-2.0, 0.0], [-1.0, 1.0], [1.0, -1.0], [2.0, 0.0]]);
+        let y = ndarray::arr1(&[0.0, 0.0, 1.0, 1.0]);
+        let settings = Settings {
+            epochs: 50,
+            ..settings(Task::Classification)
+        };
+
+        model.partial_fit(x.view(), y.view(), settings).unwrap();
+
+        let got = model.predict(x.view(), Task::Classification).unwrap();
+        assert_eq!(got, y.to_vec());
+    }
+
+    #[test]
+    fn empty_batches_only_predict() {
+        let mut model = Model {
+            weights: vec![1.0, 2.0],
+            bias: 0.5,
+        };
+        let original = model.clone();
+
+        model
+            .partial_fit(
+                Array2::zeros((0, 2)).view(),
+                Array1::zeros(0).view(),
+                settings(Task::Regression),
+            )
+            .unwrap();
+
+        assert_eq!(model, original);
+        let got = model
+            .predict(ndarray::arr2(&[[1.0, 1.0]]).view(), Task::Regression)
+            .unwrap();
+        assert_eq!(got, [3.5]);
+    }
+
+    #[test]
+    fn changing_the_number_of_features_resets_the_model() {
+        let mut model = Model {
+            weights: vec![1.0, 2.0],
+            bias: 0.5,
+        };
+
+        model
+            .partial_fit(
+                ndarray::arr2(&[[0.0, 0.0, 0.0]]).view(),
+                ndarray::arr1(&[0.0]).view(),
+                settings(Task::Regression),
+            )
+            .unwrap();
+
+        assert_eq!(model.weights, [0.0, 0.0, 0.0]);
+        assert_eq!(model.bias, 0.0);
+    }
+
+    #[test]
+    fn classification_targets_must_be_binary() {
+        let mut model = Model::default();
+
+        let err = model
+            .partial_fit(
+                ndarray::arr2(&[[1.0]]).view(),
+                ndarray::arr1(&[2.0]).view(),
+                settings(Task::Classification),
+            )
+            .unwrap_err();
+
+        assert!(matches!(err, KernelError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn test_samples_need_the_same_features() {
+        let model = Model {
+            weights: vec![1.0],
+            bias: 0.0,
+        };
+
+        let got = model.predict(Array2::zeros((1, 2)).view(), Task::Regression);
+
+        assert!(got.is_err());
+    }
+}
@@ -0,0 +1,297 @@
+//! Pad or truncate a sequence of token IDs to a fixed length, generating an
+//! attention mask which says which tokens are real and which are padding.
+
+use std::{fmt::Display, str::FromStr};
+
+use hotg_rune_proc_blocks::{
+    common::element_type,
+    ndarray::Ix1,
+    runtime_v1::{self, *},
+    BufferExt, SliceExt, ValueType,
+};
+use num_traits::{One, Zero};
+use pad_sequence_core::{pad_sequence, Side};
+
+use crate::proc_block_v1::*;
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata = Metadata::new("Pad Sequence", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.set_homepage(env!("CARGO_PKG_HOMEPAGE"));
+        metadata.add_tag("nlp");
+        metadata.add_tag("tokenization");
+
+        let length = ArgumentMetadata::new("length");
+        length.set_description("The number of tokens to pad or truncate to.");
+        let hint =
+            runtime_v1::supported_argument_type(ArgumentType::UnsignedInteger);
+        length.add_hint(&hint);
+        metadata.add_argument(&length);
+
+        let padding_value = ArgumentMetadata::new("padding_value");
+        padding_value.set_description("The token ID used for padding.");
+        padding_value.set_default_value("0");
+        metadata.add_argument(&padding_value);
+
+        let padding = ArgumentMetadata::new("padding");
+        padding.set_description("Which end of the sequence to pad.");
+        padding.set_default_value("end");
+        let hint = runtime_v1::interpret_as_string_in_enum(&["start", "end"]);
+        padding.add_hint(&hint);
+        metadata.add_argument(&padding);
+
+        let truncation = ArgumentMetadata::new("truncation");
+        truncation.set_description(
+            "Which end of the sequence to remove tokens from when it is too long.",
+        );
+        truncation.set_default_value("end");
+        let hint = runtime_v1::interpret_as_string_in_enum(&["start", "end"]);
+        truncation.add_hint(&hint);
+        metadata.add_argument(&truncation);
+
+        let element_type = ArgumentMetadata::new("element_type");
+        element_type.set_description("The type used for token IDs.");
+        element_type.set_default_value("i32");
+        let hint =
+            runtime_v1::interpret_as_string_in_enum(element_type::INTEGER);
+        element_type.add_hint(&hint);
+        metadata.add_argument(&element_type);
+
+        let tokens = TensorMetadata::new("tokens");
+        tokens.set_description("A 1D tensor of token IDs.");
+        let hint =
+            supported_shapes(&INTEGER_TYPES, DimensionsParam::Fixed(&[0]));
+        tokens.add_hint(&hint);
+        metadata.add_input(&tokens);
+
+        let padded = TensorMetadata::new("padded");
+        padded.set_description("The token IDs, padded to the desired length.");
+        let hint =
+            supported_shapes(&INTEGER_TYPES, DimensionsParam::Fixed(&[0]));
+        padded.add_hint(&hint);
+        metadata.add_output(&padded);
+
+        let attention_mask = TensorMetadata::new("attention_mask");
+        attention_mask.set_description(
+            "`1` for each real token and `0` for each padding token.",
+        );
+        let hint =
+            supported_shapes(&INTEGER_TYPES, DimensionsParam::Fixed(&[0]));
+        attention_mask.add_hint(&hint);
+        metadata.add_output(&attention_mask);
+
+        register_node(&metadata);
+    }
+
+    fn graph(node_id: String) -> Result<(), GraphError> {
+        let ctx = GraphContext::for_node(&node_id)
+            .ok_or(GraphError::MissingContext)?;
+
+        let element_type = get_element_type(|n| ctx.get_argument(n))
+            .map_err(GraphError::InvalidArgument)?;
+        let settings = Settings::from_args(|n| ctx.get_argument(n))
+            .map_err(GraphError::InvalidArgument)?;
+        let length = settings.length as u32;
+
+        ctx.add_input_tensor(
+            "tokens",
+            element_type,
+            DimensionsParam::Fixed(&[0]),
+        );
+        ctx.add_output_tensor(
+            "padded",
+            element_type,
+            DimensionsParam::Fixed(&[length]),
+        );
+        ctx.add_output_tensor(
+            "attention_mask",
+            element_type,
+            DimensionsParam::Fixed(&[length]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(node_id: String) -> Result<(), KernelError> {
+        let ctx = KernelContext::for_node(&node_id)
+            .ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_args(|n| ctx.get_argument(n))
+            .map_err(KernelError::InvalidArgument)?;
+        let padding_value = ctx.get_argument("padding_value");
+        let padding_value = padding_value.as_deref().unwrap_or("0");
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("tokens").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "tokens".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        let tokens = (&buffer[..], &dimensions[..]);
+
+        let (padded, mask) = match element_type {
+            ElementType::U8 => pad::<u8>(tokens, padding_value, &settings)?,
+            ElementType::I8 => pad::<i8>(tokens, padding_value, &settings)?,
+            ElementType::U16 => pad::<u16>(tokens, padding_value, &settings)?,
+            ElementType::I16 => pad::<i16>(tokens, padding_value, &settings)?,
+            ElementType::U32 => pad::<u32>(tokens, padding_value, &settings)?,
+            ElementType::I32 => pad::<i32>(tokens, padding_value, &settings)?,
+            ElementType::U64 => pad::<u64>(tokens, padding_value, &settings)?,
+            ElementType::I64 => pad::<i64>(tokens, padding_value, &settings)?,
+            other => {
+                return Err(KernelError::InvalidInput(InvalidInput {
+                    name: "tokens".to_string(),
+                    reason: BadInputReason::InvalidValue(format!(
+                        "Token IDs must be integers, found {:?}",
+                        other
+                    )),
+                }))
+            },
+        };
+
+        let dimensions = [settings.length as u32];
+
+        ctx.set_output_tensor(
+            "padded",
+            TensorParam {
+                element_type,
+                dimensions: &dimensions,
+                buffer: &padded,
+            },
+        );
+        ctx.set_output_tensor(
+            "attention_mask",
+            TensorParam {
+                element_type,
+                dimensions: &dimensions,
+                buffer: &mask,
+            },
+        );
+
+        Ok(())
+    }
+}
+
+const INTEGER_TYPES: [ElementType; 8] = [
+    ElementType::U8,
+    ElementType::I8,
+    ElementType::U16,
+    ElementType::I16,
+    ElementType::U32,
+    ElementType::I32,
+    ElementType::U64,
+    ElementType::I64,
+];
+
+struct Settings {
+    length: usize,
+    padding: Side,
+    truncation: Side,
+}
+
+impl Settings {
+    fn from_args(
+        get_argument: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, InvalidArgument> {
+        let length = get_argument("length")
+            .ok_or_else(|| InvalidArgument::not_found("length"))?
+            .parse()
+            .map_err(|e| InvalidArgument::invalid_value("length", e))?;
+        let padding = parse_side("padding", get_argument("padding"))?;
+        let truncation = parse_side("truncation", get_argument("truncation"))?;
+
+        Ok(Settings {
+            length,
+            padding,
+            truncation,
+        })
+    }
+}
+
+fn parse_side(
+    name: &str,
+    value: Option<String>,
+) -> Result<Side, InvalidArgument> {
+    match value {
+        Some(v) => {
+            v.parse().map_err(|e| InvalidArgument::invalid_value(name, e))
+        },
+        None => Ok(Side::default()),
+    }
+}
+
+fn pad<T>(
+    (buffer, dimensions): (&[u8], &[u32]),
+    padding_value: &str,
+    settings: &Settings,
+) -> Result<(Vec<u8>, Vec<u8>), KernelError>
+where
+    T: ValueType + Copy + Zero + One + FromStr,
+    T::Err: Display,
+{
+    let padding_value: T = padding_value.parse().map_err(|e| {
+        KernelError::InvalidArgument(InvalidArgument::invalid_value(
+            "padding_value",
+            e,
+        ))
+    })?;
+
+    let tokens = buffer
+        .try_view::<T, Ix1>(dimensions)
+        .map_err(|e| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "tokens".to_string(),
+                reason: BadInputReason::InvalidValue(e.to_string()),
+            })
+        })?;
+    let tokens = tokens.to_vec();
+
+    let (padded, mask) = pad_sequence(
+        &tokens,
+        settings.length,
+        padding_value,
+        settings.padding,
+        settings.truncation,
+    );
+
+    Ok((padded.as_bytes().to_vec(), mask.as_bytes().to_vec()))
+}
+
+fn get_element_type(
+    get_argument: impl FnOnce(&str) -> Option<String>,
+) -> Result<ElementType, InvalidArgument> {
+    use element_type::{parse_element_type, INTEGER, NAME};
+
+    match get_argument(NAME) {
+        Some(name) => parse_element_type(&name, INTEGER)
+            .map_err(|e| InvalidArgument::invalid_value(NAME, e)),
+        None => Ok(ElementType::I32),
+    }
+}
+
+impl InvalidArgument {
+    fn not_found(name: impl Into<String>) -> Self {
+        InvalidArgument {
+            name: name.into(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+
+    fn invalid_value(name: impl Into<String>, reason: impl Display) -> Self {
+        InvalidArgument {
+            name: name.into(),
+            reason: BadArgumentReason::InvalidValue(reason.to_string()),
+        }
+    }
+}
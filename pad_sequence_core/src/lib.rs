@@ -0,0 +1,148 @@
+//! Pad or truncate a sequence of token IDs to a fixed length, generating an
+//! attention mask which says which tokens are real and which are padding.
+//!
+//! This is the algorithm behind the `pad_sequence` proc-block, without any of
+//! the WebAssembly bindings, so it can be used as a normal Rust library.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use num_traits::{One, Zero};
+
+/// Which end of a sequence to add or remove tokens from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Side {
+    Start,
+    End,
+}
+
+impl Default for Side {
+    fn default() -> Self { Side::End }
+}
+
+impl FromStr for Side {
+    type Err = UnknownSide;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "start" | "pre" => Ok(Side::Start),
+            "end" | "post" => Ok(Side::End),
+            other => Err(UnknownSide(other.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UnknownSide(pub String);
+
+impl Display for UnknownSide {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Expected \"start\" or \"end\", found \"{}\"", self.0)
+    }
+}
+
+impl std::error::Error for UnknownSide {}
+
+/// Pad or truncate `tokens` so it has exactly `length` elements, returning
+/// the new tokens and an attention mask (`1` for real tokens, `0` for
+/// padding).
+///
+/// # Examples
+///
+/// ```rust
+/// use pad_sequence_core::{pad_sequence, Side};
+///
+/// let (padded, mask) = pad_sequence(&[7, 8, 9], 5, 0, Side::End, Side::End);
+/// assert_eq!(padded, [7, 8, 9, 0, 0]);
+/// assert_eq!(mask, [1, 1, 1, 0, 0]);
+///
+/// let (truncated, mask) =
+///     pad_sequence(&[7, 8, 9], 2, 0, Side::End, Side::Start);
+/// assert_eq!(truncated, [8, 9]);
+/// assert_eq!(mask, [1, 1]);
+/// ```
+pub fn pad_sequence<T>(
+    tokens: &[T],
+    length: usize,
+    padding_value: T,
+    padding: Side,
+    truncation: Side,
+) -> (Vec<T>, Vec<T>)
+where
+    T: Copy + Zero + One,
+{
+    let tokens = if tokens.len() > length {
+        match truncation {
+            Side::Start => &tokens[tokens.len() - length..],
+            Side::End => &tokens[..length],
+        }
+    } else {
+        tokens
+    };
+
+    let padding_needed = length - tokens.len();
+    let pad = std::iter::repeat(padding_value).take(padding_needed);
+    let real = std::iter::repeat(T::one()).take(tokens.len());
+    let masked = std::iter::repeat(T::zero()).take(padding_needed);
+
+    match padding {
+        Side::Start => (
+            pad.chain(tokens.iter().copied()).collect(),
+            masked.chain(real).collect(),
+        ),
+        Side::End => (
+            tokens.iter().copied().chain(pad).collect(),
+            real.chain(masked).collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pad_at_the_start() {
+        let (padded, mask) =
+            pad_sequence(&[1_i32, 2], 4, -1, Side::Start, Side::End);
+
+        assert_eq!(padded, [-1, -1, 1, 2]);
+        assert_eq!(mask, [0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn truncate_from_the_end() {
+        let (padded, mask) =
+            pad_sequence(&[1_u32, 2, 3, 4], 3, 0, Side::End, Side::End);
+
+        assert_eq!(padded, [1, 2, 3]);
+        assert_eq!(mask, [1, 1, 1]);
+    }
+
+    #[test]
+    fn sequences_of_the_right_length_are_untouched() {
+        let (padded, mask) =
+            pad_sequence(&[5_i64, 6], 2, 0, Side::Start, Side::Start);
+
+        assert_eq!(padded, [5, 6]);
+        assert_eq!(mask, [1, 1]);
+    }
+
+    #[test]
+    fn empty_sequence_is_all_padding() {
+        let (padded, mask) =
+            pad_sequence::<u8>(&[], 3, 0, Side::End, Side::End);
+
+        assert_eq!(padded, [0, 0, 0]);
+        assert_eq!(mask, [0, 0, 0]);
+    }
+
+    #[test]
+    fn parse_sides() {
+        assert_eq!("start".parse(), Ok(Side::Start));
+        assert_eq!("post".parse(), Ok(Side::End));
+        assert!("middle".parse::<Side>().is_err());
+    }
+}
@@ -22,6 +22,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         metadata.set_homepage(env!("CARGO_PKG_HOMEPAGE"));
         metadata.add_tag("string");
         metadata.add_tag("numbers");
+        metadata.with_const_eval();
 
         let input = TensorMetadata::new("input_string_of_numbers");
         let hint =
@@ -68,32 +69,8 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             DimensionsParam::Dynamic,
         );
 
-        let element_type = match ctx.get_argument("element_type").as_deref() {
-            Some("u8") => ElementType::U8,
-            Some("i8") => ElementType::I8,
-            Some("u16") => ElementType::U16,
-            Some("i16") => ElementType::I16,
-            Some("u32") => ElementType::U32,
-            Some("i32") => ElementType::I32,
-            Some("f32") => ElementType::F32,
-            Some("u64") => ElementType::U64,
-            Some("i64") => ElementType::I64,
-            Some("f64") => ElementType::F64,
-            Some(_) => {
-                return Err(GraphError::InvalidArgument(InvalidArgument {
-                    name: "element_type".to_string(),
-                    reason: BadArgumentReason::InvalidValue(
-                        "Unsupported element type".to_string(),
-                    ),
-                }));
-            },
-            None => {
-                return Err(GraphError::InvalidArgument(InvalidArgument {
-                    name: "element_type".to_string(),
-                    reason: BadArgumentReason::NotFound,
-                }))
-            },
-        };
+        let element_type = get_element_type(|n| ctx.get_argument(n))
+            .map_err(GraphError::InvalidArgument)?;
 
         ctx.add_output_tensor(
             "parsed_numbers",
@@ -132,8 +109,11 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             },
         };
 
-        match ctx.get_argument("element_type").as_deref() {
-            Some("u8") => {
+        let output_type = get_element_type(|n| ctx.get_argument(n))
+            .map_err(KernelError::InvalidArgument)?;
+
+        match output_type {
+            ElementType::U8 => {
                 let transformed = transform::<u8>(&numbers)?;
                 let output = TensorParam {
                     element_type: ElementType::U8,
@@ -142,7 +122,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
                 };
                 ctx.set_output_tensor("parsed_numbers", output);
             },
-            Some("i8") => {
+            ElementType::I8 => {
                 let transformed = transform::<i8>(&numbers)?;
                 let output = TensorParam {
                     element_type: ElementType::I8,
@@ -151,7 +131,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
                 };
                 ctx.set_output_tensor("parsed_numbers", output);
             },
-            Some("u16") => {
+            ElementType::U16 => {
                 let transformed = transform::<u16>(&numbers)?;
                 let output = TensorParam {
                     element_type: ElementType::U16,
@@ -160,7 +140,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
                 };
                 ctx.set_output_tensor("parsed_numbers", output);
             },
-            Some("i16") => {
+            ElementType::I16 => {
                 let transformed = transform::<i16>(&numbers)?;
                 let output = TensorParam {
                     element_type: ElementType::I16,
@@ -169,7 +149,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
                 };
                 ctx.set_output_tensor("parsed_numbers", output);
             },
-            Some("u32") => {
+            ElementType::U32 => {
                 let transformed = transform::<u32>(&numbers)?;
                 let output = TensorParam {
                     element_type: ElementType::U32,
@@ -178,7 +158,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
                 };
                 ctx.set_output_tensor("parsed_numbers", output);
             },
-            Some("i32") => {
+            ElementType::I32 => {
                 let transformed = transform::<i32>(&numbers)?;
                 let output = TensorParam {
                     element_type: ElementType::I32,
@@ -187,7 +167,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
                 };
                 ctx.set_output_tensor("parsed_numbers", output);
             },
-            Some("f32") => {
+            ElementType::F32 => {
                 let transformed = transform::<f32>(&numbers)?;
                 let output = TensorParam {
                     element_type: ElementType::F32,
@@ -196,7 +176,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
                 };
                 ctx.set_output_tensor("parsed_numbers", output);
             },
-            Some("u64") => {
+            ElementType::U64 => {
                 let transformed = transform::<u64>(&numbers)?;
                 let output = TensorParam {
                     element_type: ElementType::U64,
@@ -205,7 +185,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
                 };
                 ctx.set_output_tensor("parsed_numbers", output);
             },
-            Some("i64") => {
+            ElementType::I64 => {
                 let transformed = transform::<i64>(&numbers)?;
                 let output = TensorParam {
                     element_type: ElementType::I64,
@@ -214,7 +194,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
                 };
                 ctx.set_output_tensor("parsed_numbers", output);
             },
-            Some("f64") => {
+            ElementType::F64 => {
                 let transformed = transform::<f64>(&numbers)?;
                 let output = TensorParam {
                     element_type: ElementType::F64,
@@ -223,19 +203,8 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
                 };
                 ctx.set_output_tensor("parsed_numbers", output);
             },
-            Some(_) => {
-                return Err(KernelError::InvalidArgument(InvalidArgument {
-                    name: "element_type".to_string(),
-                    reason: BadArgumentReason::InvalidValue(
-                        "Unsupported element type".to_string(),
-                    ),
-                }));
-            },
-            None => {
-                return Err(KernelError::InvalidArgument(InvalidArgument {
-                    name: "element_type".to_string(),
-                    reason: BadArgumentReason::NotFound,
-                }));
+            ElementType::Utf8 => {
+                unreachable!("Already checked by get_element_type()")
             },
         }
 
@@ -243,6 +212,22 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
     }
 }
 
+fn get_element_type(
+    get_argument: impl FnOnce(&str) -> Option<String>,
+) -> Result<ElementType, InvalidArgument> {
+    use common::element_type::{parse_element_type, NAME, NUMERIC};
+
+    let name = get_argument(NAME).ok_or_else(|| InvalidArgument {
+        name: NAME.to_string(),
+        reason: BadArgumentReason::NotFound,
+    })?;
+
+    parse_element_type(&name, NUMERIC).map_err(|e| InvalidArgument {
+        name: NAME.to_string(),
+        reason: BadArgumentReason::InvalidValue(e.to_string()),
+    })
+}
+
 fn transform<T>(inputs: &[&str]) -> Result<Vec<T>, KernelError>
 where
     T: FromStr,
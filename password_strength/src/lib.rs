@@ -22,7 +22,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         metadata.add_tag("bytes");
         metadata.add_tag("string");
 
-        let input = TensorMetadata::new("bytes");
+        let input = TensorMetadata::new("input");
         input.set_description("string");
         let hint =
             supported_shapes(&[ElementType::Utf8], DimensionsParam::Dynamic);
@@ -44,7 +44,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             .ok_or(GraphError::MissingContext)?;
 
         ctx.add_input_tensor(
-            "string",
+            "input",
             ElementType::Utf8,
             DimensionsParam::Dynamic,
         );
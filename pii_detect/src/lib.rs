@@ -0,0 +1,665 @@
+//! Find personally identifiable information (PII) in text so it can be
+//! scrubbed before it leaves the device.
+//!
+//! Detection uses simple built-in patterns rather than a language model:
+//!
+//! - **Email addresses** - a local part, an `@`, and a domain with at least
+//!   two labels (e.g. `jane.doe@example.com`)
+//! - **Credit card numbers** - 13 to 19 digits, optionally grouped with
+//!   spaces or dashes, which pass the Luhn checksum
+//! - **Phone numbers** - 7 to 15 digits, optionally starting with a `+` and
+//!   separated by spaces, dashes, dots or parentheses
+//!
+//! These patterns deliberately err on the side of redacting too much (e.g. a
+//! date like `2022-10-14` looks like a phone number).
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    mask, prelude::*, runtime_v1::*, BufferExt, StringBuilder,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata = Metadata::new("PII Detect", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("text");
+        metadata.add_tag("privacy");
+        metadata.add_tag("nlp");
+        metadata.with_const_eval();
+
+        let detect = ArgumentMetadata::new("detect");
+        detect.set_description(
+            "A comma-separated list of the kinds of PII to look for.",
+        );
+        detect.set_default_value(&PiiKind::NAMES.join(","));
+        detect.add_hint(&supported_argument_type(ArgumentType::String));
+        metadata.add_argument(&detect);
+
+        let replacement = ArgumentMetadata::new("replacement");
+        replacement.set_description(
+            "The text each match is replaced with. When this isn't provided, matches are replaced with a placeholder for their kind (`[EMAIL]`, `[PHONE]` or `[CREDIT_CARD]`).",
+        );
+        replacement.add_hint(&supported_argument_type(ArgumentType::String));
+        metadata.add_argument(&replacement);
+
+        let input = TensorMetadata::new("text");
+        input.set_description("The strings to scan.");
+        let hint =
+            supported_shapes(&[ElementType::Utf8], DimensionsParam::Dynamic);
+        input.add_hint(&hint);
+        metadata.add_input(&input);
+
+        let mask = TensorMetadata::new("mask");
+        mask.set_description(
+            "A mask (`1` or `0`) saying whether each string contained any PII, with the same shape as the input.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::U8], DimensionsParam::Dynamic);
+        mask.add_hint(&hint);
+        metadata.add_output(&mask);
+
+        let redacted = TensorMetadata::new("redacted");
+        redacted.set_description(
+            "The strings with any PII replaced, with the same shape as the input.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::Utf8], DimensionsParam::Dynamic);
+        redacted.add_hint(&hint);
+        metadata.add_output(&redacted);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "text",
+            ElementType::Utf8,
+            DimensionsParam::Dynamic,
+        );
+        ctx.add_output_tensor(
+            "mask",
+            ElementType::U8,
+            DimensionsParam::Dynamic,
+        );
+        ctx.add_output_tensor(
+            "redacted",
+            ElementType::Utf8,
+            DimensionsParam::Dynamic,
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let Settings { kinds, replacement } =
+            Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("text").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "text".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::Utf8 {
+            return Err(invalid_input(format!(
+                "Expected a utf8 tensor, found {}",
+                element_type
+            )));
+        }
+
+        let strings = buffer.strings().map_err(invalid_input)?;
+
+        let mut found = Vec::with_capacity(strings.len());
+        let mut builder = StringBuilder::new();
+
+        for s in strings {
+            let matches = find_pii(s, &kinds);
+            found.push(!matches.is_empty());
+            builder.push(&redact(s, &matches, replacement.as_deref()));
+        }
+
+        ctx.set_output_tensor(
+            "mask",
+            TensorParam {
+                element_type: ElementType::U8,
+                dimensions: &dimensions,
+                buffer: &mask::from_bools(found),
+            },
+        );
+        ctx.set_output_tensor(
+            "redacted",
+            TensorParam {
+                element_type: ElementType::Utf8,
+                dimensions: &dimensions,
+                buffer: &builder.finish(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "text".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PiiKind {
+    Email,
+    Phone,
+    CreditCard,
+}
+
+impl PiiKind {
+    pub const NAMES: &'static [&'static str] =
+        &["email", "phone", "credit_card"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "email" => Some(PiiKind::Email),
+            "phone" => Some(PiiKind::Phone),
+            "credit_card" => Some(PiiKind::CreditCard),
+            _ => None,
+        }
+    }
+
+    /// The text used to replace this kind of PII by default.
+    pub fn placeholder(self) -> &'static str {
+        match self {
+            PiiKind::Email => "[EMAIL]",
+            PiiKind::Phone => "[PHONE]",
+            PiiKind::CreditCard => "[CREDIT_CARD]",
+        }
+    }
+
+    /// When two matches overlap, the one with the higher priority (lower
+    /// number) wins. The more specific patterns go first so a phone number
+    /// doesn't steal part of an email address or credit card number.
+    fn priority(self) -> usize {
+        match self {
+            PiiKind::Email => 0,
+            PiiKind::CreditCard => 1,
+            PiiKind::Phone => 2,
+        }
+    }
+
+    fn find(self, text: &str) -> Vec<Match> {
+        let spans = match self {
+            PiiKind::Email => find_emails(text),
+            PiiKind::Phone => find_phone_numbers(text),
+            PiiKind::CreditCard => find_credit_cards(text),
+        };
+
+        spans
+            .into_iter()
+            .map(|(start, end)| Match {
+                kind: self,
+                start,
+                end,
+            })
+            .collect()
+    }
+}
+
+/// A piece of PII, where `start..end` are byte offsets into the text.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub kind: PiiKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Match {
+    fn overlaps(&self, other: &Match) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+}
+
+/// Find every piece of PII in some text, sorted by where they start.
+///
+/// Matches never overlap.
+pub fn find_pii(text: &str, kinds: &[PiiKind]) -> Vec<Match> {
+    let mut kinds = kinds.to_vec();
+    kinds.sort_by_key(|kind| kind.priority());
+    kinds.dedup();
+
+    let mut matches: Vec<Match> = Vec::new();
+
+    for kind in kinds {
+        for candidate in kind.find(text) {
+            if !matches.iter().any(|m| m.overlaps(&candidate)) {
+                matches.push(candidate);
+            }
+        }
+    }
+
+    matches.sort_by_key(|m| m.start);
+    matches
+}
+
+/// Replace each match with the `replacement`, or the [`PiiKind`]'s
+/// placeholder when no replacement is given.
+///
+/// The matches must be sorted and non-overlapping, as returned by
+/// [`find_pii()`].
+pub fn redact(
+    text: &str,
+    matches: &[Match],
+    replacement: Option<&str>,
+) -> String {
+    let mut redacted = String::with_capacity(text.len());
+    let mut last = 0;
+
+    for m in matches {
+        redacted.push_str(&text[last..m.start]);
+        redacted.push_str(replacement.unwrap_or_else(|| m.kind.placeholder()));
+        last = m.end;
+    }
+
+    redacted.push_str(&text[last..]);
+    redacted
+}
+
+/// Is this byte next to a match part of the same word?
+///
+/// Matches always start and end next to an ASCII byte (or the ends of the
+/// text), so the offsets we return always land on a `char` boundary.
+fn is_word_byte(b: u8) -> bool { b.is_ascii_alphanumeric() || b == b'_' }
+
+fn at_word_boundary(bytes: &[u8], start: usize, end: usize) -> bool {
+    let before = start == 0 || !is_word_byte(bytes[start - 1]);
+    let after = end == bytes.len() || !is_word_byte(bytes[end]);
+    before && after
+}
+
+fn find_emails(text: &str) -> Vec<(usize, usize)> {
+    let bytes = text.as_bytes();
+    // Non-ASCII bytes are allowed so internationalized addresses work
+    let is_local = |b: u8| {
+        b.is_ascii_alphanumeric() || b"._%+-".contains(&b) || !b.is_ascii()
+    };
+    let is_domain = |b: u8| b.is_ascii_alphanumeric() || b == b'.' || b == b'-';
+
+    let mut spans = Vec::new();
+
+    for (at, _) in text.match_indices('@') {
+        let mut start = at;
+        while start > 0 && is_local(bytes[start - 1]) {
+            start -= 1;
+        }
+        while start < at && bytes[start] == b'.' {
+            start += 1;
+        }
+
+        let mut end = at + 1;
+        while end < bytes.len() && is_domain(bytes[end]) {
+            end += 1;
+        }
+        // Don't include the full stop at the end of a sentence
+        while end > at + 1 && matches!(bytes[end - 1], b'.' | b'-') {
+            end -= 1;
+        }
+
+        if start < at && is_valid_domain(&text[at + 1..end]) {
+            spans.push((start, end));
+        }
+    }
+
+    spans
+}
+
+fn is_valid_domain(domain: &str) -> bool {
+    let labels: Vec<&str> = domain.split('.').collect();
+
+    match labels.last() {
+        Some(tld) if labels.len() >= 2 => {
+            labels.iter().all(|label| !label.is_empty())
+                && tld.len() >= 2
+                && tld.bytes().all(|b| b.is_ascii_alphabetic())
+        },
+        _ => false,
+    }
+}
+
+fn find_credit_cards(text: &str) -> Vec<(usize, usize)> {
+    let bytes = text.as_bytes();
+    let mut spans = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_digit() || (i > 0 && is_word_byte(bytes[i - 1]))
+        {
+            i += 1;
+            continue;
+        }
+
+        // Consume digits, allowing single spaces or dashes between groups
+        let mut digits = Vec::new();
+        let mut end = i;
+        while end < bytes.len() {
+            if bytes[end].is_ascii_digit() {
+                digits.push(bytes[end] - b'0');
+                end += 1;
+            } else if matches!(bytes[end], b' ' | b'-')
+                && matches!(bytes.get(end + 1), Some(b) if b.is_ascii_digit())
+            {
+                end += 1;
+            } else {
+                break;
+            }
+        }
+
+        if (13..=19).contains(&digits.len())
+            && at_word_boundary(bytes, i, end)
+            && luhn_checksum_is_valid(&digits)
+        {
+            spans.push((i, end));
+        }
+
+        i = end;
+    }
+
+    spans
+}
+
+/// Check a number using the [Luhn algorithm][luhn], which every credit card
+/// number satisfies.
+///
+/// [luhn]: https://en.wikipedia.org/wiki/Luhn_algorithm
+fn luhn_checksum_is_valid(digits: &[u8]) -> bool {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &digit)| {
+            let digit = u32::from(digit);
+            if i % 2 == 1 {
+                let doubled = digit * 2;
+                if doubled > 9 {
+                    doubled - 9
+                } else {
+                    doubled
+                }
+            } else {
+                digit
+            }
+        })
+        .sum();
+
+    sum % 10 == 0
+}
+
+fn find_phone_numbers(text: &str) -> Vec<(usize, usize)> {
+    let bytes = text.as_bytes();
+    let is_separator = |b: u8| matches!(b, b' ' | b'-' | b'.' | b'(' | b')');
+
+    let mut spans = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        let can_start = b == b'+' || b == b'(' || b.is_ascii_digit();
+        if !can_start || (i > 0 && is_word_byte(bytes[i - 1])) {
+            i += 1;
+            continue;
+        }
+
+        let mut digits = 0;
+        // Where the last digit we've seen ends
+        let mut end = i;
+        // How many separators in a row we've seen, so "(555) 123" is fine
+        // but "1  .  2" isn't
+        let mut separators = 0;
+        let mut j = if b == b'+' { i + 1 } else { i };
+
+        while j < bytes.len() {
+            if bytes[j].is_ascii_digit() {
+                digits += 1;
+                separators = 0;
+                j += 1;
+                end = j;
+            } else if is_separator(bytes[j]) && separators < 2 {
+                separators += 1;
+                j += 1;
+            } else {
+                break;
+            }
+        }
+
+        if (7..=15).contains(&digits) && at_word_boundary(bytes, i, end) {
+            spans.push((i, end));
+        }
+
+        i = end.max(i + 1);
+    }
+
+    spans
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Settings {
+    kinds: Vec<PiiKind>,
+    replacement: Option<String>,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let detect = ctx.parse_argument_with_default::<_, E>(
+            "detect",
+            PiiKind::NAMES.join(","),
+        )?;
+        let kinds = parse_kinds(&detect).map_err(|e| {
+            E::invalid_argument(InvalidArgument::invalid_value("detect", e))
+        })?;
+        let replacement = ctx._get_argument("replacement");
+
+        Ok(Settings { kinds, replacement })
+    }
+}
+
+fn parse_kinds(s: &str) -> Result<Vec<PiiKind>, String> {
+    let kinds = s
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            PiiKind::from_name(name).ok_or_else(|| {
+                format!(
+                    "Unknown kind of PII, \"{}\". Expected one of {:?}",
+                    name,
+                    PiiKind::NAMES
+                )
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if kinds.is_empty() {
+        return Err("At least one kind of PII must be detected".to_string());
+    }
+
+    Ok(kinds)
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: &[PiiKind] =
+        &[PiiKind::Email, PiiKind::Phone, PiiKind::CreditCard];
+
+    fn redact_all(text: &str) -> String {
+        redact(text, &find_pii(text, ALL), None)
+    }
+
+    #[test]
+    fn emails() {
+        let inputs = [
+            ("mail jane.doe@example.com now", "mail [EMAIL] now"),
+            ("<bob+spam@mail.example.co.uk>", "<[EMAIL]>"),
+            ("Contact a_b@test.io.", "Contact [EMAIL]."),
+            ("not an email: @example.com", "not an email: @example.com"),
+            ("user@localhost", "user@localhost"),
+            ("twitter @handle", "twitter @handle"),
+        ];
+
+        for (text, expected) in inputs {
+            assert_eq!(redact_all(text), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn phone_numbers() {
+        let inputs = [
+            ("Call +1 (555) 123-4567!", "Call [PHONE]!"),
+            ("ring 555.123.4567 today", "ring [PHONE] today"),
+            ("+44 20 7946 0958", "[PHONE]"),
+            ("room 101", "room 101"),
+            ("order ABC1234567", "order ABC1234567"),
+        ];
+
+        for (text, expected) in inputs {
+            assert_eq!(redact_all(text), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn credit_cards_must_pass_the_luhn_check() {
+        let inputs = [
+            ("card: 4111 1111 1111 1111", "card: [CREDIT_CARD]"),
+            ("5500-0000-0000-0004 exp 12/25", "[CREDIT_CARD] exp 12/25"),
+            ("378282246310005", "[CREDIT_CARD]"),
+            // Off by one so it fails the checksum, and too long for a phone
+            // number
+            ("4111 1111 1111 1112", "4111 1111 1111 1112"),
+        ];
+
+        for (text, expected) in inputs {
+            assert_eq!(redact_all(text), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn only_look_for_the_requested_kinds() {
+        let text = "jane@example.com or 555-123-4567";
+
+        let matches = find_pii(text, &[PiiKind::Phone]);
+
+        assert_eq!(
+            redact(text, &matches, None),
+            "jane@example.com or [PHONE]"
+        );
+    }
+
+    #[test]
+    fn emails_take_priority_over_phone_numbers() {
+        let text = "5551234567@example.com";
+
+        let matches = find_pii(text, ALL);
+
+        assert_eq!(
+            matches,
+            [Match {
+                kind: PiiKind::Email,
+                start: 0,
+                end: text.len(),
+            }]
+        );
+    }
+
+    #[test]
+    fn custom_replacement() {
+        let text = "jane@example.com, 555-123-4567";
+        let matches = find_pii(text, ALL);
+
+        let got = redact(text, &matches, Some("***"));
+
+        assert_eq!(got, "***, ***");
+    }
+
+    #[test]
+    fn non_ascii_text_is_left_alone() {
+        let text = "Écrivez à élodie@exemple.fr ou appelez le 01 23 45 67 89 ☎";
+
+        let got = redact_all(text);
+
+        assert_eq!(got, "Écrivez à [EMAIL] ou appelez le [PHONE] ☎");
+    }
+
+    #[test]
+    fn parse_the_kinds_to_detect() {
+        assert_eq!(
+            parse_kinds("email, credit_card").unwrap(),
+            [PiiKind::Email, PiiKind::CreditCard]
+        );
+        assert!(parse_kinds("email,ssn").is_err());
+        assert!(parse_kinds(" , ").is_err());
+    }
+}
@@ -0,0 +1,573 @@
+//! Estimate someone's heart rate from a photoplethysmography (PPG) signal,
+//! like the ones produced by the optical sensors in smart watches.
+//!
+//! Incoming samples are appended to a sliding window which is band-passed to
+//! remove baseline drift and high frequency noise, then the heart rate is
+//! calculated from the average time between peaks. The confidence is based on
+//! how regular those peaks are, so motion artifacts and a poorly fitting
+//! sensor give low confidence values.
+//!
+//! Only the sliding window is kept between invocations, so each estimate
+//! covers more than just the latest batch of samples.
+
+use std::{
+    collections::VecDeque,
+    f32::consts::{FRAC_1_SQRT_2, PI},
+};
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    prelude::*, runtime_v1::*, state::NodeState, BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// Each node's sliding window.
+static WINDOWS: NodeState<Window> = NodeState::new();
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("PPG Heart Rate", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("sensor");
+        metadata.add_tag("health");
+        metadata.add_tag("stateful");
+
+        let sample_rate = ArgumentMetadata::new("sample_rate");
+        sample_rate.set_description("The PPG sensor's sample rate, in Hz.");
+        sample_rate.set_default_value("25");
+        sample_rate.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&sample_rate);
+
+        let low_cutoff = ArgumentMetadata::new("low_cutoff");
+        low_cutoff.set_description(
+            "The band-pass filter's lower cutoff frequency, in Hz. This corresponds to the slowest heart rate that can be detected.",
+        );
+        low_cutoff.set_default_value("0.5");
+        low_cutoff.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&low_cutoff);
+
+        let high_cutoff = ArgumentMetadata::new("high_cutoff");
+        high_cutoff.set_description(
+            "The band-pass filter's upper cutoff frequency, in Hz. This corresponds to the fastest heart rate that can be detected.",
+        );
+        high_cutoff.set_default_value("4.0");
+        high_cutoff.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&high_cutoff);
+
+        let window = ArgumentMetadata::new("window");
+        window.set_description(
+            "How many seconds of samples to use when estimating the heart rate.",
+        );
+        window.set_default_value("8.0");
+        window.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&window);
+
+        let ppg = TensorMetadata::new("ppg");
+        ppg.set_description("The latest samples from the PPG sensor.");
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Fixed(&[0]));
+        ppg.add_hint(&hint);
+        metadata.add_input(&ppg);
+
+        let bpm = TensorMetadata::new("bpm");
+        bpm.set_description(
+            "The estimated heart rate in beats per minute, or 0 if it couldn't be determined.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Fixed(&[1]));
+        bpm.add_hint(&hint);
+        metadata.add_output(&bpm);
+
+        let confidence = TensorMetadata::new("confidence");
+        confidence.set_description(
+            "How confident we are in the estimate, from 0 (no idea) to 1 (perfectly regular beats).",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Fixed(&[1]));
+        confidence.add_hint(&hint);
+        metadata.add_output(&confidence);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "ppg",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0]),
+        );
+        ctx.add_output_tensor(
+            "bpm",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[1]),
+        );
+        ctx.add_output_tensor(
+            "confidence",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[1]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            buffer,
+            ..
+        } = ctx.get_input_tensor("ppg").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "ppg".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::F32 {
+            return Err(KernelError::InvalidInput(InvalidInput {
+                name: "ppg".to_string(),
+                reason: BadInputReason::InvalidValue(format!(
+                    "Expected a f32 tensor, found {}",
+                    element_type
+                )),
+            }));
+        }
+
+        let samples = buffer.try_elements::<f32>().map_err(|e| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "ppg".to_string(),
+                reason: BadInputReason::InvalidValue(e.to_string()),
+            })
+        })?;
+
+        let estimate = WINDOWS.with(&id, |window| {
+            window.extend(samples, settings.window_length());
+            window.estimate(settings)
+        });
+
+        ctx.set_output_tensor(
+            "bpm",
+            TensorParam {
+                element_type: ElementType::F32,
+                dimensions: &[1],
+                buffer: [estimate.bpm].as_bytes(),
+            },
+        );
+        ctx.set_output_tensor(
+            "confidence",
+            TensorParam {
+                element_type: ElementType::F32,
+                dimensions: &[1],
+                buffer: [estimate.confidence].as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    sample_rate: f32,
+    low_cutoff: f32,
+    high_cutoff: f32,
+    /// The window length, in seconds.
+    window: f32,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let sample_rate: f32 =
+            ctx.parse_argument_with_default::<_, E>("sample_rate", 25.0)?;
+        let low_cutoff: f32 =
+            ctx.parse_argument_with_default::<_, E>("low_cutoff", 0.5)?;
+        let high_cutoff: f32 =
+            ctx.parse_argument_with_default::<_, E>("high_cutoff", 4.0)?;
+        let window: f32 =
+            ctx.parse_argument_with_default::<_, E>("window", 8.0)?;
+
+        let invalid = |name: &str, reason: &str| {
+            E::invalid_argument(InvalidArgument::invalid_value(name, reason))
+        };
+
+        if !(sample_rate > 0.0 && sample_rate.is_finite()) {
+            return Err(invalid(
+                "sample_rate",
+                "The sample rate must be a positive number",
+            ));
+        }
+        if !(low_cutoff > 0.0 && low_cutoff < high_cutoff) {
+            return Err(invalid(
+                "low_cutoff",
+                "The low cutoff must be positive and less than the high cutoff",
+            ));
+        }
+        if high_cutoff >= sample_rate / 2.0 {
+            return Err(invalid(
+                "high_cutoff",
+                "The high cutoff must be less than half the sample rate",
+            ));
+        }
+        if !(window > 0.0 && window.is_finite()) {
+            return Err(invalid(
+                "window",
+                "The window must be a positive number of seconds",
+            ));
+        }
+
+        Ok(Settings {
+            sample_rate,
+            low_cutoff,
+            high_cutoff,
+            window,
+        })
+    }
+
+    /// The number of samples in the sliding window.
+    fn window_length(&self) -> usize {
+        (self.window * self.sample_rate).round() as usize
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Estimate {
+    bpm: f32,
+    confidence: f32,
+}
+
+impl Estimate {
+    const UNKNOWN: Estimate = Estimate {
+        bpm: 0.0,
+        confidence: 0.0,
+    };
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Window {
+    samples: VecDeque<f32>,
+}
+
+impl Window {
+    /// Add new samples to the window, dropping the oldest ones so there are at
+    /// most `length` samples.
+    fn extend(&mut self, samples: &[f32], length: usize) {
+        self.samples.extend(samples);
+
+        let excess = self.samples.len().saturating_sub(length);
+        self.samples.drain(..excess);
+    }
+
+    fn estimate(&self, settings: Settings) -> Estimate {
+        let samples: Vec<f32> = self.samples.iter().copied().collect();
+        let filtered = band_pass(&samples, settings);
+
+        // Beats can't be closer together than the fastest heart rate we let
+        // through the filter
+        let min_distance =
+            (settings.sample_rate / settings.high_cutoff).floor() as usize;
+        let peaks = find_peaks(&filtered, min_distance);
+
+        let intervals: Vec<f32> =
+            peaks.windows(2).map(|w| (w[1] - w[0]) as f32).collect();
+
+        // We need at least a couple of beats before we can say anything
+        // about how regular they are
+        if intervals.len() < 2 {
+            return Estimate::UNKNOWN;
+        }
+
+        let count = intervals.len() as f32;
+        let mean = intervals.iter().sum::<f32>() / count;
+        let variance =
+            intervals.iter().map(|i| (i - mean).powi(2)).sum::<f32>() / count;
+        let coefficient_of_variation = variance.sqrt() / mean;
+
+        Estimate {
+            bpm: 60.0 * settings.sample_rate / mean,
+            confidence: (1.0 - coefficient_of_variation).clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Apply a zero-phase band-pass filter by running a pair of 2nd order
+/// Butterworth filters forwards and then backwards over the signal.
+fn band_pass(samples: &[f32], settings: Settings) -> Vec<f32> {
+    let Settings {
+        sample_rate,
+        low_cutoff,
+        high_cutoff,
+        ..
+    } = settings;
+
+    let filters = [
+        Biquad::high_pass(low_cutoff, sample_rate),
+        Biquad::low_pass(high_cutoff, sample_rate),
+    ];
+
+    // Remove the DC offset first so the filters don't start with a huge step
+    let mean = samples.iter().sum::<f32>() / samples.len().max(1) as f32;
+    let mut signal: Vec<f32> = samples.iter().map(|s| s - mean).collect();
+
+    for _ in 0..2 {
+        for filter in &filters {
+            filter.apply(&mut signal);
+        }
+        signal.reverse();
+    }
+
+    signal
+}
+
+/// Find the indices of local maxima above zero which are at least
+/// `min_distance` samples apart.
+///
+/// When two peaks are too close together, the larger one is kept.
+fn find_peaks(signal: &[f32], min_distance: usize) -> Vec<usize> {
+    let mut peaks: Vec<usize> = Vec::new();
+
+    for (i, window) in signal.windows(3).enumerate() {
+        let (previous, value, next) = (window[0], window[1], window[2]);
+        let is_peak = value > 0.0 && value > previous && value >= next;
+
+        if !is_peak {
+            continue;
+        }
+
+        // The window starts one sample before the one we're looking at
+        let i = i + 1;
+
+        match peaks.last_mut() {
+            Some(last) if i - *last < min_distance => {
+                if value > signal[*last] {
+                    *last = i;
+                }
+            },
+            _ => peaks.push(i),
+        }
+    }
+
+    peaks
+}
+
+/// A 2nd order IIR filter, using the coefficients from the
+/// [Audio EQ Cookbook][cookbook].
+///
+/// [cookbook]: https://www.w3.org/TR/audio-eq-cookbook/
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Biquad {
+    b: [f32; 3],
+    a: [f32; 2],
+}
+
+impl Biquad {
+    fn low_pass(cutoff: f32, sample_rate: f32) -> Self {
+        let (cos, alpha) = Biquad::intermediates(cutoff, sample_rate);
+        let b1 = 1.0 - cos;
+        Biquad::normalized([b1 / 2.0, b1, b1 / 2.0], cos, alpha)
+    }
+
+    fn high_pass(cutoff: f32, sample_rate: f32) -> Self {
+        let (cos, alpha) = Biquad::intermediates(cutoff, sample_rate);
+        let b0 = (1.0 + cos) / 2.0;
+        Biquad::normalized([b0, -(1.0 + cos), b0], cos, alpha)
+    }
+
+    fn intermediates(cutoff: f32, sample_rate: f32) -> (f32, f32) {
+        let w0 = 2.0 * PI * cutoff / sample_rate;
+        // A Q of 1/sqrt(2) gives a Butterworth response
+        let alpha = w0.sin() / (2.0 * FRAC_1_SQRT_2);
+        (w0.cos(), alpha)
+    }
+
+    fn normalized(b: [f32; 3], cos: f32, alpha: f32) -> Self {
+        let a0 = 1.0 + alpha;
+
+        Biquad {
+            b: [b[0] / a0, b[1] / a0, b[2] / a0],
+            a: [-2.0 * cos / a0, (1.0 - alpha) / a0],
+        }
+    }
+
+    fn apply(&self, signal: &mut [f32]) {
+        let Biquad { b, a } = *self;
+        let (mut x1, mut x2, mut y1, mut y2) = (0.0, 0.0, 0.0, 0.0);
+
+        for sample in signal {
+            let x0 = *sample;
+            let y0 = b[0] * x0 + b[1] * x1 + b[2] * x2 - a[0] * y1 - a[1] * y2;
+
+            x2 = x1;
+            x1 = x0;
+            y2 = y1;
+            y1 = y0;
+            *sample = y0;
+        }
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: Settings = Settings {
+        sample_rate: 25.0,
+        low_cutoff: 0.5,
+        high_cutoff: 4.0,
+        window: 8.0,
+    };
+
+    /// A pulse at `bpm` with some slow baseline drift, like you'd get from
+    /// someone breathing.
+    fn ppg(bpm: f32, seconds: f32) -> Vec<f32> {
+        let samples = (seconds * SETTINGS.sample_rate) as usize;
+
+        (0..samples)
+            .map(|i| {
+                let t = i as f32 / SETTINGS.sample_rate;
+                let pulse = (2.0 * PI * bpm / 60.0 * t).sin();
+                let drift = 3.0 * (2.0 * PI * 0.1 * t).sin();
+                100.0 + pulse + drift
+            })
+            .collect()
+    }
+
+    #[test]
+    fn estimate_a_resting_heart_rate() {
+        let mut window = Window::default();
+        window.extend(&ppg(72.0, 8.0), SETTINGS.window_length());
+
+        let Estimate { bpm, confidence } = window.estimate(SETTINGS);
+
+        assert!((bpm - 72.0).abs() < 2.0, "{}", bpm);
+        assert!(confidence > 0.9, "{}", confidence);
+    }
+
+    #[test]
+    fn estimate_an_elevated_heart_rate() {
+        let mut window = Window::default();
+        window.extend(&ppg(150.0, 8.0), SETTINGS.window_length());
+
+        let Estimate { bpm, .. } = window.estimate(SETTINGS);
+
+        assert!((bpm - 150.0).abs() < 4.0, "{}", bpm);
+    }
+
+    #[test]
+    fn unknown_until_there_are_enough_beats() {
+        let mut window = Window::default();
+        window.extend(&ppg(60.0, 1.5), SETTINGS.window_length());
+
+        assert_eq!(window.estimate(SETTINGS), Estimate::UNKNOWN);
+    }
+
+    #[test]
+    fn flat_signal_is_unknown() {
+        let mut window = Window::default();
+        window.extend(&[100.0; 200], SETTINGS.window_length());
+
+        assert_eq!(window.estimate(SETTINGS), Estimate::UNKNOWN);
+    }
+
+    #[test]
+    fn window_only_keeps_the_latest_samples() {
+        let mut window = Window::default();
+
+        window.extend(&[1.0, 2.0, 3.0], 4);
+        window.extend(&[4.0, 5.0], 4);
+
+        assert_eq!(window.samples, [2.0, 3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn window_follows_a_changing_heart_rate() {
+        let mut window = Window::default();
+        let length = SETTINGS.window_length();
+
+        window.extend(&ppg(60.0, 8.0), length);
+        window.extend(&ppg(120.0, 8.0), length);
+
+        let Estimate { bpm, .. } = window.estimate(SETTINGS);
+        assert!((bpm - 120.0).abs() < 3.0, "{}", bpm);
+    }
+
+    #[test]
+    fn peaks_must_be_far_enough_apart() {
+        let signal = [0.0, 1.0, 0.0, 2.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+
+        assert_eq!(find_peaks(&signal, 3), [3, 7]);
+        assert_eq!(find_peaks(&signal, 1), [1, 3, 7]);
+    }
+
+    #[test]
+    fn low_pass_attenuates_high_frequencies() {
+        let filter = Biquad::low_pass(2.0, 100.0);
+        let mut signal: Vec<f32> = (0..200)
+            .map(|i| if i % 2 == 0 { 1.0 } else { -1.0 })
+            .collect();
+
+        filter.apply(&mut signal);
+
+        assert!(signal[100..].iter().all(|s| s.abs() < 0.01));
+    }
+}
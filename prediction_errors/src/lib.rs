@@ -1,3 +1,5 @@
+//! **Deprecated:** use the `metrics` proc-block with `metrics=mae,mse` instead.
+
 use smartcore::metrics::{
     mean_absolute_error::MeanAbsoluteError, mean_squared_error::MeanSquareError,
 };
@@ -20,6 +22,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
         metadata.set_homepage(env!("CARGO_PKG_HOMEPAGE"));
         metadata.add_tag("metric");
+        metadata.add_tag("deprecated");
         metadata.add_tag("analytics");
         metadata.add_tag("loss");
 
@@ -94,10 +97,9 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
                 reason: BadInputReason::NotFound,
             })
         })?;
-        let _ytrue: ndarray::ArrayView1<f64> = y_true
+        let y_true_values: ndarray::ArrayView1<f64> = y_true
             .buffer
-            .view(&y_true.dimensions)
-            .and_then(|t| t.into_dimensionality())
+            .try_view(&y_true.dimensions)
             .map_err(|e| {
                 KernelError::InvalidInput(InvalidInput {
                     name: "y_train".to_string(),
@@ -111,10 +113,9 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
                 reason: BadInputReason::NotFound,
             })
         })?;
-        let _ypred: ndarray::ArrayView1<f64> = y_pred
+        let y_pred_values: ndarray::ArrayView1<f64> = y_pred
             .buffer
-            .view(&y_pred.dimensions)
-            .and_then(|t| t.into_dimensionality())
+            .try_view(&y_pred.dimensions)
             .map_err(|e| {
                 KernelError::InvalidInput(InvalidInput {
                     name: "y_pred".to_string(),
@@ -122,31 +123,28 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
                 })
             })?;
 
-        let metric = transform(
-            y_true.buffer.elements().to_vec(),
-            y_pred.buffer.elements().to_vec(),
-        )
-        .unwrap();
+        let metric =
+            transform(y_true_values.to_vec(), y_pred_values.to_vec()).unwrap();
 
-        let mae = vec![metric.0];
+        let mae = [metric.0];
 
         ctx.set_output_tensor(
             "mean_absolute_error",
             TensorParam {
                 element_type: ElementType::F64,
                 dimensions: &[1 as u32],
-                buffer: &mae.as_bytes(),
+                buffer: mae.as_bytes(),
             },
         );
 
-        let mse = vec![metric.1];
+        let mse = [metric.1];
 
         ctx.set_output_tensor(
             "mean_square_error",
             TensorParam {
                 element_type: ElementType::F64,
                 dimensions: &[1 as u32],
-                buffer: &mse.as_bytes(),
+                buffer: mse.as_bytes(),
             },
         );
 
@@ -0,0 +1,593 @@
+//! Hide sensitive parts of an image (faces, license plates, etc.) before it
+//! leaves the device.
+//!
+//! Each detection's bounding box is either pixelated (replaced with a grid of
+//! coarse blocks) or blurred with a box blur. Pixelation is the stronger of
+//! the two because the original pixels can't be recovered, so it's the
+//! default.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::{s, Array2, Array4, ArrayView2, ArrayViewMut3, Axis},
+    prelude::*,
+    runtime_v1::*,
+    BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// The pixel types this proc-block can blur.
+const SUPPORTED_ELEMENT_TYPES: &[&str] = &["u8", "f32"];
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Privacy Blur", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("image");
+        metadata.add_tag("privacy");
+
+        let method = ArgumentMetadata::new("method");
+        method.set_description(
+            "How to hide each region. `pixelate` replaces it with large blocks of colour, while `blur` smooths it out.",
+        );
+        method.set_default_value("pixelate");
+        method.add_hint(&interpret_as_string_in_enum(&["pixelate", "blur"]));
+        metadata.add_argument(&method);
+
+        let block_size = ArgumentMetadata::new("block_size");
+        block_size.set_description(
+            "The size of each block when pixelating, or the blur radius, in pixels. Larger values hide more detail.",
+        );
+        block_size.set_default_value("8");
+        block_size
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&block_size);
+
+        let coordinates = ArgumentMetadata::new("coordinates");
+        coordinates.set_description(
+            "Whether the detections use `normalized` coordinates (in the range `[0, 1]`) or `pixels`.",
+        );
+        coordinates.set_default_value("normalized");
+        let hint = interpret_as_string_in_enum(&["normalized", "pixels"]);
+        coordinates.add_hint(&hint);
+        metadata.add_argument(&coordinates);
+
+        let element_type = ArgumentMetadata::new("element_type");
+        element_type.set_description("The type of the image's pixels.");
+        element_type.set_default_value("u8");
+        element_type
+            .add_hint(&interpret_as_string_in_enum(SUPPORTED_ELEMENT_TYPES));
+        metadata.add_argument(&element_type);
+
+        let image = TensorMetadata::new("image");
+        image.set_description(
+            "An image with the dimensions `[1, height, width, channels]`.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::U8, ElementType::F32],
+            DimensionsParam::Fixed(&[1, 0, 0, 0]),
+        );
+        image.add_hint(&hint);
+        metadata.add_input(&image);
+
+        let detections = TensorMetadata::new("detections");
+        detections.set_description(
+            "The regions to hide, where each row starts with `[x, y, height, width]` and `(x, y)` is the centre of the region (e.g. the output of `object_filter`).",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        detections.add_hint(&hint);
+        metadata.add_input(&detections);
+
+        let output = TensorMetadata::new("redacted_image");
+        output.set_description("The image with each region hidden.");
+        let hint = supported_shapes(
+            &[ElementType::U8, ElementType::F32],
+            DimensionsParam::Fixed(&[1, 0, 0, 0]),
+        );
+        output.add_hint(&hint);
+        metadata.add_output(&output);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+        let element_type = ctx.element_type::<GraphError>(
+            ElementType::U8,
+            SUPPORTED_ELEMENT_TYPES,
+        )?;
+
+        ctx.add_input_tensor(
+            "image",
+            element_type,
+            DimensionsParam::Fixed(&[1, 0, 0, 0]),
+        );
+        ctx.add_input_tensor(
+            "detections",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "redacted_image",
+            element_type,
+            DimensionsParam::Fixed(&[1, 0, 0, 0]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let image = ctx.get_input_tensor("image").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "image".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+        let detections =
+            ctx.get_input_tensor("detections").ok_or_else(|| {
+                KernelError::InvalidInput(InvalidInput {
+                    name: "detections".to_string(),
+                    reason: BadInputReason::NotFound,
+                })
+            })?;
+
+        let detections: ArrayView2<'_, f32> = detections
+            .buffer
+            .try_view::<f32, _>(&detections.dimensions)
+            .map_err(|e| invalid_input("detections", e))?;
+
+        if detections.ncols() < 4 {
+            return Err(invalid_input(
+                "detections",
+                "Each detection needs at least 4 columns",
+            ));
+        }
+
+        let mut pixels: Array4<f32> = match image.element_type {
+            ElementType::U8 => image
+                .buffer
+                .try_view::<u8, _>(&image.dimensions)
+                .map(|t| t.mapv(f32::from)),
+            ElementType::F32 => image
+                .buffer
+                .try_view::<f32, _>(&image.dimensions)
+                .map(|t| t.to_owned()),
+            other => {
+                return Err(invalid_input(
+                    "image",
+                    format!("Unsupported element type: {}", other),
+                ))
+            },
+        }
+        .map_err(|e| invalid_input("image", e))?;
+
+        for mut frame in pixels.outer_iter_mut() {
+            let (height, width, _) = frame.dim();
+
+            for detection in detections.outer_iter() {
+                let region = Region::from_detection(
+                    detection.as_slice().unwrap_or(&[]),
+                    settings.coordinates,
+                    width,
+                    height,
+                );
+
+                if let Some(region) = region {
+                    settings.redact(frame.view_mut(), region);
+                }
+            }
+        }
+
+        match image.element_type {
+            ElementType::U8 => {
+                let pixels: Vec<u8> = pixels
+                    .iter()
+                    .map(|&p| p.round().clamp(0.0, 255.0) as u8)
+                    .collect();
+                ctx.set_output_tensor(
+                    "redacted_image",
+                    TensorParam {
+                        element_type: ElementType::U8,
+                        dimensions: &image.dimensions,
+                        buffer: &pixels,
+                    },
+                );
+            },
+            _ => {
+                let pixels: Vec<f32> = pixels.iter().copied().collect();
+                ctx.set_output_tensor(
+                    "redacted_image",
+                    TensorParam {
+                        element_type: ElementType::F32,
+                        dimensions: &image.dimensions,
+                        buffer: pixels.as_bytes(),
+                    },
+                );
+            },
+        }
+
+        Ok(())
+    }
+}
+
+fn invalid_input(name: &str, reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: name.to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Method {
+    Pixelate,
+    Blur,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Coordinates {
+    Normalized,
+    Pixels,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    method: Method,
+    block_size: usize,
+    coordinates: Coordinates,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let method = ctx.parse_argument_with_default::<_, E>(
+            "method",
+            String::from("pixelate"),
+        )?;
+        let block_size =
+            ctx.parse_argument_with_default::<_, E>("block_size", 8)?;
+        let coordinates = ctx.parse_argument_with_default::<_, E>(
+            "coordinates",
+            String::from("normalized"),
+        )?;
+
+        let method = match method.as_str() {
+            "pixelate" => Method::Pixelate,
+            "blur" => Method::Blur,
+            other => {
+                return Err(E::invalid_argument(
+                    InvalidArgument::invalid_value(
+                        "method",
+                        format!("Unknown method, \"{}\"", other),
+                    ),
+                ))
+            },
+        };
+
+        let coordinates = match coordinates.as_str() {
+            "normalized" => Coordinates::Normalized,
+            "pixels" => Coordinates::Pixels,
+            other => {
+                return Err(E::invalid_argument(
+                    InvalidArgument::invalid_value(
+                        "coordinates",
+                        format!("Unknown coordinate system, \"{}\"", other),
+                    ),
+                ))
+            },
+        };
+
+        if block_size == 0 {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "block_size",
+                "The block size must be greater than zero",
+            )));
+        }
+
+        Ok(Settings {
+            method,
+            block_size,
+            coordinates,
+        })
+    }
+
+    /// Hide a region of a `[height, width, channels]` image.
+    fn redact(&self, mut image: ArrayViewMut3<'_, f32>, region: Region) {
+        let patch = image.slice_mut(s![
+            region.top..region.bottom,
+            region.left..region.right,
+            ..
+        ]);
+
+        match self.method {
+            Method::Pixelate => pixelate(patch, self.block_size),
+            Method::Blur => blur(patch, self.block_size),
+        }
+    }
+}
+
+/// A rectangle of pixels, where the `bottom` and `right` edges are
+/// exclusive.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Region {
+    top: usize,
+    left: usize,
+    bottom: usize,
+    right: usize,
+}
+
+impl Region {
+    /// Get the pixels covered by a `[x, y, height, width, ...]` detection,
+    /// clamped to the image's bounds.
+    fn from_detection(
+        detection: &[f32],
+        coordinates: Coordinates,
+        image_width: usize,
+        image_height: usize,
+    ) -> Option<Region> {
+        let (x, y, height, width) = match *detection {
+            [x, y, height, width, ..] => (x, y, height, width),
+            _ => return None,
+        };
+
+        let (x_scale, y_scale) = match coordinates {
+            Coordinates::Normalized => {
+                (image_width as f32, image_height as f32)
+            },
+            Coordinates::Pixels => (1.0, 1.0),
+        };
+
+        // Round outwards so partially covered pixels are always hidden
+        let edge = |value: f32, scale: f32, max: usize, round: fn(f32) -> f32| {
+            round(value * scale).clamp(0.0, max as f32) as usize
+        };
+
+        let region = Region {
+            top: edge(y - height / 2.0, y_scale, image_height, f32::floor),
+            left: edge(x - width / 2.0, x_scale, image_width, f32::floor),
+            bottom: edge(y + height / 2.0, y_scale, image_height, f32::ceil),
+            right: edge(x + width / 2.0, x_scale, image_width, f32::ceil),
+        };
+
+        (region.top < region.bottom && region.left < region.right)
+            .then(|| region)
+    }
+}
+
+/// Replace each `block_size` x `block_size` block with its average colour.
+fn pixelate(mut patch: ArrayViewMut3<'_, f32>, block_size: usize) {
+    let (height, width, _) = patch.dim();
+
+    for top in (0..height).step_by(block_size) {
+        for left in (0..width).step_by(block_size) {
+            let mut block = patch.slice_mut(s![
+                top..(top + block_size).min(height),
+                left..(left + block_size).min(width),
+                ..
+            ]);
+            let pixels = block.len_of(Axis(0)) * block.len_of(Axis(1));
+
+            for mut channel in block.axis_iter_mut(Axis(2)) {
+                let mean = channel.sum() / pixels as f32;
+                channel.fill(mean);
+            }
+        }
+    }
+}
+
+/// Apply a box blur with the given radius, only using pixels from inside the
+/// patch so colours from outside the region don't bleed in.
+fn blur(mut patch: ArrayViewMut3<'_, f32>, radius: usize) {
+    for mut channel in patch.axis_iter_mut(Axis(2)) {
+        // A box blur is separable, so we can blur the rows then the columns
+        let mut blurred = channel.to_owned();
+        box_blur_rows(&mut blurred, radius);
+        blurred.swap_axes(0, 1);
+        box_blur_rows(&mut blurred, radius);
+        blurred.swap_axes(0, 1);
+
+        channel.assign(&blurred);
+    }
+}
+
+/// Replace each pixel with the mean of the pixels within `radius` of it on
+/// the same row.
+fn box_blur_rows(image: &mut Array2<f32>, radius: usize) {
+    for mut row in image.outer_iter_mut() {
+        let original = row.to_vec();
+
+        for (i, pixel) in row.iter_mut().enumerate() {
+            let start = i.saturating_sub(radius);
+            let end = (i + radius + 1).min(original.len());
+            let window = &original[start..end];
+            *pixel = window.iter().sum::<f32>() / window.len() as f32;
+        }
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::{
+        ndarray::{self, Array3},
+        testing::{assert_slices_close, Tolerance},
+    };
+
+    #[test]
+    fn normalized_detection_to_region() {
+        let region = Region::from_detection(
+            &[0.5, 0.25, 0.5, 0.5, 0.9, 1.0],
+            Coordinates::Normalized,
+            10,
+            20,
+        );
+
+        assert_eq!(
+            region,
+            Some(Region {
+                top: 0,
+                left: 2,
+                bottom: 10,
+                right: 8,
+            })
+        );
+    }
+
+    #[test]
+    fn regions_are_clamped_to_the_image() {
+        let region = Region::from_detection(
+            &[1.0, 1.0, 4.0, 4.0],
+            Coordinates::Pixels,
+            2,
+            2,
+        );
+
+        assert_eq!(
+            region,
+            Some(Region {
+                top: 0,
+                left: 0,
+                bottom: 2,
+                right: 2,
+            })
+        );
+        assert_eq!(
+            Region::from_detection(
+                &[10.0, 10.0, 1.0, 1.0],
+                Coordinates::Pixels,
+                2,
+                2
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn pixelate_averages_each_block() {
+        let mut image = Array3::from_shape_vec(
+            (2, 3, 1),
+            vec![0.0, 2.0, 10.0, 4.0, 6.0, 20.0],
+        )
+        .unwrap();
+
+        pixelate(image.view_mut(), 2);
+
+        let expected = [3.0, 3.0, 15.0, 3.0, 3.0, 15.0];
+        assert_slices_close(
+            image.as_slice().unwrap(),
+            &expected,
+            Tolerance::DEFAULT,
+        );
+    }
+
+    #[test]
+    fn blur_smooths_within_the_patch() {
+        let mut image = ndarray::arr3(&[[[0.0], [0.0], [9.0]]]);
+
+        blur(image.view_mut(), 1);
+
+        let expected = [0.0, 3.0, 4.5];
+        assert_slices_close(
+            image.as_slice().unwrap(),
+            &expected,
+            Tolerance::DEFAULT,
+        );
+    }
+
+    #[test]
+    fn only_the_region_is_redacted() {
+        let mut image = Array3::from_shape_fn((4, 4, 3), |(y, x, c)| {
+            (y * 16 + x * 4 + c) as f32
+        });
+        let original = image.clone();
+        let settings = Settings {
+            method: Method::Pixelate,
+            block_size: 2,
+            coordinates: Coordinates::Pixels,
+        };
+        let region = Region {
+            top: 0,
+            left: 0,
+            bottom: 2,
+            right: 2,
+        };
+
+        settings.redact(image.view_mut(), region);
+
+        assert_ne!(
+            image.slice(s![..2, ..2, ..]),
+            original.slice(s![..2, ..2, ..])
+        );
+        assert_eq!(
+            image.slice(s![2.., .., ..]),
+            original.slice(s![2.., .., ..])
+        );
+        assert_eq!(
+            image.slice(s![.., 2.., ..]),
+            original.slice(s![.., 2.., ..])
+        );
+        // Each channel is averaged separately
+        assert_eq!(image[[0, 0, 0]], 10.0);
+        assert_eq!(image[[1, 1, 2]], 12.0);
+    }
+}
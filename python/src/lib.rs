@@ -0,0 +1,108 @@
+//! Python bindings to the algorithms used by each proc-block.
+//!
+//! Every function calls the exact same Rust code that gets compiled to
+//! WebAssembly, so results in a notebook will match the deployed Rune.
+
+use hotg_rune_proc_blocks::ndarray::{Array2, ArrayD};
+use numpy::{
+    IntoPyArray, PyArray1, PyArray2, PyArrayDyn, PyReadonlyArray1,
+    PyReadonlyArray3, PyReadonlyArrayDyn,
+};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+/// Normalise a vector into a probability distribution.
+#[pyfunction]
+fn softmax<'py>(
+    py: Python<'py>,
+    input: PyReadonlyArray1<'_, f64>,
+) -> &'py PyArray1<f64> {
+    let mut output = input.to_owned_array();
+    softmax_core::softmax(output.view_mut());
+    output.into_pyarray(py)
+}
+
+/// Scale each element in a tensor to the range `[0, 1]`.
+#[pyfunction]
+fn normalize<'py>(
+    py: Python<'py>,
+    input: PyReadonlyArrayDyn<'_, f64>,
+) -> PyResult<&'py PyArrayDyn<f32>> {
+    let input = input.as_array();
+    let elements: Vec<f64> = input.iter().copied().collect();
+
+    let normalized = normalize_core::transform(&elements)
+        .ok_or_else(|| PyValueError::new_err("The input tensor was empty"))?;
+
+    let output = ArrayD::from_shape_vec(input.shape(), normalized)
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+    Ok(output.into_pyarray(py))
+}
+
+/// Normalize the pixels in a `[frames, rows, columns, channels]` image to the
+/// range `[0, 1]`.
+#[pyfunction]
+fn image_normalization<'py>(
+    py: Python<'py>,
+    image: PyReadonlyArrayDyn<'_, u8>,
+) -> PyResult<&'py PyArrayDyn<f32>> {
+    let image = image.as_array();
+    let dimensions: Vec<u32> =
+        image.shape().iter().map(|&d| d as u32).collect();
+    image_normalization_core::check_input_dimensions(&dimensions)
+        .map_err(PyValueError::new_err)?;
+
+    Ok(image
+        .mapv(image_normalization_core::normalize)
+        .into_pyarray(py))
+}
+
+/// Remove duplicate and low-confidence detections from a `[1, detections,
+/// 5 + classes]` tensor, returning a `[*, 6]` array of objects.
+#[pyfunction]
+fn object_filter<'py>(
+    py: Python<'py>,
+    bounding_boxes: PyReadonlyArray3<'_, f32>,
+    threshold: f32,
+) -> PyResult<&'py PyArray2<f32>> {
+    // Note: the object filter expects each row to be contiguous
+    let bounding_boxes = bounding_boxes.as_array();
+    let bounding_boxes = bounding_boxes.as_standard_layout();
+    let elements =
+        object_filter_core::transform(bounding_boxes.view(), threshold);
+
+    let rows = elements.len() / 6;
+    let output = Array2::from_shape_vec((rows, 6), elements)
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+    Ok(output.into_pyarray(py))
+}
+
+/// Tokenize a question and a paragraph using the BERT tokenizer, returning
+/// `(token_ids, token_mask, segment_ids)`.
+#[pyfunction]
+fn tokenize<'py>(
+    py: Python<'py>,
+    question: &str,
+    paragraph: &str,
+) -> (&'py PyArray1<i32>, &'py PyArray1<i32>, &'py PyArray1<i32>) {
+    let (token_ids, token_mask, segment_ids, _) =
+        tokenizers_core::transform((question.as_bytes(), paragraph.as_bytes()));
+
+    (
+        token_ids.into_pyarray(py),
+        token_mask.into_pyarray(py),
+        segment_ids.into_pyarray(py),
+    )
+}
+
+#[pymodule]
+fn proc_blocks(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(softmax, m)?)?;
+    m.add_function(wrap_pyfunction!(normalize, m)?)?;
+    m.add_function(wrap_pyfunction!(image_normalization, m)?)?;
+    m.add_function(wrap_pyfunction!(object_filter, m)?)?;
+    m.add_function(wrap_pyfunction!(tokenize, m)?)?;
+
+    Ok(())
+}
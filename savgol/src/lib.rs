@@ -0,0 +1,495 @@
+//! A Savitzky-Golay filter.
+//!
+//! Each sample is replaced by the value (or derivative) at that point of a
+//! polynomial which was least-squares fitted to the `window_length` samples
+//! around it. Unlike a moving average, this preserves the height and width
+//! of peaks, which makes it popular for smoothing spectra and other sensor
+//! curves.
+//!
+//! Near the edges of the signal there aren't enough samples for a centered
+//! window, so the polynomial is fitted to the first (or last)
+//! `window_length` samples instead. This matches the `"interp"` mode from
+//! `scipy.signal.savgol_filter()`.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    prelude::*, runtime_v1::*, BufferExt, SliceExt,
+};
+use std::cmp::Ordering;
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Savitzky-Golay Filter", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("signal processing");
+        metadata.add_tag("smoothing");
+        metadata.add_tag("sensor");
+
+        let window_length = ArgumentMetadata::new("window_length");
+        window_length.set_description(
+            "The number of samples each polynomial is fitted to. This must be odd.",
+        );
+        window_length.set_default_value("5");
+        window_length.add_hint(&non_negative_number());
+        window_length
+            .add_hint(&supported_argument_type(ArgumentType::Integer));
+        metadata.add_argument(&window_length);
+
+        let polyorder = ArgumentMetadata::new("polyorder");
+        polyorder.set_description(
+            "The order of the fitted polynomial. This must be less than `window_length`, and lower orders give smoother results.",
+        );
+        polyorder.set_default_value("2");
+        polyorder.add_hint(&non_negative_number());
+        polyorder.add_hint(&supported_argument_type(ArgumentType::Integer));
+        metadata.add_argument(&polyorder);
+
+        let deriv = ArgumentMetadata::new("deriv");
+        deriv.set_description(
+            "Which derivative to calculate. Use `0` to just smooth the signal.",
+        );
+        deriv.set_default_value("0");
+        deriv.add_hint(&non_negative_number());
+        deriv.add_hint(&supported_argument_type(ArgumentType::Integer));
+        metadata.add_argument(&deriv);
+
+        let delta = ArgumentMetadata::new("delta");
+        delta.set_description(
+            "The spacing between samples, used to scale derivatives.",
+        );
+        delta.set_default_value("1.0");
+        delta.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&delta);
+
+        let input = TensorMetadata::new("input");
+        input.set_description(
+            "The signal to filter. It must have at least `window_length` samples.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Fixed(&[0]));
+        input.add_hint(&hint);
+        metadata.add_input(&input);
+
+        let output = TensorMetadata::new("output");
+        output.set_description(
+            "The smoothed signal (or its derivative), with one value per input sample.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Fixed(&[0]));
+        output.add_hint(&hint);
+        metadata.add_output(&output);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = SavitzkyGolay::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "input",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0]),
+        );
+        ctx.add_output_tensor(
+            "output",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let filter = SavitzkyGolay::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            buffer,
+            ..
+        } = ctx.get_input_tensor("input").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "input".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::F32 {
+            return Err(invalid_input(format!(
+                "Expected a f32 tensor, found {}",
+                element_type
+            )));
+        }
+
+        let input = buffer.try_elements::<f32>().map_err(invalid_input)?;
+
+        if input.len() < filter.window_length {
+            return Err(invalid_input(format!(
+                "The signal has {} samples, but at least {} (the window length) are needed",
+                input.len(),
+                filter.window_length
+            )));
+        }
+
+        let output = filter.apply(input);
+
+        ctx.set_output_tensor(
+            "output",
+            TensorParam {
+                element_type: ElementType::F32,
+                dimensions: &[output.len() as u32],
+                buffer: output.as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "input".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SavitzkyGolay {
+    /// The number of samples each polynomial is fitted to (always odd).
+    pub window_length: usize,
+    /// The order of the fitted polynomial.
+    pub polyorder: usize,
+    /// Which derivative to evaluate. Derivatives higher than `polyorder` are
+    /// always zero.
+    pub deriv: usize,
+    /// The spacing between samples.
+    pub delta: f64,
+}
+
+impl SavitzkyGolay {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let window_length: usize =
+            ctx.parse_argument_with_default::<_, E>("window_length", 5)?;
+        let polyorder =
+            ctx.parse_argument_with_default::<_, E>("polyorder", 2)?;
+        let deriv = ctx.parse_argument_with_default::<_, E>("deriv", 0)?;
+        let delta: f64 =
+            ctx.parse_argument_with_default::<_, E>("delta", 1.0)?;
+
+        if window_length % 2 == 0 {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "window_length",
+                format!("Must be odd, found {}", window_length),
+            )));
+        }
+
+        if polyorder >= window_length {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "polyorder",
+                format!(
+                    "Must be less than the window length ({}), found {}",
+                    window_length, polyorder
+                ),
+            )));
+        }
+
+        if !(delta > 0.0 && delta.is_finite()) {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "delta",
+                format!("Must be greater than zero, found {}", delta),
+            )));
+        }
+
+        Ok(SavitzkyGolay {
+            window_length,
+            polyorder,
+            deriv,
+            delta,
+        })
+    }
+
+    /// Filter a signal.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if `signal` has fewer than
+    /// [`SavitzkyGolay::window_length`] samples.
+    pub fn apply(&self, signal: &[f32]) -> Vec<f32> {
+        let n = signal.len();
+        let window = self.window_length;
+        let half = window / 2;
+        assert!(n >= window);
+
+        let filter = |start: usize, weights: &[f64]| -> f32 {
+            signal[start..start + window]
+                .iter()
+                .zip(weights)
+                .map(|(&x, w)| f64::from(x) * w)
+                .sum::<f64>() as f32
+        };
+
+        let mut output = Vec::with_capacity(n);
+
+        // The first few samples are evaluated with the polynomial fitted to
+        // the first window
+        for i in 0..half {
+            output.push(filter(0, &self.weights(i as f64 - half as f64)));
+        }
+
+        let centered = self.weights(0.0);
+        for start in 0..=n - window {
+            output.push(filter(start, &centered));
+        }
+
+        // ... and the last few use the polynomial fitted to the last window
+        for i in 1..=half {
+            output.push(filter(n - window, &self.weights(i as f64)));
+        }
+
+        output
+    }
+
+    /// The weights which, when multiplied with the samples in a window and
+    /// summed, give the fitted polynomial's `deriv`'th derivative at
+    /// `position` (relative to the window's center).
+    fn weights(&self, position: f64) -> Vec<f64> {
+        let half = (self.window_length / 2) as f64;
+        let terms = self.polyorder + 1;
+
+        // Positions are scaled to [-1, 1] to keep the normal equations well
+        // conditioned for large windows
+        let scale = half.max(1.0);
+        let positions: Vec<f64> = (0..self.window_length)
+            .map(|i| (i as f64 - half) / scale)
+            .collect();
+        let x = position / scale;
+
+        // The deriv'th derivative of each basis function, x^j, at the
+        // position we are evaluating
+        let derivatives: Vec<f64> = (0..terms)
+            .map(|j| {
+                if j < self.deriv {
+                    0.0
+                } else {
+                    let coefficient: f64 =
+                        (j - self.deriv + 1..=j).map(|k| k as f64).product();
+                    coefficient * x.powi((j - self.deriv) as i32)
+                }
+            })
+            .collect();
+
+        // Solve (AᵀA) z = derivatives, where A is the Vandermonde matrix for
+        // the window, then the weights are A z
+        let mut normal = vec![vec![0.0; terms]; terms];
+        for (r, row) in normal.iter_mut().enumerate() {
+            for (c, value) in row.iter_mut().enumerate() {
+                *value = positions.iter().map(|p| p.powi((r + c) as i32)).sum();
+            }
+        }
+        let z = solve(normal, derivatives);
+
+        // Undo the scaling for the derivative and convert to the caller's
+        // units
+        let units = (scale * self.delta).powi(self.deriv as i32);
+
+        positions
+            .iter()
+            .map(|p| {
+                z.iter()
+                    .enumerate()
+                    .map(|(j, z)| z * p.powi(j as i32))
+                    .sum::<f64>()
+                    / units
+            })
+            .collect()
+    }
+}
+
+/// Solve the linear system `a x = b` using Gaussian elimination with partial
+/// pivoting.
+fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Vec<f64> {
+    let n = b.len();
+
+    for column in 0..n {
+        let pivot = (column..n)
+            .max_by(|&i, &j| {
+                a[i][column]
+                    .abs()
+                    .partial_cmp(&a[j][column].abs())
+                    .unwrap_or(Ordering::Equal)
+            })
+            .unwrap();
+        a.swap(column, pivot);
+        b.swap(column, pivot);
+
+        let (above, below) = a.split_at_mut(column + 1);
+        let pivot_row = &above[column];
+        for (offset, row) in below.iter_mut().enumerate() {
+            let factor = row[column] / pivot_row[column];
+            for (value, p) in row[column..].iter_mut().zip(&pivot_row[column..])
+            {
+                *value -= factor * p;
+            }
+            b[column + 1 + offset] -= factor * b[column];
+        }
+    }
+
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|c| a[row][c] * x[c]).sum();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+
+    x
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILTER: SavitzkyGolay = SavitzkyGolay {
+        window_length: 5,
+        polyorder: 2,
+        deriv: 0,
+        delta: 1.0,
+    };
+
+    fn assert_close(got: &[f32], expected: &[f32]) {
+        assert_eq!(got.len(), expected.len());
+
+        for (g, e) in got.iter().zip(expected) {
+            assert!((g - e).abs() < 1e-4, "{:?} != {:?}", got, expected);
+        }
+    }
+
+    #[test]
+    fn classic_five_point_quadratic_coefficients() {
+        // From the original Savitzky & Golay (1964) tables
+        let expected = [-3.0, 12.0, 17.0, 12.0, -3.0].map(|c| c / 35.0);
+
+        let weights = FILTER.weights(0.0);
+
+        for (w, e) in weights.iter().zip(expected) {
+            assert!((w - e).abs() < 1e-12, "{:?}", weights);
+        }
+    }
+
+    #[test]
+    fn compare_with_scipy() {
+        // scipy.signal.savgol_filter([2, 2, 5, 2, 1, 0, 1, 4, 9], 5, 2)
+        let signal = [2.0, 2.0, 5.0, 2.0, 1.0, 0.0, 1.0, 4.0, 9.0];
+
+        let got = FILTER.apply(&signal);
+
+        assert_close(
+            &got,
+            &[
+                1.657143, 3.171429, 3.542857, 2.857143, 0.657143, 0.171429,
+                1.0, 4.0, 9.0,
+            ],
+        );
+    }
+
+    #[test]
+    fn polynomials_pass_through_unchanged() {
+        let signal: Vec<f32> = (0..12)
+            .map(|x| {
+                let x = x as f32;
+                0.5 * x * x - 3.0 * x + 2.0
+            })
+            .collect();
+
+        let got = FILTER.apply(&signal);
+
+        assert_close(&got, &signal);
+    }
+
+    #[test]
+    fn first_derivative_of_a_quadratic() {
+        let delta = 0.5;
+        let signal: Vec<f32> = (0..10)
+            .map(|i| {
+                let t = i as f32 * delta as f32;
+                t * t
+            })
+            .collect();
+        let filter = SavitzkyGolay {
+            deriv: 1,
+            delta,
+            ..FILTER
+        };
+
+        let got = filter.apply(&signal);
+
+        let expected: Vec<f32> =
+            (0..10).map(|i| 2.0 * i as f32 * delta as f32).collect();
+        assert_close(&got, &expected);
+    }
+
+    #[test]
+    fn derivatives_above_the_polynomial_order_are_zero() {
+        let filter = SavitzkyGolay { deriv: 3, ..FILTER };
+
+        let got = filter.apply(&[1.0, 5.0, -2.0, 4.0, 8.0, 0.0]);
+
+        assert_close(&got, &[0.0; 6]);
+    }
+}
@@ -0,0 +1,281 @@
+//! Smooth a classifier's scores over time using an exponential moving average,
+//!
+//! ```text
+//! smoothed[t] = decay * smoothed[t-1] + (1 - decay) * scores[t]
+//! ```
+//!
+//! When a model is run continuously on overlapping windows (e.g. keyword
+//! spotting), the raw scores tend to flicker between classes. Smoothing them
+//! before passing them to something like `argmax` or `label` gives much more
+//! stable results.
+//!
+//! The previous average is remembered between invocations. If the number of
+//! classes changes, the average starts again from the new scores.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    prelude::*, runtime_v1::*, state::NodeState, BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// Each node's moving average.
+static AVERAGES: NodeState<MovingAverage> = NodeState::new();
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Score Smoother", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("classification");
+        metadata.add_tag("audio");
+        metadata.add_tag("stateful");
+
+        let decay = ArgumentMetadata::new("decay");
+        decay.set_description(
+            "How much of the previous average to keep each invocation, between 0 (no smoothing) and 1 (exclusive).",
+        );
+        decay.set_default_value("0.9");
+        decay.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&decay);
+
+        let scores = TensorMetadata::new("scores");
+        scores.set_description("The scores for each class.");
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Dynamic);
+        scores.add_hint(&hint);
+        metadata.add_input(&scores);
+
+        let smoothed = TensorMetadata::new("smoothed");
+        smoothed.set_description(
+            "The moving average of each score. This has the same shape as the input.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Dynamic);
+        smoothed.add_hint(&hint);
+        metadata.add_output(&smoothed);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = decay::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "scores",
+            ElementType::F32,
+            DimensionsParam::Dynamic,
+        );
+        ctx.add_output_tensor(
+            "smoothed",
+            ElementType::F32,
+            DimensionsParam::Dynamic,
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let decay = decay::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("scores").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "scores".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::F32 {
+            return Err(KernelError::InvalidInput(InvalidInput {
+                name: "scores".to_string(),
+                reason: BadInputReason::InvalidValue(format!(
+                    "Expected a f32 tensor, found {}",
+                    element_type
+                )),
+            }));
+        }
+
+        let scores = buffer
+            .try_elements_with_shape::<f32>(&dimensions)
+            .map_err(|e| {
+                KernelError::InvalidInput(InvalidInput {
+                    name: "scores".to_string(),
+                    reason: BadInputReason::InvalidValue(e.to_string()),
+                })
+            })?;
+
+        let smoothed = AVERAGES
+            .with(&id, |average| average.update(scores, decay).to_vec());
+
+        ctx.set_output_tensor(
+            "smoothed",
+            TensorParam {
+                element_type: ElementType::F32,
+                dimensions: &dimensions,
+                buffer: smoothed.as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn decay<C, E>(ctx: &C) -> Result<f32, E>
+where
+    C: ContextExt,
+    E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+{
+    let decay: f32 = ctx.parse_argument_with_default::<_, E>("decay", 0.9)?;
+
+    if (0.0..1.0).contains(&decay) {
+        Ok(decay)
+    } else {
+        Err(E::invalid_argument(InvalidArgument::invalid_value(
+            "decay",
+            format!("The decay must be in the range [0, 1), found {}", decay),
+        )))
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct MovingAverage {
+    /// The current average, or empty if we haven't seen any scores yet.
+    values: Vec<f32>,
+}
+
+impl MovingAverage {
+    /// Add a new set of scores to the average.
+    ///
+    /// The first set of scores (or any time the number of scores changes)
+    /// resets the average.
+    fn update(&mut self, scores: &[f32], decay: f32) -> &[f32] {
+        if self.values.len() != scores.len() {
+            self.values = scores.to_vec();
+        } else {
+            for (average, &score) in self.values.iter_mut().zip(scores) {
+                *average = decay * *average + (1.0 - decay) * score;
+            }
+        }
+
+        &self.values
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::testing::{assert_slices_close, Tolerance};
+
+    #[test]
+    fn first_scores_are_passed_through() {
+        let mut average = MovingAverage::default();
+
+        let got = average.update(&[0.2, 0.8], 0.9);
+
+        assert_eq!(got, [0.2, 0.8]);
+    }
+
+    #[test]
+    fn average_decays_towards_new_scores() {
+        let mut average = MovingAverage::default();
+        average.update(&[1.0, 0.0], 0.5);
+
+        let got = average.update(&[0.0, 1.0], 0.5).to_vec();
+        assert_slices_close(&got, &[0.5, 0.5], Tolerance::F32);
+
+        let got = average.update(&[0.0, 1.0], 0.5).to_vec();
+        assert_slices_close(&got, &[0.25, 0.75], Tolerance::F32);
+    }
+
+    #[test]
+    fn zero_decay_disables_smoothing() {
+        let mut average = MovingAverage::default();
+        average.update(&[1.0, 0.0], 0.0);
+
+        let got = average.update(&[0.3, 0.7], 0.0);
+
+        assert_eq!(got, [0.3, 0.7]);
+    }
+
+    #[test]
+    fn changing_the_number_of_classes_resets_the_average() {
+        let mut average = MovingAverage::default();
+        average.update(&[1.0, 0.0], 0.9);
+
+        let got = average.update(&[0.1, 0.2, 0.7], 0.9);
+
+        assert_eq!(got, [0.1, 0.2, 0.7]);
+    }
+
+    #[test]
+    fn smoothing_reduces_jitter() {
+        let mut average = MovingAverage::default();
+        let noisy = [[0.9, 0.1], [0.4, 0.6], [0.9, 0.1], [0.45, 0.55]];
+
+        let winners: Vec<bool> = noisy
+            .iter()
+            .map(|scores| {
+                let smoothed = average.update(scores, 0.8);
+                smoothed[0] > smoothed[1]
+            })
+            .collect();
+
+        assert_eq!(winners, [true, true, true, true]);
+    }
+}
@@ -0,0 +1,654 @@
+//! A tiny expression language which gets compiled to bytecode for a stack
+//! machine.
+//!
+//! Expressions are just arithmetic, comparisons, and calls to a fixed set of
+//! built-in functions. There are no loops, assignments, or side effects, so
+//! evaluating an expression always terminates and can't touch anything
+//! outside the values it's given.
+
+use std::fmt::{self, Display, Formatter};
+
+/// The longest expression we are willing to compile, in bytes.
+const MAX_LENGTH: usize = 1024;
+/// How deeply expressions may be nested, so a malicious expression can't
+/// overflow the stack while parsing.
+const MAX_DEPTH: usize = 32;
+
+/// A value that can be referenced by name in an expression.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Variable {
+    /// The current element (`x`).
+    Element,
+    /// The current element's index (`i`).
+    Index,
+    /// The total number of elements (`n`).
+    Length,
+    /// The accumulated value when reducing (`acc`).
+    Accumulator,
+}
+
+impl Variable {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "x" => Some(Variable::Element),
+            "i" => Some(Variable::Index),
+            "n" => Some(Variable::Length),
+            "acc" => Some(Variable::Accumulator),
+            _ => None,
+        }
+    }
+}
+
+/// The values an expression is evaluated with.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub(crate) struct Environment {
+    pub element: f64,
+    pub index: f64,
+    pub length: f64,
+    pub accumulator: f64,
+}
+
+impl Environment {
+    fn get(&self, variable: Variable) -> f64 {
+        match variable {
+            Variable::Element => self.element,
+            Variable::Index => self.index,
+            Variable::Length => self.length,
+            Variable::Accumulator => self.accumulator,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Function {
+    Abs,
+    Sqrt,
+    Exp,
+    Ln,
+    Log10,
+    Sin,
+    Cos,
+    Tan,
+    Tanh,
+    Floor,
+    Ceil,
+    Round,
+    Min,
+    Max,
+    Clamp,
+    If,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "abs" => Some(Function::Abs),
+            "sqrt" => Some(Function::Sqrt),
+            "exp" => Some(Function::Exp),
+            "ln" => Some(Function::Ln),
+            "log10" => Some(Function::Log10),
+            "sin" => Some(Function::Sin),
+            "cos" => Some(Function::Cos),
+            "tan" => Some(Function::Tan),
+            "tanh" => Some(Function::Tanh),
+            "floor" => Some(Function::Floor),
+            "ceil" => Some(Function::Ceil),
+            "round" => Some(Function::Round),
+            "min" => Some(Function::Min),
+            "max" => Some(Function::Max),
+            "clamp" => Some(Function::Clamp),
+            "if" => Some(Function::If),
+            _ => None,
+        }
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Function::Min | Function::Max => 2,
+            Function::Clamp | Function::If => 3,
+            _ => 1,
+        }
+    }
+
+    fn apply(self, args: &[f64]) -> f64 {
+        match (self, args) {
+            (Function::Abs, &[x]) => x.abs(),
+            (Function::Sqrt, &[x]) => x.sqrt(),
+            (Function::Exp, &[x]) => x.exp(),
+            (Function::Ln, &[x]) => x.ln(),
+            (Function::Log10, &[x]) => x.log10(),
+            (Function::Sin, &[x]) => x.sin(),
+            (Function::Cos, &[x]) => x.cos(),
+            (Function::Tan, &[x]) => x.tan(),
+            (Function::Tanh, &[x]) => x.tanh(),
+            (Function::Floor, &[x]) => x.floor(),
+            (Function::Ceil, &[x]) => x.ceil(),
+            (Function::Round, &[x]) => x.round(),
+            (Function::Min, &[a, b]) => a.min(b),
+            (Function::Max, &[a, b]) => a.max(b),
+            (Function::Clamp, &[x, low, high]) => x.max(low).min(high),
+            (Function::If, &[condition, then, otherwise]) => {
+                if condition != 0.0 {
+                    then
+                } else {
+                    otherwise
+                }
+            },
+            _ => unreachable!("{:?} was called with {:?}", self, args),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Power,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+}
+
+impl BinaryOp {
+    /// The left and right binding power for this operator, used by the
+    /// Pratt parser to handle precedence and associativity.
+    fn binding_power(self) -> (u8, u8) {
+        match self {
+            BinaryOp::Less
+            | BinaryOp::LessEqual
+            | BinaryOp::Greater
+            | BinaryOp::GreaterEqual
+            | BinaryOp::Equal
+            | BinaryOp::NotEqual => (1, 2),
+            BinaryOp::Add | BinaryOp::Subtract => (3, 4),
+            BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Remainder => {
+                (5, 6)
+            },
+            // Right-associative and binds tighter than unary minus
+            BinaryOp::Power => (9, 8),
+        }
+    }
+
+    fn apply(self, left: f64, right: f64) -> f64 {
+        match self {
+            BinaryOp::Add => left + right,
+            BinaryOp::Subtract => left - right,
+            BinaryOp::Multiply => left * right,
+            BinaryOp::Divide => left / right,
+            BinaryOp::Remainder => left % right,
+            BinaryOp::Power => left.powf(right),
+            BinaryOp::Less => bool_to_f64(left < right),
+            BinaryOp::LessEqual => bool_to_f64(left <= right),
+            BinaryOp::Greater => bool_to_f64(left > right),
+            BinaryOp::GreaterEqual => bool_to_f64(left >= right),
+            BinaryOp::Equal => bool_to_f64(left == right),
+            BinaryOp::NotEqual => bool_to_f64(left != right),
+        }
+    }
+}
+
+/// The binding power used for the operand of a unary `-` or `+`.
+const PREFIX_BINDING_POWER: u8 = 7;
+
+fn bool_to_f64(value: bool) -> f64 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Op {
+    Constant(f64),
+    Load(Variable),
+    Negate,
+    Binary(BinaryOp),
+    Call(Function),
+}
+
+/// A compiled expression.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Program {
+    ops: Vec<Op>,
+}
+
+impl Program {
+    pub(crate) fn compile(src: &str) -> Result<Self, CompileError> {
+        if src.len() > MAX_LENGTH {
+            return Err(CompileError::new(format!(
+                "Expressions can't be longer than {} bytes",
+                MAX_LENGTH
+            )));
+        }
+
+        let tokens = tokenize(src)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+            ops: Vec::new(),
+        };
+
+        parser.expression(0, 0)?;
+
+        if let Some(token) = parser.peek() {
+            return Err(CompileError::new(format!(
+                "Unexpected {} after the end of the expression",
+                token
+            )));
+        }
+
+        Ok(Program { ops: parser.ops })
+    }
+
+    /// Does this expression reference a particular [`Variable`]?
+    pub(crate) fn uses(&self, variable: Variable) -> bool {
+        self.ops.contains(&Op::Load(variable))
+    }
+
+    /// Evaluate the expression, using `stack` as scratch space so it can be
+    /// reused between calls.
+    pub(crate) fn evaluate(
+        &self,
+        env: &Environment,
+        stack: &mut Vec<f64>,
+    ) -> f64 {
+        stack.clear();
+
+        // Note: the parser only emits well-formed programs, so the stack
+        // always has enough operands.
+        for op in &self.ops {
+            match *op {
+                Op::Constant(value) => stack.push(value),
+                Op::Load(variable) => stack.push(env.get(variable)),
+                Op::Negate => {
+                    let value = stack.pop().unwrap();
+                    stack.push(-value);
+                },
+                Op::Binary(op) => {
+                    let right = stack.pop().unwrap();
+                    let left = stack.pop().unwrap();
+                    stack.push(op.apply(left, right));
+                },
+                Op::Call(function) => {
+                    let start = stack.len() - function.arity();
+                    let result = function.apply(&stack[start..]);
+                    stack.truncate(start);
+                    stack.push(result);
+                },
+            }
+        }
+
+        stack.pop().unwrap()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CompileError {
+    message: String,
+}
+
+impl CompileError {
+    fn new(message: impl Into<String>) -> Self {
+        CompileError {
+            message: message.into(),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Identifier(String),
+    Operator(BinaryOp),
+    LeftParen,
+    RightParen,
+    Comma,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "number, {}", n),
+            Token::Identifier(name) => write!(f, "identifier, \"{}\"", name),
+            Token::Operator(op) => write!(f, "operator, {:?}", op),
+            Token::LeftParen => write!(f, "\"(\""),
+            Token::RightParen => write!(f, "\")\""),
+            Token::Comma => write!(f, "\",\""),
+        }
+    }
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, CompileError> {
+    let mut tokens = Vec::new();
+    let mut chars = src.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            ',' => Token::Comma,
+            '+' => Token::Operator(BinaryOp::Add),
+            '-' => Token::Operator(BinaryOp::Subtract),
+            '*' => Token::Operator(BinaryOp::Multiply),
+            '/' => Token::Operator(BinaryOp::Divide),
+            '%' => Token::Operator(BinaryOp::Remainder),
+            '^' => Token::Operator(BinaryOp::Power),
+            '<' | '>' | '=' | '!' => {
+                let followed_by_equals =
+                    chars.next_if(|&(_, c)| c == '=').is_some();
+
+                let op = match (c, followed_by_equals) {
+                    ('<', false) => BinaryOp::Less,
+                    ('<', true) => BinaryOp::LessEqual,
+                    ('>', false) => BinaryOp::Greater,
+                    ('>', true) => BinaryOp::GreaterEqual,
+                    ('=', true) => BinaryOp::Equal,
+                    ('!', true) => BinaryOp::NotEqual,
+                    _ => {
+                        return Err(CompileError::new(format!(
+                            "Expected \"{}=\" at position {}",
+                            c, start
+                        )))
+                    },
+                };
+                Token::Operator(op)
+            },
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) =
+                    chars.next_if(|&(_, c)| c.is_ascii_digit() || c == '.')
+                {
+                    end = i + c.len_utf8();
+                }
+
+                let text = &src[start..end];
+                let number = text.parse().map_err(|_| {
+                    CompileError::new(format!("Invalid number, \"{}\"", text))
+                })?;
+                Token::Number(number)
+            },
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) = chars
+                    .next_if(|&(_, c)| c.is_ascii_alphanumeric() || c == '_')
+                {
+                    end = i + c.len_utf8();
+                }
+
+                Token::Identifier(src[start..end].to_string())
+            },
+            other => {
+                return Err(CompileError::new(format!(
+                    "Unexpected character, '{}', at position {}",
+                    other, start
+                )))
+            },
+        };
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// A Pratt parser which emits bytecode in reverse Polish notation as it
+/// goes.
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    ops: Vec<Op>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> { self.tokens.get(self.position) }
+
+    fn advance(&mut self) -> Option<&'a Token> {
+        let token = self.peek();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), CompileError> {
+        match self.advance() {
+            Some(token) if *token == expected => Ok(()),
+            Some(token) => Err(CompileError::new(format!(
+                "Expected {} but found {}",
+                expected, token
+            ))),
+            None => Err(CompileError::new(format!(
+                "Expected {} but reached the end of the expression",
+                expected
+            ))),
+        }
+    }
+
+    fn expression(
+        &mut self,
+        min_binding_power: u8,
+        depth: usize,
+    ) -> Result<(), CompileError> {
+        if depth > MAX_DEPTH {
+            return Err(CompileError::new(
+                "The expression is nested too deeply",
+            ));
+        }
+
+        self.prefix(depth)?;
+
+        while let Some(Token::Operator(op)) = self.peek() {
+            let (left, right) = op.binding_power();
+            if left < min_binding_power {
+                break;
+            }
+
+            self.position += 1;
+            self.expression(right, depth + 1)?;
+            self.ops.push(Op::Binary(*op));
+        }
+
+        Ok(())
+    }
+
+    fn prefix(&mut self, depth: usize) -> Result<(), CompileError> {
+        match self.advance() {
+            Some(Token::Number(n)) => self.ops.push(Op::Constant(*n)),
+            Some(Token::Operator(BinaryOp::Subtract)) => {
+                self.expression(PREFIX_BINDING_POWER, depth + 1)?;
+                self.ops.push(Op::Negate);
+            },
+            Some(Token::Operator(BinaryOp::Add)) => {
+                self.expression(PREFIX_BINDING_POWER, depth + 1)?;
+            },
+            Some(Token::LeftParen) => {
+                self.expression(0, depth + 1)?;
+                self.expect(Token::RightParen)?;
+            },
+            Some(Token::Identifier(name)) => {
+                if self.peek() == Some(&Token::LeftParen) {
+                    self.call(name, depth)?;
+                } else {
+                    self.ops.push(identifier(name)?);
+                }
+            },
+            Some(token) => {
+                return Err(CompileError::new(format!(
+                    "Expected a value but found {}",
+                    token
+                )))
+            },
+            None => {
+                return Err(CompileError::new(
+                    "Expected a value but reached the end of the expression",
+                ))
+            },
+        }
+
+        Ok(())
+    }
+
+    fn call(&mut self, name: &str, depth: usize) -> Result<(), CompileError> {
+        let function = Function::from_name(name).ok_or_else(|| {
+            CompileError::new(format!("Unknown function, \"{}\"", name))
+        })?;
+
+        self.expect(Token::LeftParen)?;
+
+        let mut arguments = 0;
+
+        if self.peek() != Some(&Token::RightParen) {
+            loop {
+                self.expression(0, depth + 1)?;
+                arguments += 1;
+
+                if self.peek() == Some(&Token::Comma) {
+                    self.position += 1;
+                } else {
+                    break;
+                }
+            }
+        }
+
+        self.expect(Token::RightParen)?;
+
+        if arguments != function.arity() {
+            return Err(CompileError::new(format!(
+                "\"{}\" expects {} arguments, but it was given {}",
+                name,
+                function.arity(),
+                arguments
+            )));
+        }
+
+        self.ops.push(Op::Call(function));
+
+        Ok(())
+    }
+}
+
+fn identifier(name: &str) -> Result<Op, CompileError> {
+    if let Some(variable) = Variable::from_name(name) {
+        return Ok(Op::Load(variable));
+    }
+
+    match name {
+        "pi" => Ok(Op::Constant(std::f64::consts::PI)),
+        "e" => Ok(Op::Constant(std::f64::consts::E)),
+        _ => Err(CompileError::new(format!("Unknown variable, \"{}\"", name))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(src: &str, env: Environment) -> f64 {
+        let program = Program::compile(src).unwrap();
+        program.evaluate(&env, &mut Vec::new())
+    }
+
+    fn element(x: f64) -> Environment {
+        Environment {
+            element: x,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn operator_precedence() {
+        let inputs = [
+            ("1 + 2 * 3", 7.0),
+            ("(1 + 2) * 3", 9.0),
+            ("2 ^ 3 ^ 2", 512.0),
+            ("-2 ^ 2", -4.0),
+            ("10 - 4 - 3", 3.0),
+            ("7 % 4 + 1", 4.0),
+            ("1 + 1 == 2", 1.0),
+            ("3 < 2", 0.0),
+            ("2 >= 2", 1.0),
+            ("1 != 1", 0.0),
+        ];
+
+        for (src, expected) in inputs {
+            let got = evaluate(src, Environment::default());
+            assert_eq!(got, expected, "{}", src);
+        }
+    }
+
+    #[test]
+    fn variables_and_functions() {
+        let env = Environment {
+            element: -4.0,
+            index: 2.0,
+            length: 10.0,
+            accumulator: 1.5,
+        };
+
+        assert_eq!(evaluate("abs(x) + i * n", env), 24.0);
+        assert_eq!(evaluate("acc + sqrt(abs(x))", env), 3.5);
+        assert_eq!(evaluate("clamp(x, -1, 1)", env), -1.0);
+        assert_eq!(evaluate("if(x < 0, 0, x)", env), 0.0);
+        assert_eq!(evaluate("max(min(x, 3), -2.5)", env), -2.5);
+        assert_eq!(evaluate("round(pi * 100) / 100", env), 3.14);
+    }
+
+    #[test]
+    fn the_stack_is_reused_between_calls() {
+        let program = Program::compile("x * 2").unwrap();
+        let mut stack = Vec::new();
+
+        assert_eq!(program.evaluate(&element(1.0), &mut stack), 2.0);
+        assert_eq!(program.evaluate(&element(3.0), &mut stack), 6.0);
+    }
+
+    #[test]
+    fn detect_variable_usage() {
+        let program = Program::compile("acc + x").unwrap();
+
+        assert!(program.uses(Variable::Accumulator));
+        assert!(program.uses(Variable::Element));
+        assert!(!program.uses(Variable::Index));
+    }
+
+    #[test]
+    fn invalid_expressions() {
+        let inputs = [
+            "",
+            "1 +",
+            "(1 + 2",
+            "1 2",
+            "foo",
+            "foo(1)",
+            "min(1)",
+            "x = 1",
+            "1.2.3",
+            "x $ 2",
+        ];
+
+        for src in inputs {
+            assert!(Program::compile(src).is_err(), "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn deeply_nested_expressions_are_rejected() {
+        let src = format!("{}x{}", "(".repeat(100), ")".repeat(100));
+
+        assert!(Program::compile(&src).is_err());
+    }
+}
@@ -0,0 +1,379 @@
+//! Run a small, user-provided expression against a tensor.
+//!
+//! This is a middle ground for custom transforms which are too small to
+//! justify compiling a new proc-block. The expression is compiled once and
+//! then evaluated by a tiny stack machine (see [`expr`]), so it can only do
+//! arithmetic on the values it is given.
+//!
+//! In `map` mode, the expression is evaluated for each element and the
+//! results have the same shape as the input. In `reduce` mode, the result of
+//! each evaluation is passed to the next as `acc`, and only the final value
+//! is returned.
+//!
+//! Expressions can use the following variables:
+//!
+//! - `x` - the current element
+//! - `i` - the current element's index
+//! - `n` - the number of elements
+//! - `acc` - the accumulated value (`reduce` mode only)
+//!
+//! As well as the `+`, `-`, `*`, `/`, `%`, `^`, `<`, `<=`, `>`, `>=`, `==`,
+//! and `!=` operators, the constants `pi` and `e`, and the `abs`, `sqrt`,
+//! `exp`, `ln`, `log10`, `sin`, `cos`, `tan`, `tanh`, `floor`, `ceil`,
+//! `round`, `min`, `max`, `clamp`, and `if` functions.
+
+mod expr;
+
+use crate::{
+    expr::{Environment, Program, Variable},
+    proc_block_v1::{
+        BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+        InvalidInput, KernelError,
+    },
+};
+use hotg_rune_proc_blocks::{
+    common::element_type, prelude::*, runtime_v1::*, BufferExt, SliceExt,
+    ValueType,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata = Metadata::new("Script", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("numeric");
+        metadata.add_tag("script");
+
+        let expression = ArgumentMetadata::new("expression");
+        expression.set_description(
+            "The expression to evaluate (e.g. `clamp(x * 2, 0, 1)`). It can refer to the current element (`x`), its index (`i`), the number of elements (`n`), and in `reduce` mode, the accumulated value (`acc`).",
+        );
+        expression.add_hint(&supported_argument_type(ArgumentType::LongString));
+        metadata.add_argument(&expression);
+
+        let mode = ArgumentMetadata::new("mode");
+        mode.set_description(
+            "Whether to `map` each element to a new value or `reduce` the tensor to a single value.",
+        );
+        mode.set_default_value("map");
+        mode.add_hint(&interpret_as_string_in_enum(&["map", "reduce"]));
+        metadata.add_argument(&mode);
+
+        let initial = ArgumentMetadata::new("initial");
+        initial.set_description(
+            "The value `acc` starts with when reducing (e.g. `0` for a sum or `1` for a product).",
+        );
+        initial.set_default_value("0");
+        initial.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&initial);
+
+        let element_type = ArgumentMetadata::new("element_type");
+        element_type.set_description("The type of the input's elements.");
+        element_type.set_default_value("f32");
+        element_type
+            .add_hint(&interpret_as_string_in_enum(element_type::FLOAT));
+        metadata.add_argument(&element_type);
+
+        let input = TensorMetadata::new("input");
+        input.set_description("The values to pass to the expression.");
+        let hint = supported_shapes(
+            &[ElementType::F32, ElementType::F64],
+            DimensionsParam::Dynamic,
+        );
+        input.add_hint(&hint);
+        metadata.add_input(&input);
+
+        let output = TensorMetadata::new("output");
+        output.set_description(
+            "The result of each evaluation when mapping, or a single value when reducing.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32, ElementType::F64],
+            DimensionsParam::Dynamic,
+        );
+        output.add_hint(&hint);
+        metadata.add_output(&output);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, GraphError>(&ctx)?;
+        let element_type = ctx.element_type::<GraphError>(
+            ElementType::F32,
+            element_type::FLOAT,
+        )?;
+
+        ctx.add_input_tensor("input", element_type, DimensionsParam::Dynamic);
+
+        match settings.mode {
+            Mode::Map => ctx.add_output_tensor(
+                "output",
+                element_type,
+                DimensionsParam::Dynamic,
+            ),
+            Mode::Reduce => ctx.add_output_tensor(
+                "output",
+                element_type,
+                DimensionsParam::Fixed(&[1]),
+            ),
+        }
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("input").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "input".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        let output_dimensions = match settings.mode {
+            Mode::Map => dimensions.clone(),
+            Mode::Reduce => vec![1],
+        };
+
+        match element_type {
+            ElementType::F32 => {
+                let input = elements::<f32>(&buffer, &dimensions)?
+                    .iter()
+                    .map(|&x| f64::from(x));
+                let output: Vec<f32> =
+                    settings.run(input).into_iter().map(|x| x as f32).collect();
+
+                ctx.set_output_tensor(
+                    "output",
+                    TensorParam {
+                        element_type,
+                        dimensions: &output_dimensions,
+                        buffer: output.as_bytes(),
+                    },
+                );
+            },
+            ElementType::F64 => {
+                let input =
+                    elements::<f64>(&buffer, &dimensions)?.iter().copied();
+                let output = settings.run(input);
+
+                ctx.set_output_tensor(
+                    "output",
+                    TensorParam {
+                        element_type,
+                        dimensions: &output_dimensions,
+                        buffer: output.as_bytes(),
+                    },
+                );
+            },
+            other => {
+                return Err(KernelError::InvalidInput(InvalidInput {
+                    name: "input".to_string(),
+                    reason: BadInputReason::InvalidValue(format!(
+                        "Expected f32 or f64, found {}",
+                        other
+                    )),
+                }))
+            },
+        }
+
+        Ok(())
+    }
+}
+
+fn elements<'a, T: ValueType>(
+    buffer: &'a [u8],
+    dimensions: &[u32],
+) -> Result<&'a [T], KernelError> {
+    buffer.try_elements_with_shape(dimensions).map_err(|e| {
+        KernelError::InvalidInput(InvalidInput {
+            name: "input".to_string(),
+            reason: BadInputReason::InvalidValue(e.to_string()),
+        })
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Mode {
+    Map,
+    Reduce,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Settings {
+    program: Program,
+    mode: Mode,
+    initial: f64,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let expression = ctx.required_argument::<E>("expression")?;
+        let mode = ctx.parse_argument_with_default::<_, E>(
+            "mode",
+            String::from("map"),
+        )?;
+        let initial = ctx.parse_argument_with_default::<_, E>("initial", 0.0)?;
+
+        let program = Program::compile(&expression).map_err(|e| {
+            E::invalid_argument(InvalidArgument::invalid_value(
+                "expression",
+                e,
+            ))
+        })?;
+
+        let mode = match mode.as_str() {
+            "map" => Mode::Map,
+            "reduce" => Mode::Reduce,
+            other => {
+                return Err(E::invalid_argument(
+                    InvalidArgument::invalid_value(
+                        "mode",
+                        format!("Unknown mode, \"{}\"", other),
+                    ),
+                ))
+            },
+        };
+
+        if mode == Mode::Map && program.uses(Variable::Accumulator) {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "expression",
+                "\"acc\" can only be used in reduce mode",
+            )));
+        }
+
+        Ok(Settings {
+            program,
+            mode,
+            initial,
+        })
+    }
+
+    fn run(&self, input: impl ExactSizeIterator<Item = f64>) -> Vec<f64> {
+        let mut env = Environment {
+            length: input.len() as f64,
+            accumulator: self.initial,
+            ..Default::default()
+        };
+        let mut stack = Vec::new();
+
+        match self.mode {
+            Mode::Map => input
+                .enumerate()
+                .map(|(i, x)| {
+                    env.element = x;
+                    env.index = i as f64;
+                    self.program.evaluate(&env, &mut stack)
+                })
+                .collect(),
+            Mode::Reduce => {
+                for (i, x) in input.enumerate() {
+                    env.element = x;
+                    env.index = i as f64;
+                    env.accumulator = self.program.evaluate(&env, &mut stack);
+                }
+
+                vec![env.accumulator]
+            },
+        }
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(expression: &str, mode: Mode, initial: f64) -> Settings {
+        Settings {
+            program: Program::compile(expression).unwrap(),
+            mode,
+            initial,
+        }
+    }
+
+    #[test]
+    fn map_each_element() {
+        let settings = settings("x * i + n", Mode::Map, 0.0);
+
+        let output = settings.run([1.0, 2.0, 3.0].into_iter());
+
+        assert_eq!(output, vec![3.0, 5.0, 9.0]);
+    }
+
+    #[test]
+    fn reduce_to_a_product() {
+        let settings = settings("acc * x", Mode::Reduce, 1.0);
+
+        let output = settings.run([2.0, 3.0, 4.0].into_iter());
+
+        assert_eq!(output, vec![24.0]);
+    }
+
+    #[test]
+    fn reducing_nothing_gives_the_initial_value() {
+        let settings = settings("max(acc, x)", Mode::Reduce, -1.0);
+
+        let output = settings.run(std::iter::empty());
+
+        assert_eq!(output, vec![-1.0]);
+    }
+}
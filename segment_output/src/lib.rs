@@ -31,7 +31,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         metadata.add_tag("image");
         metadata.add_tag("segmentation");
 
-        let input = TensorMetadata::new("image");
+        let input = TensorMetadata::new("input");
         input.set_description("An image-like tensor with the dimensions, `[1, rows, columns, category_confidence]`. Each \"pixel\" is associated with a set of confidence values, where each value indicates how confident the model is that the pixel is in that category.");
         let hint = supported_shapes(
             &[ElementType::F32],
@@ -100,8 +100,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
 
         let (segmented_map, indices) = match element_type {
             ElementType::F32 => {
-                let tensor =buffer.view::<f32>(&dimensions)
-                .and_then(|t| t.into_dimensionality())
+                let tensor =buffer.try_view::<f32, _>(&dimensions)
                 .map_err(|e| KernelError::InvalidInput(InvalidInput{ name: "input".to_string(), reason: BadInputReason::InvalidValue(e.to_string()) }))?;
                 transform(tensor)
             },
@@ -0,0 +1,651 @@
+//! Make predictions with a smartcore model which was trained elsewhere and
+//! serialized as JSON (e.g. the `model` output from the `decision_tree`,
+//! `linear_regression`, and `elastic_net` proc-blocks).
+//!
+//! JSON doesn't say which type of model it contains, so this must be set
+//! with the `model_type` argument. Support vector machines are also generic
+//! over their kernel function, so `svc` and `svr` models need the `kernel`
+//! argument too. The kernel's parameters (`gamma`, `degree`, etc.) are part
+//! of the serialized model and don't need to be provided again.
+//!
+//! The samples in `x_test` must have the same number of features as the
+//! data the model was trained with, otherwise the kernel fails with an
+//! invalid `x_test` input.
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use smartcore::{
+    ensemble::{
+        random_forest_classifier::RandomForestClassifier,
+        random_forest_regressor::RandomForestRegressor,
+    },
+    linalg::naive::dense_matrix::DenseMatrix,
+    linear::{
+        elastic_net::ElasticNet, lasso::Lasso,
+        linear_regression::LinearRegression,
+        logistic_regression::LogisticRegression,
+        ridge_regression::RidgeRegression,
+    },
+    svm::{
+        svc::SVC, svr::SVR, LinearKernel, PolynomialKernel, RBFKernel,
+        SigmoidKernel,
+    },
+    tree::decision_tree_classifier::DecisionTreeClassifier,
+};
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::ArrayView2, prelude::*, runtime_v1::*, BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        ensure_initialized();
+
+        let metadata =
+            Metadata::new("Smartcore Inference", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("inference");
+        metadata.add_tag("analytics");
+
+        let model_type = ArgumentMetadata::new("model_type");
+        model_type.set_description(
+            "The type of model that was serialized. This must match the proc-block that trained it.",
+        );
+        model_type.add_hint(&interpret_as_string_in_enum(ModelType::NAMES));
+        metadata.add_argument(&model_type);
+
+        let kernel = ArgumentMetadata::new("kernel");
+        kernel.set_description(
+            "The kernel function used by `svc` and `svr` models. Ignored for every other model type.",
+        );
+        kernel.set_default_value("linear");
+        kernel.add_hint(&interpret_as_string_in_enum(SvmKernel::NAMES));
+        metadata.add_argument(&kernel);
+
+        let model = TensorMetadata::new("model");
+        model.set_description("The trained model, serialized as JSON.");
+        let hint = supported_shapes(
+            &[ElementType::Utf8],
+            DimensionsParam::Fixed(&[1]),
+        );
+        model.add_hint(&hint);
+        metadata.add_input(&model);
+
+        let x_test = TensorMetadata::new("x_test");
+        x_test.set_description("The samples to make predictions for.");
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        x_test.add_hint(&hint);
+        metadata.add_input(&x_test);
+
+        let y_test = TensorMetadata::new("y_test");
+        y_test.set_description("The prediction for each row in `x_test`.");
+        let hint =
+            supported_shapes(&[ElementType::F64], DimensionsParam::Fixed(&[0]));
+        y_test.add_hint(&hint);
+        metadata.add_output(&y_test);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        ensure_initialized();
+
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = ModelType::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "model",
+            ElementType::Utf8,
+            DimensionsParam::Fixed(&[1]),
+        );
+        ctx.add_input_tensor(
+            "x_test",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "y_test",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        ensure_initialized();
+
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let model_type = ModelType::from_context::<_, KernelError>(&ctx)?;
+
+        let model = get_input(&ctx, "model", ElementType::Utf8)?;
+        let model = match model.buffer.strings() {
+            Ok(strings) if strings.len() == 1 => strings[0],
+            Ok(strings) => {
+                return Err(invalid_input(
+                    "model",
+                    format!(
+                        "Expected a single serialized model, found {}",
+                        strings.len()
+                    ),
+                ))
+            },
+            Err(e) => return Err(invalid_input("model", e)),
+        };
+
+        let x_test = get_input(&ctx, "x_test", ElementType::F64)?;
+        let x_test: ArrayView2<f64> = x_test
+            .buffer
+            .try_view(&x_test.dimensions)
+            .map_err(|e| invalid_input("x_test", e))?;
+
+        let y_test = predict(model_type, model, x_test)?;
+
+        ctx.set_output_tensor(
+            "y_test",
+            TensorParam {
+                element_type: ElementType::F64,
+                dimensions: &[y_test.len() as u32],
+                buffer: y_test.as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(name: &str, reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: name.to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+fn get_input(
+    ctx: &KernelContext,
+    name: &str,
+    element_type: ElementType,
+) -> Result<TensorResult, KernelError> {
+    let tensor = ctx.get_input_tensor(name).ok_or_else(|| {
+        KernelError::InvalidInput(InvalidInput {
+            name: name.to_string(),
+            reason: BadInputReason::NotFound,
+        })
+    })?;
+
+    if tensor.element_type != element_type {
+        return Err(invalid_input(
+            name,
+            format!(
+                "Expected a {} tensor, found {}",
+                element_type, tensor.element_type
+            ),
+        ));
+    }
+
+    Ok(tensor)
+}
+
+/// The kind of model being deserialized, as set by the `model_type` and
+/// `kernel` arguments.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ModelType {
+    Linear,
+    Ridge,
+    Lasso,
+    ElasticNet,
+    Logistic,
+    DecisionTree,
+    RandomForestClassifier,
+    RandomForestRegressor,
+    Svc(SvmKernel),
+    Svr(SvmKernel),
+}
+
+impl ModelType {
+    pub const NAMES: &'static [&'static str] = &[
+        "linear",
+        "ridge",
+        "lasso",
+        "elastic_net",
+        "logistic",
+        "decision_tree",
+        "random_forest_classifier",
+        "random_forest_regressor",
+        "svc",
+        "svr",
+    ];
+
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let name = ctx.required_argument::<E>("model_type")?;
+
+        match name.as_str() {
+            "linear" => Ok(ModelType::Linear),
+            "ridge" => Ok(ModelType::Ridge),
+            "lasso" => Ok(ModelType::Lasso),
+            "elastic_net" => Ok(ModelType::ElasticNet),
+            "logistic" => Ok(ModelType::Logistic),
+            "decision_tree" => Ok(ModelType::DecisionTree),
+            "random_forest_classifier" => {
+                Ok(ModelType::RandomForestClassifier)
+            },
+            "random_forest_regressor" => Ok(ModelType::RandomForestRegressor),
+            "svc" => SvmKernel::from_context(ctx).map(ModelType::Svc),
+            "svr" => SvmKernel::from_context(ctx).map(ModelType::Svr),
+            _ => Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "model_type",
+                format!(
+                    "Unknown model type, \"{}\". Expected one of {:?}",
+                    name,
+                    ModelType::NAMES
+                ),
+            ))),
+        }
+    }
+}
+
+/// The kernel function a support vector machine was trained with.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SvmKernel {
+    Linear,
+    Rbf,
+    Polynomial,
+    Sigmoid,
+}
+
+impl SvmKernel {
+    pub const NAMES: &'static [&'static str] =
+        &["linear", "rbf", "polynomial", "sigmoid"];
+
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let name = ctx.parse_argument_with_default::<_, E>(
+            "kernel",
+            String::from("linear"),
+        )?;
+
+        match name.as_str() {
+            "linear" => Ok(SvmKernel::Linear),
+            "rbf" => Ok(SvmKernel::Rbf),
+            "polynomial" => Ok(SvmKernel::Polynomial),
+            "sigmoid" => Ok(SvmKernel::Sigmoid),
+            _ => Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "kernel",
+                format!(
+                    "Unknown kernel, \"{}\". Expected one of {:?}",
+                    name,
+                    SvmKernel::NAMES
+                ),
+            ))),
+        }
+    }
+}
+
+type Matrix = DenseMatrix<f64>;
+
+/// Deserialize a model and use it to make a prediction for each row in
+/// `x_test`.
+pub fn predict(
+    model_type: ModelType,
+    model: &str,
+    x_test: ArrayView2<'_, f64>,
+) -> Result<Vec<f64>, KernelError> {
+    let model: Value =
+        serde_json::from_str(model).map_err(|e| invalid_input("model", e))?;
+    check_features(model_type, &model, x_test.ncols())?;
+
+    let model = &model;
+    let x = dense_matrix(x_test);
+
+    match model_type {
+        ModelType::Linear => {
+            deserialize::<LinearRegression<f64, Matrix>>(model)?.predict(&x)
+        },
+        ModelType::Ridge => {
+            deserialize::<RidgeRegression<f64, Matrix>>(model)?.predict(&x)
+        },
+        ModelType::Lasso => {
+            deserialize::<Lasso<f64, Matrix>>(model)?.predict(&x)
+        },
+        ModelType::ElasticNet => {
+            deserialize::<ElasticNet<f64, Matrix>>(model)?.predict(&x)
+        },
+        ModelType::Logistic => {
+            deserialize::<LogisticRegression<f64, Matrix>>(model)?.predict(&x)
+        },
+        ModelType::DecisionTree => {
+            deserialize::<DecisionTreeClassifier<f64>>(model)?.predict(&x)
+        },
+        ModelType::RandomForestClassifier => {
+            deserialize::<RandomForestClassifier<f64>>(model)?.predict(&x)
+        },
+        ModelType::RandomForestRegressor => {
+            deserialize::<RandomForestRegressor<f64>>(model)?.predict(&x)
+        },
+        ModelType::Svc(SvmKernel::Linear) => {
+            deserialize::<SVC<f64, Matrix, LinearKernel>>(model)?.predict(&x)
+        },
+        ModelType::Svc(SvmKernel::Rbf) => {
+            deserialize::<SVC<f64, Matrix, RBFKernel<f64>>>(model)?
+                .predict(&x)
+        },
+        ModelType::Svc(SvmKernel::Polynomial) => {
+            deserialize::<SVC<f64, Matrix, PolynomialKernel<f64>>>(model)?
+                .predict(&x)
+        },
+        ModelType::Svc(SvmKernel::Sigmoid) => {
+            deserialize::<SVC<f64, Matrix, SigmoidKernel<f64>>>(model)?
+                .predict(&x)
+        },
+        ModelType::Svr(SvmKernel::Linear) => {
+            deserialize::<SVR<f64, Matrix, LinearKernel>>(model)?.predict(&x)
+        },
+        ModelType::Svr(SvmKernel::Rbf) => {
+            deserialize::<SVR<f64, Matrix, RBFKernel<f64>>>(model)?
+                .predict(&x)
+        },
+        ModelType::Svr(SvmKernel::Polynomial) => {
+            deserialize::<SVR<f64, Matrix, PolynomialKernel<f64>>>(model)?
+                .predict(&x)
+        },
+        ModelType::Svr(SvmKernel::Sigmoid) => {
+            deserialize::<SVR<f64, Matrix, SigmoidKernel<f64>>>(model)?
+                .predict(&x)
+        },
+    }
+    .map_err(|e| KernelError::Other(e.to_string()))
+}
+
+fn deserialize<T: DeserializeOwned>(model: &Value) -> Result<T, KernelError> {
+    T::deserialize(model).map_err(|e| invalid_input("model", e))
+}
+
+/// Make sure `x_test` has as many features as the model was trained with.
+///
+/// smartcore doesn't check this itself and will panic with an out-of-bounds
+/// index instead, so we need to look at the serialized model. Trees only
+/// record the features they split on, so there we can only check that
+/// `x_test` has at least that many.
+fn check_features(
+    model_type: ModelType,
+    model: &Value,
+    features: usize,
+) -> Result<(), KernelError> {
+    let (expected, exact) = match model_type {
+        ModelType::Linear
+        | ModelType::Ridge
+        | ModelType::Lasso
+        | ModelType::ElasticNet => {
+            (model["coefficients"]["nrows"].as_u64(), true)
+        },
+        ModelType::Logistic => (model["coefficients"]["ncols"].as_u64(), true),
+        ModelType::Svc(_) | ModelType::Svr(_) => (
+            model["instances"][0].as_array().map(|row| row.len() as u64),
+            true,
+        ),
+        ModelType::DecisionTree => (split_features(model), false),
+        ModelType::RandomForestClassifier
+        | ModelType::RandomForestRegressor => (
+            model["trees"].as_array().and_then(|trees| {
+                trees.iter().filter_map(split_features).max()
+            }),
+            false,
+        ),
+    };
+
+    // Leave anything we don't recognise for deserializing to complain about
+    let expected = match expected {
+        Some(expected) => expected as usize,
+        None => return Ok(()),
+    };
+
+    if features == expected || (!exact && features > expected) {
+        return Ok(());
+    }
+
+    Err(invalid_input(
+        "x_test",
+        format!(
+            "The model expects {}{} features, but the samples have {}",
+            if exact { "" } else { "at least " },
+            expected,
+            features
+        ),
+    ))
+}
+
+/// The number of features a serialized decision tree needs, based on the
+/// highest feature any of its branches split on.
+fn split_features(tree: &Value) -> Option<u64> {
+    let nodes = tree["nodes"].as_array()?;
+
+    Some(
+        nodes
+            .iter()
+            .filter(|node| !node["split_value"].is_null())
+            .filter_map(|node| node["split_feature"].as_u64())
+            .map(|feature| feature + 1)
+            .max()
+            .unwrap_or(0),
+    )
+}
+
+fn dense_matrix(x: ArrayView2<'_, f64>) -> DenseMatrix<f64> {
+    let values: Vec<f64> = x.iter().copied().collect();
+    DenseMatrix::from_array(x.nrows(), x.ncols(), &values)
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::ndarray::Array2;
+    use smartcore::{
+        linear::linear_regression::LinearRegressionParameters,
+        svm::{svc::SVCParameters, Kernels},
+    };
+
+    /// The first 5 setosa (`0`) and versicolor (`1`) samples from the iris
+    /// dataset.
+    fn iris() -> (Array2<f64>, Vec<f64>) {
+        #[rustfmt::skip]
+        let x = vec![
+            5.1, 3.5, 1.4, 0.2,
+            4.9, 3.0, 1.4, 0.2,
+            4.7, 3.2, 1.3, 0.2,
+            4.6, 3.1, 1.5, 0.2,
+            5.0, 3.6, 1.4, 0.2,
+            7.0, 3.2, 4.7, 1.4,
+            6.4, 3.2, 4.5, 1.5,
+            6.9, 3.1, 4.9, 1.5,
+            5.5, 2.3, 4.0, 1.3,
+            6.5, 2.8, 4.6, 1.5,
+        ];
+        let y = vec![0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+
+        (Array2::from_shape_vec((10, 4), x).unwrap(), y)
+    }
+
+    #[test]
+    fn linear_regression() {
+        let x = Array2::from_shape_vec((4, 1), vec![1.0, 2.0, 3.0, 4.0])
+            .unwrap();
+        let y = vec![3.0, 5.0, 7.0, 9.0];
+        let model = LinearRegression::fit(
+            &dense_matrix(x.view()),
+            &y,
+            LinearRegressionParameters::default(),
+        )
+        .unwrap();
+        let json = serde_json::to_string(&model).unwrap();
+        let x_test = Array2::from_shape_vec((2, 1), vec![0.0, 10.0]).unwrap();
+
+        let got = predict(ModelType::Linear, &json, x_test.view()).unwrap();
+
+        assert_eq!(got.len(), 2);
+        assert!((got[0] - 1.0).abs() < 1e-6);
+        assert!((got[1] - 21.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn decision_tree() {
+        let (x, y) = iris();
+        let model = DecisionTreeClassifier::fit(
+            &dense_matrix(x.view()),
+            &y,
+            Default::default(),
+        )
+        .unwrap();
+        let json = serde_json::to_string(&model).unwrap();
+
+        let got = predict(ModelType::DecisionTree, &json, x.view()).unwrap();
+
+        assert_eq!(got, y);
+    }
+
+    #[test]
+    fn svc_with_an_rbf_kernel() {
+        let (x, y) = iris();
+        let x_train = dense_matrix(x.view());
+        let parameters =
+            SVCParameters::default().with_kernel(Kernels::rbf(0.25));
+        let model = SVC::fit(&x_train, &y, parameters).unwrap();
+        let json = serde_json::to_string(&model).unwrap();
+        let model_type = ModelType::Svc(SvmKernel::Rbf);
+
+        let got = predict(model_type, &json, x.view()).unwrap();
+
+        assert_eq!(got, model.predict(&x_train).unwrap());
+    }
+
+    #[test]
+    fn the_model_type_must_match() {
+        let (x, y) = iris();
+        let model = DecisionTreeClassifier::fit(
+            &dense_matrix(x.view()),
+            &y,
+            Default::default(),
+        )
+        .unwrap();
+        let json = serde_json::to_string(&model).unwrap();
+
+        assert!(predict(ModelType::Logistic, &json, x.view()).is_err());
+    }
+
+    #[test]
+    fn x_test_must_have_as_many_features_as_the_model() {
+        let (x, y) = iris();
+        let x_train = dense_matrix(x.view());
+        let linear =
+            LinearRegression::fit(&x_train, &y, Default::default()).unwrap();
+        let svc = SVC::fit(&x_train, &y, SVCParameters::default()).unwrap();
+        let models = [
+            (ModelType::Linear, serde_json::to_string(&linear).unwrap()),
+            (
+                ModelType::Svc(SvmKernel::Linear),
+                serde_json::to_string(&svc).unwrap(),
+            ),
+        ];
+        let x_test = Array2::zeros((1, 3));
+
+        for (model_type, json) in &models {
+            let err = predict(*model_type, json, x_test.view()).unwrap_err();
+
+            match err {
+                KernelError::InvalidInput(InvalidInput { name, .. }) => {
+                    assert_eq!(name, "x_test")
+                },
+                other => panic!("{:?}: {:?}", model_type, other),
+            }
+        }
+    }
+
+    #[test]
+    fn trees_need_every_feature_they_split_on() {
+        let (x, y) = iris();
+        let model = DecisionTreeClassifier::fit(
+            &dense_matrix(x.view()),
+            &y,
+            Default::default(),
+        )
+        .unwrap();
+        let json = serde_json::to_string(&model).unwrap();
+        let x_test = Array2::zeros((1, 0));
+
+        let err =
+            predict(ModelType::DecisionTree, &json, x_test.view()).unwrap_err();
+
+        assert!(matches!(err, KernelError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn invalid_json_is_rejected() {
+        let (x, _) = iris();
+
+        let err = predict(ModelType::Linear, "{", x.view()).unwrap_err();
+
+        assert!(matches!(err, KernelError::InvalidInput(_)));
+    }
+}
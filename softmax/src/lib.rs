@@ -1,44 +1,63 @@
-use crate::proc_block_v1::{
-    BadInputReason, GraphError, InvalidInput, KernelError,
-};
+//! Find the normalised exponential function (softmax) of a vector, or of
+//! each lane of a tensor along a particular axis.
+//!
+//! With the `half` feature enabled, setting `element_type` to `f16` or `bf16`
+//! lets the proc-block accept half-precision values stored as raw bits in a
+//! `u16` tensor. They are widened to `f32` for the calculation and the output
+//! uses the same format as the input.
+
+use std::fmt::Display;
 
+#[cfg(feature = "half")]
+use hotg_rune_proc_blocks::half_precision::HalfPrecision;
 use hotg_rune_proc_blocks::{
-    ndarray::ArrayViewMut1, runtime_v1::*, BufferExt, ValueType,
+    common::element_type, ndarray::Axis, runtime_v1::*, BufferExt, Cost,
+    ResourceLimit, ValueType,
+};
+use num_traits::{Float, FromPrimitive};
+use softmax_core::{log_softmax_along_axis, softmax_along_axis};
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
 };
-use num_traits::Float;
 
 wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
 
 struct ProcBlockV1;
 
-fn softmax<T>(mut input: ArrayViewMut1<'_, T>)
+fn softmax_in_place<T>(
+    buffer: &mut [u8],
+    dimensions: &[u32],
+    mode: Mode,
+    axis: isize,
+    temperature: f64,
+) -> Result<(), KernelError>
 where
-    T: Float + num_traits::FromPrimitive,
+    T: ValueType + Float + FromPrimitive,
 {
-    input.mapv_inplace(|x| x.exp());
+    let tensor = buffer.view_mut::<T>(dimensions).map_err(|e| {
+        KernelError::InvalidInput(InvalidInput {
+            name: "input".to_string(),
+            reason: BadInputReason::InvalidValue(e.to_string()),
+        })
+    })?;
+
+    let axis = resolve_axis(axis, tensor.ndim())
+        .map_err(KernelError::InvalidArgument)?;
+    let temperature = T::from_f64(temperature).ok_or_else(|| {
+        KernelError::InvalidArgument(InvalidArgument::invalid_value(
+            "temperature",
+            "Out of range",
+        ))
+    })?;
 
-    let sum = input.sum();
-    if !sum.is_zero() {
-        input.mapv_inplace(|x| x / sum);
+    match mode {
+        Mode::Softmax => softmax_along_axis(tensor, axis, temperature),
+        Mode::LogSoftmax => log_softmax_along_axis(tensor, axis, temperature),
     }
-}
 
-fn preprocess_buffer<'buf, T>(
-    buffer: &'buf mut [u8],
-    dimensions: &[u32],
-) -> Result<ArrayViewMut1<'buf, T>, KernelError>
-where
-    T: ValueType,
-{
-    buffer
-        .view_mut::<T>(dimensions)
-        .and_then(|t| t.into_dimensionality())
-        .map_err(|e| {
-            KernelError::InvalidInput(InvalidInput {
-                name: "confidences".to_string(),
-                reason: BadInputReason::InvalidValue(e.to_string()),
-            })
-        })
+    Ok(())
 }
 
 impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
@@ -52,21 +71,71 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         metadata.add_tag("nlp");
         metadata.add_tag("numeric");
         metadata.add_tag("classification");
+        // The input is copied into our memory and the output is written back
+        // out from the same buffer.
+        metadata
+            .with_resource_limit(ResourceLimit::MemoryBytes(Cost::linear(
+                64.0 * 1024.0,
+                16.0,
+            )))
+            .with_resource_limit(ResourceLimit::ExecutionTimeMicros(
+                Cost::linear(1000.0, 0.1),
+            ))
+            // Roughly a max, subtract, exp, sum and divide for each element
+            .with_resource_limit(ResourceLimit::ComputeCost(Cost::linear(
+                0.0,
+                5.0,
+            )));
+
+        let mode = ArgumentMetadata::new("mode");
+        mode.set_description(
+            "Whether to output probabilities (`softmax`) or log-probabilities (`log_softmax`).",
+        );
+        mode.set_default_value("softmax");
+        let hint = interpret_as_string_in_enum(&["softmax", "log_softmax"]);
+        mode.add_hint(&hint);
+        metadata.add_argument(&mode);
+
+        let axis = ArgumentMetadata::new("axis");
+        axis.set_description(
+            "The axis to normalise along. Negative values count backwards from the last axis.",
+        );
+        axis.set_default_value("-1");
+        axis.add_hint(&supported_argument_type(ArgumentType::Integer));
+        metadata.add_argument(&axis);
+
+        let temperature = ArgumentMetadata::new("temperature");
+        temperature.set_description(
+            "Divide the inputs by this value before normalising. Higher temperatures give a softer distribution.",
+        );
+        temperature.set_default_value("1.0");
+        temperature.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&temperature);
+
+        let element_type = ArgumentMetadata::new(element_type::NAME);
+        element_type.set_description(
+            "The type of the input's elements. Half-precision values (`f16` and `bf16`) are passed around as raw bits in a `u16` tensor.",
+        );
+        element_type.set_default_value("f32");
+        let hint = interpret_as_string_in_enum(&supported_element_types());
+        element_type.add_hint(&hint);
+        metadata.add_argument(&element_type);
 
         let input = TensorMetadata::new("input");
         let hint = supported_shapes(
-            &[ElementType::F32, ElementType::F64],
-            DimensionsParam::Fixed(&[0]),
+            &supported_tensor_types(),
+            DimensionsParam::Dynamic,
         );
         input.add_hint(&hint);
         metadata.add_input(&input);
 
         let soft_max = TensorMetadata::new("soft_max");
-        soft_max
-            .set_description("Vector normalised into probability distribution");
+        soft_max.set_description(
+            "The input, with each lane along the axis normalised into a probability distribution",
+        );
         let hint = supported_shapes(
-            &[ElementType::F32, ElementType::F64],
-            DimensionsParam::Fixed(&[0]),
+            &supported_tensor_types(),
+            DimensionsParam::Dynamic,
         );
         soft_max.add_hint(&hint);
         metadata.add_output(&soft_max);
@@ -78,16 +147,20 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let ctx =
             GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
 
-        ctx.add_input_tensor(
-            "input",
-            ElementType::F32,
-            DimensionsParam::Fixed(&[0]),
-        );
+        get_mode(|n| ctx.get_argument(n)).map_err(GraphError::InvalidArgument)?;
+        get_axis(|n| ctx.get_argument(n)).map_err(GraphError::InvalidArgument)?;
+        get_temperature(|n| ctx.get_argument(n))
+            .map_err(GraphError::InvalidArgument)?;
+        let element_type = get_precision(|n| ctx.get_argument(n))
+            .map_err(GraphError::InvalidArgument)?
+            .element_type();
+
+        ctx.add_input_tensor("input", element_type, DimensionsParam::Dynamic);
 
         ctx.add_output_tensor(
             "soft_max",
-            ElementType::F32,
-            DimensionsParam::Fixed(&[0]),
+            element_type,
+            DimensionsParam::Dynamic,
         );
 
         Ok(())
@@ -96,6 +169,16 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
     fn kernel(id: String) -> Result<(), KernelError> {
         let ctx =
             KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let mode = get_mode(|n| ctx.get_argument(n))
+            .map_err(KernelError::InvalidArgument)?;
+        let axis = get_axis(|n| ctx.get_argument(n))
+            .map_err(KernelError::InvalidArgument)?;
+        let temperature = get_temperature(|n| ctx.get_argument(n))
+            .map_err(KernelError::InvalidArgument)?;
+        let precision = get_precision(|n| ctx.get_argument(n))
+            .map_err(KernelError::InvalidArgument)?;
+
         let TensorResult {
             element_type,
             dimensions,
@@ -107,10 +190,33 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             })
         })?;
 
-        match element_type {
-            ElementType::F32 => preprocess_buffer::<f32>(&mut buffer, &dimensions).map(softmax)?,
-            ElementType::F64 => preprocess_buffer::<f64>(&mut buffer, &dimensions).map(softmax)?,
-            other => {
+        match (element_type, precision) {
+            (ElementType::F32, _) => softmax_in_place::<f32>(
+                &mut buffer,
+                &dimensions,
+                mode,
+                axis,
+                temperature,
+            )?,
+            (ElementType::F64, _) => softmax_in_place::<f64>(
+                &mut buffer,
+                &dimensions,
+                mode,
+                axis,
+                temperature,
+            )?,
+            #[cfg(feature = "half")]
+            (ElementType::U16, Precision::Half(format)) => {
+                buffer = softmax_half_precision(
+                    format,
+                    &buffer,
+                    &dimensions,
+                    mode,
+                    axis,
+                    temperature,
+                )?;
+            },
+            (other, _) => {
                 return Err(KernelError::Other(format!(
                 "The softmax proc-block only accepts f32 or f64 tensors, found {:?}",
                 other,
@@ -131,68 +237,231 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use hotg_rune_proc_blocks::ndarray;
+/// Widen half-precision values to `f32`, do the softmax, then narrow the
+/// results back down to the same format.
+#[cfg(feature = "half")]
+fn softmax_half_precision(
+    format: HalfPrecision,
+    buffer: &[u8],
+    dimensions: &[u32],
+    mode: Mode,
+    axis: isize,
+    temperature: f64,
+) -> Result<Vec<u8>, KernelError> {
+    use hotg_rune_proc_blocks::SliceExt;
 
-    #[test]
-    fn test_softmax_unfiorm() {
-        let mut input = ndarray::arr1(&[1.0, 1.0, 1.0, 1.0]);
-        let softmax_correct = ndarray::arr1(&[0.25, 0.25, 0.25, 0.25]);
+    let bits = buffer.try_elements::<u16>().map_err(|e| {
+        KernelError::InvalidInput(InvalidInput {
+            name: "input".to_string(),
+            reason: BadInputReason::InvalidValue(e.to_string()),
+        })
+    })?;
+    let mut values = format.widen(bits);
+
+    softmax_in_place::<f32>(
+        values.as_bytes_mut(),
+        dimensions,
+        mode,
+        axis,
+        temperature,
+    )?;
 
-        softmax(input.view_mut());
-        assert_eq!(input, softmax_correct);
+    Ok(format.narrow(&values).as_bytes().to_vec())
+}
+
+/// How the input's elements are stored.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Precision {
+    Full(ElementType),
+    /// Half-precision values, stored as raw bits in a `u16` tensor.
+    #[cfg(feature = "half")]
+    Half(HalfPrecision),
+}
+
+impl Precision {
+    fn element_type(self) -> ElementType {
+        match self {
+            Precision::Full(element_type) => element_type,
+            #[cfg(feature = "half")]
+            Precision::Half(_) => ElementType::U16,
+        }
     }
+}
 
-    #[test]
-    fn test_softmax_single() {
-        let mut input = ndarray::arr1(&[1.0, 0.0]);
-        let softmax_correct =
-            ndarray::arr1(&[0.7310585786300049, 0.26894142136999510]);
-        softmax(input.view_mut());
+fn get_precision(
+    get_argument: impl FnOnce(&str) -> Option<String>,
+) -> Result<Precision, InvalidArgument> {
+    let name = match get_argument(element_type::NAME) {
+        Some(name) => name,
+        None => return Ok(Precision::Full(ElementType::F32)),
+    };
 
-        assert_eq!(input, softmax_correct);
+    #[cfg(feature = "half")]
+    if let Some(format) = HalfPrecision::from_name(&name) {
+        return Ok(Precision::Half(format));
     }
 
-    #[test]
-    fn test_softmax() {
-        let mut input = ndarray::arr1(&[1.0, 2.0, 3.0]);
-        let softmax_correct = ndarray::arr1(&[
-            0.09003057317038046,
-            0.24472847105479767,
-            0.6652409557748219,
-        ]);
-
-        softmax(input.view_mut());
-        assert_eq!(input, softmax_correct);
+    element_type::parse_element_type(&name, element_type::FLOAT)
+        .map(Precision::Full)
+        .map_err(|e| InvalidArgument::invalid_value(element_type::NAME, e))
+}
+
+fn supported_element_types() -> Vec<&'static str> {
+    let names = element_type::FLOAT.iter().copied();
+    #[cfg(feature = "half")]
+    let names = names.chain(HalfPrecision::NAMES.iter().copied());
+    names.collect()
+}
+
+fn supported_tensor_types() -> Vec<ElementType> {
+    let types = [ElementType::F32, ElementType::F64].iter().copied();
+    #[cfg(feature = "half")]
+    let types = types.chain(std::iter::once(ElementType::U16));
+    types.collect()
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Mode {
+    Softmax,
+    LogSoftmax,
+}
+
+fn get_mode(
+    get_argument: impl FnOnce(&str) -> Option<String>,
+) -> Result<Mode, InvalidArgument> {
+    match get_argument("mode").as_deref() {
+        Some("softmax") | None => Ok(Mode::Softmax),
+        Some("log_softmax") => Ok(Mode::LogSoftmax),
+        Some(other) => Err(InvalidArgument::invalid_value(
+            "mode",
+            format!(
+                "Expected \"softmax\" or \"log_softmax\", found \"{}\"",
+                other
+            ),
+        )),
+    }
+}
+
+fn get_axis(
+    get_argument: impl FnOnce(&str) -> Option<String>,
+) -> Result<isize, InvalidArgument> {
+    match get_argument("axis") {
+        Some(axis) => axis
+            .parse()
+            .map_err(|e| InvalidArgument::invalid_value("axis", e)),
+        None => Ok(-1),
     }
+}
 
-    #[test]
-    fn test_softmax_zeros() {
-        let mut input = ndarray::arr1(&[0.0, 0.0]);
-        let softmax_correct = ndarray::arr1(&[0.5, 0.5]);
+fn get_temperature(
+    get_argument: impl FnOnce(&str) -> Option<String>,
+) -> Result<f64, InvalidArgument> {
+    let temperature = match get_argument("temperature") {
+        Some(t) => t
+            .parse()
+            .map_err(|e| InvalidArgument::invalid_value("temperature", e))?,
+        None => 1.0,
+    };
 
-        softmax(input.view_mut());
-        assert_eq!(input, softmax_correct);
+    if temperature > 0.0 && f64::is_finite(temperature) {
+        Ok(temperature)
+    } else {
+        Err(InvalidArgument::invalid_value(
+            "temperature",
+            "The temperature must be a positive number",
+        ))
     }
+}
 
-    #[test]
-    fn test_softmax_zero() {
-        let mut input = ndarray::arr1(&[0.0]);
-        let softmax_correct = ndarray::arr1(&[1.0]);
+/// Convert a (possibly negative) axis into an index.
+fn resolve_axis(axis: isize, ndim: usize) -> Result<Axis, InvalidArgument> {
+    let resolved = if axis < 0 { ndim as isize + axis } else { axis };
+
+    if (0..ndim as isize).contains(&resolved) {
+        Ok(Axis(resolved as usize))
+    } else {
+        Err(InvalidArgument::invalid_value(
+            "axis",
+            format!("Axis {} is out of bounds for a {}D tensor", axis, ndim),
+        ))
+    }
+}
 
-        softmax(input.view_mut());
-        assert_eq!(input, softmax_correct);
+impl InvalidArgument {
+    fn invalid_value(name: impl Into<String>, reason: impl Display) -> Self {
+        InvalidArgument {
+            name: name.into(),
+            reason: BadArgumentReason::InvalidValue(reason.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proc_block_v1::ProcBlockV1 as _;
+    use hotg_rune_proc_blocks::{
+        mock,
+        strategies::{element_types, mock_tensors},
+    };
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn kernel_never_panics(
+            input in mock_tensors(
+                prop_oneof![
+                    Just(ElementType::F32),
+                    Just(ElementType::F64),
+                    Just(ElementType::U16),
+                    element_types(),
+                ],
+                3,
+                8,
+            ),
+            mode in prop_oneof![3 => "softmax", 3 => "log_softmax", 1 => ".*"],
+            axis in -4_isize..4,
+            temperature in prop_oneof![3 => 0.1_f64..10.0, 1 => any::<f64>()],
+            element_type in prop_oneof![
+                proptest::sample::select(ElementType::ALL),
+                proptest::sample::select(supported_element_types()),
+            ],
+        ) {
+            mock::reset();
+            mock::set_argument("softmax", "mode", mode);
+            mock::set_argument("softmax", "axis", axis.to_string());
+            mock::set_argument(
+                "softmax",
+                "temperature",
+                temperature.to_string(),
+            );
+            mock::set_argument("softmax", ElementType::NAME, element_type);
+            mock::set_input("softmax", "input", input);
+
+            // Bad arguments and inputs should be reported as errors, so we
+            // only care that nothing panics
+            let _ = ProcBlockV1::graph("softmax".to_string());
+            let _ = ProcBlockV1::kernel("softmax".to_string());
+        }
     }
 
     #[test]
-    fn test_softmax_empty() {
-        let empty: &[f32] = &[];
-        let mut input = ndarray::Array::from_vec(empty.to_vec());
-        let softmax_correct = ndarray::Array::from_vec(empty.to_vec());
+    #[cfg(feature = "half")]
+    fn softmax_of_half_precision_values() {
+        let format = HalfPrecision::BF16;
+        let bits = format.narrow(&[0.0, 0.0, 0.0, 0.0]);
+        mock::reset();
+        mock::set_argument("softmax", ElementType::NAME, "bf16");
+        let input = mock::Tensor::new(ElementType::U16, &[4], &bits);
+        mock::set_input("softmax", "input", input);
+
+        ProcBlockV1::graph("softmax".to_string()).unwrap();
+        ProcBlockV1::kernel("softmax".to_string()).unwrap();
 
-        softmax(input.view_mut());
-        assert_eq!(input, softmax_correct);
+        let node = mock::node("softmax").unwrap();
+        assert_eq!(node.graph_outputs[0].element_type, ElementType::U16);
+        let output = &node.outputs["soft_max"];
+        assert_eq!(output.element_type, ElementType::U16);
+        assert_eq!(format.widen(&output.to_vec()), vec![0.25; 4]);
     }
 }
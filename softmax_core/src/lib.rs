@@ -0,0 +1,306 @@
+//! Find the normalised exponential function (softmax) of a vector, or of
+//! each lane of a tensor along a particular axis.
+//!
+//! This is the algorithm behind the `softmax` proc-block, without any of the
+//! WebAssembly bindings, so it can be used as a normal Rust library.
+
+use hotg_rune_proc_blocks::ndarray::{
+    ArrayViewMut, ArrayViewMut1, Axis, Dimension,
+};
+use num_traits::Float;
+
+/// Normalise a vector into a probability distribution, in place.
+pub fn softmax<T>(input: ArrayViewMut1<'_, T>)
+where
+    T: Float + num_traits::FromPrimitive,
+{
+    softmax_with_temperature(input, T::one());
+}
+
+/// Normalise a vector into a probability distribution after dividing each
+/// element by `temperature`.
+///
+/// Higher temperatures give a "softer" distribution, while lower temperatures
+/// make the largest element more dominant.
+///
+/// The largest element is subtracted from each value before exponentiating
+/// so large logits won't overflow to infinity. This doesn't change the result
+/// because softmax is invariant to adding a constant to every element.
+pub fn softmax_with_temperature<T>(
+    mut input: ArrayViewMut1<'_, T>,
+    temperature: T,
+) where
+    T: Float + num_traits::FromPrimitive,
+{
+    if temperature != T::one() {
+        input.mapv_inplace(|x| x / temperature);
+    }
+
+    let max = finite_max(&input);
+    input.mapv_inplace(|x| (x - max).exp());
+
+    let sum = input.sum();
+    if !sum.is_zero() {
+        input.mapv_inplace(|x| x / sum);
+    }
+}
+
+/// Apply [`softmax_with_temperature()`] to each lane along an axis, in place.
+///
+/// For example, using `Axis(1)` on a batch of `[N, C]` logits gives a
+/// separate probability distribution for each of the `N` samples.
+pub fn softmax_along_axis<T, D>(
+    mut input: ArrayViewMut<'_, T, D>,
+    axis: Axis,
+    temperature: T,
+) where
+    T: Float + num_traits::FromPrimitive,
+    D: Dimension,
+{
+    for lane in input.lanes_mut(axis) {
+        softmax_with_temperature(lane, temperature);
+    }
+}
+
+/// Calculate the logarithm of [`softmax_with_temperature()`], in place.
+///
+/// This is more numerically stable than taking the log of the softmax
+/// because it never needs to represent tiny probabilities directly.
+pub fn log_softmax_with_temperature<T>(
+    mut input: ArrayViewMut1<'_, T>,
+    temperature: T,
+) where
+    T: Float + num_traits::FromPrimitive,
+{
+    if input.is_empty() {
+        return;
+    }
+
+    if temperature != T::one() {
+        input.mapv_inplace(|x| x / temperature);
+    }
+
+    let max = finite_max(&input);
+    let log_sum_exp =
+        input.fold(T::zero(), |sum, &x| sum + (x - max).exp()).ln() + max;
+
+    input.mapv_inplace(|x| x - log_sum_exp);
+}
+
+/// Apply [`log_softmax_with_temperature()`] to each lane along an axis, in
+/// place.
+pub fn log_softmax_along_axis<T, D>(
+    mut input: ArrayViewMut<'_, T, D>,
+    axis: Axis,
+    temperature: T,
+) where
+    T: Float + num_traits::FromPrimitive,
+    D: Dimension,
+{
+    for lane in input.lanes_mut(axis) {
+        log_softmax_with_temperature(lane, temperature);
+    }
+}
+
+/// The largest element in a lane, or zero if there isn't a finite maximum
+/// (e.g. the lane is empty or every element is `-inf`).
+fn finite_max<T: Float>(lane: &ArrayViewMut1<'_, T>) -> T {
+    let max = lane.fold(T::neg_infinity(), |max, &x| max.max(x));
+
+    if max.is_finite() {
+        max
+    } else {
+        T::zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::{
+        ndarray,
+        strategies::vectors,
+        testing::{assert_tensors_close, is_close, Tolerance},
+    };
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn softmax_always_sums_to_one(
+            mut input in vectors(-50.0_f64..50.0, 64),
+        ) {
+            prop_assume!(!input.is_empty());
+
+            softmax(input.view_mut());
+
+            prop_assert!(input.iter().all(|&p| (0.0..=1.0).contains(&p)));
+            prop_assert!(is_close(input.sum(), 1.0, Tolerance::absolute(1e-9)));
+        }
+
+        #[test]
+        fn softmax_of_large_logits_is_finite(
+            mut input in vectors(-1e30_f32..1e30, 64),
+        ) {
+            prop_assume!(!input.is_empty());
+
+            softmax(input.view_mut());
+
+            prop_assert!(input.iter().all(|&p| (0.0..=1.0).contains(&p)));
+            let sum = f64::from(input.sum());
+            prop_assert!(is_close(sum, 1.0, Tolerance::absolute(1e-5)));
+        }
+    }
+
+    #[test]
+    fn large_logits_dont_overflow() {
+        let mut input = ndarray::arr1(&[1000.0_f64, 999.0, 0.0]);
+        let softmax_correct =
+            ndarray::arr1(&[0.7310585786300049, 0.268_941_421_369_995_1, 0.0]);
+
+        softmax(input.view_mut());
+
+        assert_tensors_close(&input, &softmax_correct, Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn large_negative_logits_dont_underflow() {
+        let mut input = ndarray::arr1(&[-1000.0_f64, -1000.0]);
+        let softmax_correct = ndarray::arr1(&[0.5, 0.5]);
+
+        softmax(input.view_mut());
+
+        assert_tensors_close(&input, &softmax_correct, Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn large_logits_along_an_axis() {
+        let mut input = ndarray::arr2(&[[100.0_f32, 100.0], [1e4, 0.0]]);
+        let softmax_correct = ndarray::arr2(&[[0.5, 0.5], [1.0, 0.0]]);
+
+        softmax_along_axis(input.view_mut(), Axis(1), 1.0);
+
+        assert_tensors_close(&input, &softmax_correct, Tolerance::F32);
+    }
+
+    #[test]
+    fn softmax_is_applied_to_each_row() {
+        let mut input = ndarray::arr2(&[[1.0, 1.0], [1.0, 0.0]]);
+        let softmax_correct = ndarray::arr2(&[
+            [0.5, 0.5],
+            [0.7310585786300049, 0.268_941_421_369_995_1],
+        ]);
+
+        softmax_along_axis(input.view_mut(), Axis(1), 1.0);
+
+        assert_tensors_close(&input, &softmax_correct, Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn softmax_is_applied_to_each_column() {
+        let mut input = ndarray::arr2(&[[1.0, 1.0], [1.0, 0.0]]);
+        let softmax_correct = ndarray::arr2(&[
+            [0.5, 0.7310585786300049],
+            [0.5, 0.268_941_421_369_995_1],
+        ]);
+
+        softmax_along_axis(input.view_mut(), Axis(0), 1.0);
+
+        assert_tensors_close(&input, &softmax_correct, Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn temperature_scales_the_logits() {
+        let mut hot = ndarray::arr1(&[2.0, 0.0]);
+        let mut cold = ndarray::arr1(&[1.0, 0.0]);
+
+        softmax_with_temperature(hot.view_mut(), 2.0);
+        softmax(cold.view_mut());
+
+        assert_tensors_close(&hot, &cold, Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn log_softmax_is_the_log_of_softmax() {
+        let mut input = ndarray::arr2(&[[1.0, 2.0, 3.0], [0.0, 0.0, 0.0]]);
+        let mut should_be = input.clone();
+        softmax_along_axis(should_be.view_mut(), Axis(1), 1.0);
+        should_be.mapv_inplace(f64::ln);
+
+        log_softmax_along_axis(input.view_mut(), Axis(1), 1.0);
+
+        assert_tensors_close(&input, &should_be, Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn log_softmax_handles_large_logits() {
+        let mut input = ndarray::arr1(&[1000.0_f32, 0.0]);
+
+        log_softmax_with_temperature(input.view_mut(), 1.0);
+
+        assert_tensors_close(
+            &input,
+            &ndarray::arr1(&[0.0, -1000.0]),
+            Tolerance::F32,
+        );
+    }
+
+    #[test]
+    fn test_softmax_unfiorm() {
+        let mut input = ndarray::arr1(&[1.0, 1.0, 1.0, 1.0]);
+        let softmax_correct = ndarray::arr1(&[0.25, 0.25, 0.25, 0.25]);
+
+        softmax(input.view_mut());
+        assert_tensors_close(&input, &softmax_correct, Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn test_softmax_single() {
+        let mut input = ndarray::arr1(&[1.0, 0.0]);
+        let softmax_correct =
+            ndarray::arr1(&[0.7310585786300049, 0.268_941_421_369_995_1]);
+        softmax(input.view_mut());
+
+        assert_tensors_close(&input, &softmax_correct, Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn test_softmax() {
+        let mut input = ndarray::arr1(&[1.0, 2.0, 3.0]);
+        let softmax_correct = ndarray::arr1(&[
+            0.09003057317038046,
+            0.24472847105479767,
+            0.6652409557748219,
+        ]);
+
+        softmax(input.view_mut());
+        assert_tensors_close(&input, &softmax_correct, Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn test_softmax_zeros() {
+        let mut input = ndarray::arr1(&[0.0, 0.0]);
+        let softmax_correct = ndarray::arr1(&[0.5, 0.5]);
+
+        softmax(input.view_mut());
+        assert_tensors_close(&input, &softmax_correct, Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn test_softmax_zero() {
+        let mut input = ndarray::arr1(&[0.0]);
+        let softmax_correct = ndarray::arr1(&[1.0]);
+
+        softmax(input.view_mut());
+        assert_tensors_close(&input, &softmax_correct, Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn test_softmax_empty() {
+        let empty: &[f32] = &[];
+        let mut input = ndarray::Array::from_vec(empty.to_vec());
+        let softmax_correct = ndarray::Array::from_vec(empty.to_vec());
+
+        softmax(input.view_mut());
+        assert_tensors_close(&input, &softmax_correct, Tolerance::DEFAULT);
+    }
+}
@@ -0,0 +1,396 @@
+//! [SpecAugment][paper]-style augmentation for spectrograms.
+//!
+//! This masks out a number of randomly placed bands of consecutive time steps
+//! and frequency bins, which makes models more robust to missing information
+//! when the augmented data is used for training.
+//!
+//! Proc-blocks don't keep any state between runs, so the random number
+//! generator is seeded using both the `seed` argument and the spectrogram's
+//! contents. That means different inputs get different masks, while the same
+//! input and seed will always be augmented the same way.
+//!
+//! [paper]: https://arxiv.org/abs/1904.08779
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    hash::fnv1a,
+    ndarray::{s, ArrayViewMut2, Axis},
+    prelude::*,
+    runtime_v1::*,
+    BufferExt,
+};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("SpecAugment", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("audio");
+        metadata.add_tag("augmentation");
+        metadata.add_tag("training");
+
+        let args = [
+            ("time_masks", "The number of time masks to apply.", "2"),
+            (
+                "max_time_mask_width",
+                "The maximum number of consecutive time steps in each time mask.",
+                "20",
+            ),
+            (
+                "frequency_masks",
+                "The number of frequency masks to apply.",
+                "2",
+            ),
+            (
+                "max_frequency_mask_width",
+                "The maximum number of consecutive frequency bins in each frequency mask.",
+                "8",
+            ),
+            ("seed", "A seed for the random number generator.", "0"),
+        ];
+
+        for (name, description, default_value) in args {
+            let arg = ArgumentMetadata::new(name);
+            arg.set_description(description);
+            arg.set_default_value(default_value);
+            arg.add_hint(&supported_argument_type(
+                ArgumentType::UnsignedInteger,
+            ));
+            metadata.add_argument(&arg);
+        }
+
+        let mask_value = ArgumentMetadata::new("mask_value");
+        mask_value.set_description("The value masked elements are set to.");
+        mask_value.set_default_value("0.0");
+        mask_value.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&mask_value);
+
+        let spectrogram = TensorMetadata::new("spectrogram");
+        spectrogram.set_description(
+            "A `[time, frequency]` spectrogram (e.g. a mel spectrogram or MFCCs).",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        spectrogram.add_hint(&hint);
+        metadata.add_input(&spectrogram);
+
+        let augmented = TensorMetadata::new("augmented");
+        augmented.set_description("The spectrogram, with the masks applied.");
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        augmented.add_hint(&hint);
+        metadata.add_output(&augmented);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = SpecAugment::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "spectrogram",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "augmented",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let augment = SpecAugment::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            mut buffer,
+        } = ctx.get_input_tensor("spectrogram").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "spectrogram".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::F32 {
+            return Err(invalid_spectrogram(format!(
+                "Expected a f32 tensor, found {}",
+                element_type
+            )));
+        }
+
+        let mut rng = SmallRng::seed_from_u64(augment.seed ^ fnv1a(&buffer));
+
+        let spectrogram = buffer
+            .try_view_mut::<f32, _>(&dimensions)
+            .map_err(invalid_spectrogram)?;
+
+        augment.apply(spectrogram, &mut rng);
+
+        ctx.set_output_tensor(
+            "augmented",
+            TensorParam {
+                element_type: ElementType::F32,
+                dimensions: &dimensions,
+                buffer: &buffer,
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_spectrogram(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "spectrogram".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct SpecAugment {
+    time_masks: usize,
+    max_time_mask_width: usize,
+    frequency_masks: usize,
+    max_frequency_mask_width: usize,
+    mask_value: f32,
+    seed: u64,
+}
+
+impl SpecAugment {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let time_masks =
+            ctx.parse_argument_with_default::<_, E>("time_masks", 2)?;
+        let max_time_mask_width =
+            ctx.parse_argument_with_default::<_, E>("max_time_mask_width", 20)?;
+        let frequency_masks =
+            ctx.parse_argument_with_default::<_, E>("frequency_masks", 2)?;
+        let max_frequency_mask_width = ctx.parse_argument_with_default::<_, E>(
+            "max_frequency_mask_width",
+            8,
+        )?;
+        let mask_value =
+            ctx.parse_argument_with_default::<_, E>("mask_value", 0.0)?;
+        let seed = ctx.parse_argument_with_default::<_, E>("seed", 0)?;
+
+        Ok(SpecAugment {
+            time_masks,
+            max_time_mask_width,
+            frequency_masks,
+            max_frequency_mask_width,
+            mask_value,
+            seed,
+        })
+    }
+
+    fn apply(
+        &self,
+        mut spectrogram: ArrayViewMut2<'_, f32>,
+        rng: &mut impl Rng,
+    ) {
+        for _ in 0..self.frequency_masks {
+            let bins = spectrogram.len_of(Axis(1));
+            let (start, end) =
+                random_band(bins, self.max_frequency_mask_width, rng);
+            spectrogram
+                .slice_mut(s![.., start..end])
+                .fill(self.mask_value);
+        }
+
+        for _ in 0..self.time_masks {
+            let steps = spectrogram.len_of(Axis(0));
+            let (start, end) =
+                random_band(steps, self.max_time_mask_width, rng);
+            spectrogram.slice_mut(s![start..end, ..]).fill(self.mask_value);
+        }
+    }
+}
+
+/// Pick a random `[start, end)` band with a width in `0..=max_width`, which
+/// fits inside `0..len`.
+fn random_band(
+    len: usize,
+    max_width: usize,
+    rng: &mut impl Rng,
+) -> (usize, usize) {
+    let width = rng.gen_range(0..=max_width.min(len));
+    let start = rng.gen_range(0..=len - width);
+
+    (start, start + width)
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::ndarray::Array2;
+
+    const DEFAULT: SpecAugment = SpecAugment {
+        time_masks: 2,
+        max_time_mask_width: 20,
+        frequency_masks: 2,
+        max_frequency_mask_width: 8,
+        mask_value: 0.0,
+        seed: 0,
+    };
+
+    #[test]
+    fn bands_always_fit() {
+        let mut rng = SmallRng::seed_from_u64(42);
+
+        for _ in 0..1000 {
+            let (start, end) = random_band(10, 20, &mut rng);
+
+            assert!(start <= end);
+            assert!(end <= 10);
+        }
+    }
+
+    #[test]
+    fn masked_bands_are_contiguous_and_within_limits() {
+        let augment = SpecAugment {
+            time_masks: 0,
+            frequency_masks: 1,
+            max_frequency_mask_width: 5,
+            mask_value: -1.0,
+            ..DEFAULT
+        };
+        let mut rng = SmallRng::seed_from_u64(7);
+
+        for _ in 0..100 {
+            let mut spectrogram = Array2::<f32>::ones((16, 32));
+
+            augment.apply(spectrogram.view_mut(), &mut rng);
+
+            let masked: Vec<usize> = spectrogram
+                .row(0)
+                .iter()
+                .enumerate()
+                .filter(|&(_, &v)| v == -1.0)
+                .map(|(i, _)| i)
+                .collect();
+            assert!(masked.len() <= 5);
+            if let (Some(first), Some(last)) = (masked.first(), masked.last()) {
+                assert_eq!(last - first + 1, masked.len());
+            }
+            // Frequency masks cover every time step
+            for row in spectrogram.outer_iter() {
+                assert_eq!(row, spectrogram.row(0));
+            }
+        }
+    }
+
+    #[test]
+    fn time_masks_cover_every_frequency_bin() {
+        let augment = SpecAugment {
+            frequency_masks: 0,
+            ..DEFAULT
+        };
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mut spectrogram = Array2::<f32>::ones((100, 8));
+
+        augment.apply(spectrogram.view_mut(), &mut rng);
+
+        for row in spectrogram.outer_iter() {
+            let masked = row.iter().filter(|&&v| v == 0.0).count();
+            assert!(masked == 0 || masked == row.len());
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_masks() {
+        let mut first = Array2::<f32>::ones((50, 40));
+        let mut second = first.clone();
+
+        DEFAULT.apply(first.view_mut(), &mut SmallRng::seed_from_u64(3));
+        DEFAULT.apply(second.view_mut(), &mut SmallRng::seed_from_u64(3));
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn no_masks_leaves_the_input_untouched() {
+        let augment = SpecAugment {
+            time_masks: 0,
+            frequency_masks: 0,
+            ..DEFAULT
+        };
+        let mut spectrogram = Array2::<f32>::ones((4, 4));
+
+        augment.apply(spectrogram.view_mut(), &mut SmallRng::seed_from_u64(0));
+
+        assert_eq!(spectrogram, Array2::ones((4, 4)));
+    }
+
+    #[test]
+    fn empty_spectrogram() {
+        let mut spectrogram = Array2::<f32>::zeros((0, 0));
+
+        DEFAULT.apply(spectrogram.view_mut(), &mut SmallRng::seed_from_u64(0));
+    }
+}
@@ -0,0 +1,566 @@
+//! Decode the raw output of an [SSD][ssd] object detection model into
+//! absolute bounding boxes.
+//!
+//! SSD models don't predict boxes directly. Instead, each box is encoded as
+//! an offset `[ty, tx, th, tw]` from one of a fixed set of anchors
+//! `[y, x, height, width]`, using the same box coder as the TensorFlow Object
+//! Detection API:
+//!
+//! ```text
+//! y      = ty / y_scale * anchor_height + anchor_y
+//! x      = tx / x_scale * anchor_width + anchor_x
+//! height = exp(th / h_scale) * anchor_height
+//! width  = exp(tw / w_scale) * anchor_width
+//! ```
+//!
+//! The decoded boxes are clipped to the image, scaled to `image_width` and
+//! `image_height`, and followed by each box's class scores. This is the
+//! `[batch, num_boxes, 4 + num_classes]` layout expected by the
+//! `non_max_suppression` proc-block (with `box_format=corners`).
+//!
+//! [ssd]: https://arxiv.org/abs/1512.02325
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::{
+        ArrayView, ArrayView1, ArrayView2, ArrayView3, Dimension, Ix2, Ix3,
+    },
+    prelude::*,
+    runtime_v1::*,
+    BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// The number of values used for each box encoding and anchor.
+const BOX_COLUMNS: usize = 4;
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata = Metadata::new("SSD Decode", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("image");
+        metadata.add_tag("object-detection");
+
+        for (name, default, description) in SCALE_ARGUMENTS {
+            let scale = ArgumentMetadata::new(name);
+            scale.set_description(description);
+            scale.set_default_value(default);
+            scale.add_hint(&supported_argument_type(ArgumentType::Float));
+            metadata.add_argument(&scale);
+        }
+
+        let image_width = ArgumentMetadata::new("image_width");
+        image_width.set_description(
+            "The width of the image, used to convert the normalized box coordinates to pixels. Leave this as `1` to keep the coordinates normalized.",
+        );
+        image_width.set_default_value("1");
+        image_width.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&image_width);
+
+        let image_height = ArgumentMetadata::new("image_height");
+        image_height.set_description(
+            "The height of the image, used to convert the normalized box coordinates to pixels. Leave this as `1` to keep the coordinates normalized.",
+        );
+        image_height.set_default_value("1");
+        image_height.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&image_height);
+
+        let clip = ArgumentMetadata::new("clip");
+        clip.set_description(
+            "Clip the boxes so they don't extend past the edges of the image.",
+        );
+        clip.set_default_value("true");
+        clip.add_hint(&interpret_as_string_in_enum(&["true", "false"]));
+        metadata.add_argument(&clip);
+
+        let sigmoid = ArgumentMetadata::new("sigmoid");
+        sigmoid.set_description(
+            "Apply a sigmoid to the class scores. Disable this if the model already outputs probabilities.",
+        );
+        sigmoid.set_default_value("true");
+        sigmoid.add_hint(&interpret_as_string_in_enum(&["true", "false"]));
+        metadata.add_argument(&sigmoid);
+
+        let box_encodings = TensorMetadata::new("box_encodings");
+        box_encodings.set_description(
+            "The model's box predictions as a `[batch, num_boxes, 4]` tensor, where each box is encoded as `[ty, tx, th, tw]` relative to its anchor.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0, BOX_COLUMNS as u32]),
+        );
+        box_encodings.add_hint(&hint);
+        metadata.add_input(&box_encodings);
+
+        let scores = TensorMetadata::new("scores");
+        scores.set_description(
+            "The model's class predictions as a `[batch, num_boxes, num_classes]` tensor.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0, 0]),
+        );
+        scores.add_hint(&hint);
+        metadata.add_input(&scores);
+
+        let anchors = TensorMetadata::new("anchors");
+        anchors.set_description(
+            "The model's anchors as a `[num_boxes, 4]` tensor, with each anchor written as `[y, x, height, width]` in normalized coordinates.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, BOX_COLUMNS as u32]),
+        );
+        anchors.add_hint(&hint);
+        metadata.add_input(&anchors);
+
+        let boxes = TensorMetadata::new("boxes");
+        boxes.set_description(
+            "The decoded boxes as a `[batch, num_boxes, 4 + num_classes]` tensor, where each box is written as `[x_min, y_min, x_max, y_max]` and followed by its class scores.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0, 0]),
+        );
+        boxes.add_hint(&hint);
+        metadata.add_output(&boxes);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "box_encodings",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, 0, BOX_COLUMNS as u32]),
+        );
+        ctx.add_input_tensor(
+            "scores",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, 0, 0]),
+        );
+        ctx.add_input_tensor(
+            "anchors",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, BOX_COLUMNS as u32]),
+        );
+        ctx.add_output_tensor(
+            "boxes",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, 0, 0]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let box_encodings = get_input(&ctx, "box_encodings")?;
+        let scores = get_input(&ctx, "scores")?;
+        let anchors = get_input(&ctx, "anchors")?;
+
+        let (batches, boxes) = match *box_encodings.dimensions {
+            [batches, boxes, columns] if columns as usize == BOX_COLUMNS => {
+                (batches, boxes)
+            },
+            ref other => {
+                return Err(invalid_input(
+                    "box_encodings",
+                    format!(
+                        "Expected a [batch, num_boxes, 4] tensor, found {:?}",
+                        other
+                    ),
+                ))
+            },
+        };
+
+        let classes = match *scores.dimensions {
+            [b, n, classes] if b == batches && n == boxes => classes,
+            ref other => {
+                return Err(invalid_input(
+                    "scores",
+                    format!(
+                        "Expected a [{}, {}, num_classes] tensor, found {:?}",
+                        batches, boxes, other
+                    ),
+                ))
+            },
+        };
+
+        match *anchors.dimensions {
+            [n, columns] if n == boxes && columns as usize == BOX_COLUMNS => {},
+            ref other => {
+                return Err(invalid_input(
+                    "anchors",
+                    format!(
+                        "Expected a [{}, 4] tensor, found {:?}",
+                        boxes, other
+                    ),
+                ))
+            },
+        }
+
+        let decoded = settings.decode(
+            f32_view::<Ix3>("box_encodings", &box_encodings)?,
+            f32_view::<Ix3>("scores", &scores)?,
+            f32_view::<Ix2>("anchors", &anchors)?,
+        );
+
+        ctx.set_output_tensor(
+            "boxes",
+            TensorParam {
+                element_type: ElementType::F32,
+                dimensions: &[batches, boxes, BOX_COLUMNS as u32 + classes],
+                buffer: decoded.as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+/// The `(name, default, description)` for each scale factor argument.
+const SCALE_ARGUMENTS: [(&str, &str, &str); 4] = [
+    (
+        "y_scale",
+        "10",
+        "The scale factor used to encode the y coordinate of each box's centre.",
+    ),
+    (
+        "x_scale",
+        "10",
+        "The scale factor used to encode the x coordinate of each box's centre.",
+    ),
+    (
+        "h_scale",
+        "5",
+        "The scale factor used to encode each box's height.",
+    ),
+    (
+        "w_scale",
+        "5",
+        "The scale factor used to encode each box's width.",
+    ),
+];
+
+fn get_input(
+    ctx: &KernelContext,
+    name: &str,
+) -> Result<TensorResult, KernelError> {
+    let tensor = ctx.get_input_tensor(name).ok_or_else(|| {
+        KernelError::InvalidInput(InvalidInput {
+            name: name.to_string(),
+            reason: BadInputReason::NotFound,
+        })
+    })?;
+
+    if tensor.element_type != ElementType::F32 {
+        return Err(invalid_input(
+            name,
+            format!("Expected a f32 tensor, found {}", tensor.element_type),
+        ));
+    }
+
+    Ok(tensor)
+}
+
+fn invalid_input(name: &str, reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: name.to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+fn f32_view<'a, D: Dimension>(
+    name: &str,
+    tensor: &'a TensorResult,
+) -> Result<ArrayView<'a, f32, D>, KernelError> {
+    tensor
+        .buffer
+        .try_view(&tensor.dimensions)
+        .map_err(|e| invalid_input(name, e))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    y_scale: f32,
+    x_scale: f32,
+    h_scale: f32,
+    w_scale: f32,
+    image_width: f32,
+    image_height: f32,
+    clip: bool,
+    sigmoid: bool,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let positive = |name: &str, default: f32| -> Result<f32, E> {
+            let value = ctx.parse_argument_with_default::<_, E>(name, default)?;
+
+            if value.is_finite() && value > 0.0 {
+                Ok(value)
+            } else {
+                Err(E::invalid_argument(InvalidArgument::invalid_value(
+                    name,
+                    "The value must be a positive number",
+                )))
+            }
+        };
+
+        Ok(Settings {
+            y_scale: positive("y_scale", 10.0)?,
+            x_scale: positive("x_scale", 10.0)?,
+            h_scale: positive("h_scale", 5.0)?,
+            w_scale: positive("w_scale", 5.0)?,
+            image_width: positive("image_width", 1.0)?,
+            image_height: positive("image_height", 1.0)?,
+            clip: ctx.parse_argument_with_default::<_, E>("clip", true)?,
+            sigmoid: ctx.parse_argument_with_default::<_, E>("sigmoid", true)?,
+        })
+    }
+
+    /// Decode every box in the batch, appending its class scores.
+    fn decode(
+        &self,
+        box_encodings: ArrayView3<'_, f32>,
+        scores: ArrayView3<'_, f32>,
+        anchors: ArrayView2<'_, f32>,
+    ) -> Vec<f32> {
+        let (batches, boxes, _) = box_encodings.dim();
+        let classes = scores.dim().2;
+        let mut decoded =
+            Vec::with_capacity(batches * boxes * (BOX_COLUMNS + classes));
+
+        for (batch_encodings, batch_scores) in
+            box_encodings.outer_iter().zip(scores.outer_iter())
+        {
+            let rows = batch_encodings
+                .outer_iter()
+                .zip(batch_scores.outer_iter())
+                .zip(anchors.outer_iter());
+
+            for ((encoding, box_scores), anchor) in rows {
+                decoded.extend(self.decode_box(&row(encoding), &row(anchor)));
+
+                if self.sigmoid {
+                    decoded.extend(box_scores.iter().map(|&s| sigmoid(s)));
+                } else {
+                    decoded.extend(box_scores);
+                }
+            }
+        }
+
+        decoded
+    }
+
+    /// Decode a single box, returning `[x_min, y_min, x_max, y_max]`.
+    fn decode_box(&self, encoding: &[f32], anchor: &[f32]) -> [f32; 4] {
+        let (ty, tx, th, tw) =
+            (encoding[0], encoding[1], encoding[2], encoding[3]);
+        let (anchor_y, anchor_x, anchor_height, anchor_width) =
+            (anchor[0], anchor[1], anchor[2], anchor[3]);
+
+        let y = ty / self.y_scale * anchor_height + anchor_y;
+        let x = tx / self.x_scale * anchor_width + anchor_x;
+        let height = (th / self.h_scale).exp() * anchor_height;
+        let width = (tw / self.w_scale).exp() * anchor_width;
+
+        let mut corners = [
+            x - width / 2.0,
+            y - height / 2.0,
+            x + width / 2.0,
+            y + height / 2.0,
+        ];
+
+        if self.clip {
+            for value in &mut corners {
+                *value = value.clamp(0.0, 1.0);
+            }
+        }
+
+        let [x_min, y_min, x_max, y_max] = corners;
+        [
+            x_min * self.image_width,
+            y_min * self.image_height,
+            x_max * self.image_width,
+            y_max * self.image_height,
+        ]
+    }
+}
+
+/// Copy a box encoding or anchor out of its tensor.
+fn row(values: ArrayView1<'_, f32>) -> [f32; BOX_COLUMNS] {
+    [values[0], values[1], values[2], values[3]]
+}
+
+fn sigmoid(x: f32) -> f32 { 1.0 / (1.0 + (-x).exp()) }
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proc_block_v1::ProcBlockV1 as _;
+    use hotg_rune_proc_blocks::{
+        mock,
+        ndarray::{self, Array3},
+        testing::{assert_slices_close, Tolerance},
+    };
+
+    const SETTINGS: Settings = Settings {
+        y_scale: 10.0,
+        x_scale: 10.0,
+        h_scale: 5.0,
+        w_scale: 5.0,
+        image_width: 1.0,
+        image_height: 1.0,
+        clip: true,
+        sigmoid: false,
+    };
+
+    #[test]
+    fn zero_encodings_give_back_the_anchor() {
+        let anchor = [0.5, 0.5, 0.2, 0.4];
+
+        let got = SETTINGS.decode_box(&[0.0, 0.0, 0.0, 0.0], &anchor);
+
+        assert_slices_close(&got, &[0.3, 0.4, 0.7, 0.6], Tolerance::F32);
+    }
+
+    #[test]
+    fn apply_the_offsets_and_scale_factors() {
+        let anchor = [0.5, 0.5, 0.2, 0.2];
+        // move down by half the anchor's height and double its width
+        let encoding = [5.0, 0.0, 0.0, 5.0 * 2.0_f32.ln()];
+
+        let got = SETTINGS.decode_box(&encoding, &anchor);
+
+        assert_slices_close(&got, &[0.3, 0.5, 0.7, 0.7], Tolerance::F32);
+    }
+
+    #[test]
+    fn clip_and_scale_to_the_image() {
+        let settings = Settings {
+            image_width: 640.0,
+            image_height: 480.0,
+            ..SETTINGS
+        };
+        let anchor = [0.0, 1.0, 0.5, 0.5];
+
+        let got = settings.decode_box(&[0.0, 0.0, 0.0, 0.0], &anchor);
+
+        assert_slices_close(
+            &got,
+            &[480.0, 0.0, 640.0, 0.25 * 480.0],
+            Tolerance::F32,
+        );
+    }
+
+    #[test]
+    fn scores_are_appended_to_each_box() {
+        let settings = Settings {
+            sigmoid: true,
+            ..SETTINGS
+        };
+        let anchors = ndarray::arr2(&[[0.5, 0.5, 1.0, 1.0]]);
+        // a batch of 2 images with 1 box and 2 classes each
+        let box_encodings = Array3::zeros((2, 1, 4));
+        let scores = ndarray::arr3(&[[[0.0, 100.0]], [[-100.0, 0.0]]]);
+
+        let got = settings.decode(
+            box_encodings.view(),
+            scores.view(),
+            anchors.view(),
+        );
+
+        #[rustfmt::skip]
+        let should_be = [
+            0.0, 0.0, 1.0, 1.0, 0.5, 1.0,
+            0.0, 0.0, 1.0, 1.0, 0.0, 0.5,
+        ];
+        assert_slices_close(&got, &should_be, Tolerance::F32);
+    }
+
+    #[test]
+    fn short_buffers_are_rejected() {
+        mock::reset();
+        let encodings =
+            mock::Tensor::new(ElementType::F32, &[1, 2, 4], &[0.0_f32; 8]);
+        mock::set_input("ssd", "box_encodings", encodings);
+        // 2 boxes with 3 classes each, but only enough scores for one box
+        let scores =
+            mock::Tensor::new(ElementType::F32, &[1, 2, 3], &[0.0_f32; 3]);
+        mock::set_input("ssd", "scores", scores);
+        let anchors =
+            mock::Tensor::new(ElementType::F32, &[2, 4], &[0.5_f32; 8]);
+        mock::set_input("ssd", "anchors", anchors);
+
+        let err = ProcBlockV1::kernel("ssd".to_string()).unwrap_err();
+
+        match err {
+            KernelError::InvalidInput(InvalidInput { name, .. }) => {
+                assert_eq!(name, "scores");
+            },
+            other => panic!("Unexpected error: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,477 @@
+//! Standardize features by removing the mean and scaling to unit variance,
+//!
+//! ```text
+//! scaled = (x - mean) / std
+//! ```
+//!
+//! The mean and (population) standard deviation are calculated for each
+//! column of `x_train` and the same transformation is applied to `x_test`,
+//! so no information about the test set leaks into the scaling. The fitted
+//! parameters are also serialized to JSON so they can be reused at inference
+//! time.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::{Array2, ArrayView2},
+    prelude::*,
+    runtime_v1::*,
+    BufferExt, SliceExt, StringBuilder,
+};
+use serde::{Deserialize, Serialize};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Standard Scaler", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("preprocessing");
+        metadata.add_tag("normalization");
+        metadata.add_tag("analytics");
+
+        let with_mean = ArgumentMetadata::new("with_mean");
+        with_mean.set_description("Subtract each column's mean.");
+        with_mean.set_default_value("true");
+        with_mean.add_hint(&interpret_as_string_in_enum(&["true", "false"]));
+        metadata.add_argument(&with_mean);
+
+        let with_std = ArgumentMetadata::new("with_std");
+        with_std
+            .set_description("Divide each column by its standard deviation.");
+        with_std.set_default_value("true");
+        with_std.add_hint(&interpret_as_string_in_enum(&["true", "false"]));
+        metadata.add_argument(&with_std);
+
+        let x_train = TensorMetadata::new("x_train");
+        x_train.set_description(
+            "The samples used to calculate the mean and standard deviation, one per row.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        x_train.add_hint(&hint);
+        metadata.add_input(&x_train);
+
+        let x_test = TensorMetadata::new("x_test");
+        x_test.set_description(
+            "More samples to scale using the parameters from `x_train`.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        x_test.add_hint(&hint);
+        metadata.add_input(&x_test);
+
+        let x_train_scaled = TensorMetadata::new("x_train_scaled");
+        x_train_scaled.set_description("The scaled training samples.");
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        x_train_scaled.add_hint(&hint);
+        metadata.add_output(&x_train_scaled);
+
+        let x_test_scaled = TensorMetadata::new("x_test_scaled");
+        x_test_scaled.set_description("The scaled test samples.");
+        let hint = supported_shapes(
+            &[ElementType::F64],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        x_test_scaled.add_hint(&hint);
+        metadata.add_output(&x_test_scaled);
+
+        let parameters = TensorMetadata::new("parameters");
+        parameters.set_description(
+            "The fitted `mean` and `scale` for each column, serialized as JSON.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::Utf8],
+            DimensionsParam::Fixed(&[1]),
+        );
+        parameters.add_hint(&hint);
+        metadata.add_output(&parameters);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "x_train",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_input_tensor(
+            "x_test",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "x_train_scaled",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "x_test_scaled",
+            ElementType::F64,
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        ctx.add_output_tensor(
+            "parameters",
+            ElementType::Utf8,
+            DimensionsParam::Fixed(&[1]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let x_train = get_input(&ctx, "x_train")?;
+        let x_train = view_2d(&x_train, "x_train")?;
+        let x_test = get_input(&ctx, "x_test")?;
+        let x_test = view_2d(&x_test, "x_test")?;
+
+        let scaler = fit(x_train, settings)?;
+        let x_train_scaled = scaler.transform(x_train);
+        let x_test_scaled = transform(&scaler, x_test)?;
+        let serialized = serde_json::to_string(&scaler)
+            .map_err(|e| KernelError::Other(e.to_string()))?;
+
+        for (name, scaled) in [
+            ("x_train_scaled", &x_train_scaled),
+            ("x_test_scaled", &x_test_scaled),
+        ] {
+            let (rows, columns) = scaled.dim();
+            ctx.set_output_tensor(
+                name,
+                TensorParam {
+                    element_type: ElementType::F64,
+                    dimensions: &[rows as u32, columns as u32],
+                    buffer: scaled.as_slice().unwrap().as_bytes(),
+                },
+            );
+        }
+
+        let mut builder = StringBuilder::new();
+        builder.push(&serialized);
+        ctx.set_output_tensor(
+            "parameters",
+            TensorParam {
+                element_type: ElementType::Utf8,
+                dimensions: &[1],
+                buffer: &builder.finish(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(name: &str, reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: name.to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+fn get_input(
+    ctx: &KernelContext,
+    name: &str,
+) -> Result<TensorResult, KernelError> {
+    let tensor = ctx.get_input_tensor(name).ok_or_else(|| {
+        KernelError::InvalidInput(InvalidInput {
+            name: name.to_string(),
+            reason: BadInputReason::NotFound,
+        })
+    })?;
+
+    if tensor.element_type != ElementType::F64 {
+        return Err(invalid_input(
+            name,
+            format!("Expected a f64 tensor, found {}", tensor.element_type),
+        ));
+    }
+
+    Ok(tensor)
+}
+
+fn view_2d<'a>(
+    tensor: &'a TensorResult,
+    name: &str,
+) -> Result<ArrayView2<'a, f64>, KernelError> {
+    tensor
+        .buffer
+        .try_view::<f64, _>(&tensor.dimensions)
+        .map_err(|e| invalid_input(name, e))
+}
+
+/// The parameters learned from the training samples.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StandardScaler {
+    /// The value subtracted from each column (all zeroes when `with_mean`
+    /// is disabled).
+    pub mean: Vec<f64>,
+    /// The value each column is divided by (all ones when `with_std` is
+    /// disabled).
+    pub scale: Vec<f64>,
+}
+
+impl StandardScaler {
+    /// Calculate the mean and standard deviation of each column.
+    ///
+    /// Constant columns get a `scale` of `1.0` so they are centered instead
+    /// of being divided by zero.
+    pub fn fit(
+        x: ArrayView2<'_, f64>,
+        with_mean: bool,
+        with_std: bool,
+    ) -> Self {
+        let rows = x.nrows() as f64;
+        let mut mean = Vec::with_capacity(x.ncols());
+        let mut scale = Vec::with_capacity(x.ncols());
+
+        for column in x.columns() {
+            let m = column.sum() / rows;
+            let variance =
+                column.iter().map(|&v| (v - m).powi(2)).sum::<f64>() / rows;
+            let std = variance.sqrt();
+
+            mean.push(if with_mean { m } else { 0.0 });
+            scale.push(if with_std && std > 0.0 { std } else { 1.0 });
+        }
+
+        StandardScaler { mean, scale }
+    }
+
+    pub fn features(&self) -> usize { self.mean.len() }
+
+    /// Scale some samples.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if `x` doesn't have [`StandardScaler::features()`]
+    /// columns.
+    pub fn transform(&self, x: ArrayView2<'_, f64>) -> Array2<f64> {
+        assert_eq!(x.ncols(), self.features());
+
+        let mut scaled = x.to_owned();
+
+        for (mut column, (&mean, &scale)) in scaled
+            .columns_mut()
+            .into_iter()
+            .zip(self.mean.iter().zip(&self.scale))
+        {
+            column.mapv_inplace(|v| (v - mean) / scale);
+        }
+
+        scaled
+    }
+}
+
+fn fit(
+    x: ArrayView2<'_, f64>,
+    settings: Settings,
+) -> Result<StandardScaler, KernelError> {
+    if x.nrows() == 0 {
+        return Err(invalid_input("x_train", "No training samples"));
+    }
+
+    Ok(StandardScaler::fit(x, settings.with_mean, settings.with_std))
+}
+
+fn transform(
+    scaler: &StandardScaler,
+    x: ArrayView2<'_, f64>,
+) -> Result<Array2<f64>, KernelError> {
+    if x.ncols() != scaler.features() {
+        return Err(invalid_input(
+            "x_test",
+            format!(
+                "The scaler was fitted with {} features, but the test samples have {}",
+                scaler.features(),
+                x.ncols()
+            ),
+        ));
+    }
+
+    Ok(scaler.transform(x))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    with_mean: bool,
+    with_std: bool,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        Ok(Settings {
+            with_mean: ctx
+                .parse_argument_with_default::<_, E>("with_mean", true)?,
+            with_std: ctx.parse_argument_with_default::<_, E>("with_std", true)?,
+        })
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::{
+        ndarray,
+        testing::{assert_close, assert_tensors_close, Tolerance},
+    };
+
+    const SETTINGS: Settings = Settings {
+        with_mean: true,
+        with_std: true,
+    };
+
+    #[test]
+    fn columns_have_zero_mean_and_unit_variance() {
+        let x = ndarray::arr2(&[[1.0, 10.0], [2.0, 20.0], [3.0, 30.0]]);
+
+        let scaler = fit(x.view(), SETTINGS).unwrap();
+        let scaled = scaler.transform(x.view());
+
+        for column in scaled.columns() {
+            let mean = column.mean().unwrap();
+            let variance = column.mapv(|x| (x - mean).powi(2)).mean().unwrap();
+            assert_close(mean, 0.0, Tolerance::absolute(1e-12));
+            assert_close(variance, 1.0, Tolerance::DEFAULT);
+        }
+    }
+
+    #[test]
+    fn test_samples_use_the_training_parameters() {
+        let x_train = ndarray::arr2(&[[0.0], [2.0]]);
+        let x_test = ndarray::arr2(&[[1.0], [5.0]]);
+
+        let scaler = fit(x_train.view(), SETTINGS).unwrap();
+        let scaled = transform(&scaler, x_test.view()).unwrap();
+
+        assert_eq!(scaler.mean, [1.0]);
+        assert_eq!(scaler.scale, [1.0]);
+        assert_tensors_close(
+            &scaled,
+            &ndarray::arr2(&[[0.0], [4.0]]),
+            Tolerance::DEFAULT,
+        );
+    }
+
+    #[test]
+    fn constant_columns_are_only_centered() {
+        let x = ndarray::arr2(&[[7.0, 1.0], [7.0, 3.0]]);
+
+        let scaler = fit(x.view(), SETTINGS).unwrap();
+
+        assert_eq!(scaler.mean, [7.0, 2.0]);
+        assert_eq!(scaler.scale, [1.0, 1.0]);
+        assert_eq!(scaler.transform(x.view()).column(0).to_vec(), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn disable_centering_and_scaling() {
+        let x = ndarray::arr2(&[[1.0], [3.0]]);
+        let settings = Settings {
+            with_mean: false,
+            with_std: false,
+        };
+
+        let scaler = fit(x.view(), settings).unwrap();
+
+        assert_eq!(scaler.mean, [0.0]);
+        assert_eq!(scaler.scale, [1.0]);
+        assert_eq!(scaler.transform(x.view()), x);
+    }
+
+    #[test]
+    fn the_parameters_can_be_serialized() {
+        let x = ndarray::arr2(&[[1.0, -4.0], [3.0, 4.0]]);
+        let scaler = fit(x.view(), SETTINGS).unwrap();
+
+        let serialized = serde_json::to_string(&scaler).unwrap();
+
+        assert_eq!(serialized, r#"{"mean":[2.0,0.0],"scale":[1.0,4.0]}"#);
+        let round_tripped: StandardScaler =
+            serde_json::from_str(&serialized).unwrap();
+        assert_eq!(round_tripped, scaler);
+    }
+
+    #[test]
+    fn the_number_of_features_must_match() {
+        let x_train = ndarray::arr2(&[[1.0, 2.0], [3.0, 4.0]]);
+        let x_test = ndarray::arr2(&[[1.0, 2.0, 3.0]]);
+        let scaler = fit(x_train.view(), SETTINGS).unwrap();
+
+        assert!(transform(&scaler, x_test.view()).is_err());
+    }
+
+    #[test]
+    fn no_training_samples() {
+        let x = Array2::<f64>::zeros((0, 3));
+
+        assert!(fit(x.view(), SETTINGS).is_err());
+    }
+}
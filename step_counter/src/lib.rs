@@ -0,0 +1,412 @@
+//! A pedometer which counts steps by looking for peaks in the magnitude of
+//! an accelerometer's readings.
+//!
+//! Each reading's magnitude has a slowly moving baseline (mostly gravity)
+//! subtracted from it, then every local maximum which rises more than
+//! `threshold` above the baseline and is at least `min_interval` samples after
+//! the previous step is counted as a new step.
+//!
+//! The running total, the baseline, and whether the signal was rising are
+//! carried over between invocations, so a step which spans two windows is
+//! only counted once.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    prelude::*, runtime_v1::*, state::NodeState, BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// Each node's step detector.
+static DETECTORS: NodeState<StepDetector> = NodeState::new();
+
+/// How much of the previous baseline to keep for each new sample.
+const BASELINE_DECAY: f32 = 0.98;
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Step Counter", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("sensor");
+        metadata.add_tag("accelerometer");
+        metadata.add_tag("stateful");
+
+        let threshold = ArgumentMetadata::new("threshold");
+        threshold.set_description(
+            "How far a peak must rise above the baseline (in the accelerometer's units) to be counted as a step. Lower values are more sensitive.",
+        );
+        threshold.set_default_value("0.15");
+        threshold.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&threshold);
+
+        let min_interval = ArgumentMetadata::new("min_interval");
+        min_interval.set_description(
+            "The minimum number of samples between two steps. This stops a single noisy step from being counted multiple times.",
+        );
+        min_interval.set_default_value("10");
+        min_interval
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&min_interval);
+
+        let acceleration = TensorMetadata::new("acceleration");
+        acceleration.set_description(
+            "A window of accelerometer readings, either as `[samples, 3]` (x, y, z) or `[samples]` magnitudes.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Dynamic);
+        acceleration.add_hint(&hint);
+        metadata.add_input(&acceleration);
+
+        let total_steps = TensorMetadata::new("total_steps");
+        total_steps.set_description("The number of steps counted so far.");
+        let hint =
+            supported_shapes(&[ElementType::U32], DimensionsParam::Fixed(&[1]));
+        total_steps.add_hint(&hint);
+        metadata.add_output(&total_steps);
+
+        let steps = TensorMetadata::new("steps");
+        steps.set_description("The number of steps found in this window.");
+        let hint =
+            supported_shapes(&[ElementType::U32], DimensionsParam::Fixed(&[1]));
+        steps.add_hint(&hint);
+        metadata.add_output(&steps);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "acceleration",
+            ElementType::F32,
+            DimensionsParam::Dynamic,
+        );
+        ctx.add_output_tensor(
+            "total_steps",
+            ElementType::U32,
+            DimensionsParam::Fixed(&[1]),
+        );
+        ctx.add_output_tensor(
+            "steps",
+            ElementType::U32,
+            DimensionsParam::Fixed(&[1]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("acceleration").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "acceleration".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::F32 {
+            return Err(invalid_acceleration(format!(
+                "Expected a f32 tensor, found {}",
+                element_type
+            )));
+        }
+
+        let acceleration = buffer
+            .try_elements_with_shape(&dimensions)
+            .map_err(invalid_acceleration)?;
+        let magnitudes = magnitudes(acceleration, &dimensions)?;
+
+        let (steps, total_steps) = DETECTORS.with(&id, |detector| {
+            let steps = detector.update(&magnitudes, settings);
+            (steps, detector.total)
+        });
+
+        ctx.set_output_tensor(
+            "total_steps",
+            TensorParam {
+                element_type: ElementType::U32,
+                dimensions: &[1],
+                buffer: [total_steps].as_bytes(),
+            },
+        );
+        ctx.set_output_tensor(
+            "steps",
+            TensorParam {
+                element_type: ElementType::U32,
+                dimensions: &[1],
+                buffer: [steps].as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_acceleration(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "acceleration".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+/// Get the magnitude of each accelerometer reading.
+fn magnitudes(
+    readings: &[f32],
+    dimensions: &[u32],
+) -> Result<Vec<f32>, KernelError> {
+    match *dimensions {
+        [_] => Ok(readings.to_vec()),
+        [_, 3] => Ok(readings
+            .chunks_exact(3)
+            .map(|xyz| xyz.iter().map(|v| v * v).sum::<f32>().sqrt())
+            .collect()),
+        _ => Err(invalid_acceleration(format!(
+            "Expected a [samples] or [samples, 3] tensor, found {:?}",
+            dimensions
+        ))),
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    threshold: f32,
+    min_interval: u32,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let threshold: f32 =
+            ctx.parse_argument_with_default::<_, E>("threshold", 0.15)?;
+        let min_interval =
+            ctx.parse_argument_with_default::<_, E>("min_interval", 10)?;
+
+        if !(threshold > 0.0 && threshold.is_finite()) {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "threshold",
+                "The threshold must be a positive number",
+            )));
+        }
+
+        Ok(Settings {
+            threshold,
+            min_interval,
+        })
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+struct StepDetector {
+    /// The total number of steps seen so far.
+    total: u32,
+    /// A moving average of the magnitude, or `None` if we haven't seen any
+    /// samples yet.
+    baseline: Option<f32>,
+    /// The previous sample, with the baseline removed.
+    previous: Option<f32>,
+    /// Was the signal rising at the previous sample?
+    rising: bool,
+    /// How many samples ago the last step was, or `None` if there haven't
+    /// been any steps.
+    since_last_step: Option<u32>,
+}
+
+impl StepDetector {
+    /// Feed the detector a new window of magnitudes, returning the number of
+    /// steps that were detected.
+    fn update(&mut self, magnitudes: &[f32], settings: Settings) -> u32 {
+        let mut steps = 0;
+
+        for &magnitude in magnitudes {
+            let baseline = match self.baseline {
+                Some(b) => {
+                    BASELINE_DECAY * b + (1.0 - BASELINE_DECAY) * magnitude
+                },
+                None => magnitude,
+            };
+            self.baseline = Some(baseline);
+            let value = magnitude - baseline;
+
+            self.since_last_step =
+                self.since_last_step.map(|n| n.saturating_add(1));
+
+            if let Some(previous) = self.previous {
+                let is_peak = self.rising && value < previous;
+
+                if is_peak && self.is_step(previous, settings) {
+                    steps += 1;
+                    // The step was at the previous sample
+                    self.since_last_step = Some(1);
+                }
+
+                if value != previous {
+                    self.rising = value > previous;
+                }
+            }
+
+            self.previous = Some(value);
+        }
+
+        self.total = self.total.saturating_add(steps);
+        steps
+    }
+
+    fn is_step(&self, peak: f32, settings: Settings) -> bool {
+        let far_enough_apart = match self.since_last_step {
+            Some(n) => n > settings.min_interval,
+            None => true,
+        };
+
+        peak > settings.threshold && far_enough_apart
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: Settings = Settings {
+        threshold: 0.15,
+        min_interval: 10,
+    };
+
+    /// Simulate someone walking at 2 steps per second, sampled at 50 Hz.
+    fn walking(seconds: usize) -> Vec<f32> {
+        (0..seconds * 50)
+            .map(|i| {
+                let t = i as f32 / 50.0;
+                1.0 + 0.4 * (2.0 * std::f32::consts::PI * 2.0 * t).sin()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn count_steps_while_walking() {
+        let mut detector = StepDetector::default();
+
+        let steps = detector.update(&walking(10), SETTINGS);
+
+        assert_eq!(steps, 20);
+        assert_eq!(detector.total, 20);
+    }
+
+    #[test]
+    fn standing_still_has_no_steps() {
+        let mut detector = StepDetector::default();
+        let noisy: Vec<f32> = (0..500)
+            .map(|i| 1.0 + if i % 2 == 0 { 0.01 } else { -0.01 })
+            .collect();
+
+        assert_eq!(detector.update(&noisy, SETTINGS), 0);
+    }
+
+    #[test]
+    fn steps_spanning_windows_are_only_counted_once() {
+        let signal = walking(10);
+        let mut whole = StepDetector::default();
+        let mut windowed = StepDetector::default();
+
+        let expected = whole.update(&signal, SETTINGS);
+        let per_window: Vec<u32> = signal
+            .chunks(13)
+            .map(|window| windowed.update(window, SETTINGS))
+            .collect();
+
+        assert_eq!(per_window.iter().sum::<u32>(), expected);
+        assert_eq!(windowed.total, expected);
+    }
+
+    #[test]
+    fn min_interval_suppresses_double_counting() {
+        // A step with a bit of ringing straight after it
+        let mut signal = vec![1.0_f32; 20];
+        signal.extend([1.5, 1.0, 1.4, 1.0]);
+        signal.extend([1.0; 20]);
+        let mut detector = StepDetector::default();
+
+        assert_eq!(detector.update(&signal, SETTINGS), 1);
+
+        let eager = Settings {
+            min_interval: 0,
+            ..SETTINGS
+        };
+        let mut detector = StepDetector::default();
+        assert_eq!(detector.update(&signal, eager), 2);
+    }
+
+    #[test]
+    fn magnitude_of_3_axis_readings() {
+        let readings = [3.0, 4.0, 0.0, 0.0, 0.0, 1.0];
+
+        let got = magnitudes(&readings, &[2, 3]).unwrap();
+
+        assert_eq!(got, [5.0, 1.0]);
+    }
+
+    #[test]
+    fn other_shapes_are_rejected() {
+        assert!(magnitudes(&[0.0; 4], &[2, 2]).is_err());
+    }
+}
@@ -0,0 +1,627 @@
+//! Seasonal-Trend decomposition using LOESS (STL).
+//!
+//! STL splits a series into three components,
+//!
+//! ```text
+//! series = trend + seasonal + residual
+//! ```
+//!
+//! by alternately smoothing each cycle-subseries (every `period`'th value)
+//! to find the seasonal component and smoothing what's left to find the
+//! trend. This follows [Cleveland et al. (1990)][paper], using locally
+//! linear LOESS for every smoother.
+//!
+//! [paper]: https://www.wessa.net/download/stl.pdf
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    prelude::*, runtime_v1::*, BufferExt, SliceExt,
+};
+use std::cmp::Ordering;
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("STL Decompose", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("time series");
+        metadata.add_tag("decomposition");
+        metadata.add_tag("analytics");
+
+        let period = ArgumentMetadata::new("period");
+        period.set_description(
+            "The number of samples in one seasonal cycle (e.g. 24 for hourly data with a daily pattern).",
+        );
+        period.add_hint(&non_negative_number());
+        period.add_hint(&supported_argument_type(ArgumentType::Integer));
+        metadata.add_argument(&period);
+
+        let seasonal = ArgumentMetadata::new("seasonal");
+        seasonal.set_description(
+            "The span of the LOESS smoother used on each cycle-subseries. This must be odd, and larger values give a seasonal component which changes more slowly.",
+        );
+        seasonal.set_default_value("7");
+        seasonal.add_hint(&non_negative_number());
+        seasonal.add_hint(&supported_argument_type(ArgumentType::Integer));
+        metadata.add_argument(&seasonal);
+
+        let robust = ArgumentMetadata::new("robust");
+        robust.set_description(
+            "Down-weight outliers so they end up in the residual instead of distorting the trend and seasonal components.",
+        );
+        robust.set_default_value("false");
+        robust.add_hint(&interpret_as_string_in_enum(&["true", "false"]));
+        metadata.add_argument(&robust);
+
+        let series = TensorMetadata::new("series");
+        series.set_description(
+            "The time series to decompose. It must contain at least two full periods.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::F32], DimensionsParam::Fixed(&[0]));
+        series.add_hint(&hint);
+        metadata.add_input(&series);
+
+        for (name, description) in [
+            ("trend", "The long-term trend."),
+            ("seasonal", "The repeating pattern with the given period."),
+            ("residual", "Whatever is left over."),
+        ] {
+            let output = TensorMetadata::new(name);
+            output.set_description(description);
+            let hint = supported_shapes(
+                &[ElementType::F32],
+                DimensionsParam::Fixed(&[0]),
+            );
+            output.add_hint(&hint);
+            metadata.add_output(&output);
+        }
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Stl::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "series",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0]),
+        );
+        for name in ["trend", "seasonal", "residual"] {
+            ctx.add_output_tensor(
+                name,
+                ElementType::F32,
+                DimensionsParam::Fixed(&[0]),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let stl = Stl::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            buffer,
+            ..
+        } = ctx.get_input_tensor("series").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "series".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::F32 {
+            return Err(invalid_input(format!(
+                "Expected a f32 tensor, found {}",
+                element_type
+            )));
+        }
+
+        let series: Vec<f64> = buffer
+            .try_elements::<f32>()
+            .map_err(invalid_input)?
+            .iter()
+            .map(|&x| f64::from(x))
+            .collect();
+
+        let Decomposition {
+            trend,
+            seasonal,
+            residual,
+        } = stl.decompose(&series).map_err(invalid_input)?;
+
+        for (name, component) in [
+            ("trend", trend),
+            ("seasonal", seasonal),
+            ("residual", residual),
+        ] {
+            let component: Vec<f32> =
+                component.into_iter().map(|x| x as f32).collect();
+            ctx.set_output_tensor(
+                name,
+                TensorParam {
+                    element_type: ElementType::F32,
+                    dimensions: &[component.len() as u32],
+                    buffer: component.as_bytes(),
+                },
+            );
+        }
+
+        Ok(())
+    }
+}
+
+fn invalid_input(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "series".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+/// The components of a decomposed series.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decomposition {
+    pub trend: Vec<f64>,
+    pub seasonal: Vec<f64>,
+    pub residual: Vec<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecomposeError {
+    TooShort { length: usize, period: usize },
+}
+
+impl std::fmt::Display for DecomposeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecomposeError::TooShort { length, period } => write!(
+                f,
+                "At least two periods ({} samples) are needed, but the series only has {}",
+                2 * period,
+                length
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecomposeError {}
+
+/// The STL parameters.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Stl {
+    /// The number of samples in each seasonal cycle.
+    pub period: usize,
+    /// The span of the cycle-subseries smoother. Must be odd.
+    pub seasonal: usize,
+    /// Use robustness weights to reduce the influence of outliers.
+    pub robust: bool,
+}
+
+impl Stl {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let period: usize = ctx.parse_argument::<_, E>("period")?;
+        let seasonal = ctx.parse_argument_with_default::<_, E>("seasonal", 7)?;
+        let robust = ctx.parse_argument_with_default::<_, E>("robust", false)?;
+
+        if period < 2 {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "period",
+                format!("The period must be at least 2, found {}", period),
+            )));
+        }
+
+        if seasonal < 3 || seasonal % 2 == 0 {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "seasonal",
+                format!(
+                    "The seasonal span must be an odd number of at least 3, found {}",
+                    seasonal
+                ),
+            )));
+        }
+
+        Ok(Stl {
+            period,
+            seasonal,
+            robust,
+        })
+    }
+
+    /// The span of the trend smoother, using the default from the paper.
+    fn trend_span(&self) -> usize {
+        let period = self.period as f64;
+        let seasonal = self.seasonal as f64;
+        let span = (1.5 * period / (1.0 - 1.5 / seasonal)).ceil() as usize;
+        next_odd(span)
+    }
+
+    /// The span of the low-pass filter's smoother.
+    fn low_pass_span(&self) -> usize { next_odd(self.period) }
+
+    pub fn decompose(
+        &self,
+        series: &[f64],
+    ) -> Result<Decomposition, DecomposeError> {
+        let n = series.len();
+        let period = self.period;
+
+        if n < 2 * period {
+            return Err(DecomposeError::TooShort { length: n, period });
+        }
+
+        // The recommended number of passes from the paper
+        let (inner, outer) = if self.robust { (1, 15) } else { (2, 0) };
+
+        let mut trend = vec![0.0; n];
+        let mut seasonal = vec![0.0; n];
+        let mut weights = vec![1.0; n];
+
+        for pass in 0..=outer {
+            for _ in 0..inner {
+                self.inner_loop(series, &weights, &mut seasonal, &mut trend);
+            }
+
+            if pass < outer {
+                weights = robustness_weights(series, &seasonal, &trend);
+            }
+        }
+
+        let residual = series
+            .iter()
+            .zip(&seasonal)
+            .zip(&trend)
+            .map(|((y, s), t)| y - s - t)
+            .collect();
+
+        Ok(Decomposition {
+            trend,
+            seasonal,
+            residual,
+        })
+    }
+
+    fn inner_loop(
+        &self,
+        series: &[f64],
+        weights: &[f64],
+        seasonal: &mut [f64],
+        trend: &mut [f64],
+    ) {
+        let n = series.len();
+        let period = self.period;
+
+        // Step 1: Detrending
+        let detrended: Vec<f64> =
+            series.iter().zip(&*trend).map(|(y, t)| y - t).collect();
+
+        // Step 2: Smooth each cycle-subseries, extending it by one value on
+        // either end
+        let mut cycles = vec![0.0; n + 2 * period];
+        for offset in 0..period {
+            let subseries = |x: &[f64]| -> Vec<f64> {
+                x.iter().skip(offset).step_by(period).copied().collect()
+            };
+            let values = subseries(&detrended);
+            let w = subseries(weights);
+
+            for (i, x) in (-1..=values.len() as isize).enumerate() {
+                cycles[offset + i * period] =
+                    loess(&values, &w, self.seasonal, x as f64);
+            }
+        }
+
+        // Step 3: Low-pass filter the smoothed cycle-subseries
+        let filtered = moving_average(&cycles, period);
+        let filtered = moving_average(&filtered, period);
+        let filtered = moving_average(&filtered, 3);
+        let ones = vec![1.0; n];
+        let low_pass: Vec<f64> = (0..n)
+            .map(|i| loess(&filtered, &ones, self.low_pass_span(), i as f64))
+            .collect();
+
+        // Step 4: Detrending of the smoothed cycle-subseries
+        for (i, s) in seasonal.iter_mut().enumerate() {
+            *s = cycles[i + period] - low_pass[i];
+        }
+
+        // Step 5: Deseasonalizing
+        let deseasonalized: Vec<f64> =
+            series.iter().zip(&*seasonal).map(|(y, s)| y - s).collect();
+
+        // Step 6: Trend smoothing
+        let span = self.trend_span();
+        for (i, t) in trend.iter_mut().enumerate() {
+            *t = loess(&deseasonalized, weights, span, i as f64);
+        }
+    }
+}
+
+fn next_odd(n: usize) -> usize {
+    if n % 2 == 0 {
+        n + 1
+    } else {
+        n
+    }
+}
+
+/// Evaluate a locally linear LOESS fit of `values` at position `x`, using
+/// the `span` nearest values.
+///
+/// `x` may be outside the series, which is how the cycle-subseries are
+/// extrapolated.
+fn loess(values: &[f64], weights: &[f64], span: usize, x: f64) -> f64 {
+    let n = values.len();
+    let q = span.min(n);
+
+    // The q values closest to x
+    let left = (x.round() as isize - (q as isize - 1) / 2)
+        .clamp(0, (n - q) as isize) as usize;
+    let right = left + q - 1;
+
+    let mut max_distance = (x - left as f64).max(right as f64 - x);
+    if span > n {
+        max_distance += ((span - n) / 2) as f64;
+    }
+
+    let mut local_weights: Vec<f64> = (left..=right)
+        .map(|j| {
+            let distance = (j as f64 - x).abs();
+            tricube(distance / max_distance.max(f64::EPSILON)) * weights[j]
+        })
+        .collect();
+
+    let total: f64 = local_weights.iter().sum();
+    if total <= 0.0 {
+        // Every neighbour was down-weighted to nothing, so fall back to the
+        // closest value
+        let nearest = (x.round().max(0.0) as usize).min(n - 1);
+        return values[nearest];
+    }
+    local_weights.iter_mut().for_each(|w| *w /= total);
+
+    // Adjust the weights so they fit a line instead of a constant
+    let mean: f64 = (left..=right)
+        .zip(&local_weights)
+        .map(|(j, w)| w * j as f64)
+        .sum();
+    let variance: f64 = (left..=right)
+        .zip(&local_weights)
+        .map(|(j, w)| w * (j as f64 - mean).powi(2))
+        .sum();
+
+    if variance.sqrt() > 0.001 * (n as f64 - 1.0) {
+        let slope = (x - mean) / variance;
+        for (j, w) in (left..=right).zip(&mut local_weights) {
+            *w *= slope * (j as f64 - mean) + 1.0;
+        }
+    }
+
+    (left..=right)
+        .zip(&local_weights)
+        .map(|(j, w)| w * values[j])
+        .sum()
+}
+
+fn tricube(u: f64) -> f64 {
+    if u < 1.0 {
+        (1.0 - u.powi(3)).powi(3)
+    } else {
+        0.0
+    }
+}
+
+fn moving_average(values: &[f64], window: usize) -> Vec<f64> {
+    values
+        .windows(window)
+        .map(|w| w.iter().sum::<f64>() / window as f64)
+        .collect()
+}
+
+/// Bisquare weights based on how far each residual is from zero, relative
+/// to 6 times the median absolute residual.
+fn robustness_weights(
+    series: &[f64],
+    seasonal: &[f64],
+    trend: &[f64],
+) -> Vec<f64> {
+    let residuals: Vec<f64> = series
+        .iter()
+        .zip(seasonal)
+        .zip(trend)
+        .map(|((y, s), t)| (y - s - t).abs())
+        .collect();
+
+    let mut sorted = residuals.clone();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let mid = sorted.len() / 2;
+    let median = if sorted.len() % 2 == 0 {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    };
+    let h = 6.0 * median;
+
+    residuals
+        .iter()
+        .map(|&r| {
+            if h <= 0.0 {
+                1.0
+            } else {
+                let u = r / h;
+                if u < 1.0 {
+                    (1.0 - u * u).powi(2)
+                } else {
+                    0.0
+                }
+            }
+        })
+        .collect()
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    const STL: Stl = Stl {
+        period: 12,
+        seasonal: 7,
+        robust: false,
+    };
+
+    /// A linear trend plus a sine wave with a period of 12 samples.
+    fn trend_and_season(length: usize) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+        let trend: Vec<f64> =
+            (0..length).map(|t| 10.0 + 0.5 * t as f64).collect();
+        let season: Vec<f64> = (0..length)
+            .map(|t| 3.0 * (2.0 * PI * t as f64 / 12.0).sin())
+            .collect();
+        let series = trend.iter().zip(&season).map(|(t, s)| t + s).collect();
+
+        (series, trend, season)
+    }
+
+    fn max_error(actual: &[f64], expected: &[f64]) -> f64 {
+        actual
+            .iter()
+            .zip(expected)
+            .map(|(a, e)| (a - e).abs())
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn components_add_up_to_the_series() {
+        let (series, _, _) = trend_and_season(60);
+
+        let d = STL.decompose(&series).unwrap();
+
+        for (i, y) in series.iter().enumerate() {
+            let total = d.trend[i] + d.seasonal[i] + d.residual[i];
+            assert!((total - y).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn recover_a_linear_trend_and_sine_wave() {
+        let (series, trend, season) = trend_and_season(120);
+
+        let d = STL.decompose(&series).unwrap();
+
+        assert!(max_error(&d.trend, &trend) < 0.3, "{:?}", d.trend);
+        assert!(max_error(&d.seasonal, &season) < 0.3, "{:?}", d.seasonal);
+        assert!(max_error(&d.residual, &vec![0.0; 120]) < 0.3);
+    }
+
+    #[test]
+    fn robust_fitting_puts_outliers_in_the_residual() {
+        let (mut series, trend, _) = trend_and_season(120);
+        // Robustness weights are relative to the typical residual, so we
+        // need a bit of noise for them to make sense
+        for (t, y) in series.iter_mut().enumerate() {
+            *y += 0.1 * (t as f64 * 2.3).sin();
+        }
+        series[50] += 100.0;
+        let stl = Stl {
+            robust: true,
+            ..STL
+        };
+
+        let d = stl.decompose(&series).unwrap();
+
+        assert!(d.residual[50] > 90.0, "{}", d.residual[50]);
+        assert!(max_error(&d.trend, &trend) < 0.5, "{:?}", d.trend);
+    }
+
+    #[test]
+    fn at_least_two_periods_are_required() {
+        let series = vec![1.0; 23];
+
+        let err = STL.decompose(&series).unwrap_err();
+
+        assert_eq!(
+            err,
+            DecomposeError::TooShort {
+                length: 23,
+                period: 12
+            }
+        );
+    }
+
+    #[test]
+    fn loess_reproduces_a_straight_line() {
+        let values: Vec<f64> = (0..10).map(|x| 2.0 * x as f64 + 1.0).collect();
+        let weights = vec![1.0; 10];
+
+        for x in [-1.0, 0.0, 4.0, 9.0, 10.0] {
+            let got = loess(&values, &weights, 5, x);
+            assert!((got - (2.0 * x + 1.0)).abs() < 1e-9, "{} {}", x, got);
+        }
+    }
+
+    #[test]
+    fn default_spans() {
+        assert_eq!(STL.trend_span(), 23);
+        assert_eq!(STL.low_pass_span(), 13);
+    }
+}
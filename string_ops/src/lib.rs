@@ -0,0 +1,361 @@
+//! Common string operations which treat text as a sequence of graphemes.
+//!
+//! A grapheme cluster is what a person would call a single character, so
+//! "é" written as `e` plus a combining accent, or a family emoji made up of
+//! several code points joined together, are each counted once. Counting
+//! bytes (or even `char`s) gives surprising results for anything outside of
+//! ASCII.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    prelude::*, runtime_v1::*, BufferExt, SliceExt, StringBuilder,
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("String Operations", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("text");
+        metadata.add_tag("string");
+        metadata.add_tag("unicode");
+        metadata.with_const_eval();
+
+        let operation = ArgumentMetadata::new("operation");
+        operation.set_description(
+            "The operation to apply to each string. `length` outputs the number of graphemes as a `u32`, while the others output strings.",
+        );
+        operation.set_default_value("length");
+        operation.add_hint(&interpret_as_string_in_enum(Operation::NAMES));
+        metadata.add_argument(&operation);
+
+        let start = ArgumentMetadata::new("start");
+        start.set_description(
+            "The index of the first grapheme to keep when taking a `substring`.",
+        );
+        start.set_default_value("0");
+        start.add_hint(&non_negative_number());
+        start.add_hint(&supported_argument_type(ArgumentType::Integer));
+        metadata.add_argument(&start);
+
+        let length = ArgumentMetadata::new("length");
+        length.set_description(
+            "The maximum number of graphemes to keep when taking a `substring`. The rest of the string is kept when this isn't provided.",
+        );
+        length.add_hint(&non_negative_number());
+        length.add_hint(&supported_argument_type(ArgumentType::Integer));
+        metadata.add_argument(&length);
+
+        let input = TensorMetadata::new("text");
+        input.set_description("The strings to operate on.");
+        let hint =
+            supported_shapes(&[ElementType::Utf8], DimensionsParam::Dynamic);
+        input.add_hint(&hint);
+        metadata.add_input(&input);
+
+        let output = TensorMetadata::new("output");
+        output.set_description(
+            "The result of applying the operation to each string, with the same shape as the input.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::Utf8, ElementType::U32],
+            DimensionsParam::Dynamic,
+        );
+        output.add_hint(&hint);
+        metadata.add_output(&output);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        let operation = Operation::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "text",
+            ElementType::Utf8,
+            DimensionsParam::Dynamic,
+        );
+        ctx.add_output_tensor(
+            "output",
+            operation.output_type(),
+            DimensionsParam::Dynamic,
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let operation = Operation::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("text").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "text".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::Utf8 {
+            return Err(invalid_input(format!(
+                "Expected a utf8 tensor, found {}",
+                element_type
+            )));
+        }
+
+        let strings = buffer.strings().map_err(invalid_input)?;
+
+        let output = match operation {
+            Operation::Length => {
+                let lengths: Vec<u32> =
+                    strings.iter().map(|s| grapheme_count(s) as u32).collect();
+                lengths.as_bytes().to_vec()
+            },
+            other => {
+                let mut builder = StringBuilder::new();
+                for s in &strings {
+                    builder.push(&other.apply(s));
+                }
+                builder.finish()
+            },
+        };
+
+        ctx.set_output_tensor(
+            "output",
+            TensorParam {
+                element_type: operation.output_type(),
+                dimensions: &dimensions,
+                buffer: &output,
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "text".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+/// The number of user-perceived characters in a string.
+pub fn grapheme_count(text: &str) -> usize { text.graphemes(true).count() }
+
+/// Get up to `length` graphemes, starting from the `start`'th grapheme.
+///
+/// Indices past the end of the string give an empty string rather than
+/// panicking.
+pub fn substring(text: &str, start: usize, length: Option<usize>) -> &str {
+    let mut boundaries = text
+        .grapheme_indices(true)
+        .map(|(index, _)| index)
+        .chain(std::iter::once(text.len()));
+
+    let first = match boundaries.nth(start) {
+        Some(index) => index,
+        None => return "",
+    };
+
+    let last = match length {
+        Some(0) => first,
+        Some(length) => boundaries.nth(length - 1).unwrap_or(text.len()),
+        None => text.len(),
+    };
+
+    &text[first..last]
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Operation {
+    /// Count the graphemes in each string.
+    Length,
+    Substring {
+        start: usize,
+        length: Option<usize>,
+    },
+    ToLower,
+    ToUpper,
+    /// Remove leading and trailing whitespace.
+    Strip,
+}
+
+impl Operation {
+    pub const NAMES: &'static [&'static str] =
+        &["length", "substring", "to_lower", "to_upper", "strip"];
+
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let name = ctx.parse_argument_with_default::<_, E>(
+            "operation",
+            String::from("length"),
+        )?;
+
+        match name.as_str() {
+            "length" => Ok(Operation::Length),
+            "substring" => {
+                let start =
+                    ctx.parse_argument_with_default::<_, E>("start", 0)?;
+                let length = match ctx._get_argument("length") {
+                    Some(_) => Some(ctx.parse_argument::<usize, E>("length")?),
+                    None => None,
+                };
+                Ok(Operation::Substring { start, length })
+            },
+            "to_lower" => Ok(Operation::ToLower),
+            "to_upper" => Ok(Operation::ToUpper),
+            "strip" => Ok(Operation::Strip),
+            _ => Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "operation",
+                format!(
+                    "Unknown operation, \"{}\". Expected one of {:?}",
+                    name,
+                    Operation::NAMES
+                ),
+            ))),
+        }
+    }
+
+    pub fn output_type(self) -> ElementType {
+        match self {
+            Operation::Length => ElementType::U32,
+            _ => ElementType::Utf8,
+        }
+    }
+
+    /// Apply a string-to-string operation.
+    ///
+    /// [`Operation::Length`] gives the number of graphemes as a string.
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            Operation::Length => grapheme_count(text).to_string(),
+            Operation::Substring { start, length } => {
+                substring(text, start, length).to_string()
+            },
+            Operation::ToLower => text.to_lowercase(),
+            Operation::ToUpper => text.to_uppercase(),
+            Operation::Strip => text.trim().to_string(),
+        }
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A family emoji, made from 4 people joined by zero-width joiners.
+    const FAMILY: &str = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}\u{200d}\u{1f466}";
+
+    #[test]
+    fn length_counts_graphemes_not_bytes() {
+        let inputs = [
+            ("hello", 5),
+            ("cafe\u{301}", 4),
+            ("日本語", 3),
+            (FAMILY, 1),
+            ("", 0),
+        ];
+
+        for (text, expected) in inputs {
+            assert_eq!(grapheme_count(text), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn substring_keeps_combining_marks_together() {
+        let got = substring("cafe\u{301} au lait", 3, Some(1));
+
+        assert_eq!(got, "e\u{301}");
+    }
+
+    #[test]
+    fn substring_without_a_length_keeps_the_rest() {
+        let text = format!("{}!", FAMILY);
+
+        let got = substring(&text, 1, None);
+
+        assert_eq!(got, "!");
+    }
+
+    #[test]
+    fn substring_past_the_end() {
+        assert_eq!(substring("abc", 1, Some(10)), "bc");
+        assert_eq!(substring("abc", 3, None), "");
+        assert_eq!(substring("abc", 4, Some(1)), "");
+        assert_eq!(substring("abc", 0, Some(0)), "");
+    }
+
+    #[test]
+    fn case_conversion_and_stripping() {
+        let inputs = [
+            (Operation::ToLower, "ÉCOLE", "école"),
+            (Operation::ToUpper, "straße", "STRASSE"),
+            (Operation::Strip, "\t  hello world \n", "hello world"),
+            (Operation::Length, FAMILY, "1"),
+        ];
+
+        for (operation, text, expected) in inputs {
+            assert_eq!(operation.apply(text), expected, "{:?}", operation);
+        }
+    }
+}
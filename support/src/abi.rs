@@ -0,0 +1,60 @@
+//! Information the host can use to make sure it knows how to talk to a
+//! proc-block.
+//!
+//! Every proc-block linked against this crate gets a custom section named
+//! [`ABI_VERSION_SECTION`] containing [`ABI_VERSION`] as a little-endian
+//! `u32`, so hosts can detect an incompatible proc-block *before* trying to
+//! instantiate it. The same information is available at runtime through the
+//! `hotg_proc_block_abi_version()` and `hotg_proc_block_abi_compatible()`
+//! exports.
+//!
+//! # Constant Folding
+//!
+//! A proc-block tagged with [`CONST_EVAL_TAG`] promises that its `kernel()`
+//! is a pure function of its arguments and inputs. It doesn't keep state
+//! between calls, talk to the host beyond reading inputs and setting
+//! outputs, or look at the clock or a random number generator.
+//!
+//! When every input to such a node is a compile-time constant, the host may
+//! call `kernel()` once while it is building the graph, after `graph()` has
+//! returned, and replace the node with the tensors it produced. The
+//! `KernelContext` passed to that call behaves exactly like it would during
+//! inference, so there is no separate hook to implement and hosts which
+//! don't support folding can keep ignoring the tag.
+
+/// The name of the custom section containing the ABI version.
+pub const ABI_VERSION_SECTION: &str = "hotg.proc-block.abi-version";
+
+/// The version of the host interface this crate was compiled against.
+///
+/// This gets bumped whenever `runtime-v1.wit` or `proc-block-v1.wit` change
+/// in a way that older hosts won't understand.
+pub const ABI_VERSION: u32 = 1;
+
+/// The tag used to declare that a proc-block can be evaluated at graph-build
+/// time (see the [module docs](self#constant-folding)).
+pub const CONST_EVAL_TAG: &str = "const-eval";
+
+/// Can a proc-block compiled against this crate be used by a host that
+/// implements `host_version`?
+pub const fn is_compatible(host_version: u32) -> bool {
+    host_version == ABI_VERSION
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "runtime_v1"))]
+#[used]
+#[link_section = "hotg.proc-block.abi-version"]
+static ABI_VERSION_CUSTOM_SECTION: [u8; 4] = ABI_VERSION.to_le_bytes();
+
+/// Get the [`ABI_VERSION`] this proc-block was compiled against.
+#[cfg(all(target_arch = "wasm32", feature = "runtime_v1"))]
+#[no_mangle]
+pub extern "C" fn hotg_proc_block_abi_version() -> u32 { ABI_VERSION }
+
+/// Check whether this proc-block can be used by a host implementing
+/// `host_version`, returning `1` if it is compatible and `0` otherwise.
+#[cfg(all(target_arch = "wasm32", feature = "runtime_v1"))]
+#[no_mangle]
+pub extern "C" fn hotg_proc_block_abi_compatible(host_version: u32) -> u32 {
+    is_compatible(host_version) as u32
+}
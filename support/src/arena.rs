@@ -0,0 +1,311 @@
+//! A bump allocator for temporary buffers which only live for a single
+//! invocation of a proc-block's kernel.
+//!
+//! Proc-blocks like `fft` and `image-normalization` allocate large buffers
+//! every time they run, and over a long-running deployment that can fragment
+//! the WebAssembly heap. Allocating those buffers from an [`Arena`] means the
+//! same memory gets reused run after run.
+//!
+//! Most proc-blocks should use [`scoped()`], which hands out a shared arena
+//! and resets it once the kernel is done.
+
+use std::{
+    cell::RefCell,
+    mem,
+    ptr::NonNull,
+    sync::{Mutex, TryLockError},
+};
+
+use once_cell::sync::Lazy;
+
+use crate::ValueType;
+
+/// Every chunk is made of `u64`'s so it is suitably aligned for any
+/// [`ValueType`].
+type Word = u64;
+
+const WORD_SIZE: usize = mem::size_of::<Word>();
+const MIN_CHUNK_SIZE: usize = 4096;
+
+/// Run `func` with an arena for temporary allocations, resetting it
+/// afterwards so the next run can reuse the memory.
+///
+/// Arenas can't be shared, so nested calls will get a fresh [`Arena`] which
+/// gets thrown away when they return.
+///
+/// # Examples
+///
+/// ```rust
+/// use hotg_rune_proc_blocks::{arena, SliceExt};
+///
+/// let bytes = arena::scoped(|arena| {
+///     let doubled = arena.alloc_slice_copy(&[1.0_f32, 2.0, 3.0]);
+///     doubled.iter_mut().for_each(|x| *x *= 2.0);
+///
+///     // Pass the buffer to the host (e.g. with set_output_tensor()) before
+///     // the arena is reset
+///     doubled.as_bytes().len()
+/// });
+///
+/// assert_eq!(bytes, 3 * std::mem::size_of::<f32>());
+/// ```
+pub fn scoped<R>(func: impl FnOnce(&Arena) -> R) -> R {
+    static ARENA: Lazy<Mutex<Arena>> = Lazy::new(|| Mutex::new(Arena::new()));
+
+    let mut arena = match ARENA.try_lock() {
+        Ok(arena) => arena,
+        // A previous kernel panicked, but the arena is still usable
+        Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(TryLockError::WouldBlock) => return func(&Arena::new()),
+    };
+
+    let result = func(&arena);
+    arena.reset();
+    result
+}
+
+/// A bump allocator which hands out slices of [`ValueType`]s.
+///
+/// Allocations are never freed individually. Instead, everything is released
+/// at once by [`Arena::reset()`], which also merges the arena's memory into a
+/// single chunk big enough for everything allocated since the last reset.
+#[derive(Debug, Default)]
+pub struct Arena {
+    chunks: RefCell<Chunks>,
+}
+
+impl Arena {
+    pub fn new() -> Self { Arena::default() }
+
+    /// Create an arena which can hold `bytes` bytes before it needs to
+    /// allocate.
+    pub fn with_capacity(bytes: usize) -> Self {
+        Arena {
+            chunks: RefCell::new(Chunks {
+                current: Chunk::new(bytes),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Allocate a slice with `len` copies of `value`.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice<T>(&self, len: usize, value: T) -> &mut [T]
+    where
+        T: ValueType + Copy,
+    {
+        let ptr = self.alloc_raw::<T>(len);
+
+        unsafe {
+            for i in 0..len {
+                ptr.as_ptr().add(i).write(value);
+            }
+            std::slice::from_raw_parts_mut(ptr.as_ptr(), len)
+        }
+    }
+
+    /// Allocate a copy of `items`.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_copy<T>(&self, items: &[T]) -> &mut [T]
+    where
+        T: ValueType + Copy,
+    {
+        let ptr = self.alloc_raw::<T>(items.len());
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                items.as_ptr(),
+                ptr.as_ptr(),
+                items.len(),
+            );
+            std::slice::from_raw_parts_mut(ptr.as_ptr(), items.len())
+        }
+    }
+
+    /// Allocate a slice from the items in an iterator.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the iterator yields fewer than `len` items.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_from_iter<T, I>(
+        &self,
+        len: usize,
+        items: I,
+    ) -> &mut [T]
+    where
+        T: ValueType + Copy,
+        I: IntoIterator<Item = T>,
+    {
+        let ptr = self.alloc_raw::<T>(len);
+        let mut items = items.into_iter();
+
+        unsafe {
+            for i in 0..len {
+                let item = items
+                    .next()
+                    .expect("The iterator yielded too few items");
+                ptr.as_ptr().add(i).write(item);
+            }
+            std::slice::from_raw_parts_mut(ptr.as_ptr(), len)
+        }
+    }
+
+    /// The number of bytes used since the last [`Arena::reset()`], including
+    /// padding and any space left at the end of full chunks.
+    pub fn allocated_bytes(&self) -> usize {
+        let chunks = self.chunks.borrow();
+        chunks.used + chunks.retired.iter().map(Chunk::size).sum::<usize>()
+    }
+
+    /// The number of bytes this arena has reserved from the heap.
+    pub fn capacity(&self) -> usize {
+        let chunks = self.chunks.borrow();
+        chunks.current.size()
+            + chunks.retired.iter().map(Chunk::size).sum::<usize>()
+    }
+
+    /// Free everything allocated from this arena so the memory can be reused.
+    pub fn reset(&mut self) {
+        let chunks = self.chunks.get_mut();
+
+        if !chunks.retired.is_empty() {
+            // Replace all the chunks with a single one big enough to hold
+            // everything, so the next run won't need to allocate.
+            let total = chunks.current.size()
+                + chunks.retired.iter().map(Chunk::size).sum::<usize>();
+            chunks.retired.clear();
+            chunks.current = Chunk::new(total);
+        }
+
+        chunks.used = 0;
+    }
+
+    fn alloc_raw<T: ValueType>(&self, len: usize) -> NonNull<T> {
+        assert!(mem::align_of::<T>() <= mem::align_of::<Word>());
+
+        let size = mem::size_of::<T>()
+            .checked_mul(len)
+            .expect("Allocation size overflowed");
+
+        self.chunks
+            .borrow_mut()
+            .bump(size, mem::align_of::<T>())
+            .cast()
+    }
+}
+
+// Safety: Each chunk is a heap allocation which is uniquely owned by the
+// arena, so moving the arena to another thread moves the chunks with it.
+// Anything handed out by alloc() borrows the arena, so no references into a
+// chunk can outlive that move. The RefCell keeps Arena !Sync, which is what
+// stops two threads from using it at once.
+unsafe impl Send for Arena {}
+
+#[derive(Debug, Default)]
+struct Chunks {
+    current: Chunk,
+    /// The number of bytes used in the current chunk.
+    used: usize,
+    /// Chunks which are full, but may still be borrowed.
+    retired: Vec<Chunk>,
+}
+
+impl Chunks {
+    fn bump(&mut self, size: usize, alignment: usize) -> NonNull<u8> {
+        let mut start = round_up(self.used, alignment);
+
+        if start + size > self.current.size() {
+            let new_size = usize::max(self.current.size() * 2, size)
+                .max(MIN_CHUNK_SIZE);
+            let full = mem::replace(&mut self.current, Chunk::new(new_size));
+            if full.size() > 0 {
+                self.retired.push(full);
+            }
+            start = 0;
+        }
+
+        self.used = start + size;
+
+        unsafe {
+            let base = self.current.ptr.as_ptr().cast::<u8>();
+            NonNull::new_unchecked(base.add(start))
+        }
+    }
+}
+
+fn round_up(n: usize, alignment: usize) -> usize {
+    (n + alignment - 1) / alignment * alignment
+}
+
+/// A heap allocation which is only ever accessed through raw pointers, so
+/// handing out references into it won't be invalidated by moving the chunk
+/// around.
+#[derive(Debug)]
+struct Chunk {
+    ptr: NonNull<Word>,
+    words: usize,
+}
+
+impl Chunk {
+    fn new(bytes: usize) -> Self {
+        let words = (bytes + WORD_SIZE - 1) / WORD_SIZE;
+        let mut buffer = mem::ManuallyDrop::new(vec![0 as Word; words]);
+
+        Chunk {
+            ptr: NonNull::new(buffer.as_mut_ptr()).unwrap(),
+            words: buffer.capacity(),
+        }
+    }
+
+    fn size(&self) -> usize { self.words * WORD_SIZE }
+}
+
+impl Default for Chunk {
+    fn default() -> Self { Chunk::new(0) }
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        unsafe {
+            drop(Vec::from_raw_parts(self.ptr.as_ptr(), 0, self.words));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocations_dont_overlap() {
+        let arena = Arena::with_capacity(16);
+
+        let first = arena.alloc_slice(3, 1_u8);
+        let second = arena.alloc_slice(2, 2.0_f64);
+        let third = arena.alloc_slice_copy(&[3_u32; 1000]);
+
+        assert_eq!(first, &[1, 1, 1]);
+        assert_eq!(second, &[2.0, 2.0]);
+        assert!(third.iter().all(|&x| x == 3));
+        assert_eq!(second.as_ptr() as usize % mem::align_of::<f64>(), 0);
+    }
+
+    #[test]
+    fn reset_merges_everything_into_one_chunk() {
+        let mut arena = Arena::new();
+        arena.alloc_slice(1000, 0_u64);
+        arena.alloc_slice(1000, 0_u64);
+        let capacity = arena.capacity();
+        assert!(arena.allocated_bytes() >= 2000 * 8);
+
+        arena.reset();
+
+        assert_eq!(arena.allocated_bytes(), 0);
+        assert_eq!(arena.capacity(), capacity);
+        // The same allocations fit without growing
+        arena.alloc_slice(1000, 0_u64);
+        arena.alloc_slice(1000, 0_u64);
+        assert_eq!(arena.capacity(), capacity);
+    }
+}
@@ -1,12 +1,21 @@
 use std::{fmt::Display, str::FromStr};
 
+use crate::{
+    dimensions::{resolve_dimensions, InvalidDimension},
+    Dimension,
+};
+
 pub mod runtime_v1 {
     // Note: this also generates a `runtime_v1` module, but it's private and
     // can't be exported. As a workaround, we've wrapped it in another
     // runtime_v1 module and re-exported its contents.
+    #[cfg(not(all(feature = "mock", not(target_arch = "wasm32"))))]
     wit_bindgen_rust::import!("../wit-files/rune/runtime-v1.wit");
+    // When testing natively, talk to an in-memory host instead
+    #[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+    use crate::mock::runtime_v1;
 
-    use crate::bindings::ContextExt;
+    use crate::{bindings::ContextExt, Capability, ResourceLimit};
 
     pub use self::runtime_v1::*;
 
@@ -19,6 +28,17 @@ pub mod runtime_v1 {
     #[derive(Debug, Clone, PartialEq, Eq, Hash)]
     pub struct InvalidElementType {
         pub actual: String,
+        /// The element types that would have been accepted.
+        pub expected: &'static [&'static str],
+    }
+
+    impl InvalidElementType {
+        pub fn new(actual: &str, expected: &'static [&'static str]) -> Self {
+            InvalidElementType {
+                actual: actual.to_string(),
+                expected,
+            }
+        }
     }
 
     impl std::error::Error for InvalidElementType {}
@@ -27,23 +47,20 @@ pub mod runtime_v1 {
         fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
             write!(
                 f,
-                "\"{}\" is not a valid input type, expected one of {:?}",
-                self.actual,
-                crate::common::element_type::ALL
+                "\"{}\" is not a valid element type, expected one of {:?}",
+                self.actual, self.expected
             )
         }
     }
 
     impl ElementType {
-        pub const ALL: &'static [&'static str] = &[
-            "u8", "i8", "u16", "i16", "u32", "i32", "f32", "u64", "i64", "f64",
-            "utf8",
-        ];
-        pub const DESCRIPTION: &'static str = "The output type.";
-        pub const NAME: &'static str = "element_type";
-        pub const NUMERIC: &'static [&'static str] = &[
-            "u8", "i8", "u16", "i16", "u32", "i32", "f32", "u64", "i64", "f64",
-        ];
+        pub const ALL: &'static [&'static str] =
+            crate::common::element_type::ALL;
+        pub const DESCRIPTION: &'static str =
+            crate::common::element_type::DESCRIPTION;
+        pub const NAME: &'static str = crate::common::element_type::NAME;
+        pub const NUMERIC: &'static [&'static str] =
+            crate::common::element_type::NUMERIC;
 
         fn human_name(self) -> &'static str {
             match self {
@@ -71,22 +88,36 @@ pub mod runtime_v1 {
         type Err = InvalidElementType;
 
         fn from_str(s: &str) -> Result<Self, Self::Err> {
-            match s {
-                "u8" => Ok(ElementType::U8),
-                "i8" => Ok(ElementType::I8),
-                "u16" => Ok(ElementType::U16),
-                "i16" => Ok(ElementType::I16),
-                "u32" => Ok(ElementType::U32),
-                "i32" => Ok(ElementType::I32),
-                "f32" => Ok(ElementType::F32),
-                "u64" => Ok(ElementType::U64),
-                "i64" => Ok(ElementType::I64),
-                "f64" => Ok(ElementType::F64),
-                "utf8" => Ok(ElementType::Utf8),
-                other => Err(InvalidElementType {
-                    actual: other.to_string(),
-                }),
-            }
+            crate::common::element_type::parse_element_type(
+                s,
+                ElementType::ALL,
+            )
+        }
+    }
+
+    impl Metadata {
+        /// Declare that this proc-block needs access to a particular host
+        /// [`Capability`].
+        pub fn with_capability(&self, capability: Capability) -> &Self {
+            self.add_tag(&capability.to_tag());
+            self
+        }
+
+        /// Declare the worst-case resources this proc-block needs (see
+        /// [`ResourceLimit`]).
+        pub fn with_resource_limit(&self, limit: ResourceLimit) -> &Self {
+            self.add_tag(&limit.to_tag());
+            self
+        }
+
+        /// Declare that this proc-block's kernel only depends on its
+        /// arguments and inputs, so the host may evaluate it while building
+        /// the graph when all of its inputs are constant.
+        ///
+        /// See [`crate::abi::CONST_EVAL_TAG`] for the exact contract.
+        pub fn with_const_eval(&self) -> &Self {
+            self.add_tag(crate::abi::CONST_EVAL_TAG);
+            self
         }
     }
 
@@ -103,6 +134,23 @@ pub mod runtime_v1 {
             element_type
         }
 
+        /// Register an `element_type` argument which only accepts the types
+        /// in `supported` (e.g. `common::element_type::FLOAT`).
+        ///
+        /// Use [`ContextExt::element_type()`] to read it.
+        pub fn element_type_in(
+            supported: &'static [&'static str],
+            default: ElementType,
+        ) -> Self {
+            let element_type = ArgumentMetadata::new(ElementType::NAME);
+            element_type.set_description(ElementType::DESCRIPTION);
+            element_type.set_default_value(default.human_name());
+            element_type.add_hint(&runtime_v1::interpret_as_string_in_enum(
+                supported,
+            ));
+            element_type
+        }
+
         /// Register an `element_type` argument which accepts any **numeric**
         /// [`ElementType`] and defaults to [`ElementType::F32`].
         pub fn numeric_element_type() -> Self {
@@ -116,6 +164,39 @@ pub mod runtime_v1 {
         }
     }
 
+    impl ArgumentMetadata {
+        /// Register an `output_name` argument, letting users rename a
+        /// proc-block's output so it matches whatever the next proc-block
+        /// expects.
+        ///
+        /// Use [`ContextExt::output_name()`] to read it.
+        pub fn output_name(default: &str) -> Self {
+            let output_name = ArgumentMetadata::new(super::OUTPUT_NAME);
+            output_name.set_description(
+                "The name to give the output tensor, so it matches the input expected by the next proc-block.",
+            );
+            output_name.set_default_value(default);
+            output_name.add_hint(&runtime_v1::supported_argument_type(
+                ArgumentType::String,
+            ));
+            output_name
+        }
+
+        /// Register an optional `topic` argument, which hosts can use to
+        /// decide where a proc-block's outputs should be forwarded to (e.g.
+        /// an MQTT topic or HTTP endpoint).
+        ///
+        /// Use [`ContextExt::topic()`] to read it.
+        pub fn topic() -> Self {
+            let topic = ArgumentMetadata::new(crate::common::topic::NAME);
+            topic.set_description(crate::common::topic::DESCRIPTION);
+            topic.add_hint(&runtime_v1::supported_argument_type(
+                ArgumentType::String,
+            ));
+            topic
+        }
+    }
+
     impl ContextExt for GraphContext {
         fn _get_argument(&self, name: &str) -> Option<String> {
             self.get_argument(name)
@@ -154,6 +235,9 @@ pub mod runtime_v1 {
     }
 }
 
+/// The argument registered by `ArgumentMetadata::output_name()`.
+const OUTPUT_NAME: &str = "output_name";
+
 pub trait ContextErrorExt {
     type InvalidArgument: InvalidArgumentExt;
 
@@ -209,4 +293,87 @@ pub trait ContextExt {
             .map_err(|e| E::InvalidArgument::invalid_value(name, e))
             .map_err(E::invalid_argument)
     }
+
+    /// Resolve a tensor's [`Dimension`]s using this context's arguments.
+    ///
+    /// The result can be passed straight to `DimensionsParam::Fixed`.
+    fn resolve_dimensions<E>(
+        &self,
+        dimensions: &[Dimension],
+    ) -> Result<Vec<u32>, E>
+    where
+        E: ContextErrorExt,
+    {
+        resolve_dimensions(dimensions, |name| self._get_argument(name))
+            .map_err(|e| match e {
+                InvalidDimension::MissingArgument(name) => {
+                    E::InvalidArgument::not_found(name)
+                },
+                other => {
+                    E::InvalidArgument::invalid_value(other.argument(), other)
+                },
+            })
+            .map_err(E::invalid_argument)
+    }
+
+    /// Get the name an output tensor should be given, as set by the argument
+    /// from `ArgumentMetadata::output_name()`, falling back to `default`.
+    fn output_name<E>(&self, default: &str) -> Result<String, E>
+    where
+        E: ContextErrorExt,
+    {
+        match self._get_argument(OUTPUT_NAME) {
+            None => Ok(default.to_string()),
+            Some(name) if name.trim().is_empty() => {
+                Err(E::invalid_argument(E::InvalidArgument::invalid_value(
+                    OUTPUT_NAME,
+                    "The output name can't be empty",
+                )))
+            },
+            Some(name) => Ok(name),
+        }
+    }
+
+    /// Get the topic set by the argument from `ArgumentMetadata::topic()`,
+    /// if there is one.
+    fn topic<E>(&self) -> Result<Option<String>, E>
+    where
+        E: ContextErrorExt,
+    {
+        use crate::common::topic;
+
+        match self._get_argument(topic::NAME) {
+            Some(t) => match topic::validate(&t) {
+                Ok(()) => Ok(Some(t)),
+                Err(e) => Err(E::invalid_argument(
+                    E::InvalidArgument::invalid_value(topic::NAME, e),
+                )),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Parse the `element_type` argument, falling back to `default` and only
+    /// accepting the types in `supported`.
+    ///
+    /// This pairs with `ArgumentMetadata::element_type_in()`.
+    fn element_type<E>(
+        &self,
+        default: runtime_v1::ElementType,
+        supported: &'static [&'static str],
+    ) -> Result<runtime_v1::ElementType, E>
+    where
+        E: ContextErrorExt,
+    {
+        use crate::common::element_type::{self, parse_element_type};
+
+        match self._get_argument(element_type::NAME) {
+            Some(name) => parse_element_type(&name, supported)
+                .map_err(|e| {
+                    E::InvalidArgument::invalid_value(element_type::NAME, e)
+                })
+                .map_err(E::invalid_argument),
+            None => Ok(default),
+        }
+    }
 }
@@ -1,4 +1,12 @@
-use ndarray::{ArrayD, ArrayViewD, ArrayViewMutD, ErrorKind, ShapeError};
+use std::{
+    fmt::{self, Display, Formatter},
+    mem,
+};
+
+use ndarray::{
+    ArrayD, ArrayView, ArrayViewD, ArrayViewMut, ArrayViewMutD, Dimension,
+    ErrorKind, IxDyn, ShapeError,
+};
 
 use crate::ValueType;
 
@@ -26,6 +34,12 @@ pub trait BufferExt {
     /// // Note:
     /// assert_eq!(reinterpreted, &[1, 256, 65535]);
     /// ```
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the buffer isn't aligned for `T` or its length isn't
+    /// a multiple of `T`'s size. Use [`BufferExt::try_elements()`] when the
+    /// buffer comes from somewhere you don't control.
     fn elements<T: ValueType>(&self) -> &[T];
 
     /// Reinterpret this byte buffer as a mutable slice of `T`'s.
@@ -33,6 +47,69 @@ pub trait BufferExt {
     /// This is the mutable version of [`BufferExt::elements()`].
     fn elements_mut<T: ValueType>(&mut self) -> &mut [T];
 
+    /// A checked version of [`BufferExt::elements()`] which returns an error
+    /// instead of panicking.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hotg_rune_proc_blocks::{BufferExt, InvalidBuffer};
+    ///
+    /// // 3 bytes isn't enough for a f64
+    /// let buffer = [0_u8; 3];
+    ///
+    /// let error = buffer.try_elements::<f64>().unwrap_err();
+    ///
+    /// assert!(matches!(error, InvalidBuffer::TrailingBytes { .. }));
+    /// ```
+    fn try_elements<T: ValueType>(&self) -> Result<&[T], InvalidBuffer>;
+
+    /// A checked version of [`BufferExt::elements_mut()`].
+    fn try_elements_mut<T: ValueType>(
+        &mut self,
+    ) -> Result<&mut [T], InvalidBuffer>;
+
+    /// Like [`BufferExt::try_elements()`], but also make sure the buffer
+    /// contains exactly as many elements as `dimensions` describes.
+    ///
+    /// Use this instead of [`BufferExt::try_elements()`] when a kernel works
+    /// with the flat elements, but still trusts the tensor's dimensions (e.g.
+    /// to index into it or to give its output the same shape).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hotg_rune_proc_blocks::{BufferExt, InvalidBuffer, SliceExt};
+    ///
+    /// let floats = [0.0_f32, 1.0, 2.0];
+    /// let buffer = floats.as_bytes();
+    ///
+    /// let elements = buffer.try_elements_with_shape::<f32>(&[3]).unwrap();
+    /// assert_eq!(elements, &floats);
+    ///
+    /// let error =
+    ///     buffer.try_elements_with_shape::<f32>(&[2, 2]).unwrap_err();
+    /// assert!(matches!(error, InvalidBuffer::LengthMismatch { .. }));
+    /// ```
+    fn try_elements_with_shape<T: ValueType>(
+        &self,
+        dimensions: &[u32],
+    ) -> Result<&[T], InvalidBuffer> {
+        let elements = self.try_elements()?;
+        checked_shape::<IxDyn>(dimensions, elements.len())?;
+        Ok(elements)
+    }
+
+    /// The mutable version of [`BufferExt::try_elements_with_shape()`].
+    fn try_elements_with_shape_mut<T: ValueType>(
+        &mut self,
+        dimensions: &[u32],
+    ) -> Result<&mut [T], InvalidBuffer> {
+        let elements = self.try_elements_mut()?;
+        checked_shape::<IxDyn>(dimensions, elements.len())?;
+        Ok(elements)
+    }
+
     /// Interpret this buffer as a sequence of UTF-8 strings, where each string
     /// is prefixed by its length as a little-endian `u16`.
     ///
@@ -59,23 +136,84 @@ pub trait BufferExt {
     fn strings(&self) -> Result<Vec<&str>, ShapeError>;
 
     /// View the buffer as a multi-dimensional array.
+    ///
+    /// Prefer [`BufferExt::try_view()`], which gives a more useful error
+    /// message and can check the number of dimensions for you.
     fn view<T: ValueType>(
         &self,
         dimensions: &[u32],
     ) -> Result<ArrayViewD<'_, T>, ShapeError> {
-        let elements = self.elements();
-        let dimensions = dims(dimensions);
-        ArrayViewD::from_shape(dimensions, elements)
+        self.try_view(dimensions).map_err(ShapeError::from)
     }
 
     /// View the buffer as a mutable multi-dimensional array.
+    ///
+    /// Prefer [`BufferExt::try_view_mut()`].
     fn view_mut<T: ValueType>(
         &mut self,
         dimensions: &[u32],
     ) -> Result<ArrayViewMutD<'_, T>, ShapeError> {
-        let elements = self.elements_mut();
-        let dimensions = dims(dimensions);
-        ArrayViewMutD::from_shape(dimensions, elements)
+        self.try_view_mut(dimensions).map_err(ShapeError::from)
+    }
+
+    /// View the buffer as an array with `D` dimensions, making sure the
+    /// buffer is aligned and has exactly the right number of bytes for the
+    /// shape.
+    ///
+    /// Use [`IxDyn`](type@ndarray::IxDyn) for `D` when the number of
+    /// dimensions isn't known ahead of time.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hotg_rune_proc_blocks::{
+    ///     ndarray::{ArrayView2, Ix2},
+    ///     BufferExt, InvalidBuffer, SliceExt,
+    /// };
+    ///
+    /// let floats = [0.0_f32, 1.0, 2.0, 3.0, 4.0, 5.0];
+    /// let buffer = floats.as_bytes();
+    ///
+    /// let tensor: ArrayView2<f32> = buffer.try_view(&[2, 3]).unwrap();
+    /// assert_eq!(tensor[[1, 0]], 3.0);
+    ///
+    /// let error = buffer.try_view::<f32, Ix2>(&[2, 2]).unwrap_err();
+    /// assert_eq!(
+    ///     error,
+    ///     InvalidBuffer::LengthMismatch {
+    ///         dimensions: vec![2, 2],
+    ///         expected: 4,
+    ///         actual: 6,
+    ///     }
+    /// );
+    /// ```
+    fn try_view<T: ValueType, D: Dimension>(
+        &self,
+        dimensions: &[u32],
+    ) -> Result<ArrayView<'_, T, D>, InvalidBuffer> {
+        let elements = self.try_elements()?;
+        let shape = checked_shape::<D>(dimensions, elements.len())?;
+
+        ArrayView::from_shape(shape, elements).map_err(|_| {
+            InvalidBuffer::Overflow {
+                dimensions: dimensions.to_vec(),
+            }
+        })
+    }
+
+    /// The mutable version of [`BufferExt::try_view()`].
+    fn try_view_mut<T: ValueType, D: Dimension>(
+        &mut self,
+        dimensions: &[u32],
+    ) -> Result<ArrayViewMut<'_, T, D>, InvalidBuffer> {
+        let elements = self.try_elements_mut()?;
+        let shape = checked_shape::<D>(dimensions, elements.len())?;
+
+        ArrayViewMut::from_shape(shape, elements).map_err(|_| {
+            InvalidBuffer::Overflow {
+                dimensions: dimensions.to_vec(),
+            }
+        })
     }
 
     fn string_view<'a>(
@@ -94,6 +232,158 @@ fn dims(d: &[u32]) -> Vec<usize> {
         .collect()
 }
 
+/// Make sure the dimensions have the right rank and describe exactly
+/// `elements` elements.
+fn checked_shape<D: Dimension>(
+    dimensions: &[u32],
+    elements: usize,
+) -> Result<D, InvalidBuffer> {
+    if let Some(rank) = D::NDIM {
+        if rank != dimensions.len() {
+            return Err(InvalidBuffer::WrongRank {
+                expected: rank,
+                actual: dimensions.len(),
+            });
+        }
+    }
+
+    let expected = dimensions
+        .iter()
+        .try_fold(1_usize, |product, &d| product.checked_mul(d as usize))
+        .ok_or_else(|| InvalidBuffer::Overflow {
+            dimensions: dimensions.to_vec(),
+        })?;
+
+    if expected != elements {
+        return Err(InvalidBuffer::LengthMismatch {
+            dimensions: dimensions.to_vec(),
+            expected,
+            actual: elements,
+        });
+    }
+
+    let mut shape = D::zeros(dimensions.len());
+    for (dest, &src) in shape.slice_mut().iter_mut().zip(dimensions) {
+        *dest = src as usize;
+    }
+
+    Ok(shape)
+}
+
+/// Check that a byte buffer can be reinterpreted as a slice of `T`'s.
+fn check_layout<T: ValueType>(buffer: &[u8]) -> Result<(), InvalidBuffer> {
+    if buffer.is_empty() {
+        // Empty buffers may have a dangling pointer, but that's fine because
+        // they'll never be read from.
+        return Ok(());
+    }
+
+    let element_size = mem::size_of::<T>();
+    if buffer.len() % element_size != 0 {
+        return Err(InvalidBuffer::TrailingBytes {
+            element_type: std::any::type_name::<T>(),
+            element_size,
+            length: buffer.len(),
+        });
+    }
+
+    let alignment = mem::align_of::<T>();
+    if buffer.as_ptr() as usize % alignment != 0 {
+        return Err(InvalidBuffer::Misaligned {
+            element_type: std::any::type_name::<T>(),
+            alignment,
+        });
+    }
+
+    Ok(())
+}
+
+/// The reasons a byte buffer can't be viewed as a tensor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidBuffer {
+    /// The buffer's length isn't a multiple of the element's size.
+    TrailingBytes {
+        element_type: &'static str,
+        element_size: usize,
+        length: usize,
+    },
+    /// The buffer's address isn't aligned properly for the element type.
+    Misaligned {
+        element_type: &'static str,
+        alignment: usize,
+    },
+    /// The shape has more elements than could possibly fit in memory.
+    Overflow { dimensions: Vec<u32> },
+    /// The buffer doesn't have the number of elements the shape requires.
+    LengthMismatch {
+        dimensions: Vec<u32>,
+        expected: usize,
+        actual: usize,
+    },
+    /// The shape has the wrong number of dimensions.
+    WrongRank { expected: usize, actual: usize },
+}
+
+impl std::error::Error for InvalidBuffer {}
+
+impl Display for InvalidBuffer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidBuffer::TrailingBytes {
+                element_type,
+                element_size,
+                length,
+            } => write!(
+                f,
+                "A {} byte buffer can't be split into {}'s ({} bytes each)",
+                length, element_type, element_size
+            ),
+            InvalidBuffer::Misaligned {
+                element_type,
+                alignment,
+            } => write!(
+                f,
+                "The buffer isn't aligned to {} bytes, as required for {}'s",
+                alignment, element_type
+            ),
+            InvalidBuffer::Overflow { dimensions } => {
+                write!(f, "The shape {:?} is too big", dimensions)
+            },
+            InvalidBuffer::LengthMismatch {
+                dimensions,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "The shape {:?} needs {} elements, but the buffer contains {}",
+                dimensions, expected, actual
+            ),
+            InvalidBuffer::WrongRank { expected, actual } => write!(
+                f,
+                "Expected a tensor with {} dimensions, found {}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl From<InvalidBuffer> for ShapeError {
+    fn from(e: InvalidBuffer) -> Self {
+        let kind = match e {
+            InvalidBuffer::TrailingBytes { .. }
+            | InvalidBuffer::Misaligned { .. } => ErrorKind::IncompatibleLayout,
+            InvalidBuffer::Overflow { .. } => ErrorKind::Overflow,
+            InvalidBuffer::LengthMismatch {
+                expected, actual, ..
+            } if actual < expected => ErrorKind::OutOfBounds,
+            InvalidBuffer::LengthMismatch { .. }
+            | InvalidBuffer::WrongRank { .. } => ErrorKind::IncompatibleShape,
+        };
+
+        ShapeError::from_kind(kind)
+    }
+}
+
 impl BufferExt for [u8] {
     fn elements<T: ValueType>(&self) -> &[T] {
         unsafe {
@@ -113,6 +403,18 @@ impl BufferExt for [u8] {
         }
     }
 
+    fn try_elements<T: ValueType>(&self) -> Result<&[T], InvalidBuffer> {
+        check_layout::<T>(self)?;
+        Ok(self.elements())
+    }
+
+    fn try_elements_mut<T: ValueType>(
+        &mut self,
+    ) -> Result<&mut [T], InvalidBuffer> {
+        check_layout::<T>(self)?;
+        Ok(self.elements_mut())
+    }
+
     fn strings(&self) -> Result<Vec<&str>, ShapeError> {
         const HEADER_SIZE: usize = std::mem::size_of::<u32>();
 
@@ -217,6 +519,80 @@ mod tests {
         assert_eq!(floats, [0.0, 0.0, 5.0, 0.0]);
     }
 
+    #[test]
+    fn odd_length_buffers_are_an_error() {
+        let buffer = [1_u8, 2, 3];
+
+        let error = buffer.try_view::<f64, ndarray::Ix1>(&[1]).unwrap_err();
+
+        assert_eq!(
+            error,
+            InvalidBuffer::TrailingBytes {
+                element_type: "f64",
+                element_size: 8,
+                length: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn misaligned_buffers_are_an_error() {
+        let mut floats = [0.0_f64; 2];
+        let buffer = as_byte_buffer_mut(&mut floats);
+
+        // Skip the first byte so everything after it is misaligned
+        let error = buffer[1..9].try_elements::<u64>().unwrap_err();
+
+        assert_eq!(
+            error,
+            InvalidBuffer::Misaligned {
+                element_type: "u64",
+                alignment: 8,
+            }
+        );
+    }
+
+    #[test]
+    fn overflowing_dimensions_are_an_error() {
+        let buffer = [0_u8; 4];
+        let dimensions = [u32::MAX, u32::MAX, u32::MAX];
+
+        let error = buffer
+            .try_view::<u8, ndarray::IxDyn>(&dimensions)
+            .unwrap_err();
+
+        assert_eq!(
+            error,
+            InvalidBuffer::Overflow {
+                dimensions: dimensions.to_vec()
+            }
+        );
+    }
+
+    #[test]
+    fn the_rank_is_checked() {
+        let buffer = [0_u8; 4];
+
+        let error = buffer.try_view::<u8, ndarray::Ix2>(&[4]).unwrap_err();
+
+        assert_eq!(
+            error,
+            InvalidBuffer::WrongRank {
+                expected: 2,
+                actual: 1
+            }
+        );
+    }
+
+    #[test]
+    fn views_into_empty_buffers() {
+        let buffer: Vec<u8> = Vec::new();
+
+        let tensor = buffer.try_view::<f64, ndarray::Ix2>(&[0, 3]).unwrap();
+
+        assert_eq!(tensor.dim(), (0, 3));
+    }
+
     #[test]
     fn load_string_tensor() {
         let strings = ["this", "is a", "sentence", "."];
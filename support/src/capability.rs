@@ -0,0 +1,120 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+/// Something a proc-block needs from the host, beyond the basic
+/// `runtime-v1` interface.
+///
+/// Hosts can use this to refuse to load a proc-block, or to sandbox it
+/// accordingly.
+///
+/// Capabilities are recorded as tags with a `capability:` prefix (see
+/// [`Capability::to_tag()`]) so they can be declared without changing the
+/// `runtime-v1` interface.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Capability {
+    /// Read or write files.
+    FileSystem,
+    /// Access a persistent key-value store.
+    KeyValueStore,
+    /// Read the current time.
+    Time,
+    /// Request random numbers from the host.
+    Entropy,
+    /// Make network requests.
+    Network,
+}
+
+impl Capability {
+    pub const ALL: &'static [Capability] = &[
+        Capability::FileSystem,
+        Capability::KeyValueStore,
+        Capability::Time,
+        Capability::Entropy,
+        Capability::Network,
+    ];
+    /// The prefix used when a [`Capability`] is stored as a tag.
+    pub const TAG_PREFIX: &'static str = "capability:";
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Capability::FileSystem => "file-system",
+            Capability::KeyValueStore => "key-value-store",
+            Capability::Time => "time",
+            Capability::Entropy => "entropy",
+            Capability::Network => "network",
+        }
+    }
+
+    /// Get the tag used to declare this capability.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hotg_rune_proc_blocks::Capability;
+    ///
+    /// assert_eq!(Capability::Time.to_tag(), "capability:time");
+    /// ```
+    pub fn to_tag(self) -> String {
+        format!("{}{}", Capability::TAG_PREFIX, self.name())
+    }
+
+    /// Parse a tag created by [`Capability::to_tag()`].
+    pub fn from_tag(tag: &str) -> Option<Capability> {
+        tag.strip_prefix(Capability::TAG_PREFIX)?.parse().ok()
+    }
+}
+
+impl Display for Capability {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.name().fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UnknownCapability {
+    pub actual: String,
+}
+
+impl std::error::Error for UnknownCapability {}
+
+impl Display for UnknownCapability {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\" is not a known capability", self.actual)
+    }
+}
+
+impl FromStr for Capability {
+    type Err = UnknownCapability;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Capability::ALL
+            .iter()
+            .copied()
+            .find(|c| c.name() == s)
+            .ok_or_else(|| UnknownCapability {
+                actual: s.to_string(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_round_trip_through_tags() {
+        for &capability in Capability::ALL {
+            let tag = capability.to_tag();
+
+            assert_eq!(Capability::from_tag(&tag), Some(capability));
+        }
+    }
+
+    #[test]
+    fn normal_tags_are_not_capabilities() {
+        assert_eq!(Capability::from_tag("image"), None);
+        assert_eq!(Capability::from_tag("capability:teleportation"), None);
+    }
+}
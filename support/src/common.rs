@@ -1,6 +1,9 @@
 //! Common arguments that are used across proc-blocks.
 
 pub mod element_type {
+    #[cfg(feature = "runtime_v1")]
+    use crate::runtime_v1::{ElementType, InvalidElementType};
+
     pub const NAME: &str = "element_type";
     pub const DESCRIPTION: &str = "The output type.";
     pub const ALL: &[&str] = &[
@@ -10,4 +13,116 @@ pub mod element_type {
     pub const NUMERIC: &[&str] = &[
         "u8", "i8", "u16", "i16", "u32", "i32", "f32", "u64", "i64", "f64",
     ];
+    pub const INTEGER: &[&str] =
+        &["u8", "i8", "u16", "i16", "u32", "i32", "u64", "i64"];
+    pub const FLOAT: &[&str] = &["f32", "f64"];
+
+    /// Parse the name of an element type (e.g. `"f32"`), only accepting the
+    /// names in `supported`.
+    ///
+    /// `supported` is normally one of the lists in this module, and should
+    /// also be used for the argument's `interpret_as_string_in_enum()` hint
+    /// so the metadata matches what the proc-block accepts.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hotg_rune_proc_blocks::{
+    ///     common::element_type::{self, parse_element_type},
+    ///     runtime_v1::ElementType,
+    /// };
+    ///
+    /// let got = parse_element_type("f64", element_type::FLOAT).unwrap();
+    /// assert_eq!(got, ElementType::F64);
+    ///
+    /// assert!(parse_element_type("u8", element_type::FLOAT).is_err());
+    /// assert!(parse_element_type("float", element_type::ALL).is_err());
+    /// ```
+    #[cfg(feature = "runtime_v1")]
+    pub fn parse_element_type(
+        name: &str,
+        supported: &'static [&'static str],
+    ) -> Result<ElementType, InvalidElementType> {
+        let element_type = match name {
+            "u8" => ElementType::U8,
+            "i8" => ElementType::I8,
+            "u16" => ElementType::U16,
+            "i16" => ElementType::I16,
+            "u32" => ElementType::U32,
+            "i32" => ElementType::I32,
+            "f32" => ElementType::F32,
+            "u64" => ElementType::U64,
+            "i64" => ElementType::I64,
+            "f64" => ElementType::F64,
+            "utf8" => ElementType::Utf8,
+            _ => return Err(InvalidElementType::new(name, supported)),
+        };
+
+        if supported.contains(&name) {
+            Ok(element_type)
+        } else {
+            Err(InvalidElementType::new(name, supported))
+        }
+    }
+}
+
+pub mod topic {
+    use std::fmt::{self, Display, Formatter};
+
+    pub const NAME: &str = "topic";
+    pub const DESCRIPTION: &str = "The topic (e.g. `sensors/kitchen/temperature`) hosts should use when forwarding this proc-block's outputs to MQTT, HTTP, etc.";
+    /// The longest topic MQTT allows, in bytes.
+    pub const MAX_LENGTH: usize = u16::MAX as usize;
+
+    /// Check that a topic can be published to.
+    ///
+    /// This follows the MQTT rules, so topics must not be empty and must not
+    /// contain wildcards (`+` and `#`) or null characters.
+    pub fn validate(topic: &str) -> Result<(), InvalidTopic> {
+        if topic.is_empty() {
+            return Err(InvalidTopic::Empty);
+        }
+        if topic.len() > MAX_LENGTH {
+            return Err(InvalidTopic::TooLong(topic.len()));
+        }
+        if let Some(c) = topic.chars().find(|c| matches!(c, '+' | '#')) {
+            return Err(InvalidTopic::Wildcard(c));
+        }
+        if topic.contains('\0') {
+            return Err(InvalidTopic::NullCharacter);
+        }
+
+        Ok(())
+    }
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub enum InvalidTopic {
+        Empty,
+        TooLong(usize),
+        Wildcard(char),
+        NullCharacter,
+    }
+
+    impl std::error::Error for InvalidTopic {}
+
+    impl Display for InvalidTopic {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            match self {
+                InvalidTopic::Empty => write!(f, "The topic can't be empty"),
+                InvalidTopic::TooLong(len) => write!(
+                    f,
+                    "The topic is {} bytes long, but the maximum is {}",
+                    len, MAX_LENGTH
+                ),
+                InvalidTopic::Wildcard(c) => write!(
+                    f,
+                    "Outputs can't be published to a topic containing the \"{}\" wildcard",
+                    c
+                ),
+                InvalidTopic::NullCharacter => {
+                    write!(f, "The topic can't contain null characters")
+                },
+            }
+        }
+    }
 }
@@ -0,0 +1,156 @@
+use crate::ValueType;
+
+/// A complex number, laid out as `[re, im]` so complex tensors can be
+/// passed around as interleaved `f32`/`f64` tensors.
+///
+/// The `runtime-v1` interface doesn't have a complex [`ElementType`][et], so
+/// by convention a complex tensor with dimensions `[..., n]` is sent as a
+/// real tensor with dimensions `[..., n, 2]` (see [`complex_dimensions()`]).
+///
+/// # Examples
+///
+/// ```rust
+/// use hotg_rune_proc_blocks::Complex;
+///
+/// let interleaved = [3.0_f32, 4.0, 0.0, -1.0];
+///
+/// let values = Complex::from_interleaved(&interleaved).unwrap();
+///
+/// assert_eq!(values[0], Complex::new(3.0, 4.0));
+/// assert_eq!(values[0].norm(), 5.0);
+/// assert_eq!(Complex::to_interleaved(values), &interleaved);
+/// ```
+///
+/// [et]: crate::runtime_v1::ElementType
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub struct Complex<T> {
+    pub re: T,
+    pub im: T,
+}
+
+// Safety: Complex<T> is repr(C) with two fields of the same type, so there
+// is no padding.
+unsafe impl ValueType for Complex<f32> {}
+unsafe impl ValueType for Complex<f64> {}
+
+impl<T> Complex<T> {
+    pub const fn new(re: T, im: T) -> Self { Complex { re, im } }
+}
+
+impl<T: ValueType> Complex<T>
+where
+    Complex<T>: ValueType,
+{
+    /// Reinterpret interleaved `[re, im, re, im, ...]` values as complex
+    /// numbers, returning `None` if there are an odd number of values.
+    pub fn from_interleaved(values: &[T]) -> Option<&[Complex<T>]> {
+        if values.len() % 2 != 0 {
+            return None;
+        }
+
+        // Safety: Complex<T> has the same layout as [T; 2] and we checked
+        // the length above.
+        unsafe {
+            Some(std::slice::from_raw_parts(
+                values.as_ptr().cast(),
+                values.len() / 2,
+            ))
+        }
+    }
+
+    /// The mutable version of [`Complex::from_interleaved()`].
+    pub fn from_interleaved_mut(
+        values: &mut [T],
+    ) -> Option<&mut [Complex<T>]> {
+        if values.len() % 2 != 0 {
+            return None;
+        }
+
+        // Safety: see from_interleaved()
+        unsafe {
+            Some(std::slice::from_raw_parts_mut(
+                values.as_mut_ptr().cast(),
+                values.len() / 2,
+            ))
+        }
+    }
+
+    /// View complex numbers as interleaved `[re, im, re, im, ...]` values.
+    pub fn to_interleaved(values: &[Complex<T>]) -> &[T] {
+        // Safety: Complex<T> has the same layout as [T; 2]
+        unsafe {
+            std::slice::from_raw_parts(
+                values.as_ptr().cast(),
+                values.len() * 2,
+            )
+        }
+    }
+}
+
+macro_rules! float_methods {
+    ($($float:ty),*) => {
+        $(
+            impl Complex<$float> {
+                /// The magnitude, `|z|`.
+                pub fn norm(self) -> $float { self.re.hypot(self.im) }
+
+                /// The phase angle, in radians.
+                pub fn arg(self) -> $float { self.im.atan2(self.re) }
+
+                /// Create a complex number from its magnitude and phase.
+                pub fn from_polar(norm: $float, arg: $float) -> Self {
+                    Complex::new(norm * arg.cos(), norm * arg.sin())
+                }
+            }
+        )*
+    };
+}
+
+float_methods!(f32, f64);
+
+/// Get the dimensions of the interleaved real tensor used to send a complex
+/// tensor with the provided dimensions.
+///
+/// # Examples
+///
+/// ```rust
+/// use hotg_rune_proc_blocks::complex_dimensions;
+///
+/// assert_eq!(complex_dimensions(&[1, 256]), vec![1, 256, 2]);
+/// ```
+pub fn complex_dimensions(dimensions: &[u32]) -> Vec<u32> {
+    let mut dimensions = dimensions.to_vec();
+    dimensions.push(2);
+    dimensions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn odd_lengths_are_not_complex() {
+        assert!(Complex::from_interleaved(&[1.0_f64, 2.0, 3.0]).is_none());
+    }
+
+    #[test]
+    fn polar_round_trip() {
+        let z = Complex::new(-1.0_f64, 1.0);
+
+        let round_tripped = Complex::<f64>::from_polar(z.norm(), z.arg());
+
+        assert!((round_tripped.re - z.re).abs() < 1e-12);
+        assert!((round_tripped.im - z.im).abs() < 1e-12);
+    }
+
+    #[test]
+    fn mutate_through_interleaved_view() {
+        let mut values = [0.0_f32; 4];
+
+        Complex::from_interleaved_mut(&mut values).unwrap()[1] =
+            Complex::new(1.0, 2.0);
+
+        assert_eq!(values, [0.0, 0.0, 1.0, 2.0]);
+    }
+}
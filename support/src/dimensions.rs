@@ -0,0 +1,167 @@
+use std::fmt::{self, Display, Formatter};
+
+/// A single dimension in a tensor's shape, as declared by a proc-block.
+///
+/// The `runtime-v1` interface only accepts concrete dimensions, where `0`
+/// means "dynamic". A [`Dimension`] lets a proc-block describe dimensions
+/// that depend on its arguments (e.g. a tokenizer's `max_sequence_length`)
+/// and resolve them to concrete values inside `graph()`, instead of
+/// hard-coding one size or falling back to a dynamic dimension.
+///
+/// # Examples
+///
+/// ```rust
+/// use hotg_rune_proc_blocks::{resolve_dimensions, Dimension};
+///
+/// let dimensions = [Dimension::Fixed(1), Dimension::FromArgument("length")];
+/// let get_argument = |name: &str| match name {
+///     "length" => Some("128".to_string()),
+///     _ => None,
+/// };
+///
+/// let resolved = resolve_dimensions(&dimensions, get_argument).unwrap();
+///
+/// assert_eq!(resolved, vec![1, 128]);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Dimension {
+    /// A dimension with a known length.
+    Fixed(u32),
+    /// A dimension whose length is only known at runtime.
+    Dynamic,
+    /// A dimension whose length comes from a required argument.
+    FromArgument(&'static str),
+    /// A dimension whose length comes from an argument, falling back to a
+    /// default when the argument isn't provided.
+    FromArgumentOr(&'static str, u32),
+}
+
+/// Resolve a list of [`Dimension`]s to the concrete dimensions expected by
+/// the `runtime-v1` interface, looking argument values up with
+/// `get_argument`.
+///
+/// Dynamic dimensions resolve to `0`. Arguments must be positive integers,
+/// so an argument can never accidentally make a dimension dynamic.
+pub fn resolve_dimensions(
+    dimensions: &[Dimension],
+    get_argument: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<u32>, InvalidDimension> {
+    dimensions
+        .iter()
+        .map(|dimension| match *dimension {
+            Dimension::Fixed(length) => Ok(length),
+            Dimension::Dynamic => Ok(0),
+            Dimension::FromArgument(name) => match get_argument(name) {
+                Some(value) => parse_length(name, &value),
+                None => Err(InvalidDimension::MissingArgument(name)),
+            },
+            Dimension::FromArgumentOr(name, default) => {
+                match get_argument(name) {
+                    Some(value) => parse_length(name, &value),
+                    None => Ok(default),
+                }
+            },
+        })
+        .collect()
+}
+
+fn parse_length(
+    name: &'static str,
+    value: &str,
+) -> Result<u32, InvalidDimension> {
+    match value.trim().parse::<u32>() {
+        Ok(length) if length > 0 => Ok(length),
+        _ => Err(InvalidDimension::InvalidLength {
+            argument: name,
+            value: value.to_string(),
+        }),
+    }
+}
+
+/// The reasons a [`Dimension`] may fail to resolve.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum InvalidDimension {
+    /// The argument a [`Dimension::FromArgument`] refers to wasn't provided.
+    MissingArgument(&'static str),
+    /// The argument wasn't a positive integer.
+    InvalidLength {
+        argument: &'static str,
+        value: String,
+    },
+}
+
+impl InvalidDimension {
+    /// The name of the argument this error is about.
+    pub fn argument(&self) -> &'static str {
+        match self {
+            InvalidDimension::MissingArgument(argument)
+            | InvalidDimension::InvalidLength { argument, .. } => argument,
+        }
+    }
+}
+
+impl std::error::Error for InvalidDimension {}
+
+impl Display for InvalidDimension {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidDimension::MissingArgument(argument) => {
+                write!(f, "The \"{}\" argument is required", argument)
+            },
+            InvalidDimension::InvalidLength { argument, value } => write!(
+                f,
+                "Expected \"{}\" to be a positive integer, found \"{}\"",
+                argument, value
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arguments(name: &str) -> Option<String> {
+        match name {
+            "length" => Some("384".to_string()),
+            "zero" => Some("0".to_string()),
+            "text" => Some("long".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn resolve_fixed_dynamic_and_arguments() {
+        let dimensions = [
+            Dimension::Fixed(1),
+            Dimension::Dynamic,
+            Dimension::FromArgument("length"),
+            Dimension::FromArgumentOr("length", 10),
+            Dimension::FromArgumentOr("missing", 10),
+        ];
+
+        let resolved = resolve_dimensions(&dimensions, arguments).unwrap();
+
+        assert_eq!(resolved, vec![1, 0, 384, 384, 10]);
+    }
+
+    #[test]
+    fn missing_required_argument() {
+        let dimensions = [Dimension::FromArgument("missing")];
+
+        let err = resolve_dimensions(&dimensions, arguments).unwrap_err();
+
+        assert_eq!(err, InvalidDimension::MissingArgument("missing"));
+    }
+
+    #[test]
+    fn arguments_must_be_positive_integers() {
+        for name in ["zero", "text"] {
+            let dimensions = [Dimension::FromArgumentOr(name, 1)];
+
+            let err = resolve_dimensions(&dimensions, arguments).unwrap_err();
+
+            assert_eq!(err.argument(), name);
+        }
+    }
+}
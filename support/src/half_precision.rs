@@ -0,0 +1,137 @@
+//! Helpers for working with half-precision (`f16` and `bf16`) tensors.
+//!
+//! The `runtime-v1` interface doesn't have a half-precision
+//! [`ElementType`][et] yet, so these values are exchanged as `u16` tensors
+//! holding the raw bit patterns. Proc-blocks can use the helpers in this
+//! module to widen them to `f32` for processing and narrow the results back
+//! down again.
+//!
+//! [et]: crate::runtime_v1::ElementType
+
+pub use half::{bf16, f16};
+
+use half::slice::{HalfBitsSliceExt, HalfFloatSliceExt};
+
+/// Reinterpret a slice of raw `u16` bit patterns as [`f16`] values.
+pub fn f16_from_bits(bits: &[u16]) -> &[f16] { bits.reinterpret_cast() }
+
+/// Reinterpret a slice of raw `u16` bit patterns as [`bf16`] values.
+pub fn bf16_from_bits(bits: &[u16]) -> &[bf16] { bits.reinterpret_cast() }
+
+/// Widen [`f16`] values to `f32`.
+///
+/// # Examples
+///
+/// ```rust
+/// use hotg_rune_proc_blocks::half_precision::{f16, f16_to_f32};
+///
+/// let values = [f16::from_f32(0.5), f16::from_f32(-2.0)];
+///
+/// assert_eq!(f16_to_f32(&values), vec![0.5, -2.0]);
+/// ```
+pub fn f16_to_f32(values: &[f16]) -> Vec<f32> { values.to_f32_vec() }
+
+/// Narrow `f32` values to [`f16`], rounding to the nearest representable
+/// value.
+pub fn f32_to_f16(values: &[f32]) -> Vec<f16> {
+    let mut output = vec![f16::ZERO; values.len()];
+    output.convert_from_f32_slice(values);
+    output
+}
+
+/// Widen [`bf16`] values to `f32`.
+pub fn bf16_to_f32(values: &[bf16]) -> Vec<f32> { values.to_f32_vec() }
+
+/// Narrow `f32` values to [`bf16`], rounding to the nearest representable
+/// value.
+pub fn f32_to_bf16(values: &[f32]) -> Vec<bf16> {
+    let mut output = vec![bf16::ZERO; values.len()];
+    output.convert_from_f32_slice(values);
+    output
+}
+
+/// A half-precision format, for tensors passed around as raw `u16` bits.
+///
+/// Proc-blocks which support half-precision inputs let users select a format
+/// through their `element_type` argument.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HalfPrecision {
+    F16,
+    BF16,
+}
+
+impl HalfPrecision {
+    /// The `element_type` values which refer to a half-precision format.
+    pub const NAMES: &'static [&'static str] = &["f16", "bf16"];
+
+    /// Look up a format by the name used in an `element_type` argument.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hotg_rune_proc_blocks::half_precision::HalfPrecision;
+    ///
+    /// assert_eq!(HalfPrecision::from_name("bf16"), Some(HalfPrecision::BF16));
+    /// assert_eq!(HalfPrecision::from_name("f32"), None);
+    /// ```
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "f16" => Some(HalfPrecision::F16),
+            "bf16" => Some(HalfPrecision::BF16),
+            _ => None,
+        }
+    }
+
+    /// Widen raw bit patterns in this format to `f32`.
+    pub fn widen(self, bits: &[u16]) -> Vec<f32> {
+        match self {
+            HalfPrecision::F16 => f16_to_f32(f16_from_bits(bits)),
+            HalfPrecision::BF16 => bf16_to_f32(bf16_from_bits(bits)),
+        }
+    }
+
+    /// Narrow `f32` values to this format, returning their raw bit patterns.
+    pub fn narrow(self, values: &[f32]) -> Vec<u16> {
+        match self {
+            HalfPrecision::F16 => {
+                f32_to_f16(values).iter().map(|v| v.to_bits()).collect()
+            },
+            HalfPrecision::BF16 => {
+                f32_to_bf16(values).iter().map(|v| v.to_bits()).collect()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SliceExt;
+
+    #[test]
+    fn round_trip_through_f32() {
+        let values = [0.0, 1.0, -0.25, 65504.0];
+
+        assert_eq!(f16_to_f32(&f32_to_f16(&values)), values);
+        assert_eq!(bf16_to_f32(&f32_to_bf16(&values[..3])), values[..3]);
+    }
+
+    #[test]
+    fn bit_patterns_match_the_byte_representation() {
+        let values = f32_to_f16(&[1.0, -2.0]);
+        let bits = [0x3c00_u16, 0xc000];
+
+        assert_eq!(f16_from_bits(&bits), &values[..]);
+        assert_eq!(values.as_bytes(), bits.as_bytes());
+    }
+
+    #[test]
+    fn half_precision_round_trips_through_bits() {
+        let values = [0.5, -2.0, 1024.0];
+
+        for format in [HalfPrecision::F16, HalfPrecision::BF16] {
+            let bits = format.narrow(&values);
+            assert_eq!(format.widen(&bits), values);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Stable, non-cryptographic hashing.
+//!
+//! [`std::collections::hash_map::DefaultHasher`] is free to change between
+//! Rust releases, which is no good when a hash ends up in a model's inputs.
+//! The hashes in this module are fully specified, so they give the same
+//! result on every platform and are easy to reproduce in other languages.
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// The 64-bit [FNV-1a][fnv] hash.
+///
+/// # Examples
+///
+/// ```rust
+/// use hotg_rune_proc_blocks::hash::fnv1a;
+///
+/// assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+/// ```
+///
+/// [fnv]: http://www.isthe.com/chongo/tech/comp/fnv/index.html
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_fnv1a_hashes() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+}
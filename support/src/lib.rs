@@ -2,18 +2,40 @@
 
 pub extern crate ndarray;
 
+pub mod abi;
+pub mod arena;
 #[cfg(feature = "runtime_v1")]
 mod bindings;
 
 mod buffer_ext;
+mod capability;
 pub mod common;
+mod complex;
+mod dimensions;
+#[cfg(feature = "half")]
+pub mod half_precision;
+pub mod hash;
+pub mod mask;
+#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+pub mod mock;
+mod resources;
+mod small_tensor;
+pub mod state;
+#[cfg(feature = "proptest")]
+pub mod strategies;
 mod string_builder;
+pub mod testing;
 mod value_type;
 
 use std::sync::Mutex;
 
 pub use crate::{
-    buffer_ext::BufferExt,
+    buffer_ext::{BufferExt, InvalidBuffer},
+    capability::{Capability, UnknownCapability},
+    complex::{complex_dimensions, Complex},
+    dimensions::{resolve_dimensions, Dimension, InvalidDimension},
+    resources::{Cost, InvalidResourceLimit, ResourceLimit},
+    small_tensor::SmallTensor,
     string_builder::{string_tensor_from_ndarray, StringBuilder},
     value_type::{SliceExt, ValueType},
 };
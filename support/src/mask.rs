@@ -0,0 +1,108 @@
+//! Helpers for working with boolean masks.
+//!
+//! The `runtime-v1` interface doesn't have a boolean [`ElementType`][et], so
+//! masks are sent as `u8` tensors where `1` means `true` and `0` means
+//! `false`. When reading a mask, any non-zero value is treated as `true`.
+//!
+//! [et]: crate::runtime_v1::ElementType
+
+/// The value used for `true` in a mask.
+pub const TRUE: u8 = 1;
+/// The value used for `false` in a mask.
+pub const FALSE: u8 = 0;
+
+/// Convert booleans to a mask.
+///
+/// # Examples
+///
+/// ```rust
+/// use hotg_rune_proc_blocks::mask;
+///
+/// let values = [0.1, 0.7, 0.4, 0.9];
+///
+/// let above_threshold = mask::from_bools(values.iter().map(|&v| v > 0.5));
+///
+/// assert_eq!(above_threshold, vec![0, 1, 0, 1]);
+/// ```
+pub fn from_bools(values: impl IntoIterator<Item = bool>) -> Vec<u8> {
+    values.into_iter().map(u8::from).collect()
+}
+
+/// Read a mask as booleans.
+pub fn to_bools(mask: &[u8]) -> impl Iterator<Item = bool> + '_ {
+    mask.iter().map(|&value| value != FALSE)
+}
+
+/// Pack a mask into bits (least significant bit first), so it takes up an
+/// eighth of the space when sent to the host.
+///
+/// # Examples
+///
+/// ```rust
+/// use hotg_rune_proc_blocks::mask;
+///
+/// let packed = mask::pack(&[1, 0, 1, 1, 0, 0, 0, 0, 1]);
+///
+/// assert_eq!(packed, vec![0b0000_1101, 0b0000_0001]);
+/// assert_eq!(mask::unpack(&packed, 9), vec![1, 0, 1, 1, 0, 0, 0, 0, 1]);
+/// ```
+pub fn pack(mask: &[u8]) -> Vec<u8> {
+    mask.chunks(8)
+        .map(|chunk| {
+            to_bools(chunk)
+                .enumerate()
+                .filter(|&(_, value)| value)
+                .fold(0, |byte, (bit, _)| byte | (1 << bit))
+        })
+        .collect()
+}
+
+/// Unpack the first `len` values from a mask created with [`pack()`].
+pub fn unpack(packed: &[u8], len: usize) -> Vec<u8> {
+    (0..len)
+        .map(|i| {
+            let byte = packed.get(i / 8).copied().unwrap_or(FALSE);
+            (byte >> (i % 8)) & 1
+        })
+        .collect()
+}
+
+/// Render a mask as RGBA pixels, where masked values get the provided
+/// `colour` and everything else is fully transparent.
+///
+/// This lets hosts draw a mask over an image without needing to interpret
+/// it themselves.
+pub fn to_rgba(mask: &[u8], colour: [u8; 4]) -> Vec<u8> {
+    to_bools(mask)
+        .flat_map(|value| if value { colour } else { [0; 4] })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn any_non_zero_value_is_true() {
+        let bools: Vec<bool> = to_bools(&[0, 1, 255]).collect();
+
+        assert_eq!(bools, vec![false, true, true]);
+    }
+
+    #[test]
+    fn pack_and_unpack_round_trip() {
+        let mask: Vec<u8> = (0..21).map(|i| u8::from(i % 3 == 0)).collect();
+
+        let packed = pack(&mask);
+
+        assert_eq!(packed.len(), 3);
+        assert_eq!(unpack(&packed, mask.len()), mask);
+    }
+
+    #[test]
+    fn render_mask_as_rgba() {
+        let pixels = to_rgba(&[1, 0], [255, 0, 0, 128]);
+
+        assert_eq!(pixels, vec![255, 0, 0, 128, 0, 0, 0, 0]);
+    }
+}
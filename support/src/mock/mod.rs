@@ -0,0 +1,226 @@
+//! An in-memory implementation of the `runtime-v1` host interface, so a
+//! proc-block's `graph()` and `kernel()` functions can be exercised with a
+//! plain `cargo test`.
+//!
+//! When the `mock` feature is enabled (and we aren't compiling to
+//! WebAssembly), [`crate::runtime_v1`] talks to this module instead of
+//! importing functions from the host. Each thread gets its own host, so tests
+//! running in parallel won't interfere with each other.
+//!
+//! Proc-blocks will normally enable the feature through their
+//! `dev-dependencies`:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! hotg-rune-proc-blocks = { path = "../support", features = ["mock"] }
+//! ```
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use hotg_rune_proc_blocks::{mock, runtime_v1::ElementType};
+//! use crate::proc_block_v1::ProcBlockV1 as _;
+//!
+//! mock::set_argument("node", "modulo", "2");
+//! let input = mock::Tensor::new(ElementType::F64, &[3], &[1.0_f64, 2.0, 3.0]);
+//! mock::set_input("node", "input", input);
+//!
+//! ProcBlockV1::graph("node".to_string()).unwrap();
+//! ProcBlockV1::kernel("node".to_string()).unwrap();
+//!
+//! let node = mock::node("node").unwrap();
+//! assert_eq!(node.outputs["output"].to_vec::<f64>(), vec![1.0, 0.0, 1.0]);
+//! ```
+//!
+//! Models aren't supported yet.
+
+pub(crate) mod runtime_v1;
+
+use std::{cell::RefCell, collections::HashMap};
+
+use crate::{
+    runtime_v1::{ArgumentType, ElementType, LogLevel},
+    SliceExt, ValueType,
+};
+
+thread_local! {
+    static HOST: RefCell<Host> = RefCell::new(Host::default());
+}
+
+#[derive(Debug, Default)]
+struct Host {
+    nodes: HashMap<String, Node>,
+    metadata: Option<Metadata>,
+    global_inputs: HashMap<String, Tensor>,
+    global_outputs: HashMap<String, Tensor>,
+    logs: Vec<LogRecord>,
+}
+
+fn with_host<T>(thunk: impl FnOnce(&mut Host) -> T) -> T {
+    HOST.with(|host| thunk(&mut host.borrow_mut()))
+}
+
+fn with_node<T>(node_id: &str, thunk: impl FnOnce(&mut Node) -> T) -> T {
+    with_host(|host| thunk(host.nodes.entry(node_id.to_string()).or_default()))
+}
+
+/// Clear everything that has been registered with this thread's host.
+pub fn reset() { with_host(|host| *host = Host::default()); }
+
+/// Make sure a node exists so `GraphContext::for_node()` and
+/// `KernelContext::for_node()` will succeed, even if it has no arguments or
+/// inputs.
+pub fn add_node(node_id: &str) { with_node(node_id, |_| {}); }
+
+/// Set an argument that will be passed to a node.
+pub fn set_argument(node_id: &str, name: &str, value: impl Into<String>) {
+    let value = value.into();
+    with_node(node_id, |node| node.arguments.insert(name.to_string(), value));
+}
+
+/// Set one of a node's input tensors.
+pub fn set_input(node_id: &str, name: &str, tensor: Tensor) {
+    with_node(node_id, |node| node.inputs.insert(name.to_string(), tensor));
+}
+
+/// Set a global input tensor, as returned by
+/// `KernelContext::get_global_input()`.
+pub fn set_global_input(name: &str, tensor: Tensor) {
+    with_host(|host| host.global_inputs.insert(name.to_string(), tensor));
+}
+
+/// Get a snapshot of a node's state.
+pub fn node(node_id: &str) -> Option<Node> {
+    with_host(|host| host.nodes.get(node_id).cloned())
+}
+
+/// Get a global output tensor set by `KernelContext::set_global_output()`.
+pub fn global_output(name: &str) -> Option<Tensor> {
+    with_host(|host| host.global_outputs.get(name).cloned())
+}
+
+/// Get the metadata passed to `register_node()`, if it has been called.
+pub fn registered_metadata() -> Option<Metadata> {
+    with_host(|host| host.metadata.clone())
+}
+
+/// Get every message that has been logged.
+pub fn logs() -> Vec<LogRecord> { with_host(|host| host.logs.clone()) }
+
+/// Everything the host knows about a node.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Node {
+    pub arguments: HashMap<String, String>,
+    pub inputs: HashMap<String, Tensor>,
+    /// The outputs set by the kernel.
+    pub outputs: HashMap<String, Tensor>,
+    /// The inputs declared by `graph()`.
+    pub graph_inputs: Vec<TensorDeclaration>,
+    /// The outputs declared by `graph()`.
+    pub graph_outputs: Vec<TensorDeclaration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tensor {
+    pub element_type: ElementType,
+    pub dimensions: Vec<u32>,
+    pub buffer: Vec<u8>,
+}
+
+impl Tensor {
+    pub fn new<T: ValueType>(
+        element_type: ElementType,
+        dimensions: &[u32],
+        values: &[T],
+    ) -> Self {
+        Tensor {
+            element_type,
+            dimensions: dimensions.to_vec(),
+            buffer: values.as_bytes().to_vec(),
+        }
+    }
+
+    /// Copy the tensor's elements out of its buffer.
+    ///
+    /// Unlike [`crate::BufferExt::elements()`], this doesn't require the
+    /// buffer to be aligned.
+    pub fn to_vec<T: ValueType + Copy>(&self) -> Vec<T> {
+        self.buffer
+            .chunks_exact(std::mem::size_of::<T>())
+            .map(|chunk| unsafe {
+                // Safety: ValueType means any bit pattern is valid and the
+                // chunk is exactly the right size.
+                std::ptr::read_unaligned(chunk.as_ptr().cast::<T>())
+            })
+            .collect()
+    }
+}
+
+/// A tensor declared by `graph()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TensorDeclaration {
+    pub name: String,
+    pub element_type: ElementType,
+    pub dimensions: Dimensions,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Dimensions {
+    Dynamic,
+    /// Fixed dimensions, where `0` means that dimension can have any length.
+    Fixed(Vec<u32>),
+}
+
+/// The metadata passed to `register_node()`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Metadata {
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+    pub repository: Option<String>,
+    pub homepage: Option<String>,
+    pub tags: Vec<String>,
+    pub arguments: Vec<ArgumentMetadata>,
+    pub inputs: Vec<TensorMetadata>,
+    pub outputs: Vec<TensorMetadata>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ArgumentMetadata {
+    pub name: String,
+    pub description: Option<String>,
+    pub default_value: Option<String>,
+    pub hints: Vec<ArgumentHint>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TensorMetadata {
+    pub name: String,
+    pub description: Option<String>,
+    pub hints: Vec<TensorHint>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TensorHint {
+    DisplayAs(String),
+    SupportedShape {
+        accepted_element_types: Vec<ElementType>,
+        dimensions: Dimensions,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgumentHint {
+    NonNegativeNumber,
+    StringEnum(Vec<String>),
+    NumberInRange { min: String, max: String },
+    SupportedArgumentType(ArgumentType),
+}
+
+/// A message passed to `log()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogRecord {
+    pub level: LogLevel,
+    pub target: String,
+    pub message: String,
+}
@@ -0,0 +1,432 @@
+//! A drop-in replacement for the bindings generated from `runtime-v1.wit`,
+//! backed by the in-memory host in [`crate::mock`].
+
+use std::cell::RefCell;
+
+use crate::mock::{self, with_host, with_node, Dimensions, TensorDeclaration};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ElementType {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+    U64,
+    I64,
+    F64,
+    Utf8,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DimensionsParam<'a> {
+    Dynamic,
+    Fixed(&'a [u32]),
+}
+
+impl From<DimensionsParam<'_>> for Dimensions {
+    fn from(dimensions: DimensionsParam<'_>) -> Self {
+        match dimensions {
+            DimensionsParam::Dynamic => Dimensions::Dynamic,
+            DimensionsParam::Fixed(fixed) => Dimensions::Fixed(fixed.to_vec()),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ArgumentType {
+    UnsignedInteger,
+    Integer,
+    Float,
+    String,
+    LongString,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TensorParam<'a> {
+    pub element_type: ElementType,
+    pub dimensions: &'a [u32],
+    pub buffer: &'a [u8],
+}
+
+impl From<TensorParam<'_>> for mock::Tensor {
+    fn from(tensor: TensorParam<'_>) -> Self {
+        mock::Tensor {
+            element_type: tensor.element_type,
+            dimensions: tensor.dimensions.to_vec(),
+            buffer: tensor.buffer.to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TensorResult {
+    pub element_type: ElementType,
+    pub dimensions: Vec<u32>,
+    pub buffer: Vec<u8>,
+}
+
+impl From<mock::Tensor> for TensorResult {
+    fn from(tensor: mock::Tensor) -> Self {
+        TensorResult {
+            element_type: tensor.element_type,
+            dimensions: tensor.dimensions,
+            buffer: tensor.buffer,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LogMetadata<'a> {
+    pub name: &'a str,
+    pub target: &'a str,
+    pub level: LogLevel,
+    pub file: Option<&'a str>,
+    pub line: Option<u32>,
+    pub module: Option<&'a str>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LogValue<'a> {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(&'a str),
+}
+
+pub type LogValueMap<'a> = &'a [(&'a str, LogValue<'a>)];
+
+#[derive(Debug)]
+pub struct Metadata(RefCell<mock::Metadata>);
+
+impl Metadata {
+    pub fn new(name: &str, version: &str) -> Self {
+        Metadata(RefCell::new(mock::Metadata {
+            name: name.to_string(),
+            version: version.to_string(),
+            ..Default::default()
+        }))
+    }
+
+    pub fn set_description(&self, description: &str) {
+        self.0.borrow_mut().description = Some(description.to_string());
+    }
+
+    pub fn set_repository(&self, url: &str) {
+        self.0.borrow_mut().repository = Some(url.to_string());
+    }
+
+    pub fn set_homepage(&self, url: &str) {
+        self.0.borrow_mut().homepage = Some(url.to_string());
+    }
+
+    pub fn add_tag(&self, tag: &str) {
+        self.0.borrow_mut().tags.push(tag.to_string());
+    }
+
+    pub fn add_argument(&self, arg: &ArgumentMetadata) {
+        let arg = arg.0.borrow().clone();
+        self.0.borrow_mut().arguments.push(arg);
+    }
+
+    pub fn add_input(&self, metadata: &TensorMetadata) {
+        let metadata = metadata.0.borrow().clone();
+        self.0.borrow_mut().inputs.push(metadata);
+    }
+
+    pub fn add_output(&self, metadata: &TensorMetadata) {
+        let metadata = metadata.0.borrow().clone();
+        self.0.borrow_mut().outputs.push(metadata);
+    }
+}
+
+#[derive(Debug)]
+pub struct ArgumentMetadata(RefCell<mock::ArgumentMetadata>);
+
+impl ArgumentMetadata {
+    pub fn new(name: &str) -> Self {
+        ArgumentMetadata(RefCell::new(mock::ArgumentMetadata {
+            name: name.to_string(),
+            ..Default::default()
+        }))
+    }
+
+    pub fn set_description(&self, description: &str) {
+        self.0.borrow_mut().description = Some(description.to_string());
+    }
+
+    pub fn set_default_value(&self, default_value: &str) {
+        self.0.borrow_mut().default_value = Some(default_value.to_string());
+    }
+
+    pub fn add_hint(&self, hint: &ArgumentHint) {
+        self.0.borrow_mut().hints.push(hint.0.clone());
+    }
+}
+
+#[derive(Debug)]
+pub struct TensorMetadata(RefCell<mock::TensorMetadata>);
+
+impl TensorMetadata {
+    pub fn new(name: &str) -> Self {
+        TensorMetadata(RefCell::new(mock::TensorMetadata {
+            name: name.to_string(),
+            ..Default::default()
+        }))
+    }
+
+    pub fn set_description(&self, description: &str) {
+        self.0.borrow_mut().description = Some(description.to_string());
+    }
+
+    pub fn add_hint(&self, hint: &TensorHint) {
+        self.0.borrow_mut().hints.push(hint.0.clone());
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TensorHint(mock::TensorHint);
+
+#[derive(Debug, Clone)]
+pub struct ArgumentHint(mock::ArgumentHint);
+
+pub fn interpret_as_image() -> TensorHint {
+    TensorHint(mock::TensorHint::DisplayAs("image".to_string()))
+}
+
+pub fn interpret_as_audio() -> TensorHint {
+    TensorHint(mock::TensorHint::DisplayAs("audio".to_string()))
+}
+
+pub fn supported_shapes(
+    supported_element_type: &[ElementType],
+    dimensions: DimensionsParam<'_>,
+) -> TensorHint {
+    TensorHint(mock::TensorHint::SupportedShape {
+        accepted_element_types: supported_element_type.to_vec(),
+        dimensions: dimensions.into(),
+    })
+}
+
+pub fn interpret_as_number_in_range(min: &str, max: &str) -> ArgumentHint {
+    ArgumentHint(mock::ArgumentHint::NumberInRange {
+        min: min.to_string(),
+        max: max.to_string(),
+    })
+}
+
+pub fn interpret_as_string_in_enum(string_enum: &[&str]) -> ArgumentHint {
+    ArgumentHint(mock::ArgumentHint::StringEnum(
+        string_enum.iter().map(|s| s.to_string()).collect(),
+    ))
+}
+
+pub fn non_negative_number() -> ArgumentHint {
+    ArgumentHint(mock::ArgumentHint::NonNegativeNumber)
+}
+
+pub fn supported_argument_type(hint: ArgumentType) -> ArgumentHint {
+    ArgumentHint(mock::ArgumentHint::SupportedArgumentType(hint))
+}
+
+pub fn register_node(metadata: &Metadata) {
+    let metadata = metadata.0.borrow().clone();
+    with_host(|host| host.metadata = Some(metadata));
+}
+
+pub fn is_enabled(_metadata: LogMetadata<'_>) -> bool { true }
+
+pub fn log(metadata: LogMetadata<'_>, message: &str, _data: LogValueMap<'_>) {
+    let record = mock::LogRecord {
+        level: metadata.level,
+        target: metadata.target.to_string(),
+        message: message.to_string(),
+    };
+    with_host(|host| host.logs.push(record));
+}
+
+#[derive(Debug)]
+pub struct GraphContext {
+    node_id: String,
+}
+
+impl GraphContext {
+    pub fn for_node(node_id: &str) -> Option<Self> {
+        with_host(|host| host.nodes.contains_key(node_id)).then(|| {
+            GraphContext {
+                node_id: node_id.to_string(),
+            }
+        })
+    }
+
+    pub fn get_argument(&self, name: &str) -> Option<String> {
+        with_node(&self.node_id, |node| node.arguments.get(name).cloned())
+    }
+
+    pub fn add_input_tensor(
+        &self,
+        name: &str,
+        element_type: ElementType,
+        dimensions: DimensionsParam<'_>,
+    ) {
+        let declaration = TensorDeclaration {
+            name: name.to_string(),
+            element_type,
+            dimensions: dimensions.into(),
+        };
+        with_node(&self.node_id, |node| node.graph_inputs.push(declaration));
+    }
+
+    pub fn add_output_tensor(
+        &self,
+        name: &str,
+        element_type: ElementType,
+        dimensions: DimensionsParam<'_>,
+    ) {
+        let declaration = TensorDeclaration {
+            name: name.to_string(),
+            element_type,
+            dimensions: dimensions.into(),
+        };
+        with_node(&self.node_id, |node| node.graph_outputs.push(declaration));
+    }
+}
+
+#[derive(Debug)]
+pub struct KernelContext {
+    node_id: String,
+}
+
+impl KernelContext {
+    pub fn for_node(node_id: &str) -> Option<Self> {
+        with_host(|host| host.nodes.contains_key(node_id)).then(|| {
+            KernelContext {
+                node_id: node_id.to_string(),
+            }
+        })
+    }
+
+    pub fn get_argument(&self, name: &str) -> Option<String> {
+        with_node(&self.node_id, |node| node.arguments.get(name).cloned())
+    }
+
+    pub fn get_input_tensor(&self, name: &str) -> Option<TensorResult> {
+        with_node(&self.node_id, |node| node.inputs.get(name).cloned())
+            .map(TensorResult::from)
+    }
+
+    pub fn set_output_tensor(&self, name: &str, tensor: TensorParam<'_>) {
+        let tensor = mock::Tensor::from(tensor);
+        with_node(&self.node_id, |node| {
+            node.outputs.insert(name.to_string(), tensor)
+        });
+    }
+
+    pub fn get_global_input(&self, name: &str) -> Option<TensorResult> {
+        with_host(|host| host.global_inputs.get(name).cloned())
+            .map(TensorResult::from)
+    }
+
+    pub fn set_global_output(&self, name: &str, tensor: TensorParam<'_>) {
+        let tensor = mock::Tensor::from(tensor);
+        with_host(|host| host.global_outputs.insert(name.to_string(), tensor));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contexts_only_exist_for_known_nodes() {
+        mock::reset();
+        assert!(GraphContext::for_node("node").is_none());
+
+        mock::add_node("node");
+
+        assert!(GraphContext::for_node("node").is_some());
+        assert!(KernelContext::for_node("node").is_some());
+    }
+
+    #[test]
+    fn graph_declarations_are_recorded() {
+        mock::reset();
+        mock::set_argument("node", "length", "3");
+        let ctx = GraphContext::for_node("node").unwrap();
+
+        assert_eq!(ctx.get_argument("length").as_deref(), Some("3"));
+        ctx.add_input_tensor("x", ElementType::F32, DimensionsParam::Dynamic);
+        ctx.add_output_tensor(
+            "y",
+            ElementType::U8,
+            DimensionsParam::Fixed(&[1, 3]),
+        );
+
+        let node = mock::node("node").unwrap();
+        assert_eq!(
+            node.graph_inputs,
+            vec![TensorDeclaration {
+                name: "x".to_string(),
+                element_type: ElementType::F32,
+                dimensions: Dimensions::Dynamic,
+            }]
+        );
+        assert_eq!(
+            node.graph_outputs[0].dimensions,
+            Dimensions::Fixed(vec![1, 3])
+        );
+    }
+
+    #[test]
+    fn kernel_inputs_and_outputs() {
+        mock::reset();
+        let input = mock::Tensor::new(ElementType::F32, &[2], &[1.0_f32, 2.0]);
+        mock::set_input("node", "input", input.clone());
+        let ctx = KernelContext::for_node("node").unwrap();
+
+        let got = ctx.get_input_tensor("input").unwrap();
+        ctx.set_output_tensor(
+            "output",
+            TensorParam {
+                element_type: got.element_type,
+                dimensions: &got.dimensions,
+                buffer: &got.buffer,
+            },
+        );
+
+        let node = mock::node("node").unwrap();
+        assert_eq!(node.outputs["output"], input);
+        assert_eq!(node.outputs["output"].to_vec::<f32>(), vec![1.0, 2.0]);
+    }
+
+    #[test]
+    fn metadata_is_registered() {
+        mock::reset();
+        let metadata = Metadata::new("Example", "1.0.0");
+        let arg = ArgumentMetadata::new("arg");
+        arg.add_hint(&non_negative_number());
+        metadata.add_argument(&arg);
+
+        register_node(&metadata);
+
+        let registered = mock::registered_metadata().unwrap();
+        assert_eq!(registered.name, "Example");
+        assert_eq!(
+            registered.arguments[0].hints,
+            vec![mock::ArgumentHint::NonNegativeNumber]
+        );
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+/// A worst-case cost, expressed as a linear function of the total number of
+/// elements across all input tensors (`fixed + per_element * n`).
+///
+/// # Examples
+///
+/// ```rust
+/// use hotg_rune_proc_blocks::Cost;
+///
+/// let cost = Cost::linear(1024.0, 8.0);
+///
+/// assert_eq!(cost.evaluate(100), 1824.0);
+/// assert_eq!(cost.to_string(), "1024+8n");
+/// assert_eq!("1024+8n".parse(), Ok(cost));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct Cost {
+    pub fixed: f64,
+    pub per_element: f64,
+}
+
+impl Cost {
+    /// A cost which doesn't depend on the size of the input.
+    pub const fn constant(fixed: f64) -> Self {
+        Cost {
+            fixed,
+            per_element: 0.0,
+        }
+    }
+
+    pub const fn linear(fixed: f64, per_element: f64) -> Self {
+        Cost { fixed, per_element }
+    }
+
+    /// Calculate the cost for inputs with `input_elements` elements in total.
+    pub fn evaluate(self, input_elements: u64) -> f64 {
+        self.fixed + self.per_element * input_elements as f64
+    }
+}
+
+impl Display for Cost {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.per_element == 0.0 {
+            write!(f, "{}", self.fixed)
+        } else {
+            write!(f, "{}+{}n", self.fixed, self.per_element)
+        }
+    }
+}
+
+impl FromStr for Cost {
+    type Err = InvalidResourceLimit;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidResourceLimit {
+            actual: s.to_string(),
+        };
+        let mut cost = Cost::constant(0.0);
+
+        for term in s.split('+').map(|t| t.trim()) {
+            match term.strip_suffix('n') {
+                Some(coefficient) => {
+                    cost.per_element += coefficient
+                        .trim()
+                        .parse::<f64>()
+                        .map_err(|_| invalid())?
+                },
+                None => {
+                    cost.fixed += term.parse::<f64>().map_err(|_| invalid())?
+                },
+            }
+        }
+
+        Ok(cost)
+    }
+}
+
+/// A declaration of the worst-case resources a proc-block needs, so
+/// schedulers on constrained devices can reject a pipeline before it is
+/// deployed.
+///
+/// Like [`crate::Capability`], resource limits are recorded as tags (with a
+/// `resource:` prefix) so they don't require changes to the `runtime-v1`
+/// interface. The `xtask bench` command can check them against measurements.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ResourceLimit {
+    /// The largest input (in elements, summed across all input tensors) the
+    /// proc-block supports.
+    MaxInputElements(u64),
+    /// How many bytes of memory the proc-block will allocate, on top of what
+    /// the module starts with.
+    MemoryBytes(Cost),
+    /// How long a single call to `kernel()` may take, in microseconds.
+    ExecutionTimeMicros(Cost),
+    /// An estimate of the work done by a single call to `kernel()`, in
+    /// floating-point operations (or any other unit, as long as it is used
+    /// consistently).
+    ///
+    /// Unlike the other variants this isn't a hard limit. It lets schedulers
+    /// choose between alternative proc-blocks (e.g. a fast resize versus an
+    /// accurate one) based on a device's power budget.
+    ComputeCost(Cost),
+}
+
+impl ResourceLimit {
+    /// The prefix used when a [`ResourceLimit`] is stored as a tag.
+    pub const TAG_PREFIX: &'static str = "resource:";
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            ResourceLimit::MaxInputElements(_) => "max-input-elements",
+            ResourceLimit::MemoryBytes(_) => "memory-bytes",
+            ResourceLimit::ExecutionTimeMicros(_) => "execution-time-us",
+            ResourceLimit::ComputeCost(_) => "compute-cost",
+        }
+    }
+
+    /// Get the tag used to declare this limit.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use hotg_rune_proc_blocks::{Cost, ResourceLimit};
+    ///
+    /// let limit = ResourceLimit::MemoryBytes(Cost::linear(4096.0, 4.0));
+    ///
+    /// assert_eq!(limit.to_tag(), "resource:memory-bytes=4096+4n");
+    /// ```
+    pub fn to_tag(self) -> String {
+        let value = match self {
+            ResourceLimit::MaxInputElements(n) => n.to_string(),
+            ResourceLimit::MemoryBytes(cost)
+            | ResourceLimit::ExecutionTimeMicros(cost)
+            | ResourceLimit::ComputeCost(cost) => cost.to_string(),
+        };
+
+        format!("{}{}={}", ResourceLimit::TAG_PREFIX, self.name(), value)
+    }
+
+    /// Parse a tag created by [`ResourceLimit::to_tag()`].
+    pub fn from_tag(tag: &str) -> Option<ResourceLimit> {
+        tag.strip_prefix(ResourceLimit::TAG_PREFIX)?.parse().ok()
+    }
+}
+
+impl FromStr for ResourceLimit {
+    type Err = InvalidResourceLimit;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidResourceLimit {
+            actual: s.to_string(),
+        };
+        let (name, value) = s.split_once('=').ok_or_else(invalid)?;
+
+        match name {
+            "max-input-elements" => value
+                .parse()
+                .map(ResourceLimit::MaxInputElements)
+                .map_err(|_| invalid()),
+            "memory-bytes" => value.parse().map(ResourceLimit::MemoryBytes),
+            "execution-time-us" => {
+                value.parse().map(ResourceLimit::ExecutionTimeMicros)
+            },
+            "compute-cost" => value.parse().map(ResourceLimit::ComputeCost),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InvalidResourceLimit {
+    pub actual: String,
+}
+
+impl std::error::Error for InvalidResourceLimit {}
+
+impl Display for InvalidResourceLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\" is not a valid resource limit", self.actual)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_round_trip_through_tags() {
+        let limits = [
+            ResourceLimit::MaxInputElements(65536),
+            ResourceLimit::MemoryBytes(Cost::constant(1024.0)),
+            ResourceLimit::ExecutionTimeMicros(Cost::linear(10.0, 0.25)),
+            ResourceLimit::ComputeCost(Cost::linear(0.0, 5.0)),
+        ];
+
+        for limit in limits {
+            let tag = limit.to_tag();
+
+            assert_eq!(ResourceLimit::from_tag(&tag), Some(limit));
+        }
+    }
+
+    #[test]
+    fn per_element_terms_can_come_first() {
+        let cost: Cost = "2n + 100".parse().unwrap();
+
+        assert_eq!(cost, Cost::linear(100.0, 2.0));
+    }
+
+    #[test]
+    fn normal_tags_are_not_resource_limits() {
+        assert_eq!(ResourceLimit::from_tag("image"), None);
+        assert_eq!(ResourceLimit::from_tag("resource:memory-bytes=lots"), None);
+        assert_eq!(ResourceLimit::from_tag("resource:gpus=1"), None);
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    fmt::{self, Debug, Formatter},
+    iter::FromIterator,
+    ops::{Deref, DerefMut},
+};
+
+/// A growable list of elements which is stored inline until it has more than
+/// `N` elements.
+///
+/// A lot of proc-blocks only ever output a handful of values (a score, the
+/// top few indices, etc.), so this lets them skip the heap allocation they
+/// would otherwise make with a [`Vec`].
+///
+/// # Examples
+///
+/// ```rust
+/// use hotg_rune_proc_blocks::{SliceExt, SmallTensor};
+///
+/// let mut indices: SmallTensor<u32> = SmallTensor::new();
+/// indices.push(3);
+/// indices.push(1);
+///
+/// assert!(indices.is_inline());
+/// assert_eq!(indices.as_slice(), &[3, 1]);
+/// // it derefs to a slice, so you can use it anywhere a byte buffer is needed
+/// assert_eq!(indices.as_bytes().len(), 2 * std::mem::size_of::<u32>());
+///
+/// // Going past the inline capacity moves everything to the heap
+/// let big: SmallTensor<u32, 4> = (0..10).collect();
+/// assert!(!big.is_inline());
+/// assert_eq!(big.len(), 10);
+/// ```
+#[derive(Clone)]
+pub struct SmallTensor<T, const N: usize = 16> {
+    storage: Storage<T, N>,
+}
+
+#[derive(Clone)]
+enum Storage<T, const N: usize> {
+    Inline { elements: [T; N], len: usize },
+    Heap(Vec<T>),
+}
+
+impl<T: Copy + Default, const N: usize> SmallTensor<T, N> {
+    pub fn new() -> Self {
+        SmallTensor {
+            storage: Storage::Inline {
+                elements: [T::default(); N],
+                len: 0,
+            },
+        }
+    }
+
+    /// Create a tensor containing a single element.
+    pub fn scalar(value: T) -> Self {
+        let mut tensor = SmallTensor::new();
+        tensor.push(value);
+        tensor
+    }
+
+    pub fn push(&mut self, value: T) {
+        match &mut self.storage {
+            Storage::Inline { elements, len } if *len < N => {
+                elements[*len] = value;
+                *len += 1;
+            },
+            Storage::Inline { elements, len } => {
+                let mut spilled = Vec::with_capacity(*len * 2 + 1);
+                spilled.extend_from_slice(&elements[..*len]);
+                spilled.push(value);
+                self.storage = Storage::Heap(spilled);
+            },
+            Storage::Heap(elements) => elements.push(value),
+        }
+    }
+
+    /// Are the elements still stored inline?
+    pub fn is_inline(&self) -> bool {
+        matches!(self.storage, Storage::Inline { .. })
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        match &self.storage {
+            Storage::Inline { elements, len } => &elements[..*len],
+            Storage::Heap(elements) => elements,
+        }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        match &mut self.storage {
+            Storage::Inline { elements, len } => &mut elements[..*len],
+            Storage::Heap(elements) => elements,
+        }
+    }
+}
+
+impl<T: Copy + Default, const N: usize> Default for SmallTensor<T, N> {
+    fn default() -> Self { SmallTensor::new() }
+}
+
+impl<T: Copy + Default, const N: usize> Deref for SmallTensor<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target { self.as_slice() }
+}
+
+impl<T: Copy + Default, const N: usize> DerefMut for SmallTensor<T, N> {
+    fn deref_mut(&mut self) -> &mut Self::Target { self.as_mut_slice() }
+}
+
+impl<T: Copy + Default, const N: usize> Extend<T> for SmallTensor<T, N> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl<T: Copy + Default, const N: usize> FromIterator<T> for SmallTensor<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut tensor = SmallTensor::new();
+        tensor.extend(iter);
+        tensor
+    }
+}
+
+impl<T: Copy + Default, const N: usize> From<&[T]> for SmallTensor<T, N> {
+    fn from(elements: &[T]) -> Self { elements.iter().copied().collect() }
+}
+
+impl<T, const N: usize> Debug for SmallTensor<T, N>
+where
+    T: Copy + Default + Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
+
+impl<T, const N: usize> PartialEq for SmallTensor<T, N>
+where
+    T: Copy + Default + PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool { self.as_slice() == other.as_slice() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spill_onto_the_heap() {
+        let mut tensor: SmallTensor<f32, 2> = SmallTensor::new();
+
+        tensor.push(1.0);
+        tensor.push(2.0);
+        assert!(tensor.is_inline());
+
+        tensor.push(3.0);
+        assert!(!tensor.is_inline());
+
+        assert_eq!(tensor.as_slice(), &[1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn inline_and_heap_tensors_compare_by_their_elements() {
+        let mut inline: SmallTensor<u8, 4> = (0..4).collect();
+        let heap: SmallTensor<u8, 4> = (0..5).collect();
+        assert!(inline.is_inline());
+        assert!(!heap.is_inline());
+        assert_ne!(inline, heap);
+
+        inline.push(4);
+
+        assert_eq!(inline, heap);
+    }
+}
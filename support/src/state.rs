@@ -0,0 +1,163 @@
+//! State which persists between invocations of a proc-block's kernel.
+//!
+//! The `proc-block-v1` interface treats each call to `kernel()` as stateless,
+//! but streaming proc-blocks (smoothing, tracking, counting, etc.) need to
+//! remember what they saw last time. Because the proc-block's WebAssembly
+//! instance lives for as long as the pipeline, we can keep that state in a
+//! `static` as long as it is keyed by node ID so each node in the pipeline
+//! gets its own copy.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use once_cell::sync::OnceCell;
+
+/// Per-node state, keyed by node ID.
+///
+/// Unlike most proc-blocks, anything stored here survives between
+/// invocations. It lives in the proc-block's WebAssembly instance, so it is
+/// created the first time a node's `kernel()` runs and sticks around until
+/// the instance is thrown away (or [`NodeState::reset()`] is called). Each
+/// node ID gets its own value, so using the same proc-block for several
+/// nodes in a pipeline won't mix up their state.
+///
+/// # Examples
+///
+/// ```rust
+/// use hotg_rune_proc_blocks::state::NodeState;
+///
+/// #[derive(Default)]
+/// struct Counter {
+///     calls: usize,
+/// }
+///
+/// static COUNTERS: NodeState<Counter> = NodeState::new();
+///
+/// fn kernel(id: &str) -> usize {
+///     COUNTERS.with(id, |counter| {
+///         counter.calls += 1;
+///         counter.calls
+///     })
+/// }
+///
+/// assert_eq!(kernel("first"), 1);
+/// assert_eq!(kernel("first"), 2);
+/// // each node gets its own state
+/// assert_eq!(kernel("second"), 1);
+/// ```
+#[derive(Debug)]
+pub struct NodeState<T> {
+    states: OnceCell<Mutex<HashMap<String, T>>>,
+}
+
+impl<T> NodeState<T> {
+    pub const fn new() -> Self {
+        NodeState {
+            states: OnceCell::new(),
+        }
+    }
+
+    /// Run a closure with mutable access to a node's state, using
+    /// [`Default`] to create the state the first time a node is seen.
+    pub fn with<R>(&self, node_id: &str, func: impl FnOnce(&mut T) -> R) -> R
+    where
+        T: Default,
+    {
+        self.with_or_insert(node_id, T::default, func)
+    }
+
+    /// Run a closure with mutable access to a node's state, using `init` to
+    /// create the state the first time a node is seen.
+    pub fn with_or_insert<R>(
+        &self,
+        node_id: &str,
+        init: impl FnOnce() -> T,
+        func: impl FnOnce(&mut T) -> R,
+    ) -> R {
+        let mut states = self.states().lock().unwrap();
+
+        let state = match states.get_mut(node_id) {
+            Some(state) => state,
+            None => states.entry(node_id.to_string()).or_insert_with(init),
+        };
+
+        func(state)
+    }
+
+    /// Like [`NodeState::with_or_insert()`], except creating the state may
+    /// fail.
+    ///
+    /// Nothing is stored when `init` fails, so the next call will try again.
+    /// Calling this from `graph()` is a good way to do expensive one-time
+    /// setup (building lookup tables, validating resources, etc.) up front,
+    /// where problems can be reported as a `GraphError` instead of the first
+    /// `kernel()` call doing the work or panicking.
+    pub fn try_with_or_insert<R, E>(
+        &self,
+        node_id: &str,
+        init: impl FnOnce() -> Result<T, E>,
+        func: impl FnOnce(&mut T) -> R,
+    ) -> Result<R, E> {
+        let mut states = self.states().lock().unwrap();
+
+        let state = match states.get_mut(node_id) {
+            Some(state) => state,
+            None => states.entry(node_id.to_string()).or_insert(init()?),
+        };
+
+        Ok(func(state))
+    }
+
+    /// Throw away a node's state so the next invocation starts from scratch,
+    /// returning whatever was there.
+    pub fn reset(&self, node_id: &str) -> Option<T> {
+        self.states().lock().unwrap().remove(node_id)
+    }
+
+    fn states(&self) -> &Mutex<HashMap<String, T>> {
+        self.states.get_or_init(|| Mutex::new(HashMap::new()))
+    }
+}
+
+impl<T> Default for NodeState<T> {
+    fn default() -> Self { NodeState::new() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_is_initialized_lazily() {
+        let state: NodeState<Vec<u32>> = NodeState::new();
+
+        let len = state.with_or_insert("node", || vec![1, 2, 3], |v| v.len());
+
+        assert_eq!(len, 3);
+    }
+
+    #[test]
+    fn failed_initialization_is_retried() {
+        let state: NodeState<u32> = NodeState::new();
+
+        let err = state.try_with_or_insert("node", || Err("oops"), |n| *n);
+        assert_eq!(err, Err("oops"));
+
+        let got: Result<u32, &str> =
+            state.try_with_or_insert("node", || Ok(7), |n| *n);
+        assert_eq!(got, Ok(7));
+        // The state sticks around once it has been created
+        let got = state.try_with_or_insert("node", || Err("unused"), |n| *n);
+        assert_eq!(got, Ok(7));
+    }
+
+    #[test]
+    fn reset_throws_away_the_old_state() {
+        let state: NodeState<u32> = NodeState::new();
+        state.with("node", |n| *n += 5);
+
+        assert_eq!(state.reset("node"), Some(5));
+
+        assert_eq!(state.with("node", |n| *n), 0);
+        assert_eq!(state.reset("unknown"), None);
+    }
+}
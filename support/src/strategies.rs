@@ -0,0 +1,165 @@
+//! [`proptest`] strategies for generating tensors.
+//!
+//! These are useful for checking properties which should hold for any
+//! input, regardless of its shape (e.g. "the output of softmax always sums
+//! to 1").
+
+use ndarray::{Array1, ArrayD, IxDyn};
+use proptest::{collection::vec, prelude::*};
+
+/// Generate tensor shapes with up to `max_rank` dimensions, where each
+/// dimension has at most `max_dimension` elements.
+///
+/// Dimensions may be zero, so make sure your code can handle empty tensors.
+pub fn shapes(
+    max_rank: usize,
+    max_dimension: usize,
+) -> impl Strategy<Value = Vec<usize>> {
+    vec(0..=max_dimension, 0..=max_rank)
+}
+
+/// Generate tensors with a random shape (see [`shapes()`]), where each
+/// element is generated using the `element` strategy.
+///
+/// # Examples
+///
+/// ```rust
+/// use hotg_rune_proc_blocks::strategies::tensors;
+/// use proptest::prelude::*;
+///
+/// proptest! {
+///     fn element_count_matches_shape(t in tensors(any::<u8>(), 3, 4)) {
+///         prop_assert_eq!(t.len(), t.shape().iter().product::<usize>());
+///     }
+/// }
+///
+/// element_count_matches_shape();
+/// ```
+pub fn tensors<S>(
+    element: S,
+    max_rank: usize,
+    max_dimension: usize,
+) -> impl Strategy<Value = ArrayD<S::Value>>
+where
+    S: Strategy + Clone,
+{
+    shapes(max_rank, max_dimension).prop_flat_map(move |shape| {
+        let len: usize = shape.iter().product();
+        vec(element.clone(), len).prop_map(move |elements| {
+            ArrayD::from_shape_vec(IxDyn(&shape), elements)
+                .expect("The element count always matches the shape")
+        })
+    })
+}
+
+/// Generate 1D tensors with up to `max_len` elements.
+pub fn vectors<S>(
+    element: S,
+    max_len: usize,
+) -> impl Strategy<Value = Array1<S::Value>>
+where
+    S: Strategy,
+{
+    vec(element, 0..=max_len).prop_map(Array1::from_vec)
+}
+
+/// Generate any of the [`ElementType`]s a tensor may have.
+///
+/// [`ElementType`]: crate::runtime_v1::ElementType
+#[cfg(feature = "runtime_v1")]
+pub fn element_types(
+) -> impl Strategy<Value = crate::runtime_v1::ElementType> {
+    element_types_from(crate::runtime_v1::ElementType::ALL)
+}
+
+/// Generate any of the numeric [`ElementType`]s.
+///
+/// [`ElementType`]: crate::runtime_v1::ElementType
+#[cfg(feature = "runtime_v1")]
+pub fn numeric_element_types(
+) -> impl Strategy<Value = crate::runtime_v1::ElementType> {
+    element_types_from(crate::runtime_v1::ElementType::NUMERIC)
+}
+
+#[cfg(feature = "runtime_v1")]
+fn element_types_from(
+    names: &'static [&'static str],
+) -> impl Strategy<Value = crate::runtime_v1::ElementType> {
+    proptest::sample::select(names).prop_map(|name| {
+        name.parse().expect("All element type names are valid")
+    })
+}
+
+/// Generate [`mock::Tensor`]s with a random shape and buffer, where the
+/// element type is generated using the `element_type` strategy.
+///
+/// Most of the time the buffer is exactly the right size for the shape and
+/// element type, but it may also be a bunch of random bytes. That makes this
+/// useful for checking that a kernel rejects malformed input instead of
+/// panicking.
+///
+/// [`mock::Tensor`]: crate::mock::Tensor
+#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+pub fn mock_tensors<S>(
+    element_type: S,
+    max_rank: usize,
+    max_dimension: usize,
+) -> impl Strategy<Value = crate::mock::Tensor>
+where
+    S: Strategy<Value = crate::runtime_v1::ElementType>,
+{
+    (element_type, shapes(max_rank, max_dimension))
+        .prop_flat_map(|(element_type, shape)| {
+            let len: usize = shape.iter().product();
+            let expected = len * element_size(element_type);
+            let buffer_len = prop_oneof![
+                3 => Just(expected),
+                1 => 0..=expected + 16,
+            ];
+            let buffer = buffer_len.prop_flat_map(|len| vec(any::<u8>(), len));
+
+            (Just(element_type), Just(shape), buffer)
+        })
+        .prop_map(|(element_type, shape, buffer)| crate::mock::Tensor {
+            element_type,
+            dimensions: shape.iter().map(|&d| d as u32).collect(),
+            buffer,
+        })
+}
+
+#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+fn element_size(element_type: crate::runtime_v1::ElementType) -> usize {
+    use crate::runtime_v1::ElementType;
+
+    match element_type {
+        ElementType::U8 | ElementType::I8 => 1,
+        ElementType::U16 | ElementType::I16 => 2,
+        ElementType::U32 | ElementType::I32 | ElementType::F32 => 4,
+        ElementType::U64 | ElementType::I64 | ElementType::F64 => 8,
+        // Strings are length-prefixed, so there isn't a fixed size
+        ElementType::Utf8 => 4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn shapes_respect_the_limits(shape in shapes(3, 5)) {
+            prop_assert!(shape.len() <= 3);
+            prop_assert!(shape.iter().all(|&d| d <= 5));
+        }
+
+        #[test]
+        fn tensors_have_the_right_number_of_elements(
+            tensor in tensors(any::<f32>(), 4, 3),
+        ) {
+            prop_assert_eq!(
+                tensor.len(),
+                tensor.shape().iter().product::<usize>()
+            );
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! Helpers for comparing floating point results in tests.
+//!
+//! Numeric proc-blocks rarely produce bit-identical results across
+//! toolchains and targets, so asserting exact equality on `f32`/`f64`
+//! outputs tends to make tests flaky. These helpers compare values using
+//! a combination of absolute and relative tolerances instead.
+
+use std::fmt::Debug;
+
+use ndarray::{ArrayBase, Data, Dimension};
+
+/// The maximum difference allowed between two values before they are
+/// considered different.
+///
+/// Two values, `a` and `b`, are "close" when
+/// `|a - b| <= max(absolute, relative * max(|a|, |b|))`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Tolerance {
+    pub absolute: f64,
+    pub relative: f64,
+}
+
+impl Tolerance {
+    /// A tolerance that is tight enough for most `f64` calculations while
+    /// still allowing for rounding differences.
+    pub const DEFAULT: Tolerance = Tolerance {
+        absolute: 1e-9,
+        relative: 1e-9,
+    };
+    /// A looser tolerance which is more appropriate for `f32` calculations.
+    pub const F32: Tolerance = Tolerance {
+        absolute: 1e-6,
+        relative: 1e-5,
+    };
+
+    /// Only allow values to differ by a fixed amount.
+    pub const fn absolute(absolute: f64) -> Self {
+        Tolerance {
+            absolute,
+            relative: 0.0,
+        }
+    }
+
+    /// Only allow values to differ by a fraction of their magnitude.
+    pub const fn relative(relative: f64) -> Self {
+        Tolerance {
+            absolute: 0.0,
+            relative,
+        }
+    }
+}
+
+impl Default for Tolerance {
+    fn default() -> Self { Tolerance::DEFAULT }
+}
+
+/// Check whether two numbers are within a [`Tolerance`] of each other.
+///
+/// # Examples
+///
+/// ```rust
+/// use hotg_rune_proc_blocks::testing::{is_close, Tolerance};
+///
+/// assert!(is_close(0.1 + 0.2, 0.3, Tolerance::DEFAULT));
+/// assert!(!is_close(1.0, 1.1, Tolerance::DEFAULT));
+/// assert!(is_close(100.0, 101.0, Tolerance::relative(0.01)));
+/// ```
+pub fn is_close(actual: f64, expected: f64, tolerance: Tolerance) -> bool {
+    if actual == expected {
+        // Handles infinities and saves us from doing any maths
+        return true;
+    }
+
+    if !actual.is_finite() || !expected.is_finite() {
+        // NaN is never close to anything, and an infinity is only close to
+        // itself (otherwise the relative tolerance would be infinite too)
+        return false;
+    }
+
+    let difference = (actual - expected).abs();
+    let magnitude = actual.abs().max(expected.abs());
+
+    difference <= tolerance.absolute.max(tolerance.relative * magnitude)
+}
+
+/// Assert that two numbers are within a [`Tolerance`] of each other.
+///
+/// # Panics
+///
+/// This will panic if the values aren't close.
+#[track_caller]
+pub fn assert_close<T>(actual: T, expected: T, tolerance: Tolerance)
+where
+    T: Into<f64> + Copy + Debug,
+{
+    assert!(
+        is_close(actual.into(), expected.into(), tolerance),
+        "{:?} is not close to {:?} (tolerance: {:?})",
+        actual,
+        expected,
+        tolerance,
+    );
+}
+
+/// Assert that two slices have the same length and each pair of elements is
+/// within a [`Tolerance`] of each other.
+///
+/// # Examples
+///
+/// ```rust
+/// use hotg_rune_proc_blocks::testing::{assert_slices_close, Tolerance};
+///
+/// let got = [0.1_f64 + 0.2, 1.0 / 3.0];
+///
+/// let expected = [0.3, 0.333333333333];
+///
+/// assert_slices_close(&got, &expected, Tolerance::absolute(1e-9));
+/// ```
+#[track_caller]
+pub fn assert_slices_close<T>(
+    actual: &[T],
+    expected: &[T],
+    tolerance: Tolerance,
+) where
+    T: Into<f64> + Copy + Debug,
+{
+    assert_eq!(
+        actual.len(),
+        expected.len(),
+        "Length mismatch: {:?} vs {:?}",
+        actual,
+        expected,
+    );
+
+    let mismatch = actual
+        .iter()
+        .zip(expected)
+        .position(|(&a, &e)| !is_close(a.into(), e.into(), tolerance));
+
+    if let Some(index) = mismatch {
+        panic!(
+            "Element {} differs: {:?} is not close to {:?} (tolerance: {:?})\n  actual: {:?}\nexpected: {:?}",
+            index, actual[index], expected[index], tolerance, actual, expected,
+        );
+    }
+}
+
+/// Assert that two tensors have the same shape and each pair of elements is
+/// within a [`Tolerance`] of each other.
+///
+/// # Examples
+///
+/// ```rust
+/// use hotg_rune_proc_blocks::{
+///     ndarray,
+///     testing::{assert_tensors_close, Tolerance},
+/// };
+///
+/// let got = ndarray::arr2(&[[1.0_f32, 2.0], [3.0, 4.000001]]);
+/// let expected = ndarray::arr2(&[[1.0_f32, 2.0], [3.0, 4.0]]);
+///
+/// assert_tensors_close(&got, &expected, Tolerance::F32);
+/// ```
+#[track_caller]
+pub fn assert_tensors_close<A, B, D>(
+    actual: &ArrayBase<A, D>,
+    expected: &ArrayBase<B, D>,
+    tolerance: Tolerance,
+) where
+    A: Data,
+    B: Data<Elem = A::Elem>,
+    A::Elem: Into<f64> + Copy + Debug,
+    D: Dimension,
+{
+    assert_eq!(
+        actual.shape(),
+        expected.shape(),
+        "Shape mismatch between {:?} and {:?}",
+        actual,
+        expected,
+    );
+
+    let mismatch = actual
+        .indexed_iter()
+        .zip(expected.iter())
+        .find(|((_, &a), &e)| !is_close(a.into(), e.into(), tolerance));
+
+    if let Some(((index, a), e)) = mismatch {
+        panic!(
+            "Element {:?} differs: {:?} is not close to {:?} (tolerance: {:?})\n  actual: {:?}\nexpected: {:?}",
+            index, a, e, tolerance, actual, expected,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nan_is_never_close() {
+        assert!(!is_close(f64::NAN, f64::NAN, Tolerance::absolute(1.0)));
+        assert!(!is_close(f64::NAN, 0.0, Tolerance::absolute(1.0)));
+    }
+
+    #[test]
+    fn infinities_are_close_to_themselves() {
+        assert!(is_close(f64::INFINITY, f64::INFINITY, Tolerance::DEFAULT));
+        assert!(!is_close(
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            Tolerance::DEFAULT
+        ));
+    }
+
+    #[test]
+    fn relative_tolerance_scales_with_magnitude() {
+        let tolerance = Tolerance::relative(1e-3);
+
+        assert!(is_close(1000.0, 1000.5, tolerance));
+        assert!(!is_close(1.0, 1.5, tolerance));
+    }
+
+    #[test]
+    #[should_panic(expected = "Element 1 differs")]
+    fn slices_that_differ_are_reported() {
+        assert_slices_close(&[1.0_f64, 2.0], &[1.0, 2.1], Tolerance::DEFAULT);
+    }
+
+    #[test]
+    #[should_panic(expected = "Shape mismatch")]
+    fn tensors_with_different_shapes_are_not_close() {
+        let a = ndarray::arr1(&[1.0_f64, 2.0]);
+        let b = ndarray::arr1(&[1.0_f64, 2.0, 3.0]);
+
+        assert_tensors_close(&a, &b, Tolerance::DEFAULT);
+    }
+}
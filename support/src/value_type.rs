@@ -19,6 +19,10 @@ unsafe impl ValueType for f32 {}
 unsafe impl ValueType for u64 {}
 unsafe impl ValueType for i64 {}
 unsafe impl ValueType for f64 {}
+#[cfg(feature = "half")]
+unsafe impl ValueType for half::f16 {}
+#[cfg(feature = "half")]
+unsafe impl ValueType for half::bf16 {}
 
 /// Extension traits for slices of [`ValueType`]s.
 pub trait SliceExt {
@@ -3,7 +3,7 @@ use smartcore::{
     linalg::naive::dense_matrix::*,
     svm::{
         svc::{SVCParameters, SVC},
-        Kernels,
+        Kernel, Kernels,
     },
 };
 use std::{convert::TryInto, fmt::Display, str::FromStr};
@@ -12,7 +12,7 @@ use crate::proc_block_v1::{
     BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
     InvalidInput, KernelError,
 };
-use hotg_rune_proc_blocks::{runtime_v1::*, BufferExt, SliceExt};
+use hotg_rune_proc_blocks::{prelude::*, runtime_v1::*, BufferExt, SliceExt};
 
 wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
 
@@ -55,19 +55,40 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         tol.set_default_value("0.001");
         metadata.add_argument(&tol);
 
-        // todo: how to add an array of string: [linear, rbf, polynomial,
-        // polynomial_with_degree, sigmoid, sigmoiod_with_gamma].
-        // Have to figure out how to how to change the parameter of polynomial,
-        // sigmoid, etc
+        let kernel = ArgumentMetadata::new("kernel");
+        kernel.set_description("The kernel function");
+        let hint =
+            runtime_v1::interpret_as_string_in_enum(KernelFunction::NAMES);
+        kernel.add_hint(&hint);
+        kernel.set_default_value("linear");
+        metadata.add_argument(&kernel);
+
+        let gamma = ArgumentMetadata::new("gamma");
+        gamma.set_description(
+            "Kernel coefficient for the rbf, polynomial, and sigmoid kernels. Defaults to 1 / number of features.",
+        );
+        let hint = runtime_v1::supported_argument_type(ArgumentType::Float);
+        gamma.add_hint(&hint);
+        metadata.add_argument(&gamma);
 
-        // let kernel = ArgumentMetadata::new("kernel");
-        // epochs.set_description(
-        //     "Tolerance for stopping criterion",
-        // );
-        // let hint = runtime_v1::supported_argument_type(ArgumentType::String);
-        // kernel.add_hint(&hint);
-        // kernel.set_default_value("linear");
-        // metadata.add_argument(&kernel);
+        let degree = ArgumentMetadata::new("degree");
+        degree.set_description("Degree of the polynomial kernel");
+        let hint = runtime_v1::supported_argument_type(ArgumentType::Float);
+        degree.add_hint(&hint);
+        degree.set_default_value("3.0");
+        metadata.add_argument(&degree);
+
+        let coef0 = ArgumentMetadata::new("coef0");
+        coef0.set_description(
+            "Independent term for the polynomial and sigmoid kernels",
+        );
+        let hint = runtime_v1::supported_argument_type(ArgumentType::Float);
+        coef0.add_hint(&hint);
+        coef0.set_default_value("0.0");
+        metadata.add_argument(&coef0);
+
+        let output_name = ArgumentMetadata::output_name("y_test");
+        metadata.add_argument(&output_name);
 
         let x_train = TensorMetadata::new("x_train");
         let supported_types = [ElementType::F64];
@@ -89,6 +110,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         metadata.add_input(&x_test);
 
         let y_test = TensorMetadata::new("y_test");
+        y_test.set_description("The predicted class for each row in `x_test`. This can be renamed with the `output_name` argument.");
         let supported_types = [ElementType::F64];
         let hint =
             supported_shapes(&supported_types, DimensionsParam::Fixed(&[0]));
@@ -101,6 +123,10 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let ctx = GraphContext::for_node(&node_id)
             .ok_or(GraphError::MissingContext)?;
 
+        // Make sure the kernel arguments are valid
+        let _ = KernelFunction::from_arguments(|n| ctx.get_argument(n))
+            .map_err(GraphError::InvalidArgument)?;
+
         ctx.add_input_tensor(
             "x_train",
             ElementType::F64,
@@ -119,8 +145,10 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             DimensionsParam::Fixed(&[0, 0]),
         );
 
+        let output_name = ctx.output_name::<GraphError>("y_test")?;
+
         ctx.add_output_tensor(
-            "y_test",
+            &output_name,
             ElementType::F64,
             DimensionsParam::Fixed(&[0]),
         );
@@ -141,8 +169,8 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let tol: f64 = get_args("tolerance", |n| ctx.get_argument(n))
             .map_err(KernelError::InvalidArgument)?;
 
-        // let _kernel: String  = get_args("kernel", |n| ctx.get_argument(n))
-        // .map_err(KernelError::InvalidArgument)?;
+        let kernel = KernelFunction::from_arguments(|n| ctx.get_argument(n))
+            .map_err(KernelError::InvalidArgument)?;
 
         let x_train = ctx.get_input_tensor("x_train").ok_or_else(|| {
             KernelError::InvalidInput(InvalidInput {
@@ -152,8 +180,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         })?;
         let _xtrain: ndarray::ArrayView2<f64> = x_train
             .buffer
-            .view(&x_train.dimensions)
-            .and_then(|t| t.into_dimensionality())
+            .try_view(&x_train.dimensions)
             .map_err(|e| {
                 KernelError::InvalidInput(InvalidInput {
                     name: "x_train".to_string(),
@@ -169,8 +196,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         })?;
         let _ytrain: ndarray::ArrayView1<f64> = y_train
             .buffer
-            .view(&y_train.dimensions)
-            .and_then(|t| t.into_dimensionality())
+            .try_view(&y_train.dimensions)
             .map_err(|e| {
                 KernelError::InvalidInput(InvalidInput {
                     name: "y_train".to_string(),
@@ -186,8 +212,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         })?;
         let _xtest: ndarray::ArrayView2<f64> = x_test
             .buffer
-            .view(&x_test.dimensions)
-            .and_then(|t| t.into_dimensionality())
+            .try_view(&x_test.dimensions)
             .map_err(|e| {
                 KernelError::InvalidInput(InvalidInput {
                     name: "x_test".to_string(),
@@ -205,20 +230,22 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         }
 
         let output = transform(
-            &x_train.buffer.elements(),
+            f64_elements("x_train", &x_train)?,
             &x_train.dimensions,
-            &y_train.buffer.elements(),
-            &x_test.buffer.elements(),
+            f64_elements("y_train", &y_train)?,
+            f64_elements("x_test", &x_test)?,
             &x_test.dimensions,
             c,
             epoch,
             tol,
+            kernel,
         )?;
 
         let y_test_dimension = [x_test.dimensions[0]];
+        let output_name = ctx.output_name::<KernelError>("y_test")?;
 
         ctx.set_output_tensor(
-            "y_test",
+            &output_name,
             TensorParam {
                 element_type: ElementType::F64,
                 dimensions: &y_test_dimension,
@@ -244,22 +271,60 @@ where
         .map_err(|e| InvalidArgument::invalid_value(name, e))
 }
 
-impl InvalidArgument {
-    fn not_found(name: impl Into<String>) -> Self {
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl Display) -> Self {
         InvalidArgument {
-            name: name.into(),
-            reason: BadArgumentReason::NotFound,
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
         }
     }
 
-    fn invalid_value(name: impl Into<String>, reason: impl Display) -> Self {
+    fn invalid_value(name: &str, error: impl Display) -> Self {
         InvalidArgument {
-            name: name.into(),
-            reason: BadArgumentReason::InvalidValue(reason.to_string()),
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
         }
     }
 }
 
+fn f64_elements<'a>(
+    name: &str,
+    tensor: &'a TensorResult,
+) -> Result<&'a [f64], KernelError> {
+    tensor
+        .buffer
+        .try_elements_with_shape(&tensor.dimensions)
+        .map_err(|e| {
+            KernelError::InvalidInput(InvalidInput {
+                name: name.to_string(),
+                reason: BadInputReason::Other(e.to_string()),
+            })
+        })
+}
+
 fn transform(
     x_train: &[f64],
     x_train_dim: &[u32],
@@ -269,12 +334,11 @@ fn transform(
     c: f64,
     epoch: u32,
     tol: f64,
+    kernel: KernelFunction,
 ) -> Result<Vec<f64>, KernelError> {
-    // todo: let user change the kernel. Right now setting it to 'linear'
     let svc_parameters = SVCParameters::default()
         .with_c(c)
         .with_epoch(epoch.try_into().unwrap())
-        .with_kernel(Kernels::linear())
         .with_tol(tol);
 
     let x_train = DenseMatrix::from_array(
@@ -283,20 +347,140 @@ fn transform(
         x_train,
     );
 
-    let model = SVC::fit(&x_train, &y_train.to_vec(), svc_parameters)
-        .map_err(|e| KernelError::Other(e.to_string()))?;
-
     let x_test = DenseMatrix::from_array(
         x_test_dim[0] as usize,
         x_test_dim[1] as usize,
         x_test,
     );
 
+    // Match scikit-learn's "auto" gamma when it isn't provided
+    let gamma = |gamma: Option<f64>| {
+        gamma.unwrap_or_else(|| 1.0 / x_train_dim[1] as f64)
+    };
+    let y_train = y_train.to_vec();
+
+    match kernel {
+        KernelFunction::Linear => fit_and_predict(
+            &x_train,
+            &y_train,
+            &x_test,
+            svc_parameters.with_kernel(Kernels::linear()),
+        ),
+        KernelFunction::Rbf { gamma: g } => fit_and_predict(
+            &x_train,
+            &y_train,
+            &x_test,
+            svc_parameters.with_kernel(Kernels::rbf(gamma(g))),
+        ),
+        KernelFunction::Polynomial {
+            degree,
+            gamma: g,
+            coef0,
+        } => fit_and_predict(
+            &x_train,
+            &y_train,
+            &x_test,
+            svc_parameters
+                .with_kernel(Kernels::polynomial(degree, gamma(g), coef0)),
+        ),
+        KernelFunction::Sigmoid { gamma: g, coef0 } => fit_and_predict(
+            &x_train,
+            &y_train,
+            &x_test,
+            svc_parameters.with_kernel(Kernels::sigmoid(gamma(g), coef0)),
+        ),
+    }
+}
+
+fn fit_and_predict<K: Kernel<f64, Vec<f64>>>(
+    x_train: &DenseMatrix<f64>,
+    y_train: &Vec<f64>,
+    x_test: &DenseMatrix<f64>,
+    parameters: SVCParameters<f64, DenseMatrix<f64>, K>,
+) -> Result<Vec<f64>, KernelError> {
+    let model = SVC::fit(x_train, y_train, parameters)
+        .map_err(|e| KernelError::Other(e.to_string()))?;
+
     model
-        .predict(&x_test)
+        .predict(x_test)
         .map_err(|e| KernelError::Other(e.to_string()))
 }
 
+/// The kernel function used by the SVM, as set by the `kernel`, `gamma`,
+/// `degree`, and `coef0` arguments.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum KernelFunction {
+    Linear,
+    Rbf {
+        gamma: Option<f64>,
+    },
+    Polynomial {
+        degree: f64,
+        gamma: Option<f64>,
+        coef0: f64,
+    },
+    Sigmoid {
+        gamma: Option<f64>,
+        coef0: f64,
+    },
+}
+
+impl KernelFunction {
+    const NAMES: &'static [&'static str] =
+        &["linear", "rbf", "polynomial", "sigmoid"];
+
+    fn from_arguments(
+        get_argument: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, InvalidArgument> {
+        let kernel = get_argument("kernel").unwrap_or_else(|| "linear".into());
+        let gamma: Option<f64> = optional_arg("gamma", &get_argument)?;
+        let degree = optional_arg("degree", &get_argument)?.unwrap_or(3.0);
+        let coef0 = optional_arg("coef0", &get_argument)?.unwrap_or(0.0);
+
+        if let Some(gamma) = gamma {
+            if !(gamma > 0.0 && gamma.is_finite()) {
+                return Err(InvalidArgument::invalid_value(
+                    "gamma",
+                    "Gamma must be a positive number",
+                ));
+            }
+        }
+
+        match kernel.as_str() {
+            "linear" => Ok(KernelFunction::Linear),
+            "rbf" => Ok(KernelFunction::Rbf { gamma }),
+            "polynomial" => Ok(KernelFunction::Polynomial {
+                degree,
+                gamma,
+                coef0,
+            }),
+            "sigmoid" => Ok(KernelFunction::Sigmoid { gamma, coef0 }),
+            other => Err(InvalidArgument::invalid_value(
+                "kernel",
+                format!(
+                    "Unknown kernel, \"{}\". Expected one of {:?}",
+                    other,
+                    KernelFunction::NAMES
+                ),
+            )),
+        }
+    }
+}
+
+fn optional_arg<T>(
+    name: &str,
+    get_argument: impl Fn(&str) -> Option<String>,
+) -> Result<Option<T>, InvalidArgument>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Display,
+{
+    match get_argument(name) {
+        Some(_) => get_args(name, get_argument).map(Some),
+        None => Ok(None),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -323,9 +507,62 @@ mod tests {
         let c: f64 = 200.0;
         let tol: f64 = 0.001;
 
-        let y_pred =
-            transform(&x_train, &dim, &y_train, &x_train, &dim, c, epoch, tol);
+        let y_pred = transform(
+            &x_train,
+            &dim,
+            &y_train,
+            &x_train,
+            &dim,
+            c,
+            epoch,
+            tol,
+            KernelFunction::Linear,
+        );
 
         assert_eq!(y_pred.unwrap(), y_train);
     }
+
+    #[test]
+    fn parse_the_kernel_arguments() {
+        let args = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                pairs
+                    .iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+
+        assert_eq!(
+            KernelFunction::from_arguments(args(&[])).unwrap(),
+            KernelFunction::Linear
+        );
+        assert_eq!(
+            KernelFunction::from_arguments(args(&[("kernel", "rbf")]))
+                .unwrap(),
+            KernelFunction::Rbf { gamma: None }
+        );
+        assert_eq!(
+            KernelFunction::from_arguments(args(&[
+                ("kernel", "polynomial"),
+                ("degree", "2"),
+                ("gamma", "0.5"),
+            ]))
+            .unwrap(),
+            KernelFunction::Polynomial {
+                degree: 2.0,
+                gamma: Some(0.5),
+                coef0: 0.0
+            }
+        );
+        assert!(
+            KernelFunction::from_arguments(args(&[("kernel", "cubic")]))
+                .is_err()
+        );
+        assert!(KernelFunction::from_arguments(args(&[
+            ("kernel", "sigmoid"),
+            ("gamma", "-1"),
+        ]))
+        .is_err());
+    }
 }
@@ -4,7 +4,7 @@ use smartcore::{
     linalg::naive::dense_matrix::*,
     svm::{
         svr::{SVRParameters, SVR},
-        Kernels,
+        Kernel, Kernels,
     },
 };
 
@@ -58,19 +58,37 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         tol.set_default_value("0.001");
         metadata.add_argument(&tol);
 
-        // todo: how to add an array of string: [linear, rbf, polynomial,
-        // polynomial_with_degree, sigmoid, sigmoiod_with_gamma].
-        // Have to figure out how to how to change the parameter of polynomial,
-        // sigmoid, etc
+        let kernel = ArgumentMetadata::new("kernel");
+        kernel.set_description("The kernel function");
+        let hint =
+            runtime_v1::interpret_as_string_in_enum(KernelFunction::NAMES);
+        kernel.add_hint(&hint);
+        kernel.set_default_value("linear");
+        metadata.add_argument(&kernel);
+
+        let gamma = ArgumentMetadata::new("gamma");
+        gamma.set_description(
+            "Kernel coefficient for the rbf, polynomial, and sigmoid kernels. Defaults to 1 / number of features.",
+        );
+        let hint = runtime_v1::supported_argument_type(ArgumentType::Float);
+        gamma.add_hint(&hint);
+        metadata.add_argument(&gamma);
 
-        // let kernel = ArgumentMetadata::new("kernel");
-        // epochs.set_description(
-        //     "Tolerance for stopping criterion",
-        // );
-        // let hint = runtime_v1::supported_argument_type(ArgumentType::String);
-        // kernel.add_hint(&hint);
-        // kernel.set_default_value("linear");
-        // metadata.add_argument(&kernel);
+        let degree = ArgumentMetadata::new("degree");
+        degree.set_description("Degree of the polynomial kernel");
+        let hint = runtime_v1::supported_argument_type(ArgumentType::Float);
+        degree.add_hint(&hint);
+        degree.set_default_value("3.0");
+        metadata.add_argument(&degree);
+
+        let coef0 = ArgumentMetadata::new("coef0");
+        coef0.set_description(
+            "Independent term for the polynomial and sigmoid kernels",
+        );
+        let hint = runtime_v1::supported_argument_type(ArgumentType::Float);
+        coef0.add_hint(&hint);
+        coef0.set_default_value("0.0");
+        metadata.add_argument(&coef0);
 
         let x_train = TensorMetadata::new("x_train");
         let supported_types = [ElementType::F64];
@@ -104,6 +122,10 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let ctx = GraphContext::for_node(&node_id)
             .ok_or(GraphError::MissingContext)?;
 
+        // Make sure the kernel arguments are valid
+        let _ = KernelFunction::from_arguments(|n| ctx.get_argument(n))
+            .map_err(GraphError::InvalidArgument)?;
+
         ctx.add_input_tensor(
             "x_train",
             ElementType::F64,
@@ -144,8 +166,8 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let tol: f64 = get_args("tolerance", |n| ctx.get_argument(n))
             .map_err(KernelError::InvalidArgument)?;
 
-        // let _kernel: String  = get_args("kernel", |n| ctx.get_argument(n))
-        // .map_err(KernelError::InvalidArgument)?;
+        let kernel = KernelFunction::from_arguments(|n| ctx.get_argument(n))
+            .map_err(KernelError::InvalidArgument)?;
 
         let x_train = ctx.get_input_tensor("x_train").ok_or_else(|| {
             KernelError::InvalidInput(InvalidInput {
@@ -178,14 +200,15 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         }
 
         let output = transform(
-            &x_train.buffer.elements(),
+            f64_elements("x_train", &x_train)?,
             &x_train.dimensions,
-            &y_train.buffer.elements(),
-            &x_test.buffer.elements(),
+            f64_elements("y_train", &y_train)?,
+            f64_elements("x_test", &x_test)?,
             &x_test.dimensions,
             c,
             eps,
             tol,
+            kernel,
         );
 
         let y_test_dimension = [x_test.dimensions[0]];
@@ -233,6 +256,21 @@ impl InvalidArgument {
     }
 }
 
+fn f64_elements<'a>(
+    name: &str,
+    tensor: &'a TensorResult,
+) -> Result<&'a [f64], KernelError> {
+    tensor
+        .buffer
+        .try_elements_with_shape(&tensor.dimensions)
+        .map_err(|e| {
+            KernelError::InvalidInput(InvalidInput {
+                name: name.to_string(),
+                reason: BadInputReason::Other(e.to_string()),
+            })
+        })
+}
+
 fn transform(
     x_train: &[f64],
     x_train_dim: &[u32],
@@ -242,12 +280,11 @@ fn transform(
     c: f64,
     eps: f64,
     tol: f64,
+    kernel: KernelFunction,
 ) -> Vec<f64> {
-    // todo: let user change the kernel. Right now setting it to 'linear'
-    let svc_parameters = SVRParameters::default()
+    let svr_parameters = SVRParameters::default()
         .with_c(c)
         .with_eps(eps.try_into().unwrap())
-        .with_kernel(Kernels::linear())
         .with_tol(tol);
 
     let x_train = DenseMatrix::from_array(
@@ -256,22 +293,141 @@ fn transform(
         x_train,
     );
 
-    let model = SVR::fit(&x_train, &y_train.to_vec(), svc_parameters).unwrap();
-
     let x_test = DenseMatrix::from_array(
         x_test_dim[0] as usize,
         x_test_dim[1] as usize,
         x_test,
     );
 
-    let y_hat = model.predict(&x_test).unwrap();
+    // Match scikit-learn's "auto" gamma when it isn't provided
+    let gamma = |gamma: Option<f64>| {
+        gamma.unwrap_or_else(|| 1.0 / x_train_dim[1] as f64)
+    };
+    let y_train = y_train.to_vec();
+
+    match kernel {
+        KernelFunction::Linear => fit_and_predict(
+            &x_train,
+            &y_train,
+            &x_test,
+            svr_parameters.with_kernel(Kernels::linear()),
+        ),
+        KernelFunction::Rbf { gamma: g } => fit_and_predict(
+            &x_train,
+            &y_train,
+            &x_test,
+            svr_parameters.with_kernel(Kernels::rbf(gamma(g))),
+        ),
+        KernelFunction::Polynomial {
+            degree,
+            gamma: g,
+            coef0,
+        } => fit_and_predict(
+            &x_train,
+            &y_train,
+            &x_test,
+            svr_parameters
+                .with_kernel(Kernels::polynomial(degree, gamma(g), coef0)),
+        ),
+        KernelFunction::Sigmoid { gamma: g, coef0 } => fit_and_predict(
+            &x_train,
+            &y_train,
+            &x_test,
+            svr_parameters.with_kernel(Kernels::sigmoid(gamma(g), coef0)),
+        ),
+    }
+}
+
+fn fit_and_predict<K: Kernel<f64, Vec<f64>>>(
+    x_train: &DenseMatrix<f64>,
+    y_train: &Vec<f64>,
+    x_test: &DenseMatrix<f64>,
+    parameters: SVRParameters<f64, DenseMatrix<f64>, K>,
+) -> Vec<f64> {
+    let model = SVR::fit(x_train, y_train, parameters).unwrap();
+
+    model.predict(x_test).unwrap()
+}
+
+/// The kernel function used by the SVM, as set by the `kernel`, `gamma`,
+/// `degree`, and `coef0` arguments.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum KernelFunction {
+    Linear,
+    Rbf {
+        gamma: Option<f64>,
+    },
+    Polynomial {
+        degree: f64,
+        gamma: Option<f64>,
+        coef0: f64,
+    },
+    Sigmoid {
+        gamma: Option<f64>,
+        coef0: f64,
+    },
+}
+
+impl KernelFunction {
+    const NAMES: &'static [&'static str] =
+        &["linear", "rbf", "polynomial", "sigmoid"];
+
+    fn from_arguments(
+        get_argument: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, InvalidArgument> {
+        let kernel = get_argument("kernel").unwrap_or_else(|| "linear".into());
+        let gamma: Option<f64> = optional_arg("gamma", &get_argument)?;
+        let degree = optional_arg("degree", &get_argument)?.unwrap_or(3.0);
+        let coef0 = optional_arg("coef0", &get_argument)?.unwrap_or(0.0);
+
+        if let Some(gamma) = gamma {
+            if !(gamma > 0.0 && gamma.is_finite()) {
+                return Err(InvalidArgument::invalid_value(
+                    "gamma",
+                    "Gamma must be a positive number",
+                ));
+            }
+        }
 
-    y_hat
+        match kernel.as_str() {
+            "linear" => Ok(KernelFunction::Linear),
+            "rbf" => Ok(KernelFunction::Rbf { gamma }),
+            "polynomial" => Ok(KernelFunction::Polynomial {
+                degree,
+                gamma,
+                coef0,
+            }),
+            "sigmoid" => Ok(KernelFunction::Sigmoid { gamma, coef0 }),
+            other => Err(InvalidArgument::invalid_value(
+                "kernel",
+                format!(
+                    "Unknown kernel, \"{}\". Expected one of {:?}",
+                    other,
+                    KernelFunction::NAMES
+                ),
+            )),
+        }
+    }
+}
+
+fn optional_arg<T>(
+    name: &str,
+    get_argument: impl Fn(&str) -> Option<String>,
+) -> Result<Option<T>, InvalidArgument>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Display,
+{
+    match get_argument(name) {
+        Some(_) => get_args(name, get_argument).map(Some),
+        None => Ok(None),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use hotg_rune_proc_blocks::testing::{assert_slices_close, Tolerance};
 
     #[test]
     fn check_model() {
@@ -298,11 +454,17 @@ mod tests {
         let dim: Vec<u32> = vec![16, 6];
 
         let y_pred = transform(
-            &x_train, &dim, &y_train, &x_train, &dim, 10.0, 2.0, 0.001,
+            &x_train,
+            &dim,
+            &y_train,
+            &x_train,
+            &dim,
+            10.0,
+            2.0,
+            0.001,
+            KernelFunction::Linear,
         );
 
-        println!("{:?}", y_pred);
-
         let should_be = vec![
             85.00037818041841,
             86.75542812311954,
@@ -321,6 +483,50 @@ mod tests {
             115.24619508029843,
             117.6680182728901,
         ];
-        assert_eq!(y_pred, should_be);
+        assert_slices_close(&y_pred, &should_be, Tolerance::relative(1e-6));
+    }
+
+    #[test]
+    fn parse_the_kernel_arguments() {
+        let args = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                pairs
+                    .iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+
+        assert_eq!(
+            KernelFunction::from_arguments(args(&[])).unwrap(),
+            KernelFunction::Linear
+        );
+        assert_eq!(
+            KernelFunction::from_arguments(args(&[("kernel", "rbf")]))
+                .unwrap(),
+            KernelFunction::Rbf { gamma: None }
+        );
+        assert_eq!(
+            KernelFunction::from_arguments(args(&[
+                ("kernel", "polynomial"),
+                ("degree", "2"),
+                ("gamma", "0.5"),
+            ]))
+            .unwrap(),
+            KernelFunction::Polynomial {
+                degree: 2.0,
+                gamma: Some(0.5),
+                coef0: 0.0
+            }
+        );
+        assert!(
+            KernelFunction::from_arguments(args(&[("kernel", "cubic")]))
+                .is_err()
+        );
+        assert!(KernelFunction::from_arguments(args(&[
+            ("kernel", "sigmoid"),
+            ("gamma", "-1"),
+        ]))
+        .is_err());
     }
 }
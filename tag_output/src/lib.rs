@@ -0,0 +1,234 @@
+//! Attach a topic to a tensor so the host knows where to forward it.
+//!
+//! Hosts which forward a pipeline's outputs to MQTT, HTTP, etc. would
+//! otherwise need to hard-code which output goes where for every pipeline.
+//! Instead, the tensor is passed through unchanged and a `topic` string
+//! tensor is emitted alongside it.
+//!
+//! Other proc-blocks can follow the same convention by registering the
+//! `topic` argument with `ArgumentMetadata::topic()`.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    common::topic, prelude::*, runtime_v1::*, StringBuilder,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata = Metadata::new("Tag Output", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("routing");
+        metadata.add_tag("mqtt");
+
+        let element_type = ArgumentMetadata::element_type();
+        metadata.add_argument(&element_type);
+
+        let topic = ArgumentMetadata::topic();
+        metadata.add_argument(&topic);
+
+        let input = TensorMetadata::new("input");
+        input.set_description("The tensor to tag.");
+        metadata.add_input(&input);
+
+        let output = TensorMetadata::new("output");
+        output.set_description("The input tensor, unchanged.");
+        metadata.add_output(&output);
+
+        let topic = TensorMetadata::new("topic");
+        topic.set_description("The topic this tensor should be sent to.");
+        let hint = supported_shapes(
+            &[ElementType::Utf8],
+            DimensionsParam::Fixed(&[1]),
+        );
+        topic.add_hint(&hint);
+        metadata.add_output(&topic);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        let Settings { element_type, .. } =
+            Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor("input", element_type, DimensionsParam::Dynamic);
+        ctx.add_output_tensor(
+            "output",
+            element_type,
+            DimensionsParam::Dynamic,
+        );
+        ctx.add_output_tensor(
+            "topic",
+            ElementType::Utf8,
+            DimensionsParam::Fixed(&[1]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let Settings {
+            element_type: expected_type,
+            topic,
+        } = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("input").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "input".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != expected_type {
+            return Err(KernelError::InvalidInput(InvalidInput {
+                name: "input".to_string(),
+                reason: BadInputReason::InvalidValue(format!(
+                    "Expected a {} tensor, found {}",
+                    expected_type, element_type
+                )),
+            }));
+        }
+
+        ctx.set_output_tensor(
+            "output",
+            TensorParam {
+                element_type,
+                dimensions: &dimensions,
+                buffer: &buffer,
+            },
+        );
+
+        let mut builder = StringBuilder::new();
+        builder.push(&topic);
+        ctx.set_output_tensor(
+            "topic",
+            TensorParam {
+                element_type: ElementType::Utf8,
+                dimensions: &[1],
+                buffer: &builder.finish(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Settings {
+    element_type: ElementType,
+    topic: String,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let element_type = ctx.parse_argument_with_default::<_, E>(
+            ElementType::NAME,
+            ElementType::F32,
+        )?;
+        // The topic is optional for most proc-blocks, but it's the whole
+        // point of this one.
+        let topic = ctx.topic::<E>()?.ok_or_else(|| {
+            E::invalid_argument(InvalidArgument::not_found(topic::NAME))
+        })?;
+
+        Ok(Settings {
+            element_type,
+            topic,
+        })
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::common::topic::InvalidTopic;
+
+    #[test]
+    fn valid_topics() {
+        let topics = ["sensors/kitchen/temperature", "/", "a", "devices/42"];
+
+        for t in topics {
+            assert_eq!(topic::validate(t), Ok(()), "{}", t);
+        }
+    }
+
+    #[test]
+    fn invalid_topics() {
+        let inputs = [
+            ("", InvalidTopic::Empty),
+            ("sensors/+/temperature", InvalidTopic::Wildcard('+')),
+            ("sensors/#", InvalidTopic::Wildcard('#')),
+            ("sensors\0", InvalidTopic::NullCharacter),
+        ];
+
+        for (t, should_be) in inputs {
+            assert_eq!(topic::validate(t), Err(should_be), "{:?}", t);
+        }
+
+        let long = "a".repeat(topic::MAX_LENGTH + 1);
+        assert_eq!(
+            topic::validate(&long),
+            Err(InvalidTopic::TooLong(long.len()))
+        );
+    }
+}
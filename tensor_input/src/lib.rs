@@ -20,7 +20,6 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let element_type = ArgumentMetadata::element_type();
         metadata.add_argument(&element_type);
 
-        let output = TensorMetadata::new("output");
         let hint = supported_shapes(
             &[
                 ElementType::U8,
@@ -37,6 +36,12 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             ],
             DimensionsParam::Fixed(&[0]),
         );
+
+        let input = TensorMetadata::new("input");
+        input.add_hint(&hint);
+        metadata.add_input(&input);
+
+        let output = TensorMetadata::new("output");
         output.add_hint(&hint);
         metadata.add_output(&output);
 
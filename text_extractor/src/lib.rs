@@ -1,6 +1,6 @@
 use crate::proc_block_v1::*;
 use hotg_rune_proc_blocks::{
-    ndarray, runtime_v1::*, string_tensor_from_ndarray, BufferExt,
+    ndarray, runtime_v1::*, string_tensor_from_ndarray, BufferExt, ValueType,
 };
 
 wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
@@ -152,9 +152,9 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
                 },
             };
         let output = transform((
-            text.buffer.elements(),
-            start_logits.buffer.elements(),
-            end_logits.buffer.elements(),
+            elements(&text, "text")?,
+            elements(&start_logits, "start_logits")?,
+            elements(&end_logits, "end_logits")?,
         ));
 
         ctx.set_output_tensor(
@@ -170,10 +170,25 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
     }
 }
 
+fn elements<'a, T: ValueType>(
+    tensor: &'a TensorResult,
+    name: &str,
+) -> Result<&'a [T], KernelError> {
+    tensor
+        .buffer
+        .try_elements_with_shape(&tensor.dimensions)
+        .map_err(|e| {
+            KernelError::InvalidInput(InvalidInput {
+                name: name.to_string(),
+                reason: BadInputReason::InvalidValue(e.to_string()),
+            })
+        })
+}
+
 fn transform<'a>(inputs: (&[u8], &[u32], &[u32])) -> Vec<String> {
     let (text, start_logits, end_logits) = inputs;
 
-    let underlying_bytes: &[u8] = text.elements();
+    let underlying_bytes: &[u8] = text;
     let input_text = core::str::from_utf8(underlying_bytes)
         .expect("Input tensor should be valid UTF8");
 
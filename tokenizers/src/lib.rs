@@ -1,12 +1,29 @@
-use crate::proc_block_v1::{
-    BadInputReason, GraphError, InvalidInput, KernelError,
-};
+//! Tokenize a question and a paragraph using the BERT tokenizer, or a single
+//! piece of text with [`transform_single()`].
+//!
+//! The encoding is truncated or padded to `max_sequence_length` tokens
+//! (384 by default, which is what most SQuAD-style BERT models expect).
+
+use std::str::FromStr;
+
 use hotg_rune_proc_blocks::{
+    prelude::*,
     runtime_v1::{
-        register_node, supported_shapes, DimensionsParam, ElementType,
-        GraphContext, KernelContext, Metadata, TensorMetadata, TensorParam,
+        interpret_as_string_in_enum, register_node, supported_argument_type,
+        supported_shapes, ArgumentMetadata, ArgumentType, DimensionsParam,
+        ElementType, GraphContext, KernelContext, Metadata, TensorMetadata,
+        TensorParam, TensorResult,
     },
-    BufferExt, SliceExt,
+    BufferExt, Dimension, SliceExt,
+};
+use tokenizers_core::{
+    transform_single, transform_with_max_length, Tokenizers,
+    DEFAULT_MAX_SEQUENCE_LENGTH, MIN_SEQUENCE_LENGTH,
+};
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
 };
 
 wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
@@ -17,7 +34,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
     fn register_metadata() {
         let metadata = Metadata::new("Tokenizers", env!("CARGO_PKG_VERSION"));
         metadata.set_description(
-            "Tokenize a question and a paragraph using the Bert tokenizer.",
+            "Tokenize a question and a paragraph, or a single piece of text, using the Bert tokenizer.",
         );
         metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
         metadata.set_homepage(env!("CARGO_PKG_HOMEPAGE"));
@@ -25,23 +42,51 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         metadata.add_tag("bert");
         metadata.add_tag("tokenization");
 
+        let max_sequence_length = ArgumentMetadata::new(MAX_SEQUENCE_LENGTH);
+        max_sequence_length.set_description(
+            "The number of tokens to truncate or pad the encoding to.",
+        );
+        max_sequence_length
+            .set_default_value(&DEFAULT_MAX_SEQUENCE_LENGTH.to_string());
+        let hint = supported_argument_type(ArgumentType::UnsignedInteger);
+        max_sequence_length.add_hint(&hint);
+        metadata.add_argument(&max_sequence_length);
+
+        let mode = ArgumentMetadata::new(MODE);
+        mode.set_description(
+            "Whether to encode a question and paragraph pair (e.g. for question answering) or a single piece of text (e.g. for sentiment analysis).",
+        );
+        mode.set_default_value(Mode::Pair.name());
+        let hint = interpret_as_string_in_enum(Mode::NAMES);
+        mode.add_hint(&hint);
+        metadata.add_argument(&mode);
+
         let question = TensorMetadata::new("question");
+        question.set_description("The question, when mode is \"pair\".");
         let hint =
             supported_shapes(&[ElementType::U8], DimensionsParam::Fixed(&[0]));
         question.add_hint(&hint);
         metadata.add_input(&question);
 
         let paragraph = TensorMetadata::new("paragraph");
+        paragraph.set_description("The paragraph, when mode is \"pair\".");
         let hint =
             supported_shapes(&[ElementType::U8], DimensionsParam::Fixed(&[0]));
         paragraph.add_hint(&hint);
         metadata.add_input(&paragraph);
 
+        let text = TensorMetadata::new("text");
+        text.set_description("The text to encode, when mode is \"single\".");
+        let hint =
+            supported_shapes(&[ElementType::U8], DimensionsParam::Fixed(&[0]));
+        text.add_hint(&hint);
+        metadata.add_input(&text);
+
         let token_ids = TensorMetadata::new("token_ids");
         token_ids.set_description("The IDs for each token in the input.");
         let hint = supported_shapes(
             &[ElementType::I32],
-            DimensionsParam::Fixed(&[1, 384]),
+            DimensionsParam::Fixed(&[1, 0]),
         );
         token_ids.add_hint(&hint);
         metadata.add_output(&token_ids);
@@ -50,7 +95,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         token_mask.set_description("A set of masks indicating whether an input token is inside a segment or not.");
         let hint = supported_shapes(
             &[ElementType::I32],
-            DimensionsParam::Fixed(&[1, 384]),
+            DimensionsParam::Fixed(&[1, 0]),
         );
         token_mask.add_hint(&hint);
         metadata.add_output(&token_mask);
@@ -59,7 +104,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         segment_ids.set_description("The ID of the segment each token is in.");
         let hint = supported_shapes(
             &[ElementType::I32],
-            DimensionsParam::Fixed(&[1, 384]),
+            DimensionsParam::Fixed(&[1, 0]),
         );
         segment_ids.add_hint(&hint);
         metadata.add_output(&segment_ids);
@@ -80,40 +125,49 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let ctx = GraphContext::for_node(&node_id)
             .ok_or(GraphError::MissingContext)?;
 
-        ctx.add_input_tensor(
-            "question",
-            ElementType::U8,
-            DimensionsParam::Fixed(&[0]),
-        );
-
-        ctx.add_input_tensor(
-            "paragraph",
-            ElementType::U8,
-            DimensionsParam::Fixed(&[0]),
-        );
+        let Settings { mode, .. } =
+            Settings::from_context::<_, GraphError>(&ctx)?;
+        let dimensions = ctx.resolve_dimensions::<GraphError>(&[
+            Dimension::Fixed(1),
+            Dimension::FromArgumentOr(
+                MAX_SEQUENCE_LENGTH,
+                DEFAULT_MAX_SEQUENCE_LENGTH as u32,
+            ),
+        ])?;
+
+        for name in mode.inputs() {
+            ctx.add_input_tensor(
+                name,
+                ElementType::U8,
+                DimensionsParam::Fixed(&[0]),
+            );
+        }
 
         ctx.add_output_tensor(
             "token_ids",
             ElementType::I32,
-            DimensionsParam::Fixed(&[1, 384]),
+            DimensionsParam::Fixed(&dimensions),
         );
         ctx.add_output_tensor(
             "token_mask",
             ElementType::I32,
-            DimensionsParam::Fixed(&[1, 384]),
+            DimensionsParam::Fixed(&dimensions),
         );
         ctx.add_output_tensor(
             "segment_ids",
             ElementType::I32,
-            DimensionsParam::Fixed(&[1, 384]),
+            DimensionsParam::Fixed(&dimensions),
         );
 
         ctx.add_output_tensor(
             "encoded_text",
             ElementType::U8,
-            DimensionsParam::Fixed(&[1, 384]),
+            DimensionsParam::Fixed(&[1, 0]),
         );
 
+        // Parse the vocabulary now instead of during the first inference
+        Tokenizers::shared();
+
         Ok(())
     }
 
@@ -121,63 +175,27 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let ctx = KernelContext::for_node(&node_id)
             .ok_or(KernelError::MissingContext)?;
 
-        let question = ctx.get_input_tensor("question").ok_or_else(|| {
-            KernelError::InvalidInput(InvalidInput {
-                name: "question".to_string(),
-                reason: BadInputReason::NotFound,
-            })
-        })?;
-
-        let paragraph = ctx.get_input_tensor("paragraph").ok_or_else(|| {
-            KernelError::InvalidInput(InvalidInput {
-                name: "paragraph".to_string(),
-                reason: BadInputReason::NotFound,
-            })
-        })?;
-
-        match question.element_type {
-            ElementType::U8 => {
-                question.buffer.view::<u8>(&question.dimensions).map_err(
-                    |e| {
-                        KernelError::InvalidInput(InvalidInput {
-                            name: "question".to_string(),
-                            reason: BadInputReason::InvalidValue(e.to_string()),
-                        })
-                    },
-                )?;
-            },
-            other => {
-                return Err(KernelError::Other(format!(
-                "The Tokenizer proc-block doesn't support {:?} element type",
-                other,
-                )))
-            },
-        };
-
-        match paragraph.element_type {
-            ElementType::U8 => {
-                paragraph.buffer.view::<u8>(&paragraph.dimensions).map_err(
-                    |e| {
-                        KernelError::InvalidInput(InvalidInput {
-                            name: "paragraph".to_string(),
-                            reason: BadInputReason::InvalidValue(e.to_string()),
-                        })
-                    },
-                )?;
+        let Settings {
+            max_sequence_length,
+            mode,
+        } = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let output = match mode {
+            Mode::Pair => {
+                let question = text_input(&ctx, "question")?;
+                let paragraph = text_input(&ctx, "paragraph")?;
+                transform_with_max_length(
+                    (&question.buffer, &paragraph.buffer),
+                    max_sequence_length,
+                )
             },
-            other => {
-                return Err(KernelError::Other(format!(
-                "The Tokenizer proc-block doesn't support {:?} element type",
-                other,
-                )))
+            Mode::Single => {
+                let text = text_input(&ctx, "text")?;
+                transform_single(&text.buffer, max_sequence_length)
+                    .map_err(|e| invalid_input("text", e))?
             },
         };
 
-        let output = transform((
-            question.buffer.elements(),
-            paragraph.buffer.elements(),
-        ));
-
         ctx.set_output_tensor(
             "token_ids",
             TensorParam {
@@ -215,384 +233,159 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
     }
 }
 
-#[macro_use]
-extern crate alloc;
+fn text_input(
+    ctx: &KernelContext,
+    name: &str,
+) -> Result<TensorResult, KernelError> {
+    let tensor = ctx.get_input_tensor(name).ok_or_else(|| {
+        KernelError::InvalidInput(InvalidInput {
+            name: name.to_string(),
+            reason: BadInputReason::NotFound,
+        })
+    })?;
+
+    match tensor.element_type {
+        ElementType::U8 => {
+            tensor
+                .buffer
+                .view::<u8>(&tensor.dimensions)
+                .map_err(|e| invalid_input(name, e))?;
+        },
+        other => {
+            return Err(KernelError::Other(format!(
+                "The Tokenizer proc-block doesn't support {:?} element type",
+                other,
+            )))
+        },
+    };
 
-pub mod tokenizer;
-pub mod vocab;
+    Ok(tensor)
+}
 
-use crate::{
-    tokenizer::{
-        base_tokenizer::{
-            Mask, Offset, OffsetSize, Token, TokenRef, TokenizedInput,
-        },
-        BertTokenizer, Tokenizer, TruncationStrategy,
-    },
-    vocab::{BertVocab, Vocab},
-};
-use alloc::{
-    string::{String, ToString},
-    vec::Vec,
-};
-use core::str::FromStr;
+fn invalid_input(name: &str, reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: name.to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+const MAX_SEQUENCE_LENGTH: &str = "max_sequence_length";
+const MODE: &str = "mode";
 
-pub struct Tokenizers {
-    bert_tokenizer: BertTokenizer,
-    bert_vocab: BertVocab,
+/// What the tokenizer is encoding.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Mode {
+    /// A question and paragraph, as used for question answering.
+    Pair,
+    /// A single piece of text, as used for text classification.
+    Single,
 }
 
-impl Default for Tokenizers {
-    fn default() -> Tokenizers {
-        let vocabulary_text = include_str!("bert-base-uncased-vocab.txt");
+impl Mode {
+    const NAMES: &'static [&'static str] = &["pair", "single"];
 
-        let vocab = BertVocab::from_str(vocabulary_text).unwrap();
-        let vocab_copy = vocab.clone();
-        let bert_tokenizer =
-            BertTokenizer::from_existing_vocab(vocab, true, true);
+    fn name(self) -> &'static str {
+        match self {
+            Mode::Pair => "pair",
+            Mode::Single => "single",
+        }
+    }
 
-        Tokenizers {
-            bert_tokenizer,
-            bert_vocab: vocab_copy,
+    fn inputs(self) -> &'static [&'static str] {
+        match self {
+            Mode::Pair => &["question", "paragraph"],
+            Mode::Single => &["text"],
         }
     }
 }
 
-fn transform(s: (&[u8], &[u8])) -> (Vec<i32>, Vec<i32>, Vec<i32>, Vec<u8>) {
-    let (s1, s2) = s;
-    let underlying_bytes_1: &[u8] = s1.elements();
-    let input_text_1: &str = core::str::from_utf8(underlying_bytes_1)
-        .expect("Input tensor should be valid UTF8");
-    let input_text_1 = input_text_1.trim_end_matches('\0');
-    assert!(!input_text_1.is_empty(), "Sentence 1 is empty");
-    let underlying_bytes_2: &[u8] = s2.elements();
-    let input_text_2: &str = core::str::from_utf8(underlying_bytes_2)
-        .expect("Input tensor should be valid UTF8");
-    let input_text_2 = input_text_2.trim_end_matches('\0');
-    assert!(!input_text_2.is_empty(), "Sentence 2 is empty");
-
-    let tok: Tokenizers = Default::default();
-
-    let TokenizedInput {
-        mut token_ids,
-        special_tokens_mask: _,
-        mut segment_ids,
-        ..
-    } = tok.bert_tokenizer.encode(
-        input_text_1,
-        Some(input_text_2),
-        384,
-        &TruncationStrategy::LongestFirst,
-        0,
-    );
-
-    let mut mask_ids: Vec<i32> = vec![1; token_ids.len()];
-    token_ids.resize(384, 0);
-    mask_ids.resize(384, 0);
-    segment_ids.resize(384, 0);
-
-    let input_ids: Vec<i32> =
-        token_ids.iter().map(|&x| x as i32).collect::<Vec<i32>>();
-
-    let seg_ids: Vec<i32> =
-        segment_ids.iter().map(|&x| x as i32).collect::<Vec<i32>>();
-
-    let mut words = String::new();
-    let tok_ids = &token_ids[0 as usize..];
-
-    for id in tok_ids {
-        let s = tok.bert_vocab.id_to_token(*id);
-
-        words.push_str(&s);
-        words.push_str("\n");
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pair" => Ok(Mode::Pair),
+            "single" => Ok(Mode::Single),
+            other => Err(format!(
+                "Expected one of {:?}, found \"{}\"",
+                Mode::NAMES,
+                other
+            )),
+        }
     }
-    words = words.to_string();
-    let words: Vec<u8> = words.as_bytes().to_vec();
+}
 
-    (input_ids, mask_ids, seg_ids, words)
+#[derive(Debug, Clone, PartialEq)]
+struct Settings {
+    max_sequence_length: usize,
+    mode: Mode,
 }
 
-#[cfg(test)]
-
-mod tests {
-    use super::*;
-    #[test]
-    fn test_input_ids() {
-        let word1: Vec<u8> = "What is Google?".as_bytes().to_vec();
-
-        let word2: Vec<u8> =
-            "Google LLC is an American multinational technology company."
-                .as_bytes()
-                .to_vec();
-        let (input_ids, _mask_ids, _segment_ids, _word_bytes) =
-            transform((&word1, &word2));
-
-        let input_ids_should_be = vec![
-            101, 2054, 2003, 8224, 1029, 102, 8224, 11775, 2003, 2019, 2137,
-            20584, 2974, 2194, 1012, 102, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0,
-        ];
-
-        assert_eq!(input_ids, input_ids_should_be);
-    }
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let max_sequence_length = ctx.parse_argument_with_default::<_, E>(
+            MAX_SEQUENCE_LENGTH,
+            DEFAULT_MAX_SEQUENCE_LENGTH,
+        )?;
+
+        if max_sequence_length < MIN_SEQUENCE_LENGTH {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                MAX_SEQUENCE_LENGTH,
+                format!(
+                    "There must be room for at least {} tokens",
+                    MIN_SEQUENCE_LENGTH
+                ),
+            )));
+        }
 
-    #[test]
-    fn test_mask_ids() {
-        let word1: Vec<u8> = "What is Google?".as_bytes().to_vec();
-
-        let word2: Vec<u8> =
-            "Google LLC is an American multinational technology company."
-                .as_bytes()
-                .to_vec();
-        let (_input_ids, mask_ids, _segment_ids, _word_bytes) =
-            transform((&word1, &word2));
-
-        let mask_ids_should_be = vec![
-            1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        ];
-
-        assert_eq!(mask_ids, mask_ids_should_be);
-    }
+        let mode = ctx.parse_argument_with_default::<_, E>(MODE, Mode::Pair)?;
 
-    #[test]
-    fn test_segment_ids() {
-        let word1: Vec<u8> = "What is Google?".as_bytes().to_vec();
-
-        let word2: Vec<u8> =
-            "Google LLC is an American multinational technology company."
-                .as_bytes()
-                .to_vec();
-
-        let (_input_ids, _mask_ids, segment_ids, _word_bytes) =
-            transform((&word1, &word2));
-
-        let segment_ids_should_be = vec![
-            0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        ];
-
-        assert_eq!(segment_ids, segment_ids_should_be);
+        Ok(Settings {
+            max_sequence_length,
+            mode,
+        })
     }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
 
-    #[test]
-    fn test_word_bytes() {
-        let word1: Vec<u8> = "What is Google?".as_bytes().to_vec();
-
-        let word2: Vec<u8> =
-            "Google LLC is an American multinational technology company."
-                .as_bytes()
-                .to_vec();
-        let (_input_ids, _mask_ids, _segment_ids, word_bytes) =
-            transform((&word1, &word2));
-
-        let word_bytes_should_be = vec![
-            91, 67, 76, 83, 93, 10, 119, 104, 97, 116, 10, 105, 115, 10, 103,
-            111, 111, 103, 108, 101, 10, 63, 10, 91, 83, 69, 80, 93, 10, 103,
-            111, 111, 103, 108, 101, 10, 108, 108, 99, 10, 105, 115, 10, 97,
-            110, 10, 97, 109, 101, 114, 105, 99, 97, 110, 10, 109, 117, 108,
-            116, 105, 110, 97, 116, 105, 111, 110, 97, 108, 10, 116, 101, 99,
-            104, 110, 111, 108, 111, 103, 121, 10, 99, 111, 109, 112, 97, 110,
-            121, 10, 46, 10, 91, 83, 69, 80, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
-            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
-            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
-            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
-            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
-            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
-            68, 93, 10, 91, 80, 65, 68, 93, 10,
-        ];
-
-        assert_eq!(word_bytes, word_bytes_should_be);
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
     }
+}
 
-    #[test]
-    #[should_panic(expected = "Sentence 1 is empty")]
-    fn empty_sentence_1() {
-        let word1: Vec<u8> = "".as_bytes().to_vec();
-        let word2: Vec<u8> = "Hi".as_bytes().to_vec();
-        let (_input_ids, _mask_ids, _segment_ids, _word_bytes) =
-            transform((&word1, &word2));
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
     }
+}
 
-    #[test]
-    #[should_panic(expected = "Sentence 2 is empty")]
-    fn empty_sentence_2() {
-        let word1: Vec<u8> = "Hi".as_bytes().to_vec();
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
 
-        let word2: Vec<u8> = "".as_bytes().to_vec();
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
 
-        let (_input_ids, _mask_ids, _segment_ids, _word_bytes) =
-            transform((&word1, &word2));
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
     }
 }
@@ -0,0 +1,586 @@
+//! Tokenize a question and a paragraph using the BERT tokenizer, or a single
+//! piece of text with [`transform_single()`].
+//!
+//! The encoding is truncated or padded to `max_sequence_length` tokens
+//! (384 by default, which is what most SQuAD-style BERT models expect).
+//!
+//! This is the algorithm behind the `tokenizers` proc-block, without any of the
+//! WebAssembly bindings, so it can be used as a normal Rust library.
+
+#[macro_use]
+extern crate alloc;
+
+pub mod tokenizer;
+pub mod vocab;
+
+use crate::{
+    tokenizer::{
+        base_tokenizer::{
+            Mask, Offset, OffsetSize, Token, TokenRef, TokenizedInput,
+        },
+        BertTokenizer, Tokenizer, TruncationStrategy,
+    },
+    vocab::{BertVocab, Vocab},
+};
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::str::FromStr;
+use hotg_rune_proc_blocks::BufferExt;
+use lazy_static::lazy_static;
+use pad_sequence_core::{pad_sequence, Side};
+
+/// The number of tokens BERT question-answering models normally expect.
+pub const DEFAULT_MAX_SEQUENCE_LENGTH: usize = 384;
+
+/// The shortest sequence we can encode a question and paragraph into, which
+/// happens to be enough room for just the `[CLS]` and two `[SEP]` tokens.
+pub const MIN_SEQUENCE_LENGTH: usize = 3;
+
+/// The token IDs, token mask, segment IDs, and encoded text for a piece of
+/// tokenized text.
+pub type Encoding = (Vec<i32>, Vec<i32>, Vec<i32>, Vec<u8>);
+
+pub struct Tokenizers {
+    bert_tokenizer: BertTokenizer,
+    bert_vocab: BertVocab,
+}
+
+impl Default for Tokenizers {
+    fn default() -> Tokenizers {
+        let vocabulary_text = include_str!("bert-base-uncased-vocab.txt");
+
+        let vocab = BertVocab::from_str(vocabulary_text).unwrap();
+        let vocab_copy = vocab.clone();
+        let bert_tokenizer =
+            BertTokenizer::from_existing_vocab(vocab, true, true);
+
+        Tokenizers {
+            bert_tokenizer,
+            bert_vocab: vocab_copy,
+        }
+    }
+}
+
+lazy_static! {
+    /// Parsing the vocabulary takes far longer than tokenizing, so it is only
+    /// done the first time a tokenizer is needed.
+    static ref TOKENIZERS: Tokenizers = Tokenizers::default();
+}
+
+impl Tokenizers {
+    /// Get a tokenizer which is shared by every caller, loading the
+    /// vocabulary on first use.
+    pub fn shared() -> &'static Tokenizers { &TOKENIZERS }
+}
+
+/// Tokenize a question and paragraph, returning the token IDs, token mask,
+/// segment IDs, and the encoded text.
+pub fn transform(
+    s: (&[u8], &[u8]),
+) -> Encoding {
+    transform_with_max_length(s, DEFAULT_MAX_SEQUENCE_LENGTH)
+}
+
+/// The same as [`transform()`], except the outputs are truncated or padded to
+/// `max_sequence_length` tokens.
+///
+/// # Panics
+///
+/// This will panic if `max_sequence_length` is less than
+/// [`MIN_SEQUENCE_LENGTH`].
+pub fn transform_with_max_length(
+    s: (&[u8], &[u8]),
+    max_sequence_length: usize,
+) -> Encoding {
+    assert!(
+        max_sequence_length >= MIN_SEQUENCE_LENGTH,
+        "The sequence length must be at least {}",
+        MIN_SEQUENCE_LENGTH
+    );
+
+    let (s1, s2) = s;
+    let underlying_bytes_1: &[u8] = s1.elements();
+    let input_text_1: &str = core::str::from_utf8(underlying_bytes_1)
+        .expect("Input tensor should be valid UTF8");
+    let input_text_1 = input_text_1.trim_end_matches('\0');
+    assert!(!input_text_1.is_empty(), "Sentence 1 is empty");
+    let underlying_bytes_2: &[u8] = s2.elements();
+    let input_text_2: &str = core::str::from_utf8(underlying_bytes_2)
+        .expect("Input tensor should be valid UTF8");
+    let input_text_2 = input_text_2.trim_end_matches('\0');
+    assert!(!input_text_2.is_empty(), "Sentence 2 is empty");
+
+    encode(input_text_1, Some(input_text_2), max_sequence_length)
+}
+
+/// Tokenize a single piece of text (e.g. for sentiment analysis or text
+/// classification), returning the token IDs, token mask, segment IDs, and the
+/// encoded text.
+///
+/// Every token is in the first segment, so the segment IDs will all be `0`.
+///
+/// # Panics
+///
+/// This will panic if `max_sequence_length` is less than
+/// [`MIN_SEQUENCE_LENGTH`].
+pub fn transform_single(
+    text: &[u8],
+    max_sequence_length: usize,
+) -> Result<Encoding, TextError> {
+    assert!(
+        max_sequence_length >= MIN_SEQUENCE_LENGTH,
+        "The sequence length must be at least {}",
+        MIN_SEQUENCE_LENGTH
+    );
+
+    let text = core::str::from_utf8(text).map_err(TextError::InvalidUtf8)?;
+    let text = text.trim_end_matches('\0');
+    if text.is_empty() {
+        return Err(TextError::Empty);
+    }
+
+    Ok(encode(text, None, max_sequence_length))
+}
+
+/// Why [`transform_single()`] couldn't tokenize its text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextError {
+    /// The text wasn't valid UTF-8.
+    InvalidUtf8(core::str::Utf8Error),
+    /// The text was empty, ignoring any trailing null padding.
+    Empty,
+}
+
+impl core::fmt::Display for TextError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TextError::InvalidUtf8(e) => {
+                write!(f, "The text isn't valid UTF-8: {}", e)
+            },
+            TextError::Empty => write!(f, "The text is empty"),
+        }
+    }
+}
+
+impl std::error::Error for TextError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TextError::InvalidUtf8(e) => Some(e),
+            TextError::Empty => None,
+        }
+    }
+}
+
+fn encode(
+    text_1: &str,
+    text_2: Option<&str>,
+    max_sequence_length: usize,
+) -> Encoding {
+    let tok = Tokenizers::shared();
+
+    let TokenizedInput {
+        token_ids,
+        special_tokens_mask: _,
+        mut segment_ids,
+        ..
+    } = tok.bert_tokenizer.encode(
+        text_1,
+        text_2,
+        max_sequence_length,
+        &TruncationStrategy::LongestFirst,
+        0,
+    );
+
+    let (token_ids, mask_ids) = pad_sequence(
+        &token_ids,
+        max_sequence_length,
+        0,
+        Side::End,
+        Side::End,
+    );
+    segment_ids.resize(max_sequence_length, 0);
+
+    let input_ids: Vec<i32> =
+        token_ids.iter().map(|&x| x as i32).collect::<Vec<i32>>();
+
+    let mask_ids: Vec<i32> =
+        mask_ids.iter().map(|&x| x as i32).collect::<Vec<i32>>();
+
+    let seg_ids: Vec<i32> =
+        segment_ids.iter().map(|&x| x as i32).collect::<Vec<i32>>();
+
+    let mut words = String::new();
+    let tok_ids = &token_ids[0 as usize..];
+
+    for id in tok_ids {
+        let s = tok.bert_vocab.id_to_token(*id);
+
+        words.push_str(&s);
+        words.push_str("\n");
+    }
+    words = words.to_string();
+    let words: Vec<u8> = words.as_bytes().to_vec();
+
+    (input_ids, mask_ids, seg_ids, words)
+}
+
+#[cfg(test)]
+
+mod tests {
+    use super::*;
+    #[test]
+    fn test_input_ids() {
+        let word1: Vec<u8> = "What is Google?".as_bytes().to_vec();
+
+        let word2: Vec<u8> =
+            "Google LLC is an American multinational technology company."
+                .as_bytes()
+                .to_vec();
+        let (input_ids, _mask_ids, _segment_ids, _word_bytes) =
+            transform((&word1, &word2));
+
+        let input_ids_should_be = vec![
+            101, 2054, 2003, 8224, 1029, 102, 8224, 11775, 2003, 2019, 2137,
+            20584, 2974, 2194, 1012, 102, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0,
+        ];
+
+        assert_eq!(input_ids, input_ids_should_be);
+    }
+
+    #[test]
+    fn test_mask_ids() {
+        let word1: Vec<u8> = "What is Google?".as_bytes().to_vec();
+
+        let word2: Vec<u8> =
+            "Google LLC is an American multinational technology company."
+                .as_bytes()
+                .to_vec();
+        let (_input_ids, mask_ids, _segment_ids, _word_bytes) =
+            transform((&word1, &word2));
+
+        let mask_ids_should_be = vec![
+            1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+
+        assert_eq!(mask_ids, mask_ids_should_be);
+    }
+
+    #[test]
+    fn test_segment_ids() {
+        let word1: Vec<u8> = "What is Google?".as_bytes().to_vec();
+
+        let word2: Vec<u8> =
+            "Google LLC is an American multinational technology company."
+                .as_bytes()
+                .to_vec();
+
+        let (_input_ids, _mask_ids, segment_ids, _word_bytes) =
+            transform((&word1, &word2));
+
+        let segment_ids_should_be = vec![
+            0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+
+        assert_eq!(segment_ids, segment_ids_should_be);
+    }
+
+    #[test]
+    fn test_word_bytes() {
+        let word1: Vec<u8> = "What is Google?".as_bytes().to_vec();
+
+        let word2: Vec<u8> =
+            "Google LLC is an American multinational technology company."
+                .as_bytes()
+                .to_vec();
+        let (_input_ids, _mask_ids, _segment_ids, word_bytes) =
+            transform((&word1, &word2));
+
+        let word_bytes_should_be = vec![
+            91, 67, 76, 83, 93, 10, 119, 104, 97, 116, 10, 105, 115, 10, 103,
+            111, 111, 103, 108, 101, 10, 63, 10, 91, 83, 69, 80, 93, 10, 103,
+            111, 111, 103, 108, 101, 10, 108, 108, 99, 10, 105, 115, 10, 97,
+            110, 10, 97, 109, 101, 114, 105, 99, 97, 110, 10, 109, 117, 108,
+            116, 105, 110, 97, 116, 105, 111, 110, 97, 108, 10, 116, 101, 99,
+            104, 110, 111, 108, 111, 103, 121, 10, 99, 111, 109, 112, 97, 110,
+            121, 10, 46, 10, 91, 83, 69, 80, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80,
+            65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91,
+            80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10,
+            91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93,
+            10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68,
+            93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65, 68, 93, 10, 91, 80, 65,
+            68, 93, 10, 91, 80, 65, 68, 93, 10,
+        ];
+
+        assert_eq!(word_bytes, word_bytes_should_be);
+    }
+
+    #[test]
+    fn tokenize_a_single_sentence() {
+        let text = "Google LLC is an American company.".as_bytes();
+
+        let (input_ids, mask_ids, segment_ids, _) =
+            transform_single(text, 12).unwrap();
+
+        // [CLS] google llc is an american company . [SEP] [PAD] [PAD] [PAD]
+        assert_eq!(&input_ids[..2], &[101, 8224]);
+        assert_eq!(input_ids[8], 102);
+        assert_eq!(&input_ids[9..], &[0, 0, 0]);
+        assert_eq!(mask_ids, &[1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0]);
+        assert_eq!(segment_ids, &[0; 12]);
+    }
+
+    #[test]
+    fn long_single_sentences_are_truncated() {
+        let text = "What is Google?".as_bytes();
+
+        let (input_ids, mask_ids, _, _) = transform_single(text, 4).unwrap();
+
+        // [CLS] what is [SEP]
+        assert_eq!(input_ids, &[101, 2054, 2003, 102]);
+        assert_eq!(mask_ids, &[1; 4]);
+    }
+
+    #[test]
+    fn single_sentences_must_be_utf8() {
+        let err = transform_single(&[0xff, 0xfe], 12).unwrap_err();
+
+        assert!(matches!(err, TextError::InvalidUtf8(_)));
+    }
+
+    #[test]
+    fn single_sentences_cant_be_empty() {
+        assert_eq!(transform_single(b"", 12).unwrap_err(), TextError::Empty);
+        assert_eq!(
+            transform_single(b"\0\0\0", 12).unwrap_err(),
+            TextError::Empty
+        );
+    }
+
+    #[test]
+    fn the_tokenizer_is_only_loaded_once() {
+        let first = Tokenizers::shared();
+        let second = Tokenizers::shared();
+
+        assert!(core::ptr::eq(first, second));
+    }
+
+    #[test]
+    fn shorter_sequences() {
+        let question = "What is Google?".as_bytes();
+        let paragraph =
+            "Google LLC is an American multinational technology company."
+                .as_bytes();
+
+        let (input_ids, mask_ids, segment_ids, _) =
+            transform_with_max_length((question, paragraph), 8);
+
+        // Tokens are removed from whichever sentence is longest, giving
+        // [CLS] what is [SEP] google llc is [SEP]
+        assert_eq!(input_ids, &[101, 2054, 2003, 102, 8224, 11775, 2003, 102]);
+        assert_eq!(mask_ids, &[1; 8]);
+        assert_eq!(segment_ids, &[0, 0, 0, 0, 1, 1, 1, 1]);
+
+        let (input_ids, mask_ids, _, _) =
+            transform_with_max_length((question, paragraph), 128);
+
+        assert_eq!(input_ids.len(), 128);
+        assert_eq!(mask_ids.iter().filter(|&&m| m == 1).count(), 16);
+    }
+
+    #[test]
+    #[should_panic(expected = "Sentence 1 is empty")]
+    fn empty_sentence_1() {
+        let word1: Vec<u8> = "".as_bytes().to_vec();
+        let word2: Vec<u8> = "Hi".as_bytes().to_vec();
+        let (_input_ids, _mask_ids, _segment_ids, _word_bytes) =
+            transform((&word1, &word2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Sentence 2 is empty")]
+    fn empty_sentence_2() {
+        let word1: Vec<u8> = "Hi".as_bytes().to_vec();
+
+        let word2: Vec<u8> = "".as_bytes().to_vec();
+
+        let (_input_ids, _mask_ids, _segment_ids, _word_bytes) =
+            transform((&word1, &word2));
+    }
+}
@@ -0,0 +1,168 @@
+//! The [Hungarian algorithm][wiki] for solving the assignment problem.
+//!
+//! [wiki]: https://en.wikipedia.org/wiki/Hungarian_algorithm
+
+use hotg_rune_proc_blocks::ndarray::ArrayView2;
+
+/// Assign each row of a cost matrix to a unique column so the total cost is
+/// as small as possible.
+///
+/// The matrix doesn't need to be square. When there are more rows than
+/// columns, some rows will be left unassigned (`None`).
+///
+/// Every cost must be finite. A NaN never compares less than anything, so
+/// the search for an augmenting path would never finish.
+pub(crate) fn minimum_cost_assignment(
+    cost: ArrayView2<'_, f32>,
+) -> Vec<Option<usize>> {
+    let (rows, columns) = cost.dim();
+
+    if rows > columns {
+        // The algorithm needs at least as many columns as rows, so solve the
+        // transposed problem and flip the result around
+        let mut assignment = vec![None; rows];
+
+        for (column, row) in
+            minimum_cost_assignment(cost.t()).into_iter().enumerate()
+        {
+            if let Some(row) = row {
+                assignment[row] = Some(column);
+            }
+        }
+
+        return assignment;
+    }
+
+    // This is the O(n²m) variant which uses potentials. Rows and columns are
+    // 1-indexed so index 0 can be used as a sentinel.
+    let mut row_potential = vec![0.0_f32; rows + 1];
+    let mut column_potential = vec![0.0_f32; columns + 1];
+    // The row assigned to each column, or 0 if it is unassigned
+    let mut assigned_row = vec![0_usize; columns + 1];
+    // The previous column in the augmenting path
+    let mut previous = vec![0_usize; columns + 1];
+
+    for row in 1..=rows {
+        assigned_row[0] = row;
+        let mut current = 0;
+        let mut min_slack = vec![f32::INFINITY; columns + 1];
+        let mut visited = vec![false; columns + 1];
+
+        // Grow an augmenting path until we reach an unassigned column
+        loop {
+            visited[current] = true;
+            let r = assigned_row[current];
+            let mut delta = f32::INFINITY;
+            let mut next = 0;
+
+            for column in 1..=columns {
+                if visited[column] {
+                    continue;
+                }
+
+                let slack = cost[[r - 1, column - 1]]
+                    - row_potential[r]
+                    - column_potential[column];
+
+                if slack < min_slack[column] {
+                    min_slack[column] = slack;
+                    previous[column] = current;
+                }
+                if min_slack[column] < delta {
+                    delta = min_slack[column];
+                    next = column;
+                }
+            }
+
+            for column in 0..=columns {
+                if visited[column] {
+                    row_potential[assigned_row[column]] += delta;
+                    column_potential[column] -= delta;
+                } else {
+                    min_slack[column] -= delta;
+                }
+            }
+
+            current = next;
+
+            if assigned_row[current] == 0 {
+                break;
+            }
+        }
+
+        // Walk back along the path, flipping assignments as we go
+        while current != 0 {
+            let column = previous[current];
+            assigned_row[current] = assigned_row[column];
+            current = column;
+        }
+    }
+
+    let mut assignment = vec![None; rows];
+
+    for (column, &row) in assigned_row.iter().enumerate().skip(1) {
+        if row != 0 {
+            assignment[row - 1] = Some(column - 1);
+        }
+    }
+
+    assignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::ndarray::{self, Array2};
+
+    #[test]
+    fn square_matrix() {
+        let cost = ndarray::arr2(&[
+            [4.0, 1.0, 3.0],
+            [2.0, 0.0, 5.0],
+            [3.0, 2.0, 2.0],
+        ]);
+
+        let assignment = minimum_cost_assignment(cost.view());
+
+        assert_eq!(assignment, [Some(1), Some(0), Some(2)]);
+    }
+
+    #[test]
+    fn greedy_choice_is_not_optimal() {
+        // Greedily giving row 0 its cheapest column would force row 1 to
+        // take the expensive one
+        let cost = ndarray::arr2(&[[1.0, 2.0], [1.5, 10.0]]);
+
+        let assignment = minimum_cost_assignment(cost.view());
+
+        assert_eq!(assignment, [Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn more_columns_than_rows() {
+        let cost = ndarray::arr2(&[[5.0, 1.0, 3.0], [5.0, 0.5, 4.0]]);
+
+        let assignment = minimum_cost_assignment(cost.view());
+
+        assert_eq!(assignment, [Some(2), Some(1)]);
+    }
+
+    #[test]
+    fn more_rows_than_columns() {
+        let cost = ndarray::arr2(&[[5.0, 5.0], [1.0, 0.5], [3.0, 4.0]]);
+
+        let assignment = minimum_cost_assignment(cost.view());
+
+        assert_eq!(assignment, [None, Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn empty_matrix() {
+        assert!(minimum_cost_assignment(Array2::zeros((0, 3)).view())
+            .is_empty());
+        assert_eq!(
+            minimum_cost_assignment(Array2::zeros((2, 0)).view()),
+            [None, None]
+        );
+    }
+}
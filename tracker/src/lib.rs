@@ -0,0 +1,581 @@
+//! A simple multi-object tracker.
+//!
+//! Each frame's detections (typically the output of the `object_filter`
+//! proc-block) are matched against the tracks from previous frames by
+//! finding the assignment which maximizes the total
+//! [Intersection over Union][iou] using the [Hungarian algorithm][hungarian].
+//! Detections which don't overlap any existing track by at least
+//! `iou_threshold` start a new track, and tracks which go unmatched for more
+//! than `max_age` frames are forgotten.
+//!
+//! Detections are only matched against tracks with the same label, so a
+//! "person" track will never be continued by a "car" detection.
+//!
+//! The tracks and the next track ID are kept between invocations, so IDs stay
+//! stable from one frame to the next.
+//!
+//! [iou]: https://en.wikipedia.org/wiki/Jaccard_index
+//! [hungarian]: https://en.wikipedia.org/wiki/Hungarian_algorithm
+
+mod hungarian;
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    ndarray::Array2, prelude::*, runtime_v1::*, state::NodeState,
+    BufferExt, SliceExt,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+/// Each node's tracks.
+static TRACKERS: NodeState<Tracker> = NodeState::new();
+
+/// The number of columns in each detection, `[x, y, height, width,
+/// confidence, label]`.
+const DETECTION_COLUMNS: usize = 6;
+
+/// The cost used when a detection can never be matched with a track.
+const UNMATCHABLE: f32 = 2.0;
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata = Metadata::new("Tracker", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("image");
+        metadata.add_tag("object-detection");
+        metadata.add_tag("tracking");
+        metadata.add_tag("stateful");
+
+        let iou_threshold = ArgumentMetadata::new("iou_threshold");
+        iou_threshold.set_description(
+            "How much a detection must overlap with a track (Intersection over Union) to be considered the same object.",
+        );
+        iou_threshold.set_default_value("0.3");
+        iou_threshold.add_hint(&supported_argument_type(ArgumentType::Float));
+        metadata.add_argument(&iou_threshold);
+
+        let max_age = ArgumentMetadata::new("max_age");
+        max_age.set_description(
+            "How many frames a track can go without a matching detection before it is forgotten.",
+        );
+        max_age.set_default_value("5");
+        max_age
+            .add_hint(&supported_argument_type(ArgumentType::UnsignedInteger));
+        metadata.add_argument(&max_age);
+
+        let detections = TensorMetadata::new("detections");
+        detections.set_description(
+            "The objects detected in the current frame, as `[x, y, height, width, confidence, label]` rows where `(x, y)` is the centre of the bounding box.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, DETECTION_COLUMNS as u32]),
+        );
+        detections.add_hint(&hint);
+        metadata.add_input(&detections);
+
+        let tracks = TensorMetadata::new("tracks");
+        tracks.set_description(
+            "The detections with an extra `track_id` column, `[x, y, height, width, confidence, label, track_id]`. A tracked object keeps the same ID from frame to frame.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::F32],
+            DimensionsParam::Fixed(&[0, DETECTION_COLUMNS as u32 + 1]),
+        );
+        tracks.add_hint(&hint);
+        metadata.add_output(&tracks);
+
+        let ages = TensorMetadata::new("ages");
+        ages.set_description(
+            "How many frames each detection's track has been followed for.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::U32], DimensionsParam::Fixed(&[0]));
+        ages.add_hint(&hint);
+        metadata.add_output(&ages);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "detections",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, DETECTION_COLUMNS as u32]),
+        );
+        ctx.add_output_tensor(
+            "tracks",
+            ElementType::F32,
+            DimensionsParam::Fixed(&[0, DETECTION_COLUMNS as u32 + 1]),
+        );
+        ctx.add_output_tensor(
+            "ages",
+            ElementType::U32,
+            DimensionsParam::Fixed(&[0]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("detections").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "detections".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::F32 {
+            return Err(invalid_detections(format!(
+                "Expected a f32 tensor, found {}",
+                element_type
+            )));
+        }
+
+        let rows = match *dimensions {
+            [rows, columns] if columns as usize == DETECTION_COLUMNS => rows,
+            _ => {
+                return Err(invalid_detections(format!(
+                    "Expected a [detections, {}] tensor, found {:?}",
+                    DETECTION_COLUMNS, dimensions
+                )))
+            },
+        };
+
+        let raw: &[f32] = buffer
+            .try_elements_with_shape(&dimensions)
+            .map_err(invalid_detections)?;
+        let detections = parse_detections(raw)?;
+
+        let matches = TRACKERS.with(&id, |tracker| {
+            tracker.update(&detections, settings)
+        });
+
+        let tracks: Vec<f32> = raw
+            .chunks_exact(DETECTION_COLUMNS)
+            .zip(&matches)
+            .flat_map(|(row, m)| row.iter().copied().chain([m.id as f32]))
+            .collect();
+        let ages: Vec<u32> = matches.iter().map(|m| m.age).collect();
+
+        ctx.set_output_tensor(
+            "tracks",
+            TensorParam {
+                element_type: ElementType::F32,
+                dimensions: &[rows, DETECTION_COLUMNS as u32 + 1],
+                buffer: tracks.as_bytes(),
+            },
+        );
+        ctx.set_output_tensor(
+            "ages",
+            TensorParam {
+                element_type: ElementType::U32,
+                dimensions: &[rows],
+                buffer: ages.as_bytes(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn parse_detections(raw: &[f32]) -> Result<Vec<Detection>, KernelError> {
+    // NaN slips straight through min() and max(), so a box with garbage
+    // coordinates could otherwise "overlap" perfectly with every track
+    if let Some(index) = raw.iter().position(|value| !value.is_finite()) {
+        return Err(invalid_detections(format!(
+            "Detection {} contains a non-finite value",
+            index / DETECTION_COLUMNS
+        )));
+    }
+
+    Ok(raw
+        .chunks_exact(DETECTION_COLUMNS)
+        .map(Detection::from_row)
+        .collect())
+}
+
+fn invalid_detections(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "detections".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    iou_threshold: f32,
+    max_age: u32,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let iou_threshold: f32 =
+            ctx.parse_argument_with_default::<_, E>("iou_threshold", 0.3)?;
+        let max_age = ctx.parse_argument_with_default::<_, E>("max_age", 5)?;
+
+        if !(iou_threshold > 0.0 && iou_threshold <= 1.0) {
+            return Err(E::invalid_argument(InvalidArgument::invalid_value(
+                "iou_threshold",
+                "The threshold must be in the range (0, 1]",
+            )));
+        }
+
+        Ok(Settings {
+            iou_threshold,
+            max_age,
+        })
+    }
+}
+
+/// An axis-aligned bounding box.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct BoundingBox {
+    /// The x coordinate of the box's centre.
+    x: f32,
+    /// The y coordinate of the box's centre.
+    y: f32,
+    height: f32,
+    width: f32,
+}
+
+impl BoundingBox {
+    fn area(&self) -> f32 { self.width.max(0.0) * self.height.max(0.0) }
+
+    /// The [Intersection over Union][iou] of two boxes, where 1.0 means they
+    /// are identical and 0.0 means they don't overlap.
+    ///
+    /// [iou]: https://en.wikipedia.org/wiki/Jaccard_index
+    fn iou(&self, other: &BoundingBox) -> f32 {
+        let overlap = |a_centre: f32, a_size: f32, b_centre: f32, b_size| {
+            let start = (a_centre - a_size / 2.0).max(b_centre - b_size / 2.0);
+            let end = (a_centre + a_size / 2.0).min(b_centre + b_size / 2.0);
+            (end - start).max(0.0)
+        };
+
+        let intersection = overlap(self.x, self.width, other.x, other.width)
+            * overlap(self.y, self.height, other.y, other.height);
+        let union = self.area() + other.area() - intersection;
+
+        if union > 0.0 {
+            intersection / union
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Detection {
+    bounds: BoundingBox,
+    label: f32,
+}
+
+impl Detection {
+    fn from_row(row: &[f32]) -> Self {
+        match *row {
+            [x, y, height, width, _confidence, label] => Detection {
+                bounds: BoundingBox {
+                    x,
+                    y,
+                    height,
+                    width,
+                },
+                label,
+            },
+            _ => unreachable!("Detections always have 6 columns"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Track {
+    id: u32,
+    label: f32,
+    /// Where the object was last seen.
+    bounds: BoundingBox,
+    /// How many frames this track has existed for.
+    age: u32,
+    /// How many frames it has been since this track was matched with a
+    /// detection.
+    frames_since_seen: u32,
+}
+
+/// The track a detection was matched with.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Match {
+    id: u32,
+    age: u32,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Tracker {
+    tracks: Vec<Track>,
+    next_id: u32,
+}
+
+impl Tracker {
+    /// Match a new frame's detections against the existing tracks, returning
+    /// the track each detection belongs to.
+    fn update(
+        &mut self,
+        detections: &[Detection],
+        settings: Settings,
+    ) -> Vec<Match> {
+        let shape = (self.tracks.len(), detections.len());
+        let costs = Array2::from_shape_fn(shape, |(t, d)| {
+            let (track, detection) = (&self.tracks[t], &detections[d]);
+
+            if track.label != detection.label {
+                return UNMATCHABLE;
+            }
+
+            // Garbage coordinates (e.g. NaN) give us a garbage cost, which
+            // would stop the assignment from ever converging
+            let cost = 1.0 - track.bounds.iou(&detection.bounds);
+            if cost.is_finite() {
+                cost
+            } else {
+                UNMATCHABLE
+            }
+        });
+        let assignment = hungarian::minimum_cost_assignment(costs.view());
+
+        let mut matches = vec![None; detections.len()];
+
+        for (track, assigned) in self.tracks.iter_mut().zip(assignment) {
+            track.age = track.age.saturating_add(1);
+
+            // The assignment always pairs things up when it can, so we need
+            // to throw away any pairs that don't actually overlap enough
+            let detection = assigned.filter(|&ix| {
+                let detection = &detections[ix];
+                track.label == detection.label
+                    && track.bounds.iou(&detection.bounds)
+                        >= settings.iou_threshold
+            });
+
+            match detection {
+                Some(ix) => {
+                    track.bounds = detections[ix].bounds;
+                    track.frames_since_seen = 0;
+                    matches[ix] = Some(Match {
+                        id: track.id,
+                        age: track.age,
+                    });
+                },
+                None => {
+                    track.frames_since_seen =
+                        track.frames_since_seen.saturating_add(1);
+                },
+            }
+        }
+
+        self.tracks
+            .retain(|track| track.frames_since_seen <= settings.max_age);
+
+        // Anything left over is a new object
+        for (m, detection) in matches.iter_mut().zip(detections) {
+            if m.is_none() {
+                let track = Track {
+                    id: self.next_id,
+                    label: detection.label,
+                    bounds: detection.bounds,
+                    age: 1,
+                    frames_since_seen: 0,
+                };
+                self.next_id = self.next_id.wrapping_add(1);
+                self.tracks.push(track);
+                *m = Some(Match {
+                    id: track.id,
+                    age: track.age,
+                });
+            }
+        }
+
+        matches.into_iter().flatten().collect()
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotg_rune_proc_blocks::testing::{assert_close, Tolerance};
+
+    const SETTINGS: Settings = Settings {
+        iou_threshold: 0.3,
+        max_age: 2,
+    };
+
+    fn detection(x: f32, y: f32, label: f32) -> Detection {
+        Detection {
+            bounds: BoundingBox {
+                x,
+                y,
+                height: 2.0,
+                width: 2.0,
+            },
+            label,
+        }
+    }
+
+    fn ids(matches: &[Match]) -> Vec<u32> {
+        matches.iter().map(|m| m.id).collect()
+    }
+
+    #[test]
+    fn intersection_over_union() {
+        let a = detection(0.0, 0.0, 0.0).bounds;
+        let b = detection(1.0, 0.0, 0.0).bounds;
+        let far_away = detection(10.0, 10.0, 0.0).bounds;
+
+        assert_close(a.iou(&a), 1.0, Tolerance::DEFAULT);
+        assert_close(a.iou(&b), 1.0 / 3.0, Tolerance::DEFAULT);
+        assert_close(a.iou(&far_away), 0.0, Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn objects_keep_their_id_as_they_move() {
+        let mut tracker = Tracker::default();
+
+        let first = tracker.update(
+            &[detection(0.0, 0.0, 0.0), detection(10.0, 0.0, 0.0)],
+            SETTINGS,
+        );
+        // The objects have moved slightly and come back in a different order
+        let second = tracker.update(
+            &[detection(10.5, 0.0, 0.0), detection(0.5, 0.0, 0.0)],
+            SETTINGS,
+        );
+
+        assert_eq!(ids(&first), [0, 1]);
+        assert_eq!(ids(&second), [1, 0]);
+        assert_eq!(second[0].age, 2);
+    }
+
+    #[test]
+    fn new_objects_get_new_ids() {
+        let mut tracker = Tracker::default();
+
+        tracker.update(&[detection(0.0, 0.0, 0.0)], SETTINGS);
+        let matches = tracker.update(
+            &[detection(0.0, 0.0, 0.0), detection(5.0, 5.0, 0.0)],
+            SETTINGS,
+        );
+
+        assert_eq!(ids(&matches), [0, 1]);
+        assert_eq!(matches[1].age, 1);
+    }
+
+    #[test]
+    fn labels_must_match() {
+        let mut tracker = Tracker::default();
+
+        tracker.update(&[detection(0.0, 0.0, 0.0)], SETTINGS);
+        let matches = tracker.update(&[detection(0.0, 0.0, 1.0)], SETTINGS);
+
+        assert_eq!(ids(&matches), [1]);
+    }
+
+    #[test]
+    fn reject_non_finite_detections() {
+        let raw = [
+            0.0, 0.0, 2.0, 2.0, 0.9, 1.0, // fine
+            f32::NAN, 0.0, 2.0, 2.0, 0.9, 1.0, // bad
+        ];
+
+        assert!(parse_detections(&raw).is_err());
+        assert_eq!(parse_detections(&raw[..6]).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn tracks_survive_brief_occlusion() {
+        let mut tracker = Tracker::default();
+
+        tracker.update(&[detection(0.0, 0.0, 0.0)], SETTINGS);
+        tracker.update(&[], SETTINGS);
+        tracker.update(&[], SETTINGS);
+        let matches = tracker.update(&[detection(0.0, 0.0, 0.0)], SETTINGS);
+
+        assert_eq!(ids(&matches), [0]);
+        assert_eq!(matches[0].age, 4);
+    }
+
+    #[test]
+    fn tracks_are_forgotten_after_max_age() {
+        let mut tracker = Tracker::default();
+
+        tracker.update(&[detection(0.0, 0.0, 0.0)], SETTINGS);
+        for _ in 0..=SETTINGS.max_age {
+            tracker.update(&[], SETTINGS);
+        }
+        let matches = tracker.update(&[detection(0.0, 0.0, 0.0)], SETTINGS);
+
+        assert_eq!(ids(&matches), [1]);
+        assert_eq!(tracker.tracks.len(), 1);
+    }
+}
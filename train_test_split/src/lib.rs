@@ -139,10 +139,9 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             })
         })?;
 
-        let _features_dummy: ndarray::ArrayView2<f64> = features
+        let feature_values: ndarray::ArrayView2<f64> = features
             .buffer
-            .view(&features.dimensions)
-            .and_then(|t| t.into_dimensionality())
+            .try_view(&features.dimensions)
             .map_err(|e| {
                 KernelError::InvalidInput(InvalidInput {
                     name: "x_train".to_string(),
@@ -165,10 +164,9 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             )));
         }
 
-        let _targets_dummy: ndarray::ArrayView1<f64> = targets
+        let target_values: ndarray::ArrayView1<f64> = targets
             .buffer
-            .view(&targets.dimensions)
-            .and_then(|t| t.into_dimensionality())
+            .try_view(&targets.dimensions)
             .map_err(|e| {
                 KernelError::InvalidInput(InvalidInput {
                     name: "targets".to_string(),
@@ -186,9 +184,11 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
             .map_err(KernelError::InvalidArgument)?;
 
         let (x_train, x_test, y_train, y_test, train_dim, test_dim) = transform(
-            features.buffer.elements(),
+            feature_values
+                .as_slice()
+                .expect("Views over a buffer are always contiguous"),
             &features.dimensions,
-            targets.buffer.elements().to_vec(),
+            target_values.to_vec(),
             test_size,
         );
 
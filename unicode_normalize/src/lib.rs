@@ -0,0 +1,347 @@
+//! Normalize strings so text which looks the same is encoded the same way.
+//!
+//! Tokenizers like BERT's look words up byte-for-byte, so "café" written
+//! with a precomposed `é` and "café" written as `e` plus a combining accent
+//! end up as different tokens. Running text through this proc-block first
+//! makes non-English input behave the way the model was trained on.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    prelude::*, runtime_v1::*, BufferExt, StringBuilder,
+};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata =
+            Metadata::new("Unicode Normalize", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("text");
+        metadata.add_tag("nlp");
+        metadata.add_tag("unicode");
+        metadata.with_const_eval();
+
+        let form = ArgumentMetadata::new("form");
+        form.set_description(
+            "The Unicode normalization form to apply. `nfc` and `nfd` only merge or split accents, while `nfkc` and `nfkd` also replace compatibility characters (ligatures, full-width letters, etc.) with their plain equivalents.",
+        );
+        form.set_default_value("nfc");
+        form.add_hint(&interpret_as_string_in_enum(Form::NAMES));
+        metadata.add_argument(&form);
+
+        let case_fold = ArgumentMetadata::new("case_fold");
+        case_fold.set_description(
+            "Convert the text to lowercase, as expected by uncased models.",
+        );
+        case_fold.set_default_value("false");
+        case_fold.add_hint(&interpret_as_string_in_enum(&["true", "false"]));
+        metadata.add_argument(&case_fold);
+
+        let strip_accents = ArgumentMetadata::new("strip_accents");
+        strip_accents.set_description(
+            "Remove accents and other combining marks (e.g. \"café\" becomes \"cafe\").",
+        );
+        strip_accents.set_default_value("false");
+        strip_accents
+            .add_hint(&interpret_as_string_in_enum(&["true", "false"]));
+        metadata.add_argument(&strip_accents);
+
+        let input = TensorMetadata::new("text");
+        input.set_description("The strings to normalize.");
+        let hint =
+            supported_shapes(&[ElementType::Utf8], DimensionsParam::Dynamic);
+        input.add_hint(&hint);
+        metadata.add_input(&input);
+
+        let output = TensorMetadata::new("normalized");
+        output.set_description(
+            "The normalized strings, with the same shape as the input.",
+        );
+        let hint =
+            supported_shapes(&[ElementType::Utf8], DimensionsParam::Dynamic);
+        output.add_hint(&hint);
+        metadata.add_output(&output);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        // Make sure the arguments are valid
+        let _ = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor(
+            "text",
+            ElementType::Utf8,
+            DimensionsParam::Dynamic,
+        );
+        ctx.add_output_tensor(
+            "normalized",
+            ElementType::Utf8,
+            DimensionsParam::Dynamic,
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let Settings {
+            form,
+            case_fold,
+            strip_accents,
+        } = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            dimensions,
+            buffer,
+        } = ctx.get_input_tensor("text").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "text".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != ElementType::Utf8 {
+            return Err(invalid_input(format!(
+                "Expected a utf8 tensor, found {}",
+                element_type
+            )));
+        }
+
+        let strings = buffer.strings().map_err(invalid_input)?;
+
+        let mut builder = StringBuilder::new();
+        for s in strings {
+            builder.push(&normalize(s, form, case_fold, strip_accents));
+        }
+
+        ctx.set_output_tensor(
+            "normalized",
+            TensorParam {
+                element_type: ElementType::Utf8,
+                dimensions: &dimensions,
+                buffer: &builder.finish(),
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "text".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+/// Normalize a string, optionally lowercasing it and removing accents
+/// first.
+///
+/// Case folding uses [`str::to_lowercase()`], which covers the same
+/// characters as full Unicode case folding apart from a handful of special
+/// cases like the German "ß".
+pub fn normalize(
+    text: &str,
+    form: Form,
+    case_fold: bool,
+    strip_accents: bool,
+) -> String {
+    let mut text = if case_fold {
+        text.to_lowercase()
+    } else {
+        text.to_string()
+    };
+
+    if strip_accents {
+        // Accents can only be removed once they've been split out into
+        // their own combining characters
+        text = text.nfd().filter(|&c| !is_combining_mark(c)).collect();
+    }
+
+    match form {
+        Form::Nfc => text.nfc().collect(),
+        Form::Nfkc => text.nfkc().collect(),
+        Form::Nfd => text.nfd().collect(),
+        Form::Nfkd => text.nfkd().collect(),
+        Form::Unchanged => text,
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Form {
+    Nfc,
+    Nfkc,
+    Nfd,
+    Nfkd,
+    /// Leave the text alone (apart from case folding and accent stripping).
+    Unchanged,
+}
+
+impl Form {
+    pub const NAMES: &'static [&'static str] =
+        &["nfc", "nfkc", "nfd", "nfkd", "none"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "nfc" => Some(Form::Nfc),
+            "nfkc" => Some(Form::Nfkc),
+            "nfd" => Some(Form::Nfd),
+            "nfkd" => Some(Form::Nfkd),
+            "none" => Some(Form::Unchanged),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    form: Form,
+    case_fold: bool,
+    strip_accents: bool,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let form = ctx
+            .parse_argument_with_default::<_, E>("form", String::from("nfc"))?;
+        let form = Form::from_name(&form).ok_or_else(|| {
+            E::invalid_argument(InvalidArgument::invalid_value(
+                "form",
+                format!(
+                    "Unknown normalization form, \"{}\". Expected one of {:?}",
+                    form,
+                    Form::NAMES
+                ),
+            ))
+        })?;
+
+        Ok(Settings {
+            form,
+            case_fold: ctx
+                .parse_argument_with_default::<_, E>("case_fold", false)?,
+            strip_accents: ctx
+                .parse_argument_with_default::<_, E>("strip_accents", false)?,
+        })
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRECOMPOSED: &str = "caf\u{e9}";
+    const DECOMPOSED: &str = "cafe\u{301}";
+
+    #[test]
+    fn nfc_merges_combining_accents() {
+        let got = normalize(DECOMPOSED, Form::Nfc, false, false);
+
+        assert_eq!(got, PRECOMPOSED);
+    }
+
+    #[test]
+    fn nfd_splits_accents_out() {
+        let got = normalize(PRECOMPOSED, Form::Nfd, false, false);
+
+        assert_eq!(got, DECOMPOSED);
+    }
+
+    #[test]
+    fn nfkc_replaces_compatibility_characters() {
+        // A "fi" ligature and a full-width "Ａ"
+        let got = normalize("\u{fb01}ne \u{ff21}", Form::Nfkc, false, false);
+
+        assert_eq!(got, "fine A");
+    }
+
+    #[test]
+    fn strip_accents_from_either_form() {
+        for text in [PRECOMPOSED, DECOMPOSED] {
+            let got = normalize(text, Form::Nfc, false, true);
+
+            assert_eq!(got, "cafe", "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn case_folding() {
+        let got = normalize("ÉCOLE Straße", Form::Nfc, true, false);
+
+        assert_eq!(got, "école straße");
+    }
+
+    #[test]
+    fn non_latin_scripts_are_untouched() {
+        let text = "日本語のテキスト";
+
+        let got = normalize(text, Form::Nfc, true, true);
+
+        assert_eq!(got, text);
+    }
+
+    #[test]
+    fn parse_forms() {
+        for &name in Form::NAMES {
+            assert!(Form::from_name(name).is_some(), "{}", name);
+        }
+        assert_eq!(Form::from_name("NFC"), None);
+    }
+}
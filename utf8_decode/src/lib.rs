@@ -23,6 +23,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         metadata.add_tag("text");
         metadata.add_tag("nlp");
         metadata.add_tag("bytes");
+        metadata.with_const_eval();
 
         let input = TensorMetadata::new("bytes");
         input.set_description("The string as UTF-8 encoded bytes");
@@ -80,8 +81,7 @@ impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
         let output = match element_type {
             ElementType::U8 => {
                 let tensor = buffer
-                    .view::<u8>(&dimensions)
-                    .and_then(|t| t.into_dimensionality())
+                    .try_view::<u8, _>(&dimensions)
                     .map_err(|e| {
                         KernelError::InvalidInput(InvalidInput {
                             name: "bytes".to_string(),
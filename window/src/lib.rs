@@ -0,0 +1,411 @@
+//! Split a continuous stream of samples into overlapping frames.
+//!
+//! Audio capabilities hand over however many samples happen to be
+//! available, but models like keyword spotters expect fixed-sized windows
+//! which overlap by some amount. This proc-block buffers the incoming
+//! samples and emits every complete `frame_size` window, starting a new
+//! window each `hop_length` samples.
+//!
+//! Samples which haven't been used by a complete frame are kept until the
+//! next invocation.
+
+use crate::proc_block_v1::{
+    BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+    InvalidInput, KernelError,
+};
+use hotg_rune_proc_blocks::{
+    prelude::*, runtime_v1::*, state::NodeState, BufferExt, SliceExt,
+    ValueType,
+};
+
+wit_bindgen_rust::export!("../wit-files/rune/proc-block-v1.wit");
+
+const SUPPORTED_ELEMENT_TYPES: &[&str] = &["i16", "f32"];
+
+// Each node's leftover samples, for each supported element type
+static I16_FRAMERS: NodeState<Framer<i16>> = NodeState::new();
+static F32_FRAMERS: NodeState<Framer<f32>> = NodeState::new();
+
+struct ProcBlockV1;
+
+impl proc_block_v1::ProcBlockV1 for ProcBlockV1 {
+    fn register_metadata() {
+        let metadata = Metadata::new("Window", env!("CARGO_PKG_VERSION"));
+        metadata.set_description(env!("CARGO_PKG_DESCRIPTION"));
+        metadata.set_repository(env!("CARGO_PKG_REPOSITORY"));
+        metadata.add_tag("audio");
+        metadata.add_tag("streaming");
+        metadata.add_tag("stateful");
+
+        let element_type = ArgumentMetadata::element_type_in(
+            SUPPORTED_ELEMENT_TYPES,
+            ElementType::I16,
+        );
+        element_type.set_description("The type of the samples.");
+        metadata.add_argument(&element_type);
+
+        let frame_size = ArgumentMetadata::new("frame_size");
+        frame_size.set_description("The number of samples in each frame.");
+        frame_size.set_default_value("16000");
+        frame_size.add_hint(&non_negative_number());
+        frame_size.add_hint(&supported_argument_type(ArgumentType::Integer));
+        metadata.add_argument(&frame_size);
+
+        let hop_length = ArgumentMetadata::new("hop_length");
+        hop_length.set_description(
+            "How many samples to move forward between the start of one frame and the next. Frames overlap when this is less than the frame_size, and samples are skipped when it is larger.",
+        );
+        hop_length.set_default_value("8000");
+        hop_length.add_hint(&non_negative_number());
+        hop_length.add_hint(&supported_argument_type(ArgumentType::Integer));
+        metadata.add_argument(&hop_length);
+
+        let input = TensorMetadata::new("samples");
+        input.set_description(
+            "The latest samples from the stream. Tensors with more than one dimension are treated as a flat sequence of samples.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::I16, ElementType::F32],
+            DimensionsParam::Dynamic,
+        );
+        input.add_hint(&hint);
+        metadata.add_input(&input);
+
+        let output = TensorMetadata::new("frames");
+        output.set_description(
+            "Every frame which could be completed this invocation, as a `[num_frames, frame_size]` tensor. This will have zero rows when there aren't enough samples for a frame yet.",
+        );
+        let hint = supported_shapes(
+            &[ElementType::I16, ElementType::F32],
+            DimensionsParam::Fixed(&[0, 0]),
+        );
+        output.add_hint(&hint);
+        metadata.add_output(&output);
+
+        register_node(&metadata);
+    }
+
+    fn graph(id: String) -> Result<(), GraphError> {
+        let ctx =
+            GraphContext::for_node(&id).ok_or(GraphError::MissingContext)?;
+
+        let Settings {
+            element_type,
+            frame_size,
+            ..
+        } = Settings::from_context::<_, GraphError>(&ctx)?;
+
+        ctx.add_input_tensor("samples", element_type, DimensionsParam::Dynamic);
+        ctx.add_output_tensor(
+            "frames",
+            element_type,
+            DimensionsParam::Fixed(&[0, frame_size as u32]),
+        );
+
+        Ok(())
+    }
+
+    fn kernel(id: String) -> Result<(), KernelError> {
+        let ctx =
+            KernelContext::for_node(&id).ok_or(KernelError::MissingContext)?;
+
+        let settings = Settings::from_context::<_, KernelError>(&ctx)?;
+
+        let TensorResult {
+            element_type,
+            buffer,
+            ..
+        } = ctx.get_input_tensor("samples").ok_or_else(|| {
+            KernelError::InvalidInput(InvalidInput {
+                name: "samples".to_string(),
+                reason: BadInputReason::NotFound,
+            })
+        })?;
+
+        if element_type != settings.element_type {
+            return Err(invalid_input(format!(
+                "Expected a {} tensor, found {}",
+                settings.element_type, element_type
+            )));
+        }
+
+        let (frames, num_frames) = match element_type {
+            ElementType::I16 => {
+                settings.apply::<i16>(&id, &I16_FRAMERS, &buffer)?
+            },
+            ElementType::F32 => {
+                settings.apply::<f32>(&id, &F32_FRAMERS, &buffer)?
+            },
+            other => {
+                return Err(KernelError::Other(format!(
+                    "The Window proc-block doesn't support {} samples",
+                    other
+                )))
+            },
+        };
+
+        ctx.set_output_tensor(
+            "frames",
+            TensorParam {
+                element_type,
+                dimensions: &[num_frames as u32, settings.frame_size as u32],
+                buffer: &frames,
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn invalid_input(reason: impl ToString) -> KernelError {
+    KernelError::InvalidInput(InvalidInput {
+        name: "samples".to_string(),
+        reason: BadInputReason::InvalidValue(reason.to_string()),
+    })
+}
+
+/// Buffers a stream of samples and splits it into (possibly overlapping)
+/// frames.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Framer<T> {
+    frame_size: usize,
+    hop_length: usize,
+    /// Samples which haven't been used by a complete frame yet.
+    pending: Vec<T>,
+    /// How many incoming samples to drop before buffering again, used when
+    /// the `hop_length` is larger than the `frame_size`.
+    skip: usize,
+}
+
+impl<T: Copy> Framer<T> {
+    pub fn new(frame_size: usize, hop_length: usize) -> Self {
+        assert!(frame_size > 0, "The frame size must be non-zero");
+        assert!(hop_length > 0, "The hop length must be non-zero");
+
+        Framer {
+            frame_size,
+            hop_length,
+            pending: Vec::new(),
+            skip: 0,
+        }
+    }
+
+    pub fn frame_size(&self) -> usize { self.frame_size }
+
+    pub fn hop_length(&self) -> usize { self.hop_length }
+
+    /// Add samples to the stream, returning every frame that can now be
+    /// completed (concatenated together) and the number of frames.
+    pub fn push(&mut self, samples: &[T]) -> (Vec<T>, usize) {
+        let skipped = self.skip.min(samples.len());
+        self.skip -= skipped;
+        self.pending.extend_from_slice(&samples[skipped..]);
+
+        let mut frames = Vec::new();
+        let mut num_frames = 0;
+        let mut start = 0;
+
+        while start + self.frame_size <= self.pending.len() {
+            frames.extend_from_slice(
+                &self.pending[start..start + self.frame_size],
+            );
+            num_frames += 1;
+            start += self.hop_length;
+        }
+
+        if start > self.pending.len() {
+            self.skip += start - self.pending.len();
+            start = self.pending.len();
+        }
+        self.pending.drain(..start);
+
+        (frames, num_frames)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Settings {
+    element_type: ElementType,
+    frame_size: usize,
+    hop_length: usize,
+}
+
+impl Settings {
+    fn from_context<C, E>(ctx: &C) -> Result<Self, E>
+    where
+        C: ContextExt,
+        E: ContextErrorExt<InvalidArgument = InvalidArgument>,
+    {
+        let element_type =
+            ctx.element_type::<E>(ElementType::I16, SUPPORTED_ELEMENT_TYPES)?;
+        let frame_size =
+            ctx.parse_argument_with_default::<_, E>("frame_size", 16000)?;
+        let hop_length =
+            ctx.parse_argument_with_default::<_, E>("hop_length", 8000)?;
+
+        for (name, value) in
+            [("frame_size", frame_size), ("hop_length", hop_length)]
+        {
+            if value == 0 {
+                return Err(E::invalid_argument(
+                    InvalidArgument::invalid_value(
+                        name,
+                        "Must be greater than zero",
+                    ),
+                ));
+            }
+        }
+
+        Ok(Settings {
+            element_type,
+            frame_size,
+            hop_length,
+        })
+    }
+
+    fn apply<T: ValueType + Copy>(
+        &self,
+        id: &str,
+        framers: &NodeState<Framer<T>>,
+        buffer: &[u8],
+    ) -> Result<(Vec<u8>, usize), KernelError> {
+        let samples = buffer.try_elements::<T>().map_err(invalid_input)?;
+
+        let (frames, num_frames) = framers.with_or_insert(
+            id,
+            || Framer::new(self.frame_size, self.hop_length),
+            |framer| {
+                if framer.frame_size() != self.frame_size
+                    || framer.hop_length() != self.hop_length
+                {
+                    // The arguments changed, so anything we buffered
+                    // was for a different window
+                    *framer = Framer::new(self.frame_size, self.hop_length);
+                }
+
+                framer.push(samples)
+            },
+        );
+
+        Ok((frames.as_bytes().to_vec(), num_frames))
+    }
+}
+
+impl ContextErrorExt for GraphError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        GraphError::InvalidArgument(inner)
+    }
+}
+
+impl ContextErrorExt for KernelError {
+    type InvalidArgument = InvalidArgument;
+
+    fn invalid_argument(inner: InvalidArgument) -> Self {
+        KernelError::InvalidArgument(inner)
+    }
+}
+
+impl InvalidArgumentExt for InvalidArgument {
+    fn other(name: &str, msg: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::Other(msg.to_string()),
+        }
+    }
+
+    fn invalid_value(name: &str, error: impl std::fmt::Display) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::InvalidValue(error.to_string()),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        InvalidArgument {
+            name: name.to_string(),
+            reason: BadArgumentReason::NotFound,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn not_enough_samples_for_a_frame() {
+        let mut framer = Framer::new(4, 2);
+
+        let (frames, num_frames) = framer.push(&[1, 2, 3]);
+
+        assert_eq!(num_frames, 0);
+        assert!(frames.is_empty());
+    }
+
+    #[test]
+    fn overlapping_frames() {
+        let mut framer = Framer::new(4, 2);
+
+        let (frames, num_frames) = framer.push(&[1, 2, 3, 4, 5, 6, 7]);
+
+        assert_eq!(num_frames, 2);
+        assert_eq!(frames, [1, 2, 3, 4, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn leftover_samples_are_carried_over() {
+        let mut framer = Framer::new(4, 2);
+        framer.push(&[1, 2, 3, 4, 5, 6, 7]);
+
+        let (frames, num_frames) = framer.push(&[8, 9]);
+
+        assert_eq!(num_frames, 1);
+        assert_eq!(frames, [5, 6, 7, 8]);
+        assert_eq!(framer.pending, [7, 8, 9]);
+    }
+
+    #[test]
+    fn chunking_does_not_change_the_frames() {
+        let samples: Vec<i16> = (0..100).collect();
+        let mut all_at_once = Framer::new(10, 3);
+        let (expected, expected_frames) = all_at_once.push(&samples);
+
+        let mut framer = Framer::new(10, 3);
+        let mut frames = Vec::new();
+        let mut num_frames = 0;
+        for chunk in samples.chunks(7) {
+            let (f, n) = framer.push(chunk);
+            frames.extend(f);
+            num_frames += n;
+        }
+
+        assert_eq!(num_frames, expected_frames);
+        assert_eq!(frames, expected);
+    }
+
+    #[test]
+    fn hop_longer_than_the_frame_skips_samples() {
+        let mut framer = Framer::new(2, 5);
+
+        let (frames, num_frames) = framer.push(&[0, 1, 2, 3]);
+        assert_eq!(num_frames, 1);
+        assert_eq!(frames, [0, 1]);
+
+        let (frames, num_frames) = framer.push(&[4, 5, 6, 7, 8, 9, 10, 11]);
+        assert_eq!(num_frames, 2);
+        assert_eq!(frames, [5, 6, 10, 11]);
+    }
+
+    #[test]
+    fn non_overlapping_frames() {
+        let mut framer = Framer::new(3, 3);
+
+        let (frames, num_frames) = framer.push(&[1.0_f32, 2.0, 3.0, 4.0]);
+
+        assert_eq!(num_frames, 1);
+        assert_eq!(frames, [1.0, 2.0, 3.0]);
+        assert_eq!(framer.pending, [4.0]);
+    }
+}
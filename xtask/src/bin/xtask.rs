@@ -4,13 +4,14 @@ use std::{
     io::{BufWriter, Write},
     path::{Path, PathBuf},
     str::FromStr,
+    time::Duration,
 };
 
 use anyhow::{Context, Error};
 use once_cell::sync::Lazy;
 use structopt::StructOpt;
 use tracing_subscriber::EnvFilter;
-use xtask::{runtime::Runtime, CompilationMode};
+use xtask::{runtime::Runtime, CompilationMode, Cost, Measurement};
 
 fn main() -> Result<(), Error> {
     tracing_subscriber::fmt::fmt()
@@ -32,6 +33,12 @@ fn main() -> Result<(), Error> {
         Command::Metadata(m) => m.execute(),
         Command::Doc(d) => d.execute(),
         Command::Graph(g) => g.execute(),
+        Command::Fuzz(f) => f.execute(),
+        Command::Python(p) => p.execute(),
+        Command::Ffi(f) => f.execute(),
+        Command::Wasi(w) => w.execute(),
+        Command::Bench(b) => b.execute(),
+        Command::CheckMetadata(c) => c.execute(),
     }
 }
 
@@ -45,6 +52,20 @@ enum Command {
     Doc(Doc),
     ///
     Graph(Graph),
+    /// Run the fuzz targets under `fuzz/` with `cargo fuzz`.
+    Fuzz(Fuzz),
+    /// Build the Python bindings as a wheel.
+    Python(Python),
+    /// Build a static library and C header for calling proc-blocks from C.
+    Ffi(Ffi),
+    /// Compile each proc-block as a WASI command-line program.
+    Wasi(Wasi),
+    /// Benchmark a proc-block and check it against its declared resource
+    /// limits.
+    Bench(Bench),
+    /// Make sure each proc-block's metadata matches the arguments and tensors
+    /// it actually uses.
+    CheckMetadata(CheckMetadata),
 }
 
 #[derive(Debug, StructOpt)]
@@ -185,6 +206,329 @@ impl FromStr for Argument {
     }
 }
 
+#[derive(Debug, StructOpt)]
+struct Fuzz {
+    /// The top-level `Cargo.toml` file.
+    #[structopt(long, default_value = "./Cargo.toml")]
+    workspace_root: PathBuf,
+    /// How many seconds to run each fuzz target for.
+    #[structopt(long, default_value = "60")]
+    seconds: u64,
+    /// The fuzz targets to run (all of them, if not provided).
+    targets: Vec<String>,
+}
+
+impl Fuzz {
+    fn execute(self) -> Result<(), Error> {
+        let proc_blocks =
+            xtask::discover_proc_block_manifests(&self.workspace_root)
+                .context("Unable to find proc-blocks")?;
+
+        let fuzz_dir = self
+            .workspace_root
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join("fuzz");
+        let available = xtask::discover_fuzz_targets(&fuzz_dir)
+            .context("Unable to find the fuzz targets")?;
+
+        for name in
+            xtask::proc_blocks_without_fuzz_targets(&proc_blocks, &available)
+        {
+            tracing::warn!(%name, "No fuzz target for this proc-block");
+        }
+
+        let targets = if self.targets.is_empty() {
+            available
+        } else {
+            for target in &self.targets {
+                if !available.contains(target) {
+                    anyhow::bail!("There is no \"{}\" fuzz target", target);
+                }
+            }
+            self.targets
+        };
+
+        let duration = Duration::from_secs(self.seconds);
+
+        for target in &targets {
+            tracing::info!(%target, "Fuzzing");
+            xtask::run_fuzz_target(&fuzz_dir, target, duration)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct Python {
+    /// The `Cargo.toml` file for the Python bindings.
+    #[structopt(long, default_value = "./python/Cargo.toml")]
+    manifest_path: PathBuf,
+    /// Compile the bindings without any optimisations.
+    #[structopt(long)]
+    debug: bool,
+    /// Where to write the wheel to.
+    #[structopt(short, long, default_value = &*WHEELS_DIR)]
+    out_dir: PathBuf,
+}
+
+impl Python {
+    fn execute(self) -> Result<(), Error> {
+        let mode = if self.debug {
+            CompilationMode::Debug
+        } else {
+            CompilationMode::Release
+        };
+
+        xtask::build_python_wheel(&self.manifest_path, &self.out_dir, mode)
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct Ffi {
+    /// The `Cargo.toml` file for the FFI crate.
+    #[structopt(long, default_value = "./ffi/Cargo.toml")]
+    manifest_path: PathBuf,
+    /// The target triple to compile for (e.g. `armv7-unknown-linux-gnueabihf`).
+    #[structopt(long)]
+    target: Option<String>,
+    /// Compile the library without any optimisations.
+    #[structopt(long)]
+    debug: bool,
+    /// Where to write the static library and header file to.
+    #[structopt(short, long, default_value = &*FFI_DIR)]
+    out_dir: PathBuf,
+}
+
+impl Ffi {
+    fn execute(self) -> Result<(), Error> {
+        let mode = if self.debug {
+            CompilationMode::Debug
+        } else {
+            CompilationMode::Release
+        };
+
+        let proc_blocks = xtask::discover_ffi_proc_blocks(&self.manifest_path)
+            .context("Unable to find the proc-blocks exposed over FFI")?;
+        tracing::debug!(?proc_blocks, "Found proc-blocks");
+
+        let library = xtask::build_ffi_library(
+            &self.manifest_path,
+            self.target.as_deref(),
+            mode,
+        )?;
+
+        std::fs::create_dir_all(&self.out_dir).with_context(|| {
+            format!(
+                "Unable to create the \"{}\" directory",
+                self.out_dir.display()
+            )
+        })?;
+
+        let dest = self.out_dir.join("libproc_blocks.a");
+        std::fs::copy(&library, &dest).with_context(|| {
+            format!(
+                "Unable to copy \"{}\" to \"{}\"",
+                library.display(),
+                dest.display()
+            )
+        })?;
+
+        let header = xtask::generate_c_header(&proc_blocks);
+        let header_path = self.out_dir.join("proc_blocks.h");
+        std::fs::write(&header_path, header).with_context(|| {
+            format!("Unable to write to \"{}\"", header_path.display())
+        })?;
+
+        tracing::info!(
+            out_dir = %self.out_dir.display(),
+            "Wrote the FFI bundle",
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct Wasi {
+    /// The `Cargo.toml` file for the command-line wrappers.
+    #[structopt(long, default_value = "./cli/Cargo.toml")]
+    manifest_path: PathBuf,
+    /// Compile the binaries without any optimisations.
+    #[structopt(long)]
+    debug: bool,
+    /// Where to write the compiled binaries to.
+    #[structopt(short, long, default_value = &*WASI_DIR)]
+    out_dir: PathBuf,
+}
+
+impl Wasi {
+    fn execute(self) -> Result<(), Error> {
+        let mode = if self.debug {
+            CompilationMode::Debug
+        } else {
+            CompilationMode::Release
+        };
+
+        let binaries =
+            xtask::build_wasi_clis(&self.manifest_path, &self.out_dir, mode)?;
+
+        for binary in binaries {
+            tracing::info!(path = %binary.display(), "Compiled");
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct Bench {
+    /// The WebAssembly module to load.
+    #[structopt(parse(from_os_str))]
+    proc_block: PathBuf,
+    /// The sizes to use for each dynamic input dimension.
+    #[structopt(long, use_delimiter = true, default_value = "1,16,256,4096")]
+    sizes: Vec<u32>,
+    /// How many times to run the kernel for each size.
+    #[structopt(long, default_value = "10")]
+    iterations: usize,
+    /// The set of key=value pairs that are passed to the proc-block as
+    /// arguments.
+    #[structopt(parse(try_from_str))]
+    args: Vec<Argument>,
+}
+
+impl Bench {
+    fn execute(self) -> Result<(), Error> {
+        let wasm = std::fs::read(&self.proc_block).with_context(|| {
+            format!("Unable to read \"{}\"", self.proc_block.display())
+        })?;
+
+        let limits = Runtime::load(&wasm)
+            .and_then(|mut r| r.metadata())
+            .context("Unable to determine the metadata")?
+            .resource_limits;
+
+        if limits.is_empty() {
+            tracing::warn!(
+                "The proc-block doesn't declare any resource limits"
+            );
+        }
+
+        let arguments: HashMap<_, _> =
+            self.args.into_iter().map(|a| (a.key, a.value)).collect();
+        let mut measurements = Vec::new();
+        let mut violations = Vec::new();
+
+        for size in self.sizes {
+            let measurement = xtask::benchmark(
+                &wasm,
+                &arguments,
+                size,
+                self.iterations,
+                limits.max_input_elements,
+            )
+            .with_context(|| {
+                format!("Unable to benchmark with a size of {}", size)
+            })?;
+
+            match measurement {
+                Some(measurement) => {
+                    if let Some(cost) = limits.compute_cost {
+                        log_compute_cost(cost, &measurement);
+                    }
+                    violations.extend(limits.check(&measurement));
+                    measurements.push(measurement);
+                },
+                None => tracing::info!(
+                    size,
+                    "Skipping inputs larger than the declared maximum",
+                ),
+            }
+        }
+
+        let json = serde_json::to_string_pretty(&measurements)
+            .context("Unable to serialize the measurements to JSON")?;
+
+        println!("{}", json);
+
+        for violation in &violations {
+            tracing::error!(%violation, "Resource limit exceeded");
+        }
+
+        if !violations.is_empty() {
+            anyhow::bail!(
+                "The proc-block exceeded its declared resource limits {} times",
+                violations.len()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct CheckMetadata {
+    /// The WebAssembly modules to check.
+    #[structopt(parse(from_os_str))]
+    proc_blocks: Vec<PathBuf>,
+}
+
+impl CheckMetadata {
+    fn execute(self) -> Result<(), Error> {
+        let mut total_problems = 0;
+
+        for proc_block in &self.proc_blocks {
+            let wasm = std::fs::read(proc_block).with_context(|| {
+                format!("Unable to read \"{}\"", proc_block.display())
+            })?;
+
+            let problems = xtask::check_metadata(&wasm).with_context(|| {
+                format!("Unable to check \"{}\"", proc_block.display())
+            })?;
+
+            for problem in &problems {
+                tracing::error!(
+                    path = %proc_block.display(),
+                    %problem,
+                    "The metadata doesn't match the proc-block",
+                );
+            }
+
+            if problems.is_empty() {
+                tracing::info!(path = %proc_block.display(), "Metadata is ok");
+            }
+
+            total_problems += problems.len();
+        }
+
+        if total_problems > 0 {
+            anyhow::bail!(
+                "Found {} problems with the proc-block metadata",
+                total_problems
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Compare the declared compute cost with how long the kernel actually took,
+/// so authors can sanity check their estimates.
+fn log_compute_cost(cost: Cost, measurement: &Measurement) {
+    let estimated = cost.evaluate(measurement.input_elements);
+    let measured_us = measurement.execution_time.as_secs_f64() * 1e6;
+
+    tracing::info!(
+        input_elements = measurement.input_elements,
+        estimated,
+        measured_us,
+        us_per_operation = measured_us / estimated,
+        "Compared the estimated compute cost with the execution time",
+    );
+}
+
 #[derive(Debug, StructOpt)]
 struct Doc {
     /// Where to write the generated documentation.
@@ -281,3 +625,24 @@ static DOCS_DIR: Lazy<String> = Lazy::new(|| {
         .display()
         .to_string()
 });
+
+static WHEELS_DIR: Lazy<String> = Lazy::new(|| {
+    Path::new(TARGET_DIR.as_str())
+        .join("wheels")
+        .display()
+        .to_string()
+});
+
+static FFI_DIR: Lazy<String> = Lazy::new(|| {
+    Path::new(TARGET_DIR.as_str())
+        .join("ffi")
+        .display()
+        .to_string()
+});
+
+static WASI_DIR: Lazy<String> = Lazy::new(|| {
+    Path::new(TARGET_DIR.as_str())
+        .join("wasi")
+        .display()
+        .to_string()
+});
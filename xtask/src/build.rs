@@ -32,10 +32,15 @@ pub fn discover_proc_block_manifests(
     for pkg_id in &metadata.workspace_members {
         let package = &metadata[pkg_id];
 
+        // The *_core crates also use hotg-rune-proc-blocks, but they are
+        // plain Rust libraries without any WebAssembly bindings
         let is_proc_block = package
             .dependencies
             .iter()
-            .any(|dep| dep.name == "hotg-rune-proc-blocks");
+            .any(|dep| dep.name == "hotg-rune-proc-blocks")
+            && package.targets.iter().any(|target| {
+                target.crate_types.iter().any(|ty| ty == "cdylib")
+            });
 
         if is_proc_block {
             tracing::debug!(name = %package.name, "Found a proc-block");
@@ -56,6 +61,11 @@ pub struct ProcBlocks {
 }
 
 impl ProcBlocks {
+    /// The names of all the proc-blocks that were discovered.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.packages.iter().map(|p| p.name.as_str())
+    }
+
     /// Compile all the proc-blocks to WebAssembly and parse them as
     /// [`walrus::Module`]s.
     pub fn compile(
@@ -0,0 +1,624 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+};
+
+use anyhow::{Context, Error};
+use serde::Serialize;
+
+use crate::{
+    resources,
+    runtime::{
+        proc_block_v1::{BadArgumentReason, GraphError},
+        runtime_v1::ArgumentType,
+        ArgumentHint, ArgumentMetadata, Dimensions, ElementType, Metadata,
+        NodeInfo, Runtime, TensorHint, TensorInfo, TensorMetadata,
+    },
+};
+
+/// The size used for each dynamic dimension when running the kernel.
+const DYNAMIC_DIMENSION: u32 = 1;
+
+/// Somewhere a proc-block's metadata disagrees with what its `graph()` and
+/// `kernel()` functions actually do.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "type")]
+pub enum Problem {
+    /// An argument's default value is rejected by its own hints or by the
+    /// `graph()` function.
+    InvalidDefault {
+        argument: String,
+        default_value: String,
+        reason: String,
+    },
+    /// The `graph()` function asked for an argument that isn't in the
+    /// metadata.
+    UndeclaredArgument { argument: String },
+    /// The `graph()` function failed, so the tensors couldn't be checked.
+    GraphFailed { reason: String },
+    /// A tensor was declared as an input but registered as an output, or
+    /// vice versa.
+    WrongDirection {
+        tensor: String,
+        declared: Direction,
+        actual: Direction,
+    },
+    /// The `graph()` function registered a tensor that isn't in the
+    /// metadata.
+    UndeclaredTensor { tensor: String, direction: Direction },
+    /// The metadata declares a tensor that `graph()` never registers.
+    UnusedTensor { tensor: String, direction: Direction },
+    /// A tensor's element type isn't one of the declared types.
+    ElementTypeMismatch {
+        tensor: String,
+        accepted: Vec<ElementType>,
+        actual: ElementType,
+    },
+    /// A tensor has a different number of dimensions to what was declared.
+    RankMismatch {
+        tensor: String,
+        declared: usize,
+        actual: usize,
+    },
+    /// The kernel set an output tensor that `graph()` didn't register.
+    UndeclaredOutput { tensor: String },
+    /// The kernel ran successfully without setting one of its outputs.
+    MissingOutput { tensor: String },
+    /// The proc-block is marked as `const-eval`, but running the kernel again
+    /// with the same inputs gave a different output.
+    ImpureConstEval { tensor: String },
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::InvalidDefault {
+                argument,
+                default_value,
+                reason,
+            } => write!(
+                f,
+                "The \"{}\" argument's default value, \"{}\", is invalid: {}",
+                argument, default_value, reason
+            ),
+            Problem::UndeclaredArgument { argument } => write!(
+                f,
+                "The \"{}\" argument is used but never declared",
+                argument
+            ),
+            Problem::GraphFailed { reason } => {
+                write!(f, "Unable to run the graph() function: {}", reason)
+            },
+            Problem::WrongDirection {
+                tensor,
+                declared,
+                actual,
+            } => write!(
+                f,
+                "The \"{}\" tensor is declared as an {} but used as an {}",
+                tensor, declared, actual
+            ),
+            Problem::UndeclaredTensor { tensor, direction } => write!(
+                f,
+                "The \"{}\" {} tensor is used but never declared",
+                tensor, direction
+            ),
+            Problem::UnusedTensor { tensor, direction } => write!(
+                f,
+                "The \"{}\" {} tensor is declared but never used",
+                tensor, direction
+            ),
+            Problem::ElementTypeMismatch {
+                tensor,
+                accepted,
+                actual,
+            } => write!(
+                f,
+                "The \"{}\" tensor is a {}, but only [{}] are declared",
+                tensor,
+                actual,
+                accepted
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Problem::RankMismatch {
+                tensor,
+                declared,
+                actual,
+            } => write!(
+                f,
+                "The \"{}\" tensor is declared with {} dimensions but has {}",
+                tensor, declared, actual
+            ),
+            Problem::UndeclaredOutput { tensor } => write!(
+                f,
+                "The kernel set the \"{}\" output, but graph() never registered it",
+                tensor
+            ),
+            Problem::MissingOutput { tensor } => write!(
+                f,
+                "The kernel never set the \"{}\" output",
+                tensor
+            ),
+            Problem::ImpureConstEval { tensor } => write!(
+                f,
+                "The proc-block can be evaluated at graph-build time, but the \"{}\" output changed when the kernel was run a second time",
+                tensor
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Direction {
+    Input,
+    Output,
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Input => write!(f, "input"),
+            Direction::Output => write!(f, "output"),
+        }
+    }
+}
+
+/// Make sure a proc-block's metadata matches the arguments and tensors its
+/// `graph()` and `kernel()` functions use.
+///
+/// The `graph()` function is called with each argument's default value (or a
+/// placeholder derived from its hints) and the kernel is run against
+/// zero-filled inputs. Kernels are allowed to reject zeroes, so a failing
+/// kernel isn't treated as a problem, but the outputs of a successful run are
+/// compared against what `graph()` registered. Proc-blocks that can be
+/// evaluated at graph-build time have their kernel run a second time to make
+/// sure it gives the same outputs.
+///
+/// Some tensors are only used for certain argument values (e.g. `y_true` when
+/// `residuals=true`, or one output per requested metric), so a tensor is only
+/// reported as unused if `graph()` never registers it for any of the argument
+/// values we try.
+#[tracing::instrument(skip(wasm))]
+pub fn check_metadata(wasm: &[u8]) -> Result<Vec<Problem>, Error> {
+    let metadata = Runtime::load(wasm)
+        .and_then(|mut r| r.metadata())
+        .context("Unable to determine the metadata")?;
+
+    let mut problems = Vec::new();
+    let mut args = HashMap::new();
+
+    for arg in &metadata.arguments {
+        match &arg.default_value {
+            Some(default_value) => {
+                if let Err(reason) = check_hints(&arg.hints, default_value) {
+                    problems.push(Problem::InvalidDefault {
+                        argument: arg.name.clone(),
+                        default_value: default_value.clone(),
+                        reason,
+                    });
+                }
+                args.insert(arg.name.clone(), default_value.clone());
+            },
+            None => {
+                if let Some(value) = placeholder(&arg.hints) {
+                    args.insert(arg.name.clone(), value);
+                }
+            },
+        }
+    }
+
+    let node = match run_graph(wasm, &args, &metadata.arguments)? {
+        GraphOutcome::Ran(node) => node,
+        GraphOutcome::Failed(problem) => {
+            problems.push(problem);
+            return Ok(problems);
+        },
+        GraphOutcome::NeedsArgument(argument) => {
+            tracing::debug!(
+                %argument,
+                "Unable to come up with a value for a required argument, skipping the tensor checks",
+            );
+            return Ok(problems);
+        },
+    };
+
+    check_tensors(&metadata, &node, &mut problems);
+    check_unused_tensors(wasm, &args, &metadata, &node, &mut problems)?;
+    check_kernel(wasm, &args, &node, metadata.const_eval, &mut problems)?;
+
+    Ok(problems)
+}
+
+enum GraphOutcome {
+    Ran(NodeInfo),
+    Failed(Problem),
+    /// The `graph()` function needs a value for a declared argument which has
+    /// no default and couldn't be derived from its hints.
+    NeedsArgument(String),
+}
+
+fn run_graph(
+    wasm: &[u8],
+    args: &HashMap<String, String>,
+    declared: &[ArgumentMetadata],
+) -> Result<GraphOutcome, Error> {
+    let mut runtime = Runtime::load(wasm)?;
+
+    let e = match runtime.graph(args.clone()) {
+        Ok(node) => return Ok(GraphOutcome::Ran(node)),
+        Err(e) => e,
+    };
+
+    let problem = match e.downcast_ref::<GraphError>() {
+        Some(GraphError::InvalidArgument(arg)) => {
+            let declaration = declared.iter().find(|a| a.name == arg.name);
+
+            match (&arg.reason, declaration) {
+                (BadArgumentReason::NotFound, Some(_))
+                    if !args.contains_key(&arg.name) =>
+                {
+                    return Ok(GraphOutcome::NeedsArgument(arg.name.clone()));
+                },
+                (BadArgumentReason::NotFound, None) => {
+                    Problem::UndeclaredArgument {
+                        argument: arg.name.clone(),
+                    }
+                },
+                (
+                    BadArgumentReason::InvalidValue(reason),
+                    Some(ArgumentMetadata {
+                        default_value: Some(default_value),
+                        ..
+                    }),
+                ) => Problem::InvalidDefault {
+                    argument: arg.name.clone(),
+                    default_value: default_value.clone(),
+                    reason: reason.clone(),
+                },
+                _ => Problem::GraphFailed {
+                    reason: format!("{}: {}", arg, arg.reason),
+                },
+            }
+        },
+        _ => Problem::GraphFailed {
+            reason: format!("{:#}", e),
+        },
+    };
+
+    Ok(GraphOutcome::Failed(problem))
+}
+
+/// Make sure the value would be accepted by the argument's hints.
+fn check_hints(hints: &[ArgumentHint], value: &str) -> Result<(), String> {
+    for hint in hints {
+        match hint {
+            ArgumentHint::StringEnum(variants) => {
+                if !variants.iter().any(|v| v == value) {
+                    return Err(format!("Expected one of {:?}", variants));
+                }
+            },
+            ArgumentHint::NumberInRange { min, max } => {
+                let value: f64 = value
+                    .parse()
+                    .map_err(|_| "Expected a number".to_string())?;
+                let in_range = match (min.parse::<f64>(), max.parse::<f64>())
+                {
+                    (Ok(min), Ok(max)) => min <= value && value <= max,
+                    _ => true,
+                };
+
+                if !in_range {
+                    return Err(format!(
+                        "Expected a number between {} and {}",
+                        min, max
+                    ));
+                }
+            },
+            ArgumentHint::NonNegativeNumber => {
+                match value.parse::<f64>() {
+                    Ok(v) if v >= 0.0 => {},
+                    _ => return Err("Expected a non-negative number".into()),
+                }
+            },
+            ArgumentHint::SupportedArgumentType(ty) => {
+                let valid = match ty {
+                    ArgumentType::UnsignedInteger => {
+                        value.parse::<u64>().is_ok()
+                    },
+                    ArgumentType::Integer => value.parse::<i64>().is_ok(),
+                    ArgumentType::Float => value.parse::<f64>().is_ok(),
+                    ArgumentType::String | ArgumentType::LongString => true,
+                };
+
+                if !valid {
+                    return Err(format!("Expected a {:?}", ty));
+                }
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Come up with a value for a required argument, based on its hints.
+fn placeholder(hints: &[ArgumentHint]) -> Option<String> {
+    hints.iter().find_map(|hint| match hint {
+        ArgumentHint::StringEnum(variants) => variants.first().cloned(),
+        ArgumentHint::NumberInRange { min, .. } => Some(min.clone()),
+        ArgumentHint::NonNegativeNumber
+        | ArgumentHint::SupportedArgumentType(
+            ArgumentType::UnsignedInteger | ArgumentType::Integer,
+        ) => Some("1".to_string()),
+        ArgumentHint::SupportedArgumentType(ArgumentType::Float) => {
+            Some("1.0".to_string())
+        },
+        ArgumentHint::SupportedArgumentType(
+            ArgumentType::String | ArgumentType::LongString,
+        ) => None,
+    })
+}
+
+fn check_tensors(
+    metadata: &Metadata,
+    node: &NodeInfo,
+    problems: &mut Vec<Problem>,
+) {
+    let sides = [
+        (Direction::Input, &node.inputs, &metadata.inputs, &metadata.outputs),
+        (
+            Direction::Output,
+            &node.outputs,
+            &metadata.outputs,
+            &metadata.inputs,
+        ),
+    ];
+
+    for (direction, used, declared, opposite) in sides {
+        for tensor in used {
+            match declared.iter().find(|t| t.name == tensor.name) {
+                Some(declaration) => {
+                    check_shape(declaration, tensor, problems)
+                },
+                None if opposite.iter().any(|t| t.name == tensor.name) => {
+                    problems.push(Problem::WrongDirection {
+                        tensor: tensor.name.clone(),
+                        declared: match direction {
+                            Direction::Input => Direction::Output,
+                            Direction::Output => Direction::Input,
+                        },
+                        actual: direction,
+                    })
+                },
+                None => problems.push(Problem::UndeclaredTensor {
+                    tensor: tensor.name.clone(),
+                    direction,
+                }),
+            }
+        }
+    }
+}
+
+fn check_unused_tensors(
+    wasm: &[u8],
+    args: &HashMap<String, String>,
+    metadata: &Metadata,
+    node: &NodeInfo,
+    problems: &mut Vec<Problem>,
+) -> Result<(), Error> {
+    // Tensors used in the wrong direction were reported by check_tensors()
+    let mut used = tensor_names(node);
+
+    let declared = metadata
+        .inputs
+        .iter()
+        .map(|t| (Direction::Input, t))
+        .chain(metadata.outputs.iter().map(|t| (Direction::Output, t)));
+    let unused: Vec<(Direction, &TensorMetadata)> =
+        declared.filter(|(_, t)| !used.contains(&t.name)).collect();
+
+    if unused.is_empty() {
+        return Ok(());
+    }
+
+    let names: Vec<&str> =
+        unused.iter().map(|(_, t)| t.name.as_str()).collect();
+
+    for alternative in alternative_args(args, &metadata.arguments, &names) {
+        // Plenty of these combinations won't make sense, so errors are fine
+        if let Ok(node) = Runtime::load(wasm)?.graph(alternative) {
+            used.extend(tensor_names(&node));
+        }
+    }
+
+    for (direction, declaration) in unused {
+        if !used.contains(&declaration.name) {
+            problems.push(Problem::UnusedTensor {
+                tensor: declaration.name.clone(),
+                direction,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn tensor_names(node: &NodeInfo) -> HashSet<String> {
+    node.inputs
+        .iter()
+        .chain(&node.outputs)
+        .map(|t| t.name.clone())
+        .collect()
+}
+
+/// Variations on `args` which might make `graph()` register different
+/// tensors.
+///
+/// Each argument is changed one at a time, trying every variant of a string
+/// enum, and setting free-form string arguments to the name of each unused
+/// tensor (e.g. `metrics=auc` adds an `auc` output).
+fn alternative_args(
+    args: &HashMap<String, String>,
+    declared: &[ArgumentMetadata],
+    unused: &[&str],
+) -> Vec<HashMap<String, String>> {
+    let mut alternatives = Vec::new();
+
+    for arg in declared {
+        let mut values: Vec<String> = Vec::new();
+
+        for hint in &arg.hints {
+            match hint {
+                ArgumentHint::StringEnum(variants) => {
+                    values.extend(variants.iter().cloned())
+                },
+                ArgumentHint::SupportedArgumentType(
+                    ArgumentType::String | ArgumentType::LongString,
+                ) => values.extend(unused.iter().map(|s| s.to_string())),
+                _ => {},
+            }
+        }
+
+        for value in values {
+            if args.get(&arg.name) == Some(&value) {
+                continue;
+            }
+
+            let mut alternative = args.clone();
+            alternative.insert(arg.name.clone(), value);
+            alternatives.push(alternative);
+        }
+    }
+
+    alternatives
+}
+
+fn check_shape(
+    declaration: &TensorMetadata,
+    tensor: &TensorInfo,
+    problems: &mut Vec<Problem>,
+) {
+    let shapes: Vec<_> = declaration
+        .hints
+        .iter()
+        .filter_map(|hint| match hint {
+            TensorHint::SupportedShape {
+                accepted_element_types,
+                dimensions,
+            } => Some((accepted_element_types, dimensions)),
+            TensorHint::DisplayAs(_) => None,
+        })
+        .collect();
+
+    if shapes.is_empty() {
+        return;
+    }
+
+    let accepted: Vec<ElementType> = shapes
+        .iter()
+        .flat_map(|(types, _)| types.iter().copied())
+        .collect();
+
+    if !accepted.contains(&tensor.element_type) {
+        problems.push(Problem::ElementTypeMismatch {
+            tensor: tensor.name.clone(),
+            accepted,
+            actual: tensor.element_type,
+        });
+    }
+
+    let actual_rank = match &tensor.dimensions {
+        Dimensions::Fixed(dims) => dims.len(),
+        Dimensions::Dynamic => return,
+    };
+    let declared_ranks: Vec<usize> = shapes
+        .iter()
+        .filter_map(|(_, dims)| match dims {
+            Dimensions::Fixed(dims) => Some(dims.len()),
+            Dimensions::Dynamic => None,
+        })
+        .collect();
+
+    // Only complain when every declared shape has a different rank
+    if declared_ranks.len() == shapes.len()
+        && !declared_ranks.contains(&actual_rank)
+    {
+        problems.push(Problem::RankMismatch {
+            tensor: tensor.name.clone(),
+            declared: declared_ranks[0],
+            actual: actual_rank,
+        });
+    }
+}
+
+fn check_kernel(
+    wasm: &[u8],
+    args: &HashMap<String, String>,
+    node: &NodeInfo,
+    const_eval: bool,
+    problems: &mut Vec<Problem>,
+) -> Result<(), Error> {
+    let inputs = node
+        .inputs
+        .iter()
+        .map(|info| {
+            let tensor = resources::input_tensor(info, DYNAMIC_DIMENSION)?;
+            Ok((info.name.clone(), tensor))
+        })
+        .collect::<Result<HashMap<_, _>, Error>>()?;
+
+    let mut runtime = Runtime::load(wasm)?;
+
+    let outputs = match runtime.kernel(args.clone(), inputs.clone()) {
+        Ok(outputs) => outputs,
+        Err(e) => {
+            tracing::debug!(
+                error = %e,
+                "The kernel rejected zero-filled inputs, skipping the output checks",
+            );
+            return Ok(());
+        },
+    };
+
+    let mut names: Vec<&String> = outputs.keys().collect();
+    names.sort();
+
+    for name in names {
+        if !node.outputs.iter().any(|t| &t.name == name) {
+            problems.push(Problem::UndeclaredOutput {
+                tensor: name.clone(),
+            });
+        }
+    }
+
+    for tensor in &node.outputs {
+        if !outputs.contains_key(&tensor.name) {
+            problems.push(Problem::MissingOutput {
+                tensor: tensor.name.clone(),
+            });
+        }
+    }
+
+    if const_eval {
+        // Reuse the same instance so state left over from the first run
+        // shows up. A kernel that fails this time around is just as impure.
+        let second_run =
+            runtime.kernel(args.clone(), inputs).unwrap_or_default();
+
+        let mut names: Vec<&String> = outputs.keys().collect();
+        names.sort();
+
+        for name in names {
+            if second_run.get(name) != outputs.get(name) {
+                problems.push(Problem::ImpureConstEval {
+                    tensor: name.clone(),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
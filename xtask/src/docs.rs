@@ -3,9 +3,12 @@ use std::io::Write;
 use anyhow::Error;
 use itertools::Itertools;
 
-use crate::runtime::{
-    runtime_v1::ArgumentType, ArgumentHint, ArgumentMetadata, Dimensions,
-    Metadata, TensorHint, TensorMetadata,
+use crate::{
+    resources::ResourceLimits,
+    runtime::{
+        runtime_v1::ArgumentType, ArgumentHint, ArgumentMetadata, Dimensions,
+        Metadata, TensorHint, TensorMetadata,
+    },
 };
 
 pub fn document(w: &mut dyn Write, meta: &Metadata) -> Result<(), Error> {
@@ -22,6 +25,9 @@ pub fn document(w: &mut dyn Write, meta: &Metadata) -> Result<(), Error> {
         repository,
         homepage,
         tags,
+        capabilities,
+        resource_limits,
+        const_eval,
         arguments,
         inputs,
         outputs,
@@ -30,6 +36,9 @@ pub fn document(w: &mut dyn Write, meta: &Metadata) -> Result<(), Error> {
     render_title(w, name, version)?;
     render_repo_and_home(w, repository, homepage)?;
     render_tags(w, tags)?;
+    render_capabilities(w, capabilities)?;
+    render_resource_limits(w, resource_limits)?;
+    render_const_eval(w, *const_eval)?;
     render_description(w, description)?;
     render_arguments(w, arguments)?;
     render_tensors(w, "Input Tensors", inputs)?;
@@ -284,6 +293,70 @@ fn render_tags(w: &mut dyn Write, tags: &[String]) -> Result<(), Error> {
     Ok(())
 }
 
+fn render_capabilities(
+    w: &mut dyn Write,
+    capabilities: &[String],
+) -> Result<(), Error> {
+    if !capabilities.is_empty() {
+        writeln!(w, "Required capabilities:")?;
+
+        for capability in capabilities {
+            writeln!(w, "- `{}`", capability)?;
+        }
+
+        writeln!(w)?;
+    }
+
+    Ok(())
+}
+
+fn render_const_eval(w: &mut dyn Write, const_eval: bool) -> Result<(), Error> {
+    if const_eval {
+        writeln!(
+            w,
+            "This proc-block can be evaluated at graph-build time when all of its inputs are constant."
+        )?;
+        writeln!(w)?;
+    }
+
+    Ok(())
+}
+
+fn render_resource_limits(
+    w: &mut dyn Write,
+    limits: &ResourceLimits,
+) -> Result<(), Error> {
+    if limits.is_empty() {
+        return Ok(());
+    }
+
+    let ResourceLimits {
+        max_input_elements,
+        memory_bytes,
+        execution_time_us,
+        compute_cost,
+    } = limits;
+
+    writeln!(w, "Resource limits (`n` is the number of input elements):")?;
+
+    if let Some(max) = max_input_elements {
+        writeln!(w, "- Max input elements: {}", max)?;
+    }
+    if let Some(cost) = memory_bytes {
+        writeln!(w, "- Memory: `{}` bytes", cost)?;
+    }
+    if let Some(cost) = execution_time_us {
+        writeln!(w, "- Execution time: `{}` μs", cost)?;
+    }
+    if let Some(cost) = compute_cost {
+        writeln!(w, "- Estimated compute cost: `{}` operations", cost)?;
+    }
+
+    writeln!(w)?;
+
+    Ok(())
+}
+
 fn non_empty(value: &Option<impl AsRef<str>>) -> Option<&str> {
     match value.as_ref().map(|v| v.as_ref()) {
         Some(v) if !v.is_empty() => Some(v),
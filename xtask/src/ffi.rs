@@ -0,0 +1,186 @@
+use anyhow::{Context, Error};
+use cargo_metadata::MetadataCommand;
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::CompilationMode;
+
+/// Find the proc-blocks that are exposed by the FFI crate.
+///
+/// These are the FFI crate's direct dependencies which contain a proc-block's
+/// algorithm (e.g. `softmax_core` for the `softmax` proc-block).
+pub fn discover_ffi_proc_blocks(
+    manifest_path: &Path,
+) -> Result<Vec<String>, Error> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .exec()
+        .with_context(|| {
+            format!(
+                "Unable to determine the cargo metadata for \"{}\"",
+                manifest_path.display()
+            )
+        })?;
+
+    let root = metadata
+        .root_package()
+        .context("The FFI crate should be a package")?;
+
+    let mut names: Vec<String> = root
+        .dependencies
+        .iter()
+        .filter_map(|dep| {
+            let name = dep.name.replace('-', "_");
+            name.strip_suffix("_core").map(String::from)
+        })
+        .collect();
+
+    names.sort();
+    names.dedup();
+
+    Ok(names)
+}
+
+/// Compile the FFI crate as a static library, returning the path to the
+/// compiled `*.a` file.
+pub fn build_ffi_library(
+    manifest_path: &Path,
+    target: Option<&str>,
+    mode: CompilationMode,
+) -> Result<PathBuf, Error> {
+    let _span = tracing::info_span!("FFI").entered();
+    tracing::info!("Compiling the FFI static library");
+
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+
+    let mut cmd = Command::new(&cargo);
+    cmd.arg("build").arg("--manifest-path").arg(manifest_path);
+
+    if let Some(target) = target {
+        cmd.arg("--target").arg(target);
+    }
+
+    match mode {
+        CompilationMode::Release => {
+            cmd.arg("--release");
+        },
+        CompilationMode::Debug => {},
+    }
+
+    tracing::debug!(command = ?cmd, "Running cargo build");
+
+    let status = cmd.status().with_context(|| {
+        format!(
+            "Unable to start \"{}\"",
+            cmd.get_program().to_string_lossy()
+        )
+    })?;
+
+    if !status.success() {
+        anyhow::bail!("Compilation failed");
+    }
+
+    let mut artifact_dir = manifest_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join("target");
+    if let Some(target) = target {
+        artifact_dir.push(target);
+    }
+    artifact_dir.push(mode.dir());
+
+    Ok(artifact_dir.join("libproc_blocks.a"))
+}
+
+/// Generate a C header declaring the functions for each proc-block.
+pub fn generate_c_header(proc_blocks: &[String]) -> String {
+    let mut header = String::from(HEADER_PRELUDE);
+
+    for name in proc_blocks {
+        writeln!(
+            header,
+            r#"
+/* {name} */
+
+typedef struct hotg_{name} hotg_{name};
+
+/* Create a new node, returning NULL if the arguments are invalid. Use
+ * hotg_last_error() to find out why. */
+hotg_{name} *hotg_{name}_create(const hotg_argument *arguments,
+                                size_t num_arguments);
+
+/* Run the node, returning 0 on success. The output tensors are owned by the
+ * node and remain valid until the next call to run() or free(). */
+int hotg_{name}_run(hotg_{name} *node, const hotg_tensor *inputs,
+                    size_t num_inputs, hotg_tensor *outputs,
+                    size_t num_outputs);
+
+/* Get a message describing the most recent error, if there was one. */
+const char *hotg_{name}_last_error(const hotg_{name} *node);
+
+/* Destroy the node. */
+void hotg_{name}_free(hotg_{name} *node);"#,
+            name = name
+        )
+        .expect("Writing to a string never fails");
+    }
+
+    header.push_str(HEADER_EPILOGUE);
+    header
+}
+
+const HEADER_PRELUDE: &str = r#"/* Generated by `cargo xtask ffi`. Do not edit. */
+
+#ifndef HOTG_PROC_BLOCKS_H
+#define HOTG_PROC_BLOCKS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum hotg_element_type {
+    HOTG_ELEMENT_TYPE_U8 = 0,
+    HOTG_ELEMENT_TYPE_I8 = 1,
+    HOTG_ELEMENT_TYPE_U16 = 2,
+    HOTG_ELEMENT_TYPE_I16 = 3,
+    HOTG_ELEMENT_TYPE_U32 = 4,
+    HOTG_ELEMENT_TYPE_I32 = 5,
+    HOTG_ELEMENT_TYPE_F32 = 6,
+    HOTG_ELEMENT_TYPE_U64 = 7,
+    HOTG_ELEMENT_TYPE_I64 = 8,
+    HOTG_ELEMENT_TYPE_F64 = 9,
+    HOTG_ELEMENT_TYPE_UTF8 = 10,
+} hotg_element_type;
+
+typedef struct hotg_argument {
+    const char *name;
+    const char *value;
+} hotg_argument;
+
+typedef struct hotg_tensor {
+    uint32_t element_type; /* one of the hotg_element_type values */
+    const uint32_t *dimensions;
+    size_t rank;
+    const uint8_t *data;
+    size_t data_len;
+} hotg_tensor;
+
+/* Get a message describing why the most recent create() call on this thread
+ * failed, or NULL if it succeeded. The message remains valid until the next
+ * call to create() on this thread. */
+const char *hotg_last_error(void);
+"#;
+
+const HEADER_EPILOGUE: &str = r#"
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* HOTG_PROC_BLOCKS_H */
+"#;
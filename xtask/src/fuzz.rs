@@ -0,0 +1,79 @@
+use anyhow::{Context, Error};
+use std::{path::Path, process::Command, time::Duration};
+
+use crate::build::ProcBlocks;
+
+/// Find the names of all fuzz targets in a `cargo-fuzz` project.
+pub fn discover_fuzz_targets(fuzz_dir: &Path) -> Result<Vec<String>, Error> {
+    let targets_dir = fuzz_dir.join("fuzz_targets");
+
+    let entries = std::fs::read_dir(&targets_dir).with_context(|| {
+        format!("Unable to read the \"{}\" directory", targets_dir.display())
+    })?;
+
+    let mut targets = Vec::new();
+
+    for entry in entries {
+        let path = entry?.path();
+
+        if path.extension().and_then(|ext| ext.to_str()) != Some("rs") {
+            continue;
+        }
+
+        if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+            tracing::debug!(%name, "Found a fuzz target");
+            targets.push(name.to_string());
+        }
+    }
+
+    targets.sort();
+
+    Ok(targets)
+}
+
+/// Get the names of all proc-blocks which don't have a fuzz target.
+pub fn proc_blocks_without_fuzz_targets<'a>(
+    proc_blocks: &'a ProcBlocks,
+    targets: &'a [String],
+) -> impl Iterator<Item = &'a str> + 'a {
+    proc_blocks.names().filter(move |name| {
+        let name = name.replace('-', "_");
+        !targets.contains(&name)
+    })
+}
+
+/// Run a single fuzz target for a fixed amount of time using `cargo fuzz`.
+pub fn run_fuzz_target(
+    fuzz_dir: &Path,
+    target: &str,
+    duration: Duration,
+) -> Result<(), Error> {
+    let _span = tracing::info_span!("Fuzz", %target).entered();
+
+    let mut cmd = Command::new("cargo");
+    cmd.arg("+nightly")
+        .arg("fuzz")
+        .arg("run")
+        .arg("--fuzz-dir")
+        .arg(fuzz_dir)
+        .arg(target)
+        .arg("--")
+        .arg(format!("-max_total_time={}", duration.as_secs()));
+
+    tracing::debug!(command = ?cmd, "Running cargo fuzz");
+
+    let status = cmd.status().with_context(|| {
+        format!(
+            "Unable to start \"{}\". Is cargo-fuzz installed?",
+            cmd.get_program().to_string_lossy()
+        )
+    })?;
+
+    tracing::debug!(exit_code = ?status.code(), "Fuzzing completed");
+
+    if !status.success() {
+        anyhow::bail!("Fuzzing \"{}\" failed", target);
+    }
+
+    Ok(())
+}
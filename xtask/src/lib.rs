@@ -1,10 +1,26 @@
 mod build;
+mod check;
 mod docs;
+mod ffi;
+mod fuzz;
 mod manifest;
+mod python;
+mod resources;
 pub mod runtime;
+mod wasi;
 
 pub use crate::{
     build::{discover_proc_block_manifests, CompilationMode},
+    check::{check_metadata, Direction, Problem},
     docs::document,
+    ffi::{build_ffi_library, discover_ffi_proc_blocks, generate_c_header},
+    fuzz::{
+        discover_fuzz_targets, proc_blocks_without_fuzz_targets,
+        run_fuzz_target,
+    },
     manifest::{generate_manifest, Manifest},
+    python::build_python_wheel,
+    resources::{benchmark, Measurement, ResourceLimits, Violation},
+    wasi::build_wasi_clis,
 };
+pub use hotg_rune_proc_blocks::Cost;
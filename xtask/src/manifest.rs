@@ -1,6 +1,6 @@
 use crate::{
     build::CompiledModule,
-    runtime::{Metadata, Runtime},
+    runtime::{self, Metadata, Runtime},
 };
 use anyhow::{Context, Error};
 use serde::Serialize;
@@ -10,6 +10,7 @@ use std::{
     io::{Seek, SeekFrom},
     path::Path,
 };
+use walrus::RawCustomSection;
 
 pub fn generate_manifest(
     modules: Vec<CompiledModule>,
@@ -21,34 +22,60 @@ pub fn generate_manifest(
         let _span = tracing::info_span!("Extracting metadata", module = %name)
             .entered();
 
-        let serialized = module.emit_wasm();
-        let metadata = extract_metadata(&serialized).with_context(|| {
-            format!("Unable to extract metadata from \"{}\"", name)
-        })?;
+        let (metadata, abi_version) = extract_metadata(&module.emit_wasm())
+            .with_context(|| {
+                format!("Unable to extract metadata from \"{}\"", name)
+            })?;
+        let hash = runtime::metadata_hash(&metadata, abi_version)?;
         tracing::debug!(
             %metadata.name,
             %metadata.version,
+            %hash,
             "Extracted metadata for proc-block",
         );
 
+        // Embed the hash so hosts can check it without instantiating the
+        // module or downloading the metadata.
+        module.customs.add(RawCustomSection {
+            name: runtime::METADATA_HASH_SECTION.to_string(),
+            data: hash.as_bytes().to_vec(),
+        });
+        let serialized = module.emit_wasm();
+
         let filename = format!("{}.wasm", name);
         manifest.serialized.insert(filename.clone(), serialized);
-        manifest.metadata.insert(filename, metadata);
+        manifest
+            .metadata
+            .insert(filename, ManifestEntry { hash, metadata });
     }
 
     Ok(manifest)
 }
 
-fn extract_metadata(serialized: &[u8]) -> Result<Metadata, Error> {
-    Runtime::load(serialized)?.metadata()
+fn extract_metadata(
+    serialized: &[u8],
+) -> Result<(Metadata, Option<u32>), Error> {
+    let mut runtime = Runtime::load(serialized)?;
+    let metadata = runtime.metadata()?;
+
+    Ok((metadata, runtime.abi_version()))
 }
 
 #[derive(Default)]
 pub struct Manifest {
-    metadata: HashMap<String, Metadata>,
+    metadata: HashMap<String, ManifestEntry>,
     serialized: HashMap<String, Vec<u8>>,
 }
 
+/// A proc-block's entry in `metadata.json`.
+#[derive(Debug, Serialize)]
+struct ManifestEntry {
+    /// See [`runtime::metadata_hash()`].
+    hash: String,
+    #[serde(flatten)]
+    metadata: Metadata,
+}
+
 impl Manifest {
     #[tracing::instrument(skip(self))]
     pub fn write_to_disk(&self, dir: &Path) -> Result<(), Error> {
@@ -0,0 +1,46 @@
+use anyhow::{Context, Error};
+use std::{path::Path, process::Command};
+
+use crate::CompilationMode;
+
+/// Use [`maturin`](https://github.com/PyO3/maturin) to build the Python
+/// bindings as a wheel.
+pub fn build_python_wheel(
+    manifest_path: &Path,
+    out_dir: &Path,
+    mode: CompilationMode,
+) -> Result<(), Error> {
+    let _span = tracing::info_span!("Python").entered();
+    tracing::info!("Building the Python bindings");
+
+    let mut cmd = Command::new("maturin");
+    cmd.arg("build")
+        .arg("--manifest-path")
+        .arg(manifest_path)
+        .arg("--out")
+        .arg(out_dir);
+
+    match mode {
+        CompilationMode::Release => {
+            cmd.arg("--release");
+        },
+        CompilationMode::Debug => {},
+    }
+
+    tracing::debug!(command = ?cmd, "Running maturin");
+
+    let status = cmd.status().with_context(|| {
+        format!(
+            "Unable to start \"{}\". Is maturin installed?",
+            cmd.get_program().to_string_lossy()
+        )
+    })?;
+
+    tracing::debug!(exit_code = ?status.code(), "Maturin completed");
+
+    if !status.success() {
+        anyhow::bail!("Building the Python bindings failed");
+    }
+
+    Ok(())
+}
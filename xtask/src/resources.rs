@@ -0,0 +1,199 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Error};
+use hotg_rune_proc_blocks::{Cost, ResourceLimit};
+use serde::{Deserialize, Serialize};
+
+use crate::runtime::{Dimension, Dimensions, Runtime, Tensor, TensorInfo};
+
+/// WebAssembly memory can only grow in whole pages.
+const WASM_PAGE_SIZE: u64 = 64 * 1024;
+
+/// The worst-case resources a proc-block has declared, as parsed from its
+/// `resource:` tags.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ResourceLimits {
+    pub max_input_elements: Option<u64>,
+    pub memory_bytes: Option<Cost>,
+    pub execution_time_us: Option<Cost>,
+    /// The estimated work done by each call to the kernel. This is only used
+    /// to compare proc-blocks, so it is never treated as a violation.
+    pub compute_cost: Option<Cost>,
+}
+
+impl ResourceLimits {
+    pub fn is_empty(&self) -> bool { *self == ResourceLimits::default() }
+
+    /// Record a limit of the form `name=value` (i.e. a tag without its
+    /// `resource:` prefix).
+    pub fn add(&mut self, limit: &str) -> Result<(), Error> {
+        match limit.parse()? {
+            ResourceLimit::MaxInputElements(n) => {
+                self.max_input_elements = Some(n)
+            },
+            ResourceLimit::MemoryBytes(cost) => self.memory_bytes = Some(cost),
+            ResourceLimit::ExecutionTimeMicros(cost) => {
+                self.execution_time_us = Some(cost)
+            },
+            ResourceLimit::ComputeCost(cost) => self.compute_cost = Some(cost),
+        }
+
+        Ok(())
+    }
+
+    /// Compare a [`Measurement`] against the declared limits.
+    pub fn check(&self, measurement: &Measurement) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let n = measurement.input_elements;
+
+        if let Some(cost) = self.memory_bytes {
+            // Memory is measured in whole pages, so give the declared value
+            // the same granularity.
+            let declared = cost.evaluate(n).max(0.0).ceil() as u64;
+            let pages = (declared + WASM_PAGE_SIZE - 1) / WASM_PAGE_SIZE;
+            let declared = pages * WASM_PAGE_SIZE;
+
+            if measurement.memory_bytes > declared {
+                violations.push(Violation {
+                    resource: "memory-bytes",
+                    input_elements: n,
+                    declared: declared as f64,
+                    measured: measurement.memory_bytes as f64,
+                });
+            }
+        }
+
+        if let Some(cost) = self.execution_time_us {
+            let declared = cost.evaluate(n);
+            let measured = measurement.execution_time.as_secs_f64() * 1e6;
+
+            if measured > declared {
+                violations.push(Violation {
+                    resource: "execution-time-us",
+                    input_elements: n,
+                    declared,
+                    measured,
+                });
+            }
+        }
+
+        violations
+    }
+}
+
+/// The resources used while running a proc-block's kernel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Measurement {
+    pub input_elements: u64,
+    /// How much the module's linear memory grew while running the kernel.
+    pub memory_bytes: u64,
+    /// The slowest of all the kernel invocations.
+    pub execution_time: Duration,
+}
+
+/// A [`Measurement`] which exceeded the declared [`ResourceLimits`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Violation {
+    pub resource: &'static str,
+    pub input_elements: u64,
+    pub declared: f64,
+    pub measured: f64,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} exceeded its declared limit with {} input elements (declared: {}, measured: {})",
+            self.resource, self.input_elements, self.declared, self.measured,
+        )
+    }
+}
+
+/// Run a proc-block's kernel against zero-filled inputs, replacing each
+/// dynamic dimension with `dynamic_dimension`, and record how much memory
+/// and time it used.
+///
+/// Returns `None` without running the kernel if the inputs would have more
+/// than `max_input_elements` elements.
+///
+/// A fresh instance is used for every call so memory allocated by previous
+/// runs doesn't hide the cost of this one.
+#[tracing::instrument(skip(wasm, args))]
+pub fn benchmark(
+    wasm: &[u8],
+    args: &HashMap<String, String>,
+    dynamic_dimension: u32,
+    iterations: usize,
+    max_input_elements: Option<u64>,
+) -> Result<Option<Measurement>, Error> {
+    let mut runtime = Runtime::load(wasm)?;
+
+    let node = runtime
+        .graph(args.clone())
+        .context("Unable to determine the proc-block's inputs")?;
+
+    let inputs = node
+        .inputs
+        .iter()
+        .map(|info| {
+            let tensor = input_tensor(info, dynamic_dimension)?;
+            Ok((info.name.clone(), tensor))
+        })
+        .collect::<Result<HashMap<_, _>, Error>>()?;
+    let input_elements = inputs.values().map(Tensor::element_count).sum();
+
+    if max_input_elements.map_or(false, |max| input_elements > max) {
+        return Ok(None);
+    }
+
+    let baseline = runtime.memory_bytes()?;
+    let mut execution_time = Duration::ZERO;
+
+    for _ in 0..iterations.max(1) {
+        let start = Instant::now();
+        runtime.kernel(args.clone(), inputs.clone())?;
+        execution_time = execution_time.max(start.elapsed());
+    }
+
+    let memory_bytes = runtime.memory_bytes()? - baseline;
+
+    tracing::debug!(
+        input_elements,
+        memory_bytes,
+        ?execution_time,
+        "Finished benchmarking"
+    );
+
+    Ok(Some(Measurement {
+        input_elements,
+        memory_bytes,
+        execution_time,
+    }))
+}
+
+pub(crate) fn input_tensor(
+    info: &TensorInfo,
+    dynamic_dimension: u32,
+) -> Result<Tensor, Error> {
+    let dimensions = match &info.dimensions {
+        Dimensions::Dynamic => vec![dynamic_dimension],
+        Dimensions::Fixed(dims) => dims
+            .iter()
+            .map(|d| match d {
+                Dimension::Fixed(d) => d.get() as u32,
+                Dimension::Dynamic => dynamic_dimension,
+            })
+            .collect(),
+    };
+
+    Tensor::zeroed(info.element_type, dimensions).with_context(|| {
+        format!("Unable to create the \"{}\" input", info.name)
+    })
+}
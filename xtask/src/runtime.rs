@@ -1,26 +1,37 @@
-use crate::runtime::{
-    proc_block_v1::{
-        BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
-        InvalidInput, KernelError, ProcBlockV1,
+use crate::{
+    resources::ResourceLimits,
+    runtime::{
+        proc_block_v1::{
+            BadArgumentReason, BadInputReason, GraphError, InvalidArgument,
+            InvalidInput, KernelError, ProcBlockV1,
+        },
+        runtime_v1::LogMetadata,
     },
-    runtime_v1::LogMetadata,
 };
 use anyhow::{Context, Error};
+use hotg_rune_proc_blocks::{
+    abi::{ABI_VERSION, ABI_VERSION_SECTION, CONST_EVAL_TAG},
+    Capability, ResourceLimit,
+};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::{
     collections::HashMap,
+    convert::TryInto,
     fmt::{self, Display, Formatter},
     num::NonZeroUsize,
     sync::{Arc, Mutex},
 };
-use wasmer::{ImportObject, Module, Store, WasmerEnv};
+use wasmer::{ImportObject, Instance, Module, Store, WasmerEnv};
 
 wit_bindgen_wasmer::export!("../wit-files/rune/runtime-v1.wit");
 wit_bindgen_wasmer::import!("../wit-files/rune/proc-block-v1.wit");
 
 pub struct Runtime {
     rune: ProcBlockV1,
+    instance: Instance,
     shared: Arc<Mutex<Shared>>,
+    abi_version: Option<u32>,
 }
 
 impl Runtime {
@@ -32,6 +43,8 @@ impl Runtime {
         let module = Module::new(&store, wasm)
             .context("Unable to instantiate the module")?;
 
+        let abi_version = check_abi_version(&module)?;
+
         tracing::debug!("Setting up the host functions");
 
         let mut imports = ImportObject::default();
@@ -44,13 +57,22 @@ impl Runtime {
 
         tracing::debug!("Instantiating the WebAssembly module");
 
-        let (rune, _) =
+        let (rune, instance) =
             ProcBlockV1::instantiate(&mut store, &module, &mut imports)
                 .context("Unable to instantiate the WebAssembly module")?;
 
-        Ok(Runtime { rune, shared })
+        Ok(Runtime {
+            rune,
+            instance,
+            shared,
+            abi_version,
+        })
     }
 
+    /// The ABI version recorded in the module's [`ABI_VERSION_SECTION`], if
+    /// it has one.
+    pub fn abi_version(&self) -> Option<u32> { self.abi_version }
+
     #[tracing::instrument(skip(self))]
     pub fn metadata(&mut self) -> Result<Metadata, Error> {
         tracing::debug!("Running the register_metadata() function");
@@ -81,6 +103,131 @@ impl Runtime {
         let mut shared = self.shared.lock().unwrap();
         Ok(std::mem::take(&mut shared.node))
     }
+
+    #[tracing::instrument(skip(self, args, inputs))]
+    pub fn kernel(
+        &mut self,
+        args: HashMap<String, String>,
+        inputs: HashMap<String, Tensor>,
+    ) -> Result<HashMap<String, Tensor>, Error> {
+        let mut shared = self.shared.lock().unwrap();
+        shared.args = args;
+        shared.inputs = inputs;
+        shared.outputs.clear();
+        drop(shared);
+
+        self.rune
+            .kernel("")
+            .context("Unable to call the kernel() function")??;
+
+        let mut shared = self.shared.lock().unwrap();
+        Ok(std::mem::take(&mut shared.outputs))
+    }
+
+    /// Evaluate the kernel ahead of time, for a node whose inputs are all
+    /// constants.
+    ///
+    /// This returns `None` when the proc-block isn't marked as
+    /// [`Metadata::const_eval`], in which case the node needs to be run
+    /// during inference like normal.
+    #[tracing::instrument(skip(self, args, inputs))]
+    pub fn const_eval(
+        &mut self,
+        args: HashMap<String, String>,
+        inputs: HashMap<String, Tensor>,
+    ) -> Result<Option<HashMap<String, Tensor>>, Error> {
+        if !self.metadata()?.const_eval {
+            return Ok(None);
+        }
+
+        tracing::debug!("Folding the node at graph-build time");
+
+        self.kernel(args, inputs).map(Some)
+    }
+
+    /// The size of the WebAssembly module's linear memory, in bytes.
+    ///
+    /// Linear memory never shrinks, so this is also the most memory the
+    /// module has used so far.
+    pub fn memory_bytes(&self) -> Result<u64, Error> {
+        let memory = self
+            .instance
+            .exports
+            .get_memory("memory")
+            .context("The module doesn't export its memory")?;
+
+        Ok(memory.size().bytes().0 as u64)
+    }
+}
+
+/// The name of the custom section `cargo xtask dist` uses to record a hash of
+/// the proc-block's metadata.
+pub const METADATA_HASH_SECTION: &str = "hotg.proc-block.metadata-hash";
+
+/// Calculate a hash of the proc-block's metadata and the ABI version it
+/// was compiled against (see [`Runtime::abi_version()`]), as a hex-encoded
+/// SHA-256 digest.
+///
+/// Hosts that cache metadata can compare this against the
+/// [`METADATA_HASH_SECTION`] in a freshly downloaded proc-block to know
+/// whether their copy is stale.
+pub fn metadata_hash(
+    metadata: &Metadata,
+    abi_version: Option<u32>,
+) -> Result<String, Error> {
+    let serialized = serde_json::to_vec(metadata)
+        .context("Unable to serialize the metadata")?;
+
+    let mut hasher = Sha256::new();
+    if let Some(version) = abi_version {
+        hasher.update(version.to_le_bytes());
+    }
+    hasher.update(&serialized);
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Read the module's ABI version, making sure it is one this host supports.
+fn check_abi_version(module: &Module) -> Result<Option<u32>, Error> {
+    let section = match module.custom_sections(ABI_VERSION_SECTION).next() {
+        Some(s) => s,
+        None => {
+            tracing::warn!(
+                "The proc-block doesn't specify an ABI version. It was probably compiled with an older version of hotg-rune-proc-blocks"
+            );
+            return Ok(None);
+        },
+    };
+
+    // Note: the linker will concatenate sections if the support crate was
+    // linked in more than once, so every chunk needs to agree.
+    let mut versions = section
+        .chunks(4)
+        .map(|chunk| chunk.try_into().ok().map(u32::from_le_bytes));
+
+    match versions.next() {
+        Some(Some(version)) if versions.all(|v| v == Some(version)) => {
+            tracing::debug!(version, "Found the proc-block's ABI version");
+
+            if version != ABI_VERSION {
+                anyhow::bail!(
+                    "The proc-block was compiled against ABI version {}, but this host only supports version {}",
+                    version,
+                    ABI_VERSION,
+                );
+            }
+
+            Ok(Some(version))
+        },
+        _ => anyhow::bail!(
+            "The \"{}\" custom section is malformed",
+            ABI_VERSION_SECTION
+        ),
+    }
 }
 
 #[derive(Default, Clone, WasmerEnv)]
@@ -91,6 +238,8 @@ struct Shared {
     args: HashMap<String, String>,
     metadata: Metadata,
     node: NodeInfo,
+    inputs: HashMap<String, Tensor>,
+    outputs: HashMap<String, Tensor>,
 }
 
 #[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
@@ -102,6 +251,16 @@ pub struct Metadata {
     pub repository: Option<String>,
     pub homepage: Option<String>,
     pub tags: Vec<String>,
+    /// Host capabilities the proc-block needs (e.g. `file-system`).
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// The worst-case resources the proc-block says it needs.
+    #[serde(default)]
+    pub resource_limits: ResourceLimits,
+    /// Can the kernel be evaluated at graph-build time when all of its inputs
+    /// are constant?
+    #[serde(default)]
+    pub const_eval: bool,
     pub arguments: Vec<ArgumentMetadata>,
     pub inputs: Vec<TensorMetadata>,
     pub outputs: Vec<TensorMetadata>,
@@ -170,6 +329,38 @@ impl Display for ElementType {
     }
 }
 
+impl ElementType {
+    /// The number of bytes used by a single element, or `None` for
+    /// variable-length types like strings.
+    pub fn size_of(self) -> Option<usize> {
+        match self {
+            ElementType::U8 | ElementType::I8 => Some(1),
+            ElementType::U16 | ElementType::I16 => Some(2),
+            ElementType::U32 | ElementType::I32 | ElementType::F32 => Some(4),
+            ElementType::U64 | ElementType::I64 | ElementType::F64 => Some(8),
+            ElementType::Utf8 => None,
+        }
+    }
+}
+
+impl From<ElementType> for runtime_v1::ElementType {
+    fn from(e: ElementType) -> Self {
+        match e {
+            ElementType::U8 => runtime_v1::ElementType::U8,
+            ElementType::I8 => runtime_v1::ElementType::I8,
+            ElementType::U16 => runtime_v1::ElementType::U16,
+            ElementType::I16 => runtime_v1::ElementType::I16,
+            ElementType::U32 => runtime_v1::ElementType::U32,
+            ElementType::I32 => runtime_v1::ElementType::I32,
+            ElementType::F32 => runtime_v1::ElementType::F32,
+            ElementType::I64 => runtime_v1::ElementType::I64,
+            ElementType::U64 => runtime_v1::ElementType::U64,
+            ElementType::F64 => runtime_v1::ElementType::F64,
+            ElementType::Utf8 => runtime_v1::ElementType::Utf8,
+        }
+    }
+}
+
 impl From<runtime_v1::ElementType> for ElementType {
     fn from(e: runtime_v1::ElementType) -> Self {
         match e {
@@ -258,6 +449,38 @@ pub struct NodeInfo {
     pub outputs: Vec<TensorInfo>,
 }
 
+/// A tensor passed between the host and a proc-block's kernel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Tensor {
+    pub element_type: ElementType,
+    pub dimensions: Vec<u32>,
+    pub buffer: Vec<u8>,
+}
+
+impl Tensor {
+    /// Create a tensor where every byte is zero.
+    pub fn zeroed(
+        element_type: ElementType,
+        dimensions: Vec<u32>,
+    ) -> Result<Self, Error> {
+        let element_size = element_type.size_of().with_context(|| {
+            format!("Unable to create a \"{}\" tensor", element_type)
+        })?;
+        let elements: usize = dimensions.iter().map(|&d| d as usize).product();
+
+        Ok(Tensor {
+            element_type,
+            dimensions,
+            buffer: vec![0; elements * element_size],
+        })
+    }
+
+    pub fn element_count(&self) -> u64 {
+        self.dimensions.iter().map(|&d| d as u64).product()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct TensorInfo {
@@ -301,7 +524,24 @@ impl runtime_v1::RuntimeV1 for RuntimeV1 {
     }
 
     fn metadata_add_tag(&mut self, self_: &Self::Metadata, tag: &str) {
-        self_.lock().unwrap().tags.push(tag.to_string());
+        let mut meta = self_.lock().unwrap();
+
+        if let Some(capability) = tag.strip_prefix(Capability::TAG_PREFIX) {
+            meta.capabilities.push(capability.to_string());
+        } else if let Some(limit) = tag.strip_prefix(ResourceLimit::TAG_PREFIX)
+        {
+            if let Err(e) = meta.resource_limits.add(limit) {
+                tracing::warn!(
+                    error = %e,
+                    tag,
+                    "Unable to parse the resource limit",
+                );
+            }
+        } else if tag == CONST_EVAL_TAG {
+            meta.const_eval = true;
+        } else {
+            meta.tags.push(tag.to_string());
+        }
     }
 
     fn metadata_add_argument(
@@ -509,18 +749,35 @@ impl runtime_v1::RuntimeV1 for RuntimeV1 {
     fn kernel_context_get_input_tensor(
         &mut self,
         _: &Self::KernelContext,
-        _name: &str,
+        name: &str,
     ) -> Option<runtime_v1::TensorResult> {
-        unimplemented!()
+        let shared = self.0.lock().unwrap();
+        let tensor = shared.inputs.get(name)?;
+
+        Some(runtime_v1::TensorResult {
+            element_type: tensor.element_type.into(),
+            dimensions: tensor.dimensions.clone(),
+            buffer: tensor.buffer.clone(),
+        })
     }
 
     fn kernel_context_set_output_tensor(
         &mut self,
         _: &Self::KernelContext,
-        _name: &str,
-        _tensor: runtime_v1::TensorParam<'_>,
+        name: &str,
+        tensor: runtime_v1::TensorParam<'_>,
     ) {
-        unimplemented!()
+        let tensor = Tensor {
+            element_type: tensor.element_type.into(),
+            dimensions: tensor.dimensions.iter().map(|d| d.get()).collect(),
+            buffer: tensor.buffer.to_vec(),
+        };
+
+        self.0
+            .lock()
+            .unwrap()
+            .outputs
+            .insert(name.to_string(), tensor);
     }
 
     fn is_enabled(&mut self, _metadata: LogMetadata<'_>) -> bool { true }
@@ -0,0 +1,96 @@
+use anyhow::{Context, Error};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::CompilationMode;
+
+/// Compile each of the command-line wrappers to `wasm32-wasi`, copying the
+/// resulting `*.wasm` files into `out_dir`.
+pub fn build_wasi_clis(
+    manifest_path: &Path,
+    out_dir: &Path,
+    mode: CompilationMode,
+) -> Result<Vec<PathBuf>, Error> {
+    let _span = tracing::info_span!("WASI").entered();
+    tracing::info!("Compiling the command-line wrappers to WASI");
+
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+
+    let mut cmd = Command::new(&cargo);
+    cmd.arg("build")
+        .arg("--manifest-path")
+        .arg(manifest_path)
+        .arg("--bins")
+        .arg("--target=wasm32-wasi");
+
+    match mode {
+        CompilationMode::Release => {
+            cmd.arg("--release");
+        },
+        CompilationMode::Debug => {},
+    }
+
+    tracing::debug!(command = ?cmd, "Running cargo build");
+
+    let status = cmd.status().with_context(|| {
+        format!(
+            "Unable to start \"{}\"",
+            cmd.get_program().to_string_lossy()
+        )
+    })?;
+
+    if !status.success() {
+        anyhow::bail!("Compilation failed");
+    }
+
+    let crate_dir = manifest_path.parent().unwrap_or_else(|| Path::new("."));
+    let artifact_dir = crate_dir
+        .join("target")
+        .join("wasm32-wasi")
+        .join(mode.dir());
+
+    std::fs::create_dir_all(out_dir).with_context(|| {
+        format!("Unable to create the \"{}\" directory", out_dir.display())
+    })?;
+
+    let bin_dir = crate_dir.join("src").join("bin");
+    let entries = std::fs::read_dir(&bin_dir).with_context(|| {
+        format!("Unable to read the \"{}\" directory", bin_dir.display())
+    })?;
+
+    let mut binaries = Vec::new();
+
+    for entry in entries {
+        let path = entry?.path();
+        let name = match path.file_stem().and_then(|s| s.to_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+
+        let filename = Path::new(name).with_extension("wasm");
+        let src = artifact_dir.join(&filename);
+        let dest = out_dir.join(&filename);
+
+        tracing::debug!(
+            src = %src.display(),
+            dest = %dest.display(),
+            "Copying WASI binary",
+        );
+
+        std::fs::copy(&src, &dest).with_context(|| {
+            format!(
+                "Unable to copy \"{}\" to \"{}\"",
+                src.display(),
+                dest.display()
+            )
+        })?;
+
+        binaries.push(dest);
+    }
+
+    binaries.sort();
+
+    Ok(binaries)
+}